@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use tokio::sync::mpsc;
 
 pub mod proto {
@@ -7,8 +9,8 @@ pub mod proto {
 pub use proto::{
     BandwidthFilter, Empty, ModuleRequest, RadioConfig, RadioFrame, RadioModulation,
     RadioModulationFsk, RadioModulationOfdm, RadioModulationQpsk, ReceiveRequest,
-    TransmitEventRequest, TransmitRequest, device_client::DeviceClient, radio_client::RadioClient,
-    radio_modulation::Modulation as ProtoModulation,
+    TransmitEventRequest, TransmitRequest, TransmitResult, device_client::DeviceClient,
+    radio_client::RadioClient, radio_modulation::Modulation as ProtoModulation,
 };
 
 use crate::app::{App, ModType, ModuleStatsSnapshot, RxEntry};
@@ -29,6 +31,8 @@ pub enum GrpcEvent {
     TxFrame(RxEntry),
     TxResult {
         latency_us: u32,
+        attempts: u32,
+        result: TransmitResult,
     },
     Statistics {
         module: usize,
@@ -60,8 +64,22 @@ pub enum GrpcCommand {
     },
 }
 
+/// HTTP/2 keepalive settings of the gRPC channel.
+///
+/// Pings keep quiet streams from being dropped by NATs or stateful firewalls.
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    /// Ping interval, pings are off if unset
+    pub interval: Option<Duration>,
+    /// Time to wait for a ping to be acknowledged before the channel is closed
+    pub timeout: Duration,
+}
+
 /// Spawns the gRPC background task.  Returns (command sender, event receiver).
-pub fn spawn(addr: String) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEvent>) {
+pub fn spawn(
+    addr: String,
+    keepalive: Keepalive,
+) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEvent>) {
     let (cmd_tx, mut cmd_rx) = mpsc::channel::<GrpcCommand>(32);
     let (evt_tx, evt_rx) = mpsc::channel::<GrpcEvent>(64);
 
@@ -91,6 +109,14 @@ pub fn spawn(addr: String) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEve
                 }
             };
 
+            let endpoint = match keepalive.interval {
+                Some(interval) => endpoint
+                    .http2_keep_alive_interval(interval)
+                    .keep_alive_timeout(keepalive.timeout)
+                    .keep_alive_while_idle(true),
+                None => endpoint,
+            };
+
             let channel = match endpoint.connect().await {
                 Ok(c) => c,
                 Err(e) => {
@@ -220,8 +246,16 @@ pub fn spawn(addr: String) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEve
                             Ok(_) => {}
                         }
                         match radio.set_modulation(modulation).await {
-                            Ok(_) => {
-                                let _ = evt_tx.send(GrpcEvent::Error("Configure OK".into())).await;
+                            Ok(resp) => {
+                                let resp = resp.into_inner();
+                                let msg = match resp.applied.as_ref().and_then(modulation_tx_power)
+                                {
+                                    Some(tx_power) if resp.tx_power_clamped => {
+                                        format!("Configure OK, tx power clamped to {}", tx_power)
+                                    }
+                                    _ => "Configure OK".to_string(),
+                                };
+                                let _ = evt_tx.send(GrpcEvent::Error(msg)).await;
                             }
                             Err(e) => {
                                 let _ = evt_tx
@@ -238,12 +272,17 @@ pub fn spawn(addr: String) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEve
                         let req = TransmitRequest {
                             module,
                             frame: Some(RadioFrame { data: data.into() }),
+                            modulation: None,
+                            seq: 0,
                         };
                         match radio.transmit(req).await {
                             Ok(resp) => {
+                                let resp = resp.into_inner();
                                 let _ = evt_tx
                                     .send(GrpcEvent::TxResult {
-                                        latency_us: resp.into_inner().latency,
+                                        latency_us: resp.latency,
+                                        attempts: resp.attempts,
+                                        result: resp.result(),
                                     })
                                     .await;
                             }
@@ -256,7 +295,13 @@ pub fn spawn(addr: String) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEve
                     }
 
                     GrpcCommand::SubscribeRx { module } => {
-                        let req = ReceiveRequest { module, timeout: 0 };
+                        let req = ReceiveRequest {
+                            module,
+                            timeout: 0,
+                            min_len: None,
+                            max_len: None,
+                            decode: false,
+                        };
                         let mut radio2 = RadioClient::new(channel.clone());
                         let evt_tx2 = evt_tx.clone();
 
@@ -269,10 +314,11 @@ pub fn spawn(addr: String) -> (mpsc::Sender<GrpcCommand>, mpsc::Receiver<GrpcEve
                                     while let Some(item) = stream.next().await {
                                         match item {
                                             Ok(rx) => {
-                                                let bytes = rx
-                                                    .frame
-                                                    .map(|f| f.data.to_vec())
-                                                    .unwrap_or_default();
+                                                // Keepalives carry no frame
+                                                let Some(frame) = rx.frame else {
+                                                    continue;
+                                                };
+                                                let bytes = frame.data.to_vec();
                                                 let preview = bytes
                                                     .iter()
                                                     .take(8)
@@ -392,6 +438,9 @@ pub fn configure_from_app(app: &App) -> Option<GrpcCommand> {
         } else {
             BandwidthFilter::Narrow as i32
         },
+        fem: None,
+        auto_spacing: false,
+        fem_auto: false,
     };
 
     let modulation_variant = match app.mod_type {
@@ -400,6 +449,7 @@ pub fn configure_from_app(app: &App) -> Option<GrpcCommand> {
             opt: app.ofdm_opt.index() as u32,
             pdt: 0x03,
             tx_power,
+            lfo: false,
         })),
         ModType::Qpsk => Some(ProtoModulation::Qpsk(RadioModulationQpsk {
             chip_freq: app.qpsk_fchip.index() as u32,
@@ -417,3 +467,12 @@ pub fn configure_from_app(app: &App) -> Option<GrpcCommand> {
 
     Some(GrpcCommand::Configure { config, modulation })
 }
+
+/// Transmit power of a modulation reported by commd, `None` for FSK.
+fn modulation_tx_power(modulation: &RadioModulation) -> Option<u32> {
+    match modulation.modulation.as_ref()? {
+        ProtoModulation::Ofdm(ofdm) => Some(ofdm.tx_power),
+        ProtoModulation::Qpsk(qpsk) => Some(qpsk.tx_power),
+        ProtoModulation::Fsk(_) => None,
+    }
+}
@@ -1,7 +1,8 @@
 use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture, EventStream},
     execute,
@@ -14,10 +15,11 @@ use tokio::sync::mpsc;
 mod app;
 mod events;
 mod grpc;
+mod replay;
 mod ui;
 
 use app::App;
-use grpc::GrpcEvent;
+use grpc::{GrpcEvent, TransmitResult};
 
 #[derive(Parser)]
 #[command(
@@ -28,12 +30,66 @@ struct Args {
     /// gRPC server address
     #[arg(default_value = "http://192.168.10.1:50051")]
     server: String,
+    /// HTTP/2 keepalive ping interval in seconds, 0 disables the pings
+    #[arg(long, default_value_t = 20)]
+    keepalive_interval: u64,
+    /// Seconds to wait for a keepalive ping to be acknowledged
+    #[arg(long, default_value_t = 10)]
+    keepalive_timeout: u64,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Retransmit a frame capture at its original inter-frame timing
+    Replay {
+        /// Capture file, one `time_us module hex` frame per line
+        file: PathBuf,
+        /// Time-scale factor, e.g. 2 replays twice as fast
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Send every frame on this module instead of the captured one
+        #[arg(long)]
+        module: Option<i32>,
+        /// Print the schedule without connecting or transmitting
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let args = Args::parse();
 
+    if let Some(Command::Replay {
+        file,
+        speed,
+        module,
+        dry_run,
+    }) = args.command
+    {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "speed must be positive",
+            ));
+        }
+
+        let content = std::fs::read_to_string(&file)?;
+        let frames = replay::parse_capture(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let options = replay::ReplayOptions {
+            speed,
+            module,
+            dry_run,
+        };
+
+        return replay::replay(args.server, &frames, &options)
+            .await
+            .map_err(io::Error::other);
+    }
+
     // ── Terminal setup ────────────────────────────────────────────────────
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -41,7 +97,12 @@ async fn main() -> io::Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run(&mut terminal, args.server).await;
+    let keepalive = grpc::Keepalive {
+        interval: (args.keepalive_interval > 0)
+            .then(|| Duration::from_secs(args.keepalive_interval)),
+        timeout: Duration::from_secs(args.keepalive_timeout),
+    };
+    let result = run(&mut terminal, args.server, keepalive).await;
 
     // ── Terminal teardown ─────────────────────────────────────────────────
     disable_raw_mode()?;
@@ -58,11 +119,12 @@ async fn main() -> io::Result<()> {
 async fn run(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     server_addr: String,
+    keepalive: grpc::Keepalive,
 ) -> io::Result<()> {
     let mut app = App::new(server_addr.clone());
 
     // ── gRPC background task ──────────────────────────────────────────────
-    let (cmd_tx, mut evt_rx) = grpc::spawn(server_addr);
+    let (cmd_tx, mut evt_rx) = grpc::spawn(server_addr, keepalive);
 
     // ── Crossterm async event stream ──────────────────────────────────────
     let mut term_events = EventStream::new();
@@ -146,8 +208,17 @@ async fn handle_grpc_event(
             app.push_rx(entry);
         }
 
-        GrpcEvent::TxResult { latency_us } => {
-            app.status_msg = format!("TX OK  ({} µs)", latency_us);
+        GrpcEvent::TxResult {
+            latency_us,
+            attempts,
+            result,
+        } => {
+            app.status_msg = match result {
+                TransmitResult::Sent => {
+                    format!("TX OK  ({} µs, {} attempts)", latency_us, attempts)
+                }
+                _ => format!("TX {}  ({} attempts)", result.as_str_name(), attempts),
+            };
         }
 
         GrpcEvent::Statistics { module, snapshot } => {
@@ -0,0 +1,233 @@
+//! Replays a frame capture through the gRPC transmit path.
+//!
+//! Captures are plain text with one frame per line:
+//!
+//! ```text
+//! # time_us module data
+//! 0       0 DEADBEEF
+//! 1520    0 0102030405
+//! ```
+//!
+//! `time_us` is any monotonic timestamp in microseconds; only the gaps between
+//! frames matter. Empty lines and lines starting with `#` are ignored.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::grpc::{RadioClient, RadioFrame, TransmitRequest, TransmitResult};
+
+/// One frame of a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedFrame {
+    pub time_us: u64,
+    pub module: i32,
+    pub data: Vec<u8>,
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parses a capture, returning the frames sorted by time.
+pub fn parse_capture(content: &str) -> Result<Vec<CapturedFrame>, String> {
+    let mut frames = Vec::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let (Some(time), Some(module), Some(data), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(format!("line {}: expected `time_us module data`", idx + 1));
+        };
+
+        frames.push(CapturedFrame {
+            time_us: time
+                .parse()
+                .map_err(|_| format!("line {}: invalid time `{}`", idx + 1, time))?,
+            module: module
+                .parse()
+                .map_err(|_| format!("line {}: invalid module `{}`", idx + 1, module))?,
+            data: parse_hex(data).ok_or_else(|| format!("line {}: invalid hex data", idx + 1))?,
+        });
+    }
+
+    frames.sort_by_key(|frame| frame.time_us);
+
+    Ok(frames)
+}
+
+/// Offset of every frame from the first one, divided by `speed`
+fn schedule(frames: &[CapturedFrame], speed: f64) -> Vec<Duration> {
+    let start = frames
+        .first()
+        .map(|frame| frame.time_us)
+        .unwrap_or_default();
+
+    frames
+        .iter()
+        .map(|frame| Duration::from_micros(((frame.time_us - start) as f64 / speed).round() as u64))
+        .collect()
+}
+
+/// Replay options
+pub struct ReplayOptions {
+    /// Time-scale factor, 2.0 replays twice as fast
+    pub speed: f64,
+    /// Send every frame on this module instead of the captured one
+    pub module: Option<i32>,
+    /// Only print what would be sent
+    pub dry_run: bool,
+}
+
+/// Transmits `frames` at their captured inter-frame timing.
+///
+/// Each frame is scheduled relative to the start of the replay, so a slow
+/// transmit delays the next frame but doesn't shift the rest of the pattern.
+pub async fn replay(
+    server: String,
+    frames: &[CapturedFrame],
+    options: &ReplayOptions,
+) -> Result<(), String> {
+    let mut radio = if options.dry_run {
+        None
+    } else {
+        Some(
+            RadioClient::connect(server)
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+    };
+
+    let offsets = schedule(frames, options.speed);
+    let start = Instant::now();
+
+    for (seq, (frame, offset)) in frames.iter().zip(offsets).enumerate() {
+        let module = options.module.unwrap_or(frame.module);
+        let preview = frame
+            .data
+            .iter()
+            .take(8)
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let Some(radio) = radio.as_mut() else {
+            println!(
+                "+{:>10.3} ms  module {}  {:>4} bytes  {}",
+                offset.as_secs_f64() * 1e3,
+                module,
+                frame.data.len(),
+                preview
+            );
+            continue;
+        };
+
+        tokio::time::sleep_until(start + offset).await;
+
+        let response = radio
+            .transmit(TransmitRequest {
+                module,
+                frame: Some(RadioFrame {
+                    data: frame.data.clone(),
+                }),
+                modulation: None,
+                seq: seq as u32,
+            })
+            .await
+            .map_err(|e| format!("Transmit: {}", e.message()))?
+            .into_inner();
+
+        let lag = start.elapsed().saturating_sub(offset);
+        match response.result() {
+            TransmitResult::Sent => println!(
+                "+{:>10.3} ms  module {}  {:>4} bytes  {}  (lag {} us)",
+                offset.as_secs_f64() * 1e3,
+                module,
+                frame.data.len(),
+                preview,
+                lag.as_micros()
+            ),
+            result => eprintln!(
+                "+{:>10.3} ms  module {}  not sent: {:?} after {} attempts",
+                offset.as_secs_f64() * 1e3,
+                module,
+                result,
+                response.attempts
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capture() {
+        let frames = parse_capture(
+            "# time_us module data\n\
+             2000 1 0a0B\n\
+             \n\
+             500  0 DEADBEEF\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            frames,
+            [
+                CapturedFrame {
+                    time_us: 500,
+                    module: 0,
+                    data: vec![0xDE, 0xAD, 0xBE, 0xEF],
+                },
+                CapturedFrame {
+                    time_us: 2000,
+                    module: 1,
+                    data: vec![0x0A, 0x0B],
+                },
+            ]
+        );
+
+        assert!(parse_capture("100 0 ABC").is_err());
+        assert!(parse_capture("100 0").is_err());
+        assert!(parse_capture("x 0 AB").is_err());
+        assert!(parse_capture("100 0 AB extra").is_err());
+    }
+
+    #[test]
+    fn test_schedule_scales_time() {
+        let frames = parse_capture("1000 0 00\n1500 0 00\n3000 0 00").unwrap();
+
+        assert_eq!(
+            schedule(&frames, 1.0),
+            [
+                Duration::ZERO,
+                Duration::from_micros(500),
+                Duration::from_micros(2000)
+            ]
+        );
+        assert_eq!(
+            schedule(&frames, 2.0),
+            [
+                Duration::ZERO,
+                Duration::from_micros(250),
+                Duration::from_micros(1000)
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Cost of the per-frame work on the commd receive path
+//!
+//! Run with `cargo bench -p kaonic-commd --no-default-features --features
+//! machine-host --bench receive_path`. Compares building the decode buffers
+//! for every frame against reusing them, and broadcasting a boxed frame
+//! (copied per subscriber) against a shared one.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use kaonic_ctrl::protocol::ReceiveModule;
+use kaonic_frame::frame::Frame;
+use kaonic_net::{
+    coder::{LdpcPacketCoder, PacketCoder},
+    packet::Packet,
+};
+use tokio::sync::broadcast;
+
+/// Frame size of the commd receive stream decoder
+const NET_FRAME_SIZE: usize = 2048;
+const PAYLOAD_SIZE: usize = 600;
+/// gRPC receive streams plus the UDP client forwarder
+const SUBSCRIBERS: usize = 2;
+
+fn encoded() -> Frame<NET_FRAME_SIZE> {
+    let mut packet = Packet::<NET_FRAME_SIZE>::new();
+    let payload: Vec<u8> = (0..PAYLOAD_SIZE).map(|i| (i * 7 + 3) as u8).collect();
+    packet
+        .frame_mut()
+        .push_data(&payload)
+        .expect("payload fits the packet");
+    packet.build();
+
+    let mut frame = Frame::new();
+    LdpcPacketCoder::<NET_FRAME_SIZE>::new()
+        .encode(&packet, &mut frame)
+        .expect("encoded frame");
+
+    frame
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("receive_decode");
+    let encoded = encoded();
+
+    group.bench_function("fresh", |b| {
+        b.iter(|| {
+            let mut coder = Box::new(LdpcPacketCoder::<NET_FRAME_SIZE>::new());
+            let mut frame = Box::new(Frame::<NET_FRAME_SIZE>::new());
+            let mut packet = Box::new(Packet::<NET_FRAME_SIZE>::new());
+
+            frame
+                .copy_from_slice(black_box(encoded.as_slice()))
+                .unwrap();
+            coder.decode(&frame, &mut packet).unwrap();
+            packet.validate()
+        })
+    });
+
+    let mut coder = Box::new(LdpcPacketCoder::<NET_FRAME_SIZE>::new());
+    let mut frame = Box::new(Frame::<NET_FRAME_SIZE>::new());
+    let mut packet = Box::new(Packet::<NET_FRAME_SIZE>::new());
+
+    group.bench_function("reused", |b| {
+        b.iter(|| {
+            frame
+                .copy_from_slice(black_box(encoded.as_slice()))
+                .unwrap();
+            coder.decode(&frame, &mut packet).unwrap();
+            packet.validate()
+        })
+    });
+
+    group.finish();
+}
+
+fn receive_module() -> ReceiveModule {
+    let mut module = ReceiveModule::new();
+    module.frame.data[..PAYLOAD_SIZE].fill(0xA5);
+    module.frame.len = PAYLOAD_SIZE as u16;
+    module.rssi = -70;
+    module
+}
+
+fn bench_broadcast(c: &mut Criterion) {
+    let mut group = c.benchmark_group("receive_broadcast");
+
+    let (send, _) = broadcast::channel::<Box<ReceiveModule>>(16);
+    let mut receivers: Vec<_> = (0..SUBSCRIBERS).map(|_| send.subscribe()).collect();
+
+    group.bench_function("box", |b| {
+        b.iter(|| {
+            send.send(Box::new(receive_module())).unwrap();
+            for receiver in receivers.iter_mut() {
+                black_box(receiver.try_recv().unwrap());
+            }
+        })
+    });
+
+    let (send, _) = broadcast::channel::<Arc<ReceiveModule>>(16);
+    let mut receivers: Vec<_> = (0..SUBSCRIBERS).map(|_| send.subscribe()).collect();
+
+    group.bench_function("arc", |b| {
+        b.iter(|| {
+            send.send(Arc::new(receive_module())).unwrap();
+            for receiver in receivers.iter_mut() {
+                black_box(receiver.try_recv().unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_broadcast);
+criterion_main!(benches);
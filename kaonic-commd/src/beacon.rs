@@ -0,0 +1,1070 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use kaonic_frame::frame::Frame;
+use kaonic_net::{
+    coder::{BinaryPacketCoder, LinkCoding, PacketCoder, PayloadCode},
+    packet::{HEADER_SIZE, Packet, PacketType},
+};
+use kaonic_radio::{
+    error::KaonicError,
+    platform::{PlatformRadio, PlatformRadioFrame},
+    radio::Radio,
+};
+use radio_common::{
+    Hertz, RadioChannel,
+    modulation::{
+        Modulation, OfdmBandwidthOption, OfdmModulation, QpskChipFrequency, QpskModulation,
+        QpskRateMode,
+    },
+};
+use tokio::sync::watch;
+
+use crate::{
+    events::{EventBus, RadioEvent},
+    power_control::PowerControl,
+};
+
+const BEACON_FRAME_SIZE: usize = 64;
+const BEACON_VERSION: u8 = 2;
+const BEACON_PAYLOAD_SIZE: usize = 8;
+/// Version 1 beacons end before the coding
+const BEACON_V1_PAYLOAD_SIZE: usize = 5;
+/// Version 3 beacons append the RSSI reports to a version 2 payload
+const BEACON_REPORTS_VERSION: u8 = 3;
+/// Node id and RSSI of one report
+const BEACON_REPORT_SIZE: usize = 5;
+/// Version 4 beacons put the advertised link between a version 2 payload and
+/// the RSSI reports
+const BEACON_LINK_VERSION: u8 = 4;
+/// Data channel and two modulation parameters
+const BEACON_LINK_SIZE: usize = 4;
+
+/// Peers a beacon reports the RSSI of, as many as fit the beacon frame
+pub const MAX_RSSI_REPORTS: usize = 7;
+
+/// Node understands kaonic-net LDPC coded packets
+pub const CAPABILITY_LDPC: u16 = 1 << 0;
+/// Node codes kaonic-net packets as advertised in [`Beacon::coding`]
+pub const CAPABILITY_CODING: u16 = 1 << 1;
+/// Node's coding is set manually and doesn't follow its peers
+pub const CAPABILITY_CODING_MANUAL: u16 = 1 << 2;
+/// Node's modulation is set manually and doesn't follow its peers
+pub const CAPABILITY_MODULATION_MANUAL: u16 = 1 << 3;
+
+pub type NodeId = u32;
+
+/// Modulation scheme advertised in a beacon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BeaconModulation {
+    Off = 0,
+    Ofdm = 1,
+    Qpsk = 2,
+    Fsk = 3,
+}
+
+impl BeaconModulation {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Off),
+            1 => Some(Self::Ofdm),
+            2 => Some(Self::Qpsk),
+            3 => Some(Self::Fsk),
+            _ => None,
+        }
+    }
+}
+
+/// Data channel and modulation the sender of a beacon transmits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkAdvert {
+    pub channel: RadioChannel,
+    /// Modulation with the sender's tx power, OFDM with the default PDT
+    pub modulation: Modulation,
+}
+
+impl LinkAdvert {
+    fn pack(&self) -> [u8; BEACON_LINK_SIZE] {
+        let channel = self.channel.to_le_bytes();
+        let params = match self.modulation {
+            Modulation::Ofdm(ofdm) => [ofdm.mcs as u8, ofdm.opt as u8],
+            Modulation::Qpsk(qpsk) => [qpsk.fchip as u8, qpsk.mode as u8],
+            Modulation::Off | Modulation::Fsk => [0, 0],
+        };
+
+        [channel[0], channel[1], params[0], params[1]]
+    }
+
+    fn unpack(data: &[u8], modulation: BeaconModulation, tx_power: u8) -> Option<Self> {
+        let modulation = match modulation {
+            BeaconModulation::Ofdm => Modulation::Ofdm(OfdmModulation {
+                mcs: kaonic_qos::scheme::ofdm_mcs(data[2])?,
+                opt: match data[3] {
+                    0 => OfdmBandwidthOption::Option1,
+                    1 => OfdmBandwidthOption::Option2,
+                    2 => OfdmBandwidthOption::Option3,
+                    3 => OfdmBandwidthOption::Option4,
+                    _ => return None,
+                },
+                tx_power,
+                ..Default::default()
+            }),
+            BeaconModulation::Qpsk => Modulation::Qpsk(QpskModulation {
+                fchip: match data[2] {
+                    0 => QpskChipFrequency::Fchip100,
+                    1 => QpskChipFrequency::Fchip200,
+                    2 => QpskChipFrequency::Fchip1000,
+                    3 => QpskChipFrequency::Fchip2000,
+                    _ => return None,
+                },
+                mode: match data[3] {
+                    0 => QpskRateMode::RateMode0,
+                    1 => QpskRateMode::RateMode1,
+                    2 => QpskRateMode::RateMode2,
+                    3 => QpskRateMode::RateMode3,
+                    4 => QpskRateMode::RateMode4,
+                    _ => return None,
+                },
+                tx_power,
+            }),
+            BeaconModulation::Fsk => Modulation::Fsk,
+            BeaconModulation::Off => Modulation::Off,
+        };
+
+        Some(Self {
+            channel: RadioChannel::from_le_bytes([data[0], data[1]]),
+            modulation,
+        })
+    }
+
+    /// `current` switched to the advertised modulation, `None` if there is
+    /// nothing to switch to
+    ///
+    /// The local tx power stays, and so do the receive settings of OFDM.
+    pub fn modulation_for(&self, current: &Modulation) -> Option<Modulation> {
+        let mut modulation = match (self.modulation, current) {
+            (_, Modulation::Off) => return None,
+            (Modulation::Ofdm(advert), Modulation::Ofdm(current)) => {
+                Modulation::Ofdm(OfdmModulation {
+                    mcs: advert.mcs,
+                    opt: advert.opt,
+                    ..*current
+                })
+            }
+            (advert @ (Modulation::Ofdm(_) | Modulation::Qpsk(_)), _) => advert,
+            (Modulation::Off | Modulation::Fsk, _) => return None,
+        };
+
+        modulation.set_tx_power(current.tx_power());
+
+        Some(modulation)
+    }
+}
+
+/// RSSI at which the sender of a beacon hears its peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RssiReports {
+    reports: [(NodeId, i8); MAX_RSSI_REPORTS],
+    len: usize,
+}
+
+impl RssiReports {
+    /// Adds the report of `node_id`, returns false once the reports are full
+    pub fn push(&mut self, node_id: NodeId, rssi: i8) -> bool {
+        if self.len == MAX_RSSI_REPORTS {
+            return false;
+        }
+
+        self.reports[self.len] = (node_id, rssi);
+        self.len += 1;
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(NodeId, i8)> {
+        self.reports[..self.len].iter()
+    }
+
+    /// RSSI reported for `node_id`
+    pub fn get(&self, node_id: NodeId) -> Option<i8> {
+        self.iter()
+            .find(|(id, _)| *id == node_id)
+            .map(|(_, rssi)| *rssi)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Node identity announced on the beacon channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beacon {
+    pub node_id: NodeId,
+    pub capabilities: u16,
+    pub modulation: BeaconModulation,
+    pub tx_power: u8,
+    /// Whitening and payload code, the default for version 1 beacons
+    pub coding: LinkCoding,
+    /// Peers heard by the sender, only version 3 and 4 beacons carry them
+    pub reports: RssiReports,
+    /// Data channel and modulation of the sender, only version 4 beacons
+    /// carry them
+    pub link: Option<LinkAdvert>,
+}
+
+impl Beacon {
+    pub fn new(node_id: NodeId, capabilities: u16, modulation: &Modulation) -> Self {
+        let (modulation, tx_power) = match modulation {
+            Modulation::Ofdm(ofdm) => (BeaconModulation::Ofdm, ofdm.tx_power),
+            Modulation::Qpsk(qpsk) => (BeaconModulation::Qpsk, qpsk.tx_power),
+            Modulation::Fsk => (BeaconModulation::Fsk, 0),
+            Modulation::Off => (BeaconModulation::Off, 0),
+        };
+
+        Self {
+            node_id,
+            capabilities,
+            modulation,
+            tx_power,
+            coding: LinkCoding::default(),
+            reports: RssiReports::default(),
+            link: None,
+        }
+    }
+
+    pub fn with_coding(mut self, coding: LinkCoding) -> Self {
+        self.coding = coding;
+        self
+    }
+
+    /// Reports peer RSSI in the beacon, without reports a version 2 beacon is
+    /// sent which nodes without power control still understand
+    pub fn with_reports(mut self, reports: RssiReports) -> Self {
+        self.reports = reports;
+        self
+    }
+
+    /// Advertises the data channel and modulation, which makes it a version 4
+    /// beacon older nodes ignore
+    pub fn with_link(mut self, link: LinkAdvert) -> Self {
+        self.link = Some(link);
+        self
+    }
+
+    /// Serializes the beacon as a kaonic-net packet of type `Beacon`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut packet = Packet::<BEACON_FRAME_SIZE>::new();
+        let mut frame = Frame::<BEACON_FRAME_SIZE>::new();
+
+        let capabilities = self.capabilities.to_le_bytes();
+        let seed = self.coding.whitening_seed.to_le_bytes();
+
+        packet
+            .header_mut()
+            .set_packet_type(PacketType::Beacon)
+            .set_id(self.node_id);
+        let version = match (self.link, self.reports.is_empty()) {
+            (Some(_), _) => BEACON_LINK_VERSION,
+            (None, true) => BEACON_VERSION,
+            (None, false) => BEACON_REPORTS_VERSION,
+        };
+
+        packet
+            .frame_mut()
+            .push_data(&[
+                version,
+                capabilities[0],
+                capabilities[1],
+                self.modulation as u8,
+                self.tx_power,
+                seed[0],
+                seed[1],
+                self.coding.payload_code as u8,
+            ])
+            .expect("beacon payload fits the frame");
+
+        if let Some(link) = &self.link {
+            packet
+                .frame_mut()
+                .push_data(&link.pack())
+                .expect("beacon payload fits the frame");
+        }
+
+        if version != BEACON_VERSION {
+            packet
+                .frame_mut()
+                .push_data(&[self.reports.len as u8])
+                .expect("beacon payload fits the frame");
+
+            for (node_id, rssi) in self.reports.iter() {
+                let id = node_id.to_le_bytes();
+                packet
+                    .frame_mut()
+                    .push_data(&[id[0], id[1], id[2], id[3], *rssi as u8])
+                    .expect("beacon reports fit the frame");
+            }
+        }
+
+        packet.build();
+
+        BinaryPacketCoder::new()
+            .encode(&packet, &mut frame)
+            .expect("beacon fits the frame");
+
+        frame.as_slice().to_vec()
+    }
+
+    /// Parses a received frame, returning `None` for anything but a valid beacon
+    ///
+    /// Version 1 beacons are accepted and report the default coding.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let payload_len = data.len().checked_sub(HEADER_SIZE)?;
+        if payload_len < BEACON_V1_PAYLOAD_SIZE || data[0] != PacketType::Beacon as u8 {
+            return None;
+        }
+
+        let mut packet = Packet::<BEACON_FRAME_SIZE>::new();
+
+        BinaryPacketCoder::new()
+            .decode(&Frame::new_from_slice(data).ok()?, &mut packet)
+            .ok()?;
+
+        if !packet.validate() {
+            return None;
+        }
+
+        let payload = packet.frame().as_slice();
+        let coding = match (payload.first(), payload.len()) {
+            (Some(1), BEACON_V1_PAYLOAD_SIZE) => LinkCoding::default(),
+            (Some(&BEACON_VERSION), BEACON_PAYLOAD_SIZE) => LinkCoding {
+                whitening_seed: u16::from_le_bytes([payload[5], payload[6]]),
+                payload_code: PayloadCode::from_u8(payload[7])?,
+            },
+            (Some(&(BEACON_REPORTS_VERSION | BEACON_LINK_VERSION)), len)
+                if len > BEACON_PAYLOAD_SIZE =>
+            {
+                LinkCoding {
+                    whitening_seed: u16::from_le_bytes([payload[5], payload[6]]),
+                    payload_code: PayloadCode::from_u8(payload[7])?,
+                }
+            }
+            _ => return None,
+        };
+
+        let modulation = BeaconModulation::from_u8(payload[3])?;
+        let tx_power = payload[4];
+
+        let mut link = None;
+        let mut reports_at = BEACON_PAYLOAD_SIZE;
+        if payload[0] == BEACON_LINK_VERSION {
+            let data = payload.get(reports_at..reports_at + BEACON_LINK_SIZE)?;
+            link = Some(LinkAdvert::unpack(data, modulation, tx_power)?);
+            reports_at += BEACON_LINK_SIZE;
+        }
+
+        let mut reports = RssiReports::default();
+        if payload[0] == BEACON_REPORTS_VERSION || payload[0] == BEACON_LINK_VERSION {
+            let count = usize::from(*payload.get(reports_at)?);
+            let data = &payload[reports_at + 1..];
+            if count > MAX_RSSI_REPORTS || data.len() != count * BEACON_REPORT_SIZE {
+                return None;
+            }
+
+            for report in data.chunks_exact(BEACON_REPORT_SIZE) {
+                let node_id = NodeId::from_le_bytes([report[0], report[1], report[2], report[3]]);
+                reports.push(node_id, report[4] as i8);
+            }
+        }
+
+        Some(Self {
+            node_id: packet.header().id(),
+            capabilities: u16::from_le_bytes([payload[1], payload[2]]),
+            modulation,
+            tx_power,
+            coding,
+            reports,
+            link,
+        })
+    }
+}
+
+/// Neighbor discovered through its beacon
+#[derive(Debug, Clone, Copy)]
+pub struct Peer {
+    pub beacon: Beacon,
+    pub module: usize,
+    pub rssi: i8,
+    /// RSSI at which the peer reported hearing us
+    pub reported_rssi: Option<i8>,
+    pub last_seen: Instant,
+}
+
+/// Peers heard on any module, keyed by node id
+///
+/// Also holds the coding of this node, which follows the one advertised by
+/// its peers unless it is set manually, and the links of the peers the
+/// modules follow if the modulation isn't manual.
+pub struct PeerTable {
+    peers: HashMap<NodeId, Peer>,
+    timeout: Duration,
+    node_id: NodeId,
+    coding: watch::Sender<LinkCoding>,
+    manual_coding: bool,
+    manual_modulation: bool,
+    /// Link followed by each module
+    links: HashMap<usize, LinkAdvert>,
+    /// Followed links the modules haven't switched to yet
+    pending_links: HashMap<usize, LinkAdvert>,
+    events: Option<EventBus>,
+    power_control: Option<PowerControl>,
+    /// Modules a peer reported our RSSI on since their last power adjustment
+    new_reports: HashSet<usize>,
+}
+
+impl PeerTable {
+    /// Keeps the default coding until [`PeerTable::with_coding`]
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            peers: HashMap::new(),
+            timeout,
+            node_id: 0,
+            coding: watch::Sender::new(LinkCoding::default()),
+            manual_coding: true,
+            manual_modulation: true,
+            links: HashMap::new(),
+            pending_links: HashMap::new(),
+            events: None,
+            power_control: None,
+            new_reports: HashSet::new(),
+        }
+    }
+
+    /// Starts `node_id` on `coding`, adopting the one of its peers unless `manual`
+    pub fn with_coding(mut self, node_id: NodeId, coding: LinkCoding, manual: bool) -> Self {
+        self.node_id = node_id;
+        self.coding = watch::Sender::new(coding);
+        self.manual_coding = manual;
+        self
+    }
+
+    /// Lets the modules follow the channel and modulation of their peers
+    /// unless `manual`
+    pub fn with_manual_modulation(mut self, manual: bool) -> Self {
+        self.manual_modulation = manual;
+        self
+    }
+
+    /// Publishes peers coming and going on `events`
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Adjusts the tx power to the RSSI the peers report for our beacons
+    pub fn with_power_control(mut self, power_control: PowerControl) -> Self {
+        self.power_control = Some(power_control);
+        self
+    }
+
+    fn publish(&self, event: RadioEvent) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+
+    /// Coding this node uses and advertises
+    pub fn coding(&self) -> LinkCoding {
+        *self.coding.borrow()
+    }
+
+    /// Follows the changes of [`PeerTable::coding`]
+    pub fn subscribe_coding(&self) -> watch::Receiver<LinkCoding> {
+        self.coding.subscribe()
+    }
+
+    /// Coding flags to advertise next to the other capabilities
+    pub fn coding_capabilities(&self) -> u16 {
+        match self.manual_coding {
+            true => CAPABILITY_CODING | CAPABILITY_CODING_MANUAL,
+            false => CAPABILITY_CODING,
+        }
+    }
+
+    /// Whether the modules follow the links of their peers, only then is
+    /// the own link advertised
+    pub fn follows_modulation(&self) -> bool {
+        !self.manual_modulation
+    }
+
+    /// Modulation flags to advertise next to the other capabilities
+    pub fn modulation_capabilities(&self) -> u16 {
+        match self.manual_modulation {
+            true => CAPABILITY_MODULATION_MANUAL,
+            false => 0,
+        }
+    }
+
+    /// Takes over the coding advertised in `beacon` if the peer leads
+    ///
+    /// A manual coding never changes. Otherwise the node follows peers with a
+    /// manual coding and automatic ones with a lower node id, so a network
+    /// settles on one coding instead of nodes swapping theirs back and forth.
+    fn adopt_coding(&self, beacon: &Beacon) {
+        if self.manual_coding || beacon.capabilities & CAPABILITY_CODING == 0 {
+            return;
+        }
+
+        let leads =
+            beacon.capabilities & CAPABILITY_CODING_MANUAL != 0 || beacon.node_id < self.node_id;
+        if !leads {
+            return;
+        }
+
+        let changed = self.coding.send_if_modified(|coding| {
+            let changed = *coding != beacon.coding;
+            *coding = beacon.coding;
+            changed
+        });
+
+        if changed {
+            log::info!(
+                "coding of peer {:0>8X} adopted: {:?}",
+                beacon.node_id,
+                beacon.coding
+            );
+        }
+    }
+
+    /// Stages the link of the peer `module` follows for its next transmission
+    ///
+    /// A manual modulation never changes. Like the coding, modules follow
+    /// peers with a manual modulation first and otherwise the automatic peer
+    /// with the lowest node id below ours.
+    fn adopt_link(&mut self, module: usize) {
+        if self.manual_modulation {
+            return;
+        }
+
+        let leader = self
+            .peers
+            .values()
+            .filter(|peer| peer.module == module)
+            .filter_map(|peer| {
+                let automatic = peer.beacon.capabilities & CAPABILITY_MODULATION_MANUAL == 0;
+                let leads = !automatic || peer.beacon.node_id < self.node_id;
+
+                leads.then_some((automatic, peer.beacon.node_id, peer.beacon.link?))
+            })
+            .min_by_key(|(automatic, node_id, _)| (*automatic, *node_id));
+
+        let Some((_, node_id, link)) = leader else {
+            return;
+        };
+
+        if self.links.get(&module) == Some(&link) || self.pending_links.get(&module) == Some(&link)
+        {
+            return;
+        }
+
+        log::info!(
+            "radio[{}] follows peer {:0>8X}: channel {} {}",
+            module,
+            node_id,
+            link.channel,
+            link.modulation
+        );
+
+        self.pending_links.insert(module, link);
+    }
+
+    /// Link `module` started following since the last call, to switch to
+    /// before its next transmission
+    ///
+    /// Staged again with the next beacon of the peer until
+    /// [`PeerTable::link_followed`] confirms the switch.
+    pub fn take_pending_link(&mut self, module: usize) -> Option<LinkAdvert> {
+        self.pending_links.remove(&module)
+    }
+
+    /// Records that `module` switched to `link`
+    pub fn link_followed(&mut self, module: usize, link: LinkAdvert) {
+        self.links.insert(module, link);
+    }
+
+    pub fn update(&mut self, module: usize, beacon: Beacon, rssi: i8, now: Instant) {
+        if !self.peers.contains_key(&beacon.node_id) {
+            self.publish(RadioEvent::PeerUp {
+                module,
+                node_id: beacon.node_id,
+                rssi,
+            });
+        }
+
+        let reported_rssi = beacon.reports.get(self.node_id);
+        if reported_rssi.is_some() {
+            self.new_reports.insert(module);
+        }
+
+        self.peers.insert(
+            beacon.node_id,
+            Peer {
+                beacon,
+                module,
+                rssi,
+                reported_rssi,
+                last_seen: now,
+            },
+        );
+
+        self.adopt_coding(&beacon);
+        self.adopt_link(module);
+    }
+
+    /// Drops peers which haven't been heard for longer than the timeout
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let events = &self.events;
+
+        self.peers.retain(|&node_id, peer| {
+            let alive = now.saturating_duration_since(peer.last_seen) <= timeout;
+            if !alive && let Some(events) = events {
+                events.publish(RadioEvent::PeerDown { node_id });
+            }
+            alive
+        });
+    }
+
+    /// Returns peers which are still alive at `now`
+    pub fn peers(&mut self, now: Instant) -> Vec<Peer> {
+        self.expire(now);
+        self.peers.values().copied().collect()
+    }
+
+    /// RSSI of the peers heard on `module` to report in our beacon, empty
+    /// without power control
+    pub fn rssi_reports(&self, module: usize) -> RssiReports {
+        let mut reports = RssiReports::default();
+
+        if self.power_control.is_some() {
+            let mut peers: Vec<_> = self.peers.values().filter(|p| p.module == module).collect();
+            peers.sort_by_key(|peer| core::cmp::Reverse(peer.last_seen));
+
+            for peer in peers.into_iter().take(MAX_RSSI_REPORTS) {
+                reports.push(peer.beacon.node_id, peer.rssi);
+            }
+        }
+
+        reports
+    }
+
+    /// Modulation with the tx power for the weakest peer on `module` which
+    /// reported our RSSI, `None` if it stays unchanged
+    ///
+    /// Each step waits for a report received after the previous one, a report
+    /// doesn't show the effect of a step taken after it.
+    pub fn power_adjustment(
+        &mut self,
+        module: usize,
+        current: &Modulation,
+        freq: Hertz,
+    ) -> Option<Modulation> {
+        if !self.new_reports.remove(&module) {
+            return None;
+        }
+
+        let weakest = self
+            .peers
+            .values()
+            .filter(|peer| peer.module == module)
+            .filter_map(|peer| peer.reported_rssi)
+            .min()?;
+
+        self.power_control?.update(weakest, current, freq)
+    }
+}
+
+/// Derives a stable node id from the device serial (FNV-1a)
+pub fn node_id_from_serial(serial: &str) -> NodeId {
+    serial.bytes().fold(0x811c_9dc5, |hash: u32, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Time until the next beacon slot, slots start at multiples of `interval`
+/// since the epoch so nodes with synchronized clocks meet on the beacon channel
+pub fn until_next_slot(now: SystemTime, interval: Duration) -> Duration {
+    let interval_ms = interval.as_millis().max(1);
+    let now_ms = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    match now_ms % interval_ms {
+        0 => Duration::ZERO,
+        rem => Duration::from_millis((interval_ms - rem) as u64),
+    }
+}
+
+/// Spends one beacon slot on the beacon channel
+///
+/// Retunes the radio to `channel`, transmits `beacon` at `tx_at` and reports
+/// every beacon heard during `slot` to `on_beacon`. The data channel is
+/// restored afterwards, the caller keeps the radio locked for the whole slot.
+pub fn run_beacon_slot(
+    radio: &mut PlatformRadio,
+    channel: RadioChannel,
+    beacon: &[u8],
+    tx_at: Duration,
+    slot: Duration,
+    mut on_beacon: impl FnMut(Beacon, i8),
+) -> Result<(), KaonicError> {
+    let beacon = PlatformRadioFrame::new_from_slice(beacon)?;
+    let data_config = radio.get_config();
+
+    let mut beacon_config = data_config;
+    beacon_config.channel = channel;
+    radio.set_config(&beacon_config)?;
+
+    let start = Instant::now();
+    let mut rx_frame = PlatformRadioFrame::new();
+    let mut tx_result = None;
+
+    loop {
+        let elapsed = start.elapsed();
+
+        if tx_result.is_none() && elapsed >= tx_at {
+            tx_result = Some(radio.transmit(&beacon));
+            continue;
+        }
+
+        if elapsed >= slot {
+            break;
+        }
+
+        let until = if tx_result.is_none() { tx_at } else { slot };
+
+        match radio.receive(rx_frame.clear(), until.saturating_sub(elapsed)) {
+            Ok(rr) => {
+                if let Some(beacon) = Beacon::decode(rx_frame.as_slice()) {
+                    on_beacon(beacon, rr.rssi);
+                }
+            }
+            Err(KaonicError::Timeout) => {}
+            Err(KaonicError::BufferOverrun) => {
+                let _ = radio.flush_receive();
+            }
+            Err(e) => {
+                log::warn!("beacon slot receive error: {e:?}");
+                break;
+            }
+        }
+    }
+
+    radio.set_config(&data_config)?;
+
+    tx_result.unwrap_or(Err(KaonicError::Timeout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kaonic_net::coder::LdpcPacketCoder;
+    use kaonic_radio::power::TxPowerLimit;
+    use radio_common::modulation::OfdmModulation;
+
+    use crate::decoder::PacketDecoder;
+
+    #[test]
+    fn test_beacon_populates_peer_table_and_ages_out() {
+        let beacon = Beacon::new(
+            0xCAFE,
+            CAPABILITY_LDPC,
+            &Modulation::Ofdm(OfdmModulation::default()),
+        );
+
+        let received = Beacon::decode(&beacon.encode()).expect("valid beacon");
+        assert_eq!(received, beacon);
+
+        let start = Instant::now();
+        let mut table = PeerTable::new(Duration::from_secs(30));
+
+        table.update(1, received, -60, start);
+
+        let peers = table.peers(start + Duration::from_secs(10));
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].beacon.node_id, 0xCAFE);
+        assert_eq!(peers[0].module, 1);
+        assert_eq!(peers[0].rssi, -60);
+
+        assert!(table.peers(start + Duration::from_secs(31)).is_empty());
+    }
+
+    #[test]
+    fn test_slots_align_to_interval() {
+        let interval = Duration::from_secs(10);
+
+        let at = |ms| UNIX_EPOCH + Duration::from_millis(ms);
+
+        assert_eq!(until_next_slot(at(20_000), interval), Duration::ZERO);
+        assert_eq!(
+            until_next_slot(at(23_500), interval),
+            Duration::from_millis(6_500)
+        );
+    }
+
+    #[cfg(feature = "machine-host")]
+    #[test]
+    fn test_beacon_slot_uses_beacon_channel() {
+        let mut radio = PlatformRadio::new();
+        let data_channel = radio.get_config().channel;
+
+        let foreign = Beacon::new(0xBEEF, 0, &Modulation::Off).encode();
+        radio
+            .transmit(&PlatformRadioFrame::new_from_slice(&foreign).unwrap())
+            .unwrap();
+
+        let own = Beacon::new(0xCAFE, CAPABILITY_LDPC, &Modulation::Off);
+
+        let mut heard = Vec::new();
+        run_beacon_slot(
+            &mut radio,
+            data_channel + 5,
+            &own.encode(),
+            Duration::from_millis(5),
+            Duration::from_millis(20),
+            |beacon, _| heard.push(beacon.node_id),
+        )
+        .expect("beacon transmitted");
+
+        // Our own beacon comes back through the loopback once transmitted
+        assert_eq!(heard, vec![0xBEEF, 0xCAFE]);
+        assert_eq!(radio.get_config().channel, data_channel);
+    }
+
+    fn coded_frame(coding: LinkCoding) -> Vec<u8> {
+        let mut packet = Packet::<2048>::new();
+        let mut frame = Frame::<2048>::new();
+
+        packet.frame_mut().push_data(b"payload").expect("payload");
+        packet.build();
+
+        LdpcPacketCoder::<2048>::new()
+            .with_coding(coding)
+            .encode(&packet, &mut frame)
+            .expect("encoded frame");
+
+        frame.as_slice().to_vec()
+    }
+
+    #[test]
+    fn test_received_beacon_updates_decode_coding() {
+        let peer_coding = LinkCoding {
+            whitening_seed: 0x1A5,
+            payload_code: PayloadCode::Rate2_3,
+        };
+        let beacon = Beacon::new(
+            0x10,
+            CAPABILITY_LDPC | CAPABILITY_CODING,
+            &Modulation::Ofdm(OfdmModulation::default()),
+        )
+        .with_coding(peer_coding);
+
+        let received = Beacon::decode(&beacon.encode()).expect("valid beacon");
+        assert_eq!(received.coding, peer_coding);
+
+        let mut table =
+            PeerTable::new(Duration::from_secs(30)).with_coding(0x20, LinkCoding::default(), false);
+        let mut coding = table.subscribe_coding();
+        let mut decoder = PacketDecoder::new(None);
+
+        // Frames of the peer don't even have the layout of the default code
+        let frame = coded_frame(peer_coding);
+        decoder.set_coding(*coding.borrow());
+        assert!(decoder.decode(&frame).is_none());
+
+        table.update(0, received, -60, Instant::now());
+
+        assert!(coding.has_changed().unwrap());
+        assert_eq!(table.coding(), peer_coding);
+
+        decoder.set_coding(*coding.borrow_and_update());
+        let decoded = decoder.decode(&frame).expect("coded frame");
+        assert!(decoded.valid);
+        assert_eq!(&decoded.payload[..], b"payload");
+    }
+
+    #[test]
+    fn test_coding_follows_leading_peers_only() {
+        let coding = LinkCoding {
+            whitening_seed: 0x0F0,
+            payload_code: PayloadCode::Rate4_5,
+        };
+        let beacon = |node_id, capabilities| {
+            Beacon::new(node_id, capabilities, &Modulation::Off).with_coding(coding)
+        };
+        let now = Instant::now();
+
+        let mut table =
+            PeerTable::new(Duration::from_secs(30)).with_coding(0x20, LinkCoding::default(), false);
+
+        // Automatic peers with a higher node id follow us instead
+        table.update(0, beacon(0x30, CAPABILITY_CODING), -60, now);
+        assert_eq!(table.coding(), LinkCoding::default());
+
+        // Version 1 beacons advertise no coding
+        table.update(0, beacon(0x10, 0), -60, now);
+        assert_eq!(table.coding(), LinkCoding::default());
+
+        table.update(
+            0,
+            beacon(0x30, CAPABILITY_CODING | CAPABILITY_CODING_MANUAL),
+            -60,
+            now,
+        );
+        assert_eq!(table.coding(), coding);
+
+        // A manual coding is kept
+        let mut table =
+            PeerTable::new(Duration::from_secs(30)).with_coding(0x20, LinkCoding::default(), true);
+        table.update(0, beacon(0x10, CAPABILITY_CODING), -60, now);
+        assert_eq!(table.coding(), LinkCoding::default());
+        assert_eq!(
+            table.coding_capabilities(),
+            CAPABILITY_CODING | CAPABILITY_CODING_MANUAL
+        );
+    }
+
+    #[test]
+    fn test_reported_rssi_adjusts_tx_power() {
+        let mut reports = RssiReports::default();
+        reports.push(0x10, -88);
+        reports.push(0x20, -52);
+
+        let beacon = Beacon::new(0x30, CAPABILITY_LDPC, &Modulation::Off).with_reports(reports);
+        let received = Beacon::decode(&beacon.encode()).expect("valid beacon");
+        assert_eq!(received.reports, reports);
+
+        let control = PowerControl::new(-75, 3, TxPowerLimit::default());
+        let mut table = PeerTable::new(Duration::from_secs(30))
+            .with_coding(0x20, LinkCoding::default(), true)
+            .with_power_control(control);
+        table.update(0, received, -60, Instant::now());
+
+        // The peer hears us at -52 dBm, well above the target
+        let modulation = Modulation::Ofdm(OfdmModulation {
+            tx_power: 20,
+            ..Default::default()
+        });
+        let freq = Hertz::from_mhz(869);
+        let adjusted = table
+            .power_adjustment(0, &modulation, freq)
+            .expect("power lowered");
+        assert_eq!(adjusted.tx_power(), 19);
+        assert!(table.power_adjustment(1, &modulation, freq).is_none());
+
+        // The next step waits for a new report
+        assert!(table.power_adjustment(0, &adjusted, freq).is_none());
+        table.update(0, received, -60, Instant::now());
+        let adjusted = table
+            .power_adjustment(0, &adjusted, freq)
+            .expect("power lowered again");
+        assert_eq!(adjusted.tx_power(), 18);
+
+        assert_eq!(table.rssi_reports(0).get(0x30), Some(-60));
+    }
+
+    #[test]
+    fn test_received_beacon_updates_pending_modulation() {
+        let qpsk = Modulation::Qpsk(QpskModulation {
+            fchip: QpskChipFrequency::Fchip1000,
+            mode: QpskRateMode::RateMode2,
+            tx_power: 14,
+        });
+        let link = LinkAdvert {
+            channel: 7,
+            modulation: qpsk,
+        };
+
+        let mut reports = RssiReports::default();
+        for node_id in 0..MAX_RSSI_REPORTS as NodeId {
+            reports.push(node_id, -70);
+        }
+        let beacon = Beacon::new(0x30, CAPABILITY_MODULATION_MANUAL, &qpsk)
+            .with_link(link)
+            .with_reports(reports);
+        let received = Beacon::decode(&beacon.encode()).expect("valid beacon");
+        assert_eq!(received, beacon);
+
+        let now = Instant::now();
+        let mut table = PeerTable::new(Duration::from_secs(30))
+            .with_coding(0x20, LinkCoding::default(), true)
+            .with_manual_modulation(false);
+        table.update(1, received, -60, now);
+
+        assert_eq!(table.take_pending_link(0), None);
+        assert_eq!(table.take_pending_link(1), Some(link));
+
+        // Staged again until the module switched to it
+        table.update(1, received, -60, now);
+        assert_eq!(table.take_pending_link(1), Some(link));
+        table.link_followed(1, link);
+
+        // Then only once the peer advertises another link
+        table.update(1, received, -60, now);
+        assert_eq!(table.take_pending_link(1), None);
+
+        // The local tx power is kept
+        let own = Modulation::Ofdm(OfdmModulation::default());
+        let followed = link.modulation_for(&own).expect("modulation to follow");
+        assert_eq!(followed.tx_power(), own.tx_power());
+        assert!(matches!(followed, Modulation::Qpsk(q) if q.mode == QpskRateMode::RateMode2));
+
+        // A manual modulation stays
+        let mut table = PeerTable::new(Duration::from_secs(30));
+        table.update(1, received, -60, now);
+        assert_eq!(table.take_pending_link(1), None);
+        assert_eq!(
+            table.modulation_capabilities(),
+            CAPABILITY_MODULATION_MANUAL
+        );
+    }
+
+    #[test]
+    fn test_decode_version_1_beacon() {
+        let mut packet = Packet::<BEACON_FRAME_SIZE>::new();
+        let mut frame = Frame::<BEACON_FRAME_SIZE>::new();
+
+        packet
+            .header_mut()
+            .set_packet_type(PacketType::Beacon)
+            .set_id(0xBEEF);
+        packet
+            .frame_mut()
+            .push_data(&[1, 0x01, 0x00, BeaconModulation::Qpsk as u8, 14])
+            .expect("beacon payload");
+        packet.build();
+        BinaryPacketCoder::new()
+            .encode(&packet, &mut frame)
+            .expect("beacon frame");
+
+        let beacon = Beacon::decode(frame.as_slice()).expect("valid beacon");
+        assert_eq!(beacon.node_id, 0xBEEF);
+        assert_eq!(beacon.capabilities, CAPABILITY_LDPC);
+        assert_eq!(beacon.modulation, BeaconModulation::Qpsk);
+        assert_eq!(beacon.tx_power, 14);
+        assert_eq!(beacon.coding, LinkCoding::default());
+    }
+
+    #[test]
+    fn test_decode_rejects_other_frames() {
+        assert!(Beacon::decode(b"raw frame").is_none());
+
+        let mut data = Beacon::new(1, 0, &Modulation::Off).encode();
+        data[HEADER_SIZE + 4] ^= 0xFF;
+        assert!(Beacon::decode(&data).is_none());
+    }
+}
@@ -0,0 +1,96 @@
+//! Features reported by `GetCapabilities`.
+//!
+//! The set combines what this build was compiled with, what the detected
+//! transceiver can do and which optional features the configuration enables,
+//! so clients only offer controls the connected device supports.
+
+use kaonic_ctrl::protocol::{
+    CAPABILITY_APPLY_CONFIG, CAPABILITY_BAND_09, CAPABILITY_BAND_24, CAPABILITY_BEACONS,
+    CAPABILITY_DIVERSITY, CAPABILITY_FSK, CAPABILITY_LDPC, CAPABILITY_OFDM,
+    CAPABILITY_POWER_CONTROL, CAPABILITY_QOS, CAPABILITY_QPSK, CAPABILITY_RAW_CRC,
+    CAPABILITY_SIMULATED,
+};
+use kaonic_radio::radio::PartNumber;
+
+use crate::config::{CommdConfig, ReceiveMode};
+
+/// Capabilities of a device with the transceiver `part_number`, a full
+/// AT86RF215 is assumed if it's unknown
+pub fn capabilities(config: &CommdConfig, part_number: Option<PartNumber>) -> u32 {
+    let mut capabilities = CAPABILITY_BAND_09 | CAPABILITY_LDPC | CAPABILITY_APPLY_CONFIG;
+
+    // The IQ variant has no baseband core and can't modulate by itself
+    if part_number != Some(PartNumber::At86Rf215Iq) {
+        capabilities |= CAPABILITY_OFDM | CAPABILITY_QPSK | CAPABILITY_FSK | CAPABILITY_QOS;
+    }
+
+    // The M variant is sub-GHz only
+    if part_number != Some(PartNumber::At86Rf215M) {
+        capabilities |= CAPABILITY_BAND_24;
+    }
+
+    let enabled = [
+        (config.beacon.enabled, CAPABILITY_BEACONS),
+        (config.transmit.raw_crc, CAPABILITY_RAW_CRC),
+        (
+            config.receive.mode == ReceiveMode::Diversity,
+            CAPABILITY_DIVERSITY,
+        ),
+        (config.power_control.enabled, CAPABILITY_POWER_CONTROL),
+        (cfg!(feature = "machine-host"), CAPABILITY_SIMULATED),
+    ];
+
+    for (enabled, capability) in enabled {
+        if enabled {
+            capabilities |= capability;
+        }
+    }
+
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_follow_config_and_part() {
+        let config = CommdConfig::parse(
+            r#"
+            [beacon]
+            enabled = true
+
+            [transmit]
+            raw_crc = true
+            "#,
+        )
+        .expect("valid config");
+
+        let full = capabilities(&config, Some(PartNumber::At86Rf215));
+        for capability in [
+            CAPABILITY_OFDM,
+            CAPABILITY_QPSK,
+            CAPABILITY_BAND_24,
+            CAPABILITY_QOS,
+            CAPABILITY_BEACONS,
+            CAPABILITY_RAW_CRC,
+        ] {
+            assert_ne!(full & capability, 0);
+        }
+        assert_eq!(full & (CAPABILITY_DIVERSITY | CAPABILITY_POWER_CONTROL), 0);
+        assert_eq!(
+            full & CAPABILITY_SIMULATED != 0,
+            cfg!(feature = "machine-host")
+        );
+
+        let defaults = capabilities(&CommdConfig::default(), None);
+        assert_eq!(defaults & (CAPABILITY_BEACONS | CAPABILITY_RAW_CRC), 0);
+
+        let sub_ghz = capabilities(&config, Some(PartNumber::At86Rf215M));
+        assert_eq!(sub_ghz & CAPABILITY_BAND_24, 0);
+        assert_ne!(sub_ghz & CAPABILITY_BAND_09, 0);
+
+        let iq = capabilities(&config, Some(PartNumber::At86Rf215Iq));
+        assert_eq!(iq & (CAPABILITY_OFDM | CAPABILITY_QPSK | CAPABILITY_QOS), 0);
+    }
+}
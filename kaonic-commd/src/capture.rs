@@ -0,0 +1,160 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use kaonic_net::coder::LinkCoding;
+use tokio::sync::watch;
+
+use crate::{config::CaptureConfig, decoder::PacketDecoder};
+
+/// Raw bytes of a received frame as the radio delivered them
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub data: Vec<u8>,
+    pub rssi: i8,
+    /// Outcome of the kaonic-net decode, `None` for frames without its layout
+    pub valid: Option<bool>,
+    pub received: Instant,
+}
+
+/// Ring of the last frames a module received, kept for offline re-decoding
+///
+/// Frames are stored before any CRC trailer is stripped, so a failed decode
+/// can be replayed bit for bit. Kaonic-net frames are run through the LDPC
+/// decoder to tell failures apart, which costs a second decode per frame
+/// while adaptive modulation is on.
+pub struct ReceiveCapture {
+    config: CaptureConfig,
+    frames: VecDeque<CapturedFrame>,
+    decoder: Box<PacketDecoder>,
+    coding: watch::Receiver<LinkCoding>,
+}
+
+/// Shared between the receive loop of a module and the gRPC debug call
+pub type SharedReceiveCapture = Arc<Mutex<ReceiveCapture>>;
+
+impl ReceiveCapture {
+    /// Frames are decoded with the current `coding` within `iteration_budget`
+    pub fn new(
+        config: &CaptureConfig,
+        coding: watch::Receiver<LinkCoding>,
+        iteration_budget: Option<usize>,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            frames: VecDeque::with_capacity(config.frames),
+            decoder: PacketDecoder::new(iteration_budget),
+            coding,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.frames > 0
+    }
+
+    /// Records `data`, dropping the oldest frame once the ring is full
+    pub fn capture(&mut self, data: &[u8], rssi: i8) {
+        if !self.enabled() {
+            return;
+        }
+
+        self.decoder.set_coding(*self.coding.borrow());
+        let valid = self.decoder.decode(data).map(|decoded| decoded.valid);
+
+        if self.config.failures_only && valid != Some(false) {
+            return;
+        }
+
+        if self.frames.len() == self.config.frames {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(CapturedFrame {
+            data: data.to_vec(),
+            rssi,
+            valid,
+            received: Instant::now(),
+        });
+    }
+
+    /// Captured frames, oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &CapturedFrame> {
+        self.frames.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kaonic_frame::frame::Frame;
+    use kaonic_net::{
+        coder::{HEADER_LDPC_CODE, LdpcPacketCoder, PacketCoder},
+        packet::Packet,
+    };
+
+    fn coded_frame() -> Vec<u8> {
+        let mut packet = Packet::<2048>::new();
+        let mut frame = Frame::<2048>::new();
+        packet
+            .frame_mut()
+            .push_data(b"@@ CAPTURED @@")
+            .expect("packet with data");
+        packet.build();
+        LdpcPacketCoder::new()
+            .encode(&packet, &mut frame)
+            .expect("encoded frame");
+
+        frame.as_slice().to_vec()
+    }
+
+    fn capture(frames: usize, failures_only: bool) -> ReceiveCapture {
+        let config = CaptureConfig {
+            frames,
+            failures_only,
+        };
+        ReceiveCapture::new(
+            &config,
+            watch::Sender::new(LinkCoding::default()).subscribe(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_failed_decode_is_retained() {
+        let good = coded_frame();
+        let mut bad = good.clone();
+        bad[HEADER_LDPC_CODE.n() / 8..]
+            .iter_mut()
+            .for_each(|byte| *byte ^= 0x5A);
+
+        let mut ring = capture(2, true);
+        ring.capture(&good, -60);
+        ring.capture(b"@@ RAW FRAME @@", -61);
+        ring.capture(&bad, -90);
+
+        let frames: Vec<_> = ring.frames().collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, bad);
+        assert_eq!(frames[0].rssi, -90);
+        assert_eq!(frames[0].valid, Some(false));
+    }
+
+    #[test]
+    fn test_ring_keeps_last_frames() {
+        let mut ring = capture(2, false);
+        ring.capture(&coded_frame(), -60);
+        ring.capture(b"first", -61);
+        ring.capture(b"second", -62);
+
+        let frames: Vec<_> = ring.frames().map(|frame| frame.data.clone()).collect();
+        assert_eq!(frames, [b"first".to_vec(), b"second".to_vec()]);
+        assert!(ring.frames().all(|frame| frame.valid.is_none()));
+
+        let mut disabled = capture(0, false);
+        disabled.capture(b"first", -61);
+        assert_eq!(disabled.frames().count(), 0);
+    }
+}
@@ -0,0 +1,77 @@
+use core::ops::Range;
+use std::time::Duration;
+
+use kaonic_radio::{
+    error::KaonicError,
+    frequency_plan::FrequencyPlan,
+    radio::{ChannelEnergy, Radio},
+};
+use radio_common::{Hertz, RadioChannel, RadioConfig};
+
+/// Channels scanned for automatic channel selection while tuned on `config`
+///
+/// `channel_count` overrides the channel count of the matching frequency plan.
+/// Returns `None` if neither is known.
+pub fn scan_range(config: &RadioConfig, channel_count: Option<u16>) -> Option<Range<RadioChannel>> {
+    let count =
+        channel_count.or_else(|| FrequencyPlan::matching(config).map(|plan| plan.channel_count))?;
+
+    Some(0..count)
+}
+
+/// Carrier frequency `config` tunes the radio to
+pub fn carrier(config: &RadioConfig) -> Hertz {
+    Hertz::new(config.freq.as_hz() + config.channel as u64 * config.channel_spacing.as_hz())
+}
+
+/// Scans the band `radio` is tuned on and moves it to the quietest channel
+///
+/// Returns [`KaonicError::IncorrectSettings`] if the channels to scan aren't
+/// known, see [`scan_range`].
+///
+/// Takes about `dwell` per channel, e.g. 350 ms for the 35 channels of EU 868
+/// at the default 10 ms. The caller holds the radio all along, so the
+/// module's receive worker, transmit queue and other calls wait that long.
+pub fn select_best_channel<R: Radio>(
+    radio: &mut R,
+    channel_count: Option<u16>,
+    dwell: Duration,
+) -> Result<ChannelEnergy, KaonicError> {
+    let channels =
+        scan_range(&radio.get_config(), channel_count).ok_or(KaonicError::IncorrectSettings)?;
+
+    radio.select_best_channel(channels, dwell)
+}
+
+#[cfg(test)]
+mod tests {
+    use kaonic_radio::frequency_plan::EU_868;
+    use radio_common::{RadioConfigBuilder, frequency::BandwidthFilter};
+
+    use super::*;
+
+    #[test]
+    fn test_carrier() {
+        let config = EU_868.radio_config(3, BandwidthFilter::Wide).unwrap();
+        assert_eq!(carrier(&config), EU_868.channel_to_freq(3).unwrap());
+
+        // The same carrier from another raster
+        let other = RadioConfigBuilder::new()
+            .freq(Hertz::from_khz(863_500))
+            .channel_spacing(Hertz::from_khz(100))
+            .channel(2)
+            .build();
+        assert_eq!(carrier(&other), carrier(&config));
+    }
+
+    #[test]
+    fn test_scan_range() {
+        let config = EU_868.radio_config(3, BandwidthFilter::Wide).unwrap();
+        assert_eq!(scan_range(&config, None), Some(0..35));
+        assert_eq!(scan_range(&config, Some(8)), Some(0..8));
+
+        let custom = RadioConfigBuilder::new().build();
+        assert_eq!(scan_range(&custom, None), None);
+        assert_eq!(scan_range(&custom, Some(4)), Some(0..4));
+    }
+}
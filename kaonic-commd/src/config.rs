@@ -0,0 +1,1222 @@
+use kaonic_net::{
+    coder::{LinkCoding, PayloadCode},
+    packet::PacketIdNamespace,
+};
+use kaonic_qos::{ChannelQuality, ModulationBounds, scheme::ofdm_mcs};
+use kaonic_radio::{
+    power::{TX_POWER_MAX, TxPowerLimit},
+    radio::{AddressFilter, AgcGainMap, AutoAck, CcaMode, MAX_ACK_TIME_US, MAX_TX_RETRIES},
+    regulatory::RegulatoryProfile,
+    spi::{RF215_MAX_SPI_SPEED, SpiMode, SpiSettings},
+};
+use serde::{Deserialize, Deserializer, de::Error};
+
+/// Default location of the daemon configuration
+pub const CONFIG_PATH: &str = "/etc/kaonic/kaonic-commd.toml";
+
+/// kaonic-commd startup configuration
+///
+/// Every section is optional; a missing file or key keeps the default.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct CommdConfig {
+    pub worker: WorkerConfig,
+    pub thermal: ThermalConfig,
+    pub beacon: BeaconConfig,
+    pub battery: BatteryConfig,
+    pub network: NetworkConfig,
+    pub transmit: TransmitConfig,
+    pub receive: ReceiveConfig,
+    pub tx_power: TxPowerConfig,
+    pub power_control: PowerControlConfig,
+    pub channel: ChannelConfig,
+    pub grpc: GrpcConfig,
+    pub qos: QosConfig,
+    pub coding: CodingConfig,
+    pub spi: SpiConfig,
+    pub address_filter: AddressFilterConfig,
+    pub auto_ack: AutoAckConfig,
+    pub capture: CaptureConfig,
+}
+
+/// Startup and scheduling of the per-module radio workers
+///
+/// Real-time priority requires `CAP_SYS_NICE` (or root). Without it the
+/// threads keep the default scheduler and a warning is logged (Linux only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WorkerConfig {
+    /// `SCHED_FIFO` priority (1-99)
+    pub rt_priority: Option<i32>,
+    /// CPU core for each module's worker thread, indexed by module
+    pub cpu_affinity: Vec<usize>,
+    /// Take each transceiver through a verified reset before it's set up
+    pub bringup: bool,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            rt_priority: None,
+            cpu_affinity: Vec::new(),
+            bringup: true,
+        }
+    }
+}
+
+/// Transceiver temperature monitoring
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThermalConfig {
+    /// Sampling interval in milliseconds
+    pub interval_ms: u64,
+    /// Temperature in °C above which TX power is reduced
+    pub throttle_celsius: Option<f32>,
+    /// TX power applied while throttled
+    pub throttle_tx_power: u8,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 5000,
+            throttle_celsius: None,
+            throttle_tx_power: 10,
+        }
+    }
+}
+
+/// Peer-discovery beacons
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BeaconConfig {
+    /// Periodically transmit an identity beacon on every module
+    pub enabled: bool,
+    /// Beacon transmit interval in milliseconds
+    pub interval_ms: u64,
+    /// Peers not heard from for this long are dropped from the peer table
+    pub peer_timeout_ms: u64,
+    /// Node id announced in beacons, derived from the device serial if unset
+    pub node_id: Option<u32>,
+    /// Dedicated beacon channel, beacons share the data channel if unset
+    pub channel: Option<u16>,
+    /// Time spent on the beacon channel per interval in milliseconds
+    pub slot_ms: u64,
+    /// Switch to the data channel and modulation advertised by peers
+    /// before transmitting, instead of keeping the configured ones
+    pub follow_modulation: bool,
+}
+
+impl Default for BeaconConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: 10_000,
+            peer_timeout_ms: 35_000,
+            node_id: None,
+            channel: None,
+            slot_ms: 200,
+            follow_modulation: false,
+        }
+    }
+}
+
+/// Supply voltage (EVDD) monitoring of the transceivers
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct BatteryConfig {
+    /// Brownout threshold in millivolts (1700-3675), monitoring is off if unset
+    pub threshold_mv: Option<u16>,
+    /// Refuse to transmit while the supply is below the threshold
+    pub tx_inhibit: bool,
+}
+
+/// Reassembly of segmented client messages on the UDP server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Partially received messages kept at once, the oldest is evicted beyond it
+    pub max_pending: usize,
+    /// Node prefix in the top 8 bits of the sent message ids, fully random if unset
+    pub id_prefix: Option<u8>,
+}
+
+impl NetworkConfig {
+    pub fn id_namespace(&self) -> PacketIdNamespace {
+        self.id_prefix
+            .map(|prefix| PacketIdNamespace::new(prefix.into(), 8))
+            .unwrap_or(PacketIdNamespace::NONE)
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            max_pending: 8,
+            id_prefix: None,
+        }
+    }
+}
+
+/// Radio behaviour around transmissions
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransmitConfig {
+    /// Let the baseband switch back to RX at the end of each frame (TX2RX)
+    ///
+    /// Shortens the RX gap after a transmission so an immediate ACK is caught,
+    /// but frames are sent without a clear channel assessment.
+    pub auto_turnaround: bool,
+    /// Retries after a failed transmit attempt (0-15), platform default if unset
+    pub retries: Option<u8>,
+    /// Time a module gets to return to RX after each transmission before the
+    /// frame is aborted, platform default if unset
+    pub rx_restore_timeout_ms: Option<u64>,
+    /// Clear channel assessment before each attempt: energy detection,
+    /// carrier sense on frame starts of the configured PHY, or both
+    #[serde(deserialize_with = "deserialize_cca_mode")]
+    pub cca_mode: CcaMode,
+    /// Listen before talk rules the transmissions comply with: the energy
+    /// detection threshold and listen time, and the minimum off-time
+    #[serde(deserialize_with = "deserialize_regulatory_profile")]
+    pub regulatory_profile: RegulatoryProfile,
+    /// Append a CRC-32 to raw frames on transmit and check it on receive
+    ///
+    /// The trailer is stripped from received frames that match it. Both ends
+    /// of the link must agree on this setting.
+    pub raw_crc: bool,
+    /// Let the transceiver append and check an FCS on every frame
+    ///
+    /// Modules whose transceiver can't fall back to the `raw_crc` CRC-32.
+    pub hardware_fcs: bool,
+    /// Read the transmit power back from the transceiver after each frame
+    /// and warn if it isn't the power of the modulation
+    pub verify_tx_power: bool,
+    /// Whether each module may transmit, indexed by module
+    ///
+    /// Modules past the end of the list transmit. A module set to `false`
+    /// only receives: transmit requests fail, and it sends no beacons,
+    /// heartbeats or ACKs.
+    pub tx_enabled: Vec<bool>,
+}
+
+impl TransmitConfig {
+    /// Whether `module` may transmit
+    pub fn module_tx_enabled(&self, module: usize) -> bool {
+        self.tx_enabled.get(module).copied().unwrap_or(true)
+    }
+}
+
+fn deserialize_cca_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CcaMode, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "energy" => Ok(CcaMode::Energy),
+        "carrier_sense" => Ok(CcaMode::CarrierSense),
+        "combined" => Ok(CcaMode::Combined),
+        _ => Err(D::Error::custom(format!(
+            "unknown cca mode '{name}', expected energy, carrier_sense or combined"
+        ))),
+    }
+}
+
+fn deserialize_regulatory_profile<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<RegulatoryProfile, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "fcc" => Ok(RegulatoryProfile::Fcc),
+        "etsi" => Ok(RegulatoryProfile::Etsi),
+        _ => Err(D::Error::custom(format!(
+            "unknown regulatory profile '{name}', expected fcc or etsi"
+        ))),
+    }
+}
+
+/// How the modules share the received frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReceiveMode {
+    /// Every module is tuned and delivers its frames on its own
+    #[default]
+    Independent,
+    /// Module 1 follows the channel and modulation of module 0, only the
+    /// better copy of each frame received on both is delivered
+    Diversity,
+}
+
+/// Reception across the modules
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReceiveConfig {
+    #[serde(deserialize_with = "deserialize_receive_mode")]
+    pub mode: ReceiveMode,
+    /// Copies from both modules within this time are taken as one frame
+    pub diversity_window_ms: u64,
+    /// Gain of the external LNA the AGC accounts for, the board's if unset
+    ///
+    /// The Kaonic 1S front end needs `external12db`. A wrong map skews RSSI
+    /// and energy readings, and with them channel selection and QoS.
+    #[serde(deserialize_with = "deserialize_agc_gain_map")]
+    pub agc_gain_map: Option<AgcGainMap>,
+    /// LDPC bit-flip iterations a kaonic-net frame may cost to decode in
+    /// total, unbounded if unset
+    ///
+    /// A frame exceeding it counts as corrupted, so a badly corrupted large
+    /// frame can't hold up the receive loop.
+    pub ldpc_iteration_budget: Option<usize>,
+    /// Frames per second a module forwards at most, unlimited if unset
+    ///
+    /// Frames above it are still counted and fed to the QoS, but not
+    /// captured or forwarded to clients.
+    pub max_frame_rate: Option<u32>,
+}
+
+impl Default for ReceiveConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReceiveMode::default(),
+            diversity_window_ms: 5,
+            agc_gain_map: None,
+            ldpc_iteration_budget: None,
+            max_frame_rate: None,
+        }
+    }
+}
+
+fn deserialize_agc_gain_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<AgcGainMap>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "internal" => Ok(Some(AgcGainMap::Internal)),
+        "external9db" => Ok(Some(AgcGainMap::Extranal9dB)),
+        "external12db" => Ok(Some(AgcGainMap::Extranal12dB)),
+        _ => Err(D::Error::custom(format!(
+            "unknown agc gain map '{name}', expected internal, external9db or external12db"
+        ))),
+    }
+}
+
+fn deserialize_receive_mode<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<ReceiveMode, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "independent" => Ok(ReceiveMode::Independent),
+        "diversity" => Ok(ReceiveMode::Diversity),
+        _ => Err(D::Error::custom(format!(
+            "unknown receive mode '{name}', expected independent or diversity"
+        ))),
+    }
+}
+
+/// Per-band transmit power ceiling in PAC power levels (0-31)
+///
+/// Every modulation applied to a module, including adaptive QoS boosts, is
+/// clamped to the ceiling of its band. Unset bands are only limited by the
+/// PAC range.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct TxPowerConfig {
+    /// Ceiling for the sub-GHz band
+    pub band_09: Option<u8>,
+    /// Ceiling for the 2.4GHz band
+    pub band_24: Option<u8>,
+}
+
+impl TxPowerConfig {
+    pub fn limit(&self) -> TxPowerLimit {
+        let ceiling = |band: Option<u8>| band.unwrap_or(TX_POWER_MAX).min(TX_POWER_MAX);
+
+        TxPowerLimit::new(ceiling(self.band_09), ceiling(self.band_24))
+    }
+}
+
+/// Closed-loop TX power control from the RSSI peers report in their beacons
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PowerControlConfig {
+    /// Adjust the TX power to the RSSI reported by the peers, needs beacons
+    pub enabled: bool,
+    /// RSSI in dBm the weakest peer should receive our frames at
+    pub target_rssi: i8,
+    /// Reported RSSI within this many dB of the target keeps the power
+    pub hysteresis_db: u8,
+}
+
+impl Default for PowerControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_rssi: -75,
+            hysteresis_db: 3,
+        }
+    }
+}
+
+/// Automatic channel selection
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ChannelConfig {
+    /// Scan the band at startup and move every module to its quietest channel
+    pub auto_select: bool,
+    /// Channels scanned, starting at 0
+    ///
+    /// Defaults to the channel count of the frequency plan the radio is tuned
+    /// on. Required when the radio isn't on one of the presets.
+    pub channel_count: Option<u16>,
+    /// Time spent measuring the energy of each channel in milliseconds
+    pub dwell_ms: u64,
+    /// What `SetConfig` does when it tunes a module to the carrier of another
+    #[serde(deserialize_with = "deserialize_channel_conflict")]
+    pub conflict: ChannelConflict,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            auto_select: false,
+            channel_count: None,
+            dwell_ms: 10,
+            conflict: ChannelConflict::default(),
+        }
+    }
+}
+
+/// Response to two modules on the same carrier
+///
+/// The transmitter of one module desenses the receiver of the other, so
+/// it's usually a misconfiguration. Receive diversity is exempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelConflict {
+    /// Apply the configuration and log a warning
+    #[default]
+    Warn,
+    /// Refuse the configuration
+    Reject,
+}
+
+fn deserialize_channel_conflict<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<ChannelConflict, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "warn" => Ok(ChannelConflict::Warn),
+        "reject" => Ok(ChannelConflict::Reject),
+        _ => Err(D::Error::custom(format!(
+            "unknown channel conflict '{name}', expected warn or reject"
+        ))),
+    }
+}
+
+/// Keepalives and timeouts of the gRPC server
+///
+/// Keeps long-lived streams from being dropped by NATs or stateful firewalls
+/// while the radio is quiet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GrpcConfig {
+    /// HTTP/2 keepalive ping interval in milliseconds, 0 disables the pings
+    pub keepalive_interval_ms: u64,
+    /// Connections that don't acknowledge a ping within this time are closed
+    pub keepalive_timeout_ms: u64,
+    /// Send an empty `ReceiveResponse` (no frame) on receive streams that have
+    /// been idle for this many milliseconds, off if unset
+    pub stream_keepalive_ms: Option<u64>,
+    /// Calls waiting for a module, e.g. `Transmit` or `SetConfig`, fail with
+    /// `DEADLINE_EXCEEDED` if it doesn't acknowledge within this many milliseconds
+    pub command_timeout_ms: u64,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            keepalive_interval_ms: 30_000,
+            keepalive_timeout_ms: 10_000,
+            stream_keepalive_ms: None,
+            command_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Adaptive modulation from the decode results of received kaonic-net frames
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QosConfig {
+    /// Follow the QoS modulation recommendation on every module
+    pub enabled: bool,
+    /// Packet error rate in percent above which the modulation steps down
+    pub per_threshold: u32,
+    /// Switch between OFDM and QPSK with the channel quality
+    pub adaptive_modulation_type: bool,
+    /// Quality at and below which the adaptive modulation type picks QPSK
+    #[serde(deserialize_with = "deserialize_quality")]
+    pub qpsk_crossover: ChannelQuality,
+    /// Minimum time between two modulation changes in milliseconds,
+    /// recommendations in between are coalesced
+    pub modulation_debounce_ms: u64,
+    /// Heartbeat transmit interval in milliseconds, no heartbeats if unset
+    ///
+    /// Heartbeats give the peers' QoS something to decode while no data flows.
+    pub heartbeat_interval_ms: Option<u64>,
+    /// Most robust OFDM MCS (0-6) the QoS recommends, unbounded if unset
+    pub min_mcs: Option<u8>,
+    /// Fastest OFDM MCS (0-6) the QoS recommends, unbounded if unset
+    pub max_mcs: Option<u8>,
+    /// Highest transmit power the QoS recommends, unbounded if unset
+    pub max_tx_power: Option<u8>,
+}
+
+impl QosConfig {
+    /// Bounds of the recommended modulations, MCS out of range are ignored
+    pub fn modulation_bounds(&self) -> ModulationBounds {
+        ModulationBounds {
+            min_mcs: self.min_mcs.and_then(ofdm_mcs),
+            max_mcs: self.max_mcs.and_then(ofdm_mcs),
+            max_tx_power: self.max_tx_power,
+        }
+    }
+}
+
+impl Default for QosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            per_threshold: 10,
+            adaptive_modulation_type: false,
+            qpsk_crossover: ChannelQuality::Poor,
+            modulation_debounce_ms: 1000,
+            heartbeat_interval_ms: None,
+            min_mcs: None,
+            max_mcs: None,
+            max_tx_power: None,
+        }
+    }
+}
+
+fn deserialize_quality<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<ChannelQuality, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "excellent" => Ok(ChannelQuality::Excellent),
+        "good" => Ok(ChannelQuality::Good),
+        "fair" => Ok(ChannelQuality::Fair),
+        "poor" => Ok(ChannelQuality::Poor),
+        "bad" => Ok(ChannelQuality::Bad),
+        _ => Err(D::Error::custom(format!(
+            "unknown channel quality '{name}', expected excellent, good, fair, poor or bad"
+        ))),
+    }
+}
+
+/// Air format of kaonic-net frames, advertised in beacons
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct CodingConfig {
+    /// PN9 whitening seed (9 bits), 0 disables whitening
+    pub whitening_seed: u16,
+    /// Payload LDPC code: "rate1/2", "rate2/3" or "rate4/5"
+    #[serde(deserialize_with = "deserialize_payload_code")]
+    pub payload_code: PayloadCode,
+    /// Keep this coding instead of adopting the one of discovered peers
+    pub manual: bool,
+}
+
+impl CodingConfig {
+    pub fn link_coding(&self) -> LinkCoding {
+        LinkCoding {
+            whitening_seed: self.whitening_seed,
+            payload_code: self.payload_code,
+        }
+    }
+}
+
+/// SPI bus of the radio modules, read once at startup
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpiConfig {
+    /// Clock in Hz, the board revision's default (5 or 12 MHz) if unset
+    pub max_speed_hz: Option<u32>,
+    /// SPI mode 0-3
+    pub mode: u8,
+}
+
+impl SpiConfig {
+    pub fn settings(&self) -> SpiSettings {
+        SpiSettings {
+            max_speed: self.max_speed_hz,
+            mode: SpiMode::from_u8(self.mode).unwrap_or_default(),
+        }
+    }
+}
+
+/// IEEE 802.15.4 frame filter of the basebands
+///
+/// While enabled only 802.15.4 MAC frames addressed to this node, or
+/// broadcast on its PAN, are received. kaonic-net and raw frames are dropped.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct AddressFilterConfig {
+    pub enabled: bool,
+    pub pan_id: u16,
+    pub short_address: u16,
+    /// EUI-64, TOML integers limit it to 63 bits
+    pub extended_address: u64,
+}
+
+impl AddressFilterConfig {
+    pub fn filter(&self) -> Option<AddressFilter> {
+        self.enabled.then_some(AddressFilter {
+            pan_id: self.pan_id,
+            short_address: self.short_address,
+            extended_address: self.extended_address,
+        })
+    }
+}
+
+/// Hardware acknowledgement of frames passing the address filter
+///
+/// Frames with the ACK request bit set are acknowledged by the baseband
+/// without the daemon being involved. Needs `[address_filter]`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoAckConfig {
+    pub enabled: bool,
+    /// Frame end to ACK start in µs (0-2047), the 802.15.4 turnaround time if unset
+    pub ack_time_us: Option<u16>,
+    /// Set the frame pending bit of the ACKs
+    pub frame_pending: bool,
+}
+
+impl AutoAckConfig {
+    pub fn auto_ack(&self) -> Option<AutoAck> {
+        self.enabled.then_some(AutoAck {
+            frame_pending: self.frame_pending,
+            ack_time_us: self.ack_time_us,
+        })
+    }
+}
+
+/// Raw copies of received frames kept for offline re-decoding
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// Frames kept per module, capture is off at 0
+    pub frames: usize,
+    /// Keep only kaonic-net frames that failed to decode
+    pub failures_only: bool,
+}
+
+fn deserialize_payload_code<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<PayloadCode, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    match name.to_ascii_lowercase().as_str() {
+        "rate1/2" => Ok(PayloadCode::Rate1_2),
+        "rate2/3" => Ok(PayloadCode::Rate2_3),
+        "rate4/5" => Ok(PayloadCode::Rate4_5),
+        _ => Err(D::Error::custom(format!(
+            "unknown payload code '{name}', expected rate1/2, rate2/3 or rate4/5"
+        ))),
+    }
+}
+
+impl CommdConfig {
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        let config: Self = toml::from_str(content)?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Rejects values which parse but can't be applied
+    fn validate(&self) -> Result<(), toml::de::Error> {
+        if self
+            .transmit
+            .retries
+            .is_some_and(|retries| retries > MAX_TX_RETRIES)
+        {
+            return Err(toml::de::Error::custom(format!(
+                "transmit.retries must be at most {MAX_TX_RETRIES}"
+            )));
+        }
+
+        if self.transmit.rx_restore_timeout_ms == Some(0) {
+            return Err(toml::de::Error::custom(
+                "transmit.rx_restore_timeout_ms must not be zero",
+            ));
+        }
+
+        // ETSI listen before talk is an energy measurement before each frame
+        if self.transmit.regulatory_profile == RegulatoryProfile::Etsi {
+            if self.transmit.auto_turnaround {
+                return Err(toml::de::Error::custom(
+                    "transmit.regulatory_profile etsi needs auto_turnaround off",
+                ));
+            }
+
+            if !self.transmit.cca_mode.senses_energy() {
+                return Err(toml::de::Error::custom(
+                    "transmit.regulatory_profile etsi needs cca_mode energy or combined",
+                ));
+            }
+        }
+
+        if self.coding.whitening_seed > 0x1FF {
+            return Err(toml::de::Error::custom(
+                "coding.whitening_seed must fit in 9 bits",
+            ));
+        }
+
+        if self
+            .spi
+            .max_speed_hz
+            .is_some_and(|speed| speed == 0 || speed > RF215_MAX_SPI_SPEED)
+        {
+            return Err(toml::de::Error::custom(format!(
+                "spi.max_speed_hz must be between 1 and {RF215_MAX_SPI_SPEED}"
+            )));
+        }
+
+        if SpiMode::from_u8(self.spi.mode).is_none() {
+            return Err(toml::de::Error::custom("spi.mode must be 0, 1, 2 or 3"));
+        }
+
+        if self.grpc.command_timeout_ms == 0 {
+            return Err(toml::de::Error::custom(
+                "grpc.command_timeout_ms must be greater than 0",
+            ));
+        }
+
+        if self.receive.max_frame_rate == Some(0) {
+            return Err(toml::de::Error::custom(
+                "receive.max_frame_rate must be greater than 0",
+            ));
+        }
+
+        if self.receive.mode == ReceiveMode::Diversity && self.receive.diversity_window_ms == 0 {
+            return Err(toml::de::Error::custom(
+                "receive.diversity_window_ms must be greater than 0",
+            ));
+        }
+
+        for (key, mcs) in [("min_mcs", self.qos.min_mcs), ("max_mcs", self.qos.max_mcs)] {
+            if mcs.is_some_and(|mcs| ofdm_mcs(mcs).is_none()) {
+                return Err(toml::de::Error::custom(format!(
+                    "qos.{key} must be between 0 and 6"
+                )));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.qos.min_mcs, self.qos.max_mcs)
+            && min > max
+        {
+            return Err(toml::de::Error::custom(
+                "qos.min_mcs must not be above qos.max_mcs",
+            ));
+        }
+
+        if self
+            .qos
+            .max_tx_power
+            .is_some_and(|power| power > TX_POWER_MAX)
+        {
+            return Err(toml::de::Error::custom(format!(
+                "qos.max_tx_power must be at most {TX_POWER_MAX}"
+            )));
+        }
+
+        if self.power_control.enabled && !self.beacon.enabled {
+            return Err(toml::de::Error::custom(
+                "power_control needs beacon to be enabled",
+            ));
+        }
+
+        if self.auto_ack.enabled && !self.address_filter.enabled {
+            return Err(toml::de::Error::custom(
+                "auto_ack needs address_filter to be enabled",
+            ));
+        }
+
+        if self
+            .auto_ack
+            .ack_time_us
+            .is_some_and(|time| time > MAX_ACK_TIME_US)
+        {
+            return Err(toml::de::Error::custom(format!(
+                "auto_ack.ack_time_us must be at most {MAX_ACK_TIME_US}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Loads the configuration from `path`, falling back to defaults
+    pub fn load(path: &str) -> Self {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => {
+                log::info!("{} not found, using default config", path);
+                return Self::default();
+            }
+        };
+
+        match Self::parse(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("invalid {}: {}, using default config", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use radio_common::modulation::OfdmMcs;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_worker_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [worker]
+            rt_priority = 50
+            cpu_affinity = [1, 0]
+            bringup = false
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.worker.rt_priority, Some(50));
+        assert_eq!(config.worker.cpu_affinity, [1, 0]);
+        assert!(!config.worker.bringup);
+    }
+
+    #[test]
+    fn test_parse_thermal_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [thermal]
+            throttle_celsius = 85.0
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.thermal.throttle_celsius, Some(85.0));
+        assert_eq!(config.thermal.interval_ms, 5000);
+    }
+
+    #[test]
+    fn test_parse_beacon_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [beacon]
+            enabled = true
+            interval_ms = 2000
+            channel = 12
+            follow_modulation = true
+            "#,
+        )
+        .expect("valid config");
+
+        assert!(config.beacon.enabled);
+        assert!(config.beacon.follow_modulation);
+        assert_eq!(config.beacon.interval_ms, 2000);
+        assert_eq!(config.beacon.peer_timeout_ms, 35_000);
+        assert_eq!(config.beacon.channel, Some(12));
+        assert_eq!(config.beacon.slot_ms, 200);
+    }
+
+    #[test]
+    fn test_parse_battery_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [battery]
+            threshold_mv = 3300
+            tx_inhibit = true
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.battery.threshold_mv, Some(3300));
+        assert!(config.battery.tx_inhibit);
+    }
+
+    #[test]
+    fn test_parse_network_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [network]
+            max_pending = 4
+            id_prefix = 0x2A
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.network.max_pending, 4);
+        assert_eq!(
+            config.network.id_namespace().apply(0x0012_3456),
+            0x2A12_3456
+        );
+    }
+
+    #[test]
+    fn test_parse_transmit_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [transmit]
+            auto_turnaround = true
+            retries = 1
+            rx_restore_timeout_ms = 20
+            raw_crc = true
+            cca_mode = "carrier_sense"
+            verify_tx_power = true
+            hardware_fcs = true
+            tx_enabled = [false]
+            "#,
+        )
+        .expect("valid config");
+
+        assert!(config.transmit.auto_turnaround);
+        assert_eq!(config.transmit.retries, Some(1));
+        assert_eq!(config.transmit.rx_restore_timeout_ms, Some(20));
+        assert!(config.transmit.raw_crc);
+        assert_eq!(config.transmit.cca_mode, CcaMode::CarrierSense);
+        assert!(config.transmit.verify_tx_power);
+        assert!(config.transmit.hardware_fcs);
+        assert!(!config.transmit.module_tx_enabled(0));
+        assert!(config.transmit.module_tx_enabled(1));
+
+        assert!(CommdConfig::parse("[transmit]\nretries = 255").is_err());
+        assert!(CommdConfig::parse("[transmit]\nrx_restore_timeout_ms = 0").is_err());
+        assert!(CommdConfig::parse("[transmit]\ncca_mode = \"preamble\"").is_err());
+        assert_eq!(config.transmit.regulatory_profile, RegulatoryProfile::Fcc);
+    }
+
+    #[test]
+    fn test_parse_regulatory_profile() {
+        let config =
+            CommdConfig::parse("[transmit]\nregulatory_profile = \"etsi\"").expect("valid config");
+        assert_eq!(config.transmit.regulatory_profile, RegulatoryProfile::Etsi);
+
+        let config = CommdConfig::parse(
+            "[transmit]\nregulatory_profile = \"ETSI\"\ncca_mode = \"combined\"",
+        )
+        .expect("valid config");
+        assert_eq!(config.transmit.regulatory_profile, RegulatoryProfile::Etsi);
+
+        // Transmissions without an energy measurement can't comply
+        assert!(
+            CommdConfig::parse(
+                "[transmit]\nregulatory_profile = \"etsi\"\ncca_mode = \"carrier_sense\""
+            )
+            .is_err()
+        );
+        assert!(
+            CommdConfig::parse("[transmit]\nregulatory_profile = \"etsi\"\nauto_turnaround = true")
+                .is_err()
+        );
+        assert!(CommdConfig::parse("[transmit]\nregulatory_profile = \"arib\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_receive_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [receive]
+            mode = "diversity"
+            diversity_window_ms = 8
+            agc_gain_map = "external9db"
+            ldpc_iteration_budget = 60
+            max_frame_rate = 200
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.receive.mode, ReceiveMode::Diversity);
+        assert_eq!(config.receive.diversity_window_ms, 8);
+        assert_eq!(config.receive.agc_gain_map, Some(AgcGainMap::Extranal9dB));
+        assert_eq!(config.receive.ldpc_iteration_budget, Some(60));
+        assert_eq!(config.receive.max_frame_rate, Some(200));
+
+        let config = CommdConfig::parse("").expect("valid config");
+        assert_eq!(config.receive.mode, ReceiveMode::Independent);
+        assert_eq!(config.receive.agc_gain_map, None);
+        assert_eq!(config.receive.ldpc_iteration_budget, None);
+        assert_eq!(config.receive.max_frame_rate, None);
+
+        assert!(CommdConfig::parse("[receive]\nmode = \"combined\"").is_err());
+        assert!(CommdConfig::parse("[receive]\nmax_frame_rate = 0").is_err());
+        assert!(CommdConfig::parse("[receive]\nagc_gain_map = \"external6db\"").is_err());
+        assert!(
+            CommdConfig::parse("[receive]\nmode = \"diversity\"\ndiversity_window_ms = 0").is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_power_control_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [beacon]
+            enabled = true
+
+            [power_control]
+            enabled = true
+            target_rssi = -80
+            "#,
+        )
+        .expect("valid config");
+
+        assert!(config.power_control.enabled);
+        assert_eq!(config.power_control.target_rssi, -80);
+        assert_eq!(config.power_control.hysteresis_db, 3);
+
+        assert!(CommdConfig::parse("[power_control]\nenabled = true").is_err());
+    }
+
+    #[test]
+    fn test_parse_tx_power_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [tx_power]
+            band_09 = 14
+            band_24 = 40
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.tx_power.band_09, Some(14));
+        assert_eq!(config.tx_power.limit(), TxPowerLimit::new(14, TX_POWER_MAX));
+    }
+
+    #[test]
+    fn test_parse_channel_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [channel]
+            auto_select = true
+            channel_count = 16
+            conflict = "reject"
+            "#,
+        )
+        .expect("valid config");
+
+        assert!(config.channel.auto_select);
+        assert_eq!(config.channel.channel_count, Some(16));
+        assert_eq!(config.channel.dwell_ms, 10);
+        assert_eq!(config.channel.conflict, ChannelConflict::Reject);
+
+        assert!(CommdConfig::parse("[channel]\nconflict = \"ignore\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_grpc_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [grpc]
+            keepalive_interval_ms = 0
+            stream_keepalive_ms = 15000
+            command_timeout_ms = 2500
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.grpc.keepalive_interval_ms, 0);
+        assert_eq!(config.grpc.keepalive_timeout_ms, 10_000);
+        assert_eq!(config.grpc.stream_keepalive_ms, Some(15_000));
+        assert_eq!(config.grpc.command_timeout_ms, 2500);
+
+        assert!(CommdConfig::parse("[grpc]\ncommand_timeout_ms = 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_qos_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [qos]
+            enabled = true
+            per_threshold = 25
+            adaptive_modulation_type = true
+            qpsk_crossover = "fair"
+            modulation_debounce_ms = 250
+            heartbeat_interval_ms = 2000
+            min_mcs = 2
+            max_mcs = 4
+            max_tx_power = 12
+            "#,
+        )
+        .expect("valid config");
+
+        assert!(config.qos.enabled);
+        assert_eq!(config.qos.per_threshold, 25);
+        assert!(config.qos.adaptive_modulation_type);
+        assert_eq!(config.qos.qpsk_crossover, ChannelQuality::Fair);
+        assert_eq!(config.qos.modulation_debounce_ms, 250);
+        assert_eq!(config.qos.heartbeat_interval_ms, Some(2000));
+
+        assert_eq!(
+            config.qos.modulation_bounds(),
+            ModulationBounds {
+                min_mcs: Some(OfdmMcs::QpskC1_2_2x),
+                max_mcs: Some(OfdmMcs::QpskC3_4),
+                max_tx_power: Some(12),
+            }
+        );
+
+        assert!(CommdConfig::parse("[qos]\nqpsk_crossover = \"awful\"").is_err());
+        assert!(CommdConfig::parse("[qos]\nmax_mcs = 7").is_err());
+        assert!(CommdConfig::parse("[qos]\nmin_mcs = 4\nmax_mcs = 3").is_err());
+        assert!(CommdConfig::parse("[qos]\nmax_tx_power = 32").is_err());
+    }
+
+    #[test]
+    fn test_parse_coding_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [coding]
+            whitening_seed = 0x1A5
+            payload_code = "rate2/3"
+            manual = true
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(
+            config.coding.link_coding(),
+            LinkCoding {
+                whitening_seed: 0x1A5,
+                payload_code: PayloadCode::Rate2_3,
+            }
+        );
+        assert!(config.coding.manual);
+
+        assert!(CommdConfig::parse("[coding]\nwhitening_seed = 512").is_err());
+        assert!(CommdConfig::parse("[coding]\npayload_code = \"rate1/3\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_empty_config() {
+        let config = CommdConfig::parse("").expect("valid config");
+
+        assert_eq!(config.worker.rt_priority, None);
+        assert!(config.worker.cpu_affinity.is_empty());
+        assert!(config.worker.bringup);
+        assert!(!config.beacon.enabled);
+        assert_eq!(config.beacon.channel, None);
+        assert_eq!(config.network.max_pending, 8);
+        assert_eq!(config.network.id_namespace(), PacketIdNamespace::NONE);
+        assert!(!config.transmit.auto_turnaround);
+        assert_eq!(config.transmit.retries, None);
+        assert_eq!(config.transmit.rx_restore_timeout_ms, None);
+        assert!(!config.transmit.raw_crc);
+        assert!(config.transmit.module_tx_enabled(0));
+        assert!(!config.transmit.hardware_fcs);
+        assert_eq!(config.tx_power.limit(), TxPowerLimit::default());
+        assert!(!config.channel.auto_select);
+        assert_eq!(config.channel.conflict, ChannelConflict::Warn);
+        assert_eq!(config.grpc.keepalive_interval_ms, 30_000);
+        assert_eq!(config.grpc.stream_keepalive_ms, None);
+        assert!(!config.qos.enabled);
+        assert!(!config.qos.adaptive_modulation_type);
+        assert_eq!(config.qos.qpsk_crossover, ChannelQuality::Poor);
+        assert_eq!(config.qos.modulation_debounce_ms, 1000);
+        assert_eq!(config.qos.heartbeat_interval_ms, None);
+        assert_eq!(config.coding.link_coding(), LinkCoding::default());
+        assert!(!config.coding.manual);
+        assert_eq!(config.spi.settings(), SpiSettings::default());
+        assert_eq!(config.address_filter.filter(), None);
+        assert_eq!(config.auto_ack.auto_ack(), None);
+        assert_eq!(config.capture.frames, 0);
+    }
+
+    #[test]
+    fn test_parse_spi_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [spi]
+            max_speed_hz = 8000000
+            mode = 0
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(
+            config.spi.settings(),
+            SpiSettings {
+                max_speed: Some(8_000_000),
+                mode: SpiMode::Mode0,
+            }
+        );
+
+        assert!(CommdConfig::parse("[spi]\nmax_speed_hz = 50000000").is_err());
+        assert!(CommdConfig::parse("[spi]\nmax_speed_hz = 0").is_err());
+        assert!(CommdConfig::parse("[spi]\nmode = 4").is_err());
+    }
+
+    #[test]
+    fn test_parse_auto_ack_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [address_filter]
+            enabled = true
+            pan_id = 0xCAFE
+            short_address = 0x0001
+            extended_address = 0x0011223344556677
+
+            [auto_ack]
+            enabled = true
+            ack_time_us = 192
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(
+            config.address_filter.filter(),
+            Some(AddressFilter {
+                pan_id: 0xCAFE,
+                short_address: 0x0001,
+                extended_address: 0x0011_2233_4455_6677,
+            })
+        );
+        assert_eq!(
+            config.auto_ack.auto_ack(),
+            Some(AutoAck {
+                frame_pending: false,
+                ack_time_us: Some(192),
+            })
+        );
+
+        assert!(CommdConfig::parse("[auto_ack]\nenabled = true").is_err());
+        assert!(
+            CommdConfig::parse(
+                "[address_filter]\nenabled = true\n[auto_ack]\nenabled = true\nack_time_us = 2048"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_capture_config() {
+        let config = CommdConfig::parse(
+            r#"
+            [capture]
+            frames = 16
+            failures_only = true
+            "#,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.capture.frames, 16);
+        assert!(config.capture.failures_only);
+    }
+}
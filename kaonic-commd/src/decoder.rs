@@ -0,0 +1,137 @@
+use kaonic_frame::frame::Frame;
+use kaonic_net::{
+    coder::{HEADER_LDPC_CODE, LdpcPacketCoder, LinkCoding, PacketCoder, PayloadCode},
+    packet::{Packet, PacketType},
+};
+
+use crate::grpc_server::kaonic::DecodedPacket;
+
+const NET_FRAME_SIZE: usize = 2048;
+
+/// Whether `data` has the length of a kaonic-net frame with `code`, a header
+/// codeword followed by whole payload codewords
+fn has_coded_layout(data: &[u8], code: PayloadCode) -> bool {
+    let header_len = HEADER_LDPC_CODE.n() / 8;
+    let block_len = code.ldpc().n() / 8;
+
+    data.len() >= header_len
+        && data.len() <= NET_FRAME_SIZE
+        && (data.len() - header_len).is_multiple_of(block_len)
+}
+
+/// Whether `data` has the length of a kaonic-net frame with any payload code
+///
+/// Independent of the coding in use, so both ends of a link agree on it.
+pub fn is_coded_frame(data: &[u8]) -> bool {
+    PayloadCode::ALL
+        .iter()
+        .any(|code| has_coded_layout(data, *code))
+}
+
+/// Scratch buffers for the kaonic-net decode path
+///
+/// One decoder is kept per receive stream and per adaptive module so frames
+/// are decoded without reallocating the coder and packet buffers.
+pub struct PacketDecoder {
+    coder: LdpcPacketCoder<NET_FRAME_SIZE>,
+    frame: Frame<NET_FRAME_SIZE>,
+    packet: Packet<NET_FRAME_SIZE>,
+}
+
+impl PacketDecoder {
+    /// Gives up on frames costing more than `iteration_budget` LDPC
+    /// iterations, they decode as invalid
+    pub fn new(iteration_budget: Option<usize>) -> Box<Self> {
+        let mut coder = LdpcPacketCoder::new();
+        if let Some(budget) = iteration_budget {
+            coder = coder.with_iteration_budget(budget);
+        }
+
+        Box::new(Self {
+            coder,
+            frame: Frame::new(),
+            packet: Packet::new(),
+        })
+    }
+
+    /// Decodes the following frames with `coding`
+    pub fn set_coding(&mut self, coding: LinkCoding) {
+        self.coder.set_coding(coding);
+    }
+
+    /// Runs the kaonic-net LDPC decode path on a raw frame.
+    ///
+    /// Returns `None` when the frame length doesn't match a coded packet layout
+    /// of the current payload code.
+    pub fn decode(&mut self, data: &[u8]) -> Option<DecodedPacket> {
+        if !has_coded_layout(data, self.coder.coding().payload_code) {
+            return None;
+        }
+
+        self.frame.copy_from_slice(data).ok()?;
+
+        let decoded = match self.coder.decode(&self.frame, &mut self.packet) {
+            Ok(()) => DecodedPacket {
+                valid: self.packet.validate(),
+                packet_id: self.packet.header().id(),
+                payload_len: self.packet.frame().len() as u32,
+                corrected_bits: self.coder.corrected_bits() as u32,
+                payload: self.packet.frame().as_slice().to_vec(),
+            },
+            Err(_) => DecodedPacket::default(),
+        };
+
+        Some(decoded)
+    }
+
+    /// Type of the packet decoded last, only meaningful if it was valid
+    pub fn packet_type(&self) -> PacketType {
+        self.packet.header().packet_type()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_packet_coded_frame() {
+        let test_data = "@@ TEST PACKET DATA @@";
+        let mut packet = Packet::<NET_FRAME_SIZE>::new();
+        let mut frame = Frame::<NET_FRAME_SIZE>::new();
+        let mut coder = LdpcPacketCoder::<NET_FRAME_SIZE>::new();
+
+        packet.header_mut().set_id(0xCAFE);
+        packet
+            .frame_mut()
+            .push_data(test_data.as_bytes())
+            .expect("packet with data");
+        packet.build();
+
+        coder.encode(&packet, &mut frame).expect("encoded frame");
+
+        let mut decoder = PacketDecoder::new(None);
+        let decoded = decoder.decode(frame.as_slice()).expect("decoded fields");
+
+        assert!(decoded.valid);
+        assert_eq!(decoded.packet_id, 0xCAFE);
+        assert_eq!(decoded.payload_len as usize, test_data.len());
+        assert_eq!(decoded.corrected_bits, 0);
+        assert_eq!(&decoded.payload[..], test_data.as_bytes());
+
+        // A flipped bit in the header and one in the payload are corrected
+        let mut noisy = frame.as_slice().to_vec();
+        noisy[1] ^= 0x10;
+        noisy[HEADER_LDPC_CODE.n() / 8 + 3] ^= 0x01;
+
+        let decoded = decoder.decode(&noisy).expect("decoded fields");
+        assert!(decoded.valid);
+        assert_eq!(decoded.corrected_bits, 2);
+        assert_eq!(&decoded.payload[..], test_data.as_bytes());
+    }
+
+    #[test]
+    fn test_decode_packet_raw_frame() {
+        assert!(PacketDecoder::new(None).decode(b"raw frame").is_none());
+    }
+}
@@ -0,0 +1,231 @@
+//! Receive diversity over two modules tuned to the same channel.
+//!
+//! Both modules receive every transmission, each copy is handed to the
+//! combiner instead of the event bus. A copy of the same frame from the other
+//! module arriving within the diversity window is taken as the same
+//! transmission, the better of the two is published and the other one
+//! suppressed. A copy without a partner is published once the window has
+//! passed.
+
+use std::{collections::VecDeque, time::Duration};
+
+use tokio::{sync::mpsc, task::JoinHandle, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    events::{EventBus, RadioEvent},
+    radio_server::SharedReceiveModule,
+};
+
+/// Frames the receive loops may queue ahead of the combiner
+pub const DIVERSITY_QUEUE: usize = 16;
+
+/// Prefers a copy passing the raw-frame CRC check, then the stronger one
+fn better(a: SharedReceiveModule, b: SharedReceiveModule) -> SharedReceiveModule {
+    let rank = |rx: &SharedReceiveModule| (rx.crc_valid != Some(false), rx.receive.rssi);
+
+    if rank(&b) > rank(&a) { b } else { a }
+}
+
+/// Copies of one transmission come from both modules with the same payload,
+/// a copy failing the raw-frame CRC check only has to match in length
+fn same_transmission(a: &SharedReceiveModule, b: &SharedReceiveModule) -> bool {
+    if a.receive.module == b.receive.module {
+        return false;
+    }
+
+    let (a_frame, b_frame) = (&a.receive.frame, &b.receive.frame);
+    if a.crc_valid == Some(false) || b.crc_valid == Some(false) {
+        a_frame.len == b_frame.len
+    } else {
+        a_frame.as_slice() == b_frame.as_slice()
+    }
+}
+
+/// Publishes one copy of each frame received on `frames` to `events`
+pub fn spawn_diversity_combiner(
+    mut frames: mpsc::Receiver<SharedReceiveModule>,
+    events: EventBus,
+    window: Duration,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let publish = |rx| events.publish(RadioEvent::Receive(rx));
+
+        // First copies still waiting for their partner and when their
+        // window closes, oldest first
+        let mut pending: VecDeque<(SharedReceiveModule, Instant)> = VecDeque::new();
+
+        loop {
+            let deadline = pending.front().map(|(_, deadline)| *deadline);
+
+            tokio::select! {
+                rx = frames.recv() => {
+                    let Some(rx) = rx else {
+                        break;
+                    };
+
+                    let partner = pending
+                        .iter()
+                        .position(|(first, _)| same_transmission(first, &rx));
+                    if let Some((first, _)) = partner.and_then(|index| pending.remove(index)) {
+                        publish(better(first, rx));
+                        continue;
+                    }
+
+                    if pending.len() >= DIVERSITY_QUEUE
+                        && let Some((first, _)) = pending.pop_front()
+                    {
+                        publish(first);
+                    }
+                    pending.push_back((rx, Instant::now() + window));
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() =>
+                {
+                    if let Some((first, _)) = pending.pop_front() {
+                        publish(first);
+                    }
+                }
+                _ = cancel.cancelled() => break,
+            }
+        }
+
+        for (first, _) in pending {
+            publish(first);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use kaonic_ctrl::protocol::{RadioFrame, ReceiveModule};
+    use kaonic_qos::ChannelQuality;
+    use radio_common::modulation::Modulation;
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::radio_server::ReceivedFrame;
+
+    const WINDOW: Duration = Duration::from_millis(20);
+
+    fn received(module: usize, rssi: i8, payload: &[u8]) -> SharedReceiveModule {
+        let mut frame = RadioFrame::new();
+        frame.data[..payload.len()].copy_from_slice(payload);
+        frame.len = payload.len() as u16;
+
+        Arc::new(ReceivedFrame {
+            receive: ReceiveModule {
+                module,
+                frame,
+                rssi,
+            },
+            crc_valid: None,
+            modulation: Modulation::Off,
+            quality: ChannelQuality::Good,
+        })
+    }
+
+    async fn next_receive(events: &mut broadcast::Receiver<RadioEvent>) -> SharedReceiveModule {
+        match tokio::time::timeout(Duration::from_secs(1), events.recv()).await {
+            Ok(Ok(RadioEvent::Receive(rx))) => rx,
+            other => panic!("expected a received frame, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_diversity_selects_stronger_copy() {
+        let bus = EventBus::new(16);
+        let mut events = bus.subscribe();
+        let cancel = CancellationToken::new();
+        let (frames, combiner_frames) = mpsc::channel(DIVERSITY_QUEUE);
+        let combiner =
+            spawn_diversity_combiner(combiner_frames, bus.clone(), WINDOW, cancel.clone());
+
+        // Same frame on both modules, the second module hears it better
+        frames.send(received(0, -80, b"frame")).await.unwrap();
+        frames.send(received(1, -62, b"frame")).await.unwrap();
+
+        let rx = next_receive(&mut events).await;
+        assert_eq!(rx.receive.module, 1);
+        assert_eq!(rx.receive.rssi, -62);
+
+        // A frame only one module heard is published after the window
+        frames.send(received(0, -70, b"single")).await.unwrap();
+        let rx = next_receive(&mut events).await;
+        assert_eq!(rx.receive.module, 0);
+        assert_eq!(rx.receive.frame.as_slice(), b"single");
+
+        // A copy failing the CRC check loses against a weaker valid one
+        let mut corrupted = *received(0, -50, b"frxme");
+        corrupted.crc_valid = Some(false);
+        frames.send(Arc::new(corrupted)).await.unwrap();
+        let mut valid = *received(1, -75, b"frame");
+        valid.crc_valid = Some(true);
+        frames.send(Arc::new(valid)).await.unwrap();
+
+        let rx = next_receive(&mut events).await;
+        assert_eq!(rx.receive.module, 1);
+        assert_eq!(rx.crc_valid, Some(true));
+
+        // The duplicates never made it to the bus
+        tokio::time::sleep(WINDOW * 2).await;
+        assert!(matches!(
+            events.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+
+        cancel.cancel();
+        combiner.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_diversity_keeps_different_frames() {
+        let bus = EventBus::new(16);
+        let mut events = bus.subscribe();
+        let cancel = CancellationToken::new();
+        let (frames, combiner_frames) = mpsc::channel(DIVERSITY_QUEUE);
+        let combiner =
+            spawn_diversity_combiner(combiner_frames, bus.clone(), WINDOW, cancel.clone());
+
+        // Two transmissions within the window, each heard by a single module
+        frames.send(received(0, -70, b"alpha")).await.unwrap();
+        frames.send(received(1, -60, b"bravo")).await.unwrap();
+
+        let mut payloads = Vec::new();
+        for _ in 0..2 {
+            let rx = next_receive(&mut events).await;
+            payloads.push(rx.receive.frame.as_slice().to_vec());
+        }
+        payloads.sort();
+        assert_eq!(payloads, [b"alpha".to_vec(), b"bravo".to_vec()]);
+
+        // Interleaved copies of two transmissions pair up by payload
+        frames.send(received(0, -70, b"first")).await.unwrap();
+        frames.send(received(0, -71, b"second")).await.unwrap();
+        frames.send(received(1, -60, b"first")).await.unwrap();
+        frames.send(received(1, -80, b"second")).await.unwrap();
+
+        let rx = next_receive(&mut events).await;
+        assert_eq!(
+            (rx.receive.module, rx.receive.frame.as_slice()),
+            (1, &b"first"[..])
+        );
+        let rx = next_receive(&mut events).await;
+        assert_eq!(
+            (rx.receive.module, rx.receive.frame.as_slice()),
+            (0, &b"second"[..])
+        );
+
+        tokio::time::sleep(WINDOW * 2).await;
+        assert!(matches!(
+            events.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+
+        cancel.cancel();
+        combiner.await.unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+//! Radio activity shared with every part of the daemon that observes it.
+//!
+//! Received frames, adaptive modulation switches and peers coming and going
+//! are all published on one [`EventBus`]. Observers subscribe and pick the
+//! events they care about, nothing has to be threaded through the radio
+//! workers for a new one.
+//!
+//! Finished transmissions go to a [`TransmitBus`] of their own, so a burst of
+//! them can't make the receive observers lag and miss frames.
+
+use std::sync::Arc;
+
+use radio_common::modulation::Modulation;
+use tokio::{sync::broadcast, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    beacon::NodeId,
+    radio_server::{SharedReceiveModule, TransmitEvent},
+};
+
+/// Something that happened on one of the radio modules
+#[derive(Debug, Clone)]
+pub enum RadioEvent {
+    /// Frame received by a module
+    Receive(SharedReceiveModule),
+    /// Adaptive modulation switched the module to `modulation`
+    ModulationChanged {
+        module: usize,
+        modulation: Modulation,
+    },
+    /// First beacon of a peer, or the first one since it timed out
+    PeerUp {
+        module: usize,
+        node_id: NodeId,
+        rssi: i8,
+    },
+    /// Peer not heard for longer than the peer timeout
+    PeerDown { node_id: NodeId },
+}
+
+/// Broadcast bus of [`RadioEvent`]s
+///
+/// Slow subscribers miss events, see [`broadcast::error::RecvError::Lagged`],
+/// publishers never wait for them.
+#[derive(Debug, Clone)]
+pub struct EventBus<E = RadioEvent> {
+    sender: broadcast::Sender<E>,
+}
+
+/// Frames the radios are done with, sent, given up on or refused
+pub type TransmitBus = EventBus<Arc<TransmitEvent>>;
+
+impl<E: Clone> EventBus<E> {
+    /// Each subscriber may fall up to `capacity` events behind
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Hands `event` to every current subscriber, dropped if there are none
+    pub fn publish(&self, event: E) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Receives every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<E> {
+        self.sender.subscribe()
+    }
+}
+
+/// Starts logging the peer and modulation changes published on `events`
+/// until `cancel`
+pub fn spawn_activity_log(events: &EventBus, cancel: CancellationToken) -> JoinHandle<()> {
+    let mut events = events.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = cancel.cancelled() => break,
+            };
+
+            match event {
+                Ok(event) => log_activity(&event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::debug!("activity log lagged by {skipped} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+fn log_activity(event: &RadioEvent) {
+    match event {
+        RadioEvent::Receive(_) => {}
+        RadioEvent::ModulationChanged { module, modulation } => {
+            log::info!("radio[{module}] qos modulation: {modulation:?}");
+        }
+        RadioEvent::PeerUp {
+            module,
+            node_id,
+            rssi,
+        } => {
+            log::info!("discovered peer {node_id:0>8X} on radio[{module}] ({rssi} dBm)");
+        }
+        RadioEvent::PeerDown { node_id } => log::info!("peer {node_id:0>8X} timed out"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_every_subscriber_receives_each_event() {
+        let bus = EventBus::new(8);
+        let mut observers = [bus.subscribe(), bus.subscribe(), bus.subscribe()];
+
+        bus.publish(RadioEvent::PeerUp {
+            module: 1,
+            node_id: 0x1234,
+            rssi: -60,
+        });
+        bus.publish(RadioEvent::ModulationChanged {
+            module: 0,
+            modulation: Modulation::Fsk,
+        });
+        bus.publish(RadioEvent::PeerDown { node_id: 0x1234 });
+
+        for observer in &mut observers {
+            assert!(matches!(
+                observer.recv().await,
+                Ok(RadioEvent::PeerUp {
+                    module: 1,
+                    node_id: 0x1234,
+                    rssi: -60
+                })
+            ));
+            assert!(matches!(
+                observer.recv().await,
+                Ok(RadioEvent::ModulationChanged {
+                    module: 0,
+                    modulation: Modulation::Fsk
+                })
+            ));
+            assert!(matches!(
+                observer.recv().await,
+                Ok(RadioEvent::PeerDown { node_id: 0x1234 })
+            ));
+            assert!(observer.try_recv().is_err());
+        }
+    }
+}
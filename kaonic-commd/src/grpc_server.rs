@@ -1,20 +1,53 @@
-use std::time::Instant;
+use std::{
+    ops::RangeInclusive,
+    sync::{
+        Arc, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use kaonic_ctrl::protocol::{ReceiveModule, TransmitModule};
-use kaonic_radio::{platform::PlatformRadioFrame, radio::Radio};
+use kaonic_ctrl::protocol::GetCapabilitiesResponse;
+use kaonic_net::coder::{LinkCoding, PayloadCode};
+use kaonic_qos::ChannelQuality;
+use kaonic_radio::{
+    error::{BringupStep, KaonicError},
+    fem::{FemPath, FemPins},
+    frequency_plan::{FREQUENCY_PLANS, FrequencyPlan},
+    platform::{PlatformRadio, PlatformRadioFrame},
+    power::TxPowerLimit,
+    radio::{IrqCounters, Radio, ResetKind, TransmitReport},
+    regulatory::LbtParams,
+};
 use radio_common::{
-    RadioConfig,
+    RadioChannel, RadioConfig,
     frequency::{BandwidthFilter, Hertz},
     modulation::{
         Modulation, OfdmBandwidthOption, OfdmMcs, OfdmModulation, QpskChipFrequency,
         QpskModulation, QpskRateMode,
     },
 };
-use tokio::sync::broadcast;
+use radio_rf215::{
+    radio::Band,
+    transceiver::{Band09, Band24},
+};
+use tokio::sync::{broadcast, oneshot, watch};
 use tokio_stream::wrappers::ReceiverStream;
-use tonic::{Request, Response, Status};
+use tokio_util::sync::CancellationToken;
+use tonic::{Code, Request, Response, Status};
 
-use crate::radio_server::{SharedModuleStats, SharedRadio};
+use crate::{
+    beacon::{BeaconModulation, Peer},
+    capture::SharedReceiveCapture,
+    channel,
+    config::{ChannelConfig, ChannelConflict, CommdConfig, QosConfig, ReceiveMode},
+    decoder::PacketDecoder,
+    events::{EventBus, RadioEvent, TransmitBus},
+    qos::{LinkQos, SharedLinkQos},
+    radio_server::{BringupStatus, SharedModuleStats, SharedPeerTable, SharedRadio, TransmitEvent},
+    raw_crc::{FrameIntegrity, append_raw_crc},
+    tx_queue::{TransmitJob, TransmitOutcome, TransmitQueue},
+};
 
 pub mod kaonic {
     tonic::include_proto!("kaonic");
@@ -24,13 +57,65 @@ pub use kaonic::device_server::DeviceServer;
 pub use kaonic::radio_server::RadioServer as GrpcRadioServer;
 
 use kaonic::{
-    Empty, InfoResponse, ModuleRequest, RadioConfig as ProtoRadioConfig, RadioFrame as ProtoFrame,
-    RadioModulation, RadioModulationFsk, RadioModulationOfdm, RadioModulationQpsk, ReceiveRequest,
-    ReceiveResponse, StatisticsResponse, TransmitEventRequest, TransmitEventResponse,
-    TransmitRequest, TransmitResponse, device_server::Device,
+    ApplyConfigRequest, ApplyConfigResponse, Bringup as ProtoBringup, CapabilitiesResponse,
+    CapturedFrame as ProtoCapturedFrame, ChannelQuality as ProtoChannelQuality, Empty,
+    FemPins as ProtoFemPins, FrameIntegrity as ProtoFrameIntegrity,
+    FrequencyPlan as ProtoFrequencyPlan, FrequencyPlansResponse, InfoResponse,
+    IrqCounters as ProtoIrqCounters, ListPeersResponse, ModuleRequest,
+    PayloadCode as ProtoPayloadCode, Peer as ProtoPeer, PeerModulation, PhaseMeasurementResponse,
+    QosSettings, RadioConfig as ProtoRadioConfig, RadioFrame as ProtoFrame, RadioModulation,
+    RadioModulationFsk, RadioModulationOfdm, RadioModulationQpsk, ReceiveCaptureResponse,
+    ReceiveRequest, ReceiveResponse, ResetModuleRequest, ResetModuleResponse, SelectChannelRequest,
+    SelectChannelResponse, SetModulationResponse, StatisticsResponse, TransmitBatchRequest,
+    TransmitBatchResponse, TransmitEventRequest, TransmitEventResponse, TransmitRequest,
+    TransmitResponse, TransmitResult, device_server::Device,
     radio_modulation::Modulation as ProtoModulation, radio_server::Radio as RadioTrait,
 };
 
+//***********************************************************************************************//
+// Helpers — request errors
+//***********************************************************************************************//
+
+/// Error of a request helper, `?` turns it into the [`Status`] of the call
+///
+/// A fraction of the size of a [`Status`], which would bloat every helper result.
+#[derive(Debug)]
+struct RequestError {
+    code: Code,
+    message: String,
+}
+
+impl RequestError {
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(Code::InvalidArgument, message)
+    }
+
+    fn failed_precondition(message: impl Into<String>) -> Self {
+        Self::new(Code::FailedPrecondition, message)
+    }
+
+    fn unimplemented(message: impl Into<String>) -> Self {
+        Self::new(Code::Unimplemented, message)
+    }
+
+    fn internal(message: impl Into<String>) -> Self {
+        Self::new(Code::Internal, message)
+    }
+
+    fn new(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<RequestError> for Status {
+    fn from(e: RequestError) -> Self {
+        Status::new(e.code, e.message)
+    }
+}
+
 //***********************************************************************************************//
 // Helpers — RadioFrame
 //***********************************************************************************************//
@@ -45,6 +130,36 @@ fn bytes_to_frame(data: &[u8]) -> ProtoFrame {
     }
 }
 
+//***********************************************************************************************//
+// Helpers — receive filter
+//***********************************************************************************************//
+
+/// Per-stream selection of the frames a `ReceiveStream` client wants
+struct ReceiveFilter {
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+}
+
+impl ReceiveFilter {
+    fn from_request(req: &ReceiveRequest) -> Result<Self, String> {
+        if let (Some(min_len), Some(max_len)) = (req.min_len, req.max_len)
+            && min_len > max_len
+        {
+            return Err(format!("min_len {} exceeds max_len {}", min_len, max_len));
+        }
+
+        Ok(Self {
+            min_len: req.min_len.map(|len| len as usize),
+            max_len: req.max_len.map(|len| len as usize),
+        })
+    }
+
+    fn accepts(&self, frame: &[u8]) -> bool {
+        self.min_len.is_none_or(|min_len| frame.len() >= min_len)
+            && self.max_len.is_none_or(|max_len| frame.len() <= max_len)
+    }
+}
+
 //***********************************************************************************************//
 // Helpers — enum conversions (proto ↔ radio-common)
 //***********************************************************************************************//
@@ -140,6 +255,7 @@ fn modulation_to_proto(module: i32, modulation: &Modulation) -> RadioModulation
             opt: ofdm_opt_to_u32(&o.opt),
             pdt: o.pdt as u32,
             tx_power: o.tx_power as u32,
+            lfo: o.lfo,
         })),
         Modulation::Qpsk(q) => Some(ProtoModulation::Qpsk(RadioModulationQpsk {
             chip_freq: qpsk_fchip_to_u32(&q.fchip),
@@ -162,6 +278,7 @@ fn modulation_from_proto(req: &RadioModulation) -> Modulation {
             opt: ofdm_opt_from_u32(o.opt),
             pdt: o.pdt as u8,
             tx_power: o.tx_power as u8,
+            lfo: o.lfo,
         }),
         Some(ProtoModulation::Qpsk(q)) => Modulation::Qpsk(QpskModulation {
             fchip: qpsk_fchip_from_u32(q.chip_freq),
@@ -173,7 +290,12 @@ fn modulation_from_proto(req: &RadioModulation) -> Modulation {
     }
 }
 
-fn config_to_proto(module: i32, cfg: &RadioConfig) -> ProtoRadioConfig {
+fn config_to_proto(
+    module: i32,
+    cfg: &RadioConfig,
+    fem: FemPath,
+    auto_spacing: bool,
+) -> ProtoRadioConfig {
     ProtoRadioConfig {
         module,
         freq: cfg.freq.as_hz(),
@@ -183,18 +305,286 @@ fn config_to_proto(module: i32, cfg: &RadioConfig) -> ProtoRadioConfig {
             BandwidthFilter::Wide => 1,
             BandwidthFilter::Narrow => 0,
         },
+        fem: match fem {
+            FemPath::Auto => None,
+            FemPath::Manual(pins) => Some(ProtoFemPins {
+                flt_v1: pins.flt_v1,
+                flt_v2: pins.flt_v2,
+                flt_24: pins.flt_24,
+                ant_24: pins.ant_24,
+            }),
+        },
+        auto_spacing,
+        fem_auto: fem == FemPath::Auto,
+    }
+}
+
+/// Front end path of a config request, `None` keeps the current one
+fn fem_path_from_proto(req: &ProtoRadioConfig) -> Option<FemPath> {
+    if req.fem_auto {
+        return Some(FemPath::Auto);
+    }
+
+    req.fem.as_ref().map(|pins| {
+        FemPath::Manual(FemPins {
+            flt_v1: pins.flt_v1,
+            flt_v2: pins.flt_v2,
+            flt_24: pins.flt_24,
+            ant_24: pins.ant_24,
+        })
+    })
+}
+
+/// Sets the front end path of a config request, a radio without a
+/// switchable front end only takes the automatic one
+fn set_fem_path(radio: &mut PlatformRadio, path: Option<FemPath>) -> Result<(), RequestError> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+
+    match radio.set_fem_path(path) {
+        Ok(()) => Ok(()),
+        Err(KaonicError::NotSupported) if path == FemPath::Auto => Ok(()),
+        Err(KaonicError::NotSupported) => Err(RequestError::unimplemented(format!(
+            "front end can't be set to {:?}",
+            path
+        ))),
+        Err(e) => Err(RequestError::internal(format!("set_fem_path: {:?}", e))),
     }
 }
 
-fn config_from_proto(req: &ProtoRadioConfig) -> RadioConfig {
-    RadioConfig {
+/// Range of the RF215 band covering `freq`, if there is one
+fn rf215_band(freq: Hertz) -> Option<RangeInclusive<Hertz>> {
+    [
+        Band09::MIN_FREQUENCY..=Band09::MAX_FREQUENCY,
+        Band24::MIN_FREQUENCY..=Band24::MAX_FREQUENCY,
+    ]
+    .into_iter()
+    .find(|band| band.contains(&freq))
+}
+
+/// Sets the channel spacing of an `auto_spacing` request from `modulation`,
+/// warns about a set spacing narrower than the modulation occupies
+fn resolve_channel_spacing(
+    req: &mut ProtoRadioConfig,
+    modulation: &Modulation,
+) -> Result<(), RequestError> {
+    if req.auto_spacing {
+        let spacing = modulation.recommended_channel_spacing().ok_or_else(|| {
+            RequestError::failed_precondition(format!(
+                "no channel spacing for the modulation of module {}",
+                req.module
+            ))
+        })?;
+        req.channel_spacing = spacing.as_hz();
+    } else if let Some(bandwidth) = modulation.occupied_bandwidth()
+        && req.channel_spacing < bandwidth.as_hz()
+    {
+        log::warn!(
+            "module {} channel spacing {} Hz is narrower than the {} Hz its modulation occupies",
+            req.module,
+            req.channel_spacing,
+            bandwidth.as_hz()
+        );
+    }
+
+    Ok(())
+}
+
+fn config_from_proto(req: &ProtoRadioConfig) -> Result<RadioConfig, RequestError> {
+    let channel = RadioChannel::try_from(req.channel).map_err(|_| {
+        RequestError::invalid_argument(format!("channel {} out of range", req.channel))
+    })?;
+
+    let band = rf215_band(Hertz::new(req.freq)).ok_or_else(|| {
+        RequestError::invalid_argument(format!("{} Hz is outside the RF215 bands", req.freq))
+    })?;
+
+    // The radio tunes to freq + channel * channel_spacing, which has to stay
+    // in the band of freq
+    let channel_freq = req
+        .channel_spacing
+        .checked_mul(channel as u64)
+        .and_then(|offset| req.freq.checked_add(offset));
+    if !channel_freq.is_some_and(|freq| band.contains(&Hertz::new(freq))) {
+        return Err(RequestError::invalid_argument(format!(
+            "channel {} with {} Hz spacing leaves the band of {} Hz",
+            channel, req.channel_spacing, req.freq
+        )));
+    }
+
+    Ok(RadioConfig {
         freq: Hertz::new(req.freq),
         channel_spacing: Hertz::new(req.channel_spacing),
-        channel: req.channel as u16,
+        channel,
         bandwidth_filter: match req.bandwidth_filter {
             1 => BandwidthFilter::Wide,
             _ => BandwidthFilter::Narrow,
         },
+    })
+}
+
+fn peer_to_proto(peer: &Peer, now: Instant) -> ProtoPeer {
+    ProtoPeer {
+        node_id: peer.beacon.node_id,
+        module: peer.module as i32,
+        capabilities: peer.beacon.capabilities as u32,
+        modulation: match peer.beacon.modulation {
+            BeaconModulation::Off => PeerModulation::Off,
+            BeaconModulation::Ofdm => PeerModulation::Ofdm,
+            BeaconModulation::Qpsk => PeerModulation::Qpsk,
+            BeaconModulation::Fsk => PeerModulation::Fsk,
+        } as i32,
+        tx_power: peer.beacon.tx_power as u32,
+        rssi: peer.rssi as i32,
+        last_seen_ms: now.saturating_duration_since(peer.last_seen).as_millis() as u64,
+        whitening_seed: peer.beacon.coding.whitening_seed as u32,
+        payload_code: match peer.beacon.coding.payload_code {
+            PayloadCode::Rate1_2 => ProtoPayloadCode::Rate12,
+            PayloadCode::Rate2_3 => ProtoPayloadCode::Rate23,
+            PayloadCode::Rate4_5 => ProtoPayloadCode::Rate45,
+        } as i32,
+    }
+}
+
+fn frequency_plan_to_proto(plan: &FrequencyPlan) -> ProtoFrequencyPlan {
+    ProtoFrequencyPlan {
+        name: plan.name.to_string(),
+        band_start: plan.band_start.as_hz(),
+        band_end: plan.band_end.as_hz(),
+        freq: plan.freq.as_hz(),
+        channel_spacing: plan.channel_spacing.as_hz(),
+        channel_count: plan.channel_count as u32,
+    }
+}
+
+fn transmit_result_to_proto(result: kaonic_radio::radio::TransmitResult) -> TransmitResult {
+    match result {
+        kaonic_radio::radio::TransmitResult::Sent => TransmitResult::Sent,
+        kaonic_radio::radio::TransmitResult::ChannelBusy => TransmitResult::ChannelBusy,
+        kaonic_radio::radio::TransmitResult::MaxRetries => TransmitResult::MaxRetries,
+    }
+}
+
+fn transmit_report_to_proto(
+    report: &TransmitReport,
+    tx_power: u8,
+    latency: u32,
+) -> TransmitResponse {
+    TransmitResponse {
+        latency,
+        attempts: report.attempts as u32,
+        result: transmit_result_to_proto(report.result) as i32,
+        tx_power: tx_power as u32,
+    }
+}
+
+fn transmit_event_to_proto(module: i32, event: &TransmitEvent) -> TransmitEventResponse {
+    let (result, attempts, error) = match event.error {
+        Some(e) => (TransmitResult::Error, 0, format!("{e:?}")),
+        None => (
+            transmit_result_to_proto(event.report.result),
+            event.report.attempts as u32,
+            String::new(),
+        ),
+    };
+
+    TransmitEventResponse {
+        module,
+        frame: Some(bytes_to_frame(event.frame.as_slice())),
+        latency: event.latency.as_micros() as u32,
+        seq: event.seq,
+        result: result as i32,
+        attempts,
+        air_time: event.air_time.as_micros() as u32,
+        error,
+    }
+}
+
+fn channel_quality_to_proto(quality: ChannelQuality) -> ProtoChannelQuality {
+    match quality {
+        ChannelQuality::Excellent => ProtoChannelQuality::Excellent,
+        ChannelQuality::Good => ProtoChannelQuality::Good,
+        ChannelQuality::Fair => ProtoChannelQuality::Fair,
+        ChannelQuality::Poor => ProtoChannelQuality::Poor,
+        ChannelQuality::Bad => ProtoChannelQuality::Bad,
+    }
+}
+
+fn channel_quality_from_proto(quality: ProtoChannelQuality) -> ChannelQuality {
+    match quality {
+        ProtoChannelQuality::Excellent => ChannelQuality::Excellent,
+        ProtoChannelQuality::Good => ChannelQuality::Good,
+        ProtoChannelQuality::Fair => ChannelQuality::Fair,
+        ProtoChannelQuality::Poor => ChannelQuality::Poor,
+        ProtoChannelQuality::Bad => ChannelQuality::Bad,
+    }
+}
+
+/// QoS config with the settings a client can change replaced by `settings`
+fn qos_config_from_proto(
+    settings: &QosSettings,
+    current: &QosConfig,
+) -> Result<QosConfig, RequestError> {
+    let qpsk_crossover = ProtoChannelQuality::try_from(settings.qpsk_crossover).map_err(|_| {
+        RequestError::invalid_argument(format!(
+            "unknown qpsk_crossover {}",
+            settings.qpsk_crossover
+        ))
+    })?;
+
+    Ok(QosConfig {
+        enabled: settings.enabled,
+        per_threshold: settings.per_threshold,
+        adaptive_modulation_type: settings.adaptive_modulation_type,
+        qpsk_crossover: channel_quality_from_proto(qpsk_crossover),
+        ..current.clone()
+    })
+}
+
+fn integrity_to_proto(integrity: FrameIntegrity) -> ProtoFrameIntegrity {
+    match integrity {
+        FrameIntegrity::None => ProtoFrameIntegrity::None,
+        FrameIntegrity::SoftwareCrc => ProtoFrameIntegrity::SoftwareCrc,
+        FrameIntegrity::HardwareFcs => ProtoFrameIntegrity::HardwareFcs,
+    }
+}
+
+fn bringup_to_proto(bringup: BringupStatus) -> ProtoBringup {
+    match bringup {
+        BringupStatus::Skipped => ProtoBringup::Skipped,
+        BringupStatus::Done => ProtoBringup::Done,
+        BringupStatus::Failed(step) => match step {
+            BringupStep::Reset => ProtoBringup::ResetFailed,
+            BringupStep::Wakeup => ProtoBringup::NoWakeup,
+            BringupStep::TrxOff => ProtoBringup::NotTrxOff,
+            BringupStep::Configure => ProtoBringup::ConfigureFailed,
+            BringupStep::Receive => ProtoBringup::ReceiveFailed,
+        },
+    }
+}
+
+fn irq_counters_to_proto(irqs: IrqCounters) -> ProtoIrqCounters {
+    ProtoIrqCounters {
+        wakeup: irqs.wakeup,
+        transceiver_ready: irqs.transceiver_ready,
+        energy_detection: irqs.energy_detection,
+        battery_low: irqs.battery_low,
+        transceiver_error: irqs.transceiver_error,
+        iq_if_sync_fail: irqs.iq_if_sync_fail,
+    }
+}
+
+fn qos_to_proto(module: i32, qos: &LinkQos) -> QosSettings {
+    let config = qos.config();
+
+    QosSettings {
+        module,
+        enabled: config.enabled,
+        per_threshold: config.per_threshold,
+        adaptive_modulation_type: config.adaptive_modulation_type,
+        qpsk_crossover: channel_quality_to_proto(config.qpsk_crossover) as i32,
+        quality: channel_quality_to_proto(qos.quality()) as i32,
     }
 }
 
@@ -208,6 +598,9 @@ pub struct DeviceService {
     mtu: u32,
     version: &'static str,
     stats: Vec<SharedModuleStats>,
+    peers: SharedPeerTable,
+    capabilities: GetCapabilitiesResponse,
+    capture: Vec<SharedReceiveCapture>,
 }
 
 impl DeviceService {
@@ -216,6 +609,8 @@ impl DeviceService {
         serial: String,
         mtu: u32,
         stats: Vec<SharedModuleStats>,
+        peers: SharedPeerTable,
+        capabilities: GetCapabilitiesResponse,
     ) -> Self {
         Self {
             module_count,
@@ -223,8 +618,17 @@ impl DeviceService {
             mtu,
             version: env!("CARGO_PKG_VERSION"),
             stats,
+            peers,
+            capabilities,
+            capture: Vec::new(),
         }
     }
+
+    /// Serves the received frames each module keeps, see `[capture]`
+    pub fn with_capture(mut self, capture: Vec<SharedReceiveCapture>) -> Self {
+        self.capture = capture;
+        self
+    }
 }
 
 #[tonic::async_trait]
@@ -258,36 +662,251 @@ impl Device for DeviceService {
             tx_bytes: s.tx_bytes.load(Ordering::Relaxed),
             rx_errors: s.rx_errors.load(Ordering::Relaxed),
             tx_errors: s.tx_errors.load(Ordering::Relaxed),
+            temperature: *s.temperature.lock().unwrap(),
+            battery_low: s.battery_low.load(Ordering::Relaxed),
+            auto_turnaround: s.auto_turnaround.load(Ordering::Relaxed),
+            rx_overruns: s.rx_overruns.load(Ordering::Relaxed),
+            integrity: integrity_to_proto(*s.integrity.lock().unwrap()) as i32,
+            bringup: bringup_to_proto(*s.bringup.lock().unwrap()) as i32,
+            irqs: s.irq_counters.lock().unwrap().map(irq_counters_to_proto),
+            rx_rate_dropped: s.rx_rate_dropped.load(Ordering::Relaxed),
+        }))
+    }
+
+    async fn list_peers(&self, _: Request<Empty>) -> Result<Response<ListPeersResponse>, Status> {
+        let now = Instant::now();
+        let peers = self.peers.lock().unwrap().peers(now);
+
+        Ok(Response::new(ListPeersResponse {
+            peers: peers.iter().map(|peer| peer_to_proto(peer, now)).collect(),
+        }))
+    }
+
+    async fn get_frequency_plans(
+        &self,
+        _: Request<Empty>,
+    ) -> Result<Response<FrequencyPlansResponse>, Status> {
+        Ok(Response::new(FrequencyPlansResponse {
+            plans: FREQUENCY_PLANS
+                .iter()
+                .map(frequency_plan_to_proto)
+                .collect(),
+        }))
+    }
+
+    async fn get_capabilities(
+        &self,
+        _: Request<Empty>,
+    ) -> Result<Response<CapabilitiesResponse>, Status> {
+        Ok(Response::new(CapabilitiesResponse {
+            api_version: self.capabilities.api_version,
+            capabilities: self.capabilities.capabilities,
+            part_number: self.capabilities.part_number.clone().unwrap_or_default(),
         }))
     }
+
+    async fn get_receive_capture(
+        &self,
+        request: Request<ModuleRequest>,
+    ) -> Result<Response<ReceiveCaptureResponse>, Status> {
+        let idx = request.into_inner().module as usize;
+        if idx >= self.module_count {
+            return Err(Status::invalid_argument(format!(
+                "module {} out of range",
+                idx
+            )));
+        }
+
+        let capture = self
+            .capture
+            .get(idx)
+            .map(|capture| capture.lock().unwrap())
+            .filter(|capture| capture.enabled())
+            .ok_or_else(|| Status::failed_precondition("receive capture disabled"))?;
+
+        let now = Instant::now();
+        let frames = capture
+            .frames()
+            .map(|frame| ProtoCapturedFrame {
+                data: frame.data.clone(),
+                rssi: frame.rssi as i32,
+                valid: frame.valid,
+                age_ms: now.duration_since(frame.received).as_millis() as u64,
+            })
+            .collect();
+
+        Ok(Response::new(ReceiveCaptureResponse { frames }))
+    }
 }
 
 //***********************************************************************************************//
 // Radio service
 //***********************************************************************************************//
 
+/// Modules as the config requests see them, handed to the blocking task of a request
+#[derive(Clone)]
+struct ModuleSetup {
+    radios: Vec<SharedRadio>,
+    /// Modules whose channel spacing follows their modulation
+    auto_spacing: Arc<[AtomicBool]>,
+    /// Module 1 follows module 0 on purpose, no channel conflict then
+    diversity: bool,
+    conflict: ChannelConflict,
+}
+
+impl ModuleSetup {
+    /// Locks every module in index order, requests holding more than one
+    /// module can't deadlock that way
+    fn lock_radios(&self) -> Vec<MutexGuard<'_, PlatformRadio>> {
+        self.radios
+            .iter()
+            .map(|radio| radio.lock().unwrap())
+            .collect()
+    }
+
+    /// Warns about or refuses tuning module `idx` with `cfg` to the carrier
+    /// of another module, see [`ChannelConflict`]
+    fn check_channel_conflict(
+        &self,
+        idx: usize,
+        cfg: &RadioConfig,
+        radios: &[MutexGuard<'_, PlatformRadio>],
+    ) -> Result<(), RequestError> {
+        if self.diversity {
+            return Ok(());
+        }
+
+        let carrier = channel::carrier(cfg);
+        for (other, radio) in radios.iter().enumerate() {
+            if other == idx || channel::carrier(&radio.get_config()) != carrier {
+                continue;
+            }
+
+            match self.conflict {
+                ChannelConflict::Warn => log::warn!(
+                    "radio[{idx}] tuned to {} kHz like module {other}, \
+                     their transmissions desense each other",
+                    carrier.as_khz()
+                ),
+                ChannelConflict::Reject => {
+                    return Err(RequestError::failed_precondition(format!(
+                        "module {other} is already on {} kHz",
+                        carrier.as_khz()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Config of module `idx` with the channel spacing of `modulation`, if
+    /// the spacing follows the modulation and changes with it
+    fn spacing_config(
+        &self,
+        idx: usize,
+        modulation: &Modulation,
+        radios: &[MutexGuard<'_, PlatformRadio>],
+    ) -> Result<Option<RadioConfig>, RequestError> {
+        if !self.auto_spacing[idx].load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let Some(spacing) = modulation.recommended_channel_spacing() else {
+            log::warn!("radio[{idx}] keeps its channel spacing, the modulation has none");
+            return Ok(None);
+        };
+
+        let mut config = radios[idx].get_config();
+        if config.channel_spacing == spacing {
+            return Ok(None);
+        }
+
+        config.channel_spacing = spacing;
+        self.check_channel_conflict(idx, &config, radios)?;
+        Ok(Some(config))
+    }
+}
+
 pub struct RadioService {
     radios: Vec<SharedRadio>,
-    module_rx_send: broadcast::Sender<Box<ReceiveModule>>,
-    module_tx_send: broadcast::Sender<Box<TransmitModule>>,
+    transmit_queues: Vec<TransmitQueue>,
+    qos: Vec<SharedLinkQos>,
+    events: EventBus,
+    transmits: TransmitBus,
+    integrity: Vec<FrameIntegrity>,
+    /// Modules set to receive only refuse transmit requests
+    tx_enabled: Vec<bool>,
+    stream_keepalive: Option<Duration>,
+    command_timeout: Duration,
+    channel: ChannelConfig,
+    setup: ModuleSetup,
+    coding: watch::Receiver<LinkCoding>,
+    iteration_budget: Option<usize>,
+    /// Re-applied after a hard reset, which drops them with the rest of the setup
+    power_limit: TxPowerLimit,
+    lbt: LbtParams,
+    cancel: CancellationToken,
 }
 
 impl RadioService {
     pub fn new(
         radios: Vec<SharedRadio>,
-        module_rx_send: broadcast::Sender<Box<ReceiveModule>>,
-        module_tx_send: broadcast::Sender<Box<TransmitModule>>,
+        transmit_queues: Vec<TransmitQueue>,
+        qos: Vec<SharedLinkQos>,
+        events: EventBus,
+        transmits: TransmitBus,
+        config: &CommdConfig,
+        cancel: CancellationToken,
     ) -> Self {
+        let integrity = if config.transmit.raw_crc {
+            FrameIntegrity::SoftwareCrc
+        } else {
+            FrameIntegrity::None
+        };
+
         Self {
+            integrity: vec![integrity; radios.len()],
+            tx_enabled: (0..radios.len())
+                .map(|module| config.transmit.module_tx_enabled(module))
+                .collect(),
+            setup: ModuleSetup {
+                radios: radios.clone(),
+                auto_spacing: (0..radios.len()).map(|_| AtomicBool::new(false)).collect(),
+                diversity: config.receive.mode == ReceiveMode::Diversity,
+                conflict: config.channel.conflict,
+            },
             radios,
-            module_rx_send,
-            module_tx_send,
+            transmit_queues,
+            qos,
+            events,
+            transmits,
+            stream_keepalive: config.grpc.stream_keepalive_ms.map(Duration::from_millis),
+            command_timeout: Duration::from_millis(config.grpc.command_timeout_ms),
+            channel: config.channel.clone(),
+            coding: watch::Sender::new(LinkCoding::default()).subscribe(),
+            iteration_budget: config.receive.ldpc_iteration_budget,
+            power_limit: config.tx_power.limit(),
+            lbt: config.transmit.regulatory_profile.lbt(),
+            cancel,
         }
     }
 
-    fn module_index(&self, module: i32) -> Result<usize, Status> {
+    /// Decodes streamed frames with the node's current coding instead of the default
+    pub fn with_coding(mut self, coding: watch::Receiver<LinkCoding>) -> Self {
+        self.coding = coding;
+        self
+    }
+
+    /// Protects frames the way each module was set up to, see `RadioServer::integrity`
+    pub fn with_integrity(mut self, integrity: Vec<FrameIntegrity>) -> Self {
+        self.integrity = integrity;
+        self
+    }
+
+    fn module_index(&self, module: i32) -> Result<usize, RequestError> {
         if module < 0 || module as usize >= self.radios.len() {
-            return Err(Status::invalid_argument(format!(
+            return Err(RequestError::invalid_argument(format!(
                 "module {} out of range (have {})",
                 module,
                 self.radios.len()
@@ -295,6 +914,79 @@ impl RadioService {
         }
         Ok(module as usize)
     }
+
+    /// Module index and radio frame of `req`, with the raw CRC if enabled
+    fn transmit_frame(
+        &self,
+        req: &TransmitRequest,
+    ) -> Result<(usize, PlatformRadioFrame), RequestError> {
+        let idx = self.module_index(req.module)?;
+        if !self.tx_enabled[idx] {
+            return Err(RequestError::failed_precondition(format!(
+                "transmit disabled on module {idx}"
+            )));
+        }
+
+        let frame = req
+            .frame
+            .as_ref()
+            .ok_or_else(|| RequestError::invalid_argument("missing frame"))?;
+
+        let mut tx_frame = PlatformRadioFrame::new_from_slice(&frame_to_bytes(frame))
+            .map_err(|_| RequestError::invalid_argument("frame too long"))?;
+        if self.integrity[idx] == FrameIntegrity::SoftwareCrc {
+            append_raw_crc(&mut tx_frame)
+                .map_err(|_| RequestError::invalid_argument("frame too long for the crc"))?;
+        }
+
+        Ok((idx, tx_frame))
+    }
+
+    /// Runs `command` with every module locked, off the runtime as a busy
+    /// module may hold its radio for a while
+    ///
+    /// A module held for longer than the command timeout fails the call, the
+    /// command still runs once the module is free.
+    async fn with_modules<T: Send + 'static>(
+        &self,
+        idx: usize,
+        command: &'static str,
+        f: impl FnOnce(&ModuleSetup, &mut [MutexGuard<'_, PlatformRadio>]) -> Result<T, RequestError>
+        + Send
+        + 'static,
+    ) -> Result<T, Status> {
+        let setup = self.setup.clone();
+        self.acknowledged(idx, command, async move {
+            tokio::task::spawn_blocking(move || f(&setup, &mut setup.lock_radios()))
+                .await
+                .map_err(|e| Status::internal(format!("{command}: {e}")))?
+                .map_err(Status::from)
+        })
+        .await
+    }
+
+    /// Waits for the worker of module `idx` to finish `command`, a hung
+    /// worker fails the call after the command timeout
+    async fn acknowledged<T>(
+        &self,
+        idx: usize,
+        command: &str,
+        ack: impl Future<Output = Result<T, Status>>,
+    ) -> Result<T, Status> {
+        match tokio::time::timeout(self.command_timeout, ack).await {
+            Ok(result) => result,
+            Err(_) => {
+                log::warn!(
+                    "radio[{idx}] {command} not acknowledged within {:?}",
+                    self.command_timeout
+                );
+                Err(Status::deadline_exceeded(format!(
+                    "module {idx} didn't acknowledge {command} within {} ms",
+                    self.command_timeout.as_millis()
+                )))
+            }
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -307,8 +999,13 @@ impl RadioTrait for RadioService {
     ) -> Result<Response<ProtoRadioConfig>, Status> {
         let module = request.into_inner().module;
         let idx = self.module_index(module)?;
-        let cfg = self.radios[idx].lock().unwrap().get_config();
-        Ok(Response::new(config_to_proto(module, &cfg)))
+        let radio = self.radios[idx].lock().unwrap();
+        Ok(Response::new(config_to_proto(
+            module,
+            &radio.get_config(),
+            radio.fem_path(),
+            self.setup.auto_spacing[idx].load(Ordering::Relaxed),
+        )))
     }
 
     // ── SetConfig ───────────────────────────────────────────────────────────
@@ -317,14 +1014,38 @@ impl RadioTrait for RadioService {
         &self,
         request: Request<ProtoRadioConfig>,
     ) -> Result<Response<Empty>, Status> {
-        let req = request.into_inner();
+        let mut req = request.into_inner();
         let idx = self.module_index(req.module)?;
-        let cfg = config_from_proto(&req);
-        self.radios[idx]
-            .lock()
-            .unwrap()
-            .set_config(&cfg)
-            .map_err(|e| Status::internal(format!("set_config: {:?}", e)))?;
+
+        // The spacing is taken from the modulation the config is applied with,
+        // a SetModulation in between has to wait
+        self.with_modules(idx, "set_config", move |setup, radios| {
+            let modulation = radios[idx].get_modulation();
+            resolve_channel_spacing(&mut req, &modulation)?;
+            let cfg = config_from_proto(&req)?;
+            setup.check_channel_conflict(idx, &cfg, radios)?;
+            let radio = &mut radios[idx];
+            let fem_path = radio.fem_path();
+            set_fem_path(radio, fem_path_from_proto(&req))?;
+            let result = radio.set_config(&cfg);
+            if result.is_err() {
+                let _ = radio.set_fem_path(fem_path);
+            }
+            match result {
+                Ok(()) => {}
+                Err(KaonicError::IncorrectSettings) => {
+                    return Err(RequestError::invalid_argument(format!(
+                        "radio can't tune to {}",
+                        cfg
+                    )));
+                }
+                Err(e) => return Err(RequestError::internal(format!("set_config: {:?}", e))),
+            }
+            setup.auto_spacing[idx].store(req.auto_spacing, Ordering::Relaxed);
+            Ok(())
+        })
+        .await?;
+
         Ok(Response::new(Empty {}))
     }
 
@@ -345,16 +1066,38 @@ impl RadioTrait for RadioService {
     async fn set_modulation(
         &self,
         request: Request<RadioModulation>,
-    ) -> Result<Response<Empty>, Status> {
+    ) -> Result<Response<SetModulationResponse>, Status> {
         let req = request.into_inner();
         let idx = self.module_index(req.module)?;
         let modulation = modulation_from_proto(&req);
-        self.radios[idx]
-            .lock()
-            .unwrap()
-            .set_modulation(&modulation)
-            .map_err(|e| Status::internal(format!("set_modulation: {:?}", e)))?;
-        Ok(Response::new(Empty {}))
+        let applied = self
+            .with_modules(idx, "set_modulation", move |setup, radios| {
+                let config = setup.spacing_config(idx, &modulation, radios)?;
+                let radio = &mut radios[idx];
+                match config {
+                    Some(config) => radio.set_config_with_modulation(&config, &modulation),
+                    None => radio.set_modulation(&modulation),
+                }
+                .map_err(|e| RequestError::internal(format!("set_modulation: {:?}", e)))?;
+                Ok(radio.get_modulation())
+            })
+            .await?;
+
+        // The radio clamps the power to the ceiling of its band
+        let tx_power_clamped = applied.tx_power() < modulation.tx_power();
+        if tx_power_clamped {
+            log::warn!(
+                "module {} tx power clamped from {} to {}",
+                req.module,
+                modulation.tx_power(),
+                applied.tx_power()
+            );
+        }
+
+        Ok(Response::new(SetModulationResponse {
+            applied: Some(modulation_to_proto(req.module, &applied)),
+            tx_power_clamped,
+        }))
     }
 
     // ── Transmit ────────────────────────────────────────────────────────────
@@ -364,26 +1107,333 @@ impl RadioTrait for RadioService {
         request: Request<TransmitRequest>,
     ) -> Result<Response<TransmitResponse>, Status> {
         let req = request.into_inner();
-        let idx = self.module_index(req.module)?;
-        let frame = req
-            .frame
-            .ok_or_else(|| Status::invalid_argument("missing frame"))?;
-        let bytes = frame_to_bytes(&frame);
+        let start = Instant::now();
+        let (idx, tx_frame) = self.transmit_frame(&req)?;
+
+        // The worker reports the frame on the transmit event stream
+        let (reply, outcome) = oneshot::channel();
+        let job = TransmitJob {
+            frame: tx_frame,
+            modulation: req.modulation.as_ref().map(modulation_from_proto),
+            seq: req.seq,
+            requested: start,
+            reply: Some(reply),
+        };
+        // A full queue waits for the worker as well
+        let TransmitOutcome {
+            result,
+            report,
+            tx_power,
+        } = self
+            .acknowledged(idx, "transmit", async {
+                self.transmit_queues[idx]
+                    .send(job)
+                    .await
+                    .map_err(|_| Status::unavailable("transmit worker stopped"))?;
+                outcome
+                    .await
+                    .map_err(|_| Status::unavailable("transmit worker stopped"))
+            })
+            .await?;
+
+        match result {
+            Ok(_) => {}
+            // The radio gave up on the frame, report why instead of failing the call
+            Err(_) if !report.result.is_sent() && report.attempts > 0 => {}
+            Err(e) => return Err(Status::internal(format!("transmit: {:?}", e))),
+        }
+
+        Ok(Response::new(transmit_report_to_proto(
+            &report,
+            tx_power,
+            start.elapsed().as_micros() as u32,
+        )))
+    }
+
+    // ── TransmitBatch ───────────────────────────────────────────────────────
 
+    async fn transmit_batch(
+        &self,
+        request: Request<TransmitBatchRequest>,
+    ) -> Result<Response<TransmitBatchResponse>, Status> {
         let start = Instant::now();
-        let tx_frame = PlatformRadioFrame::new_from_slice(&bytes);
-        self.radios[idx]
-            .lock()
-            .unwrap()
-            .transmit(&tx_frame)
-            .map_err(|e| Status::internal(format!("transmit: {:?}", e)))?;
-        let _ = self.module_tx_send.send(Box::new(TransmitModule {
-            module: idx,
-            frame: kaonic_ctrl::protocol::RadioFrame::new_from_frame(&tx_frame),
-        }));
-
-        Ok(Response::new(TransmitResponse {
-            latency: start.elapsed().as_micros() as u32,
+
+        // Nothing is queued unless every frame is valid
+        let mut jobs = Vec::new();
+        for req in request.into_inner().frames {
+            let (idx, frame) = self.transmit_frame(&req)?;
+            jobs.push((
+                idx,
+                TransmitJob {
+                    frame,
+                    modulation: req.modulation.as_ref().map(modulation_from_proto),
+                    seq: req.seq,
+                    requested: start,
+                    reply: None,
+                },
+            ));
+        }
+
+        let queued = jobs.len() as u32;
+        for (idx, job) in jobs {
+            self.transmit_queues[idx]
+                .send(job)
+                .await
+                .map_err(|_| Status::unavailable("transmit worker stopped"))?;
+        }
+
+        Ok(Response::new(TransmitBatchResponse { queued }))
+    }
+
+    // ── MeasurePhase ────────────────────────────────────────────────────────
+
+    async fn measure_phase(
+        &self,
+        request: Request<ModuleRequest>,
+    ) -> Result<Response<PhaseMeasurementResponse>, Status> {
+        let module = request.into_inner().module;
+        let idx = self.module_index(module)?;
+        let measurement = match self.radios[idx].lock().unwrap().measure_phase() {
+            Ok(measurement) => measurement,
+            Err(KaonicError::NotSupported) => {
+                return Err(Status::unimplemented("no phase measurement unit"));
+            }
+            Err(e) => return Err(Status::internal(format!("measure_phase: {:?}", e))),
+        };
+
+        Ok(Response::new(PhaseMeasurementResponse {
+            module,
+            phase: measurement.phase.into(),
+            quality: measurement.quality.into(),
+            i: measurement.i.into(),
+            q: measurement.q.into(),
+        }))
+    }
+
+    // ── SelectBestChannel ───────────────────────────────────────────────────
+
+    async fn select_best_channel(
+        &self,
+        request: Request<SelectChannelRequest>,
+    ) -> Result<Response<SelectChannelResponse>, Status> {
+        let req = request.into_inner();
+        let idx = self.module_index(req.module)?;
+        let channel_count = match req.channel_count {
+            Some(count) => Some(
+                u16::try_from(count)
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| {
+                        Status::invalid_argument(format!("channel_count {} out of range", count))
+                    })?,
+            ),
+            None => self.channel.channel_count,
+        };
+        let dwell = Duration::from_millis(req.dwell_ms.map_or(self.channel.dwell_ms, u64::from));
+
+        // Scanning a whole band keeps the radio locked for channel count x
+        // dwell, off the runtime so only this module's users wait
+        let radio = self.radios[idx].clone();
+        let best = tokio::task::spawn_blocking(move || {
+            channel::select_best_channel(&mut *radio.lock().unwrap(), channel_count, dwell)
+        })
+        .await
+        .map_err(|e| Status::internal(format!("select_best_channel: {}", e)))?;
+
+        let best = match best {
+            Ok(best) => best,
+            Err(KaonicError::IncorrectSettings) => {
+                return Err(Status::failed_precondition(
+                    "radio isn't on a known frequency plan, set channel_count",
+                ));
+            }
+            Err(e) => return Err(Status::internal(format!("select_best_channel: {:?}", e))),
+        };
+
+        Ok(Response::new(SelectChannelResponse {
+            module: req.module,
+            channel: best.channel.into(),
+            rssi: best.rssi.into(),
+        }))
+    }
+
+    // ── ResetModule ─────────────────────────────────────────────────────────
+
+    async fn reset_module(
+        &self,
+        request: Request<ResetModuleRequest>,
+    ) -> Result<Response<ResetModuleResponse>, Status> {
+        let req = request.into_inner();
+        let idx = self.module_index(req.module)?;
+        let kind = if req.hard {
+            ResetKind::Hard
+        } else {
+            ResetKind::Soft
+        };
+
+        // The reset waits on the transceiver, keep it off the runtime
+        let radio = self.radios[idx].clone();
+        let hardware_fcs = self.integrity[idx] == FrameIntegrity::HardwareFcs;
+        let power_limit = self.power_limit;
+        let lbt = self.lbt;
+        let reset = self
+            .acknowledged(idx, "reset_module", async move {
+                tokio::task::spawn_blocking(move || {
+                    let mut radio = radio.lock().unwrap();
+                    radio.reset(kind)?;
+                    // Peers still expect the FCS after a hard reset
+                    if hardware_fcs && !radio.hardware_fcs() {
+                        radio.set_hardware_fcs(true)?;
+                    }
+                    if kind == ResetKind::Hard
+                        && let Err(e) = radio.set_power_limit(power_limit)
+                    {
+                        log::warn!("radio[{idx}] tx power ceiling not restored: {e:?}");
+                    }
+                    if kind == ResetKind::Hard
+                        && let Err(e) = radio.set_lbt(lbt)
+                    {
+                        log::warn!("radio[{idx}] listen before talk not restored: {e:?}");
+                    }
+                    Ok::<_, KaonicError>((radio.get_config(), radio.get_modulation()))
+                })
+                .await
+                .map_err(|e| Status::internal(format!("reset_module: {}", e)))
+            })
+            .await?;
+
+        let (config, modulation) = match reset {
+            Ok(state) => state,
+            Err(KaonicError::NotSupported) => {
+                return Err(Status::unimplemented("radio can't be reset"));
+            }
+            Err(e) => return Err(Status::internal(format!("reset_module: {:?}", e))),
+        };
+
+        log::warn!("module {} reset ({:?})", req.module, kind);
+
+        // The default config has a spacing of its own
+        if kind == ResetKind::Hard {
+            self.setup.auto_spacing[idx].store(false, Ordering::Relaxed);
+        }
+
+        let fem = self.radios[idx].lock().unwrap().fem_path();
+        let auto_spacing = self.setup.auto_spacing[idx].load(Ordering::Relaxed);
+        Ok(Response::new(ResetModuleResponse {
+            config: Some(config_to_proto(req.module, &config, fem, auto_spacing)),
+            modulation: Some(modulation_to_proto(req.module, &modulation)),
+        }))
+    }
+
+    // ── GetQos / SetQos ─────────────────────────────────────────────────────
+
+    async fn get_qos(
+        &self,
+        request: Request<ModuleRequest>,
+    ) -> Result<Response<QosSettings>, Status> {
+        let module = request.into_inner().module;
+        let idx = self.module_index(module)?;
+        let qos = self.qos[idx].lock().unwrap();
+        Ok(Response::new(qos_to_proto(module, &qos)))
+    }
+
+    async fn set_qos(
+        &self,
+        request: Request<QosSettings>,
+    ) -> Result<Response<QosSettings>, Status> {
+        let req = request.into_inner();
+        let idx = self.module_index(req.module)?;
+
+        let mut qos = self.qos[idx].lock().unwrap();
+        let config = qos_config_from_proto(&req, qos.config())?;
+        qos.set_config(config);
+
+        Ok(Response::new(qos_to_proto(req.module, &qos)))
+    }
+
+    // ── ApplyConfig ─────────────────────────────────────────────────────────
+
+    async fn apply_config(
+        &self,
+        request: Request<ApplyConfigRequest>,
+    ) -> Result<Response<ApplyConfigResponse>, Status> {
+        let req = request.into_inner();
+        let mut proto_config = req
+            .config
+            .ok_or_else(|| Status::invalid_argument("missing config"))?;
+        let module = proto_config.module;
+        let idx = self.module_index(module)?;
+        let modulation = req.modulation.as_ref().map(modulation_from_proto);
+
+        // Everything is checked before the radio is touched, so a bad request
+        // leaves the module as it was
+        let qos_config = match &req.qos {
+            Some(settings) => {
+                let qos = self.qos[idx].lock().unwrap();
+                Some(qos_config_from_proto(settings, qos.config())?)
+            }
+            None => None,
+        };
+
+        // The radio stays locked until the QoS follows, so neither a
+        // transmission nor a QoS step sees half of the change
+        let qos = self.qos[idx].clone();
+        let (applied, qos) = self
+            .with_modules(idx, "apply_config", move |setup, radios| {
+                let spacing_modulation = match modulation {
+                    Some(modulation) => modulation,
+                    None => radios[idx].get_modulation(),
+                };
+                resolve_channel_spacing(&mut proto_config, &spacing_modulation)?;
+                let cfg = config_from_proto(&proto_config)?;
+                setup.check_channel_conflict(idx, &cfg, radios)?;
+                let radio = &mut radios[idx];
+                let fem_path = radio.fem_path();
+                set_fem_path(radio, fem_path_from_proto(&proto_config))?;
+                let result = match &modulation {
+                    Some(modulation) => radio.set_config_with_modulation(&cfg, modulation),
+                    None => radio.set_config(&cfg),
+                };
+                if result.is_err() {
+                    let _ = radio.set_fem_path(fem_path);
+                }
+                match result {
+                    Ok(()) => {}
+                    Err(KaonicError::IncorrectSettings) => {
+                        return Err(RequestError::invalid_argument(format!(
+                            "radio can't tune to {}",
+                            cfg
+                        )));
+                    }
+                    Err(e) => {
+                        return Err(RequestError::internal(format!("apply_config: {:?}", e)));
+                    }
+                }
+                setup.auto_spacing[idx].store(proto_config.auto_spacing, Ordering::Relaxed);
+
+                let mut qos = qos.lock().unwrap();
+                if let Some(config) = qos_config {
+                    qos.set_config(config);
+                }
+                Ok((radio.get_modulation(), qos_to_proto(module, &qos)))
+            })
+            .await?;
+
+        // The radio clamps the power to the ceiling of its band
+        let tx_power_clamped =
+            modulation.is_some_and(|modulation| applied.tx_power() < modulation.tx_power());
+        if tx_power_clamped {
+            log::warn!(
+                "module {} tx power clamped to {}",
+                module,
+                applied.tx_power()
+            );
+        }
+
+        Ok(Response::new(ApplyConfigResponse {
+            applied: Some(modulation_to_proto(module, &applied)),
+            tx_power_clamped,
+            qos: Some(qos),
         }))
     }
 
@@ -398,27 +1448,78 @@ impl RadioTrait for RadioService {
         let req = request.into_inner();
         let idx = self.module_index(req.module)?;
         let proto_module = req.module;
+        let filter = ReceiveFilter::from_request(&req).map_err(Status::invalid_argument)?;
 
-        let mut rx = self.module_rx_send.subscribe();
+        let mut rx = self.events.subscribe();
         let (tx, stream_recv) = tokio::sync::mpsc::channel(16);
+        let stream_keepalive = self.stream_keepalive;
+        let coding = self.coding.clone();
+        let iteration_budget = self.iteration_budget;
+        let cancel = self.cancel.clone();
 
         tokio::spawn(async move {
+            // Decoding costs a full LDPC pass per frame, only streams asking
+            // for it pay for it
+            let mut decoder = req.decode.then(|| PacketDecoder::new(iteration_budget));
+            let mut last_sent = tokio::time::Instant::now();
+
             loop {
-                match rx.recv().await {
-                    Ok(msg) => {
-                        if msg.module != idx {
+                // Ends the stream on shutdown so the server can drain
+                let recv = async {
+                    tokio::select! {
+                        result = rx.recv() => Some(result),
+                        _ = cancel.cancelled() => None,
+                    }
+                };
+                let result = match stream_keepalive {
+                    Some(idle) => match tokio::time::timeout_at(last_sent + idle, recv).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            // Nothing for this module in a while, keep the stream alive
+                            let keepalive = ReceiveResponse {
+                                module: proto_module,
+                                ..Default::default()
+                            };
+                            if tx.send(Ok(keepalive)).await.is_err() {
+                                break;
+                            }
+                            last_sent = tokio::time::Instant::now();
+                            continue;
+                        }
+                    },
+                    None => recv.await,
+                };
+                let Some(result) = result else {
+                    break;
+                };
+
+                match result {
+                    Ok(RadioEvent::Receive(msg)) => {
+                        // Filtered frames still count in the module statistics,
+                        // they are only kept off this stream
+                        let rx = &msg.receive;
+                        if rx.module != idx || !filter.accepts(rx.frame.as_slice()) {
                             continue;
                         }
                         let resp = ReceiveResponse {
                             module: proto_module,
-                            frame: Some(bytes_to_frame(msg.frame.as_slice())),
-                            rssi: msg.rssi as i32,
+                            frame: Some(bytes_to_frame(rx.frame.as_slice())),
+                            rssi: rx.rssi as i32,
                             latency: 0,
+                            decoded: decoder.as_mut().and_then(|decoder| {
+                                decoder.set_coding(*coding.borrow());
+                                decoder.decode(rx.frame.as_slice())
+                            }),
+                            crc_valid: msg.crc_valid,
+                            modulation: Some(modulation_to_proto(proto_module, &msg.modulation)),
+                            quality: Some(channel_quality_to_proto(msg.quality) as i32),
                         };
                         if tx.send(Ok(resp)).await.is_err() {
                             break;
                         }
+                        last_sent = tokio::time::Instant::now();
                     }
+                    Ok(_) => continue,
                     Err(broadcast::error::RecvError::Lagged(_)) => continue,
                     Err(broadcast::error::RecvError::Closed) => break,
                 }
@@ -438,21 +1539,23 @@ impl RadioTrait for RadioService {
         let idx = self.module_index(req.module)?;
         let proto_module = req.module;
 
-        let mut rx = self.module_tx_send.subscribe();
+        let mut rx = self.transmits.subscribe();
         let (tx, stream_recv) = tokio::sync::mpsc::channel(16);
+        let cancel = self.cancel.clone();
 
         tokio::spawn(async move {
             loop {
-                match rx.recv().await {
+                let result = tokio::select! {
+                    result = rx.recv() => result,
+                    _ = cancel.cancelled() => break,
+                };
+
+                match result {
                     Ok(msg) => {
                         if msg.module != idx {
                             continue;
                         }
-                        let resp = TransmitEventResponse {
-                            module: proto_module,
-                            frame: Some(bytes_to_frame(msg.frame.as_slice())),
-                            latency: 0,
-                        };
+                        let resp = transmit_event_to_proto(proto_module, &msg);
                         if tx.send(Ok(resp)).await.is_err() {
                             break;
                         }
@@ -0,0 +1,42 @@
+use kaonic_frame::frame::Frame;
+use kaonic_net::{
+    coder::{self, LdpcPacketCoder, LinkCoding, PacketCoder},
+    packet::{Packet, PacketType},
+};
+
+use crate::beacon::NodeId;
+
+const HEARTBEAT_FRAME_SIZE: usize = 2048;
+const HEARTBEAT_VERSION: u8 = 1;
+
+/// Serializes a heartbeat of `node_id` as a kaonic-net packet of type
+/// `Heartbeat`
+///
+/// Unlike beacons it is LDPC coded with `coding` like data, so the outcome
+/// of its decode tells the receiving QoS how data would fare on the link.
+pub fn encode(node_id: NodeId, coding: LinkCoding) -> Vec<u8> {
+    let mut packet = Packet::<HEARTBEAT_FRAME_SIZE>::new();
+    let mut frame = Frame::<HEARTBEAT_FRAME_SIZE>::new();
+
+    packet
+        .header_mut()
+        .set_packet_type(PacketType::Heartbeat)
+        .set_id(node_id);
+    packet
+        .frame_mut()
+        .push_data(&[HEARTBEAT_VERSION])
+        .expect("heartbeat payload fits the frame");
+    packet.build();
+
+    LdpcPacketCoder::new()
+        .with_coding(coding)
+        .encode(&packet, &mut frame)
+        .expect("heartbeat fits the frame");
+
+    frame.as_slice().to_vec()
+}
+
+/// Length of a heartbeat coded with `coding`
+pub fn encoded_len(coding: LinkCoding) -> usize {
+    coder::encoded_len(1, coding.payload_code)
+}
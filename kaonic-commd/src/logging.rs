@@ -0,0 +1,163 @@
+//! Where the log records of commd go.
+//!
+//! Records are written to stderr unless `--log syslog` (or `KAONIC_LOG=syslog`)
+//! sends them to the local syslog daemon, and from there to journald on
+//! systemd systems. Syslog records carry the severity of their level and are
+//! prefixed with the module that logged them. Without a reachable syslog
+//! socket commd falls back to stderr.
+
+use std::str::FromStr;
+
+use log::{LevelFilter, Log};
+
+/// Environment variable selecting the target when `--log` isn't given
+pub const LOG_TARGET_VAR: &str = "KAONIC_LOG";
+
+const LOG_LEVEL: LevelFilter = LevelFilter::Debug;
+
+/// Destination of the log records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogTarget {
+    #[default]
+    Stderr,
+    Syslog,
+}
+
+impl FromStr for LogTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stderr" => Ok(Self::Stderr),
+            "syslog" => Ok(Self::Syslog),
+            _ => Err(format!(
+                "unknown log target '{s}', expected stderr or syslog"
+            )),
+        }
+    }
+}
+
+/// Installs the logger for `target`, once at startup
+pub fn init(target: LogTarget) {
+    log::set_boxed_logger(logger(target)).expect("logger installed once");
+    log::set_max_level(LOG_LEVEL);
+}
+
+fn logger(target: LogTarget) -> Box<dyn Log> {
+    if target == LogTarget::Syslog {
+        match syslog_logger::connect(LOG_LEVEL) {
+            Ok(logger) => return Box::new(logger),
+            Err(e) => eprintln!("syslog unavailable ({e}), logging to stderr"),
+        }
+    }
+
+    Box::new(env_logger::builder().filter_level(LOG_LEVEL).build())
+}
+
+#[cfg(target_os = "linux")]
+mod syslog_logger {
+    use std::{io::Write, sync::Mutex};
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use syslog::{Facility, Formatter3164, LoggerBackend};
+
+    pub struct SyslogLogger {
+        level: LevelFilter,
+        logger: Mutex<syslog::Logger<LoggerBackend, Formatter3164>>,
+    }
+
+    /// Connects to the local syslog socket as a daemon, forwarding the
+    /// records up to `level`
+    pub fn connect(level: LevelFilter) -> Result<SyslogLogger, String> {
+        let formatter = Formatter3164 {
+            facility: Facility::LOG_DAEMON,
+            hostname: None,
+            process: env!("CARGO_PKG_NAME").into(),
+            pid: std::process::id(),
+        };
+
+        let logger = syslog::unix(formatter).map_err(|e| e.to_string())?;
+
+        Ok(SyslogLogger {
+            level,
+            logger: Mutex::new(logger),
+        })
+    }
+
+    impl Log for SyslogLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= self.level
+        }
+
+        fn log(&self, record: &Record) {
+            if !self.enabled(record.metadata()) {
+                return;
+            }
+
+            let message = format!("{}: {}", record.target(), record.args());
+            let mut logger = self.logger.lock().unwrap_or_else(|e| e.into_inner());
+
+            // Nowhere to report a lost record to
+            let _ = match record.level() {
+                Level::Error => logger.err(message),
+                Level::Warn => logger.warning(message),
+                Level::Info => logger.info(message),
+                Level::Debug | Level::Trace => logger.debug(message),
+            };
+        }
+
+        fn flush(&self) {
+            let _ = self
+                .logger
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .backend
+                .flush();
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod syslog_logger {
+    pub fn connect(_level: log::LevelFilter) -> Result<env_logger::Logger, &'static str> {
+        Err("not supported on this platform")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(logger: &dyn Log, level: log::Level) -> bool {
+        logger.enabled(&log::Metadata::builder().level(level).build())
+    }
+
+    #[test]
+    fn test_parse_log_target() {
+        assert_eq!("syslog".parse(), Ok(LogTarget::Syslog));
+        assert_eq!("stderr".parse(), Ok(LogTarget::Stderr));
+        assert!("journal".parse::<LogTarget>().is_err());
+        assert_eq!(LogTarget::default(), LogTarget::Stderr);
+    }
+
+    #[test]
+    fn test_syslog_logger_or_fallback() {
+        // Syslog if the socket exists, stderr otherwise, both filter on their
+        // own level instead of the global one
+        for target in [LogTarget::Syslog, LogTarget::Stderr] {
+            let logger = logger(target);
+            assert!(enabled(logger.as_ref(), log::Level::Error));
+            assert!(enabled(logger.as_ref(), log::Level::Debug));
+            assert!(!enabled(logger.as_ref(), log::Level::Trace));
+
+            logger.log(
+                &log::Record::builder()
+                    .level(log::Level::Warn)
+                    .target("kaonic_commd::logging")
+                    .args(format_args!("syslog smoke test"))
+                    .build(),
+            );
+            logger.flush();
+        }
+    }
+}
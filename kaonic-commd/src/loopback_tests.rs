@@ -0,0 +1,2064 @@
+//! End-to-end test of the gRPC boundary against the host (loopback) platform.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use kaonic_ctrl::protocol::{
+    API_VERSION, CAPABILITY_OFDM, CAPABILITY_RAW_CRC, CAPABILITY_SIMULATED, RADIO_FRAME_SIZE,
+};
+use kaonic_frame::frame::Frame;
+use kaonic_net::{
+    coder::{LdpcPacketCoder, PacketCoder},
+    packet::Packet,
+};
+use kaonic_radio::{
+    error::KaonicError,
+    fem::{FemPath, FemPins},
+    frequency_plan::EU_868,
+    platform::PlatformRadioFrame,
+    power::TxPowerLimit,
+    radio::Radio,
+    regulatory::RegulatoryProfile,
+};
+use radio_common::frequency::BandwidthFilter;
+use radio_rf215::regs::{RadioInterrupt, RadioInterruptMask};
+use tokio::sync::mpsc;
+use tokio_stream::{StreamExt, wrappers::TcpListenerStream};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{ChannelConflict, CommdConfig, ReceiveMode};
+use crate::grpc_server::kaonic::{
+    ApplyConfigRequest, Bringup, ChannelQuality, Empty, FemPins as ProtoFemPins, FrameIntegrity,
+    ModuleRequest, QosSettings, RadioConfig, RadioFrame, RadioModulation, RadioModulationOfdm,
+    ReceiveRequest, ResetModuleRequest, SelectChannelRequest, TransmitBatchRequest,
+    TransmitEventRequest, TransmitRequest, TransmitResult, device_client::DeviceClient,
+    radio_client::RadioClient, radio_modulation::Modulation,
+};
+use crate::grpc_server::{DeviceServer, DeviceService, GrpcRadioServer, RadioService};
+use crate::radio_server::{RadioServer, SharedRadio};
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Starts the gRPC services on an ephemeral port of the host platform
+async fn spawn_server(
+    stream_keepalive: Option<Duration>,
+) -> (CancellationToken, SocketAddr, Vec<SharedRadio>) {
+    let mut config = CommdConfig::default();
+    config.grpc.stream_keepalive_ms =
+        stream_keepalive.map(|keepalive| keepalive.as_millis() as u64);
+
+    spawn_server_with_config(config).await
+}
+
+/// Like [`spawn_server`] with a full daemon configuration
+async fn spawn_server_with_config(
+    config: CommdConfig,
+) -> (CancellationToken, SocketAddr, Vec<SharedRadio>) {
+    let cancel = CancellationToken::new();
+    let (client_send, _client_recv) = mpsc::channel(16);
+
+    let radio_server = RadioServer::new(
+        client_send,
+        cancel.clone(),
+        "test".to_string(),
+        RADIO_FRAME_SIZE,
+        config.clone(),
+    )
+    .expect("radio server");
+
+    let device_service = DeviceService::new(
+        radio_server.module_count(),
+        "test".to_string(),
+        RADIO_FRAME_SIZE as u32,
+        radio_server.stats(),
+        radio_server.peers(),
+        radio_server.capabilities(),
+    )
+    .with_capture(radio_server.capture());
+    let radios = radio_server.radios();
+    let radio_service = RadioService::new(
+        radio_server.radios(),
+        radio_server.transmit_queues(),
+        radio_server.qos(),
+        radio_server.events(),
+        radio_server.transmits(),
+        &config,
+        cancel.clone(),
+    )
+    .with_coding(radio_server.coding())
+    .with_integrity(radio_server.integrity());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener");
+    let addr = listener.local_addr().expect("local address");
+
+    {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .http2_keepalive_interval(Some(Duration::from_millis(100)))
+                .add_service(DeviceServer::new(device_service))
+                .add_service(GrpcRadioServer::new(radio_service))
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), cancel.cancelled())
+                .await
+                .expect("gRPC server");
+        });
+    }
+
+    (cancel, addr, radios)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_grpc_loopback() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    client
+        .set_config(RadioConfig {
+            module: 0,
+            freq: 869_535_000,
+            channel_spacing: 200_000,
+            channel: 10,
+            bandwidth_filter: 0,
+            fem: None,
+            auto_spacing: false,
+            fem_auto: false,
+        })
+        .await
+        .expect("set config");
+
+    client
+        .set_modulation(RadioModulation {
+            module: 0,
+            modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+                mcs: 3,
+                opt: 1,
+                pdt: 3,
+                tx_power: 10,
+                lfo: false,
+            })),
+        })
+        .await
+        .expect("set modulation");
+
+    let config = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(config.channel, 10);
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    let payload = b"@@ LOOPBACK FRAME @@".to_vec();
+    client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: payload.clone(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit");
+
+    let received = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+        .await
+        .expect("frame looped back")
+        .expect("stream open")
+        .expect("receive response");
+
+    assert_eq!(received.module, 0);
+    assert_eq!(received.frame.expect("frame").data, payload);
+    assert!(received.decoded.is_none());
+    assert!(received.crc_valid.is_none());
+
+    // Retries are reported instead of failing the call
+    radios[0].lock().unwrap().simulate_busy_channel(1);
+    let response = client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: payload.clone(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit")
+        .into_inner();
+    assert_eq!(response.attempts, 2);
+    assert_eq!(response.result(), TransmitResult::Sent);
+
+    radios[0].lock().unwrap().simulate_busy_channel(u32::MAX);
+    let response = client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: payload.clone(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit")
+        .into_inner();
+    assert_eq!(response.attempts, 4);
+    assert_eq!(response.result(), TransmitResult::ChannelBusy);
+    radios[0].lock().unwrap().simulate_busy_channel(0);
+
+    // A per-frame modulation only applies to its frame
+    let own = client
+        .get_modulation(ModuleRequest { module: 0 })
+        .await
+        .expect("get modulation")
+        .into_inner();
+    let robust = RadioModulationOfdm {
+        mcs: 0,
+        opt: 3,
+        pdt: 3,
+        tx_power: 10,
+        lfo: false,
+    };
+    client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame { data: payload }),
+            modulation: Some(RadioModulation {
+                module: 0,
+                modulation: Some(Modulation::Ofdm(robust)),
+            }),
+            seq: 0,
+        })
+        .await
+        .expect("transmit");
+
+    let modulation = client
+        .get_modulation(ModuleRequest { module: 0 })
+        .await
+        .expect("get modulation")
+        .into_inner();
+    assert_eq!(modulation.modulation, own.modulation);
+    assert_ne!(modulation.modulation, Some(Modulation::Ofdm(robust)));
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_stream_survives_idle_period() {
+    let (cancel, addr, _radios) = spawn_server(Some(Duration::from_millis(50))).await;
+
+    let channel = tonic::transport::Endpoint::from_shared(format!("http://{}", addr))
+        .expect("endpoint")
+        .http2_keep_alive_interval(Duration::from_millis(100))
+        .keep_alive_timeout(Duration::from_secs(1))
+        .keep_alive_while_idle(true)
+        .connect()
+        .await
+        .expect("gRPC channel");
+    let mut client = RadioClient::new(channel);
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    // Quiet link: only keepalives arrive, and the stream stays open
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let keepalive = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+        .await
+        .expect("keepalive")
+        .expect("stream open")
+        .expect("receive response");
+    assert_eq!(keepalive.module, 0);
+    assert!(keepalive.frame.is_none());
+
+    let payload = b"@@ AFTER IDLE @@".to_vec();
+    client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: payload.clone(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit");
+
+    let received = tokio::time::timeout(RECEIVE_TIMEOUT, async {
+        while let Some(response) = stream.next().await {
+            let response = response.expect("receive response");
+            if let Some(frame) = response.frame {
+                return frame;
+            }
+        }
+        panic!("stream closed");
+    })
+    .await
+    .expect("frame after idle period");
+    assert_eq!(received.data, payload);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transmit_completion_event_per_frame() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+
+    let client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut events = client
+        .clone()
+        .transmit_event_stream(TransmitEventRequest { module: 0 })
+        .await
+        .expect("transmit event stream")
+        .into_inner();
+
+    // Fire-and-forget batch, completions are correlated through the events
+    let tasks: Vec<_> = (1..=5u32)
+        .map(|seq| {
+            let mut client = client.clone();
+            tokio::spawn(async move {
+                client
+                    .transmit(TransmitRequest {
+                        module: 0,
+                        frame: Some(RadioFrame {
+                            data: vec![seq as u8; 16],
+                        }),
+                        modulation: None,
+                        seq,
+                    })
+                    .await
+                    .expect("transmit")
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.expect("transmit task");
+    }
+
+    let modulation = radios[0].lock().unwrap().get_modulation();
+    let mut seqs = Vec::new();
+    for _ in 0..5 {
+        let event = tokio::time::timeout(RECEIVE_TIMEOUT, events.next())
+            .await
+            .expect("transmit event")
+            .expect("stream open")
+            .expect("transmit event response");
+
+        assert_eq!(event.module, 0);
+        assert_eq!(event.result(), TransmitResult::Sent);
+        assert_eq!(event.attempts, 1);
+        let air_time = modulation.air_time(16).expect("air time");
+        assert_eq!(event.air_time, air_time.as_micros() as u32);
+        assert_eq!(event.frame.expect("frame").data, vec![event.seq as u8; 16]);
+        seqs.push(event.seq);
+    }
+    seqs.sort();
+    assert_eq!(seqs, [1, 2, 3, 4, 5]);
+
+    // Frames the radio gives up on are reported too
+    radios[0].lock().unwrap().simulate_busy_channel(u32::MAX);
+    let mut busy_client = client.clone();
+    busy_client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: vec![0xBB; 8],
+            }),
+            modulation: None,
+            seq: 9,
+        })
+        .await
+        .expect("transmit");
+    radios[0].lock().unwrap().simulate_busy_channel(0);
+
+    let event = tokio::time::timeout(RECEIVE_TIMEOUT, events.next())
+        .await
+        .expect("transmit event")
+        .expect("stream open")
+        .expect("transmit event response");
+    assert_eq!(event.seq, 9);
+    assert_eq!(event.result(), TransmitResult::ChannelBusy);
+    assert_eq!(event.attempts, 4);
+    assert_eq!(event.air_time, 0);
+
+    // So are frames the radio refuses outright, the call fails as before
+    radios[0]
+        .lock()
+        .unwrap()
+        .simulate_tx_error(KaonicError::InvalidState);
+    let mut refused_client = client.clone();
+    refused_client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: vec![0xEE; 8],
+            }),
+            modulation: None,
+            seq: 10,
+        })
+        .await
+        .expect_err("refused transmit");
+
+    let event = tokio::time::timeout(RECEIVE_TIMEOUT, events.next())
+        .await
+        .expect("transmit event")
+        .expect("stream open")
+        .expect("transmit event response");
+    assert_eq!(event.seq, 10);
+    assert_eq!(event.result(), TransmitResult::Error);
+    assert_eq!(event.attempts, 0);
+    assert_eq!(event.air_time, 0);
+    assert_eq!(event.error, "InvalidState");
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transmit_batch() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut events = client
+        .transmit_event_stream(TransmitEventRequest { module: 1 })
+        .await
+        .expect("transmit event stream")
+        .into_inner();
+
+    let frame = |seq: u32| TransmitRequest {
+        module: 1,
+        frame: Some(RadioFrame {
+            data: vec![seq as u8; 16],
+        }),
+        modulation: None,
+        seq,
+    };
+
+    // One bad frame and nothing is queued
+    let mut invalid = frame(9);
+    invalid.module = 7;
+    client
+        .transmit_batch(TransmitBatchRequest {
+            frames: vec![frame(8), invalid],
+        })
+        .await
+        .expect_err("out of range module");
+
+    let response = client
+        .transmit_batch(TransmitBatchRequest {
+            frames: (1..=4).map(frame).collect(),
+        })
+        .await
+        .expect("transmit batch")
+        .into_inner();
+    assert_eq!(response.queued, 4);
+
+    let mut seqs = Vec::new();
+    for _ in 0..4 {
+        let event = tokio::time::timeout(RECEIVE_TIMEOUT, events.next())
+            .await
+            .expect("transmit event")
+            .expect("stream open")
+            .expect("transmit event response");
+
+        assert_eq!(event.module, 1);
+        assert_eq!(event.result(), TransmitResult::Sent);
+        seqs.push(event.seq);
+    }
+    assert_eq!(seqs, [1, 2, 3, 4]);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transmit_rejected_when_disabled() {
+    let mut config = CommdConfig::default();
+    config.transmit.tx_enabled = vec![false];
+
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    let frame = |module: i32| TransmitRequest {
+        module,
+        frame: Some(RadioFrame {
+            data: b"@@ RECEIVE ONLY @@".to_vec(),
+        }),
+        modulation: None,
+        seq: 0,
+    };
+
+    let status = client.transmit(frame(0)).await.expect_err("receive only");
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    assert!(status.message().contains("transmit disabled"));
+
+    let status = client
+        .transmit_batch(TransmitBatchRequest {
+            frames: vec![frame(0)],
+        })
+        .await
+        .expect_err("receive only");
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    // The other module isn't listed and still transmits
+    client.transmit(frame(1)).await.expect("transmit");
+
+    // Module 0 keeps receiving
+    radios[0]
+        .lock()
+        .unwrap()
+        .transmit(&PlatformRadioFrame::new_from_slice(b"@@ INCOMING @@").unwrap())
+        .expect("incoming frame");
+    let received = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+        .await
+        .expect("frame received")
+        .expect("stream open")
+        .expect("receive response");
+    assert_eq!(received.frame.expect("frame").data, b"@@ INCOMING @@");
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_after_transmit_timeout() {
+    let mut config = CommdConfig::default();
+    config.transmit.rx_restore_timeout_ms = Some(20);
+
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+    assert_eq!(
+        radios[0].lock().unwrap().rx_restore_timeout(),
+        Duration::from_millis(20)
+    );
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    // Every attempt times out and leaves the radio in TX
+    radios[0].lock().unwrap().simulate_tx_timeout();
+    let response = client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: b"@@ TIMED OUT @@".to_vec(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit")
+        .into_inner();
+    assert_eq!(response.result(), TransmitResult::MaxRetries);
+    assert_eq!(radios[0].lock().unwrap().rx_restores(), 1);
+
+    radios[0]
+        .lock()
+        .unwrap()
+        .transmit(&PlatformRadioFrame::new_from_slice(b"@@ INCOMING @@").unwrap())
+        .expect("incoming frame");
+    let received = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+        .await
+        .expect("frame received")
+        .expect("stream open")
+        .expect("receive response");
+    assert_eq!(received.frame.expect("frame").data, b"@@ INCOMING @@");
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_config_rejects_bad_frequency() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let before = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+
+    let config = |freq: u64, channel_spacing: u64| RadioConfig {
+        module: 0,
+        freq,
+        channel_spacing,
+        channel: 10,
+        bandwidth_filter: 0,
+        fem: None,
+        auto_spacing: false,
+        fem_auto: false,
+    };
+
+    for (freq, channel_spacing) in [
+        // Overflows the channel frequency
+        (869_535_000, u64::MAX / 2),
+        (u64::MAX, 200_000),
+        // Between the sub-GHz and 2.4 GHz bands
+        (1_500_000_000, 200_000),
+        // Channel 10 lands past the end of the sub-GHz band
+        (1_019_000_000, 200_000),
+    ] {
+        let status = client
+            .set_config(config(freq, channel_spacing))
+            .await
+            .expect_err("bad frequency");
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    let after = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(after, before);
+
+    client
+        .set_config(config(2_405_000_000, 5_000_000))
+        .await
+        .expect("2.4 GHz config");
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_config_auto_spacing() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    client
+        .set_modulation(RadioModulation {
+            module: 0,
+            modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+                mcs: 3,
+                opt: 1,
+                pdt: 3,
+                tx_power: 10,
+                lfo: false,
+            })),
+        })
+        .await
+        .expect("set modulation");
+
+    client
+        .set_config(RadioConfig {
+            module: 0,
+            freq: 869_535_000,
+            channel_spacing: 0,
+            channel: 2,
+            bandwidth_filter: 0,
+            fem: None,
+            auto_spacing: true,
+            fem_auto: false,
+        })
+        .await
+        .expect("set config");
+
+    // Option 2 occupies 552 kHz
+    let config = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(config.channel_spacing, 575_000);
+    assert_eq!(config.channel, 2);
+    assert!(config.auto_spacing);
+
+    // The spacing follows a new modulation, option 3 occupies 276 kHz
+    client
+        .set_modulation(RadioModulation {
+            module: 0,
+            modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+                mcs: 3,
+                opt: 2,
+                pdt: 3,
+                tx_power: 10,
+                lfo: false,
+            })),
+        })
+        .await
+        .expect("set modulation");
+
+    let config = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(config.channel_spacing, 300_000);
+    assert!(config.auto_spacing);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_config_manual_fem_path() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    // Narrow at 869 MHz picks the SAW filter unless the path is held
+    let config = |fem, fem_auto| RadioConfig {
+        module: 0,
+        freq: 869_535_000,
+        channel_spacing: 200_000,
+        channel: 0,
+        bandwidth_filter: 0,
+        fem,
+        auto_spacing: false,
+        fem_auto,
+    };
+    let fem = ProtoFemPins {
+        flt_v1: true,
+        flt_v2: false,
+        flt_24: true,
+        ant_24: true,
+    };
+
+    client
+        .set_config(config(Some(fem), false))
+        .await
+        .expect("manual fem path");
+
+    let pins = FemPins {
+        flt_v1: true,
+        flt_v2: false,
+        flt_24: true,
+        ant_24: true,
+    };
+    {
+        let radio = radios[0].lock().unwrap();
+        assert_eq!(radio.fem_path(), FemPath::Manual(pins));
+        assert_eq!(radio.fem_pins(), pins);
+    }
+
+    let current = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(current.fem, Some(fem));
+    assert!(!current.fem_auto);
+
+    // Leaving it out keeps the held path
+    client
+        .set_config(config(None, false))
+        .await
+        .expect("unchanged fem path");
+    assert_eq!(radios[0].lock().unwrap().fem_path(), FemPath::Manual(pins));
+
+    client
+        .set_config(config(Some(fem), true))
+        .await
+        .expect("automatic fem path");
+    assert_eq!(radios[0].lock().unwrap().fem_pins(), FemPins::NARROW_868);
+
+    // A hard reset lets go of a held path too
+    client
+        .set_config(config(Some(fem), false))
+        .await
+        .expect("manual fem path");
+    client
+        .reset_module(ResetModuleRequest {
+            module: 0,
+            hard: true,
+        })
+        .await
+        .expect("hard reset");
+    assert_eq!(radios[0].lock().unwrap().fem_path(), FemPath::Auto);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_measure_phase_without_pmu() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let status = client
+        .measure_phase(ModuleRequest { module: 0 })
+        .await
+        .expect_err("host radio has no PMU");
+    assert_eq!(status.code(), tonic::Code::Unimplemented);
+
+    let status = client
+        .measure_phase(ModuleRequest { module: 7 })
+        .await
+        .expect_err("unknown module");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_capabilities() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = DeviceClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let capabilities = client
+        .get_capabilities(Empty {})
+        .await
+        .expect("capabilities")
+        .into_inner();
+
+    assert_eq!(capabilities.api_version, API_VERSION);
+    assert_ne!(capabilities.capabilities & CAPABILITY_OFDM, 0);
+    assert_ne!(capabilities.capabilities & CAPABILITY_SIMULATED, 0);
+    assert_eq!(capabilities.capabilities & CAPABILITY_RAW_CRC, 0);
+    // The host radio doesn't know its part
+    assert!(capabilities.part_number.is_empty());
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_qos_settings() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let settings = client
+        .get_qos(ModuleRequest { module: 0 })
+        .await
+        .expect("get qos")
+        .into_inner();
+    assert!(!settings.enabled);
+    assert!(!settings.adaptive_modulation_type);
+    assert_eq!(settings.qpsk_crossover(), ChannelQuality::Poor);
+    assert_eq!(settings.quality(), ChannelQuality::Excellent);
+
+    let request = QosSettings {
+        module: 0,
+        enabled: true,
+        per_threshold: 20,
+        adaptive_modulation_type: true,
+        qpsk_crossover: ChannelQuality::Fair as i32,
+        quality: ChannelQuality::Bad as i32,
+    };
+    let applied = client.set_qos(request).await.expect("set qos").into_inner();
+    assert!(applied.enabled);
+    assert_eq!(applied.per_threshold, 20);
+    assert!(applied.adaptive_modulation_type);
+    assert_eq!(applied.qpsk_crossover(), ChannelQuality::Fair);
+    // The quality is reported, not set
+    assert_eq!(applied.quality(), ChannelQuality::Excellent);
+
+    let status = client
+        .set_qos(QosSettings {
+            qpsk_crossover: 9,
+            ..request
+        })
+        .await
+        .expect_err("unknown crossover");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_select_best_channel() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let request = SelectChannelRequest {
+        module: 0,
+        channel_count: None,
+        dwell_ms: Some(0),
+    };
+
+    // The default configuration isn't on a frequency plan
+    let status = client
+        .select_best_channel(request)
+        .await
+        .expect_err("unknown band");
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    {
+        let mut radio = radios[0].lock().unwrap();
+        radio
+            .set_config(&EU_868.radio_config(0, BandwidthFilter::Wide).unwrap())
+            .unwrap();
+        for channel in 0..EU_868.channel_count {
+            radio.simulate_channel_energy(channel, -70);
+        }
+        radio.simulate_channel_energy(12, -90);
+        radio.simulate_channel_energy(17, -98);
+    }
+
+    let best = client
+        .select_best_channel(request)
+        .await
+        .expect("select best channel")
+        .into_inner();
+    assert_eq!(best.module, 0);
+    assert_eq!(best.channel, 17);
+    assert_eq!(best.rssi, -98);
+
+    let config = client
+        .get_config(ModuleRequest { module: 0 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(config.channel, 17);
+
+    // Only the first 16 channels
+    let best = client
+        .select_best_channel(SelectChannelRequest {
+            channel_count: Some(16),
+            ..request
+        })
+        .await
+        .expect("select best channel")
+        .into_inner();
+    assert_eq!(best.channel, 12);
+
+    let status = client
+        .select_best_channel(SelectChannelRequest {
+            channel_count: Some(0),
+            ..request
+        })
+        .await
+        .expect_err("no channels");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_stream_length_filter() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let status = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: Some(16),
+            max_len: Some(8),
+            decode: false,
+        })
+        .await
+        .expect_err("empty length range");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: Some(8),
+            max_len: Some(16),
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    for len in [4, 8, 12, 16, 32] {
+        client
+            .transmit(TransmitRequest {
+                module: 0,
+                frame: Some(RadioFrame {
+                    data: vec![len as u8; len],
+                }),
+                modulation: None,
+                seq: len as u32,
+            })
+            .await
+            .expect("transmit");
+    }
+
+    // Frames on both edges of the range are kept, the others are dropped
+    let mut lengths = Vec::new();
+    for _ in 0..3 {
+        let response = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+            .await
+            .expect("frame in range")
+            .expect("stream open")
+            .expect("receive response");
+        lengths.push(response.frame.expect("frame").data.len());
+    }
+    assert_eq!(lengths, [8, 12, 16]);
+
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), stream.next())
+            .await
+            .is_err(),
+        "frames outside the range are not streamed"
+    );
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_stream_decode_opt_in() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut streams = Vec::new();
+    for decode in [false, true] {
+        let stream = client
+            .receive_stream(ReceiveRequest {
+                module: 0,
+                timeout: 0,
+                min_len: None,
+                max_len: None,
+                decode,
+            })
+            .await
+            .expect("receive stream")
+            .into_inner();
+        streams.push(stream);
+    }
+
+    let payload = b"@@ CODED FRAME @@";
+    let mut packet = Packet::<2048>::new();
+    let mut frame = Frame::<2048>::new();
+    packet.header_mut().set_id(0x1234);
+    packet
+        .frame_mut()
+        .push_data(payload)
+        .expect("packet with data");
+    packet.build();
+    LdpcPacketCoder::new()
+        .encode(&packet, &mut frame)
+        .expect("encoded frame");
+
+    client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: frame.as_slice().to_vec(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit");
+
+    let mut decoded = Vec::new();
+    for stream in streams.iter_mut() {
+        let response = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+            .await
+            .expect("frame looped back")
+            .expect("stream open")
+            .expect("receive response");
+        decoded.push(response.decoded);
+    }
+
+    // Only the stream that asked for it pays for the LDPC decode
+    assert!(decoded[0].is_none());
+    let decoded = decoded[1].as_ref().expect("decoded packet");
+    assert!(decoded.valid);
+    assert_eq!(decoded.packet_id, 0x1234);
+    assert_eq!(&decoded.payload[..], payload);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_raw_crc_on_raw_frames_only() {
+    let mut config = CommdConfig::default();
+    config.transmit.raw_crc = true;
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: true,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    let mut packet = Packet::<2048>::new();
+    let mut coded = Frame::<2048>::new();
+    packet
+        .frame_mut()
+        .push_data(b"@@ CODED FRAME @@")
+        .expect("packet with data");
+    packet.build();
+    LdpcPacketCoder::new()
+        .encode(&packet, &mut coded)
+        .expect("encoded frame");
+
+    let raw = b"@@ RAW FRAME @@".to_vec();
+    for data in [raw.clone(), coded.as_slice().to_vec()] {
+        client
+            .transmit(TransmitRequest {
+                module: 0,
+                frame: Some(RadioFrame { data }),
+                modulation: None,
+                seq: 0,
+            })
+            .await
+            .expect("transmit");
+    }
+
+    // A raw frame with a trailer that doesn't match, e.g. from a bit error
+    let mut corrupted = raw.clone();
+    corrupted.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+    radios[0]
+        .lock()
+        .unwrap()
+        .transmit(&PlatformRadioFrame::new_from_slice(&corrupted).unwrap())
+        .expect("corrupted frame");
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(
+            tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+                .await
+                .expect("frame looped back")
+                .expect("stream open")
+                .expect("receive response"),
+        );
+    }
+
+    // The trailer is stripped from the raw frame
+    assert_eq!(received[0].frame.as_ref().expect("frame").data, raw);
+    assert_eq!(received[0].crc_valid, Some(true));
+
+    // The coded frame goes out unchanged and is checked by its own CRC
+    assert_eq!(
+        received[1].frame.as_ref().expect("frame").data,
+        coded.as_slice()
+    );
+    assert_eq!(received[1].crc_valid, None);
+    assert!(received[1].decoded.as_ref().expect("decoded packet").valid);
+
+    assert_eq!(received[2].frame.as_ref().expect("frame").data, corrupted);
+    assert_eq!(received[2].crc_valid, Some(false));
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_statistics_report_bringup() {
+    for (enabled, expected) in [(true, Bringup::Done), (false, Bringup::Skipped)] {
+        let mut config = CommdConfig::default();
+        config.worker.bringup = enabled;
+        let (cancel, addr, _radios) = spawn_server_with_config(config).await;
+        let mut device = DeviceClient::connect(format!("http://{}", addr))
+            .await
+            .expect("gRPC client");
+
+        let statistics = device
+            .get_statistics(ModuleRequest { module: 0 })
+            .await
+            .expect("statistics")
+            .into_inner();
+        assert_eq!(statistics.bringup(), expected);
+
+        cancel.cancel();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_statistics_count_irqs() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+    let mut device = DeviceClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    // The status is read with the event of the incoming frame
+    {
+        let mut radio = radios[0].lock().unwrap();
+        radio.simulate_irqs(
+            RadioInterruptMask::new()
+                .add_irq(RadioInterrupt::TransceiverError)
+                .add_irq(RadioInterrupt::IqIfSyncFail)
+                .build(),
+        );
+        radio
+            .transmit(&PlatformRadioFrame::new_from_slice(b"@@ INCOMING @@").unwrap())
+            .expect("incoming frame");
+    }
+    tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+        .await
+        .expect("frame received")
+        .expect("stream open")
+        .expect("receive response");
+
+    let irqs = device
+        .get_statistics(ModuleRequest { module: 0 })
+        .await
+        .expect("statistics")
+        .into_inner()
+        .irqs
+        .expect("irqs counted");
+    assert_eq!(irqs.transceiver_error, 1);
+    assert_eq!(irqs.iq_if_sync_fail, 1);
+    assert_eq!(irqs.battery_low, 0);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_rate_limit_drops_burst() {
+    const MAX_FRAME_RATE: u32 = 5;
+    const BURST: u64 = 30;
+
+    let mut config = CommdConfig::default();
+    config.receive.max_frame_rate = Some(MAX_FRAME_RATE);
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut device = DeviceClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    {
+        let mut radio = radios[0].lock().unwrap();
+        for i in 0..BURST {
+            radio
+                .transmit(&PlatformRadioFrame::new_from_slice(&i.to_le_bytes()).unwrap())
+                .expect("incoming frame");
+        }
+    }
+
+    // Commands get through while the burst is read
+    tokio::time::timeout(
+        RECEIVE_TIMEOUT,
+        client.get_config(ModuleRequest { module: 0 }),
+    )
+    .await
+    .expect("responsive during burst")
+    .expect("get config");
+
+    let stats = tokio::time::timeout(RECEIVE_TIMEOUT, async {
+        loop {
+            let stats = device
+                .get_statistics(ModuleRequest { module: 0 })
+                .await
+                .expect("statistics")
+                .into_inner();
+            if stats.rx_packets == BURST {
+                break stats;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("burst counted");
+    assert_eq!(stats.rx_rate_dropped, BURST - MAX_FRAME_RATE as u64);
+
+    for _ in 0..MAX_FRAME_RATE {
+        tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+            .await
+            .expect("frame received")
+            .expect("stream open")
+            .expect("receive response");
+    }
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), stream.next())
+            .await
+            .is_err()
+    );
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hardware_fcs_falls_back_to_raw_crc() {
+    let mut config = CommdConfig::default();
+    config.transmit.hardware_fcs = true;
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut device = DeviceClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    // The simulated radio has no FCS
+    let statistics = device
+        .get_statistics(ModuleRequest { module: 0 })
+        .await
+        .expect("statistics")
+        .into_inner();
+    assert_eq!(statistics.integrity(), FrameIntegrity::SoftwareCrc);
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    let raw = b"@@ RAW FRAME @@".to_vec();
+    client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame { data: raw.clone() }),
+            modulation: None,
+            seq: 0,
+        })
+        .await
+        .expect("transmit");
+
+    let mut corrupted = raw.clone();
+    corrupted.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+    radios[0]
+        .lock()
+        .unwrap()
+        .transmit(&PlatformRadioFrame::new_from_slice(&corrupted).unwrap())
+        .expect("corrupted frame");
+
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        received.push(
+            tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+                .await
+                .expect("frame looped back")
+                .expect("stream open")
+                .expect("receive response"),
+        );
+    }
+
+    assert_eq!(received[0].frame.as_ref().expect("frame").data, raw);
+    assert_eq!(received[0].crc_valid, Some(true));
+    assert_eq!(received[1].crc_valid, Some(false));
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_capture_keeps_failed_decodes() {
+    let mut config = CommdConfig::default();
+    config.capture.frames = 4;
+    config.capture.failures_only = true;
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut device = DeviceClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    let mut packet = Packet::<2048>::new();
+    let mut coded = Frame::<2048>::new();
+    packet
+        .frame_mut()
+        .push_data(b"@@ CODED FRAME @@")
+        .expect("packet with data");
+    packet.build();
+    LdpcPacketCoder::new()
+        .encode(&packet, &mut coded)
+        .expect("encoded frame");
+
+    // Too many bit errors for the LDPC decoder
+    let mut corrupted = coded.as_slice().to_vec();
+    corrupted.iter_mut().for_each(|byte| *byte ^= 0x5A);
+
+    for data in [coded.as_slice(), b"@@ RAW FRAME @@", &corrupted] {
+        radios[0]
+            .lock()
+            .unwrap()
+            .transmit(&PlatformRadioFrame::new_from_slice(data).unwrap())
+            .expect("frame");
+    }
+
+    for _ in 0..3 {
+        tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+            .await
+            .expect("frame looped back")
+            .expect("stream open")
+            .expect("receive response");
+    }
+
+    let capture = device
+        .get_receive_capture(ModuleRequest { module: 0 })
+        .await
+        .expect("capture")
+        .into_inner();
+    assert_eq!(capture.frames.len(), 1);
+    assert_eq!(capture.frames[0].data, corrupted);
+    assert_eq!(capture.frames[0].valid, Some(false));
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_config_channel_conflict() {
+    let config = |module, channel| RadioConfig {
+        module,
+        freq: 869_535_000,
+        channel_spacing: 200_000,
+        channel,
+        bandwidth_filter: 0,
+        fem: None,
+        auto_spacing: false,
+        fem_auto: false,
+    };
+
+    let mut reject = CommdConfig::default();
+    reject.channel.conflict = ChannelConflict::Reject;
+
+    let (cancel, addr, _radios) = spawn_server_with_config(reject.clone()).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    client.set_config(config(0, 3)).await.expect("set config");
+
+    let status = client
+        .set_config(config(1, 3))
+        .await
+        .expect_err("same carrier as module 0");
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+    client.set_config(config(1, 4)).await.expect("set config");
+    cancel.cancel();
+
+    // Only warned about
+    let (cancel, addr, _radios) = spawn_server_with_config(CommdConfig::default()).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    client.set_config(config(0, 3)).await.expect("set config");
+    client.set_config(config(1, 3)).await.expect("set config");
+    cancel.cancel();
+
+    // Diversity puts both modules on one channel on purpose
+    reject.receive.mode = ReceiveMode::Diversity;
+    let (cancel, addr, _radios) = spawn_server_with_config(reject).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    client.set_config(config(0, 3)).await.expect("set config");
+    client.set_config(config(1, 3)).await.expect("set config");
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transmit_reports_applied_tx_power() {
+    let mut config = CommdConfig::default();
+    config.transmit.verify_tx_power = true;
+
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    radios[0]
+        .lock()
+        .unwrap()
+        .set_power_limit(TxPowerLimit::new(14, 20))
+        .expect("power limit");
+
+    // The per-frame power is clamped, the response has the one read back
+    let response = client
+        .transmit(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: b"tx power".to_vec(),
+            }),
+            modulation: Some(RadioModulation {
+                module: 0,
+                modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+                    mcs: 3,
+                    opt: 1,
+                    pdt: 3,
+                    tx_power: 20,
+                    lfo: false,
+                })),
+            }),
+            seq: 0,
+        })
+        .await
+        .expect("transmit")
+        .into_inner();
+    assert_eq!(response.tx_power, 14);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_modulation_reports_tx_power_clamp() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    radios[0]
+        .lock()
+        .unwrap()
+        .set_power_limit(TxPowerLimit::new(14, 20))
+        .expect("power limit");
+
+    let request = |tx_power| RadioModulation {
+        module: 0,
+        modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+            mcs: 3,
+            opt: 1,
+            pdt: 3,
+            tx_power,
+            lfo: false,
+        })),
+    };
+
+    let response = client
+        .set_modulation(request(12))
+        .await
+        .expect("set modulation")
+        .into_inner();
+    assert!(!response.tx_power_clamped);
+
+    let response = client
+        .set_modulation(request(20))
+        .await
+        .expect("set modulation")
+        .into_inner();
+    assert!(response.tx_power_clamped);
+    match response.applied.and_then(|applied| applied.modulation) {
+        Some(Modulation::Ofdm(ofdm)) => assert_eq!(ofdm.tx_power, 14),
+        other => panic!("unexpected modulation {other:?}"),
+    }
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_apply_config() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let config = RadioConfig {
+        module: 1,
+        freq: 869_535_000,
+        channel_spacing: 200_000,
+        channel: 6,
+        bandwidth_filter: 0,
+        fem: None,
+        auto_spacing: false,
+        fem_auto: false,
+    };
+    let modulation = RadioModulation {
+        module: 0,
+        modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+            mcs: 2,
+            opt: 2,
+            pdt: 3,
+            tx_power: 10,
+            lfo: false,
+        })),
+    };
+    let qos = QosSettings {
+        module: 0,
+        enabled: true,
+        per_threshold: 15,
+        adaptive_modulation_type: false,
+        qpsk_crossover: ChannelQuality::Poor as i32,
+        quality: ChannelQuality::Excellent as i32,
+    };
+
+    let response = client
+        .apply_config(ApplyConfigRequest {
+            config: Some(config),
+            modulation: Some(modulation),
+            qos: Some(qos),
+        })
+        .await
+        .expect("apply config")
+        .into_inner();
+    assert!(!response.tx_power_clamped);
+    assert_eq!(response.qos.map(|qos| qos.per_threshold), Some(15));
+
+    let applied = client
+        .get_config(ModuleRequest { module: 1 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(applied.channel, 6);
+    let applied = client
+        .get_modulation(ModuleRequest { module: 1 })
+        .await
+        .expect("get modulation")
+        .into_inner();
+    assert_eq!(applied.modulation, modulation.modulation);
+    assert!(
+        client
+            .get_qos(ModuleRequest { module: 1 })
+            .await
+            .expect("get qos")
+            .into_inner()
+            .enabled
+    );
+
+    // A bad part leaves the module as it was
+    let status = client
+        .apply_config(ApplyConfigRequest {
+            config: Some(RadioConfig {
+                channel: 9,
+                ..config
+            }),
+            modulation: Some(modulation),
+            qos: Some(QosSettings {
+                qpsk_crossover: 9,
+                ..qos
+            }),
+        })
+        .await
+        .expect_err("unknown crossover");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    let applied = client
+        .get_config(ModuleRequest { module: 1 })
+        .await
+        .expect("get config")
+        .into_inner();
+    assert_eq!(applied.channel, 6);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_continues_after_overrun() {
+    let (cancel, addr, radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    radios[0].lock().unwrap().simulate_rx_overrun();
+
+    for data in [b"overrun".to_vec(), b"after overrun".to_vec()] {
+        client
+            .transmit(TransmitRequest {
+                module: 0,
+                frame: Some(RadioFrame { data }),
+                modulation: None,
+                seq: 0,
+            })
+            .await
+            .expect("transmit");
+    }
+
+    // The overrun frame is dropped and the loop keeps receiving
+    let received = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+        .await
+        .expect("frame after the overrun")
+        .expect("stream open")
+        .expect("receive response");
+    assert_eq!(received.frame.expect("frame").data, b"after overrun");
+    assert_eq!(radios[0].lock().unwrap().rx_flushes(), 1);
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reset_module() {
+    let mut config = CommdConfig::default();
+    config.tx_power.band_09 = Some(14);
+    config.tx_power.band_24 = Some(14);
+    config.transmit.regulatory_profile = RegulatoryProfile::Etsi;
+
+    let (cancel, addr, radios) = spawn_server_with_config(config).await;
+
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    client
+        .set_config(RadioConfig {
+            module: 0,
+            freq: 869_535_000,
+            channel_spacing: 200_000,
+            channel: 3,
+            bandwidth_filter: 0,
+            fem: None,
+            auto_spacing: false,
+            fem_auto: false,
+        })
+        .await
+        .expect("set config");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    for hard in [false, true] {
+        let response = client
+            .reset_module(ResetModuleRequest { module: 0, hard })
+            .await
+            .expect("reset module")
+            .into_inner();
+
+        // Only a hard reset drops the configuration
+        let channel = response.config.expect("config").channel;
+        assert_eq!(channel == 3, !hard);
+
+        // The configured power ceiling survives both
+        let response = client
+            .set_modulation(RadioModulation {
+                module: 0,
+                modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+                    mcs: 3,
+                    opt: 1,
+                    pdt: 3,
+                    tx_power: 20,
+                    lfo: false,
+                })),
+            })
+            .await
+            .expect("set modulation")
+            .into_inner();
+        assert!(response.tx_power_clamped, "hard: {hard}");
+        assert_eq!(
+            radios[0].lock().unwrap().lbt(),
+            RegulatoryProfile::Etsi.lbt(),
+            "hard: {hard}"
+        );
+
+        let payload = vec![hard as u8; 12];
+        client
+            .transmit(TransmitRequest {
+                module: 0,
+                frame: Some(RadioFrame {
+                    data: payload.clone(),
+                }),
+                modulation: None,
+                seq: 0,
+            })
+            .await
+            .expect("transmit");
+
+        let received = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+            .await
+            .expect("frame received after the reset")
+            .expect("stream open")
+            .expect("receive response");
+        assert_eq!(received.frame.expect("frame").data, payload);
+    }
+
+    client
+        .reset_module(ResetModuleRequest {
+            module: 7,
+            hard: false,
+        })
+        .await
+        .expect_err("out of range module");
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_receive_tagged_with_modulation() {
+    let (cancel, addr, _radios) = spawn_server(None).await;
+    let mut client = RadioClient::connect(format!("http://{}", addr))
+        .await
+        .expect("gRPC client");
+
+    let mut stream = client
+        .receive_stream(ReceiveRequest {
+            module: 0,
+            timeout: 0,
+            min_len: None,
+            max_len: None,
+            decode: false,
+        })
+        .await
+        .expect("receive stream")
+        .into_inner();
+
+    for mcs in [6, 1] {
+        let modulation = RadioModulation {
+            module: 0,
+            modulation: Some(Modulation::Ofdm(RadioModulationOfdm {
+                mcs,
+                opt: 1,
+                pdt: 3,
+                tx_power: 10,
+                lfo: false,
+            })),
+        };
+        client
+            .set_modulation(modulation)
+            .await
+            .expect("set modulation");
+
+        client
+            .transmit(TransmitRequest {
+                module: 0,
+                frame: Some(RadioFrame {
+                    data: vec![mcs as u8; 16],
+                }),
+                modulation: None,
+                seq: 0,
+            })
+            .await
+            .expect("transmit");
+
+        let received = tokio::time::timeout(RECEIVE_TIMEOUT, stream.next())
+            .await
+            .expect("frame looped back")
+            .expect("stream open")
+            .expect("receive response");
+
+        assert_eq!(received.quality(), ChannelQuality::Excellent);
+        assert_eq!(received.modulation, Some(modulation));
+        assert_eq!(received.frame.expect("frame").data, vec![mcs as u8; 16]);
+    }
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transmit_times_out_on_hung_worker() {
+    use crate::grpc_server::kaonic::radio_server::Radio as _;
+
+    let mut config = CommdConfig::default();
+    config.grpc.command_timeout_ms = 200;
+
+    let cancel = CancellationToken::new();
+    let (client_send, _client_recv) = mpsc::channel(16);
+    let radio_server = RadioServer::new(
+        client_send,
+        cancel.clone(),
+        "test".to_string(),
+        RADIO_FRAME_SIZE,
+        config.clone(),
+    )
+    .expect("radio server");
+
+    // Takes the first job and never answers, the second one fills the queue
+    let (queue, _jobs) = mpsc::channel(1);
+    let radio_service = RadioService::new(
+        radio_server.radios(),
+        vec![queue],
+        radio_server.qos(),
+        radio_server.events(),
+        radio_server.transmits(),
+        &config,
+        cancel.clone(),
+    );
+
+    let request = || {
+        tonic::Request::new(TransmitRequest {
+            module: 0,
+            frame: Some(RadioFrame {
+                data: b"never sent".to_vec(),
+            }),
+            modulation: None,
+            seq: 0,
+        })
+    };
+
+    for _ in 0..2 {
+        let started = std::time::Instant::now();
+        let status = radio_service
+            .transmit(request())
+            .await
+            .expect_err("hung worker");
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+        assert!(started.elapsed() < RECEIVE_TIMEOUT);
+    }
+
+    cancel.cancel();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_config_times_out_on_busy_module() {
+    use crate::grpc_server::kaonic::radio_server::Radio as _;
+
+    let mut config = CommdConfig::default();
+    config.grpc.command_timeout_ms = 200;
+
+    let cancel = CancellationToken::new();
+    let (client_send, _client_recv) = mpsc::channel(16);
+    let radio_server = RadioServer::new(
+        client_send,
+        cancel.clone(),
+        "test".to_string(),
+        RADIO_FRAME_SIZE,
+        config.clone(),
+    )
+    .expect("radio server");
+    let radios = radio_server.radios();
+    let radio_service = RadioService::new(
+        radios.clone(),
+        radio_server.transmit_queues(),
+        radio_server.qos(),
+        radio_server.events(),
+        radio_server.transmits(),
+        &config,
+        cancel.clone(),
+    );
+
+    // Stands in for a transmission that doesn't let go of the module
+    let (busy, release) = std::sync::mpsc::channel::<()>();
+    let radio = radios[0].clone();
+    let holder = std::thread::spawn(move || {
+        let _radio = radio.lock().unwrap();
+        let _ = release.recv();
+    });
+
+    let started = std::time::Instant::now();
+    let status = radio_service
+        .set_config(tonic::Request::new(RadioConfig {
+            module: 0,
+            freq: 869_535_000,
+            channel_spacing: 200_000,
+            channel: 10,
+            bandwidth_filter: 0,
+            fem: None,
+            auto_spacing: false,
+            fem_auto: false,
+        }))
+        .await
+        .expect_err("busy module");
+    assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+    let status = radio_service
+        .reset_module(tonic::Request::new(ResetModuleRequest {
+            module: 0,
+            hard: false,
+        }))
+        .await
+        .expect_err("busy module");
+    assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+    assert!(started.elapsed() < RECEIVE_TIMEOUT);
+
+    drop(busy);
+    holder.join().unwrap();
+    cancel.cancel();
+}
@@ -1,27 +1,77 @@
+use clap::Parser;
 use kaonic_ctrl::{
     protocol::{MessageCoder, RADIO_FRAME_SIZE},
     server::Server,
 };
+use std::{net::SocketAddr, time::Duration};
+
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use crate::config::{CONFIG_PATH, CommdConfig};
 use crate::grpc_server::{DeviceServer, DeviceService, GrpcRadioServer, RadioService};
 use crate::radio_server::RadioServer;
 
+mod beacon;
+mod capabilities;
+mod capture;
+mod channel;
+mod config;
+mod decoder;
+mod diversity;
+mod events;
 mod grpc_server;
+mod heartbeat;
+mod logging;
+mod metrics;
+mod power_control;
+mod qos;
 mod radio_server;
+mod raw_crc;
+mod rx_rate;
+mod shutdown;
+mod tap;
+mod thermal;
+mod tx_queue;
+mod worker;
+
+#[cfg(all(test, feature = "machine-host"))]
+mod loopback_tests;
 
 const SERVER_MTU: usize = 1400;
 const SERVER_SEGMENTS: usize = 5;
 
+/// How long an orderly shutdown may take before the process exits anyway
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 const UDP_ADDR: &str = "0.0.0.0:9090";
 const GRPC_ADDR: &str = "0.0.0.0:50051";
 
+#[derive(Parser, Debug)]
+#[command(name = "kaonic-commd", version)]
+#[command(about = "Kaonic communication daemon")]
+struct Args {
+    /// Serve Prometheus metrics on this address, metrics are off without it
+    #[arg(long = "metrics", value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+
+    /// Where the log records go, stderr or syslog
+    #[arg(long = "log", env = logging::LOG_TARGET_VAR, default_value = "stderr")]
+    log_target: logging::LogTarget,
+
+    /// Write received frames to a named pipe or file, `-` for stdout
+    #[arg(long = "rx-tap", value_name = "PATH")]
+    rx_tap: Option<tap::TapTarget>,
+
+    /// Format of the receive tap, hex or raw
+    #[arg(long, default_value = "hex", requires = "rx_tap")]
+    rx_tap_format: tap::TapFormat,
+}
+
 #[tokio::main(flavor = "multi_thread", worker_threads = 12)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Debug)
-        .init();
+    let args = Args::parse();
+    logging::init(args.log_target);
 
     let version = env!("CARGO_PKG_VERSION");
     let udp_addr = UDP_ADDR.parse().expect("valid UDP listen address");
@@ -29,47 +79,93 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     log::info!("Kaonic Communication Daemon: v{}", version);
 
+    let config = CommdConfig::load(CONFIG_PATH);
+    let max_pending = config.network.max_pending;
+    let id_namespace = config.network.id_namespace();
+    // Reported to clients as the largest payload they can hand over per frame
+    let mtu = if config.transmit.raw_crc || config.transmit.hardware_fcs {
+        RADIO_FRAME_SIZE - raw_crc::CRC_LEN
+    } else {
+        RADIO_FRAME_SIZE
+    };
+    let grpc_config = config.grpc.clone();
+
     let cancel = CancellationToken::new();
 
     let (client_send, client_recv) = mpsc::channel(16);
 
     let serial = read_serial();
-    let radio_server = RadioServer::new(
+    let mut radio_server = RadioServer::new(
         client_send,
         cancel.clone(),
         serial.clone(),
-        RADIO_FRAME_SIZE,
+        mtu,
+        config.clone(),
     )
     .expect("radio server");
 
     // Capture shared state before the UDP server takes ownership of radio_server
     let module_count = radio_server.module_count();
     let shared_radios = radio_server.radios();
+    let transmit_queues = radio_server.transmit_queues();
+    let link_qos = radio_server.qos();
+    let metrics_qos = link_qos.clone();
     let shared_stats = radio_server.stats();
-    let rx_sender = radio_server.rx_sender();
-    let tx_sender = radio_server.tx_sender();
+    let events = radio_server.events();
+    let transmits = radio_server.transmits();
+    let tap_events = events.subscribe();
+    let peers = radio_server.peers();
+    let capabilities = radio_server.capabilities();
+    let coding = radio_server.coding();
+    let integrity = radio_server.integrity();
+    let capture = radio_server.capture();
+    let workers = radio_server.take_workers();
 
     // Start UDP server
-    let server = Server::listen(
+    let mut server = Server::listen(
         udp_addr,
         MessageCoder::<SERVER_MTU, SERVER_SEGMENTS>::new(),
         radio_server,
         client_recv,
+        max_pending,
+        id_namespace,
         cancel.clone(),
     )
     .await
     .expect("UDP server");
 
     // Start gRPC server sharing the same radio hardware
-    let device_service =
-        DeviceService::new(module_count, serial, RADIO_FRAME_SIZE as u32, shared_stats);
-    let radio_service = RadioService::new(shared_radios, rx_sender, tx_sender);
+    let device_service = DeviceService::new(
+        module_count,
+        serial,
+        mtu as u32,
+        shared_stats.clone(),
+        peers,
+        capabilities,
+    )
+    .with_capture(capture);
+    let radio_service = RadioService::new(
+        shared_radios.clone(),
+        transmit_queues,
+        link_qos,
+        events,
+        transmits,
+        &config,
+        cancel.clone(),
+    )
+    .with_coding(coding)
+    .with_integrity(integrity);
+    let keepalive_interval = (grpc_config.keepalive_interval_ms > 0)
+        .then(|| Duration::from_millis(grpc_config.keepalive_interval_ms));
+    let keepalive_timeout = Duration::from_millis(grpc_config.keepalive_timeout_ms);
 
-    {
+    let grpc_server = {
         let cancel = cancel.clone();
         tokio::spawn(async move {
             log::info!("gRPC server listening on {}", grpc_addr);
             if let Err(e) = tonic::transport::Server::builder()
+                .http2_keepalive_interval(keepalive_interval)
+                .http2_keepalive_timeout(Some(keepalive_timeout))
                 .add_service(DeviceServer::new(device_service))
                 .add_service(GrpcRadioServer::new(radio_service))
                 .serve_with_shutdown(grpc_addr, cancel.cancelled())
@@ -77,38 +173,64 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             {
                 log::error!("gRPC server error: {}", e);
             }
-        });
-    }
+        })
+    };
 
     log::info!("server started");
 
-    let _ = tokio::spawn(async move {
-        // SIGTERM (Unix only)
-        #[cfg(unix)]
-        let terminate = async {
-            use tokio::signal::unix::{SignalKind, signal};
-            let mut sigterm =
-                signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
-            sigterm.recv().await;
-        };
-
-        #[cfg(not(unix))]
-        let terminate = std::future::pending::<()>();
-
-        tokio::select! {
-            _ = tokio::signal::ctrl_c() => {
-                log::warn!("Stopping by Ctrl+C");
-                cancel.cancel();
-            },
-            _ = terminate => {
-                log::warn!("Stopping by terminate");
-                cancel.cancel();
-            },
-        }
+    let mut servers = server.take_tasks();
+    servers.push(grpc_server);
+
+    if let Some(addr) = args.metrics_addr {
+        let listener = metrics::bind(addr).await.expect("metrics listener");
+        servers.push(metrics::spawn_metrics_server(
+            listener,
+            shared_stats.clone(),
+            metrics_qos,
+            cancel.clone(),
+        ));
+    }
+
+    if let Some(target) = args.rx_tap {
+        servers.push(tap::spawn_rx_tap(
+            target,
+            args.rx_tap_format,
+            tap_events,
+            cancel.clone(),
+        ));
+    }
 
-        log::info!("Shutdown signal received. Cancelling tasks...");
-    })
+    // SIGTERM (Unix only)
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut sigterm =
+            signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+        sigterm.recv().await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => log::warn!("Stopping by Ctrl+C"),
+        _ = terminate => log::warn!("Stopping by terminate"),
+    }
+
+    log::info!("Shutdown signal received. Stopping servers and radio workers...");
+
+    let clean = shutdown::shutdown(
+        cancel,
+        servers,
+        workers,
+        &shared_radios,
+        &shared_stats,
+        SHUTDOWN_TIMEOUT,
+    )
     .await;
+    if !clean {
+        log::warn!("shutdown timed out, exiting anyway");
+    }
 
     Ok(())
 }
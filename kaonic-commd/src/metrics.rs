@@ -0,0 +1,259 @@
+//! Prometheus export of the module statistics.
+//!
+//! A bare HTTP/1.1 listener answering `GET /metrics` with the counters the
+//! radio server already keeps, in the Prometheus text format. Throughput is
+//! left to the scraper, `rate()` over the byte counters gives it.
+
+use std::{fmt::Write, net::SocketAddr, sync::atomic::Ordering, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{qos::SharedLinkQos, radio_server::SharedModuleStats};
+
+/// Scrapers holding a connection open longer than this are dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MAX_REQUEST_LEN: usize = 4096;
+
+type Counter = fn(&SharedModuleStats) -> u64;
+
+/// Starts serving the metrics of every module on `listener` until `cancel`
+pub fn spawn_metrics_server(
+    listener: TcpListener,
+    stats: Vec<SharedModuleStats>,
+    qos: Vec<SharedLinkQos>,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::warn!("metrics accept error: {e}");
+                        continue;
+                    }
+                },
+                _ = cancel.cancelled() => break,
+            };
+
+            let body = render(&stats, &qos);
+            tokio::spawn(async move {
+                match tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, body)).await {
+                    Ok(Err(e)) => log::debug!("metrics request from {peer} failed: {e}"),
+                    Err(_) => log::debug!("metrics request from {peer} timed out"),
+                    Ok(Ok(())) => {}
+                }
+            });
+        }
+    })
+}
+
+/// Binds the metrics listener on `addr`
+pub async fn bind(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("metrics listening on {}", listener.local_addr()?);
+    Ok(listener)
+}
+
+async fn respond(mut stream: TcpStream, body: String) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 512];
+
+    // Only the request line matters, the headers are read to keep clients happy
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let len = stream.read(&mut buf).await?;
+        if len == 0 || request.len() + len > MAX_REQUEST_LEN {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..len]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4", body),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Metric families, each with one sample per module
+fn render(stats: &[SharedModuleStats], qos: &[SharedLinkQos]) -> String {
+    let mut out = String::new();
+
+    let counters: [(&str, &str, Counter); 9] = [
+        ("rx_frames_total", "Frames received", |s| {
+            s.rx_packets.load(Ordering::Relaxed)
+        }),
+        ("tx_frames_total", "Frames transmitted", |s| {
+            s.tx_packets.load(Ordering::Relaxed)
+        }),
+        ("rx_bytes_total", "Bytes received", |s| {
+            s.rx_bytes.load(Ordering::Relaxed)
+        }),
+        ("tx_bytes_total", "Bytes transmitted", |s| {
+            s.tx_bytes.load(Ordering::Relaxed)
+        }),
+        ("rx_errors_total", "Receive errors", |s| {
+            s.rx_errors.load(Ordering::Relaxed)
+        }),
+        ("tx_errors_total", "Transmissions that failed", |s| {
+            s.tx_errors.load(Ordering::Relaxed)
+        }),
+        (
+            "tx_retries_total",
+            "Transmit attempts beyond the first one",
+            |s| s.tx_retries.load(Ordering::Relaxed),
+        ),
+        ("rx_overruns_total", "Receive buffer overruns", |s| {
+            s.rx_overruns.load(Ordering::Relaxed)
+        }),
+        (
+            "rx_rate_dropped_total",
+            "Frames over the receive rate limit",
+            |s| s.rx_rate_dropped.load(Ordering::Relaxed),
+        ),
+    ];
+
+    for (name, help, value) in counters {
+        family(
+            &mut out,
+            name,
+            help,
+            "counter",
+            stats.iter().map(|s| Some(value(s) as f64)),
+        );
+    }
+
+    family(
+        &mut out,
+        "rssi_dbm",
+        "RSSI of the last received frame",
+        "gauge",
+        stats
+            .iter()
+            .map(|s| s.last_rssi.lock().unwrap().map(f64::from)),
+    );
+    family(
+        &mut out,
+        "channel_quality",
+        "Channel quality assessment, 0 excellent to 4 bad",
+        "gauge",
+        qos.iter()
+            .map(|q| Some(q.lock().unwrap().quality() as u8 as f64)),
+    );
+    family(
+        &mut out,
+        "temperature_celsius",
+        "Transceiver temperature",
+        "gauge",
+        stats
+            .iter()
+            .map(|s| s.temperature.lock().unwrap().map(f64::from)),
+    );
+    family(
+        &mut out,
+        "battery_low",
+        "Supply voltage is below the battery monitor threshold",
+        "gauge",
+        stats
+            .iter()
+            .map(|s| Some(s.battery_low.load(Ordering::Relaxed) as u8 as f64)),
+    );
+    family(
+        &mut out,
+        "tx_in_flight",
+        "Transmit requests currently being handled",
+        "gauge",
+        stats
+            .iter()
+            .map(|s| Some(s.tx_in_flight.load(Ordering::Relaxed) as f64)),
+    );
+
+    out
+}
+
+/// Writes one metric family, modules without a value are left out
+fn family(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    values: impl Iterator<Item = Option<f64>>,
+) {
+    let _ = writeln!(out, "# HELP kaonic_{name} {help}");
+    let _ = writeln!(out, "# TYPE kaonic_{name} {kind}");
+    for (module, value) in values.enumerate() {
+        if let Some(value) = value {
+            let _ = writeln!(out, "kaonic_{name}{{module=\"{module}\"}} {value}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scrape_metrics() {
+        let stats: Vec<SharedModuleStats> = vec![Arc::default(), Arc::default()];
+        stats[1].tx_packets.store(3, Ordering::Relaxed);
+        *stats[0].last_rssi.lock().unwrap() = Some(-72);
+
+        let listener = bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let cancel = CancellationToken::new();
+        let server = spawn_metrics_server(listener, stats, Vec::new(), cancel.clone());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        for name in [
+            "kaonic_rx_frames_total",
+            "kaonic_tx_frames_total",
+            "kaonic_rx_bytes_total",
+            "kaonic_tx_bytes_total",
+            "kaonic_rx_errors_total",
+            "kaonic_tx_errors_total",
+            "kaonic_tx_retries_total",
+            "kaonic_rssi_dbm",
+            "kaonic_channel_quality",
+        ] {
+            assert!(
+                response.contains(&format!("# TYPE {name} ")),
+                "{name} missing"
+            );
+        }
+        assert!(response.contains("kaonic_tx_frames_total{module=\"1\"} 3\n"));
+        assert!(response.contains("kaonic_rssi_dbm{module=\"0\"} -72\n"));
+        assert!(!response.contains("kaonic_rssi_dbm{module=\"1\"}"));
+
+        cancel.cancel();
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+use kaonic_radio::power::TxPowerLimit;
+use radio_common::{Hertz, Modulation};
+
+/// Largest tx power change applied per report, the next report shows its effect
+const POWER_STEP: u8 = 1;
+
+/// Closed-loop TX power control driven by the RSSI peers report for our frames
+///
+/// The power is lowered while the weakest peer still hears us above the target
+/// and raised once it falls below, so the link runs at the least power that
+/// keeps every peer at the target.
+#[derive(Debug, Clone, Copy)]
+pub struct PowerControl {
+    target_rssi: i8,
+    hysteresis_db: u8,
+    limit: TxPowerLimit,
+}
+
+impl PowerControl {
+    /// Raises the power up to the ceiling of `limit` at most
+    pub fn new(target_rssi: i8, hysteresis_db: u8, limit: TxPowerLimit) -> Self {
+        Self {
+            target_rssi,
+            hysteresis_db,
+            limit,
+        }
+    }
+
+    /// Returns the modulation to apply at `freq` for a peer hearing us at
+    /// `reported_rssi`
+    pub fn update(
+        &self,
+        reported_rssi: i8,
+        current: &Modulation,
+        freq: Hertz,
+    ) -> Option<Modulation> {
+        let ceiling = self.limit.ceiling(freq);
+
+        let error = i16::from(reported_rssi) - i16::from(self.target_rssi);
+        let hysteresis = i16::from(self.hysteresis_db);
+
+        let tx_power = current.tx_power();
+        let adjusted = if error > hysteresis {
+            tx_power.saturating_sub(POWER_STEP)
+        } else if error < -hysteresis {
+            tx_power
+                .saturating_add(POWER_STEP)
+                .min(ceiling.max(tx_power))
+        } else {
+            tx_power
+        };
+
+        if adjusted == tx_power || matches!(current, Modulation::Off | Modulation::Fsk) {
+            return None;
+        }
+
+        let mut modulation = *current;
+        modulation.set_tx_power(adjusted);
+        Some(modulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use radio_common::modulation::OfdmModulation;
+
+    use super::*;
+
+    #[test]
+    fn test_reported_rssi_steers_tx_power() {
+        let control = PowerControl::new(-75, 3, TxPowerLimit::new(20, 20));
+        let freq = Hertz::from_mhz(869);
+        let modulation = Modulation::Ofdm(OfdmModulation {
+            tx_power: 20,
+            ..Default::default()
+        });
+
+        // Peer hears us far above the target
+        let lowered = control.update(-50, &modulation, freq).expect("lowered");
+        assert_eq!(lowered.tx_power(), 19);
+
+        // Within hysteresis
+        assert!(control.update(-73, &lowered, freq).is_none());
+
+        let raised = control.update(-90, &lowered, freq).expect("raised");
+        assert_eq!(raised.tx_power(), 20);
+
+        // Never above the band ceiling
+        assert!(control.update(-90, &raised, freq).is_none());
+        assert!(control.update(-90, &Modulation::Fsk, freq).is_none());
+    }
+}
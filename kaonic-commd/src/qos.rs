@@ -0,0 +1,287 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use kaonic_net::{coder::LinkCoding, packet::PacketType};
+use kaonic_qos::{ChannelQuality, ModulationScheme, QoSManager, QoSSettings, StdClock};
+use radio_common::modulation::Modulation;
+use tokio::sync::watch;
+
+use crate::{config::QosConfig, decoder::PacketDecoder, heartbeat};
+
+/// Adaptive modulation of one module
+///
+/// Received kaonic-net frames are run through the LDPC decoder and the
+/// outcome feeds the packet error rate of a [`QoSManager`]. Raw frames
+/// aren't coded and don't count.
+pub struct LinkQos {
+    config: QosConfig,
+    manager: QoSManager<StdClock>,
+    decoder: Box<PacketDecoder>,
+    coding: watch::Receiver<LinkCoding>,
+    heartbeat: bool,
+}
+
+/// Shared between the receive loop of a module and the gRPC settings calls
+pub type SharedLinkQos = Arc<Mutex<LinkQos>>;
+
+impl LinkQos {
+    /// Starts from the modulation family and tx power the radio is using,
+    /// frames are decoded with the current `coding` within `iteration_budget`
+    pub fn new(
+        config: &QosConfig,
+        modulation: &Modulation,
+        coding: watch::Receiver<LinkCoding>,
+        iteration_budget: Option<usize>,
+    ) -> Self {
+        let mut manager = QoSManager::new()
+            .with_per_threshold(config.per_threshold)
+            .enable_adaptive_modulation_type(config.adaptive_modulation_type)
+            .with_qpsk_crossover(config.qpsk_crossover)
+            .with_modulation_debounce(Duration::from_millis(config.modulation_debounce_ms))
+            .with_modulation_bounds(config.modulation_bounds());
+
+        match modulation {
+            Modulation::Ofdm(ofdm) => {
+                manager = manager.with_default_modulation(ModulationScheme::Ofdm(*ofdm));
+            }
+            Modulation::Qpsk(qpsk) => {
+                manager = manager.with_default_modulation(ModulationScheme::Qpsk(*qpsk));
+            }
+            Modulation::Fsk | Modulation::Off => {}
+        }
+
+        Self {
+            config: config.clone(),
+            manager,
+            decoder: PacketDecoder::new(iteration_budget),
+            coding,
+            heartbeat: false,
+        }
+    }
+
+    pub fn config(&self) -> &QosConfig {
+        &self.config
+    }
+
+    /// Applies new settings, the assessment so far is kept
+    pub fn set_config(&mut self, config: QosConfig) {
+        self.manager.update_settings(QoSSettings {
+            per_threshold: Some(config.per_threshold),
+            adaptive_modulation_type: Some(config.adaptive_modulation_type),
+            qpsk_crossover: Some(config.qpsk_crossover),
+            modulation_debounce: Some(Duration::from_millis(config.modulation_debounce_ms)),
+            modulation_bounds: Some(config.modulation_bounds()),
+            ..Default::default()
+        });
+        self.config = config;
+    }
+
+    /// Current channel quality, the worse of the EDV and PER assessments
+    pub fn quality(&self) -> ChannelQuality {
+        self.manager.quality()
+    }
+
+    /// Records a received frame, returns the modulation to switch to if the
+    /// recommendation changed
+    ///
+    /// While disabled only frames as long as a heartbeat are decoded, so
+    /// heartbeats are still told apart from data.
+    pub fn on_receive(&mut self, data: &[u8]) -> Option<Modulation> {
+        self.heartbeat = false;
+
+        let coding = *self.coding.borrow();
+        if !self.config.enabled && data.len() != heartbeat::encoded_len(coding) {
+            return None;
+        }
+
+        self.decoder.set_coding(coding);
+        let decoded = self.decoder.decode(data)?;
+        self.heartbeat = decoded.valid && self.decoder.packet_type() == PacketType::Heartbeat;
+
+        if !self.config.enabled {
+            return None;
+        }
+
+        self.manager.update_decode(decoded.valid);
+
+        self.pending_change()
+    }
+
+    /// Whether the frame last passed to [`LinkQos::on_receive`] was a
+    /// heartbeat, which has served its purpose once it's counted
+    pub fn heartbeat(&self) -> bool {
+        self.heartbeat
+    }
+
+    /// Modulation to switch to once the debounce allows it, for a
+    /// recommendation held back while frames were coming in
+    pub fn pending_change(&mut self) -> Option<Modulation> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        self.manager
+            .modulation_change()
+            .map(|scheme| scheme.to_modulation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kaonic_frame::frame::Frame;
+    use kaonic_net::{
+        coder::{LdpcPacketCoder, PacketCoder},
+        packet::Packet,
+    };
+    use radio_common::modulation::OfdmMcs;
+
+    /// Enabled and without debounce, so every change shows up immediately
+    fn link_qos(config: QosConfig) -> LinkQos {
+        let mut qos = LinkQos {
+            config: QosConfig::default(),
+            manager: QoSManager::new().with_modulation_debounce(Duration::ZERO),
+            decoder: PacketDecoder::new(None),
+            coding: watch::Sender::new(LinkCoding::default()).subscribe(),
+            heartbeat: false,
+        };
+        qos.set_config(QosConfig {
+            enabled: true,
+            modulation_debounce_ms: 0,
+            ..config
+        });
+
+        qos
+    }
+
+    fn coded_frame() -> Vec<u8> {
+        let mut packet = Packet::<2048>::new();
+        let mut frame = Frame::<2048>::new();
+
+        packet.frame_mut().push_data(b"payload").expect("payload");
+        packet.build();
+
+        LdpcPacketCoder::<2048>::new()
+            .encode(&packet, &mut frame)
+            .expect("encoded frame");
+
+        frame.as_slice().to_vec()
+    }
+
+    fn mcs(modulation: Option<Modulation>) -> Option<OfdmMcs> {
+        match modulation {
+            Some(Modulation::Ofdm(ofdm)) => Some(ofdm.mcs),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_decode_failures_step_modulation_down() {
+        let mut qos = link_qos(QosConfig::default());
+
+        let frame = coded_frame();
+        let mut corrupted = frame.clone();
+        corrupted.iter_mut().for_each(|byte| *byte ^= 0xA5);
+
+        // Raw frames are ignored
+        assert_eq!(qos.on_receive(b"raw frame"), None);
+
+        assert_eq!(mcs(qos.on_receive(&frame)), Some(OfdmMcs::QamC3_4));
+
+        let changes: Vec<_> = (0..kaonic_qos::DEFAULT_PER_WINDOW)
+            .filter_map(|_| qos.on_receive(&corrupted))
+            .collect();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(mcs(changes.into_iter().next()), Some(OfdmMcs::QpskC3_4));
+    }
+
+    #[test]
+    fn test_adaptive_modulation_type_switches_to_qpsk() {
+        let mut qos = link_qos(QosConfig {
+            adaptive_modulation_type: true,
+            qpsk_crossover: ChannelQuality::Good,
+            ..Default::default()
+        });
+
+        let frame = coded_frame();
+        let mut corrupted = frame.clone();
+        corrupted.iter_mut().for_each(|byte| *byte ^= 0xA5);
+
+        assert!(matches!(qos.on_receive(&frame), Some(Modulation::Ofdm(_))));
+
+        let changes: Vec<_> = (0..kaonic_qos::DEFAULT_PER_WINDOW)
+            .filter_map(|_| qos.on_receive(&corrupted))
+            .collect();
+
+        assert_eq!(qos.quality(), ChannelQuality::Good);
+        assert!(matches!(changes[..], [Modulation::Qpsk(_)]));
+
+        // Disabled, decode failures don't count anymore
+        qos.set_config(QosConfig::default());
+        assert_eq!(qos.on_receive(&corrupted), None);
+    }
+
+    #[test]
+    fn test_heartbeats_update_idle_link() {
+        let mut qos = link_qos(QosConfig::default());
+
+        let mut corrupted = coded_frame();
+        corrupted.iter_mut().for_each(|byte| *byte ^= 0xA5);
+        for _ in 0..kaonic_qos::DEFAULT_PER_WINDOW {
+            qos.on_receive(&corrupted);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Good);
+        assert!(!qos.heartbeat());
+
+        // No data flows anymore, only the peer's heartbeats arrive
+        let heartbeat = heartbeat::encode(0xCAFE, LinkCoding::default());
+        for _ in 0..kaonic_qos::DEFAULT_PER_WINDOW {
+            qos.on_receive(&heartbeat);
+            assert!(qos.heartbeat());
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Excellent);
+
+        qos.on_receive(&coded_frame());
+        assert!(!qos.heartbeat());
+
+        // Still recognized with QoS disabled, so they aren't forwarded as data
+        qos.set_config(QosConfig::default());
+        assert_eq!(qos.on_receive(&heartbeat), None);
+        assert!(qos.heartbeat());
+        qos.on_receive(&coded_frame());
+        assert!(!qos.heartbeat());
+    }
+
+    #[test]
+    fn test_modulation_changes_are_debounced() {
+        let mut qos = link_qos(QosConfig::default());
+        qos.set_config(QosConfig {
+            enabled: true,
+            modulation_debounce_ms: 500,
+            ..Default::default()
+        });
+
+        let frame = coded_frame();
+        let mut corrupted = frame.clone();
+        corrupted.iter_mut().for_each(|byte| *byte ^= 0xA5);
+
+        assert_eq!(mcs(qos.on_receive(&frame)), Some(OfdmMcs::QamC3_4));
+
+        // The step down is recommended within the interval and held back
+        let changes: Vec<_> = (0..kaonic_qos::DEFAULT_PER_WINDOW * 4)
+            .filter_map(|_| qos.on_receive(&corrupted))
+            .collect();
+        assert!(changes.is_empty());
+        assert_eq!(qos.pending_change(), None);
+
+        // Only the latest recommendation is applied once the interval is over
+        std::thread::sleep(Duration::from_millis(550));
+        let change = qos.pending_change();
+        assert!(change.is_some());
+        assert_eq!(qos.pending_change(), None);
+    }
+}
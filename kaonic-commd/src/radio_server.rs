@@ -1,30 +1,69 @@
 use std::{
     sync::{
         Arc,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
-    time::Instant,
+    time::{Duration, Instant, SystemTime},
 };
 
 use kaonic_ctrl::{
     protocol::{
-        GetStatisticsResponse, Message, MessageBuilder, Payload, RadioFrame, ReceiveModule,
-        TransmitModule,
+        API_VERSION, ChannelQuality as CtrlChannelQuality, CheckedReceiveModule, FrequencyPlan,
+        GetCapabilitiesResponse, GetFrequencyPlansResponse, GetStatisticsResponse, Message,
+        MessageBuilder, Payload, QosSettings, RadioFrame, ReceiveModule, TransmitModule,
+        TransmitReport, TransmitResult,
     },
     server::ServerHandler,
 };
+use kaonic_net::coder::LinkCoding;
+use kaonic_qos::ChannelQuality;
 use kaonic_radio::{
-    error::KaonicError,
-    platform::{PlatformRadio, PlatformRadioEvent, PlatformRadioFrame, create_machine},
-    radio::Radio,
+    error::{BringupStep, KaonicError},
+    frequency_plan::FREQUENCY_PLANS,
+    platform::{PlatformRadio, PlatformRadioEvent, PlatformRadioFrame, create_machine_with_spi},
+    radio::{self, IrqCounters, Radio, TxTurnaround},
 };
 
-use rand::rngs::OsRng;
+use radio_common::modulation::Modulation;
+use rand::{Rng, rngs::OsRng};
+
+use crate::{
+    beacon::{self, Beacon, CAPABILITY_LDPC, LinkAdvert, NodeId, PeerTable, node_id_from_serial},
+    capabilities::capabilities,
+    capture::{ReceiveCapture, SharedReceiveCapture},
+    channel,
+    config::{BeaconConfig, CommdConfig, QosConfig, ReceiveMode, ThermalConfig},
+    diversity::{DIVERSITY_QUEUE, spawn_diversity_combiner},
+    events::{EventBus, RadioEvent, TransmitBus, spawn_activity_log},
+    heartbeat,
+    power_control::PowerControl,
+    qos::{LinkQos, SharedLinkQos},
+    raw_crc::{FrameIntegrity, append_raw_crc, setup_integrity, verify_raw_crc},
+    rx_rate::ReceiveRateLimiter,
+    shutdown::{InFlight, Workers},
+    thermal::ThermalThrottle,
+    tx_queue::{TransmitQueue, spawn_transmit_queue},
+    worker::tune_current_thread,
+};
 use tokio::sync::{broadcast, mpsc, watch};
 use tokio_util::sync::CancellationToken;
 
 pub type SharedRadio = Arc<std::sync::Mutex<PlatformRadio>>;
-const MODULE_EVENT_CHANNEL_CAPACITY: usize = 256;
+/// Received frames of all modules share the bus
+const EVENT_BUS_CAPACITY: usize = 512;
+
+/// Transmit events of all modules share the bus
+const TRANSMIT_BUS_CAPACITY: usize = 256;
+
+/// Longest the event thread blocks before checking for shutdown
+///
+/// Radio interrupts end the wait right away, so this only bounds how long
+/// the thread takes to notice a cancel.
+const EVENT_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How often a module worker checks for a QoS modulation change the debounce
+/// held back
+const QOS_PENDING_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Default)]
 pub struct ModuleStats {
@@ -34,18 +73,144 @@ pub struct ModuleStats {
     pub tx_bytes: AtomicU64,
     pub rx_errors: AtomicU64,
     pub tx_errors: AtomicU64,
+    /// Attempts transmissions needed beyond their first one
+    pub tx_retries: AtomicU64,
+    /// Receive buffer overruns, the frame is dropped and RX restarted
+    pub rx_overruns: AtomicU64,
+    /// Frames over the receive rate limit, counted but not forwarded
+    pub rx_rate_dropped: AtomicU64,
+    /// Last transceiver temperature in °C, if the platform has a sensor
+    pub temperature: std::sync::Mutex<Option<f32>>,
+    /// RSSI in dBm of the last received frame
+    pub last_rssi: std::sync::Mutex<Option<i8>>,
+    /// Supply voltage is below the battery monitor threshold
+    pub battery_low: AtomicBool,
+    /// The radio switches back to RX by itself after each transmission
+    pub auto_turnaround: AtomicBool,
+    /// UDP transmit requests currently being handled
+    pub tx_in_flight: AtomicU64,
+    /// Protection of the module's frames, set up at startup
+    pub integrity: std::sync::Mutex<FrameIntegrity>,
+    /// Outcome of the startup sequence of the transceiver
+    pub bringup: std::sync::Mutex<BringupStatus>,
+    /// Transceiver IRQs seen so far, `None` if the platform doesn't count them
+    pub irq_counters: std::sync::Mutex<Option<IrqCounters>>,
+}
+
+/// Outcome of [`Radio::bringup`] at startup
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BringupStatus {
+    /// Disabled in the config or not supported by the platform
+    #[default]
+    Skipped,
+    Done,
+    Failed(BringupStep),
 }
 
 pub type SharedModuleStats = Arc<ModuleStats>;
 
+pub type SharedPeerTable = Arc<std::sync::Mutex<PeerTable>>;
+
+/// Received frame and the outcome of its raw-frame CRC check
+#[derive(Debug, Clone, Copy)]
+pub struct ReceivedFrame {
+    pub receive: ReceiveModule,
+    /// `None` without the software CRC and for kaonic-net frames
+    pub crc_valid: Option<bool>,
+    /// Modulation of the module when the frame came in, before QoS reacted to it
+    pub modulation: Modulation,
+    /// QoS channel quality at that time
+    pub quality: ChannelQuality,
+}
+
+/// Received frames are shared between all subscribers instead of copied per subscriber
+pub type SharedReceiveModule = Arc<ReceivedFrame>;
+
+/// What the receive loop of a module works with, see `RadioServer::manage_radio`
+struct ModuleReceiver {
+    module: u16,
+    radio: SharedRadio,
+    events: EventBus,
+    stats: SharedModuleStats,
+    peers: SharedPeerTable,
+    /// Own beacons heard back are ignored
+    node_id: NodeId,
+    /// Frames carry the software CRC trailer
+    raw_crc: bool,
+    link_qos: SharedLinkQos,
+    capture: SharedReceiveCapture,
+    /// Frames go to the diversity combiner instead of the event bus if set
+    diversity: Option<mpsc::Sender<SharedReceiveModule>>,
+    rate_limiter: Option<ReceiveRateLimiter>,
+}
+
+/// Frame the radio is done with, sent, given up on or refused
+#[derive(Debug, Clone, Copy)]
+pub struct TransmitEvent {
+    pub module: usize,
+    pub frame: RadioFrame,
+    /// Client supplied tag of the transmit request
+    pub seq: u32,
+    pub report: radio::TransmitReport,
+    /// Why the radio refused the frame without trying, `report` doesn't apply then
+    pub error: Option<KaonicError>,
+    /// Estimated time on air from the frame length and modulation, zero if
+    /// the frame didn't go out
+    pub air_time: Duration,
+    /// Time from the request to the end of the transmission
+    pub latency: Duration,
+}
+
+impl TransmitEvent {
+    /// Event of `frame` sent with `modulation`, requested at `requested`
+    pub fn new(
+        module: usize,
+        frame: &PlatformRadioFrame,
+        seq: u32,
+        result: &Result<(), KaonicError>,
+        report: radio::TransmitReport,
+        modulation: &Modulation,
+        requested: Instant,
+    ) -> Self {
+        // A radio giving up reports its attempts, one refusing the frame doesn't
+        let error = match result {
+            Err(e) if report.result.is_sent() || report.attempts == 0 => Some(*e),
+            _ => None,
+        };
+
+        let air_time = match (result, report.result.is_sent()) {
+            (Ok(()), true) => modulation.air_time(frame.len()).unwrap_or_default(),
+            _ => Duration::ZERO,
+        };
+
+        Self {
+            module,
+            frame: RadioFrame::new_from_frame(frame),
+            seq,
+            report,
+            error,
+            air_time,
+            latency: requested.elapsed(),
+        }
+    }
+}
+
 pub struct RadioServer {
     radios: Vec<SharedRadio>,
+    transmit_queues: Vec<TransmitQueue>,
+    qos: Vec<SharedLinkQos>,
+    capture: Vec<SharedReceiveCapture>,
     stats: Vec<SharedModuleStats>,
-    module_rx_send: broadcast::Sender<Box<ReceiveModule>>,
-    module_tx_send: broadcast::Sender<Box<TransmitModule>>,
+    events: EventBus,
+    transmits: TransmitBus,
+    peers: SharedPeerTable,
     cancel: CancellationToken,
     serial: String,
     mtu: usize,
+    capabilities: GetCapabilitiesResponse,
+    /// Modules set to receive only refuse transmit requests
+    tx_enabled: Vec<bool>,
+    workers: Workers,
 }
 
 impl RadioServer {
@@ -54,15 +219,52 @@ impl RadioServer {
         cancel: CancellationToken,
         serial: String,
         mtu: usize,
+        config: CommdConfig,
     ) -> Result<Self, KaonicError> {
-        let mut machine = create_machine()?;
+        let mut machine = create_machine_with_spi(&config.spi.settings())?;
+
+        let events = EventBus::new(EVENT_BUS_CAPACITY);
+        let transmits = TransmitBus::new(TRANSMIT_BUS_CAPACITY);
+
+        let node_id = config
+            .beacon
+            .node_id
+            .unwrap_or_else(|| node_id_from_serial(&serial));
+        let mut peer_table = PeerTable::new(Duration::from_millis(config.beacon.peer_timeout_ms))
+            .with_coding(node_id, config.coding.link_coding(), config.coding.manual)
+            .with_manual_modulation(!config.beacon.follow_modulation)
+            .with_events(events.clone());
+        if config.power_control.enabled {
+            peer_table = peer_table.with_power_control(PowerControl::new(
+                config.power_control.target_rssi,
+                config.power_control.hysteresis_db,
+                config.tx_power.limit(),
+            ));
+        }
+        let peers: SharedPeerTable = Arc::new(std::sync::Mutex::new(peer_table));
 
-        let (module_rx_send, module_rx_recv) = broadcast::channel(MODULE_EVENT_CHANNEL_CAPACITY);
-        let (module_tx_send, module_tx_recv) = broadcast::channel(MODULE_EVENT_CHANNEL_CAPACITY);
+        if config.beacon.enabled {
+            log::info!("beacon node id {:0>8X}", node_id);
+        }
 
         let mut radio_index = 0;
         let mut radios = Vec::new();
+        let mut transmit_queues = Vec::new();
+        let mut qos = Vec::new();
+        let mut capture = Vec::new();
+        let mut workers = Workers::default();
         let mut stats: Vec<SharedModuleStats> = Vec::new();
+
+        // In diversity mode the receive loops hand their frames to the
+        // combiner, which publishes one copy of each
+        let (diversity_send, diversity_recv) = match config.receive.mode {
+            ReceiveMode::Independent => (None, None),
+            ReceiveMode::Diversity => {
+                let (send, recv) = mpsc::channel(DIVERSITY_QUEUE);
+                (Some(send), Some(recv))
+            }
+        };
+
         loop {
             let radio = machine.take_radio(radio_index);
             if radio.is_none() {
@@ -73,116 +275,435 @@ impl RadioServer {
 
             let (event_send, event_recv) = watch::channel(false);
 
-            let radio = radio.unwrap();
+            let mut radio = radio.unwrap();
             let event = radio.event();
 
-            let radio = Arc::new(std::sync::Mutex::new(radio));
+            let bringup = if config.worker.bringup {
+                match radio.bringup() {
+                    Ok(()) => {
+                        log::info!("radio[{radio_index}] bringup done");
+                        BringupStatus::Done
+                    }
+                    Err(KaonicError::Bringup(step)) => {
+                        log::error!("radio[{radio_index}] bringup failed at {step:?}");
+                        BringupStatus::Failed(step)
+                    }
+                    Err(e) => {
+                        log::warn!("radio[{radio_index}] bringup not available: {e:?}");
+                        BringupStatus::Skipped
+                    }
+                }
+            } else {
+                BringupStatus::Skipped
+            };
+
+            if let Some(threshold_mv) = config.battery.threshold_mv
+                && let Err(e) = radio.set_battery_monitor(threshold_mv, config.battery.tx_inhibit)
+            {
+                log::warn!("radio[{radio_index}] battery monitor not configured: {e:?}");
+            }
+
+            if let Err(e) = radio.set_power_limit(config.tx_power.limit()) {
+                log::warn!("radio[{radio_index}] tx power ceiling not configured: {e:?}");
+            }
+
+            if config.transmit.auto_turnaround
+                && let Err(e) = radio.set_tx_turnaround(TxTurnaround::Auto)
+            {
+                log::warn!("radio[{radio_index}] automatic turnaround not available: {e:?}");
+            }
+
+            if let Some(retries) = config.transmit.retries
+                && let Err(e) = radio.set_tx_retries(retries)
+            {
+                log::warn!("radio[{radio_index}] tx retries not configured: {e:?}");
+            }
+
+            if let Some(timeout_ms) = config.transmit.rx_restore_timeout_ms
+                && let Err(e) = radio.set_rx_restore_timeout(Duration::from_millis(timeout_ms))
+            {
+                log::warn!("radio[{radio_index}] rx restore timeout not configured: {e:?}");
+            }
+
+            if let Err(e) = radio.set_cca_mode(config.transmit.cca_mode) {
+                log::warn!("radio[{radio_index}] cca mode not configured: {e:?}");
+            }
+
+            if let Err(e) = radio.set_lbt(config.transmit.regulatory_profile.lbt()) {
+                log::warn!("radio[{radio_index}] listen before talk not configured: {e:?}");
+            }
+
+            if let Some(map) = config.receive.agc_gain_map
+                && let Err(e) = radio.set_agc_gain_map(map)
+            {
+                log::warn!("radio[{radio_index}] agc gain map not configured: {e:?}");
+            }
+
+            if let Some(filter) = config.address_filter.filter() {
+                if let Err(e) = radio.set_address_filter(Some(filter)) {
+                    log::warn!("radio[{radio_index}] address filter not configured: {e:?}");
+                } else if let Some(ack) = config.auto_ack.auto_ack()
+                    && config.transmit.module_tx_enabled(radio_index)
+                    && let Err(e) = radio.set_auto_ack(Some(ack))
+                {
+                    log::warn!("radio[{radio_index}] auto-ack not configured: {e:?}");
+                }
+            }
+
+            if config.channel.auto_select {
+                match channel::select_best_channel(
+                    &mut radio,
+                    config.channel.channel_count,
+                    Duration::from_millis(config.channel.dwell_ms),
+                ) {
+                    Ok(best) => log::info!(
+                        "radio[{radio_index}] selected channel {} ({} dBm)",
+                        best.channel,
+                        best.rssi
+                    ),
+                    Err(e) => log::warn!("radio[{radio_index}] channel selection failed: {e:?}"),
+                }
+            }
+
+            log::info!(
+                "radio[{radio_index}] tx turnaround: {:?}",
+                radio.tx_turnaround()
+            );
+
+            let integrity = setup_integrity(radio_index, &mut radio, &config.transmit);
+
             let module_stats: SharedModuleStats = Arc::new(ModuleStats::default());
+            module_stats.auto_turnaround.store(
+                radio.tx_turnaround() == TxTurnaround::Auto,
+                Ordering::Relaxed,
+            );
+            *module_stats.integrity.lock().unwrap() = integrity;
+            *module_stats.bringup.lock().unwrap() = bringup;
 
-            std::thread::Builder::new()
-                .name(format!("kaonic-radio-event-{}", radio_index))
-                .spawn(move || {
-                    radio_event_thread(event, event_send);
-                })
-                .unwrap();
+            let link_qos: SharedLinkQos = Arc::new(std::sync::Mutex::new(LinkQos::new(
+                &config.qos,
+                &radio.get_modulation(),
+                peers.lock().unwrap().subscribe_coding(),
+                config.receive.ldpc_iteration_budget,
+            )));
+
+            let receive_capture: SharedReceiveCapture =
+                Arc::new(std::sync::Mutex::new(ReceiveCapture::new(
+                    &config.capture,
+                    peers.lock().unwrap().subscribe_coding(),
+                    config.receive.ldpc_iteration_budget,
+                )));
+
+            let radio = Arc::new(std::sync::Mutex::new(radio));
 
             {
+                let worker = config.worker.clone();
+                let cancel = cancel.clone();
+                let thread = std::thread::Builder::new()
+                    .name(format!("kaonic-radio-event-{}", radio_index))
+                    .spawn(move || {
+                        tune_current_thread(radio_index, &worker);
+                        radio_event_thread(event, event_send, cancel);
+                    })
+                    .unwrap();
+                workers.threads.push(thread);
+            }
+
+            {
+                let cancel = cancel.clone();
+                let worker = config.worker.clone();
+                let receiver = ModuleReceiver {
+                    module: radio_index as u16,
+                    radio: radio.clone(),
+                    events: events.clone(),
+                    stats: module_stats.clone(),
+                    peers: peers.clone(),
+                    node_id,
+                    raw_crc: integrity == FrameIntegrity::SoftwareCrc,
+                    link_qos: link_qos.clone(),
+                    capture: receive_capture.clone(),
+                    diversity: diversity_send.clone(),
+                    rate_limiter: config
+                        .receive
+                        .max_frame_rate
+                        .map(|rate| ReceiveRateLimiter::new(radio_index as u16, rate)),
+                };
+
+                // The receive loop gets a thread of its own so the worker
+                // scheduling applies to it, not just to the IRQ thread
+                let thread = std::thread::Builder::new()
+                    .name(format!("kaonic-radio-rx-{}", radio_index))
+                    .spawn(move || {
+                        tune_current_thread(radio_index, &worker);
+
+                        let runtime = tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()
+                            .expect("radio worker runtime");
+
+                        runtime.block_on(Self::manage_radio(receiver, event_recv, cancel));
+                    })
+                    .unwrap();
+                workers.threads.push(thread);
+            }
+
+            let tx_enabled = config.transmit.module_tx_enabled(radio_index);
+            if !tx_enabled {
+                log::info!("radio[{radio_index}] transmit disabled, receive only");
+            }
+
+            if config.beacon.enabled && tx_enabled {
                 let cancel = cancel.clone();
-                let module_rx_send = module_rx_send.clone();
                 let radio = radio.clone();
                 let module_stats = module_stats.clone();
+                let peers = peers.clone();
+                let beacon = config.beacon.clone();
 
-                tokio::spawn(Box::pin(async move {
-                    Self::manage_radio(
-                        radio_index as u16,
+                workers.tasks.push(tokio::spawn(Box::pin(async move {
+                    Self::transmit_beacons(
+                        radio_index,
                         radio,
-                        module_rx_send,
-                        event_recv,
+                        module_stats,
+                        peers,
+                        node_id,
+                        beacon,
                         cancel,
+                    )
+                    .await;
+                })));
+            }
+
+            if let Some(interval_ms) = config.qos.heartbeat_interval_ms
+                && tx_enabled
+            {
+                let cancel = cancel.clone();
+                let radio = radio.clone();
+                let module_stats = module_stats.clone();
+                let peers = peers.clone();
+                let link_qos = link_qos.clone();
+
+                workers.tasks.push(tokio::spawn(Box::pin(async move {
+                    Self::transmit_heartbeats(
+                        radio_index,
+                        radio,
                         module_stats,
+                        peers,
+                        link_qos,
+                        node_id,
+                        Duration::from_millis(interval_ms.max(100)),
+                        cancel,
                     )
                     .await;
-                }));
+                })));
+            }
+
+            {
+                let cancel = cancel.clone();
+                let radio = radio.clone();
+                let module_stats = module_stats.clone();
+                let thermal = config.thermal.clone();
+
+                workers.tasks.push(tokio::spawn(Box::pin(async move {
+                    Self::monitor_thermal(radio_index, radio, module_stats, thermal, cancel).await;
+                })));
+            }
+
+            {
+                let (queue, task) = spawn_transmit_queue(
+                    radio_index,
+                    radio.clone(),
+                    peers.clone(),
+                    transmits.clone(),
+                    config.transmit.verify_tx_power,
+                    cancel.clone(),
+                );
+                transmit_queues.push(queue);
+                workers.tasks.push(task);
             }
 
             radio_index += 1;
             radios.push(radio);
+            qos.push(link_qos);
+            capture.push(receive_capture);
             stats.push(module_stats);
         }
 
+        if let Some(frames) = diversity_recv {
+            if radios.len() < 2 {
+                log::warn!(
+                    "receive diversity needs two modules, found {}",
+                    radios.len()
+                );
+            } else {
+                // Module 1 listens on the channel of module 0
+                let (radio_config, modulation) = {
+                    let radio = radios[0].lock().unwrap();
+                    (radio.get_config(), radio.get_modulation())
+                };
+
+                let mut radio = radios[1].lock().unwrap();
+                if let Err(e) = radio.set_config(&radio_config) {
+                    log::warn!("radio[1] diversity config not applied: {e:?}");
+                }
+                if let Err(e) = radio.set_modulation(&modulation) {
+                    log::warn!("radio[1] diversity modulation not applied: {e:?}");
+                }
+            }
+
+            workers.tasks.push(spawn_diversity_combiner(
+                frames,
+                events.clone(),
+                Duration::from_millis(config.receive.diversity_window_ms),
+                cancel.clone(),
+            ));
+        }
+
+        let part_number = radios
+            .first()
+            .and_then(|radio| radio.lock().unwrap().part_number());
+        let capabilities = GetCapabilitiesResponse {
+            api_version: API_VERSION,
+            capabilities: capabilities(&config, part_number),
+            part_number: part_number.map(|part| part.to_string()),
+        };
+
         {
             let cancel = cancel.clone();
             let client_send = client_send.clone();
-            tokio::spawn(Box::pin(async move {
-                let _ = Self::manage_module_receive(client_send, module_rx_recv, cancel).await;
-            }));
+            let events = events.subscribe();
+            workers.tasks.push(tokio::spawn(Box::pin(async move {
+                let _ = Self::manage_module_receive(client_send, events, cancel).await;
+            })));
         }
 
+        workers
+            .tasks
+            .push(spawn_activity_log(&events, cancel.clone()));
+
         {
             let cancel = cancel.clone();
             let client_send = client_send.clone();
-            tokio::spawn(Box::pin(async move {
-                let _ = Self::manage_module_transmit(client_send, module_tx_recv, cancel).await;
-            }));
+            let transmits = transmits.subscribe();
+            workers.tasks.push(tokio::spawn(Box::pin(async move {
+                let _ = Self::manage_module_transmit(client_send, transmits, cancel).await;
+            })));
         }
 
+        let tx_enabled = (0..radios.len())
+            .map(|module| config.transmit.module_tx_enabled(module))
+            .collect();
+
         Ok(Self {
             radios,
+            transmit_queues,
+            qos,
+            capture,
             stats,
-            module_rx_send,
-            module_tx_send,
+            events,
+            transmits,
+            peers,
             cancel,
             serial,
             mtu,
+            capabilities,
+            tx_enabled,
+            workers,
         })
     }
 
+    /// Hands over the worker tasks and threads to be joined on shutdown.
+    pub fn take_workers(&mut self) -> Workers {
+        std::mem::take(&mut self.workers)
+    }
+
     /// Returns clones of the shared radio handles.
     pub fn radios(&self) -> Vec<SharedRadio> {
         self.radios.clone()
     }
 
+    /// Returns the transmit queues of the gRPC requests, one per module.
+    pub fn transmit_queues(&self) -> Vec<TransmitQueue> {
+        self.transmit_queues.clone()
+    }
+
+    /// Returns the adaptive modulation state of each module.
+    pub fn qos(&self) -> Vec<SharedLinkQos> {
+        self.qos.clone()
+    }
+
     /// Returns the number of available radio modules.
     pub fn module_count(&self) -> usize {
         self.radios.len()
     }
 
+    /// Returns the ring of received frames of each module.
+    pub fn capture(&self) -> Vec<SharedReceiveCapture> {
+        self.capture.clone()
+    }
+
     /// Returns clones of the per-module statistics handles.
     pub fn stats(&self) -> Vec<SharedModuleStats> {
         self.stats.clone()
     }
 
-    /// Subscribes to the broadcast channel of received radio frames.
-    pub fn subscribe_rx(&self) -> broadcast::Receiver<Box<ReceiveModule>> {
-        self.module_rx_send.subscribe()
+    /// Returns the frame integrity each module was set up with.
+    pub fn integrity(&self) -> Vec<FrameIntegrity> {
+        self.stats
+            .iter()
+            .map(|stats| *stats.integrity.lock().unwrap())
+            .collect()
+    }
+
+    /// Returns the API version and features reported by `GetCapabilities`.
+    pub fn capabilities(&self) -> GetCapabilitiesResponse {
+        self.capabilities.clone()
+    }
+
+    /// Returns the table of peers discovered through beacons.
+    pub fn peers(&self) -> SharedPeerTable {
+        self.peers.clone()
+    }
+
+    /// Follows the coding of kaonic-net frames, which beacons may change.
+    pub fn coding(&self) -> watch::Receiver<LinkCoding> {
+        self.peers.lock().unwrap().subscribe_coding()
     }
 
-    /// Returns a clone of the broadcast sender for received radio frames.
-    pub fn rx_sender(&self) -> broadcast::Sender<Box<ReceiveModule>> {
-        self.module_rx_send.clone()
+    /// Returns the bus of received frames and the other radio activity.
+    pub fn events(&self) -> EventBus {
+        self.events.clone()
     }
 
-    /// Returns a clone of the broadcast sender for transmitted radio frames.
-    pub fn tx_sender(&self) -> broadcast::Sender<Box<TransmitModule>> {
-        self.module_tx_send.clone()
+    /// Returns the bus of transmit events.
+    pub fn transmits(&self) -> TransmitBus {
+        self.transmits.clone()
     }
 
     async fn manage_module_receive(
         client_send: mpsc::Sender<Box<Message>>,
-        mut module_rx_recv: broadcast::Receiver<Box<ReceiveModule>>,
+        mut events: broadcast::Receiver<RadioEvent>,
         cancel: CancellationToken,
     ) {
         loop {
             tokio::select! {
                 biased;
 
-                recv_result = module_rx_recv.recv() => match recv_result {
-                    Ok(rx) => {
+                recv_result = events.recv() => match recv_result {
+                    Ok(RadioEvent::Receive(rx)) => {
+                        let payload = match rx.crc_valid {
+                            Some(crc_valid) => Payload::CheckedReceiveModule(CheckedReceiveModule {
+                                receive: rx.receive,
+                                crc_valid,
+                            }),
+                            None => Payload::ReceiveModule(rx.receive),
+                        };
+
                         let _ = client_send.send(Box::new(MessageBuilder::new()
                             .with_rnd_id(OsRng)
-                            .with_payload(Payload::ReceiveModule(*rx))
+                            .with_payload(payload)
                             .build())).await;
                     }
+                    Ok(_) => {}
                     Err(broadcast::error::RecvError::Lagged(skipped)) => {
                         log::warn!("radio server rx stream lagged by {skipped} messages");
                         continue;
@@ -199,7 +720,7 @@ impl RadioServer {
 
     async fn manage_module_transmit(
         client_send: mpsc::Sender<Box<Message>>,
-        mut module_tx_recv: broadcast::Receiver<Box<TransmitModule>>,
+        mut transmits: broadcast::Receiver<Arc<TransmitEvent>>,
         cancel: CancellationToken,
     ) {
         loop {
@@ -207,12 +728,15 @@ impl RadioServer {
                 biased;
 
 
-                recv_result = module_tx_recv.recv() => match recv_result {
+                recv_result = transmits.recv() => match recv_result {
                     Ok(tx) => {
                         if false {
                             let _ = client_send.send(Box::new(MessageBuilder::new()
                                 .with_rnd_id(OsRng)
-                                .with_payload(Payload::TransmitModuleEvent(*tx))
+                                .with_payload(Payload::TransmitModuleEvent(TransmitModule {
+                                    module: tx.module,
+                                    frame: tx.frame,
+                                }))
                                 .build())).await;
                         }
                     }
@@ -230,47 +754,432 @@ impl RadioServer {
         }
     }
 
-    async fn manage_radio(
-        module: u16,
+    async fn monitor_thermal(
+        module: usize,
         radio: SharedRadio,
-        module_rx_send: broadcast::Sender<Box<ReceiveModule>>,
-        mut event_recv: watch::Receiver<bool>,
+        stats: SharedModuleStats,
+        config: ThermalConfig,
         cancel: CancellationToken,
+    ) {
+        let mut interval = tokio::time::interval(core::time::Duration::from_millis(
+            config.interval_ms.max(100),
+        ));
+
+        let mut throttle = config
+            .throttle_celsius
+            .map(|threshold| ThermalThrottle::new(threshold, config.throttle_tx_power));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    // The sensor read is blocking sysfs I/O under the radio lock,
+                    // keep it off the runtime threads
+                    let step = {
+                        let radio = radio.clone();
+                        let stats = stats.clone();
+                        let mut throttle = throttle.take();
+                        tokio::task::spawn_blocking(move || {
+                            let result = Self::update_thermal(module, &radio, &stats, &mut throttle);
+                            (result, throttle)
+                        })
+                    };
+
+                    match step.await {
+                        Ok((result, t)) => {
+                            throttle = t;
+                            if let Err(KaonicError::NotSupported) = result {
+                                log::debug!("radio[{module}] has no temperature sensor");
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("radio[{module}] thermal monitor failed: {e}");
+                            break;
+                        }
+                    }
+                },
+
+                _ = cancel.cancelled() => {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Samples the temperature once and throttles the tx power if needed
+    fn update_thermal(
+        module: usize,
+        radio: &SharedRadio,
+        stats: &SharedModuleStats,
+        throttle: &mut Option<ThermalThrottle>,
+    ) -> Result<(), KaonicError> {
+        let mut radio = radio.lock().unwrap();
+
+        let temperature = match radio.read_temperature() {
+            Ok(temperature) => temperature,
+            Err(KaonicError::NotSupported) => return Err(KaonicError::NotSupported),
+            Err(e) => {
+                log::warn!("radio[{module}] temperature read error: {e:?}");
+                return Err(e);
+            }
+        };
+
+        *stats.temperature.lock().unwrap() = Some(temperature);
+
+        if let Some(throttle) = throttle
+            && let Some(modulation) = throttle.update(temperature, &radio.get_modulation())
+        {
+            log::warn!(
+                "radio[{module}] {:.1}°C, {} tx power to {}",
+                temperature,
+                if throttle.is_throttled() {
+                    "throttling"
+                } else {
+                    "restoring"
+                },
+                modulation.tx_power()
+            );
+
+            let _ = radio.set_modulation(&modulation);
+        }
+
+        Ok(())
+    }
+
+    async fn transmit_beacons(
+        module: usize,
+        radio: SharedRadio,
         stats: SharedModuleStats,
+        peers: SharedPeerTable,
+        node_id: NodeId,
+        config: BeaconConfig,
+        cancel: CancellationToken,
     ) {
-        let mut rx_frame = PlatformRadioFrame::new();
+        let interval = Duration::from_millis(config.interval_ms.max(100));
+        let slot = Duration::from_millis(config.slot_ms).min(interval / 2);
+
+        let mut next_beacon = tokio::time::Instant::now();
+
+        loop {
+            let deadline = match config.channel {
+                Some(_) => {
+                    tokio::time::Instant::now()
+                        + beacon::until_next_slot(SystemTime::now(), interval)
+                }
+                None => {
+                    let deadline = next_beacon;
+                    next_beacon += interval;
+                    deadline
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {},
+
+                _ = cancel.cancelled() => {
+                    break;
+                }
+            }
+
+            let radio = radio.clone();
+            let stats = stats.clone();
+            let peers = peers.clone();
+
+            // Radio and peer table locks are blocking, keep them off the runtime
+            let _ = tokio::task::spawn_blocking(move || {
+                Self::send_beacon(
+                    module,
+                    &radio,
+                    &stats,
+                    &peers,
+                    node_id,
+                    config.channel,
+                    slot,
+                );
+
+                // Peers time out here too, not only when they are listed
+                peers.lock().unwrap().expire(Instant::now());
+            })
+            .await;
+        }
+    }
+
+    /// Transmits one beacon, on the beacon channel if one is configured.
+    /// Data transmissions wait for the radio lock until the slot is over.
+    fn send_beacon(
+        module: usize,
+        radio: &SharedRadio,
+        stats: &SharedModuleStats,
+        peers: &SharedPeerTable,
+        node_id: NodeId,
+        channel: Option<u16>,
+        slot: Duration,
+    ) {
+        let mut radio = radio.lock().unwrap();
+
+        let modulation = radio.get_modulation();
+        if matches!(modulation, Modulation::Off) {
+            return;
+        }
+
+        let data = {
+            let mut peers = peers.lock().unwrap();
+            let capabilities =
+                CAPABILITY_LDPC | peers.coding_capabilities() | peers.modulation_capabilities();
+
+            if let Some(adjusted) =
+                peers.power_adjustment(module, &modulation, radio.get_config().freq)
+            {
+                log::debug!(
+                    "radio[{module}] power control: tx power {} -> {}",
+                    modulation.tx_power(),
+                    adjusted.tx_power()
+                );
+
+                if let Err(e) = radio.set_modulation(&adjusted) {
+                    log::warn!("radio[{module}] power control error: {e:?}");
+                }
+            }
+
+            let modulation = radio.get_modulation();
+            let link = LinkAdvert {
+                channel: radio.get_config().channel,
+                modulation,
+            };
+
+            let mut beacon = Beacon::new(node_id, capabilities, &modulation)
+                .with_coding(peers.coding())
+                .with_reports(peers.rssi_reports(module));
+            // Nodes before version 4 drop beacons advertising a link
+            if peers.follows_modulation() {
+                beacon = beacon.with_link(link);
+            }
+            beacon.encode()
+        };
+
+        let result = match channel {
+            Some(channel) => {
+                // Random offset within the slot so nodes don't all transmit at its start
+                let tx_at =
+                    Duration::from_millis(OsRng.gen_range(0..=(slot.as_millis() as u64 * 3 / 4)));
+
+                beacon::run_beacon_slot(&mut radio, channel, &data, tx_at, slot, |beacon, rssi| {
+                    if beacon.node_id != node_id {
+                        peers
+                            .lock()
+                            .unwrap()
+                            .update(module, beacon, rssi, Instant::now());
+                    }
+                })
+            }
+            None => PlatformRadioFrame::new_from_slice(&data)
+                .map_err(KaonicError::from)
+                .and_then(|frame| radio.transmit(&frame)),
+        };
+
+        match result {
+            Ok(_) => {
+                stats.tx_packets.fetch_add(1, Ordering::Relaxed);
+                stats
+                    .tx_bytes
+                    .fetch_add(data.len() as u64, Ordering::Relaxed);
+            }
+            Err(e) => {
+                stats.tx_errors.fetch_add(1, Ordering::Relaxed);
+                log::warn!("radio[{module}] beacon transmit error: {e:?}");
+            }
+        }
+    }
+
+    /// Transmits a heartbeat every `interval` while QoS is enabled, so the
+    /// peers' assessment of the link stays current when no data flows
+    #[allow(clippy::too_many_arguments)]
+    async fn transmit_heartbeats(
+        module: usize,
+        radio: SharedRadio,
+        stats: SharedModuleStats,
+        peers: SharedPeerTable,
+        link_qos: SharedLinkQos,
+        node_id: NodeId,
+        interval: Duration,
+        cancel: CancellationToken,
+    ) {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
 
         loop {
-            let mut receive_module = Box::new(ReceiveModule::new());
+            tokio::select! {
+                _ = ticks.tick() => {},
+
+                _ = cancel.cancelled() => {
+                    break;
+                }
+            }
+
+            if !link_qos.lock().unwrap().config().enabled {
+                continue;
+            }
+
+            let radio = radio.clone();
+            let stats = stats.clone();
+            let coding = peers.lock().unwrap().coding();
+
+            // The radio lock is blocking, keep it off the runtime
+            let _ = tokio::task::spawn_blocking(move || {
+                let data = heartbeat::encode(node_id, coding);
+
+                let mut radio = radio.lock().unwrap();
+                if matches!(radio.get_modulation(), Modulation::Off) {
+                    return;
+                }
+
+                let result = PlatformRadioFrame::new_from_slice(&data)
+                    .map_err(KaonicError::from)
+                    .and_then(|frame| radio.transmit(&frame));
+
+                match result {
+                    Ok(_) => {
+                        stats.tx_packets.fetch_add(1, Ordering::Relaxed);
+                        stats
+                            .tx_bytes
+                            .fetch_add(data.len() as u64, Ordering::Relaxed);
+                    }
+                    Err(e) => {
+                        stats.tx_errors.fetch_add(1, Ordering::Relaxed);
+                        log::warn!("radio[{module}] heartbeat transmit error: {e:?}");
+                    }
+                }
+            })
+            .await;
+        }
+    }
+
+    async fn manage_radio(
+        receiver: ModuleReceiver,
+        mut event_recv: watch::Receiver<bool>,
+        cancel: CancellationToken,
+    ) {
+        let ModuleReceiver {
+            module,
+            radio,
+            events,
+            stats,
+            peers,
+            node_id,
+            raw_crc,
+            link_qos,
+            capture,
+            diversity,
+            mut rate_limiter,
+        } = receiver;
 
+        let mut rx_frame = PlatformRadioFrame::new();
+        let mut qos_pending = tokio::time::interval(QOS_PENDING_INTERVAL);
+
+        loop {
             tokio::select! {
                 biased;
 
                 _ = event_recv.changed() => {
-                    let _ = radio.lock().unwrap().update_event();
+                    {
+                        let mut radio = radio.lock().unwrap();
+                        let _ = radio.update_event();
+                        stats.battery_low.store(radio.battery_low(), Ordering::Relaxed);
+                        *stats.irq_counters.lock().unwrap() = radio.irq_counters().ok();
+                    }
 
                     loop {
-                        match radio.lock().unwrap()
-                            .receive(rx_frame.clear(), core::time::Duration::from_millis(2))
-                        {
+                        // Bound first, a guard in the match scrutinee would be
+                        // held through the arms and the flush below would deadlock
+                        let (result, modulation) = {
+                            let mut radio = radio.lock().unwrap();
+                            let result =
+                                radio.receive(rx_frame.clear(), core::time::Duration::from_millis(2));
+                            (result, radio.get_modulation())
+                        };
+
+                        match result {
                             Ok(rr) => {
                                 let frame_len = rx_frame.len() as u64;
                                 stats.rx_packets.fetch_add(1, Ordering::Relaxed);
                                 stats.rx_bytes.fetch_add(frame_len, Ordering::Relaxed);
+                                *stats.last_rssi.lock().unwrap() = Some(rr.rssi);
+
+                                if let Some(beacon) = Beacon::decode(rx_frame.as_slice()) {
+                                    if beacon.node_id != node_id {
+                                        peers.lock().unwrap().update(
+                                            module.into(),
+                                            beacon,
+                                            rr.rssi,
+                                            Instant::now(),
+                                        );
+                                    }
+                                    continue;
+                                }
 
-                                receive_module.module = module.into();
-                                receive_module.frame = RadioFrame::new_from_frame(&rx_frame);
-                                receive_module.rssi = rr.rssi;
+                                let (quality, qos_change, heartbeat) = {
+                                    let mut link_qos = link_qos.lock().unwrap();
+                                    let quality = link_qos.quality();
+                                    let change = link_qos.on_receive(rx_frame.as_slice());
+                                    (quality, change, link_qos.heartbeat())
+                                };
+                                Self::apply_qos_change(module, &radio, &events, qos_change);
 
-                                if let Err(_) = module_rx_send.send(receive_module) {
-                                    log::error!("can't send module-rx event");
+                                // Heartbeats only feed the QoS, like beacons they aren't forwarded
+                                if heartbeat {
+                                    continue;
                                 }
 
-                                receive_module = Box::new(ReceiveModule::new());
+                                if let Some(limiter) = &mut rate_limiter
+                                    && !limiter.admit(Instant::now())
+                                {
+                                    stats.rx_rate_dropped.fetch_add(1, Ordering::Relaxed);
+                                    // Lets a command waiting for the radio in
+                                    // before the next frame is read
+                                    std::thread::yield_now();
+                                    continue;
+                                }
+
+                                // Before the CRC trailer is stripped
+                                capture.lock().unwrap().capture(rx_frame.as_slice(), rr.rssi);
+
+                                let crc_valid = if raw_crc {
+                                    verify_raw_crc(&mut rx_frame)
+                                } else {
+                                    None
+                                };
+
+                                let receive_module = Arc::new(ReceivedFrame {
+                                    receive: ReceiveModule {
+                                        module: module.into(),
+                                        frame: RadioFrame::new_from_frame(&rx_frame),
+                                        rssi: rr.rssi,
+                                    },
+                                    crc_valid,
+                                    modulation,
+                                    quality,
+                                });
+
+                                match &diversity {
+                                    Some(combiner) => {
+                                        if combiner.send(receive_module).await.is_err() {
+                                            break;
+                                        }
+                                    }
+                                    None => events.publish(RadioEvent::Receive(receive_module)),
+                                }
                             }
                             Err(KaonicError::Timeout) => {
                                 break;
                             }
+                            Err(KaonicError::BufferOverrun) => {
+                                stats.rx_overruns.fetch_add(1, Ordering::Relaxed);
+                                log::warn!("radio[{module}] receive buffer overrun, flushing");
+
+                                if let Err(e) = radio.lock().unwrap().flush_receive() {
+                                    log::warn!("radio[{module}] receive flush error: {e:?}");
+                                    break;
+                                }
+                            }
                             Err(e) => {
                                 stats.rx_errors.fetch_add(1, Ordering::Relaxed);
                                 log::warn!("radio[{module}] receive error: {e:?}");
@@ -280,12 +1189,35 @@ impl RadioServer {
                     }
                 },
 
+                _ = qos_pending.tick() => {
+                    let qos_change = link_qos.lock().unwrap().pending_change();
+                    Self::apply_qos_change(module, &radio, &events, qos_change);
+                },
+
                 _ = cancel.cancelled() => {
                     break;
                 }
             }
         }
     }
+
+    /// Reprograms `radio` with the modulation QoS switched to, if any
+    fn apply_qos_change(
+        module: u16,
+        radio: &SharedRadio,
+        events: &EventBus,
+        change: Option<Modulation>,
+    ) {
+        if let Some(modulation) = change {
+            match radio.lock().unwrap().set_modulation(&modulation) {
+                Ok(_) => events.publish(RadioEvent::ModulationChanged {
+                    module: module.into(),
+                    modulation,
+                }),
+                Err(e) => log::warn!("radio[{module}] qos modulation error: {e:?}"),
+            }
+        }
+    }
 }
 
 impl ServerHandler<Message> for RadioServer {
@@ -300,27 +1232,61 @@ impl ServerHandler<Message> for RadioServer {
 
         match request.payload {
             Payload::TransmitModuleRequest(tx) => {
-                if tx.module < self.radios.len() {
+                if tx.module < self.radios.len() && !self.tx_enabled[tx.module] {
+                    log::warn!("radio[{}] transmit disabled", tx.module);
+                    response.payload = Payload::Error;
+                } else if tx.module < self.radios.len() {
+                    let _in_flight = InFlight::new(&self.stats[tx.module].tx_in_flight);
                     let mut radio = self.radios[tx.module].lock().unwrap();
                     let frame_len = tx.frame.as_slice().len() as u64;
 
-                    if let Ok(_) =
-                        radio.transmit(&PlatformRadioFrame::new_from_slice(tx.frame.as_slice()))
-                    {
+                    let Ok(mut tx_frame) = PlatformRadioFrame::new_from_slice(tx.frame.as_slice())
+                    else {
+                        log::warn!("radio[{}] frame too long", tx.module);
+                        response.payload = Payload::Error;
+                        return Some(response);
+                    };
+                    let raw_crc = *self.stats[tx.module].integrity.lock().unwrap()
+                        == FrameIntegrity::SoftwareCrc;
+                    if raw_crc && append_raw_crc(&mut tx_frame).is_err() {
+                        log::warn!("radio[{}] frame too long for the crc", tx.module);
+                        response.payload = Payload::Error;
+                        return Some(response);
+                    }
+
+                    let modulation = radio.get_modulation();
+                    let result = radio.transmit(&tx_frame);
+                    let report = radio.last_transmit();
+
+                    self.stats[tx.module]
+                        .tx_retries
+                        .fetch_add(report.attempts.saturating_sub(1).into(), Ordering::Relaxed);
+
+                    if result.is_ok() {
                         self.stats[tx.module]
                             .tx_packets
                             .fetch_add(1, Ordering::Relaxed);
                         self.stats[tx.module]
                             .tx_bytes
                             .fetch_add(frame_len, Ordering::Relaxed);
-                        let _ = self.module_tx_send.send(Box::new(tx));
-                        response.payload = Payload::TransmitModuleResponse;
                     } else {
                         self.stats[tx.module]
                             .tx_errors
                             .fetch_add(1, Ordering::Relaxed);
-                        response.payload = Payload::Error;
                     }
+
+                    self.transmits.publish(Arc::new(TransmitEvent::new(
+                        tx.module,
+                        &tx_frame,
+                        request.id,
+                        &result,
+                        report,
+                        &modulation,
+                        start_time,
+                    )));
+
+                    response.payload =
+                        Payload::TransmitModuleReport(transmit_report_to_ctrl(report));
                 } else {
                     response.payload = Payload::Error;
                 }
@@ -402,6 +1368,74 @@ impl ServerHandler<Message> for RadioServer {
                     response.payload = Payload::Error;
                 }
             }
+            Payload::GetCapabilitiesRequest => {
+                response.payload = Payload::GetCapabilitiesResponse(self.capabilities.clone());
+            }
+            Payload::GetFrequencyPlansRequest => {
+                response.payload = Payload::GetFrequencyPlansResponse(GetFrequencyPlansResponse {
+                    plans: FREQUENCY_PLANS
+                        .iter()
+                        .map(|plan| FrequencyPlan {
+                            name: plan.name.to_string(),
+                            band_start: plan.band_start,
+                            band_end: plan.band_end,
+                            freq: plan.freq,
+                            channel_spacing: plan.channel_spacing,
+                            channel_count: plan.channel_count,
+                        })
+                        .collect(),
+                });
+            }
+            Payload::GetQosRequest(get) => {
+                if get.module < self.qos.len() {
+                    let qos = self.qos[get.module].lock().unwrap();
+                    response.payload = Payload::GetQosResponse(qos_to_ctrl(get.module, &qos));
+                } else {
+                    response.payload = Payload::Error;
+                }
+            }
+            Payload::SetQosRequest(set) => {
+                if set.module < self.qos.len() {
+                    let mut qos = self.qos[set.module].lock().unwrap();
+                    let config = qos_config_from_ctrl(&set, qos.config());
+                    qos.set_config(config);
+
+                    response.payload = Payload::SetQosResponse;
+                } else {
+                    response.payload = Payload::Error;
+                }
+            }
+            Payload::ApplyConfigRequest(apply) => {
+                if apply.module < self.radios.len() {
+                    // The radio stays locked until the QoS follows, so neither
+                    // a transmission nor a QoS step sees half of the change
+                    let mut radio = self.radios[apply.module].lock().unwrap();
+                    let result = match apply.modulation {
+                        Some(modulation) => {
+                            radio.set_config_with_modulation(&apply.config, &modulation)
+                        }
+                        None => radio.set_config(&apply.config),
+                    };
+
+                    match result {
+                        Ok(()) => {
+                            if let Some(settings) = apply.qos {
+                                let mut qos = self.qos[apply.module].lock().unwrap();
+                                let config = qos_config_from_ctrl(&settings, qos.config());
+                                qos.set_config(config);
+                            }
+
+                            response.payload = Payload::ApplyConfigResponse;
+                        }
+                        Err(e) => {
+                            log::warn!("radio[{}] apply config error: {e:?}", apply.module);
+                            response.payload = Payload::Error;
+                        }
+                    }
+                } else {
+                    response.payload = Payload::Error;
+                }
+            }
             Payload::Ping => {
                 response.payload = Payload::Pong;
             }
@@ -421,10 +1455,70 @@ impl ServerHandler<Message> for RadioServer {
 fn radio_event_thread(
     event: Arc<std::sync::Mutex<PlatformRadioEvent>>,
     notify: tokio::sync::watch::Sender<bool>,
+    cancel: CancellationToken,
 ) {
-    loop {
-        if event.lock().unwrap().wait_for_event(None) {
+    while !cancel.is_cancelled() {
+        if event
+            .lock()
+            .unwrap()
+            .wait_for_event(Some(EVENT_WAIT_TIMEOUT))
+        {
             let _ = notify.send(true);
         }
     }
 }
+
+fn transmit_report_to_ctrl(report: radio::TransmitReport) -> TransmitReport {
+    TransmitReport {
+        attempts: report.attempts,
+        result: match report.result {
+            radio::TransmitResult::Sent => TransmitResult::Sent,
+            radio::TransmitResult::ChannelBusy => TransmitResult::ChannelBusy,
+            radio::TransmitResult::MaxRetries => TransmitResult::MaxRetries,
+        },
+    }
+}
+
+fn channel_quality_to_ctrl(quality: ChannelQuality) -> CtrlChannelQuality {
+    match quality {
+        ChannelQuality::Excellent => CtrlChannelQuality::Excellent,
+        ChannelQuality::Good => CtrlChannelQuality::Good,
+        ChannelQuality::Fair => CtrlChannelQuality::Fair,
+        ChannelQuality::Poor => CtrlChannelQuality::Poor,
+        ChannelQuality::Bad => CtrlChannelQuality::Bad,
+    }
+}
+
+fn channel_quality_from_ctrl(quality: CtrlChannelQuality) -> ChannelQuality {
+    match quality {
+        CtrlChannelQuality::Excellent => ChannelQuality::Excellent,
+        CtrlChannelQuality::Good => ChannelQuality::Good,
+        CtrlChannelQuality::Fair => ChannelQuality::Fair,
+        CtrlChannelQuality::Poor => ChannelQuality::Poor,
+        CtrlChannelQuality::Bad => ChannelQuality::Bad,
+    }
+}
+
+/// QoS config with the settings a client can change replaced by `settings`
+fn qos_config_from_ctrl(settings: &QosSettings, current: &QosConfig) -> QosConfig {
+    QosConfig {
+        enabled: settings.enabled,
+        per_threshold: settings.per_threshold,
+        adaptive_modulation_type: settings.adaptive_modulation_type,
+        qpsk_crossover: channel_quality_from_ctrl(settings.qpsk_crossover),
+        ..current.clone()
+    }
+}
+
+fn qos_to_ctrl(module: usize, qos: &LinkQos) -> QosSettings {
+    let config = qos.config();
+
+    QosSettings {
+        module,
+        enabled: config.enabled,
+        per_threshold: config.per_threshold,
+        adaptive_modulation_type: config.adaptive_modulation_type,
+        qpsk_crossover: channel_quality_to_ctrl(config.qpsk_crossover),
+        quality: channel_quality_to_ctrl(qos.quality()),
+    }
+}
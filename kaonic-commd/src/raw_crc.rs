@@ -0,0 +1,163 @@
+use kaonic_frame::{error::FrameError, frame::Frame};
+use kaonic_net::packet::crc32;
+use kaonic_radio::{error::KaonicError, radio::Radio};
+
+use crate::{config::TransmitConfig, decoder::is_coded_frame};
+
+/// Length of the CRC-32 trailer in bytes
+pub const CRC_LEN: usize = 4;
+
+/// Appends the CRC-32 (little endian) of the frame contents to the frame.
+pub fn append_crc<const S: usize>(frame: &mut Frame<S>) -> Result<(), FrameError> {
+    let crc = crc32(frame.as_slice());
+    frame.push_data(&crc.to_le_bytes()).map(|_| ())
+}
+
+/// Checks the CRC-32 trailer of a received frame and strips it on a match.
+///
+/// A frame that is too short or doesn't match its trailer is left untouched.
+pub fn verify_crc<const S: usize>(frame: &mut Frame<S>) -> bool {
+    let len = frame.len();
+    if len < CRC_LEN {
+        return false;
+    }
+
+    let (data, trailer) = frame.as_slice().split_at(len - CRC_LEN);
+    if crc32(data).to_le_bytes() != trailer {
+        return false;
+    }
+
+    frame.resize(len - CRC_LEN);
+    true
+}
+
+/// Appends the CRC-32 to a raw frame, kaonic-net frames are left as they are.
+///
+/// kaonic-net frames carry their own CRC inside the LDPC coded packet and a
+/// trailer would break their codeword layout.
+pub fn append_raw_crc<const S: usize>(frame: &mut Frame<S>) -> Result<(), FrameError> {
+    if is_coded_frame(frame.as_slice()) {
+        return Ok(());
+    }
+
+    append_crc(frame)
+}
+
+/// Checks a received frame sent with [`append_raw_crc`].
+///
+/// Returns `None` for a kaonic-net frame, otherwise whether the CRC-32 trailer
+/// matched. A raw frame plus trailer can have the length of a kaonic-net frame,
+/// so the trailer is checked first.
+pub fn verify_raw_crc<const S: usize>(frame: &mut Frame<S>) -> Option<bool> {
+    if verify_crc(frame) {
+        return Some(true);
+    }
+
+    (!is_coded_frame(frame.as_slice())).then_some(false)
+}
+
+/// How the frames of a module are protected against corruption
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameIntegrity {
+    /// Frames are sent and delivered as they are
+    #[default]
+    None,
+    /// CRC-32 trailer on raw frames, see [`append_raw_crc`]
+    SoftwareCrc,
+    /// FCS appended and checked by the transceiver
+    HardwareFcs,
+}
+
+/// Sets up the frame integrity `config` asks for on `radio`
+///
+/// A transceiver without hardware FCS falls back to the software CRC, so
+/// frames aren't silently left unprotected.
+pub fn setup_integrity<R: Radio>(
+    module: usize,
+    radio: &mut R,
+    config: &TransmitConfig,
+) -> FrameIntegrity {
+    let integrity = if !config.hardware_fcs {
+        if config.raw_crc {
+            FrameIntegrity::SoftwareCrc
+        } else {
+            FrameIntegrity::None
+        }
+    } else {
+        match radio.set_hardware_fcs(true) {
+            Ok(()) => FrameIntegrity::HardwareFcs,
+            Err(KaonicError::NotSupported) => {
+                log::warn!("radio[{module}] has no hardware FCS, falling back to software CRC");
+                FrameIntegrity::SoftwareCrc
+            }
+            Err(e) => {
+                log::warn!(
+                    "radio[{module}] hardware FCS not configured, falling back to software CRC: {e:?}"
+                );
+                FrameIntegrity::SoftwareCrc
+            }
+        }
+    };
+
+    log::info!("radio[{module}] frame integrity: {integrity:?}");
+
+    integrity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kaonic_net::coder::{HEADER_LDPC_CODE, PAYLOAD_LDPC_CODE};
+
+    #[test]
+    fn test_crc_roundtrip() {
+        let mut frame = Frame::<64>::new_from_slice(b"raw frame").unwrap();
+        append_crc(&mut frame).unwrap();
+        assert_eq!(frame.len(), 9 + CRC_LEN);
+
+        assert!(verify_crc(&mut frame));
+        assert_eq!(frame.as_slice(), b"raw frame");
+    }
+
+    #[test]
+    fn test_corrupted_frame_is_flagged() {
+        let mut frame = Frame::<64>::new_from_slice(b"raw frame").unwrap();
+        append_crc(&mut frame).unwrap();
+        frame.as_slice_mut()[3] ^= 0x10;
+
+        assert!(!verify_crc(&mut frame));
+        assert_eq!(frame.len(), 9 + CRC_LEN);
+
+        assert!(!verify_crc(
+            &mut Frame::<64>::new_from_slice(&[0xAA, 0x55]).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_coded_frames_are_left_alone() {
+        let coded = [0x5Au8; HEADER_LDPC_CODE.n() / 8 + PAYLOAD_LDPC_CODE.n() / 8];
+        assert!(is_coded_frame(&coded));
+
+        let mut frame = Frame::<2048>::new_from_slice(&coded).unwrap();
+        append_raw_crc(&mut frame).unwrap();
+        assert_eq!(frame.as_slice(), &coded);
+        assert_eq!(verify_raw_crc(&mut frame), None);
+        assert_eq!(frame.as_slice(), &coded);
+
+        let mut frame = Frame::<2048>::new_from_slice(b"raw frame").unwrap();
+        append_raw_crc(&mut frame).unwrap();
+        assert_eq!(frame.len(), 9 + CRC_LEN);
+        assert_eq!(verify_raw_crc(&mut frame), Some(true));
+
+        append_raw_crc(&mut frame).unwrap();
+        frame.as_slice_mut()[0] ^= 0x01;
+        assert_eq!(verify_raw_crc(&mut frame), Some(false));
+    }
+
+    #[test]
+    fn test_append_to_full_frame_fails() {
+        let mut frame = Frame::<8>::new_from_slice(&[0u8; 6]).unwrap();
+        assert!(append_crc(&mut frame).is_err());
+    }
+}
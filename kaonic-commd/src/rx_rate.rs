@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+/// Window the receive rate is counted over
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Caps the received frames a module forwards per second
+///
+/// Frames above the rate are only counted and fed to the QoS, so a flood of
+/// short frames can't keep the receive loop busy forwarding them while
+/// commands wait for the radio.
+#[derive(Debug)]
+pub struct ReceiveRateLimiter {
+    module: u16,
+    max_rate: u32,
+    window_start: Option<Instant>,
+    forwarded: u32,
+    dropped: u64,
+    /// The previous window dropped frames too
+    limiting: bool,
+}
+
+impl ReceiveRateLimiter {
+    pub fn new(module: u16, max_rate: u32) -> Self {
+        Self {
+            module,
+            max_rate,
+            window_start: None,
+            forwarded: 0,
+            dropped: 0,
+            limiting: false,
+        }
+    }
+
+    /// Whether the frame received at `now` is forwarded
+    pub fn admit(&mut self, now: Instant) -> bool {
+        let start = *self.window_start.get_or_insert(now);
+
+        if now.duration_since(start) >= RATE_WINDOW {
+            if self.dropped > 0 {
+                log::info!(
+                    "radio[{}] dropped {} frames above {} frames/s",
+                    self.module,
+                    self.dropped,
+                    self.max_rate
+                );
+            }
+
+            self.limiting = self.dropped > 0;
+            self.window_start = Some(now);
+            self.forwarded = 0;
+            self.dropped = 0;
+        }
+
+        if self.forwarded < self.max_rate {
+            self.forwarded += 1;
+            return true;
+        }
+
+        if self.dropped == 0 && !self.limiting {
+            log::warn!(
+                "radio[{}] receive rate above {} frames/s, dropping excess frames",
+                self.module,
+                self.max_rate
+            );
+        }
+
+        self.dropped += 1;
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_drops_above_rate() {
+        let start = Instant::now();
+        let mut limiter = ReceiveRateLimiter::new(0, 3);
+
+        let admitted = (0..10)
+            .filter(|i| limiter.admit(start + Duration::from_millis(i * 10)))
+            .count();
+        assert_eq!(admitted, 3);
+
+        // The next window starts over
+        assert!(limiter.admit(start + RATE_WINDOW));
+        assert!(limiter.admit(start + RATE_WINDOW));
+        assert!(limiter.admit(start + RATE_WINDOW));
+        assert!(!limiter.admit(start + RATE_WINDOW));
+    }
+}
@@ -0,0 +1,230 @@
+//! Ordered shutdown of the daemon.
+//!
+//! Stopping everything at once by dropping the runtime loses whatever the
+//! radio workers were doing, including the final statistics. [`shutdown`]
+//! stops the servers first, then waits for transmissions already handed to a
+//! radio, joins the workers and only then logs the statistics.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use tokio::{task::JoinHandle, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::radio_server::{SharedModuleStats, SharedRadio};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Radio worker tasks, event and receive threads of a [`RadioServer`](crate::radio_server::RadioServer)
+#[derive(Default)]
+pub struct Workers {
+    pub tasks: Vec<JoinHandle<()>>,
+    pub threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+/// Counts a transmission as in flight until dropped
+pub struct InFlight<'a>(&'a AtomicU64);
+
+impl<'a> InFlight<'a> {
+    pub fn new(counter: &'a AtomicU64) -> Self {
+        counter.fetch_add(1, Ordering::AcqRel);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Polls `done` until it returns `true` or `deadline` passes.
+async fn wait_until(deadline: Instant, mut done: impl FnMut() -> bool) -> bool {
+    while !done() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    true
+}
+
+/// Stops the daemon in order and logs the final per-module statistics.
+///
+/// 1. Cancels `cancel`, so the servers stop accepting and open streams end.
+/// 2. Waits for the `servers` to drain their in-flight requests.
+/// 3. Waits for UDP transmissions still holding a radio.
+/// 4. Joins the radio `workers`.
+/// 5. Logs the statistics and flushes the logger.
+///
+/// Each step gives up once `timeout` has passed since the start. Returns
+/// `false` if anything had to be abandoned.
+pub async fn shutdown(
+    cancel: CancellationToken,
+    servers: Vec<JoinHandle<()>>,
+    workers: Workers,
+    radios: &[SharedRadio],
+    stats: &[SharedModuleStats],
+    timeout: Duration,
+) -> bool {
+    let deadline = Instant::now() + timeout;
+    let mut clean = true;
+
+    cancel.cancel();
+
+    for server in servers {
+        if tokio::time::timeout_at(deadline, server).await.is_err() {
+            log::warn!("shutdown: server didn't drain in time");
+            clean = false;
+        }
+    }
+
+    let idle = wait_until(deadline, || {
+        stats
+            .iter()
+            .all(|stats| stats.tx_in_flight.load(Ordering::Acquire) == 0)
+    })
+    .await;
+    if !idle {
+        log::warn!("shutdown: transmissions still in flight");
+        clean = false;
+    }
+
+    for task in workers.tasks {
+        if tokio::time::timeout_at(deadline, task).await.is_err() {
+            log::warn!("shutdown: radio worker didn't stop in time");
+            clean = false;
+        }
+    }
+
+    let threads = workers.threads;
+    if wait_until(deadline, || threads.iter().all(|t| t.is_finished())).await {
+        for thread in threads {
+            let _ = thread.join();
+        }
+    } else {
+        log::warn!("shutdown: radio threads didn't stop in time");
+        clean = false;
+    }
+
+    for (module, (radio, stats)) in radios.iter().zip(stats).enumerate() {
+        // Taking the lock makes sure nothing is still using the radio
+        if radio.try_lock().is_err() {
+            log::warn!("radio[{module}] still busy at shutdown");
+            clean = false;
+        }
+
+        log::info!(
+            "radio[{module}] final stats: rx {} packets {} bytes, {} errors, {} overruns; \
+             tx {} packets {} bytes, {} errors",
+            stats.rx_packets.load(Ordering::Relaxed),
+            stats.rx_bytes.load(Ordering::Relaxed),
+            stats.rx_errors.load(Ordering::Relaxed),
+            stats.rx_overruns.load(Ordering::Relaxed),
+            stats.tx_packets.load(Ordering::Relaxed),
+            stats.tx_bytes.load(Ordering::Relaxed),
+            stats.tx_errors.load(Ordering::Relaxed),
+        );
+    }
+
+    log::logger().flush();
+
+    clean
+}
+
+#[cfg(all(test, feature = "machine-host"))]
+mod tests {
+    use std::sync::mpsc as std_mpsc;
+
+    use kaonic_ctrl::{
+        protocol::{MessageBuilder, Payload, RADIO_FRAME_SIZE, RadioFrame, TransmitModule},
+        server::ServerHandler,
+    };
+    use kaonic_radio::platform::PlatformRadioFrame;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{config::CommdConfig, radio_server::RadioServer};
+
+    // The radio workers block their runtime thread while waiting for the radio
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_shutdown_accounts_transmit_in_flight() {
+        let cancel = CancellationToken::new();
+        let (client_send, _client_recv) = mpsc::channel(16);
+        let mut server = RadioServer::new(
+            client_send,
+            cancel.clone(),
+            "test".to_string(),
+            RADIO_FRAME_SIZE,
+            CommdConfig::default(),
+        )
+        .expect("radio server");
+        let radios = server.radios();
+        let stats = server.stats();
+        let workers = server.take_workers();
+
+        // Something else holds the radio, so the transmit below is stuck in flight
+        let (locked_send, locked_recv) = std_mpsc::channel();
+        let (release_send, release_recv) = std_mpsc::channel::<()>();
+        let holder = {
+            let radio = radios[0].clone();
+            std::thread::spawn(move || {
+                let _radio = radio.lock().unwrap();
+                locked_send.send(()).unwrap();
+                let _ = release_recv.recv();
+            })
+        };
+        locked_recv.recv().unwrap();
+
+        let request = MessageBuilder::new()
+            .with_id(7)
+            .with_payload(Payload::TransmitModuleRequest(TransmitModule {
+                module: 0,
+                frame: RadioFrame::new_from_frame(
+                    &PlatformRadioFrame::new_from_slice(&[0xA5; 16]).unwrap(),
+                ),
+            }))
+            .build();
+        let transmit = std::thread::spawn(move || {
+            let response = server.new_message();
+            server.handle_message(&request, response)
+        });
+
+        let started = wait_until(Instant::now() + Duration::from_secs(1), || {
+            stats[0].tx_in_flight.load(Ordering::Acquire) == 1
+        })
+        .await;
+        assert!(started, "transmit in flight");
+
+        let shutdown = {
+            let radios = radios.clone();
+            let stats = stats.clone();
+            tokio::spawn(async move {
+                shutdown(
+                    cancel,
+                    Vec::new(),
+                    workers,
+                    &radios,
+                    &stats,
+                    Duration::from_secs(5),
+                )
+                .await
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!shutdown.is_finished(), "shutdown waits for the transmit");
+
+        release_send.send(()).unwrap();
+        assert!(shutdown.await.expect("shutdown task"), "clean shutdown");
+
+        holder.join().unwrap();
+        assert!(transmit.join().unwrap().is_some());
+        assert_eq!(stats[0].tx_in_flight.load(Ordering::Acquire), 0);
+        assert_eq!(stats[0].tx_packets.load(Ordering::Relaxed), 1);
+        assert_eq!(stats[0].tx_bytes.load(Ordering::Relaxed), 16);
+    }
+}
@@ -0,0 +1,335 @@
+//! Live tap of the received frames.
+//!
+//! Started with `--rx-tap <path>` commd also writes the payload of every
+//! received frame to `path`, a named pipe or a file, or to stdout for `-`.
+//! Command line tools on the device can follow the traffic that way without
+//! a gRPC client. The tap never holds up the radio: frames arriving while
+//! the reader is slow or absent are dropped.
+
+use std::{fmt::Write as _, path::PathBuf, str::FromStr, time::Duration};
+
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::broadcast,
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{events::RadioEvent, radio_server::ReceivedFrame};
+
+/// Pause before reopening the tap after its reader went away
+const REOPEN_DELAY: Duration = Duration::from_millis(500);
+
+/// Pause between attempts to open a named pipe that has no reader yet
+#[cfg(target_os = "linux")]
+const READER_POLL: Duration = Duration::from_millis(100);
+
+/// How each frame is written to the tap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TapFormat {
+    /// `<module> <rssi> <payload as hex>` line per frame
+    #[default]
+    Hex,
+    /// Module, RSSI as `i8`, payload length as little endian `u16`, payload
+    Raw,
+}
+
+impl FromStr for TapFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("unknown tap format '{s}', expected hex or raw")),
+        }
+    }
+}
+
+/// Where the tap writes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapTarget {
+    Stdout,
+    /// Named pipe or file, appended to
+    Path(PathBuf),
+}
+
+impl From<&str> for TapTarget {
+    fn from(s: &str) -> Self {
+        match s {
+            "-" => Self::Stdout,
+            path => Self::Path(path.into()),
+        }
+    }
+}
+
+/// Starts writing the frames received from `events` to `target` until `cancel`
+pub fn spawn_rx_tap(
+    target: TapTarget,
+    format: TapFormat,
+    mut events: broadcast::Receiver<RadioEvent>,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut line = Vec::new();
+
+        loop {
+            // A named pipe is only opened once it has a reader
+            let mut out = tokio::select! {
+                out = open(&target) => match out {
+                    Ok(out) => out,
+                    Err(e) => {
+                        log::error!("rx tap {target:?} can't be opened: {e}");
+                        return;
+                    }
+                },
+                _ = cancel.cancelled() => return,
+            };
+
+            // Frames received while nobody was reading aren't kept
+            events = events.resubscribe();
+            log::info!("rx tap {target:?} opened");
+
+            loop {
+                let rx = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(RadioEvent::Receive(rx)) => rx,
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            log::warn!("rx tap dropped {skipped} events");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    },
+                    _ = cancel.cancelled() => return,
+                };
+
+                // The payload of a frame failing the CRC check isn't what was sent
+                if rx.crc_valid == Some(false) {
+                    continue;
+                }
+
+                line.clear();
+                encode(format, &rx, &mut line);
+
+                if let Err(e) = write(&mut out, &line).await {
+                    log::warn!("rx tap {target:?} closed: {e}");
+                    break;
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(REOPEN_DELAY) => {}
+                _ = cancel.cancelled() => return,
+            }
+        }
+    })
+}
+
+async fn open(target: &TapTarget) -> std::io::Result<Box<dyn AsyncWrite + Send + Unpin>> {
+    match target {
+        TapTarget::Stdout => Ok(Box::new(tokio::io::stdout())),
+        TapTarget::Path(path) => {
+            #[cfg(target_os = "linux")]
+            if is_fifo(path).await {
+                return Ok(Box::new(open_fifo(path).await?));
+            }
+
+            let file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .await?;
+            Ok(Box::new(file))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn is_fifo(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+/// Opens the write end of a named pipe without blocking a thread on it
+///
+/// Without a reader the open fails with `ENXIO`, it's retried then.
+#[cfg(target_os = "linux")]
+async fn open_fifo(path: &std::path::Path) -> std::io::Result<tokio::net::unix::pipe::Sender> {
+    loop {
+        match tokio::net::unix::pipe::OpenOptions::new().open_sender(path) {
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => {
+                tokio::time::sleep(READER_POLL).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn write(out: &mut (dyn AsyncWrite + Send + Unpin), line: &[u8]) -> std::io::Result<()> {
+    out.write_all(line).await?;
+    out.flush().await
+}
+
+fn encode(format: TapFormat, rx: &ReceivedFrame, out: &mut Vec<u8>) {
+    let payload = rx.receive.frame.as_slice();
+
+    match format {
+        TapFormat::Hex => {
+            let mut line = format!("{} {} ", rx.receive.module, rx.receive.rssi);
+            for byte in payload {
+                let _ = write!(line, "{byte:02x}");
+            }
+            line.push('\n');
+            out.extend_from_slice(line.as_bytes());
+        }
+        TapFormat::Raw => {
+            out.push(rx.receive.module as u8);
+            out.push(rx.receive.rssi as u8);
+            out.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+            out.extend_from_slice(payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use kaonic_ctrl::protocol::{RadioFrame, ReceiveModule};
+    use kaonic_qos::ChannelQuality;
+    use radio_common::modulation::Modulation;
+
+    use super::*;
+    use crate::events::EventBus;
+
+    fn received(module: usize, rssi: i8, payload: &[u8], crc_valid: Option<bool>) -> RadioEvent {
+        let mut frame = RadioFrame::new();
+        frame.data[..payload.len()].copy_from_slice(payload);
+        frame.len = payload.len() as u16;
+
+        RadioEvent::Receive(Arc::new(ReceivedFrame {
+            receive: ReceiveModule {
+                module,
+                frame,
+                rssi,
+            },
+            crc_valid,
+            modulation: Modulation::Off,
+            quality: ChannelQuality::Good,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_rx_tap_writes_received_frames() {
+        let path = std::env::temp_dir().join(format!("kaonic-rx-tap-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let bus = EventBus::new(16);
+        let cancel = CancellationToken::new();
+        let tap = spawn_rx_tap(
+            TapTarget::Path(path.clone()),
+            TapFormat::Hex,
+            bus.subscribe(),
+            cancel.clone(),
+        );
+
+        // Wait for the tap to be open, frames before that are dropped
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        bus.publish(received(0, -72, &[0xCA, 0xFE], None));
+        bus.publish(received(1, -90, &[0x01], Some(false)));
+        bus.publish(RadioEvent::PeerDown { node_id: 1 });
+        bus.publish(received(1, -40, &[0x00, 0x10, 0xFF], Some(true)));
+
+        let expected = "0 -72 cafe\n1 -40 0010ff\n";
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+        while std::fs::read_to_string(&path).unwrap() != expected {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "tap output incomplete"
+            );
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        cancel.cancel();
+        tap.await.unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let RadioEvent::Receive(rx) = received(2, -1, &[0xAB, 0xCD], None) else {
+            unreachable!()
+        };
+        let mut raw = Vec::new();
+        encode(TapFormat::Raw, &rx, &mut raw);
+        assert_eq!(raw, [2, 0xFF, 2, 0, 0xAB, 0xCD]);
+
+        assert_eq!("raw".parse(), Ok(TapFormat::Raw));
+        assert!("bin".parse::<TapFormat>().is_err());
+        assert_eq!(TapTarget::from("-"), TapTarget::Stdout);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_rx_tap_waits_for_fifo_reader() {
+        use std::io::BufRead;
+
+        let path = std::env::temp_dir().join(format!("kaonic-rx-fifo-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        let bus = EventBus::new(16);
+
+        // Nobody reads, cancelling doesn't wait on the open
+        let cancel = CancellationToken::new();
+        let tap = spawn_rx_tap(
+            TapTarget::Path(path.clone()),
+            TapFormat::Hex,
+            bus.subscribe(),
+            cancel.clone(),
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+        tokio::time::timeout(Duration::from_secs(1), tap)
+            .await
+            .expect("tap stopped without a reader")
+            .unwrap();
+
+        // A reader showing up later gets the frames from then on
+        let cancel = CancellationToken::new();
+        let tap = spawn_rx_tap(
+            TapTarget::Path(path.clone()),
+            TapFormat::Hex,
+            bus.subscribe(),
+            cancel.clone(),
+        );
+        let reader = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || {
+                let mut line = String::new();
+                std::io::BufReader::new(std::fs::File::open(path).unwrap())
+                    .read_line(&mut line)
+                    .unwrap();
+                line
+            })
+        };
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+        while !reader.is_finished() {
+            assert!(tokio::time::Instant::now() < deadline, "no frame read");
+            bus.publish(received(0, -60, &[0xBE, 0xEF], None));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(reader.await.unwrap(), "0 -60 beef\n");
+
+        cancel.cancel();
+        tap.await.unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,73 @@
+use radio_common::Modulation;
+
+/// Hysteresis below the throttle threshold before full power is restored
+const THROTTLE_HYSTERESIS_CELSIUS: f32 = 5.0;
+
+/// TX power throttling driven by transceiver temperature
+pub struct ThermalThrottle {
+    threshold: f32,
+    tx_power: u8,
+    restore_tx_power: Option<u8>,
+}
+
+impl ThermalThrottle {
+    pub fn new(threshold: f32, tx_power: u8) -> Self {
+        Self {
+            threshold,
+            tx_power,
+            restore_tx_power: None,
+        }
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.restore_tx_power.is_some()
+    }
+
+    /// Returns the modulation to apply when throttling starts or ends
+    pub fn update(&mut self, temperature: f32, current: &Modulation) -> Option<Modulation> {
+        let mut modulation = *current;
+
+        match self.restore_tx_power {
+            None if temperature >= self.threshold && current.tx_power() > self.tx_power => {
+                self.restore_tx_power = Some(current.tx_power());
+                modulation.set_tx_power(self.tx_power);
+                Some(modulation)
+            }
+            Some(tx_power) if temperature < self.threshold - THROTTLE_HYSTERESIS_CELSIUS => {
+                self.restore_tx_power = None;
+                modulation.set_tx_power(tx_power);
+                Some(modulation)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use radio_common::modulation::OfdmModulation;
+
+    use super::*;
+
+    #[test]
+    fn test_throttle_and_restore() {
+        let mut throttle = ThermalThrottle::new(80.0, 10);
+        let modulation = Modulation::Ofdm(OfdmModulation {
+            tx_power: 20,
+            ..Default::default()
+        });
+
+        assert!(throttle.update(60.0, &modulation).is_none());
+
+        let throttled = throttle.update(82.0, &modulation).expect("throttled");
+        assert_eq!(throttled.tx_power(), 10);
+        assert!(throttle.is_throttled());
+
+        // Within hysteresis
+        assert!(throttle.update(78.0, &throttled).is_none());
+
+        let restored = throttle.update(70.0, &throttled).expect("restored");
+        assert_eq!(restored.tx_power(), 20);
+        assert!(!throttle.is_throttled());
+    }
+}
@@ -0,0 +1,369 @@
+//! Per-module transmit worker for gRPC transmit requests.
+//!
+//! A frame may come with a modulation of its own, which reprograms the
+//! transceivers (see [`Radio::transmit_with_modulation`]). The worker takes
+//! every request that queued up while the radio was busy, sends them grouped
+//! by modulation and then puts the module back on its own modulation. A burst
+//! of mixed frames costs one switch per extra modulation plus the restore
+//! instead of one per frame.
+//!
+//! Every frame ends in a [`TransmitEvent`], whether it was sent, given up on
+//! or refused by the radio.
+//!
+//! A module following the link of a peer switches to its channel and
+//! modulation before the next batch, see
+//! [`crate::beacon::PeerTable::take_pending_link`].
+//!
+//! With power verification on, the worker reads the transmit power back from
+//! the transceiver after each frame and warns if it isn't the power the
+//! modulation asked for, which catches register writes that didn't take.
+
+use std::{sync::Arc, time::Instant};
+
+use kaonic_radio::{
+    error::KaonicError,
+    platform::{PlatformRadio, PlatformRadioFrame},
+    radio::{Radio, TransmitReport},
+};
+use radio_common::modulation::Modulation;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    beacon::LinkAdvert,
+    events::TransmitBus,
+    radio_server::{SharedPeerTable, SharedRadio, TransmitEvent},
+};
+
+/// Requests waiting for a module before `Transmit` calls have to wait
+const TRANSMIT_QUEUE_CAPACITY: usize = 64;
+
+/// Most frames sent under one hold of the radio, bounds the receive gap
+const MAX_TRANSMIT_BATCH: usize = 16;
+
+/// Frame waiting for its module's transmit worker
+pub struct TransmitJob {
+    pub frame: PlatformRadioFrame,
+    /// Sent with the module's own modulation if unset
+    pub modulation: Option<Modulation>,
+    /// Client tag echoed in the [`TransmitEvent`]
+    pub seq: u32,
+    /// Start of the event latency
+    pub requested: Instant,
+    /// Unset for batched frames, which only report through the event
+    pub reply: Option<oneshot::Sender<TransmitOutcome>>,
+}
+
+/// What the radio made of a [`TransmitJob`]
+pub struct TransmitOutcome {
+    pub result: Result<(), KaonicError>,
+    pub report: TransmitReport,
+    /// Transmit power the frame went out with, read back if verified
+    pub tx_power: u8,
+}
+
+pub type TransmitQueue = mpsc::Sender<TransmitJob>;
+
+/// Starts the transmit worker of `module`, it stops on `cancel`.
+///
+/// With `verify_tx_power` the power of each frame is read back from the radio.
+pub fn spawn_transmit_queue(
+    module: usize,
+    radio: SharedRadio,
+    peers: SharedPeerTable,
+    transmits: TransmitBus,
+    verify_tx_power: bool,
+    cancel: CancellationToken,
+) -> (TransmitQueue, JoinHandle<()>) {
+    let (queue, jobs) = mpsc::channel(TRANSMIT_QUEUE_CAPACITY);
+
+    let task = tokio::spawn(Box::pin(async move {
+        run_transmit_queue(
+            module,
+            radio,
+            peers,
+            transmits,
+            verify_tx_power,
+            jobs,
+            cancel,
+        )
+        .await;
+    }));
+
+    (queue, task)
+}
+
+async fn run_transmit_queue(
+    module: usize,
+    radio: SharedRadio,
+    peers: SharedPeerTable,
+    transmits: TransmitBus,
+    verify_tx_power: bool,
+    mut jobs: mpsc::Receiver<TransmitJob>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let job = tokio::select! {
+            biased;
+
+            _ = cancel.cancelled() => break,
+
+            job = jobs.recv() => match job {
+                Some(job) => job,
+                None => break,
+            },
+        };
+
+        let mut batch = vec![job];
+        while batch.len() < MAX_TRANSMIT_BATCH
+            && let Ok(job) = jobs.try_recv()
+        {
+            batch.push(job);
+        }
+
+        let link = peers.lock().unwrap().take_pending_link(module);
+
+        let radio = radio.clone();
+        let transmits = transmits.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut radio = radio.lock().unwrap();
+
+            let followed = link.filter(|link| follow_link(module, &mut radio, link));
+
+            transmit_batch(module, &mut radio, batch, &transmits, verify_tx_power);
+
+            followed
+        })
+        .await;
+
+        match result {
+            // A link the radio didn't switch to is staged again with the next beacon
+            Ok(Some(link)) => peers.lock().unwrap().link_followed(module, link),
+            Ok(None) => {}
+            Err(e) => log::error!("transmit worker failed: {e}"),
+        }
+    }
+}
+
+/// Sends `batch` grouped by modulation, then restores the module's modulation
+fn transmit_batch(
+    module: usize,
+    radio: &mut PlatformRadio,
+    batch: Vec<TransmitJob>,
+    transmits: &TransmitBus,
+    verify_tx_power: bool,
+) {
+    let own = radio.get_modulation();
+
+    for (modulation, jobs) in group_by_modulation(batch, own) {
+        for job in jobs {
+            let result = radio.transmit_with_modulation(&job.frame, &modulation);
+            let report = radio.last_transmit();
+            let tx_power = applied_tx_power(module, radio, verify_tx_power);
+
+            // Nobody may be listening, the event is dropped then
+            transmits.publish(Arc::new(TransmitEvent::new(
+                module,
+                &job.frame,
+                job.seq,
+                &result,
+                report,
+                &modulation,
+                job.requested,
+            )));
+
+            // The requester may be gone already, nothing to report then
+            if let Some(reply) = job.reply {
+                let _ = reply.send(TransmitOutcome {
+                    result,
+                    report,
+                    tx_power,
+                });
+            }
+        }
+    }
+
+    if radio.get_modulation() != own
+        && let Err(e) = radio.set_modulation(&own)
+    {
+        log::warn!("can't restore modulation after a per-frame one: {e:?}");
+    }
+}
+
+/// Moves the module to the data channel and modulation of the peer it
+/// follows, returns whether it got there
+fn follow_link(module: usize, radio: &mut PlatformRadio, link: &LinkAdvert) -> bool {
+    let mut followed = true;
+
+    let mut config = radio.get_config();
+    if config.channel != link.channel {
+        config.channel = link.channel;
+
+        if let Err(e) = radio.set_config(&config) {
+            log::warn!("radio[{module}] can't follow peer channel: {e:?}");
+            followed = false;
+        }
+    }
+
+    let current = radio.get_modulation();
+    if let Some(modulation) = link.modulation_for(&current)
+        && modulation != current
+        && let Err(e) = radio.set_modulation(&modulation)
+    {
+        log::warn!("radio[{module}] can't follow peer modulation: {e:?}");
+        followed = false;
+    }
+
+    followed
+}
+
+/// Transmit power of the radio's modulation, read back from the transceiver
+/// with `verify`
+///
+/// A read back power that differs is logged and reported, as it's the power
+/// actually used.
+fn applied_tx_power(module: usize, radio: &mut PlatformRadio, verify: bool) -> u8 {
+    let expected = radio.get_modulation().tx_power();
+    if !verify {
+        return expected;
+    }
+
+    match radio.read_tx_power() {
+        Ok(actual) => {
+            if actual != expected {
+                log::warn!("radio[{module}] tx power reads back {actual}, expected {expected}");
+            }
+            actual
+        }
+        Err(KaonicError::NotSupported) => expected,
+        Err(e) => {
+            log::warn!("radio[{module}] can't read back tx power: {e:?}");
+            expected
+        }
+    }
+}
+
+/// Splits `batch` into runs of one modulation
+///
+/// The run with the module's own modulation goes first as it needs no switch,
+/// the others follow in the order of their first frame. Frames keep their
+/// order within a run.
+fn group_by_modulation(
+    batch: Vec<TransmitJob>,
+    own: Modulation,
+) -> Vec<(Modulation, Vec<TransmitJob>)> {
+    let mut groups: Vec<(Modulation, Vec<TransmitJob>)> = vec![(own, Vec::new())];
+
+    for job in batch {
+        let modulation = job.modulation.unwrap_or(own);
+
+        match groups.iter_mut().find(|(group, _)| *group == modulation) {
+            Some((_, jobs)) => jobs.push(job),
+            None => groups.push((modulation, vec![job])),
+        }
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use radio_common::modulation::{OfdmMcs, OfdmModulation};
+
+    fn job(
+        data: u8,
+        modulation: Option<Modulation>,
+    ) -> (TransmitJob, oneshot::Receiver<TransmitOutcome>) {
+        let (reply, outcome) = oneshot::channel();
+        let job = TransmitJob {
+            frame: PlatformRadioFrame::new_from_slice(&[data]).unwrap(),
+            modulation,
+            seq: data.into(),
+            requested: Instant::now(),
+            reply: Some(reply),
+        };
+
+        (job, outcome)
+    }
+
+    #[cfg(feature = "machine-host")]
+    #[test]
+    fn test_mixed_batch_switches_once_per_modulation() {
+        let mut radio = PlatformRadio::new();
+        let own = radio.get_modulation();
+        let robust = Modulation::Ofdm(OfdmModulation {
+            mcs: OfdmMcs::BpskC1_2_4x,
+            ..Default::default()
+        });
+
+        let (batch, outcomes): (Vec<_>, Vec<_>) = [
+            job(0, Some(robust)),
+            job(1, None),
+            job(2, Some(robust)),
+            job(3, Some(own)),
+            job(4, Some(robust)),
+        ]
+        .into_iter()
+        .unzip();
+
+        let transmits = TransmitBus::new(8);
+        let mut event_recv = transmits.subscribe();
+        transmit_batch(0, &mut radio, batch, &transmits, true);
+
+        // One switch to the robust modulation and one back
+        assert_eq!(radio.modulation_changes(), 2);
+        assert_eq!(radio.get_modulation(), own);
+
+        for mut outcome in outcomes {
+            let outcome = outcome.try_recv().expect("outcome");
+            assert!(outcome.result.is_ok());
+            assert_eq!(outcome.tx_power, own.tx_power());
+        }
+
+        // One event per frame, timed with the modulation it went out with
+        let mut seqs = Vec::new();
+        while let Ok(event) = event_recv.try_recv() {
+            let modulation = if [0, 2, 4].contains(&event.seq) {
+                robust
+            } else {
+                own
+            };
+            assert_eq!(Some(event.air_time), modulation.air_time(1));
+            seqs.push(event.seq);
+        }
+        assert_eq!(seqs, vec![1, 3, 0, 2, 4]);
+
+        // Frames of the module's modulation first, each run in request order
+        let mut sent = Vec::new();
+        let mut frame = PlatformRadioFrame::new();
+        while radio.receive(frame.clear(), Duration::ZERO).is_ok() {
+            sent.push(frame.as_slice()[0]);
+        }
+        assert_eq!(sent, vec![1, 3, 0, 2, 4]);
+    }
+
+    #[test]
+    fn test_group_by_modulation_keeps_own_modulation_first() {
+        let own = Modulation::Ofdm(OfdmModulation::default());
+        let robust = Modulation::Ofdm(OfdmModulation {
+            mcs: OfdmMcs::BpskC1_2_4x,
+            ..Default::default()
+        });
+
+        let batch = vec![job(0, Some(robust)).0, job(1, None).0];
+        let groups = group_by_modulation(batch, own);
+
+        let order: Vec<_> = groups
+            .iter()
+            .map(|(modulation, jobs)| (*modulation, jobs.len()))
+            .collect();
+        assert_eq!(order, vec![(own, 1), (robust, 1)]);
+    }
+}
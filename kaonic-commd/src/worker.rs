@@ -0,0 +1,117 @@
+use crate::config::WorkerConfig;
+
+/// Outcome of applying [`WorkerConfig`] to the calling thread
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadTuning {
+    pub rt_priority: bool,
+    pub cpu_affinity: bool,
+}
+
+/// Applies real-time priority and CPU pinning for `module` to the calling thread.
+///
+/// Failures (e.g. missing `CAP_SYS_NICE`) are logged and the thread keeps the
+/// default scheduling.
+pub fn tune_current_thread(module: usize, config: &WorkerConfig) -> ThreadTuning {
+    let mut tuning = ThreadTuning::default();
+
+    if let Some(priority) = config.rt_priority {
+        match set_rt_priority(priority) {
+            Ok(()) => {
+                log::info!("radio[{module}] worker: SCHED_FIFO priority {priority}");
+                tuning.rt_priority = true;
+            }
+            Err(e) => {
+                log::warn!("radio[{module}] worker: can't set rt priority {priority}: {e}");
+            }
+        }
+    }
+
+    if let Some(&cpu) = config.cpu_affinity.get(module) {
+        match set_cpu_affinity(cpu) {
+            Ok(()) => {
+                log::info!("radio[{module}] worker: pinned to cpu {cpu}");
+                tuning.cpu_affinity = true;
+            }
+            Err(e) => {
+                log::warn!("radio[{module}] worker: can't pin to cpu {cpu}: {e}");
+            }
+        }
+    }
+
+    tuning
+}
+
+#[cfg(target_os = "linux")]
+fn set_rt_priority(priority: i32) -> std::io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+
+    // SAFETY: `param` is a valid sched_param and pid 0 refers to the calling thread
+    if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(cpu: usize) -> std::io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+    }
+
+    // SAFETY: cpu_set_t is plain data and `cpu` is within CPU_SETSIZE
+    unsafe {
+        let mut set: libc::cpu_set_t = core::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+
+        if libc::sched_setaffinity(0, core::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_rt_priority(_priority: i32) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(_cpu: usize) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tune_thread_affinity() {
+        let config = WorkerConfig {
+            rt_priority: None,
+            cpu_affinity: vec![0],
+            ..Default::default()
+        };
+
+        let tuning = std::thread::spawn(move || tune_current_thread(0, &config))
+            .join()
+            .expect("worker thread");
+
+        assert_eq!(tuning.cpu_affinity, cfg!(target_os = "linux"));
+        assert!(!tuning.rt_priority);
+    }
+
+    #[test]
+    fn test_tune_thread_without_module_entry() {
+        let config = WorkerConfig {
+            rt_priority: None,
+            cpu_affinity: vec![0],
+            ..Default::default()
+        };
+
+        assert_eq!(tune_current_thread(1, &config), ThreadTuning::default());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{net::SocketAddr, sync::Arc, time::Instant};
 
-use kaonic_net::request::RequestQueue;
+use kaonic_net::{packet::PacketIdNamespace, request::RequestQueue};
 use rand::{RngCore, rngs::OsRng};
 use tokio::{
     net::UdpSocket,
@@ -35,6 +35,7 @@ impl<T: PeerMessage + Send + std::fmt::Debug + 'static> Client<T> {
         listen_addr: SocketAddr,
         server_addr: SocketAddr,
         coder: C,
+        id_namespace: PacketIdNamespace,
         cancel: CancellationToken,
     ) -> Result<Self, ControllerError> {
         let request_queue = Arc::new(Mutex::new(RequestQueue::new()));
@@ -47,7 +48,7 @@ impl<T: PeerMessage + Send + std::fmt::Debug + 'static> Client<T> {
 
         let socket = UdpSocket::bind(listen_addr).await?;
 
-        let peer = Peer::new(socket, coder, Some(server_addr));
+        let peer = Peer::new(socket, coder, Some(server_addr)).with_id_namespace(id_namespace);
         let peer_send = peer.tx_send();
         let peer_recv = peer.rx_recv();
 
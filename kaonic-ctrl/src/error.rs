@@ -2,6 +2,8 @@ use kaonic_frame::error::FrameError;
 use kaonic_net::error::NetworkError;
 use tokio::io;
 
+use crate::protocol::TransmitReport;
+
 #[derive(Clone, Copy, Debug)]
 pub enum ControllerError {
     OutOfMemory,
@@ -9,6 +11,8 @@ pub enum ControllerError {
     SocketError,
     Timeout,
     MethodError,
+    /// The radio gave up on the frame
+    TransmitFailed(TransmitReport),
 }
 
 impl From<NetworkError> for ControllerError {
@@ -2,7 +2,10 @@ use std::time::Instant;
 
 use kaonic_frame::frame::{Frame, FrameSegment};
 use kaonic_net::{
-    NetworkTime, coder::BinaryPacketCoder, network::Network, packet::AssembledPacket,
+    NetworkTime,
+    coder::BinaryPacketCoder,
+    network::Network,
+    packet::{AssembledPacket, PacketIdNamespace},
 };
 use rand::{CryptoRng, RngCore};
 
@@ -24,6 +27,29 @@ impl<const MTU: usize, const R: usize> ControllerNetwork<MTU, R> {
         }
     }
 
+    /// Limits the number of partially received messages kept for reassembly
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.network = self.network.with_max_pending(max_pending);
+        self
+    }
+
+    /// Prefixes the ids of sent messages with the node prefix of `namespace`
+    pub fn with_id_namespace(mut self, namespace: PacketIdNamespace) -> Self {
+        self.network = self.network.with_id_namespace(namespace);
+        self
+    }
+
+    /// Limits the messages sent to `ttl` hops, 0 leaves them unlimited
+    pub fn with_ttl(mut self, ttl: u8) -> Self {
+        self.network = self.network.with_ttl(ttl);
+        self
+    }
+
+    /// Number of partial messages evicted to stay within the pending budget
+    pub fn evictions(&self) -> usize {
+        self.network.evictions()
+    }
+
     pub fn receive<'a>(
         &mut self,
         rx_frame: &Frame<MTU>,
@@ -49,6 +75,17 @@ impl<const MTU: usize, const R: usize> ControllerNetwork<MTU, R> {
         Ok(frames)
     }
 
+    /// Prepares a received frame for the next hop, see [`Network::forward`]
+    pub fn forward(
+        &mut self,
+        rx_frame: &Frame<MTU>,
+        output_frame: &mut Frame<MTU>,
+    ) -> Result<(), ControllerError> {
+        self.network.forward(rx_frame, output_frame)?;
+
+        Ok(())
+    }
+
     fn current_time() -> NetworkTime {
         crate::system_time()
     }
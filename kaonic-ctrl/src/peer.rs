@@ -6,7 +6,10 @@ use std::{
 };
 
 use kaonic_frame::frame::{Frame, FrameSegment};
-use kaonic_net::{packet::AssembledPacket, request::Responder};
+use kaonic_net::{
+    packet::{AssembledPacket, PacketIdNamespace},
+    request::Responder,
+};
 use rand::rngs::OsRng;
 use tokio::{
     net::UdpSocket,
@@ -149,6 +152,18 @@ impl<
         }
     }
 
+    /// Limits the number of partially received messages kept for reassembly
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.network = self.network.with_max_pending(max_pending);
+        self
+    }
+
+    /// Prefixes the ids of sent messages with the node prefix of `namespace`
+    pub fn with_id_namespace(mut self, namespace: PacketIdNamespace) -> Self {
+        self.network = self.network.with_id_namespace(namespace);
+        self
+    }
+
     pub fn tx_send(&self) -> PeerSender<T> {
         self.tx_send.clone()
     }
@@ -171,6 +186,8 @@ impl<
         cleanup_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         const CLIENT_TIMEOUT: Duration = Duration::from_secs(120);
 
+        let mut evictions = 0usize;
+
         loop {
             if !running {
                 log::warn!("stop serving peer");
@@ -255,6 +272,11 @@ impl<
                     if removed > 0 {
                         log::info!("removed {} stale client(s), {} active", removed, clients.len());
                     }
+
+                    if self.network.evictions() > evictions {
+                        log::warn!("evicted {} partial message(s) over the reassembly budget", self.network.evictions() - evictions);
+                        evictions = self.network.evictions();
+                    }
                 },
 
                 _ = cancel.cancelled() => {
@@ -1,5 +1,5 @@
 use kaonic_frame::frame::FrameSegment;
-use radio_common::{Modulation, RadioConfig};
+use radio_common::{Hertz, Modulation, RadioConfig};
 use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 
@@ -56,6 +56,32 @@ pub struct TransmitModule {
     pub frame: RadioFrame,
 }
 
+/// How a transmission ended
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransmitResult {
+    /// Frame went out
+    #[default]
+    Sent,
+    /// Every attempt found the channel busy
+    ChannelBusy,
+    /// Retry limit reached without sending the frame
+    MaxRetries,
+}
+
+impl TransmitResult {
+    /// Returns `true` if the frame left the radio
+    pub fn is_sent(&self) -> bool {
+        matches!(self, Self::Sent)
+    }
+}
+
+/// Attempts made by a transmission, including the first one, and its result
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransmitReport {
+    pub attempts: u8,
+    pub result: TransmitResult,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ReceiveModule {
     pub module: usize,
@@ -73,6 +99,16 @@ impl ReceiveModule {
     }
 }
 
+/// [`ReceiveModule`] of a raw frame whose CRC-32 trailer the server checked
+///
+/// Sent instead of [`ReceiveModule`] when the server appends a CRC to raw
+/// frames, so clients without CRC support still decode plain receive events.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CheckedReceiveModule {
+    pub receive: ReceiveModule,
+    pub crc_valid: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GetInfoResponse {
     pub module_count: usize,
@@ -81,6 +117,45 @@ pub struct GetInfoResponse {
     pub version: String,
 }
 
+/// Version of the control API, raised whenever requests or responses change
+pub const API_VERSION: u32 = 1;
+
+/// OFDM modulation
+pub const CAPABILITY_OFDM: u32 = 1 << 0;
+/// O-QPSK modulation
+pub const CAPABILITY_QPSK: u32 = 1 << 1;
+/// FSK modulation
+pub const CAPABILITY_FSK: u32 = 1 << 2;
+/// Sub-GHz band
+pub const CAPABILITY_BAND_09: u32 = 1 << 3;
+/// 2.4GHz band
+pub const CAPABILITY_BAND_24: u32 = 1 << 4;
+/// Adaptive modulation through `GetQos`/`SetQos`
+pub const CAPABILITY_QOS: u32 = 1 << 5;
+/// LDPC coded network packets
+pub const CAPABILITY_LDPC: u32 = 1 << 6;
+/// Peer discovery beacons are enabled
+pub const CAPABILITY_BEACONS: u32 = 1 << 7;
+/// Raw frames carry a CRC-32 trailer
+pub const CAPABILITY_RAW_CRC: u32 = 1 << 8;
+/// Both modules receive as one in diversity mode
+pub const CAPABILITY_DIVERSITY: u32 = 1 << 9;
+/// TX power follows the RSSI reported by the peers
+pub const CAPABILITY_POWER_CONTROL: u32 = 1 << 10;
+/// Radios are simulated by a host build
+pub const CAPABILITY_SIMULATED: u32 = 1 << 11;
+/// Config, modulation and QoS of a module change together through `ApplyConfig`
+pub const CAPABILITY_APPLY_CONFIG: u32 = 1 << 12;
+
+/// Features the device supports, a bitmask of the `CAPABILITY_*` flags
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetCapabilitiesResponse {
+    pub api_version: u32,
+    pub capabilities: u32,
+    /// Transceiver part, `None` if unknown
+    pub part_number: Option<String>,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct GetStatisticsRequest {
     pub module: usize,
@@ -131,6 +206,65 @@ pub struct GetRadioConfigResponse {
     pub config: RadioConfig,
 }
 
+/// Legal channel raster of a region, channel n is at `freq + n * channel_spacing`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrequencyPlan {
+    pub name: String,
+    pub band_start: Hertz,
+    pub band_end: Hertz,
+    pub freq: Hertz,
+    pub channel_spacing: Hertz,
+    pub channel_count: u16,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetFrequencyPlansResponse {
+    pub plans: Vec<FrequencyPlan>,
+}
+
+/// Link quality as assessed by the module's QoS, ordered from best to worst
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChannelQuality {
+    Excellent,
+    Good,
+    Fair,
+    Poor,
+    Bad,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GetQosRequest {
+    pub module: usize,
+}
+
+/// Adaptive modulation settings of a module
+///
+/// With `adaptive_modulation_type` the module runs QPSK at `qpsk_crossover`
+/// and worse, OFDM above. `quality` is the current assessment and is ignored
+/// by [`Payload::SetQosRequest`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct QosSettings {
+    pub module: usize,
+    pub enabled: bool,
+    pub per_threshold: u32,
+    pub adaptive_modulation_type: bool,
+    pub qpsk_crossover: ChannelQuality,
+    pub quality: ChannelQuality,
+}
+
+/// Frequency, modulation and QoS of a module applied as one change
+///
+/// The radio doesn't receive while they're written, so it never runs the new
+/// frequency with the old modulation as separate requests would. `None`
+/// leaves the modulation or QoS as they are, the module of `qos` is ignored.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ApplyConfigRequest {
+    pub module: usize,
+    pub config: RadioConfig,
+    pub modulation: Option<Modulation>,
+    pub qos: Option<QosSettings>,
+}
+
 //***********************************************************************************************//
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -156,6 +290,18 @@ pub enum Payload {
     GetStatisticsResponse(GetStatisticsResponse),
     NotImplemented,
     Error,
+    GetFrequencyPlansRequest,
+    GetFrequencyPlansResponse(GetFrequencyPlansResponse),
+    TransmitModuleReport(TransmitReport),
+    CheckedReceiveModule(CheckedReceiveModule),
+    GetQosRequest(GetQosRequest),
+    GetQosResponse(QosSettings),
+    SetQosRequest(QosSettings),
+    SetQosResponse,
+    GetCapabilitiesRequest,
+    GetCapabilitiesResponse(GetCapabilitiesResponse),
+    ApplyConfigRequest(ApplyConfigRequest),
+    ApplyConfigResponse,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -190,6 +336,7 @@ impl Payload {
             Payload::TransmitModuleRequest(tx) => tx.frame.validate(),
             Payload::TransmitModuleEvent(tx) => tx.frame.validate(),
             Payload::ReceiveModule(rx) => rx.frame.validate(),
+            Payload::CheckedReceiveModule(rx) => rx.receive.frame.validate(),
             _ => Ok(()),
         }
     }
@@ -16,7 +16,11 @@ use crate::{
     },
 };
 
+pub use crate::protocol::{ApplyConfigRequest, ChannelQuality, FrequencyPlan, QosSettings};
 pub use crate::protocol::GetInfoResponse;
+pub use crate::protocol::GetCapabilitiesResponse;
+pub use crate::protocol::TransmitReport;
+pub use crate::protocol::TransmitResult;
 
 /// Default timeout for all request/response operations.
 pub const DEFAULT_TIMEOUT: core::time::Duration = core::time::Duration::from_secs(6);
@@ -118,11 +122,14 @@ impl RadioClient {
     }
 
     /// Transmits a frame through the specified radio module.
+    ///
+    /// Returns the attempts the radio needed, or [`ControllerError::TransmitFailed`]
+    /// if it gave up. Servers which don't report attempts yield a default report.
     pub async fn transmit(
         &mut self,
         module: usize,
         frame: &Frame<RADIO_FRAME_SIZE>,
-    ) -> Result<(), ControllerError> {
+    ) -> Result<TransmitReport, ControllerError> {
         let response = self
             .request(Payload::TransmitModuleRequest(crate::protocol::TransmitModule {
                 module,
@@ -132,7 +139,9 @@ impl RadioClient {
 
         match response.payload {
             Payload::Error => Err(ControllerError::MethodError),
-            Payload::TransmitModuleResponse => Ok(()),
+            Payload::TransmitModuleResponse => Ok(TransmitReport::default()),
+            Payload::TransmitModuleReport(report) if report.result.is_sent() => Ok(report),
+            Payload::TransmitModuleReport(report) => Err(ControllerError::TransmitFailed(report)),
             _ => Err(ControllerError::DecodeError),
         }
     }
@@ -198,6 +207,23 @@ impl RadioClient {
         Ok(())
     }
 
+    /// Applies config, modulation and QoS of the module in `request` as one
+    /// change, see [`crate::protocol::ApplyConfigRequest`].
+    ///
+    /// Only devices reporting `CAPABILITY_APPLY_CONFIG` understand it.
+    pub async fn apply_config(
+        &mut self,
+        request: ApplyConfigRequest,
+    ) -> Result<(), ControllerError> {
+        let response = self.request(Payload::ApplyConfigRequest(request)).await?;
+
+        match response.payload {
+            Payload::Error => Err(ControllerError::MethodError),
+            Payload::ApplyConfigResponse => Ok(()),
+            _ => Err(ControllerError::DecodeError),
+        }
+    }
+
     /// Queries the device for general info (e.g. number of radio modules).
     pub async fn get_info(&mut self) -> Result<GetInfoResponse, ControllerError> {
         let response = self.request(Payload::GetInfoRequest).await?;
@@ -209,6 +235,54 @@ impl RadioClient {
         }
     }
 
+    /// Queries the regional frequency plans supported by the device.
+    pub async fn get_frequency_plans(&mut self) -> Result<Vec<FrequencyPlan>, ControllerError> {
+        let response = self.request(Payload::GetFrequencyPlansRequest).await?;
+
+        match response.payload {
+            Payload::Error => Err(ControllerError::MethodError),
+            Payload::GetFrequencyPlansResponse(response) => Ok(response.plans),
+            _ => Err(ControllerError::DecodeError),
+        }
+    }
+
+    /// Retrieves the adaptive modulation settings of the specified module.
+    pub async fn get_qos(&mut self, module: usize) -> Result<QosSettings, ControllerError> {
+        let response = self
+            .request(Payload::GetQosRequest(crate::protocol::GetQosRequest {
+                module,
+            }))
+            .await?;
+
+        match response.payload {
+            Payload::Error => Err(ControllerError::MethodError),
+            Payload::GetQosResponse(settings) => Ok(settings),
+            _ => Err(ControllerError::DecodeError),
+        }
+    }
+
+    /// Applies adaptive modulation settings to the module in `settings`.
+    pub async fn set_qos(&mut self, settings: QosSettings) -> Result<(), ControllerError> {
+        let response = self.request(Payload::SetQosRequest(settings)).await?;
+
+        match response.payload {
+            Payload::Error => Err(ControllerError::MethodError),
+            Payload::SetQosResponse => Ok(()),
+            _ => Err(ControllerError::DecodeError),
+        }
+    }
+
+    /// Queries the API version and the features supported by the device.
+    pub async fn get_capabilities(&mut self) -> Result<GetCapabilitiesResponse, ControllerError> {
+        let response = self.request(Payload::GetCapabilitiesRequest).await?;
+
+        match response.payload {
+            Payload::Error => Err(ControllerError::MethodError),
+            Payload::GetCapabilitiesResponse(response) => Ok(response),
+            _ => Err(ControllerError::DecodeError),
+        }
+    }
+
     /// Cancels the background receive task and shuts down the underlying client.
     pub fn cancel(&mut self) {
         self.client.cancel();
@@ -253,6 +327,14 @@ impl RadioClient {
                             Payload::ReceiveModule(rx) => {
                                 let _ = module_rx_send.send(Box::new(rx));
                             },
+                            // Frames failing the server's raw-frame CRC check are dropped
+                            Payload::CheckedReceiveModule(rx) => {
+                                if rx.crc_valid {
+                                    let _ = module_rx_send.send(Box::new(rx.receive));
+                                } else {
+                                    log::debug!("radio[{}] dropped frame with a bad crc", rx.receive.module);
+                                }
+                            },
                             Payload::TransmitModuleEvent(tx) => {
                                 let _ = module_tx_send.send(Box::new(tx));
                             },
@@ -1,6 +1,7 @@
 use std::{net::SocketAddr, time::Instant};
 
-use tokio::{net::UdpSocket, sync::mpsc};
+use kaonic_net::packet::PacketIdNamespace;
+use tokio::{net::UdpSocket, sync::mpsc, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
@@ -15,6 +16,7 @@ pub trait ServerHandler<T> {
 
 pub struct Server<T: PeerMessage> {
     peer_send: PeerSender<T>,
+    tasks: Vec<JoinHandle<()>>,
 }
 
 impl<T: PeerMessage + Send + std::fmt::Debug + 'static> Server<T> {
@@ -28,6 +30,8 @@ impl<T: PeerMessage + Send + std::fmt::Debug + 'static> Server<T> {
         coder: C,
         handler: H,
         client_recv: mpsc::Receiver<Box<T>>,
+        max_pending: usize,
+        id_namespace: PacketIdNamespace,
         cancel: CancellationToken,
     ) -> Result<Self, ControllerError> {
         log::info!("listen server on {}", listen_addr);
@@ -35,27 +39,36 @@ impl<T: PeerMessage + Send + std::fmt::Debug + 'static> Server<T> {
         let socket = UdpSocket::bind(listen_addr).await?;
         socket.set_broadcast(true)?;
 
-        let peer = Peer::new(socket, coder, None);
+        let peer = Peer::new(socket, coder, None)
+            .with_max_pending(max_pending)
+            .with_id_namespace(id_namespace);
         let peer_send = peer.tx_send();
         let peer_recv = peer.rx_recv();
 
+        let mut tasks = Vec::new();
+
         {
             let peer_send = peer_send.clone();
             let cancel = cancel.clone();
-            tokio::spawn(Box::pin(async move {
+            tasks.push(tokio::spawn(Box::pin(async move {
                 let _ =
                     Self::manage_requests(handler, peer_send, client_recv, peer_recv, cancel).await;
-            }));
+            })));
         }
 
         {
             let cancel = cancel.clone();
-            tokio::spawn(Box::pin(async move {
+            tasks.push(tokio::spawn(Box::pin(async move {
                 let _ = peer.serve(cancel).await;
-            }));
+            })));
         }
 
-        Ok(Self { peer_send })
+        Ok(Self { peer_send, tasks })
+    }
+
+    /// Request handling and socket tasks, they end once `cancel` is cancelled
+    pub fn take_tasks(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.tasks)
     }
 
     pub async fn broadcast(&mut self, message: T) {
@@ -2,6 +2,7 @@ use kaonic_ctrl::{
     client::Client, peer::NETWORK_MTU, protocol::MessageCoder, radio::RadioClient, server::Server,
 };
 use kaonic_frame::frame::Frame;
+use kaonic_net::packet::PacketIdNamespace;
 use tokio_util::sync::CancellationToken;
 
 const SEGMENTS_COUNT: usize = 4;
@@ -83,6 +84,7 @@ async fn test_radio_client() {
         "0.0.0.0:9091".parse().unwrap(),
         "127.0.0.1:9090".parse().unwrap(),
         MessageCoder::<NETWORK_MTU, SEGMENTS_COUNT>::new(),
+        PacketIdNamespace::NONE,
         cancel.clone(),
     )
     .await
@@ -20,6 +20,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let device_info = response.into_inner();
             println!("✅ Device Serial: {}", device_info.serial);
             println!("✅ Device Machine: {}", device_info.machine);
+            if !device_info.provisioned {
+                println!("⚠️  Device is not provisioned");
+            }
         }
         Err(e) => {
             println!("❌ Failed to get device info: {}", e);
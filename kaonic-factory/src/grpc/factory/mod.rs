@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::time::Instant;
 use tokio_stream::wrappers::ReceiverStream;
@@ -11,13 +13,16 @@ pub mod kaonic {
 
 use kaonic::{
     factory_server::Factory, DeviceInfoResponse, Empty, FactoryTestCaseResponse,
-    RunAllTestsRequest, RunTestRequest, TestCase, TestResult, TestStatus, TestStatusUpdate,
+    RunAllTestsRequest, RunTestRequest, TestCase, TestReportResponse, TestResult, TestStatus,
+    TestStatusUpdate,
 };
+use report::TestReport;
 
 pub mod bluetooth;
 pub mod i2c;
 pub mod memory;
 pub mod pmic;
+pub mod report;
 pub mod rf215;
 pub mod vendor;
 pub mod wifi;
@@ -29,8 +34,20 @@ pub trait FactoryTest: Send + Sync {
     async fn execute(&self) -> Result<String, String>;
 }
 
+/// Directory of the provisioning files, unless set by `KAONIC_INFO_DIR`
+const DEFAULT_INFO_DIR: &str = "/etc/kaonic";
+const INFO_DIR_VAR: &str = "KAONIC_INFO_DIR";
+
+const SERIAL_FILE: &str = "kaonic_serial";
+const MACHINE_FILE: &str = "kaonic_machine";
+
+/// Used on units without provisioning files, e.g. during bring-up
+const SERIAL_VAR: &str = "KAONIC_SERIAL";
+const MACHINE_VAR: &str = "KAONIC_MACHINE";
+
 pub struct FactoryService {
     tests: Arc<HashMap<String, Box<dyn FactoryTest>>>,
+    info_dir: PathBuf,
 }
 
 impl Default for FactoryService {
@@ -65,25 +82,57 @@ impl Default for FactoryService {
             Box::new(rf215::Rf215Test) as Box<dyn FactoryTest>,
         );
 
-        FactoryService {
-            tests: Arc::new(tests),
+        FactoryService::with_tests(tests)
+    }
+}
+
+/// Trimmed content of a provisioning file, `None` if missing or empty
+fn read_info_file(path: &Path) -> Option<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Some(content.trim().to_string()).filter(|s| !s.is_empty()),
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", path.display(), e);
+            None
         }
     }
 }
 
 impl FactoryService {
-    fn read_device_info() -> Result<(String, String), String> {
-        let serial = fs::read_to_string("/etc/kaonic/kaonic_serial")
-            .map_err(|e| format!("Failed to read serial: {}", e))?
-            .trim()
-            .to_string();
+    fn with_tests(tests: HashMap<String, Box<dyn FactoryTest>>) -> Self {
+        let info_dir = env::var_os(INFO_DIR_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| DEFAULT_INFO_DIR.into());
 
-        let machine = fs::read_to_string("/etc/kaonic/kaonic_machine")
-            .map_err(|e| format!("Failed to read machine: {}", e))?
-            .trim()
-            .to_string();
+        FactoryService {
+            tests: Arc::new(tests),
+            info_dir,
+        }
+    }
 
-        Ok((serial, machine))
+    /// Reads the provisioning files from `info_dir` instead of `/etc/kaonic`
+    pub fn with_info_dir(mut self, info_dir: impl Into<PathBuf>) -> Self {
+        self.info_dir = info_dir.into();
+        self
+    }
+
+    /// Serial and machine of the unit
+    ///
+    /// A missing file falls back to its environment variable, or is left
+    /// empty, and the unit is reported as not provisioned.
+    fn read_device_info(&self) -> DeviceInfoResponse {
+        let serial = read_info_file(&self.info_dir.join(SERIAL_FILE));
+        let machine = read_info_file(&self.info_dir.join(MACHINE_FILE));
+        let provisioned = serial.is_some() && machine.is_some();
+
+        DeviceInfoResponse {
+            serial: serial
+                .or_else(|| env::var(SERIAL_VAR).ok())
+                .unwrap_or_default(),
+            machine: machine
+                .or_else(|| env::var(MACHINE_VAR).ok())
+                .unwrap_or_default(),
+            provisioned,
+        }
     }
 
     fn get_available_test_cases(&self) -> Vec<TestCase> {
@@ -120,6 +169,24 @@ impl FactoryService {
             duration_ms: duration.as_millis() as i64,
         }
     }
+
+    /// Runs every test one after the other, ordered by id so reports of
+    /// different units line up
+    pub async fn run_report(&self) -> TestReport {
+        let mut test_cases = self.get_available_test_cases();
+        test_cases.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut results = Vec::with_capacity(test_cases.len());
+        for test_case in test_cases {
+            let result = self.execute_test(&test_case.id).await;
+            results.push((test_case, result));
+        }
+
+        TestReport {
+            device: self.read_device_info(),
+            results,
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -204,19 +271,129 @@ impl Factory for FactoryService {
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 
+    async fn run_all_tests_report(
+        &self,
+        _request: Request<RunAllTestsRequest>,
+    ) -> Result<Response<TestReportResponse>, Status> {
+        let report = self.run_report().await;
+
+        Ok(Response::new(TestReportResponse {
+            passed: report.passed(),
+            json: report.to_json().to_string(),
+        }))
+    }
+
     async fn get_device_info(
         &self,
         _request: Request<Empty>,
     ) -> Result<Response<DeviceInfoResponse>, Status> {
-        match Self::read_device_info() {
-            Ok((serial, machine)) => {
-                let response = DeviceInfoResponse { serial, machine };
-                Ok(Response::new(response))
-            }
-            Err(err) => Err(Status::internal(format!(
-                "Failed to read device info: {}",
-                err
-            ))),
+        Ok(Response::new(self.read_device_info()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_info_fallback() {
+        let dir = env::temp_dir().join(format!("kaonic-factory-info-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        env::set_var(SERIAL_VAR, "DEV-0001");
+        env::remove_var(MACHINE_VAR);
+
+        let service = FactoryService::default().with_info_dir(&dir);
+
+        // Nothing provisioned, the serial comes from the environment
+        let info = service.read_device_info();
+        assert_eq!(info.serial, "DEV-0001");
+        assert_eq!(info.machine, "");
+        assert!(!info.provisioned);
+
+        // A file takes precedence over the environment
+        fs::write(dir.join(MACHINE_FILE), "kaonic1s\n").unwrap();
+        let info = service.read_device_info();
+        assert_eq!(info.serial, "DEV-0001");
+        assert_eq!(info.machine, "kaonic1s");
+        assert!(!info.provisioned);
+
+        fs::write(dir.join(SERIAL_FILE), " K1S-42 \n").unwrap();
+        let info = service.read_device_info();
+        assert_eq!(info.serial, "K1S-42");
+        assert!(info.provisioned);
+
+        env::remove_var(SERIAL_VAR);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    struct SampleTest(Result<&'static str, &'static str>);
+
+    #[tonic::async_trait]
+    impl FactoryTest for SampleTest {
+        fn name(&self) -> &str {
+            "Sample Test"
         }
+
+        fn description(&self) -> &str {
+            "Returns a fixed outcome"
+        }
+
+        async fn execute(&self) -> Result<String, String> {
+            self.0.map(str::to_string).map_err(str::to_string)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_json_schema() {
+        let dir = env::temp_dir().join(format!("kaonic-factory-report-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(SERIAL_FILE), "K1S-42\n").unwrap();
+        fs::write(dir.join(MACHINE_FILE), "kaonic1s\n").unwrap();
+
+        let mut tests = HashMap::new();
+        tests.insert(
+            "sample:pass".to_string(),
+            Box::new(SampleTest(Ok("all good"))) as Box<dyn FactoryTest>,
+        );
+        tests.insert(
+            "sample:fail".to_string(),
+            Box::new(SampleTest(Err("no response"))) as Box<dyn FactoryTest>,
+        );
+        let service = FactoryService::with_tests(tests).with_info_dir(&dir);
+
+        let report = service.run_report().await;
+        assert!(!report.passed());
+
+        let json = report.to_json();
+        let mut keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["device", "duration_ms", "passed", "schema", "tests"]);
+        assert_eq!(json["schema"], report::REPORT_SCHEMA);
+        assert_eq!(json["passed"], false);
+        assert!(json["duration_ms"].is_i64());
+        assert_eq!(json["device"]["serial"], "K1S-42");
+        assert_eq!(json["device"]["machine"], "kaonic1s");
+        assert_eq!(json["device"]["provisioned"], true);
+
+        // Ordered by id
+        let tests = json["tests"].as_array().unwrap();
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[0]["id"], "sample:fail");
+        assert_eq!(tests[0]["status"], "failed");
+        assert_eq!(tests[0]["message"], "no response");
+        assert_eq!(tests[1]["id"], "sample:pass");
+        assert_eq!(tests[1]["status"], "passed");
+        assert_eq!(tests[1]["name"], "Sample Test");
+        for test in tests {
+            let mut keys: Vec<_> = test.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            assert_eq!(keys, ["duration_ms", "id", "message", "name", "status"]);
+            assert!(test["duration_ms"].is_i64());
+        }
+
+        let _ = fs::remove_dir_all(&dir);
     }
 }
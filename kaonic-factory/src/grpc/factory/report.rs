@@ -0,0 +1,93 @@
+use super::kaonic::{DeviceInfoResponse, TestCase, TestResult, TestStatus};
+
+/// Version of the JSON layout, bumped whenever a field changes meaning
+pub const REPORT_SCHEMA: u32 = 1;
+
+/// Outcome of a full factory run on one unit
+pub struct TestReport {
+    pub device: DeviceInfoResponse,
+    pub results: Vec<(TestCase, TestResult)>,
+}
+
+fn status_name(status: i32) -> String {
+    TestStatus::try_from(status)
+        .unwrap_or(TestStatus::Failed)
+        .as_str_name()
+        .to_lowercase()
+}
+
+impl TestReport {
+    /// True if there was at least one test and every test passed
+    pub fn passed(&self) -> bool {
+        !self.results.is_empty()
+            && self
+                .results
+                .iter()
+                .all(|(_, result)| result.status == TestStatus::Passed as i32)
+    }
+
+    pub fn duration_ms(&self) -> i64 {
+        self.results
+            .iter()
+            .map(|(_, result)| result.duration_ms)
+            .sum()
+    }
+
+    /// Machine readable form of the report, one object per run
+    pub fn to_json(&self) -> serde_json::Value {
+        let tests = self
+            .results
+            .iter()
+            .map(|(test_case, result)| {
+                serde_json::json!({
+                    "id": result.test_id,
+                    "name": test_case.name,
+                    "status": status_name(result.status),
+                    "message": result.message,
+                    "duration_ms": result.duration_ms,
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "schema": REPORT_SCHEMA,
+            "device": {
+                "serial": self.device.serial,
+                "machine": self.device.machine,
+                "provisioned": self.device.provisioned,
+            },
+            "passed": self.passed(),
+            "duration_ms": self.duration_ms(),
+            "tests": serde_json::Value::Array(tests),
+        })
+    }
+
+    /// One line per test followed by the totals, for operators at the jig
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for (_, result) in &self.results {
+            text.push_str(&format!(
+                "{:<8} {} ({} ms): {}\n",
+                status_name(result.status).to_uppercase(),
+                result.test_id,
+                result.duration_ms,
+                result.message
+            ));
+        }
+
+        let passed = self
+            .results
+            .iter()
+            .filter(|(_, result)| result.status == TestStatus::Passed as i32)
+            .count();
+        text.push_str(&format!(
+            "{}/{} tests passed in {} ms\n",
+            passed,
+            self.results.len(),
+            self.duration_ms()
+        ));
+
+        text
+    }
+}
@@ -98,6 +98,7 @@ impl Rf215Test {
         let mut machine = create_machine().map_err(|_| format!("Failed to create machine"))?;
 
         let radio_names = ["RF215-A", "RF215-B"];
+        let bus_errors = [machine.radio_error(0), machine.radio_error(1)];
 
         let results = machine
             .for_each_radio(|idx, radio| match radio {
@@ -105,10 +106,13 @@ impl Rf215Test {
                     .test_rf215_instance(r, radio_names[idx])
                     .map(|info| format!("{}: {}", radio_names[idx], info))
                     .map_err(|_| KaonicError::IncorrectSettings),
-                None => Ok(format!(
-                    "{}: not connected (hardware missing or configuration error)",
-                    radio_names[idx]
-                )),
+                None => Ok(match bus_errors[idx] {
+                    Some(e) => format!("{}: not connected ({})", radio_names[idx], e),
+                    None => format!(
+                        "{}: not connected (hardware missing or configuration error)",
+                        radio_names[idx]
+                    ),
+                }),
             })
             .map_err(|e| format!("RF215 iteration error: {:?}", e))?;
 
@@ -1,7 +1,43 @@
 mod grpc;
 
+use grpc::factory::FactoryService;
+
+const USAGE: &str = "usage: kaonic-factory [run [--json]]";
+
+/// Runs the whole suite without the gRPC server, for test jigs
+///
+/// Exits with 0 if every test passed and 1 otherwise.
+async fn run_headless(json: bool) -> ! {
+    let report = FactoryService::default().run_report().await;
+
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report.to_text());
+    }
+
+    std::process::exit(if report.passed() { 0 } else { 1 });
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => {}
+        ["run"] => {
+            simple_logger::SimpleLogger::new().env().init().unwrap();
+            run_headless(false).await;
+        }
+        // Logs go to stdout as well, keep it to the report
+        ["run", "--json"] => run_headless(true).await,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+
     simple_logger::SimpleLogger::new().env().init().unwrap();
 
     let version = env!("CARGO_PKG_VERSION");
@@ -22,10 +22,11 @@ impl<const S: usize, const R: usize> FrameSegment<S, R> {
         }
     }
 
-    pub fn new_from_slice(slice: &[u8]) -> Self {
+    /// Frame holding a copy of `slice`, which must fit the capacity
+    pub fn new_from_slice(slice: &[u8]) -> Result<Self, FrameError> {
         let mut frame = Self::new();
-        frame.copy_from_slice(slice);
-        frame
+        frame.copy_from_slice(slice)?;
+        Ok(frame)
     }
 
     pub fn capacity(&self) -> usize {
@@ -38,11 +39,18 @@ impl<const S: usize, const R: usize> FrameSegment<S, R> {
         Ok(self.len)
     }
 
-    pub fn copy_from_slice(&mut self, data: &[u8]) {
-        let len = min(data.len(), Self::CAPACITY);
+    /// Replaces the content with `data`
+    ///
+    /// Data exceeding the capacity is rejected, the frame is left unchanged.
+    pub fn copy_from_slice(&mut self, data: &[u8]) -> Result<usize, FrameError> {
+        if data.len() > Self::CAPACITY {
+            return Err(FrameError::OutOfMemory);
+        }
+
+        self.as_flat_mut()[..data.len()].copy_from_slice(data);
+        self.len = data.len();
 
-        self.as_flat_mut()[..len].copy_from_slice(&data[..len]);
-        self.len = len
+        Ok(self.len)
     }
 
     pub fn as_slice(&self) -> &[u8] {
@@ -55,9 +63,14 @@ impl<const S: usize, const R: usize> FrameSegment<S, R> {
         &mut self.as_flat_mut()[..end]
     }
 
+    /// Drops the first `count` bytes
     pub fn move_left(&mut self, count: usize) {
         if self.len > count {
-            self.as_flat_mut().copy_within(count.., 0);
+            let end = self.len;
+            self.as_flat_mut().copy_within(count..end, 0);
+            self.len -= count;
+        } else {
+            self.len = 0;
         }
     }
 
@@ -70,19 +83,18 @@ impl<const S: usize, const R: usize> FrameSegment<S, R> {
         self.len
     }
 
+    /// Sets the length, saturating at the capacity
     pub fn resize(&mut self, len: usize) {
-        self.len = min(len, S);
+        self.len = min(len, Self::CAPACITY);
     }
 
     pub fn alloc_buffer(&mut self, len: usize) -> Result<&mut [u8], FrameError> {
-        let alloc_len = if self.len + len <= Self::CAPACITY {
-            len
-        } else {
+        if len > Self::CAPACITY - self.len {
             return Err(FrameError::OutOfMemory);
-        };
+        }
 
         let start = self.len;
-        self.len += alloc_len;
+        self.len += len;
         let end = self.len;
 
         Ok(&mut self.as_flat_mut()[start..end])
@@ -142,3 +154,64 @@ impl<const S: usize, const R: usize> fmt::Display for FrameSegment<S, R> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_data_past_capacity() {
+        let mut frame = Frame::<8>::new();
+
+        assert_eq!(frame.push_data(&[1; 6]).unwrap(), 6);
+        assert!(matches!(
+            frame.push_data(&[2; 3]),
+            Err(FrameError::OutOfMemory)
+        ));
+        assert_eq!(frame.as_slice(), &[1; 6]);
+
+        assert_eq!(frame.push_data(&[2; 2]).unwrap(), 8);
+        assert!(matches!(
+            frame.push_data(&[3]),
+            Err(FrameError::OutOfMemory)
+        ));
+        assert!(matches!(
+            frame.alloc_buffer(usize::MAX),
+            Err(FrameError::OutOfMemory)
+        ));
+        assert_eq!(frame.len(), 8);
+    }
+
+    #[test]
+    fn test_copy_from_slice_past_capacity() {
+        let mut frame = FrameSegment::<4, 2>::new_from_slice(b"frame").unwrap();
+
+        assert!(matches!(
+            frame.copy_from_slice(&[0xAA; 9]),
+            Err(FrameError::OutOfMemory)
+        ));
+        assert_eq!(frame.as_slice(), b"frame");
+
+        assert_eq!(frame.copy_from_slice(&[0xAA; 8]).unwrap(), 8);
+        assert_eq!(frame.as_slice(), &[0xAA; 8]);
+
+        assert!(matches!(
+            Frame::<4>::new_from_slice(b"frame"),
+            Err(FrameError::OutOfMemory)
+        ));
+
+        frame.resize(100);
+        assert_eq!(frame.len(), 8);
+    }
+
+    #[test]
+    fn test_move_left() {
+        let mut frame = Frame::<8>::new_from_slice(b"header").unwrap();
+
+        frame.move_left(2);
+        assert_eq!(frame.as_slice(), b"ader");
+
+        frame.move_left(10);
+        assert!(frame.as_slice().is_empty());
+    }
+}
@@ -1,16 +1,21 @@
-use kaonic_ctrl::{client::Client, protocol::MessageCoder, radio::RadioClient};
+use kaonic_ctrl::{client::Client, error::ControllerError, protocol::{MessageCoder, ReceiveModule, RADIO_FRAME_SIZE}, radio::{ApplyConfigRequest, ChannelQuality, FrequencyPlan, GetCapabilitiesResponse, RadioClient}};
 use kaonic_frame::frame::Frame;
+use kaonic_net::coder::{LdpcPacketCoder, PacketCoder};
+use kaonic_net::packet::PacketIdNamespace;
+use crate::reticulum::ReticulumPacketInfo;
 use radio_common::{
     frequency::BandwidthFilter,
     modulation::{OfdmBandwidthOption, OfdmMcs, OfdmModulation, QpskChipFrequency, QpskModulation, QpskRateMode},
     Hertz, Modulation, RadioConfig,
 };
+use rand::{rngs::OsRng, Rng};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex as StdMutex};
 use tokio::runtime::Runtime;
 use tokio::sync::{broadcast, mpsc, oneshot, Mutex as AsyncMutex};
 use tokio_util::sync::CancellationToken;
 use std::fmt;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Module selector (mirrors the old gRPC RadioModule for API compatibility)
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -36,13 +41,123 @@ pub enum PhyConfig {
     Qpsk(RadioPhyConfigQpsk),
 }
 
-/// QoS configuration (kept for API compatibility; not applied via binary protocol)
+impl PhyConfig {
+    /// Radio modulation for this PHY selection
+    pub fn to_modulation(&self, tx_power: u8) -> Modulation {
+        match self {
+            PhyConfig::Ofdm(ofdm) => {
+                let mcs = match ofdm.mcs {
+                    0 => OfdmMcs::BpskC1_2_4x,
+                    1 => OfdmMcs::BpskC1_2_2x,
+                    2 => OfdmMcs::QpskC1_2_2x,
+                    3 => OfdmMcs::QpskC1_2,
+                    4 => OfdmMcs::QpskC3_4,
+                    5 => OfdmMcs::QamC1_2,
+                    _ => OfdmMcs::QamC3_4,
+                };
+                let opt = match ofdm.opt {
+                    0 => OfdmBandwidthOption::Option1,
+                    1 => OfdmBandwidthOption::Option2,
+                    2 => OfdmBandwidthOption::Option3,
+                    _ => OfdmBandwidthOption::Option4,
+                };
+                Modulation::Ofdm(OfdmModulation {
+                    mcs,
+                    opt,
+                    pdt: 0x03,
+                    tx_power,
+                    lfo: false,
+                })
+            }
+            PhyConfig::Qpsk(qpsk) => {
+                let fchip = match qpsk.chip_freq {
+                    100 => QpskChipFrequency::Fchip100,
+                    200 => QpskChipFrequency::Fchip200,
+                    1000 => QpskChipFrequency::Fchip1000,
+                    _ => QpskChipFrequency::Fchip2000,
+                };
+                let mode = match qpsk.rate_mode {
+                    0 => QpskRateMode::RateMode0,
+                    1 => QpskRateMode::RateMode1,
+                    2 => QpskRateMode::RateMode2,
+                    3 => QpskRateMode::RateMode3,
+                    _ => QpskRateMode::RateMode4,
+                };
+                Modulation::Qpsk(QpskModulation {
+                    fchip,
+                    mode,
+                    tx_power,
+                })
+            }
+        }
+    }
+}
+
+/// QoS configuration
+///
+/// Only the enable flag and the automatic OFDM/QPSK choice reach the device,
+/// the binary protocol has no tx power, backoff or CCA settings.
 pub struct QoSConfig {
     pub enabled: bool,
     pub adaptive_modulation: bool,
     pub adaptive_tx_power: bool,
     pub adaptive_backoff: bool,
     pub cca_threshold: i32,
+    /// Let QoS pick OFDM or QPSK from the channel quality
+    pub adaptive_modulation_type: bool,
+    /// Best channel quality that still runs on QPSK
+    pub qpsk_crossover: ChannelQuality,
+}
+
+/// First reconnect delay, short so a blip is bridged before anyone notices
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(250);
+/// Longest delay between two reconnect attempts
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Exponential backoff of the receive stream reconnects
+///
+/// Each delay is drawn from the upper half of the current step, so GUIs that
+/// lost the same device don't all come back in lockstep.
+#[derive(Clone, Debug)]
+pub struct ReconnectBackoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl ReconnectBackoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    /// Attempts made since the last reset
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Delay before the next attempt, doubling per attempt up to `max`
+    pub fn next_delay<R: Rng>(&mut self, rng: &mut R) -> Duration {
+        let factor = 1u32.checked_shl(self.attempt).unwrap_or(u32::MAX);
+        let step = self.initial.saturating_mul(factor).min(self.max).as_millis() as u64;
+        self.attempt = self.attempt.saturating_add(1);
+
+        Duration::from_millis(rng.gen_range(step / 2..=step))
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Receive stream reconnect in progress, shown in the status bar
+#[derive(Clone, Copy, Debug)]
+pub struct ReconnectState {
+    pub attempt: u32,
+    pub retry_at: Instant,
 }
 
 /// Central client that provides a TX queue and RX broadcast channel backed
@@ -53,7 +168,12 @@ pub struct GrpcClient {
     tx_sender: mpsc::Sender<TxRequest>,
     rx_broadcast: broadcast::Sender<ReceiveEvent>,
     radio_client: Arc<AsyncMutex<Option<RadioClient>>>,
+    /// Bumped whenever `radio_client` is replaced, so a receive stream can
+    /// tell a reconnect by the user from a connection that died under it
+    connection: Arc<AtomicU64>,
+    reconnect: Arc<StdMutex<Option<ReconnectState>>>,
     rx_started: Arc<StdMutex<bool>>,
+    mtu: Arc<StdMutex<usize>>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -62,10 +182,18 @@ pub enum TxTarget {
     Network,
 }
 
+/// Outcome of a completed transmit request
+#[derive(Clone, Copy, Debug)]
+pub struct TxResponse {
+    pub latency: u32,
+    /// Attempts the radio needed, 0 if the server doesn't report them
+    pub attempts: u8,
+}
+
 pub struct TxRequest {
     pub target: TxTarget,
     pub payload: Vec<u8>,
-    pub resp: Option<oneshot::Sender<Result<u32, String>>>,
+    pub resp: Option<oneshot::Sender<Result<TxResponse, String>>>,
 }
 
 impl fmt::Debug for TxRequest {
@@ -91,6 +219,100 @@ pub struct ReceiveEvent {
     pub rssi: i32,
     pub latency: u32,
     pub packet_type: PacketType,
+    /// Set when the frame is a Reticulum announce
+    pub reticulum_info: Option<ReticulumPacketInfo>,
+}
+
+/// Default window received frames are collected over before the UI takes them
+pub const RX_COALESCE_WINDOW: Duration = Duration::from_millis(50);
+
+/// Collects received frames for a window after the first one, so a busy link
+/// costs the UI one update per window instead of one per frame
+///
+/// Every frame is kept, a zero window hands each one over on its own.
+#[derive(Debug)]
+pub struct ReceiveBatcher {
+    window: Duration,
+    pending: Vec<ReceiveEvent>,
+    started: Option<Instant>,
+}
+
+impl ReceiveBatcher {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Vec::new(),
+            started: None,
+        }
+    }
+
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    pub fn push(&mut self, event: ReceiveEvent, now: Instant) {
+        self.started.get_or_insert(now);
+        self.pending.push(event);
+    }
+
+    /// Frames collected so far, once the window of the oldest one is over
+    pub fn take_ready(&mut self, now: Instant) -> Option<Vec<ReceiveEvent>> {
+        let started = self.started?;
+        if now.duration_since(started) < self.window {
+            return None;
+        }
+
+        self.started = None;
+        Some(std::mem::take(&mut self.pending))
+    }
+}
+
+/// Largest payload `target` takes in a single frame on a server reporting `mtu`.
+///
+/// Module frames go out raw, so the server MTU is the limit. Network frames
+/// are read by the receiving side as one LDPC coded kaonic-net packet, which
+/// only has room for the coder's payload size.
+pub fn max_payload_size(target: TxTarget, mtu: usize) -> usize {
+    let raw = mtu.min(RADIO_FRAME_SIZE);
+    match target {
+        TxTarget::Radio(_) => raw,
+        TxTarget::Network => raw.min(LdpcPacketCoder::<RADIO_FRAME_SIZE>::MAX_PAYLOAD_SIZE),
+    }
+}
+
+/// Connects to kaonic-commd at `addr` and verifies it with a GetInfo
+/// round-trip, returning the client and the MTU the server reported.
+async fn connect(addr: &str) -> Result<(RadioClient, usize), String> {
+    let server_addr: std::net::SocketAddr = addr
+        .parse()
+        .map_err(|e| format!("Invalid address '{}': {}", addr, e))?;
+    let listen_addr: std::net::SocketAddr = "0.0.0.0:0".parse().unwrap();
+
+    let cancel = CancellationToken::new();
+    let client = Client::connect(
+        listen_addr,
+        server_addr,
+        MessageCoder::<1400, 5>::new(),
+        PacketIdNamespace::NONE,
+        cancel.clone(),
+    )
+    .await
+    .map_err(|e| format!("Connect error: {:?}", e))?;
+
+    let mut rc = RadioClient::new(client, cancel.clone())
+        .await
+        .map_err(|e| {
+            cancel.cancel();
+            format!("RadioClient error: {:?}", e)
+        })?;
+
+    // Stop the client tasks of a device that doesn't answer
+    let info = rc.get_info().await.map_err(|e| {
+        rc.cancel();
+        format!("GetInfo error: {:?}", e)
+    })?;
+
+    Ok((rc, info.mtu))
 }
 
 /// Check whether data begins with a kaonic-net network packet header.
@@ -113,8 +335,10 @@ impl GrpcClient {
         let radio_client: Arc<AsyncMutex<Option<RadioClient>>> =
             Arc::new(AsyncMutex::new(None));
         let rx_started = Arc::new(StdMutex::new(false));
+        let mtu = Arc::new(StdMutex::new(RADIO_FRAME_SIZE));
 
         let radio_client_worker = radio_client.clone();
+        let mtu_worker = mtu.clone();
         let runtime_clone = runtime.clone();
 
         // Background TX worker: dequeues requests and transmits via RadioClient
@@ -124,15 +348,29 @@ impl GrpcClient {
                     TxTarget::Radio(m) => m as usize,
                     TxTarget::Network => 0,
                 };
+                let max_payload = max_payload_size(req.target, *mtu_worker.lock().unwrap());
                 let mut rc = radio_client_worker.lock().await;
-                let res = if let Some(ref mut client) = *rc {
-                    let mut frame = Frame::<2048>::new();
-                    frame.copy_from_slice(&req.payload);
-                    client
-                        .transmit(module_idx, &frame)
-                        .await
-                        .map(|_| 0u32)
-                        .map_err(|e| format!("TX error: {:?}", e))
+                let res = if req.payload.len() > max_payload {
+                    Err(format!(
+                        "Payload of {} bytes exceeds the {} byte limit",
+                        req.payload.len(),
+                        max_payload
+                    ))
+                } else if let Some(ref mut client) = *rc {
+                    match Frame::<2048>::new_from_slice(&req.payload) {
+                        Ok(frame) => client
+                            .transmit(module_idx, &frame)
+                            .await
+                            .map(|report| TxResponse { latency: 0, attempts: report.attempts })
+                            .map_err(|e| match e {
+                                ControllerError::TransmitFailed(report) => format!(
+                                    "TX {:?} after {} attempts",
+                                    report.result, report.attempts
+                                ),
+                                e => format!("TX error: {:?}", e),
+                            }),
+                        Err(_) => Err("Payload doesn't fit a frame".to_string()),
+                    }
                 } else {
                     Err("Not connected".to_string())
                 };
@@ -148,10 +386,23 @@ impl GrpcClient {
             tx_sender,
             rx_broadcast,
             radio_client,
+            connection: Arc::new(AtomicU64::new(0)),
+            reconnect: Arc::new(StdMutex::new(None)),
             rx_started,
+            mtu,
         }
     }
 
+    /// Set while the receive stream waits to reconnect to the device
+    pub fn reconnect_state(&self) -> Option<ReconnectState> {
+        *self.reconnect.lock().unwrap()
+    }
+
+    /// Largest payload `target` takes in a single frame on the connected device.
+    pub fn max_payload_size(&self, target: TxTarget) -> usize {
+        max_payload_size(target, *self.mtu.lock().unwrap())
+    }
+
     /// Subscribe to the receive broadcast channel.
     pub fn rx_subscribe(&self) -> broadcast::Receiver<ReceiveEvent> {
         self.rx_broadcast.subscribe()
@@ -164,15 +415,15 @@ impl GrpcClient {
             .map_err(|e| format!("TX queue full: {}", e))
     }
 
-    /// Blocking transmit with optional timeout (ms).  Returns 0 for latency
-    /// since the binary protocol does not report it.
+    /// Blocking transmit with optional timeout (ms).  Latency is 0 since the
+    /// binary protocol does not report it.
     pub fn tx_send_blocking(
         &self,
         target: TxTarget,
         payload: Vec<u8>,
         timeout_ms: Option<u64>,
-    ) -> Result<u32, String> {
-        let (tx, rx) = oneshot::channel::<Result<u32, String>>();
+    ) -> Result<TxResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<TxResponse, String>>();
         let req = TxRequest { target, payload, resp: Some(tx) };
         self.tx_enqueue(req)?;
         if let Some(ms) = timeout_ms {
@@ -199,7 +450,9 @@ impl GrpcClient {
                 client.cancel();
             }
             *rc = None;
+            self.connection.fetch_add(1, Ordering::SeqCst);
         });
+        *self.reconnect.lock().unwrap() = None;
         *self.rx_started.lock().unwrap() = false;
         if let Ok(mut s) = self.server_addr.lock() {
             *s = addr;
@@ -213,42 +466,62 @@ impl GrpcClient {
     /// Connect to kaonic-commd via UDP, verify with a GetInfo round-trip, and
     /// store the RadioClient for subsequent operations.
     pub fn get_device_info(&self) -> Result<(), String> {
-        let addr_str = self.server_addr.lock().unwrap().clone();
-        let server_addr: std::net::SocketAddr = addr_str
-            .parse()
-            .map_err(|e| format!("Invalid address '{}': {}", addr_str, e))?;
-        let listen_addr: std::net::SocketAddr = "0.0.0.0:0".parse().unwrap();
+        let addr = self.server_addr.lock().unwrap().clone();
         let radio_client = self.radio_client.clone();
+        let connection = self.connection.clone();
+        let reconnect = self.reconnect.clone();
         let rx_started = self.rx_started.clone();
+        let mtu = self.mtu.clone();
 
         self.runtime.block_on(async move {
-            let cancel = CancellationToken::new();
-            let client = Client::connect(
-                listen_addr,
-                server_addr,
-                MessageCoder::<1400, 5>::new(),
-                cancel.clone(),
-            )
-            .await
-            .map_err(|e| format!("Connect error: {:?}", e))?;
-
-            let mut rc = RadioClient::new(client, cancel)
-                .await
-                .map_err(|e| format!("RadioClient error: {:?}", e))?;
-
-            rc.get_info()
-                .await
-                .map_err(|e| format!("GetInfo error: {:?}", e))?;
+            let (rc, server_mtu) = connect(&addr).await?;
+            *mtu.lock().unwrap() = server_mtu;
 
             *rx_started.lock().unwrap() = false;
-            *radio_client.lock().await = Some(rc);
+            let mut radio_client = radio_client.lock().await;
+            *radio_client = Some(rc);
+            connection.fetch_add(1, Ordering::SeqCst);
+            *reconnect.lock().unwrap() = None;
             Ok(())
         })
     }
 
-    /// Apply radio frequency/channel configuration and modulation.
-    /// QoS parameters are accepted for API compatibility but are not forwarded
-    /// (the binary protocol does not support them).
+    /// Fetch the regional frequency plans offered by the connected device.
+    pub fn get_frequency_plans(&self) -> Result<Vec<FrequencyPlan>, String> {
+        let radio_client = self.radio_client.clone();
+        self.runtime.block_on(async move {
+            let mut rc = radio_client.lock().await;
+            if let Some(ref mut client) = *rc {
+                client
+                    .get_frequency_plans()
+                    .await
+                    .map_err(|e| format!("GetFrequencyPlans error: {:?}", e))
+            } else {
+                Err("Not connected".to_string())
+            }
+        })
+    }
+
+    /// Fetch the API version and feature flags of the connected device.
+    pub fn get_capabilities(&self) -> Result<GetCapabilitiesResponse, String> {
+        let radio_client = self.radio_client.clone();
+        self.runtime.block_on(async move {
+            let mut rc = radio_client.lock().await;
+            if let Some(ref mut client) = *rc {
+                client
+                    .get_capabilities()
+                    .await
+                    .map_err(|e| format!("GetCapabilities error: {:?}", e))
+            } else {
+                Err("Not connected".to_string())
+            }
+        })
+    }
+
+    /// Apply radio frequency/channel configuration, modulation and QoS.
+    ///
+    /// With `apply_config` they go out as one request, otherwise the radio
+    /// briefly runs the new frequency with the old modulation.
     pub fn configure_radio(
         &self,
         module: RadioModule,
@@ -257,9 +530,10 @@ impl GrpcClient {
         channel_spacing: u32,
         tx_power: u32,
         phy_config: Option<PhyConfig>,
-        _qos_enabled: bool,
-        _qos_config: QoSConfig,
+        qos_enabled: bool,
+        qos_config: QoSConfig,
         bandwidth_filter: i32,
+        apply_config: bool,
     ) -> Result<(), String> {
         let module_idx = module as usize;
         let bw = if bandwidth_filter == 0 {
@@ -273,56 +547,32 @@ impl GrpcClient {
             channel_spacing: Hertz::from_khz(channel_spacing as u64),
             bandwidth_filter: bw,
         };
-        let modulation = phy_config.map(|pc| match pc {
-            PhyConfig::Ofdm(ofdm) => {
-                let mcs = match ofdm.mcs {
-                    0 => OfdmMcs::BpskC1_2_4x,
-                    1 => OfdmMcs::BpskC1_2_2x,
-                    2 => OfdmMcs::QpskC1_2_2x,
-                    3 => OfdmMcs::QpskC1_2,
-                    4 => OfdmMcs::QpskC3_4,
-                    5 => OfdmMcs::QamC1_2,
-                    _ => OfdmMcs::QamC3_4,
-                };
-                let opt = match ofdm.opt {
-                    0 => OfdmBandwidthOption::Option1,
-                    1 => OfdmBandwidthOption::Option2,
-                    2 => OfdmBandwidthOption::Option3,
-                    _ => OfdmBandwidthOption::Option4,
-                };
-                Modulation::Ofdm(OfdmModulation {
-                    mcs,
-                    opt,
-                    pdt: 0x03,
-                    tx_power: tx_power as u8,
-                })
-            }
-            PhyConfig::Qpsk(qpsk) => {
-                let fchip = match qpsk.chip_freq {
-                    100 => QpskChipFrequency::Fchip100,
-                    200 => QpskChipFrequency::Fchip200,
-                    1000 => QpskChipFrequency::Fchip1000,
-                    _ => QpskChipFrequency::Fchip2000,
-                };
-                let mode = match qpsk.rate_mode {
-                    0 => QpskRateMode::RateMode0,
-                    1 => QpskRateMode::RateMode1,
-                    2 => QpskRateMode::RateMode2,
-                    3 => QpskRateMode::RateMode3,
-                    _ => QpskRateMode::RateMode4,
-                };
-                Modulation::Qpsk(QpskModulation {
-                    fchip,
-                    mode,
-                    tx_power: tx_power as u8,
-                })
-            }
-        });
+        let modulation = phy_config.map(|pc| pc.to_modulation(tx_power as u8));
 
         let radio_client = self.radio_client.clone();
         self.runtime.block_on(async move {
             let mut rc = radio_client.lock().await;
             if let Some(ref mut client) = *rc {
+                let mut qos = client
+                    .get_qos(module_idx)
+                    .await
+                    .map_err(|e| format!("QoS error: {:?}", e))?;
+                qos.enabled = qos_enabled;
+                qos.adaptive_modulation_type = qos_config.adaptive_modulation_type;
+                qos.qpsk_crossover = qos_config.qpsk_crossover;
+
+                if apply_config {
+                    return client
+                        .apply_config(ApplyConfigRequest {
+                            module: module_idx,
+                            config,
+                            modulation,
+                            qos: Some(qos),
+                        })
+                        .await
+                        .map_err(|e| format!("Config error: {:?}", e));
+                }
+
                 client
                     .set_radio_config(module_idx, config)
                     .await
@@ -333,6 +583,11 @@ impl GrpcClient {
                         .await
                         .map_err(|e| format!("Modulation error: {:?}", e))?;
                 }
+
+                client
+                    .set_qos(qos)
+                    .await
+                    .map_err(|e| format!("QoS error: {:?}", e))?;
                 Ok(())
             } else {
                 Err("Not connected".to_string())
@@ -359,47 +614,187 @@ impl GrpcClient {
 
         let radio_client = self.radio_client.clone();
         let rx_broadcast = self.rx_broadcast.clone();
+        let server_addr = self.server_addr.clone();
+        let connection = self.connection.clone();
+        let reconnect = self.reconnect.clone();
+        let mtu = self.mtu.clone();
 
         self.runtime.spawn(async move {
-            let mut module_rx = {
-                let rc = radio_client.lock().await;
-                match *rc {
-                    Some(ref client) => client.module_receive(),
-                    None => return,
-                }
-            };
+            let mut generation = connection.load(Ordering::SeqCst);
+            let mut backoff = ReconnectBackoff::new(RECONNECT_INITIAL_DELAY, RECONNECT_MAX_DELAY);
 
             loop {
-                match module_rx.recv().await {
-                    Ok(rx_module) => {
-                        let frame_data = rx_module.frame.as_slice().to_vec();
-                        let packet_type =
-                            if frame_data.len() >= kaonic_net::packet::HEADER_SIZE {
-                                PacketType::Network
-                            } else {
-                                PacketType::Custom
-                            };
-                        let event = ReceiveEvent {
-                            timestamp: chrono::Local::now(),
-                            module: rx_module.module as i32,
-                            frame_data,
-                            rssi: 0,
-                            latency: 0,
-                            packet_type,
-                        };
-                        if rx.send(event.clone()).is_err() {
-                            return;
+                let module_rx = {
+                    let rc = radio_client.lock().await;
+                    match *rc {
+                        Some(ref client) if connection.load(Ordering::SeqCst) == generation => {
+                            client.module_receive()
                         }
-                        let _ = rx_broadcast.send(event);
+                        _ => return,
                     }
-                    Err(_) => break,
+                };
+
+                if !Self::forward_receive(module_rx, &rx, &rx_broadcast).await {
+                    return;
+                }
+
+                // The connection ended without the user replacing it
+                loop {
+                    let delay = backoff.next_delay(&mut OsRng);
+                    *reconnect.lock().unwrap() = Some(ReconnectState {
+                        attempt: backoff.attempt(),
+                        retry_at: Instant::now() + delay,
+                    });
+                    tokio::time::sleep(delay).await;
+
+                    if connection.load(Ordering::SeqCst) != generation {
+                        *reconnect.lock().unwrap() = None;
+                        return;
+                    }
+
+                    let addr = server_addr.lock().unwrap().clone();
+                    let Ok((client, server_mtu)) = connect(&addr).await else {
+                        continue;
+                    };
+
+                    let mut rc = radio_client.lock().await;
+                    if connection.load(Ordering::SeqCst) != generation {
+                        let mut client = client;
+                        client.cancel();
+                        *reconnect.lock().unwrap() = None;
+                        return;
+                    }
+                    if let Some(ref mut old) = *rc {
+                        old.cancel();
+                    }
+                    *rc = Some(client);
+                    *mtu.lock().unwrap() = server_mtu;
+                    generation = connection.fetch_add(1, Ordering::SeqCst) + 1;
+
+                    backoff.reset();
+                    *reconnect.lock().unwrap() = None;
+                    break;
                 }
             }
         });
     }
 
+    /// Forwards received frames until the stream closes, returns false once
+    /// the GUI side is gone
+    async fn forward_receive(
+        mut module_rx: broadcast::Receiver<Box<ReceiveModule>>,
+        rx: &mpsc::UnboundedSender<ReceiveEvent>,
+        rx_broadcast: &broadcast::Sender<ReceiveEvent>,
+    ) -> bool {
+        loop {
+            match module_rx.recv().await {
+                Ok(rx_module) => {
+                    let frame_data = rx_module.frame.as_slice().to_vec();
+                    let packet_type =
+                        if frame_data.len() >= kaonic_net::packet::HEADER_SIZE {
+                            PacketType::Network
+                        } else {
+                            PacketType::Custom
+                        };
+                    let event = ReceiveEvent {
+                        timestamp: chrono::Local::now(),
+                        module: rx_module.module as i32,
+                        reticulum_info: ReticulumPacketInfo::parse(&frame_data),
+                        frame_data,
+                        rssi: rx_module.rssi as i32,
+                        latency: 0,
+                        packet_type,
+                    };
+                    if rx.send(event.clone()).is_err() {
+                        return false;
+                    }
+                    let _ = rx_broadcast.send(event);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return true,
+            }
+        }
+    }
+
     /// No-op: radio frames already include network-layer packets; no separate
     /// network receive stream is needed with the binary protocol.
     pub fn start_network_receive_stream(&self, _rx: mpsc::UnboundedSender<ReceiveEvent>) {}
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_backoff_bounds() {
+        let mut backoff = ReconnectBackoff::new(RECONNECT_INITIAL_DELAY, RECONNECT_MAX_DELAY);
+
+        for _ in 0..100 {
+            backoff.reset();
+
+            // Quick first retry for short blips
+            let first = backoff.next_delay(&mut OsRng);
+            assert!(first >= RECONNECT_INITIAL_DELAY / 2 && first <= RECONNECT_INITIAL_DELAY);
+
+            let mut step = RECONNECT_INITIAL_DELAY;
+            for _ in 0..40 {
+                step = (step * 2).min(RECONNECT_MAX_DELAY);
+                let delay = backoff.next_delay(&mut OsRng);
+                assert!(delay >= step / 2 && delay <= step, "{delay:?} outside {step:?}");
+            }
+            assert_eq!(backoff.attempt(), 41);
+        }
+
+        // Draws spread out instead of repeating in lockstep
+        let delays: Vec<_> = (0..20)
+            .map(|_| {
+                let mut backoff =
+                    ReconnectBackoff::new(RECONNECT_INITIAL_DELAY, RECONNECT_MAX_DELAY);
+                (0..8).map(|_| backoff.next_delay(&mut OsRng)).last().unwrap()
+            })
+            .collect();
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    fn receive_event(rssi: i32) -> ReceiveEvent {
+        ReceiveEvent {
+            timestamp: chrono::Local::now(),
+            module: 0,
+            frame_data: vec![0; 16],
+            rssi,
+            latency: 0,
+            packet_type: PacketType::Custom,
+            reticulum_info: None,
+        }
+    }
+
+    #[test]
+    fn test_receive_batcher_coalesces_window() {
+        let start = Instant::now();
+        let mut batcher = ReceiveBatcher::new(RX_COALESCE_WINDOW);
+        assert!(batcher.take_ready(start).is_none());
+
+        for (i, rssi) in [-60, -61, -62].into_iter().enumerate() {
+            let now = start + Duration::from_millis(i as u64 * 10);
+            batcher.push(receive_event(rssi), now);
+            assert!(batcher.take_ready(now).is_none());
+        }
+
+        let batch = batcher.take_ready(start + RX_COALESCE_WINDOW).expect("batch");
+        let rssi: Vec<_> = batch.iter().map(|event| event.rssi).collect();
+        assert_eq!(rssi, [-60, -61, -62]);
+
+        // The next window starts with the next frame
+        let later = start + RX_COALESCE_WINDOW * 3;
+        assert!(batcher.take_ready(later).is_none());
+        batcher.push(receive_event(-70), later);
+        assert!(batcher.take_ready(later + RX_COALESCE_WINDOW / 2).is_none());
+        assert_eq!(batcher.take_ready(later + RX_COALESCE_WINDOW).unwrap().len(), 1);
+
+        // Without a window every frame goes out on its own
+        batcher.set_window(Duration::ZERO);
+        batcher.push(receive_event(-80), later);
+        assert_eq!(batcher.take_ready(later).unwrap().len(), 1);
+    }
+}
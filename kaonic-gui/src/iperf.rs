@@ -79,8 +79,11 @@ pub fn start_client(
 
             // Transmit using central TX queue (Network target) with a oneshot reply
             match client.lock().tx_send_blocking(TxTarget::Network, payload.clone(), Some(5000)) {
-                Ok(_latency) => {
-                    // latency available if needed
+                Ok(tx) => {
+                    if tx.attempts > 1 {
+                        let mut s = state.lock();
+                        s.iperf_output.push_str(&format!("seq {} sent after {} attempts\n", seq, tx.attempts));
+                    }
                 }
                 Err(e) => {
                     let mut s = state.lock();
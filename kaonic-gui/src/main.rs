@@ -17,6 +17,8 @@ use winit::window::WindowBuilder;
 mod grpc_client;
 mod ui;
 mod iperf;
+mod reticulum;
+mod theme;
 
 use clap::Parser;
 use grpc_client::GrpcClient;
@@ -128,6 +130,12 @@ fn main() {
     // Create app
     let mut app = RadioGuiApp::new(client.clone(), state.clone(), runtime);
 
+    // Restore the saved display settings
+    match theme::Theme::load() {
+        Ok(theme) => state.lock().theme = theme,
+        Err(e) => state.lock().status_message = e,
+    }
+
     // Apply CLI options
     let cli = Cli::parse();
     if let Some(ip) = cli.ip {
@@ -154,6 +162,9 @@ fn main() {
                 s.status_message = "Connected successfully".to_string();
                 drop(s);
 
+                app.fetch_frequency_plans();
+                app.fetch_capabilities();
+
                 // start receive stream (uses client + state)
                 app.start_receiving();
             }
@@ -0,0 +1,301 @@
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::grpc_client::ReceiveEvent;
+
+/// Size of a Reticulum destination or transport hash, truncated SHA-256
+pub const RETICULUM_HASH_SIZE: usize = 16;
+
+/// Default time a destination stays listed after its last announce
+pub const DEFAULT_DESTINATION_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+const FLAG_IFAC: u8 = 1 << 7;
+const FLAG_HEADER_2: u8 = 1 << 6;
+const FLAG_CONTEXT: u8 = 1 << 5;
+const FLAG_TRANSPORT: u8 = 1 << 4;
+const DESTINATION_TYPE_MASK: u8 = 0b11 << 2;
+const PACKET_TYPE_MASK: u8 = 0b11;
+
+const PACKET_TYPE_ANNOUNCE: u8 = 0x01;
+const DESTINATION_TYPE_SINGLE: u8 = 0x00;
+
+/// Announce data without app data: public key, name hash, random hash and signature
+const ANNOUNCE_MIN_DATA_SIZE: usize = 64 + 10 + 10 + 64;
+/// Ratchet public key of announces with the context flag set
+const ANNOUNCE_RATCHET_SIZE: usize = 32;
+/// Reticulum drops packets that travelled more hops than this
+const MAX_HOPS: u8 = 128;
+
+/// Header of a received Reticulum announce
+///
+/// There is no signature check, so the frame is only taken for an announce
+/// when the header and the size fit one. Frames arrive as the radio received
+/// them, announces carried inside kaonic-net packets aren't reassembled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReticulumPacketInfo {
+    pub destination: [u8; RETICULUM_HASH_SIZE],
+    pub hops: u8,
+}
+
+impl ReticulumPacketInfo {
+    /// Reads the header of an announce, other frames return `None`
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (&flags, rest) = data.split_first()?;
+        let (&hops, rest) = rest.split_first()?;
+
+        // Interface access codes are stripped by the receiving interface
+        if flags & FLAG_IFAC != 0
+            || flags & PACKET_TYPE_MASK != PACKET_TYPE_ANNOUNCE
+            || (flags & DESTINATION_TYPE_MASK) >> 2 != DESTINATION_TYPE_SINGLE
+            || hops > MAX_HOPS
+        {
+            return None;
+        }
+
+        // Only transported packets carry the transport id of the node
+        // that rebroadcast them ahead of the destination
+        let header_2 = flags & FLAG_HEADER_2 != 0;
+        if header_2 != (flags & FLAG_TRANSPORT != 0) {
+            return None;
+        }
+
+        let rest = if header_2 { split_hash(rest)?.1 } else { rest };
+        let (destination, rest) = split_hash(rest)?;
+
+        // Context byte ahead of the announce data
+        let min_data_size = if flags & FLAG_CONTEXT != 0 {
+            ANNOUNCE_MIN_DATA_SIZE + ANNOUNCE_RATCHET_SIZE
+        } else {
+            ANNOUNCE_MIN_DATA_SIZE
+        };
+        if rest.len() < 1 + min_data_size {
+            return None;
+        }
+
+        Some(Self { destination, hops })
+    }
+}
+
+fn split_hash(data: &[u8]) -> Option<([u8; RETICULUM_HASH_SIZE], &[u8])> {
+    if data.len() < RETICULUM_HASH_SIZE {
+        return None;
+    }
+
+    let (hash, rest) = data.split_at(RETICULUM_HASH_SIZE);
+    Some((hash.try_into().ok()?, rest))
+}
+
+/// A destination heard announcing
+#[derive(Clone, Debug)]
+pub struct Destination {
+    pub hash: [u8; RETICULUM_HASH_SIZE],
+    pub first_seen: DateTime<Local>,
+    pub last_seen: DateTime<Local>,
+    /// Hops and RSSI of the latest announce
+    pub hops: u8,
+    pub rssi: i32,
+    pub announces: u32,
+}
+
+/// Unique Reticulum destinations from the received announces
+#[derive(Debug, Default)]
+pub struct DestinationTable {
+    destinations: HashMap<[u8; RETICULUM_HASH_SIZE], Destination>,
+}
+
+impl DestinationTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the announce of `event` if it carries one, returns whether it did
+    pub fn record(&mut self, event: &ReceiveEvent) -> bool {
+        let Some(info) = &event.reticulum_info else {
+            return false;
+        };
+
+        let destination = self
+            .destinations
+            .entry(info.destination)
+            .or_insert_with(|| Destination {
+                hash: info.destination,
+                first_seen: event.timestamp,
+                last_seen: event.timestamp,
+                hops: info.hops,
+                rssi: event.rssi,
+                announces: 0,
+            });
+        destination.last_seen = event.timestamp;
+        destination.hops = info.hops;
+        destination.rssi = event.rssi;
+        destination.announces = destination.announces.saturating_add(1);
+
+        true
+    }
+
+    /// Drops the destinations that didn't announce for longer than `max_age`,
+    /// returns whether any were dropped
+    pub fn expire(&mut self, now: DateTime<Local>, max_age: Duration) -> bool {
+        let count = self.destinations.len();
+        self.destinations.retain(|_, destination| {
+            // A clock step back leaves the age negative, keep those
+            (now - destination.last_seen)
+                .to_std()
+                .map_or(true, |age| age <= max_age)
+        });
+
+        self.destinations.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.destinations.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.destinations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.destinations.is_empty()
+    }
+
+    /// Destinations, latest announce first
+    pub fn destinations(&self) -> Vec<Destination> {
+        let mut destinations: Vec<Destination> = self.destinations.values().cloned().collect();
+        destinations.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then(a.hash.cmp(&b.hash)));
+        destinations
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("destination,first_seen,last_seen,hops,rssi,announces\n");
+        for destination in self.destinations() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                hex::encode(destination.hash),
+                destination.first_seen.to_rfc3339(),
+                destination.last_seen.to_rfc3339(),
+                destination.hops,
+                destination.rssi,
+                destination.announces,
+            );
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_client::PacketType;
+
+    fn announce(flags: u8, hops: u8, destination: u8, data_size: usize) -> Vec<u8> {
+        let mut frame = vec![flags, hops];
+        if flags & FLAG_HEADER_2 != 0 {
+            frame.extend_from_slice(&[0xAA; RETICULUM_HASH_SIZE]);
+        }
+        frame.extend_from_slice(&[destination; RETICULUM_HASH_SIZE]);
+        frame.push(0);
+        frame.extend_from_slice(&vec![0x55; data_size]);
+        frame
+    }
+
+    fn receive_event(frame: Vec<u8>, rssi: i32, timestamp: DateTime<Local>) -> ReceiveEvent {
+        ReceiveEvent {
+            timestamp,
+            module: 0,
+            reticulum_info: ReticulumPacketInfo::parse(&frame),
+            frame_data: frame,
+            rssi,
+            latency: 0,
+            packet_type: PacketType::Network,
+        }
+    }
+
+    #[test]
+    fn test_parse_announce() {
+        let info = ReticulumPacketInfo::parse(&announce(0x01, 3, 0x11, ANNOUNCE_MIN_DATA_SIZE))
+            .expect("announce");
+        assert_eq!(info.destination, [0x11; RETICULUM_HASH_SIZE]);
+        assert_eq!(info.hops, 3);
+
+        // Transported announce with a ratchet
+        let info = ReticulumPacketInfo::parse(&announce(
+            FLAG_HEADER_2 | FLAG_CONTEXT | FLAG_TRANSPORT | 0x01,
+            1,
+            0x22,
+            ANNOUNCE_MIN_DATA_SIZE + ANNOUNCE_RATCHET_SIZE,
+        ))
+        .expect("transported announce");
+        assert_eq!(info.destination, [0x22; RETICULUM_HASH_SIZE]);
+        assert_eq!(info.hops, 1);
+
+        // Too short, data packets, link destinations, access codes and a
+        // transport id without transport aren't announces
+        let not_announces = [
+            announce(0x01, 0, 0x11, ANNOUNCE_MIN_DATA_SIZE - 1),
+            announce(FLAG_CONTEXT | 0x01, 0, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            announce(0x00, 0, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            announce(0x0D, 0, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            announce(FLAG_IFAC | 0x01, 0, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            announce(FLAG_HEADER_2 | 0x01, 0, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            announce(0x01, MAX_HOPS + 1, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            Vec::new(),
+        ];
+        for frame in not_announces {
+            assert!(
+                ReticulumPacketInfo::parse(&frame).is_none(),
+                "{:02X?}",
+                &frame[..frame.len().min(2)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_destination_table_deduplicates_and_expires() {
+        let start = Local::now();
+        let mut table = DestinationTable::new();
+
+        assert!(table.record(&receive_event(
+            announce(0x01, 2, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            -80,
+            start
+        )));
+        assert!(table.record(&receive_event(
+            announce(0x01, 1, 0x22, ANNOUNCE_MIN_DATA_SIZE),
+            -60,
+            start
+        )));
+        let later = start + chrono::Duration::seconds(60);
+        assert!(table.record(&receive_event(
+            announce(0x01, 4, 0x11, ANNOUNCE_MIN_DATA_SIZE),
+            -90,
+            later
+        )));
+        assert!(!table.record(&receive_event(vec![0; 16], -50, later)));
+
+        // One entry per destination, latest announce first
+        let destinations = table.destinations();
+        assert_eq!(destinations.len(), 2);
+        assert_eq!(destinations[0].hash, [0x11; RETICULUM_HASH_SIZE]);
+        assert_eq!(destinations[0].first_seen, start);
+        assert_eq!(destinations[0].last_seen, later);
+        assert_eq!(
+            (
+                destinations[0].hops,
+                destinations[0].rssi,
+                destinations[0].announces
+            ),
+            (4, -90, 2)
+        );
+
+        assert_eq!(table.to_csv().lines().count(), 3);
+
+        assert!(!table.expire(later, Duration::from_secs(60)));
+        assert!(table.expire(later, Duration::from_secs(30)));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.destinations()[0].hash, [0x11; RETICULUM_HASH_SIZE]);
+    }
+}
@@ -0,0 +1,266 @@
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// RGBA color as imgui takes it
+pub type Color = [f32; 4];
+
+/// Signal quality labels of the RSSI colors, best first
+pub const RSSI_LEVELS: [&str; 4] = ["Excellent", "Good", "Fair", "Poor"];
+
+/// Lower RSSI bounds in dBm of the excellent, good and fair colors
+pub const DEFAULT_RSSI_THRESHOLDS: [i32; 3] = [-50, -70, -85];
+
+/// Range the RSSI thresholds can be set to, matching what the radio reports
+pub const RSSI_THRESHOLD_RANGE: (i32, i32) = (-127, 0);
+
+/// Selectable color presets
+pub const PALETTES: [(&str, PaletteKind); 2] = [
+    ("Default", PaletteKind::Default),
+    ("Colorblind safe", PaletteKind::ColorblindSafe),
+];
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]
+}
+
+/// Color preset of the GUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteKind {
+    /// Green to red signal colors, cyan and magenta packet sources
+    #[default]
+    Default,
+    /// Okabe-Ito colors that stay apart with red-green color blindness
+    ColorblindSafe,
+}
+
+impl PaletteKind {
+    pub fn palette(self) -> Palette {
+        match self {
+            PaletteKind::Default => Palette {
+                rssi: [
+                    [0.0, 1.0, 0.0, 1.0],
+                    [0.5, 1.0, 0.0, 1.0],
+                    [1.0, 0.65, 0.0, 1.0],
+                    [1.0, 0.0, 0.0, 1.0],
+                ],
+                radio: [0.0, 1.0, 1.0, 1.0],
+                network: [1.0, 0.0, 1.0, 1.0],
+                ok: [0.0, 1.0, 0.0, 1.0],
+                warning: [1.0, 0.65, 0.0, 1.0],
+                error: [1.0, 0.0, 0.0, 1.0],
+            },
+            PaletteKind::ColorblindSafe => Palette {
+                rssi: [
+                    rgb(86, 180, 233), // sky blue
+                    rgb(0, 158, 115),  // bluish green
+                    rgb(240, 228, 66), // yellow
+                    rgb(213, 94, 0),   // vermillion
+                ],
+                radio: rgb(86, 180, 233),    // sky blue
+                network: rgb(204, 121, 167), // reddish purple
+                ok: rgb(86, 180, 233),
+                warning: rgb(230, 159, 0), // orange
+                error: rgb(213, 94, 0),
+            },
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            PaletteKind::Default => "default",
+            PaletteKind::ColorblindSafe => "colorblind_safe",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        PALETTES
+            .iter()
+            .map(|(_, kind)| *kind)
+            .find(|kind| kind.key() == key)
+    }
+}
+
+/// Colors of a preset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Signal quality colors in the order of [`RSSI_LEVELS`]
+    pub rssi: [Color; 4],
+    /// Frames received by a radio module
+    pub radio: Color,
+    /// Frames received through the network
+    pub network: Color,
+    pub ok: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+/// Color settings of the GUI, kept across runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub palette: PaletteKind,
+    /// Lower RSSI bounds in dBm of the excellent, good and fair colors,
+    /// weaker signals get the poor color
+    pub rssi_thresholds: [i32; 3],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            palette: PaletteKind::default(),
+            rssi_thresholds: DEFAULT_RSSI_THRESHOLDS,
+        }
+    }
+}
+
+impl Theme {
+    pub fn colors(&self) -> Palette {
+        self.palette.palette()
+    }
+
+    /// Color of a signal received with `rssi` dBm
+    pub fn rssi_color(&self, rssi: i32) -> Color {
+        let colors = self.colors().rssi;
+        self.rssi_thresholds
+            .iter()
+            .position(|threshold| rssi >= *threshold)
+            .map_or(colors[3], |level| colors[level])
+    }
+
+    /// Keeps the thresholds in range and descending, so each level stays reachable
+    pub fn normalize(&mut self) {
+        let (min, max) = RSSI_THRESHOLD_RANGE;
+        let mut upper = max;
+        for threshold in self.rssi_thresholds.iter_mut() {
+            *threshold = (*threshold).clamp(min, upper);
+            upper = *threshold;
+        }
+    }
+
+    /// Loads the saved theme, the default when none was saved yet
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = settings_path() else {
+            return Ok(Self::default());
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+        };
+
+        serde_json::from_str::<Value>(&text)
+            .map(|value| Self::from_json(&value))
+            .map_err(|err| format!("Invalid theme {}: {}", path.display(), err))
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = settings_path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no configuration directory")
+        })?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, serde_json::to_string_pretty(&self.to_json())?)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "palette": self.palette.key(),
+            "rssi_thresholds": self.rssi_thresholds,
+        })
+    }
+
+    /// Missing or invalid keys keep their default
+    fn from_json(value: &Value) -> Self {
+        let mut theme = Self::default();
+
+        if let Some(palette) = value["palette"].as_str().and_then(PaletteKind::from_key) {
+            theme.palette = palette;
+        }
+        if let Some(thresholds) = value["rssi_thresholds"].as_array() {
+            let thresholds: Vec<i32> = thresholds
+                .iter()
+                .filter_map(|threshold| threshold.as_i64())
+                .filter_map(|threshold| i32::try_from(threshold).ok())
+                .collect();
+            if let Ok(thresholds) = thresholds.try_into() {
+                theme.rssi_thresholds = thresholds;
+            }
+        }
+
+        theme.normalize();
+        theme
+    }
+}
+
+/// `kaonic-gui/theme.json` in the user's configuration directory
+fn settings_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("kaonic-gui").join("theme.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_json() {
+        // Missing keys keep their default
+        assert_eq!(Theme::from_json(&json!({})), Theme::default());
+        assert_eq!(
+            Theme::from_json(&json!({ "palette": "colorblind_safe" })),
+            Theme {
+                palette: PaletteKind::ColorblindSafe,
+                rssi_thresholds: DEFAULT_RSSI_THRESHOLDS,
+            }
+        );
+
+        // Unknown palettes and thresholds of the wrong length are ignored
+        let theme = Theme::from_json(&json!({ "palette": "neon", "rssi_thresholds": [-40, -60] }));
+        assert_eq!(theme, Theme::default());
+        let theme = Theme::from_json(&json!({ "rssi_thresholds": [-40, -60, -80, -90] }));
+        assert_eq!(theme.rssi_thresholds, DEFAULT_RSSI_THRESHOLDS);
+
+        // Out of order and out of range thresholds end up descending within range
+        let theme = Theme::from_json(&json!({ "rssi_thresholds": [-90, -60, 20] }));
+        assert_eq!(theme.rssi_thresholds, [-90, -90, -90]);
+        let theme = Theme::from_json(&json!({ "rssi_thresholds": [10, -200, -100] }));
+        assert_eq!(theme.rssi_thresholds, [0, -127, -127]);
+
+        // A saved theme loads back unchanged
+        let theme = Theme {
+            palette: PaletteKind::ColorblindSafe,
+            rssi_thresholds: [-45, -65, -95],
+        };
+        assert_eq!(Theme::from_json(&theme.to_json()), theme);
+    }
+
+    #[test]
+    fn test_rssi_color_boundaries() {
+        let theme = Theme::default();
+        let colors = theme.colors().rssi;
+
+        // Each threshold is the lowest RSSI of its level
+        assert_eq!(theme.rssi_color(0), colors[0]);
+        assert_eq!(theme.rssi_color(-50), colors[0]);
+        assert_eq!(theme.rssi_color(-51), colors[1]);
+        assert_eq!(theme.rssi_color(-70), colors[1]);
+        assert_eq!(theme.rssi_color(-71), colors[2]);
+        assert_eq!(theme.rssi_color(-85), colors[2]);
+        assert_eq!(theme.rssi_color(-86), colors[3]);
+        assert_eq!(theme.rssi_color(-127), colors[3]);
+
+        // Equal thresholds skip the levels between them
+        let theme = Theme {
+            rssi_thresholds: [-60, -60, -60],
+            ..Theme::default()
+        };
+        assert_eq!(theme.rssi_color(-60), colors[0]);
+        assert_eq!(theme.rssi_color(-61), colors[3]);
+    }
+}
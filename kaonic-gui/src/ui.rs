@@ -1,20 +1,41 @@
-use crate::grpc_client::{GrpcClient, PhyConfig, QoSConfig, RadioModule, RadioPhyConfigOfdm, RadioPhyConfigQpsk, ReceiveEvent, TxTarget};
+use crate::grpc_client::{GrpcClient, PhyConfig, QoSConfig, RadioModule, RadioPhyConfigOfdm, RadioPhyConfigQpsk, ReceiveBatcher, ReceiveEvent, TxTarget, RX_COALESCE_WINDOW};
 use imgui::*;
+use kaonic_ctrl::protocol::{CAPABILITY_APPLY_CONFIG, CAPABILITY_OFDM, CAPABILITY_QOS, CAPABILITY_QPSK};
+use kaonic_ctrl::radio::{ChannelQuality, FrequencyPlan};
+use kaonic_qos::distance::estimate_distance_m;
 use parking_lot::Mutex;
+use radio_common::Modulation;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::runtime::Runtime;
 use tokio::sync::{mpsc, oneshot};
 use crate::iperf::{start_client, start_server_monitor};
+use crate::reticulum::{DestinationTable, DEFAULT_DESTINATION_MAX_AGE};
+use crate::theme::{Theme, PALETTES, RSSI_LEVELS, RSSI_THRESHOLD_RANGE};
+
+/// Crossover choices of the automatic OFDM/QPSK selection, best quality first
+const QPSK_CROSSOVERS: [(&str, ChannelQuality); 5] = [
+    ("Excellent", ChannelQuality::Excellent),
+    ("Good", ChannelQuality::Good),
+    ("Fair", ChannelQuality::Fair),
+    ("Poor", ChannelQuality::Poor),
+    ("Bad", ChannelQuality::Bad),
+];
+
+/// RF215 channel numbers are 9 bits wide, the radio rejects what its band can't tune
+const MAX_CHANNEL: i32 = 511;
 
 pub struct AppState {
     // Connection
     pub server_addr: String,
     pub connected: bool,
     pub status_message: String,
+    pub capabilities: Option<u32>, // CAPABILITY_* flags, None offers everything
 
     // Radio configuration
     pub selected_module: i32,
+    pub frequency_plans: Vec<FrequencyPlan>,
+    pub frequency_plan: usize, // index into frequency_plans, len() = Custom
     pub freq_mhz: f32,
     pub channel: i32,
     pub channel_spacing_khz: i32,
@@ -33,6 +54,8 @@ pub struct AppState {
     pub qos_adaptive_tx_power: bool,
     pub qos_adaptive_backoff: bool,
     pub qos_cca_threshold: i32,
+    pub qos_adaptive_modulation_type: bool,
+    pub qos_qpsk_crossover: usize, // index into QPSK_CROSSOVERS
 
     // Bandwidth Filter
     pub bandwidth_filter: i32, // 0 = Narrow, 1 = Wide
@@ -44,16 +67,32 @@ pub struct AppState {
     pub continuous_tx: bool,
     pub tx_pause_ms: i32,
     pub tx_target: i32, // 0 = Module, 1 = Network
+    pub receive_only: bool, // Transmit controls disabled
 
     // Receive
     pub rx_events: Vec<ReceiveEvent>,
     pub rx_stream_active: bool,
     pub max_rx_events: usize,
     pub selected_index: Option<usize>,
+    pub rx_coalesce_ms: i32, // 0 = update per frame
 
     // RSSI visualization
     pub rssi_history: Vec<(Instant, i32)>, // (timestamp, rssi)
     pub rssi_window_secs: f32,
+
+    // Distance estimate (log-distance path loss)
+    pub distance_tx_power: i32,         // assumed peer TX power in dBm
+    pub distance_reference_rssi: i32,   // RSSI at 1 m from a 0 dBm transmitter
+    pub distance_path_loss_exponent: f32,
+
+    // Colors, saved to the user's configuration directory
+    pub theme: Theme,
+
+    // Reticulum destinations heard announcing
+    pub reticulum_destinations: DestinationTable,
+    pub reticulum_max_age_mins: i32,
+    pub reticulum_export_path: String,
+    pub reticulum_autosave: bool, // Export on every change
     
     // Waterfall data: (timestamp, rssi, payload_size)
     pub waterfall_data: Vec<(Instant, i32, usize)>,
@@ -86,8 +125,11 @@ impl AppState {
             server_addr: "192.168.10.1".to_string(),
             connected: false,
             status_message: "Not connected".to_string(),
+            capabilities: None,
 
             selected_module: 0,
+            frequency_plans: Vec::new(),
+            frequency_plan: 0,
             freq_mhz: 915.0,
             channel: 0,
             channel_spacing_khz: 200,
@@ -104,6 +146,8 @@ impl AppState {
             qos_adaptive_tx_power: true,
             qos_adaptive_backoff: true,
             qos_cca_threshold: -75,
+            qos_adaptive_modulation_type: false,
+            qos_qpsk_crossover: 3, // Poor
 
             bandwidth_filter: 1, // Default to Wide
 
@@ -113,14 +157,27 @@ impl AppState {
             continuous_tx: false,
             tx_pause_ms: 1000,
             tx_target: 0,
+            receive_only: false,
 
             rx_events: Vec::new(),
             rx_stream_active: false,
             max_rx_events: 100,
             selected_index: None,
+            rx_coalesce_ms: RX_COALESCE_WINDOW.as_millis() as i32,
 
             rssi_history: Vec::new(),
             rssi_window_secs: 30.0,
+
+            distance_tx_power: 10,
+            distance_reference_rssi: -40,
+            distance_path_loss_exponent: 2.7,
+
+            theme: Theme::default(),
+
+            reticulum_destinations: DestinationTable::new(),
+            reticulum_max_age_mins: (DEFAULT_DESTINATION_MAX_AGE.as_secs() / 60) as i32,
+            reticulum_export_path: String::new(),
+            reticulum_autosave: false,
             
             waterfall_data: Vec::new(),
             waterfall_max_entries: 500,
@@ -144,6 +201,45 @@ impl AppState {
             iperf_output: String::new(),
         }
     }
+
+    /// Whether the connected device offers `capability`, unknown ones are assumed
+    pub fn supports(&self, capability: u32) -> bool {
+        self.capabilities.map_or(true, |capabilities| capabilities & capability != 0)
+    }
+
+    /// Writes the Reticulum destinations as CSV to the export path
+    pub fn export_reticulum_destinations(&self) -> Result<(), String> {
+        if self.reticulum_export_path.is_empty() {
+            return Err("No export file selected".to_string());
+        }
+
+        std::fs::write(&self.reticulum_export_path, self.reticulum_destinations.to_csv())
+            .map_err(|e| format!("Failed to export destinations: {}", e))
+    }
+
+    /// PHY configuration for the current modulation panel selection
+    pub fn phy_config(&self) -> PhyConfig {
+        if self.modulation_type == 0 {
+            PhyConfig::Ofdm(RadioPhyConfigOfdm {
+                mcs: self.ofdm_mcs as u32,
+                opt: self.ofdm_opt as u32,
+            })
+        } else {
+            // Convert chip_freq index to actual frequency value
+            let chip_freq_value = match self.qpsk_chip_freq {
+                0 => 100,
+                1 => 200,
+                2 => 1000,
+                3 => 2000,
+                _ => 1000, // default
+            };
+
+            PhyConfig::Qpsk(RadioPhyConfigQpsk {
+                chip_freq: chip_freq_value,
+                rate_mode: self.qpsk_rate_mode as u32,
+            })
+        }
+    }
 }
 
 pub struct RadioGuiApp {
@@ -151,6 +247,7 @@ pub struct RadioGuiApp {
     state: Arc<Mutex<AppState>>,
     runtime: Arc<Runtime>,
     rx_receiver: Arc<Mutex<Option<mpsc::UnboundedReceiver<ReceiveEvent>>>>,
+    rx_batcher: ReceiveBatcher,
     pub last_frame: Instant,
     last_tx_time: Instant,
     // iPerf task handles
@@ -169,6 +266,7 @@ impl RadioGuiApp {
             state,
             runtime,
             rx_receiver: Arc::new(Mutex::new(None)),
+            rx_batcher: ReceiveBatcher::new(RX_COALESCE_WINDOW),
             last_frame: Instant::now(),
             last_tx_time: Instant::now(),
             iperf_server_task: None,
@@ -179,34 +277,48 @@ impl RadioGuiApp {
     pub fn render(&mut self, ui: &Ui) {
         let now = Instant::now();
         
-        // Process received events
+        // Collect received events, the state takes them once per window
         if let Some(ref mut rx) = *self.rx_receiver.lock() {
             while let Ok(event) = rx.try_recv() {
-                let mut state = self.state.lock();
-                
-                // (packet type statistics removed)
-                
-                // Add to events list
-                state.rx_events.push(event.clone());
-                if state.rx_events.len() > state.max_rx_events {
-                    state.rx_events.remove(0);
-                }
+                self.rx_batcher.push(event, now);
+            }
+        }
+        let batch = self.rx_batcher.take_ready(now);
+
+        let mut state = self.state.lock();
+        let mut destinations_changed = false;
+        if let Some(batch) = batch {
+            // (packet type statistics removed)
 
-                // Add to RSSI history with current timestamp
+            // Every frame stays in the events list, RSSI history and waterfall
+            for event in &batch {
                 state.rssi_history.push((now, event.rssi));
-                
-                // Add to waterfall data
                 state.waterfall_data.push((now, event.rssi, event.frame_data.len()));
-                if state.waterfall_data.len() > state.waterfall_max_entries {
-                    state.waterfall_data.remove(0);
-                }
+                destinations_changed |= state.reticulum_destinations.record(event);
             }
+            state.rx_events.extend(batch);
+
+            let excess = state.rx_events.len().saturating_sub(state.max_rx_events);
+            state.rx_events.drain(..excess);
+            let excess = state.waterfall_data.len().saturating_sub(state.waterfall_max_entries);
+            state.waterfall_data.drain(..excess);
         }
-        
+        self.rx_batcher
+            .set_window(std::time::Duration::from_millis(state.rx_coalesce_ms.max(0) as u64));
+
         // Clean up old RSSI history entries (older than window)
-        let mut state = self.state.lock();
         let cutoff_time = now - std::time::Duration::from_secs_f32(state.rssi_window_secs);
         state.rssi_history.retain(|(timestamp, _)| *timestamp >= cutoff_time);
+
+        let max_age = std::time::Duration::from_secs(state.reticulum_max_age_mins.max(1) as u64 * 60);
+        destinations_changed |= state.reticulum_destinations.expire(chrono::Local::now(), max_age);
+        if destinations_changed && state.reticulum_autosave {
+            // Stop on the first failure instead of retrying every frame
+            if let Err(e) = state.export_reticulum_destinations() {
+                state.status_message = e;
+                state.reticulum_autosave = false;
+            }
+        }
         
         // Handle continuous transmission
         if state.continuous_tx && state.connected {
@@ -235,7 +347,7 @@ impl RadioGuiApp {
 
                 // Enqueue non-blocking and await response in background to keep UI responsive
                 let data_len = data.len();
-                let (resp_tx, resp_rx) = oneshot::channel::<Result<u32, String>>();
+                let (resp_tx, resp_rx) = oneshot::channel::<Result<crate::grpc_client::TxResponse, String>>();
                 let req = crate::grpc_client::TxRequest { target: TxTarget::Radio(module), payload: data, resp: Some(resp_tx) };
                 if let Err(e) = self.client.lock().tx_enqueue(req) {
                     let mut s = self.state.lock();
@@ -245,10 +357,13 @@ impl RadioGuiApp {
                     let runtime = self.runtime.clone();
                     runtime.spawn(async move {
                         match resp_rx.await {
-                            Ok(Ok(lat)) => {
+                            Ok(Ok(tx)) => {
                                 let mut s = state_clone.lock();
-                                s.last_tx_latency = Some(lat);
-                                s.status_message = format!("Transmitted {} bytes (latency: {} ms)", data_len, lat);
+                                s.last_tx_latency = Some(tx.latency);
+                                s.status_message = format!(
+                                    "Transmitted {} bytes (latency: {} ms, attempts: {})",
+                                    data_len, tx.latency, tx.attempts
+                                );
                             }
                             Ok(Err(e)) => {
                                 let mut s = state_clone.lock();
@@ -331,6 +446,14 @@ impl RadioGuiApp {
                             self.draw_ota_panel(ui);
                             ui.unindent();
                         }
+                        ui.separator();
+
+                        // Display Section
+                        if ui.collapsing_header("Display", TreeNodeFlags::empty()) {
+                            ui.indent();
+                            self.draw_display_panel(ui);
+                            ui.unindent();
+                        }
                     });
 
                 ui.same_line();
@@ -375,13 +498,17 @@ impl RadioGuiApp {
                 // Connect
                 self.client.lock().set_server_addr(addr);
 
+                let result = self.client.lock().get_device_info();
                 let mut state = self.state.lock();
-                match self.client.lock().get_device_info() {
+                match result {
                     Ok(_) => {
                         state.connected = true;
                         state.status_message = "Connected successfully".to_string();
                         drop(state);
-                        
+
+                        self.fetch_frequency_plans();
+                        self.fetch_capabilities();
+
                         // Fetch firmware version
                         self.fetch_ota_version(ip_addr.clone());
                     }
@@ -456,26 +583,67 @@ impl RadioGuiApp {
 
         let mut state = self.state.lock();
 
+        if !state.frequency_plans.is_empty() {
+            let mut regions: Vec<&str> = state
+                .frequency_plans
+                .iter()
+                .map(|plan| plan.name.as_str())
+                .collect();
+            regions.push("Custom");
+
+            ui.text("Region:");
+            ui.set_next_item_width(-1.0);
+            let mut selected = state.frequency_plan;
+            if ui.combo_simple_string("##region", &mut selected, &regions) {
+                state.frequency_plan = selected;
+                if let Some(plan) = state.frequency_plans.get(selected).cloned() {
+                    state.freq_mhz = plan.freq.as_hz() as f32 / 1_000_000.0;
+                    state.channel_spacing_khz = plan.channel_spacing.as_khz() as i32;
+                    if state.channel >= plan.channel_count as i32 {
+                        state.channel = 0;
+                    }
+                }
+            }
+        }
+
+        let plan = state.frequency_plans.get(state.frequency_plan).cloned();
+
         ui.text("Frequency (MHz):");
         ui.set_next_item_width(-1.0);
-        Drag::new("##freq")
+        let freq_changed = Drag::new("##freq")
             .range(300.0, 2500.0)
             .speed(1.0)
             .build(ui, &mut state.freq_mhz);
 
+        // Channels are limited to the legal raster of the selected region
+        let max_channel = plan
+            .as_ref()
+            .map_or(MAX_CHANNEL, |plan| (plan.channel_count as i32 - 1).min(MAX_CHANNEL));
+
         ui.text("Channel:");
         ui.set_next_item_width(-1.0);
         Drag::new("##channel")
-            .range(0, 255)
+            .range(0, max_channel)
             .build(ui, &mut state.channel);
 
+        if let Some(plan) = &plan {
+            let freq_mhz = state.freq_mhz as f64
+                + state.channel as f64 * plan.channel_spacing.as_hz() as f64 / 1_000_000.0;
+            ui.text_disabled(format!("Center: {:.3} MHz", freq_mhz));
+        }
+
         ui.text("Channel Spacing (kHz):");
         ui.set_next_item_width(-1.0);
-        Drag::new("##spacing")
+        let spacing_changed = Drag::new("##spacing")
             .range(25, 2000)
             .speed(10.0)
             .build(ui, &mut state.channel_spacing_khz);
 
+        // Manual edits leave the region raster
+        if plan.is_some() && (freq_changed || spacing_changed) {
+            state.frequency_plan = state.frequency_plans.len();
+        }
+
         ui.text("TX Power (dBm):");
         ui.set_next_item_width(-1.0);
         
@@ -513,11 +681,18 @@ impl RadioGuiApp {
 
         let mut state = self.state.lock();
 
+        let ofdm = state.supports(CAPABILITY_OFDM);
+        let qpsk = state.supports(CAPABILITY_QPSK);
+
         ui.text("Type:");
         ui.same_line();
-        ui.radio_button("OFDM", &mut state.modulation_type, 0);
+        ui.enabled(ofdm, || {
+            ui.radio_button("OFDM", &mut state.modulation_type, 0);
+        });
         ui.same_line();
-        ui.radio_button("QPSK", &mut state.modulation_type, 1);
+        ui.enabled(qpsk, || {
+            ui.radio_button("QPSK", &mut state.modulation_type, 1);
+        });
 
         if state.modulation_type == 0 {
             // OFDM
@@ -545,6 +720,54 @@ impl RadioGuiApp {
             ui.set_next_item_width(-1.0);
             ui.slider("##ratemode", 0, 3, &mut state.qpsk_rate_mode);
         }
+
+        // Throughput / range advisory for the current selection
+        let modulation = state.phy_config().to_modulation(state.tx_power as u8);
+        if let Modulation::Ofdm(ofdm) = &modulation {
+            if !ofdm.is_supported() {
+                ui.text_colored(
+                    [1.0, 0.0, 0.0, 1.0],
+                    "MCS not supported with this option",
+                );
+            }
+        }
+        if let (Some(rate_bps), Some(sensitivity)) =
+            (modulation.data_rate_bps(), modulation.sensitivity_dbm())
+        {
+            // Most sensitive setting of the selected modulation type, the
+            // RF215 doesn't support MCS 0 and 1 with option 4
+            let reference = if state.modulation_type == 0 {
+                PhyConfig::Ofdm(RadioPhyConfigOfdm { mcs: 2, opt: 3 })
+            } else {
+                PhyConfig::Qpsk(RadioPhyConfigQpsk { chip_freq: 100, rate_mode: 0 })
+            }
+            .to_modulation(0)
+            .sensitivity_dbm()
+            .unwrap_or(sensitivity);
+
+            // Free-space range scales with 10^(link margin / 20)
+            let range = 10f32.powf((reference - sensitivity) / 20.0).clamp(0.0, 1.0);
+
+            let (advice, color) = if range >= 0.5 {
+                ("Long range, low throughput", [0.0, 1.0, 0.0, 1.0])
+            } else if range >= 0.2 {
+                ("Balanced range and throughput", [1.0, 0.85, 0.0, 1.0])
+            } else {
+                ("Short range, high throughput", [1.0, 0.55, 0.0, 1.0])
+            };
+
+            ui.spacing();
+            ui.text(format!(
+                "Est. data rate: {:.1} kbps, sensitivity: {:.0} dBm",
+                rate_bps as f32 / 1000.0,
+                sensitivity
+            ));
+            ProgressBar::new(range)
+                .overlay_text(format!("Relative range: {:.0}%", range * 100.0))
+                .size([-1.0, 0.0])
+                .build(ui);
+            ui.text_colored(color, advice);
+        }
     }
 
     fn draw_qos_panel(&mut self, ui: &Ui) {
@@ -553,6 +776,11 @@ impl RadioGuiApp {
 
         let mut state = self.state.lock();
 
+        if !state.supports(CAPABILITY_QOS) {
+            ui.text_disabled("Not supported by the device");
+            return;
+        }
+
         ui.checkbox("Enable QoS", &mut state.qos_enabled);
 
         if state.qos_enabled {
@@ -560,6 +788,15 @@ impl RadioGuiApp {
             ui.checkbox("Adaptive Modulation", &mut state.qos_adaptive_modulation);
             ui.checkbox("Adaptive TX Power", &mut state.qos_adaptive_tx_power);
             ui.checkbox("Adaptive Backoff", &mut state.qos_adaptive_backoff);
+            ui.checkbox("Auto OFDM/QPSK", &mut state.qos_adaptive_modulation_type);
+
+            if state.qos_adaptive_modulation_type {
+                let labels: Vec<&str> = QPSK_CROSSOVERS.iter().map(|(label, _)| *label).collect();
+
+                ui.text("QPSK at or below quality:");
+                ui.set_next_item_width(-1.0);
+                ui.combo_simple_string("##qpsk_crossover", &mut state.qos_qpsk_crossover, &labels);
+            }
 
             ui.text("CCA Threshold (dBm):");
             ui.set_next_item_width(-1.0);
@@ -583,26 +820,7 @@ impl RadioGuiApp {
                     RadioModule::ModuleB
                 };
 
-                let phy_config = if state.modulation_type == 0 {
-                    Some(PhyConfig::Ofdm(RadioPhyConfigOfdm {
-                        mcs: state.ofdm_mcs as u32,
-                        opt: state.ofdm_opt as u32,
-                    }))
-                } else {
-                    // Convert chip_freq index to actual frequency value
-                    let chip_freq_value = match state.qpsk_chip_freq {
-                        0 => 100,
-                        1 => 200,
-                        2 => 1000,
-                        3 => 2000,
-                        _ => 1000, // default
-                    };
-                    
-                    Some(PhyConfig::Qpsk(RadioPhyConfigQpsk {
-                        chip_freq: chip_freq_value,
-                        rate_mode: state.qpsk_rate_mode as u32,
-                    }))
-                };
+                let phy_config = Some(state.phy_config());
 
                 let qos_config = QoSConfig {
                     enabled: state.qos_enabled,
@@ -610,11 +828,18 @@ impl RadioGuiApp {
                     adaptive_tx_power: state.qos_adaptive_tx_power,
                     adaptive_backoff: state.qos_adaptive_backoff,
                     cca_threshold: state.qos_cca_threshold,
+                    adaptive_modulation_type: state.qos_adaptive_modulation_type,
+                    qpsk_crossover: QPSK_CROSSOVERS[state.qos_qpsk_crossover].1,
                 };
 
+                // Older devices don't know the request, unknown ones aren't assumed to
+                let apply_config = state
+                    .capabilities
+                    .is_some_and(|capabilities| capabilities & CAPABILITY_APPLY_CONFIG != 0);
+
                 let result = self.client.lock().configure_radio(
                     module,
-                    (state.freq_mhz * 1_000.0) as u32,
+                    (state.freq_mhz * 1_000.0).round() as u32,
                     state.channel as u32,
                     state.channel_spacing_khz as u32,
                     state.tx_power as u32,
@@ -622,6 +847,7 @@ impl RadioGuiApp {
                     state.qos_enabled,
                     qos_config,
                     state.bandwidth_filter,
+                    apply_config,
                 );
 
                 drop(state);
@@ -639,8 +865,25 @@ impl RadioGuiApp {
     }
 
     fn draw_transmit_panel(&mut self, ui: &Ui) {
+        let (max_module_payload, max_network_payload) = {
+            let client = self.client.lock();
+            (
+                client.max_payload_size(TxTarget::Radio(RadioModule::ModuleA)),
+                client.max_payload_size(TxTarget::Network),
+            )
+        };
+
         let mut state = self.state.lock();
-        let enabled = state.connected;
+
+        if ui.checkbox("Receive Only", &mut state.receive_only) && state.receive_only {
+            state.continuous_tx = false;
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Disables transmitting from this GUI.\nModules set to tx_enabled = false in the commd config refuse transmits anyway.",
+            );
+        }
+        let enabled = state.connected && !state.receive_only;
 
         ui.text("Target:");
         ui.same_line();
@@ -661,7 +904,33 @@ impl RadioGuiApp {
         } else {
             state.tx_data.len()  // Number of bytes from text
         };
-        ui.text(format!("Data length: {} bytes", data_length));
+
+        // Raw frames are capped by the frame buffer whatever the modulation,
+        // network frames also have to fit the LDPC parity
+        let (max_payload, path) = if state.tx_target == 0 {
+            (max_module_payload, "raw")
+        } else {
+            (max_network_payload, "LDPC coded")
+        };
+        let too_large = data_length > max_payload;
+        let limit_hint = match state.phy_config() {
+            PhyConfig::Ofdm(ofdm) => format!("OFDM MCS {} option {}", ofdm.mcs, ofdm.opt + 1),
+            PhyConfig::Qpsk(qpsk) => format!("QPSK {} kchip/s rate mode {}", qpsk.chip_freq, qpsk.rate_mode),
+        };
+        let limit_hint = format!(
+            "A single {} frame carries at most {} bytes with {}.\nShorten the data to transmit it.",
+            path, max_payload, limit_hint
+        );
+
+        let length_text = format!("Data length: {} / {} bytes", data_length, max_payload);
+        if too_large {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], &length_text);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(&limit_hint);
+            }
+        } else {
+            ui.text(&length_text);
+        }
 
         ui.text("Pause between transmits (ms):");
         ui.set_next_item_width(-1.0);
@@ -671,8 +940,13 @@ impl RadioGuiApp {
         drop(state);
 
         // Single transmit button
-        let _once_token = ui.begin_disabled(!enabled || continuous_enabled);
-        if ui.button("Transmit Once") {
+        let once_token = ui.begin_disabled(!enabled || continuous_enabled || too_large);
+        let once_clicked = ui.button("Transmit Once");
+        drop(once_token);
+        if too_large && ui.is_item_hovered_with_flags(ItemHoveredFlags::ALLOW_WHEN_DISABLED) {
+            ui.tooltip_text(&limit_hint);
+        }
+        if once_clicked {
             let state = self.state.lock();
             let data = if state.tx_hex_mode {
                 // Parse hex string
@@ -697,7 +971,7 @@ impl RadioGuiApp {
                 };
                 drop(state);
                 // Enqueue and await in background
-                let (resp_tx, resp_rx) = oneshot::channel::<Result<u32, String>>();
+                let (resp_tx, resp_rx) = oneshot::channel::<Result<crate::grpc_client::TxResponse, String>>();
                 let req = crate::grpc_client::TxRequest { target: TxTarget::Radio(module), payload: data.clone(), resp: Some(resp_tx) };
                 if let Err(e) = self.client.lock().tx_enqueue(req) {
                     let mut s = self.state.lock();
@@ -707,10 +981,13 @@ impl RadioGuiApp {
                     let runtime = self.runtime.clone();
                     runtime.spawn(async move {
                         match resp_rx.await {
-                            Ok(Ok(lat)) => {
+                            Ok(Ok(tx)) => {
                                 let mut s = state_clone.lock();
-                                s.last_tx_latency = Some(lat);
-                                s.status_message = format!("Transmitted {} bytes (latency: {} ms)", data_len, lat);
+                                s.last_tx_latency = Some(tx.latency);
+                                s.status_message = format!(
+                                    "Transmitted {} bytes (latency: {} ms, attempts: {})",
+                                    data_len, tx.latency, tx.attempts
+                                );
                             }
                             Ok(Err(e)) => {
                                 let mut s = state_clone.lock();
@@ -726,7 +1003,7 @@ impl RadioGuiApp {
             } else {
                 drop(state);
                 // Network target
-                let (resp_tx, resp_rx) = oneshot::channel::<Result<u32, String>>();
+                let (resp_tx, resp_rx) = oneshot::channel::<Result<crate::grpc_client::TxResponse, String>>();
                 let req = crate::grpc_client::TxRequest { target: TxTarget::Network, payload: data.clone(), resp: Some(resp_tx) };
                 if let Err(e) = self.client.lock().tx_enqueue(req) {
                     let mut s = self.state.lock();
@@ -736,10 +1013,13 @@ impl RadioGuiApp {
                     let runtime = self.runtime.clone();
                     runtime.spawn(async move {
                         match resp_rx.await {
-                            Ok(Ok(lat)) => {
+                            Ok(Ok(tx)) => {
                                 let mut s = state_clone.lock();
-                                s.last_tx_latency = Some(lat);
-                                s.status_message = format!("Transmitted {} bytes (latency: {} ms)", data_len, lat);
+                                s.last_tx_latency = Some(tx.latency);
+                                s.status_message = format!(
+                                    "Transmitted {} bytes (latency: {} ms, attempts: {})",
+                                    data_len, tx.latency, tx.attempts
+                                );
                             }
                             Ok(Err(e)) => {
                                 let mut s = state_clone.lock();
@@ -754,19 +1034,22 @@ impl RadioGuiApp {
                 }
             };
         }
-        drop(_once_token);
 
         ui.same_line();
 
         // Continuous transmit control
-        let _start_token = ui.begin_disabled(!enabled || continuous_enabled);
-        if ui.button("Start Continuous TX") {
+        let start_token = ui.begin_disabled(!enabled || continuous_enabled || too_large);
+        let start_clicked = ui.button("Start Continuous TX");
+        drop(start_token);
+        if too_large && ui.is_item_hovered_with_flags(ItemHoveredFlags::ALLOW_WHEN_DISABLED) {
+            ui.tooltip_text(&limit_hint);
+        }
+        if start_clicked {
             let mut state = self.state.lock();
             state.continuous_tx = true;
             self.last_tx_time = Instant::now();
             state.status_message = "Continuous transmission started".to_string();
         }
-        drop(_start_token);
         
         ui.same_line();
         
@@ -826,6 +1109,52 @@ impl RadioGuiApp {
         });
     }
     
+    fn draw_display_panel(&mut self, ui: &Ui) {
+        let mut state = self.state.lock();
+        let mut save = false;
+
+        let labels: Vec<&str> = PALETTES.iter().map(|(label, _)| *label).collect();
+        let mut selected = PALETTES
+            .iter()
+            .position(|(_, kind)| *kind == state.theme.palette)
+            .unwrap_or(0);
+        ui.text("Palette:");
+        ui.set_next_item_width(-1.0);
+        if ui.combo_simple_string("##palette", &mut selected, &labels) {
+            state.theme.palette = PALETTES[selected].1;
+            save = true;
+        }
+
+        // Each threshold is the weakest RSSI still shown in its level's color
+        let colors = state.theme.colors();
+        let (min, max) = RSSI_THRESHOLD_RANGE;
+        for level in 0..state.theme.rssi_thresholds.len() {
+            ui.text_colored(colors.rssi[level], format!("{} at or above (dBm):", RSSI_LEVELS[level]));
+            ui.set_next_item_width(-1.0);
+            // Ctrl+click allows typing any value, normalize keeps it in range and ordered
+            if ui.slider(format!("##rssi_threshold{}", level), min, max, &mut state.theme.rssi_thresholds[level]) {
+                state.theme.normalize();
+            }
+            // Save once the slider is released instead of every frame while dragging
+            save |= ui.is_item_deactivated_after_edit();
+        }
+        ui.text_colored(
+            colors.rssi[RSSI_LEVELS.len() - 1],
+            format!("{} below {} dBm", RSSI_LEVELS[RSSI_LEVELS.len() - 1], state.theme.rssi_thresholds[2]),
+        );
+
+        if ui.button("Reset to Defaults") {
+            state.theme = Theme::default();
+            save = true;
+        }
+
+        if save {
+            if let Err(e) = state.theme.save() {
+                state.status_message = format!("Failed to save display settings: {}", e);
+            }
+        }
+    }
+
     fn draw_ota_panel(&mut self, ui: &Ui) {
         let mut state = self.state.lock();
         let ip_addr = state.server_addr.clone();
@@ -1034,6 +1363,27 @@ impl RadioGuiApp {
         });
     }
     
+    /// Loads the region presets from the device, keeping the current settings selected.
+    /// Older firmware without frequency plans just hides the region dropdown.
+    pub fn fetch_capabilities(&self) {
+        let capabilities = self
+            .client
+            .lock()
+            .get_capabilities()
+            .ok()
+            .map(|response| response.capabilities);
+
+        self.state.lock().capabilities = capabilities;
+    }
+
+    pub fn fetch_frequency_plans(&self) {
+        let plans = self.client.lock().get_frequency_plans().unwrap_or_default();
+
+        let mut state = self.state.lock();
+        state.frequency_plan = plans.len();
+        state.frequency_plans = plans;
+    }
+
     fn fetch_ota_version(&self, ip: String) {
         let state = Arc::clone(&self.state);
         
@@ -1080,10 +1430,53 @@ impl RadioGuiApp {
             s.waterfall_data.clear();
         }
 
+        {
+            let mut s = self.state.lock();
+            ui.text("Update Window (ms, 0 = per frame):");
+            ui.set_next_item_width(-1.0);
+            // Ctrl+click allows typing any value, keep it in the slider range
+            if ui.slider("##rx_coalesce", 0, 500, &mut s.rx_coalesce_ms) {
+                s.rx_coalesce_ms = s.rx_coalesce_ms.clamp(0, 500);
+            }
+        }
+
+        if ui.collapsing_header("Distance Estimate", TreeNodeFlags::empty()) {
+            let mut s = self.state.lock();
+            ui.text_disabled("Rough log-distance path loss model, expect errors of 3x or more");
+            ui.text("Peer TX Power (dBm):");
+            ui.set_next_item_width(-1.0);
+            ui.slider("##dist_txpower", 0, 31, &mut s.distance_tx_power);
+            ui.text("RSSI at 1 m, 0 dBm TX:");
+            ui.set_next_item_width(-1.0);
+            ui.slider("##dist_ref", -80, 0, &mut s.distance_reference_rssi);
+            ui.text("Path Loss Exponent (2 = free space):");
+            ui.set_next_item_width(-1.0);
+            // Ctrl+click allows typing any value, keep it in the slider range
+            if ui.slider("##dist_exp", 1.5, 6.0, &mut s.distance_path_loss_exponent) {
+                s.distance_path_loss_exponent = s.distance_path_loss_exponent.clamp(1.5, 6.0);
+            }
+        }
+
+        let destination_count = { let s = self.state.lock(); s.reticulum_destinations.len() };
+        let header = format!("Reticulum Destinations ({})###reticulum", destination_count);
+        if ui.collapsing_header(&header, TreeNodeFlags::empty()) {
+            self.draw_reticulum_panel(ui);
+        }
+
         ui.separator();
 
         // Snapshot events to iterate without holding the lock
         let events_snapshot = { let s = self.state.lock(); s.rx_events.clone() };
+        let theme = { let s = self.state.lock(); s.theme };
+        let colors = theme.colors();
+        let estimate_distance = {
+            let s = self.state.lock();
+            let (tx_power, reference_rssi, exponent) =
+                (s.distance_tx_power, s.distance_reference_rssi, s.distance_path_loss_exponent);
+            move |rssi: i32| {
+                estimate_distance_m(rssi.clamp(-128, 127) as i8, tx_power as i8, exponent, reference_rssi as i8)
+            }
+        };
 
         // Determine available space and split into table + preview panels
         let avail = ui.content_region_avail();
@@ -1111,9 +1504,9 @@ impl RadioGuiApp {
                     let idx = events_snapshot.len().saturating_sub(1 + i);
 
                     // Choose color by packet type (used for Type text and selection bg)
-                    let (r, g, b, a) = match event.packet_type {
-                        crate::grpc_client::PacketType::Network => (1.0, 0.0, 1.0, 0.12), // magenta
-                        _ => (0.0, 1.0, 1.0, 0.08), // cyan for radio
+                    let ([r, g, b, _], a) = match event.packet_type {
+                        crate::grpc_client::PacketType::Network => (colors.network, 0.12),
+                        _ => (colors.radio, 0.08),
                     };
 
                     // If this row is selected, push header-style background so the whole row is highlighted
@@ -1164,11 +1557,16 @@ impl RadioGuiApp {
                     ui.next_column();
 
                     // RSSI
-                    let rssi_label = format!("{} dBm##row{}_rssi", event.rssi, idx);
+                    let rssi_label = match estimate_distance(event.rssi) {
+                        Some(distance) => format!("{} dBm (~{:.0} m)##row{}_rssi", event.rssi, distance, idx),
+                        None => format!("{} dBm##row{}_rssi", event.rssi, idx),
+                    };
+                    let _tc = ui.push_style_color(StyleColor::Text, theme.rssi_color(event.rssi));
                     if ui.selectable(&rssi_label) {
                         let mut s = self.state.lock();
                         s.selected_index = Some(idx);
                     }
+                    drop(_tc);
                     ui.next_column();
 
                     // Latency
@@ -1231,6 +1629,9 @@ impl RadioGuiApp {
                     ui.text(format!("Source: {}", match ev.module {0 => "Module A", 1 => "Module B", _ => "Network"}));
                     ui.text(format!("Size: {} B", ev.frame_data.len()));
                     ui.text(format!("RSSI: {} dBm", ev.rssi));
+                    if let Some(distance) = estimate_distance(ev.rssi) {
+                        ui.text(format!("Distance: ~{:.0} m (estimate)", distance));
+                    }
                     ui.text(format!("Latency: {} ms", ev.latency));
                     ui.separator();
                     // Hex dump
@@ -1256,16 +1657,114 @@ impl RadioGuiApp {
         // (preview rendered in dedicated child window above)
     }
 
+    fn draw_reticulum_panel(&mut self, ui: &Ui) {
+        let mut state = self.state.lock();
+        ui.text_disabled("Announces are recognized by their header, signatures aren't checked");
+
+        ui.text("Forget after (minutes):");
+        ui.set_next_item_width(-1.0);
+        // Ctrl+click allows typing any value, keep it in the slider range
+        if ui.slider("##reticulum_max_age", 1, 240, &mut state.reticulum_max_age_mins) {
+            state.reticulum_max_age_mins = state.reticulum_max_age_mins.clamp(1, 240);
+        }
+
+        ui.set_next_item_width(250.0);
+        ui.input_text("##reticulum_export", &mut state.reticulum_export_path)
+            .hint("Export file...")
+            .build();
+        ui.same_line();
+        if ui.button("Browse...##reticulum") {
+            drop(state); // Release lock before blocking dialog
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV files", &["csv"])
+                .set_file_name("reticulum-destinations.csv")
+                .save_file()
+            {
+                let mut state = self.state.lock();
+                state.reticulum_export_path = path.display().to_string();
+            }
+            state = self.state.lock(); // Re-acquire lock for later use
+        }
+        ui.same_line();
+        if ui.button("Export") {
+            state.status_message = match state.export_reticulum_destinations() {
+                Ok(()) => format!(
+                    "Exported {} destinations to {}",
+                    state.reticulum_destinations.len(),
+                    state.reticulum_export_path
+                ),
+                Err(e) => e,
+            };
+        }
+        ui.checkbox("Save automatically", &mut state.reticulum_autosave);
+        ui.same_line();
+        if ui.button("Clear##reticulum") {
+            state.reticulum_destinations.clear();
+        }
+
+        if state.reticulum_destinations.is_empty() {
+            ui.text("No announces heard");
+            return;
+        }
+
+        let destinations = state.reticulum_destinations.destinations();
+        let theme = state.theme;
+        drop(state);
+
+        ui.child_window("reticulum_table")
+            .size([0.0, 150.0])
+            .border(true)
+            .build(|| {
+                ui.columns(6, "reticulum_cols", false);
+                ui.text("Destination"); ui.next_column();
+                ui.text("Hops"); ui.next_column();
+                ui.text("RSSI"); ui.next_column();
+                ui.text("First Seen"); ui.next_column();
+                ui.text("Last Seen"); ui.next_column();
+                ui.text("Announces"); ui.next_column();
+                ui.separator();
+
+                for destination in &destinations {
+                    ui.text(hex::encode(destination.hash)); ui.next_column();
+                    ui.text(destination.hops.to_string()); ui.next_column();
+                    ui.text_colored(theme.rssi_color(destination.rssi), format!("{} dBm", destination.rssi));
+                    ui.next_column();
+                    ui.text(destination.first_seen.format("%H:%M:%S").to_string()); ui.next_column();
+                    ui.text(destination.last_seen.format("%H:%M:%S").to_string()); ui.next_column();
+                    ui.text(destination.announces.to_string()); ui.next_column();
+                }
+
+                ui.columns(1, "", false);
+            });
+    }
+
     fn draw_status_bar(&mut self, ui: &Ui) {
+        let reconnect = self.client.lock().reconnect_state();
         let state = self.state.lock();
+        let colors = state.theme.colors();
         let status_color = if state.connected {
-            [0.0, 1.0, 0.0, 1.0]
+            colors.ok
         } else {
-            [1.0, 0.0, 0.0, 1.0]
+            colors.error
         };
         
         ui.separator();
-        ui.text_colored(status_color, &state.status_message);
+        match reconnect {
+            Some(reconnect) if state.connected => {
+                let wait = reconnect
+                    .retry_at
+                    .saturating_duration_since(std::time::Instant::now());
+                ui.text_colored(
+                    colors.warning,
+                    format!(
+                        "Connection lost, reconnect attempt {} in {:.1} s",
+                        reconnect.attempt,
+                        wait.as_secs_f32()
+                    ),
+                );
+            }
+            _ => ui.text_colored(status_color, &state.status_message),
+        }
         
         ui.same_line();
         let window_width = ui.window_size()[0];
@@ -1278,18 +1777,8 @@ impl RadioGuiApp {
             right_pos -= rssi_width;
             ui.set_cursor_pos([right_pos, ui.cursor_pos()[1]]);
             
-            // Color based on signal strength
-            let rssi_color = if last_event.rssi >= -50 {
-                [0.0, 1.0, 0.0, 1.0]  // Green: Excellent (>= -50 dBm)
-            } else if last_event.rssi >= -70 {
-                [0.5, 1.0, 0.0, 1.0]  // Yellow-green: Good (-50 to -70 dBm)
-            } else if last_event.rssi >= -85 {
-                [1.0, 0.65, 0.0, 1.0] // Orange: Fair (-70 to -85 dBm)
-            } else {
-                [1.0, 0.0, 0.0, 1.0]  // Red: Poor (< -85 dBm)
-            };
-            
-            ui.text_colored(rssi_color, &rssi_text);
+            // Color based on signal strength and the configured thresholds
+            ui.text_colored(state.theme.rssi_color(last_event.rssi), &rssi_text);
             ui.same_line();
         }
         
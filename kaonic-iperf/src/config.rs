@@ -1,3 +1,5 @@
+use kaonic_net::packet::PacketIdNamespace;
+use kaonic_qos::ModulationScheme;
 use radio_common::{
     modulation::{
         OfdmBandwidthOption, OfdmMcs, OfdmModulation, QpskChipFrequency, QpskModulation,
@@ -17,6 +19,18 @@ pub struct IperfConfig {
     pub timeout: u64,
     pub ip: Option<String>,
     pub module: usize,
+    /// Server checks the padding pattern and reports bit errors
+    pub verify_payload: bool,
+    /// Node prefix in the top 8 bits of the sent message ids, fully random if unset
+    pub id_prefix: Option<u8>,
+}
+
+impl IperfConfig {
+    pub fn id_namespace(&self) -> PacketIdNamespace {
+        self.id_prefix
+            .map(|prefix| PacketIdNamespace::new(prefix.into(), 8))
+            .unwrap_or(PacketIdNamespace::NONE)
+    }
 }
 
 impl Default for IperfConfig {
@@ -27,6 +41,28 @@ impl Default for IperfConfig {
             timeout: 10,
             ip: None,
             module: 0,
+            verify_payload: true,
+            id_prefix: None,
+        }
+    }
+}
+
+/// Modulations stepped through by `--sweep`
+#[derive(Debug)]
+pub struct SweepConfig {
+    pub modulations: Vec<ModulationScheme>,
+    /// Test duration at each modulation in seconds
+    pub step_duration: u64,
+    /// Pause after switching modulation before the test starts
+    pub settle_ms: u64,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        SweepConfig {
+            modulations: Vec::new(),
+            step_duration: 5,
+            settle_ms: 200,
         }
     }
 }
@@ -35,6 +71,7 @@ impl Default for IperfConfig {
 pub struct Config {
     pub radios: Vec<RadioConfigWithModule>,
     pub iperf: IperfConfig,
+    pub sweep: SweepConfig,
     pub modulation: HashMap<String, toml::Value>,
 }
 
@@ -52,6 +89,15 @@ struct IperfPartial {
     timeout: Option<u64>,
     ip: Option<String>,
     module: Option<i64>,
+    verify_payload: Option<bool>,
+    id_prefix: Option<u8>,
+}
+
+#[derive(Deserialize)]
+struct SweepPartial {
+    modulations: Option<Vec<String>>,
+    step_duration: Option<u64>,
+    settle_ms: Option<u64>,
 }
 
 /// Loads configuration from the given TOML file path and maps radio-* sections to protobufs.
@@ -94,10 +140,13 @@ pub fn load_config(path: &str) -> Result<Config, Box<dyn Error>> {
 
                 let config = builder.build();
 
-                // Parse modulation from preset if specified
+                // Parse modulation from a preset or a scheme string if specified
                 let mod_config =
                     if let Some(mod_name) = rtab.get("modulation").and_then(|x| x.as_str()) {
-                        parse_modulation(&modulation, mod_name)
+                        Some(
+                            resolve_modulation(&modulation, mod_name)
+                                .map_err(|e| format!("{}: {}", key, e))?,
+                        )
                     } else {
                         None
                     };
@@ -130,27 +179,77 @@ pub fn load_config(path: &str) -> Result<Config, Box<dyn Error>> {
             if let Some(m) = partial.module {
                 d.module = m as usize;
             }
+            if let Some(x) = partial.verify_payload {
+                d.verify_payload = x;
+            }
+            if let Some(x) = partial.id_prefix {
+                d.id_prefix = Some(x);
+            }
         }
         d
     } else {
         IperfConfig::default()
     };
 
-    // Modulation presets
-    let mut modulation: HashMap<String, toml::Value> = HashMap::new();
-    if let Some(mv) = table.get("modulation").and_then(|v| v.as_table()) {
-        for (k, v) in mv.iter() {
-            modulation.insert(k.clone(), v.clone());
+    // Parse sweep section
+    let mut sweep = SweepConfig::default();
+    if let Some(v) = table.get("sweep") {
+        let partial: SweepPartial = v.clone().try_into().map_err(|e| format!("sweep: {}", e))?;
+        if let Some(names) = partial.modulations {
+            for name in names {
+                let scheme = resolve_modulation(&modulation, &name)
+                    .ok()
+                    .and_then(to_scheme)
+                    .ok_or_else(|| format!("sweep: invalid modulation '{}'", name))?;
+                sweep.modulations.push(scheme);
+            }
+        }
+        if let Some(x) = partial.step_duration {
+            sweep.step_duration = x;
+        }
+        if let Some(x) = partial.settle_ms {
+            sweep.settle_ms = x;
         }
     }
 
     Ok(Config {
         radios,
         iperf,
+        sweep,
         modulation,
     })
 }
 
+/// Scheme of an OFDM or QPSK modulation, other modulations have none
+pub fn to_scheme(modulation: Modulation) -> Option<ModulationScheme> {
+    match modulation {
+        Modulation::Ofdm(ofdm) => Some(ModulationScheme::Ofdm(ofdm)),
+        Modulation::Qpsk(qpsk) => Some(ModulationScheme::Qpsk(qpsk)),
+        _ => None,
+    }
+}
+
+/// Resolves a radio's `modulation` key to a preset name or a scheme like `ofdm:mcs3:opt2`.
+fn resolve_modulation(
+    presets: &HashMap<String, toml::Value>,
+    name: &str,
+) -> Result<Modulation, Box<dyn Error>> {
+    let scheme = match presets.get(name) {
+        Some(toml::Value::String(scheme)) => scheme.as_str(),
+        Some(_) => {
+            return parse_modulation(presets, name)
+                .ok_or_else(|| format!("invalid modulation preset '{}'", name).into())
+        }
+        None => name,
+    };
+
+    let scheme: ModulationScheme = scheme
+        .parse()
+        .map_err(|e| format!("invalid modulation '{}': {}", scheme, e))?;
+
+    Ok(scheme.to_modulation())
+}
+
 fn parse_modulation(presets: &HashMap<String, toml::Value>, name: &str) -> Option<Modulation> {
     let preset = presets.get(name)?;
     let mod_type = preset.get("type")?.as_str()?;
@@ -188,6 +287,7 @@ fn parse_modulation(presets: &HashMap<String, toml::Value>, name: &str) -> Optio
                 opt,
                 pdt: 0x03,
                 tx_power,
+                lfo: false,
             }))
         }
         "qpsk" => {
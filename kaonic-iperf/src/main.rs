@@ -2,16 +2,26 @@ use clap::Parser;
 use crc32fast::Hasher;
 use log::{error, warn};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use tokio::time::timeout;
 use tokio_util::sync::CancellationToken;
 
-use kaonic_ctrl::{client::Client, protocol::MessageCoder, radio::RadioClient};
+use kaonic_ctrl::{
+    client::Client,
+    error::ControllerError,
+    protocol::{MessageCoder, ReceiveModule},
+    radio::RadioClient,
+};
 use kaonic_frame::frame::Frame;
+use kaonic_qos::ModulationScheme;
 
 mod config;
+mod sweep;
 
 const DEFAULT_COMMD_ADDR: &str = "192.168.10.1:9090";
 const MIN_PACKET_SIZE: usize = 24; // MAGIC(4) + SEQ(4) + TIMESTAMP(8) + padding(4) + CRC(4)
+const HEADER_SIZE: usize = 16;
+const CRC_SIZE: usize = 4;
 const MAX_PACKET_SIZE: usize = 2048;
 const RESPONSE_TIMEOUT_MS: u64 = 500;
 
@@ -34,6 +44,18 @@ struct Args {
     /// Run as client (initiator)
     #[arg(long, conflicts_with = "server")]
     client: bool,
+
+    /// Run as client stepping through the modulations of the [sweep] section
+    #[arg(long, conflicts_with_all = ["server", "client"])]
+    sweep: bool,
+
+    /// Write the client or sweep results as JSON to this file
+    #[arg(long, conflicts_with = "server")]
+    json: Option<String>,
+
+    /// Modulation for the test module (overrides config file), e.g. ofdm:mcs3:opt2 or qpsk:2000:mode3
+    #[arg(long, short = 'm')]
+    modulation: Option<ModulationScheme>,
 }
 
 // Packet structure:
@@ -54,6 +76,11 @@ fn now_ms() -> u64 {
         .as_millis() as u64
 }
 
+/// Padding byte the client writes at offset `pos` of a packet
+fn pattern_byte(pos: usize) -> u8 {
+    (pos & 0xFF) as u8
+}
+
 fn fill_packet(frame: &mut Frame<2048>, seq: u32, size: usize) {
     let size = size.clamp(MIN_PACKET_SIZE, MAX_PACKET_SIZE);
     frame.clear();
@@ -70,8 +97,8 @@ fn fill_packet(frame: &mut Frame<2048>, seq: u32, size: usize) {
     pos += 8;
 
     // Padding (fill to size - 4 bytes for CRC)
-    while pos < size - 4 {
-        buffer[pos] = (pos & 0xFF) as u8;
+    while pos < size - CRC_SIZE {
+        buffer[pos] = pattern_byte(pos);
         pos += 1;
     }
 
@@ -83,30 +110,83 @@ fn fill_packet(frame: &mut Frame<2048>, seq: u32, size: usize) {
 #[derive(Debug)]
 enum ParseError {
     TooShort,
+    TooLong,
     BadMagic,
     CrcMismatch { expected: u32, actual: u32 },
 }
 
+/// Bit errors found in packet padding
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PatternErrors {
+    bit_errors: u64,
+    bits: u64,
+}
+
+impl PatternErrors {
+    fn add(&mut self, other: PatternErrors) {
+        self.bit_errors += other.bit_errors;
+        self.bits += other.bits;
+    }
+
+    /// Bit error rate, `None` before any padding was checked
+    fn ber(&self) -> Option<f64> {
+        (self.bits > 0).then(|| self.bit_errors as f64 / self.bits as f64)
+    }
+}
+
+/// Compares the padding of `data` against the pattern [`fill_packet`] writes
+///
+/// The header and CRC aren't predictable and aren't checked. Returns `None`
+/// for frames which aren't iperf packets, a corrupted magic included.
+fn check_pattern(data: &[u8]) -> Option<PatternErrors> {
+    if data.len() < MIN_PACKET_SIZE
+        || data.len() > MAX_PACKET_SIZE
+        || read_bytes::<4>(data, 0) != Some(MAGIC)
+    {
+        return None;
+    }
+
+    let padding = &data[HEADER_SIZE..data.len() - CRC_SIZE];
+    let bit_errors = padding
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| (byte ^ pattern_byte(HEADER_SIZE + i)).count_ones() as u64)
+        .sum();
+
+    Some(PatternErrors {
+        bit_errors,
+        bits: padding.len() as u64 * 8,
+    })
+}
+
+/// Reads `N` bytes at `offset`, `None` if they run past the end of `data`
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
 /// Returns (seq, timestamp) if packet is valid
+///
+/// Frames come straight off the air, so every field is read through
+/// bounds-checked accessors and malformed input is rejected instead of
+/// panicking.
 fn parse_packet(data: &[u8]) -> Result<(u32, u64), ParseError> {
     if data.len() < MIN_PACKET_SIZE {
         return Err(ParseError::TooShort);
     }
 
+    if data.len() > MAX_PACKET_SIZE {
+        return Err(ParseError::TooLong);
+    }
+
     // Check magic
-    if data[0..4] != MAGIC {
+    if read_bytes::<4>(data, 0) != Some(MAGIC) {
         return Err(ParseError::BadMagic);
     }
 
     // Verify CRC (last 4 bytes)
-    let payload_end = data.len() - 4;
-    let expected_crc = u32::from_le_bytes([
-        data[payload_end],
-        data[payload_end + 1],
-        data[payload_end + 2],
-        data[payload_end + 3],
-    ]);
-    let actual_crc = compute_crc(&data[..payload_end]);
+    let (payload, crc) = data.split_at(data.len() - 4);
+    let expected_crc = u32::from_le_bytes(read_bytes(crc, 0).ok_or(ParseError::TooShort)?);
+    let actual_crc = compute_crc(payload);
 
     if expected_crc != actual_crc {
         return Err(ParseError::CrcMismatch {
@@ -116,66 +196,33 @@ fn parse_packet(data: &[u8]) -> Result<(u32, u64), ParseError> {
     }
 
     // Parse header
-    let seq = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
-    let timestamp = u64::from_le_bytes([
-        data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15],
-    ]);
+    let seq = u32::from_le_bytes(read_bytes(payload, 4).ok_or(ParseError::TooShort)?);
+    let timestamp = u64::from_le_bytes(read_bytes(payload, 8).ok_or(ParseError::TooShort)?);
 
     Ok((seq, timestamp))
 }
 
 async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn std::error::Error>> {
     println!("=== Kaonic RTT Server ===");
-    println!("Connecting to {}...", address);
-
-    let server_addr: std::net::SocketAddr = address.parse()?;
-    let listen_addr: std::net::SocketAddr = "0.0.0.0:0".parse()?;
-
-    let cancel = CancellationToken::new();
+    let mut radio_client = connect(address, cfg).await?;
+    configure_radio(&mut radio_client, cfg).await?;
+    println!();
 
-    let client = Client::connect(
-        listen_addr,
-        server_addr,
-        MessageCoder::<1400, 5>::new(),
-        cancel.clone(),
-    )
-    .await
-    .map_err(|e| format!("Client connect error: {:?}", e))?;
-
-    let mut radio_client = RadioClient::new(client, cancel.clone())
+    // Restored once the hold time of a sweep step has passed
+    let base_modulation = radio_client
+        .get_modulation(cfg.iperf.module)
         .await
-        .map_err(|e| format!("RadioClient init error: {:?}", e))?;
-    println!("Connected.");
-
-    // Apply radio configuration for the target module only
-    if let Some(radio_cfg) = cfg.radios.iter().find(|r| r.module == cfg.iperf.module) {
-        println!("Configuring radio module {}...", cfg.iperf.module);
-        radio_client
-            .set_radio_config(cfg.iperf.module, radio_cfg.config.clone())
-            .await
-            .map_err(|e| format!("Config error: {:?}", e))?;
-
-        if let Some(modulation) = radio_cfg.modulation {
-            radio_client
-                .set_modulation(cfg.iperf.module, modulation)
-                .await
-                .map_err(|e| format!("Modulation error: {:?}", e))?;
-            println!("Modulation configured: {:?}", modulation);
-        }
-
-        println!("Radio configuration applied.\n");
-    } else {
-        println!(
-            "Warning: no radio config found for module {}\n",
-            cfg.iperf.module
-        );
-    }
+        .map_err(|e| format!("Modulation error: {:?}", e))?;
+    let mut revert_at: Option<tokio::time::Instant> = None;
 
     let mut module_rx = radio_client.module_receive();
     let mut count: u64 = 0;
+    // Sum of the RSSI of the echoed packets
+    let mut rssi_sum: i64 = 0;
     let mut ignored: u64 = 0;
     let mut crc_errors: u64 = 0;
     let mut bytes_received: u64 = 0;
+    let mut payload_errors = PatternErrors::default();
     let mut start_time: Option<Instant> = None;
 
     let shutdown = tokio::signal::ctrl_c();
@@ -187,6 +234,13 @@ async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
                 println!("\nShutting down...");
                 break;
             }
+            _ = tokio::time::sleep_until(revert_at.unwrap_or_else(tokio::time::Instant::now)), if revert_at.is_some() => {
+                revert_at = None;
+                match radio_client.set_modulation(cfg.iperf.module, base_modulation).await {
+                    Ok(()) => println!("[SWEEP] Back to {:?}", base_modulation),
+                    Err(e) => warn!("Modulation error: {:?}", e),
+                }
+            }
             result = module_rx.recv() => {
                 match result {
                     Ok(rx_module) => {
@@ -196,11 +250,31 @@ async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
 
                         let rx_data = rx_module.frame.as_slice();
 
+                        if let Some(control) = sweep::SweepControl::parse(rx_data) {
+                            match sweep::follow(&mut radio_client, cfg.iperf.module, rx_data, &control).await {
+                                Ok(()) => revert_at = Some(tokio::time::Instant::now() + control.hold()),
+                                Err(e) => warn!("Sweep step {} failed: {:?}", control.step, e),
+                            }
+                            continue;
+                        }
+
+                        let pattern = if cfg.iperf.verify_payload {
+                            check_pattern(rx_data)
+                        } else {
+                            None
+                        };
+                        if let Some(pattern) = pattern {
+                            payload_errors.add(pattern);
+                        }
+
                         match parse_packet(rx_data) {
                             Ok((seq, _ts)) => {
                                 // Track receive stats
                                 let packet_size = rx_data.len() as u64;
                                 println!("[RX] seq={} size={} bytes", seq, packet_size);
+                                if let Some(pattern) = pattern.filter(|p| p.bit_errors > 0) {
+                                    warn!("seq={} {} bit errors in payload", seq, pattern.bit_errors);
+                                }
                                 if start_time.is_none() {
                                     start_time = Some(Instant::now());
                                 }
@@ -219,21 +293,24 @@ async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
                                     .unwrap_or(0.0);
 
                                 // Echo back the same packet
-                                let mut echo_frame = Frame::<2048>::new();
-                                echo_frame.copy_from_slice(rx_data);
+                                let Ok(echo_frame) = Frame::<2048>::new_from_slice(rx_data) else {
+                                    warn!("seq={} too long to echo", seq);
+                                    continue;
+                                };
 
                                 match radio_client.transmit(cfg.iperf.module, &echo_frame).await {
-                                    Ok(_) => {
+                                    Ok(report) => {
                                         count += 1;
+                                        rssi_sum += rx_module.rssi as i64;
                                         println!(
-                                            "[{}] Echo seq={} size={}  rx={:.2} kb/s",
-                                            count, seq, rx_data.len(), speed_kbps
+                                            "[{}] Echo seq={} size={}  rx={:.2} kb/s  attempts={}",
+                                            count, seq, rx_data.len(), speed_kbps, report.attempts
                                         );
                                     }
                                     Err(e) => warn!("Transmit error: {:?}", e),
                                 }
                             }
-                            Err(ParseError::TooShort) => {
+                            Err(ParseError::TooShort | ParseError::TooLong) => {
                                 ignored += 1;
                             }
                             Err(ParseError::BadMagic) => {
@@ -242,8 +319,11 @@ async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
                             Err(ParseError::CrcMismatch { expected, actual }) => {
                                 crc_errors += 1;
                                 warn!(
-                                    "CRC mismatch: expected={:#010x} actual={:#010x} size={}",
-                                    expected, actual, rx_data.len()
+                                    "CRC mismatch: expected={:#010x} actual={:#010x} size={} payload_bit_errors={}",
+                                    expected,
+                                    actual,
+                                    rx_data.len(),
+                                    pattern.map_or("-".to_string(), |p| p.bit_errors.to_string())
                                 );
                             }
                         }
@@ -260,12 +340,24 @@ async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
     radio_client.cancel();
 
     println!("\nTotal packets echoed: {}", count);
+    if let Some(scheme) = config::to_scheme(base_modulation) {
+        println!("Modulation: {}", scheme);
+    }
+    if count > 0 {
+        println!("Avg RSSI: {:.1} dBm", rssi_sum as f64 / count as f64);
+    }
     if ignored > 0 {
         println!("Ignored (non-iperf): {}", ignored);
     }
     if crc_errors > 0 {
         println!("CRC errors: {}", crc_errors);
     }
+    if let Some(ber) = payload_errors.ber() {
+        println!(
+            "Payload BER: {:.3e} ({} bit errors in {} bits)",
+            ber, payload_errors.bit_errors, payload_errors.bits
+        );
+    }
     if let Some(start) = start_time {
         let elapsed = start.elapsed().as_secs_f64();
         if elapsed > 0.0 {
@@ -277,13 +369,151 @@ async fn run_server(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
     Ok(())
 }
 
-async fn run_client(address: &str, cfg: &config::Config) -> Result<(), Box<dyn std::error::Error>> {
-    let packet_size = cfg
-        .iperf
-        .payload_size
-        .clamp(MIN_PACKET_SIZE, MAX_PACKET_SIZE);
+/// Counters of one throughput test
+#[derive(Debug, Clone, Copy)]
+struct TestStats {
+    elapsed: Duration,
+    packet_size: usize,
+    packets_sent: u64,
+    received: u64,
+    timeouts: u64,
+    crc_errors: u64,
+    tx_attempts: u64,
+    tx_failures: u64,
+    rtt_min: u64,
+    rtt_max: u64,
+    rtt_sum: u64,
+    /// Sum of the RSSI of the received responses
+    rssi_sum: i64,
+    rssi_min: i8,
+    rssi_max: i8,
+    bytes_transferred: u64,
+    /// Modulation of the test module, `None` if it has no scheme
+    modulation: Option<ModulationScheme>,
+}
 
-    println!("=== Kaonic RTT Client ===");
+impl TestStats {
+    fn new(packet_size: usize) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            packet_size,
+            packets_sent: 0,
+            received: 0,
+            timeouts: 0,
+            crc_errors: 0,
+            tx_attempts: 0,
+            tx_failures: 0,
+            rtt_min: u64::MAX,
+            rtt_max: 0,
+            rtt_sum: 0,
+            rssi_sum: 0,
+            rssi_min: i8::MAX,
+            rssi_max: i8::MIN,
+            bytes_transferred: 0,
+            modulation: None,
+        }
+    }
+
+    fn avg_rtt_ms(&self) -> Option<f64> {
+        (self.received > 0).then(|| self.rtt_sum as f64 / self.received as f64)
+    }
+
+    fn avg_rssi(&self) -> Option<f64> {
+        (self.received > 0).then(|| self.rssi_sum as f64 / self.received as f64)
+    }
+
+    fn speed_kbps(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed > 0.0 {
+            (self.bytes_transferred as f64 * 8.0) / elapsed / 1000.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Share of the sent packets without a response
+    fn loss_percent(&self) -> Option<f64> {
+        (self.packets_sent > 0)
+            .then(|| (self.packets_sent - self.received) as f64 / self.packets_sent as f64 * 100.0)
+    }
+
+    /// Summary printed after a test
+    fn summary(&self) -> String {
+        let mut lines = vec![
+            "=== Results ===".to_string(),
+            format!("Duration:     {:.2} s", self.elapsed.as_secs_f64()),
+            format!("Packet size:  {} bytes", self.packet_size),
+        ];
+
+        if let Some(scheme) = self.modulation {
+            lines.push(format!("Modulation:   {}", scheme));
+        }
+
+        lines.push(format!(
+            "Packets:      {} sent, {} received, {} timeouts, {} CRC errors",
+            self.packets_sent, self.received, self.timeouts, self.crc_errors
+        ));
+
+        if self.tx_attempts > 0 {
+            lines.push(format!(
+                "Transmit:     {} attempts, {} frames dropped by the radio",
+                self.tx_attempts, self.tx_failures
+            ));
+        }
+
+        if let Some(avg_rtt) = self.avg_rtt_ms() {
+            lines.push(format!(
+                "RTT:          min={} ms, avg={:.1} ms, max={} ms",
+                self.rtt_min, avg_rtt, self.rtt_max
+            ));
+        }
+
+        if let Some(avg_rssi) = self.avg_rssi() {
+            lines.push(format!(
+                "RSSI:         min={} dBm, avg={:.1} dBm, max={} dBm",
+                self.rssi_min, avg_rssi, self.rssi_max
+            ));
+        }
+
+        if !self.elapsed.is_zero() {
+            lines.push(format!("Speed:        {:.2} kb/s", self.speed_kbps()));
+        }
+
+        if let Some(loss) = self.loss_percent() {
+            lines.push(format!("Packet loss:  {:.1}%", loss));
+        }
+
+        lines.join("\n")
+    }
+
+    fn print(&self) {
+        println!("\n{}", self.summary());
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        let received = self.received > 0;
+
+        serde_json::json!({
+            "modulation": self.modulation.map(|scheme| scheme.to_string()),
+            "duration_s": self.elapsed.as_secs_f64(),
+            "packet_size": self.packet_size,
+            "packets_sent": self.packets_sent,
+            "packets_received": self.received,
+            "crc_errors": self.crc_errors,
+            "per_percent": self.loss_percent(),
+            "throughput_kbps": self.speed_kbps(),
+            "avg_rssi_dbm": self.avg_rssi(),
+            "min_rssi_dbm": received.then_some(self.rssi_min),
+            "max_rssi_dbm": received.then_some(self.rssi_max),
+            "avg_rtt_ms": self.avg_rtt_ms(),
+        })
+    }
+}
+
+async fn connect(
+    address: &str,
+    cfg: &config::Config,
+) -> Result<RadioClient, Box<dyn std::error::Error>> {
     println!("Connecting to {}...", address);
 
     let server_addr: std::net::SocketAddr = address.parse()?;
@@ -295,75 +525,146 @@ async fn run_client(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
         listen_addr,
         server_addr,
         MessageCoder::<1400, 5>::new(),
+        cfg.iperf.id_namespace(),
         cancel.clone(),
     )
     .await
     .map_err(|e| format!("Client connect error: {:?}", e))?;
 
-    let mut radio_client = RadioClient::new(client, cancel.clone())
+    let radio_client = RadioClient::new(client, cancel.clone())
         .await
         .map_err(|e| format!("RadioClient init error: {:?}", e))?;
     println!("Connected.");
 
-    // Apply radio configuration for the target module only
-    if let Some(radio_cfg) = cfg.radios.iter().find(|r| r.module == cfg.iperf.module) {
-        println!("Configuring radio module {}...", cfg.iperf.module);
-        radio_client
-            .set_radio_config(cfg.iperf.module, radio_cfg.config.clone())
-            .await
-            .map_err(|e| format!("Config error: {:?}", e))?;
-
-        if let Some(modulation) = radio_cfg.modulation {
-            radio_client
-                .set_modulation(cfg.iperf.module, modulation)
-                .await
-                .map_err(|e| format!("Modulation error: {:?}", e))?;
-            println!("Modulation configured: {:?}", modulation);
-        }
+    Ok(radio_client)
+}
 
-        println!("Radio configuration applied.");
-    } else {
+/// Applies the radio configuration of the test module only
+async fn configure_radio(
+    radio_client: &mut RadioClient,
+    cfg: &config::Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(radio_cfg) = cfg.radios.iter().find(|r| r.module == cfg.iperf.module) else {
         println!(
             "Warning: no radio config found for module {}",
             cfg.iperf.module
         );
+        return Ok(());
+    };
+
+    println!("Configuring radio module {}...", cfg.iperf.module);
+    radio_client
+        .set_radio_config(cfg.iperf.module, radio_cfg.config.clone())
+        .await
+        .map_err(|e| format!("Config error: {:?}", e))?;
+
+    if let Some(modulation) = radio_cfg.modulation {
+        radio_client
+            .set_modulation(cfg.iperf.module, modulation)
+            .await
+            .map_err(|e| format!("Modulation error: {:?}", e))?;
+        println!("Modulation configured: {:?}", modulation);
     }
 
+    println!("Radio configuration applied.");
+
+    Ok(())
+}
+
+async fn run_client(
+    address: &str,
+    cfg: &config::Config,
+    json_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let packet_size = cfg
+        .iperf
+        .payload_size
+        .clamp(MIN_PACKET_SIZE, MAX_PACKET_SIZE);
+
+    println!("=== Kaonic RTT Client ===");
+    let mut radio_client = connect(address, cfg).await?;
+    configure_radio(&mut radio_client, cfg).await?;
+
     println!("Packet size: {} bytes", packet_size);
     println!("Duration: {} seconds\n", cfg.iperf.duration);
 
     // Start receive stream
     let mut module_rx = radio_client.module_receive();
 
+    let stats = run_test(
+        &mut radio_client,
+        &mut module_rx,
+        cfg.iperf.module,
+        packet_size,
+        Duration::from_secs(cfg.iperf.duration),
+    )
+    .await;
+
+    radio_client.cancel();
+
+    stats.print();
+
+    if let Some(path) = json_path {
+        std::fs::write(path, serde_json::to_string_pretty(&stats.to_json())?)?;
+        println!("\nResults written to {}", path);
+    }
+
+    Ok(())
+}
+
+/// Sends packets for `duration` and waits for each echo
+async fn run_test(
+    radio_client: &mut RadioClient,
+    module_rx: &mut broadcast::Receiver<Box<ReceiveModule>>,
+    module: usize,
+    packet_size: usize,
+    duration: Duration,
+) -> TestStats {
+    let mut stats = TestStats::new(packet_size);
+    stats.modulation = match radio_client.get_modulation(module).await {
+        Ok(modulation) => config::to_scheme(modulation),
+        Err(e) => {
+            warn!("Modulation error: {:?}", e);
+            None
+        }
+    };
+
     let start = Instant::now();
-    let test_duration = Duration::from_secs(cfg.iperf.duration);
     let mut seq: u32 = 0;
-    let mut rtt_min: u64 = u64::MAX;
-    let mut rtt_max: u64 = 0;
-    let mut rtt_sum: u64 = 0;
-    let mut rtt_count: u64 = 0;
-    let mut bytes_transferred: u64 = 0;
-    let mut timeouts: u64 = 0;
-    let mut crc_errors: u64 = 0;
 
     // Pre-allocate reusable packet frame
     let mut tx_frame = Frame::<2048>::new();
 
-    while start.elapsed() < test_duration {
+    while start.elapsed() < duration {
         // Send request packet
         fill_packet(&mut tx_frame, seq, packet_size);
         let send_time = Instant::now();
 
-        if let Err(e) = radio_client.transmit(cfg.iperf.module, &tx_frame).await {
-            error!("Transmit error: {:?}", e);
-            seq = seq.wrapping_add(1);
-            continue;
+        match radio_client.transmit(module, &tx_frame).await {
+            Ok(report) => {
+                stats.tx_attempts += report.attempts as u64;
+            }
+            Err(ControllerError::TransmitFailed(report)) => {
+                println!(
+                    "seq={:<6} TX {:?} after {} attempts",
+                    seq, report.result, report.attempts
+                );
+                stats.tx_attempts += report.attempts as u64;
+                stats.tx_failures += 1;
+                seq = seq.wrapping_add(1);
+                continue;
+            }
+            Err(e) => {
+                error!("Transmit error: {:?}", e);
+                seq = seq.wrapping_add(1);
+                continue;
+            }
         }
 
         // Wait for response
         match timeout(Duration::from_millis(RESPONSE_TIMEOUT_MS), module_rx.recv()).await {
             Ok(Ok(rx_module)) => {
-                if rx_module.module != cfg.iperf.module {
+                if rx_module.module != module {
                     continue;
                 }
 
@@ -373,17 +674,20 @@ async fn run_client(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
                 match parse_packet(rx_data) {
                     Ok((resp_seq, _)) => {
                         if resp_seq == seq {
-                            rtt_min = rtt_min.min(rtt);
-                            rtt_max = rtt_max.max(rtt);
-                            rtt_sum += rtt;
-                            rtt_count += 1;
-                            bytes_transferred += (packet_size * 2) as u64; // req + resp
+                            stats.rtt_min = stats.rtt_min.min(rtt);
+                            stats.rtt_max = stats.rtt_max.max(rtt);
+                            stats.rtt_sum += rtt;
+                            stats.rssi_sum += rx_module.rssi as i64;
+                            stats.rssi_min = stats.rssi_min.min(rx_module.rssi);
+                            stats.rssi_max = stats.rssi_max.max(rx_module.rssi);
+                            stats.received += 1;
+                            stats.bytes_transferred += (packet_size * 2) as u64; // req + resp
 
                             println!("seq={:<6} rtt={:<4} ms  size={}", seq, rtt, rx_data.len());
                         }
                     }
                     Err(ParseError::CrcMismatch { expected, actual }) => {
-                        crc_errors += 1;
+                        stats.crc_errors += 1;
                         println!(
                             "seq={:<6} CRC ERROR (expected={:#010x} actual={:#010x})",
                             seq, expected, actual
@@ -396,51 +700,21 @@ async fn run_client(address: &str, cfg: &config::Config) -> Result<(), Box<dyn s
             }
             Ok(Err(_)) => {
                 // Channel error
-                timeouts += 1;
+                stats.timeouts += 1;
             }
             Err(_) => {
                 println!("seq={:<6} TIMEOUT", seq);
-                timeouts += 1;
+                stats.timeouts += 1;
             }
         }
 
         seq = seq.wrapping_add(1);
     }
 
-    radio_client.cancel();
-
-    // Print results
-    let elapsed = start.elapsed().as_secs_f64();
-    let packets_sent = seq as u64;
+    stats.elapsed = start.elapsed();
+    stats.packets_sent = seq as u64;
 
-    println!("\n=== Results ===");
-    println!("Duration:     {:.2} s", elapsed);
-    println!("Packet size:  {} bytes", packet_size);
-    println!(
-        "Packets:      {} sent, {} received, {} timeouts, {} CRC errors",
-        packets_sent, rtt_count, timeouts, crc_errors
-    );
-
-    if rtt_count > 0 {
-        let avg_rtt = rtt_sum as f64 / rtt_count as f64;
-
-        println!(
-            "RTT:          min={} ms, avg={:.1} ms, max={} ms",
-            rtt_min, avg_rtt, rtt_max
-        );
-    }
-
-    if elapsed > 0.0 {
-        let speed_kbps = (bytes_transferred as f64 * 8.0) / elapsed / 1000.0;
-        println!("Speed:        {:.2} kb/s", speed_kbps);
-    }
-
-    if packets_sent > 0 {
-        let loss = ((packets_sent - rtt_count) as f64 / packets_sent as f64) * 100.0;
-        println!("Packet loss:  {:.1}%", loss);
-    }
-
-    Ok(())
+    stats
 }
 
 #[tokio::main]
@@ -450,7 +724,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     // Load config from specified path (required)
-    let cfg = match config::load_config(&args.config) {
+    let mut cfg = match config::load_config(&args.config) {
         Ok(c) => {
             println!(
                 "Loaded config from {} with {} radio(s)",
@@ -465,8 +739,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    if !args.server && !args.client {
-        eprintln!("Error: specify --server or --client");
+    if let Some(scheme) = args.modulation {
+        match cfg.radios.iter_mut().find(|r| r.module == cfg.iperf.module) {
+            Some(radio_cfg) => radio_cfg.modulation = Some(scheme.to_modulation()),
+            None => {
+                eprintln!(
+                    "Error: --modulation needs a radio section for module {}",
+                    cfg.iperf.module
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !args.server && !args.client && !args.sweep {
+        eprintln!("Error: specify --server, --client or --sweep");
         std::process::exit(1);
     }
 
@@ -486,9 +773,139 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     if args.server {
         run_server(&address, &cfg).await?;
+    } else if args.sweep {
+        sweep::run_sweep(&address, &cfg, args.json.as_deref()).await?;
     } else {
-        run_client(&address, &cfg).await?;
+        run_client(&address, &cfg, args.json.as_deref()).await?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(seq: u32, size: usize) -> Vec<u8> {
+        let mut frame = Frame::<2048>::new();
+        fill_packet(&mut frame, seq, size);
+        frame.as_slice().to_vec()
+    }
+
+    #[test]
+    fn test_parse_valid_packet() {
+        for size in [MIN_PACKET_SIZE, 25, 255, MAX_PACKET_SIZE] {
+            let data = packet(7, size);
+            assert_eq!(data.len(), size);
+            assert_eq!(parse_packet(&data).unwrap().0, 7);
+        }
+    }
+
+    #[test]
+    fn test_parse_truncated_packets() {
+        let data = packet(1, 64);
+
+        for len in 0..data.len() {
+            assert!(parse_packet(&data[..len]).is_err(), "length {}", len);
+        }
+
+        assert!(matches!(parse_packet(&[]), Err(ParseError::TooShort)));
+        assert!(matches!(
+            parse_packet(&data[..MIN_PACKET_SIZE - 1]),
+            Err(ParseError::TooShort)
+        ));
+    }
+
+    #[test]
+    fn test_parse_oversized_and_corrupt_packets() {
+        let mut data = packet(1, MAX_PACKET_SIZE);
+        data.push(0);
+        assert!(matches!(parse_packet(&data), Err(ParseError::TooLong)));
+
+        // Pseudo-random garbage of every length must be rejected, not panic
+        let mut state = 0x1234_5678u32;
+        for len in 0..=MAX_PACKET_SIZE + 8 {
+            let garbage: Vec<u8> = (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect();
+            assert!(parse_packet(&garbage).is_err(), "length {}", len);
+        }
+
+        let mut data = packet(1, 100);
+        data[0] ^= 0xFF;
+        assert!(matches!(parse_packet(&data), Err(ParseError::BadMagic)));
+
+        let mut data = packet(1, 100);
+        data[50] ^= 0x01;
+        assert!(matches!(
+            parse_packet(&data),
+            Err(ParseError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pattern_bit_errors() {
+        let data = packet(3, 100);
+        let clean = check_pattern(&data).unwrap();
+        assert_eq!(clean.bit_errors, 0);
+        assert_eq!(clean.bits, (100 - HEADER_SIZE - CRC_SIZE) as u64 * 8);
+
+        // Three bit errors in the padding, the header and CRC aren't counted
+        let mut corrupted = data.clone();
+        corrupted[20] ^= 0x81;
+        corrupted[60] ^= 0x10;
+        corrupted[8] ^= 0xFF;
+        corrupted[98] ^= 0xFF;
+        let errors = check_pattern(&corrupted).unwrap();
+        assert_eq!(errors.bit_errors, 3);
+        assert!(matches!(
+            parse_packet(&corrupted),
+            Err(ParseError::CrcMismatch { .. })
+        ));
+
+        let mut total = PatternErrors::default();
+        assert_eq!(total.ber(), None);
+        total.add(clean);
+        total.add(errors);
+        assert_eq!(total.ber(), Some(3.0 / (2.0 * clean.bits as f64)));
+
+        corrupted[0] ^= 0xFF;
+        assert_eq!(check_pattern(&corrupted), None);
+    }
+
+    #[test]
+    fn test_summary_physical_fields() {
+        let mut stats = TestStats::new(100);
+        stats.elapsed = Duration::from_secs(1);
+        stats.packets_sent = 4;
+
+        // Nothing received and no scheme to report
+        let summary = stats.summary();
+        assert!(!summary.contains("Modulation:"));
+        assert!(!summary.contains("RSSI:"));
+        let json = stats.to_json();
+        assert!(json["modulation"].is_null());
+        assert!(json["avg_rssi_dbm"].is_null());
+        assert!(json["min_rssi_dbm"].is_null());
+
+        stats.modulation = Some("ofdm:mcs3:opt2:tx10".parse().unwrap());
+        stats.received = 2;
+        stats.rssi_sum = -150;
+        stats.rssi_min = -80;
+        stats.rssi_max = -70;
+
+        let summary = stats.summary();
+        assert!(summary.contains("Modulation:   ofdm:mcs3:opt2:tx10"));
+        assert!(summary.contains("RSSI:         min=-80 dBm, avg=-75.0 dBm, max=-70 dBm"));
+        let json = stats.to_json();
+        assert_eq!(json["modulation"], "ofdm:mcs3:opt2:tx10");
+        assert_eq!(json["avg_rssi_dbm"], -75.0);
+        assert_eq!(json["min_rssi_dbm"], -80);
+        assert_eq!(json["max_rssi_dbm"], -70);
+    }
+}
@@ -0,0 +1,381 @@
+//! Modulation sweep
+//!
+//! The client steps through the modulations of the `[sweep]` section and runs
+//! a short RTT test at each. Before every step it sends a control packet on
+//! the base modulation, the server echoes it, switches to the modulation it
+//! carries and falls back to its base modulation once the hold time of the
+//! step is over. A lost control packet therefore never leaves both sides on
+//! different modulations for longer than one step.
+
+use std::time::{Duration, Instant};
+
+use kaonic_ctrl::{error::ControllerError, protocol::ReceiveModule, radio::RadioClient};
+use kaonic_frame::frame::Frame;
+use kaonic_qos::ModulationScheme;
+use log::warn;
+use tokio::{sync::broadcast, time::timeout};
+
+use crate::{
+    compute_crc, config, configure_radio, connect, read_bytes, run_test, TestStats,
+    MAX_PACKET_SIZE, MIN_PACKET_SIZE, RESPONSE_TIMEOUT_MS,
+};
+
+// Control packet structure:
+// MAGIC (4) + STEP (2) + HOLD_MS (4) + SCHEME_LEN (1) + SCHEME (N) + CRC32 (4)
+const SWEEP_MAGIC: [u8; 4] = [0x8B, 0x53, 0x57, 0x50];
+const CONTROL_HEADER_SIZE: usize = 11;
+
+/// Control packets sent before giving up on a step
+const CONTROL_ATTEMPTS: usize = 5;
+
+/// Asks the server to use `scheme` for the next `hold_ms` milliseconds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepControl {
+    pub step: u16,
+    pub hold_ms: u32,
+    pub scheme: ModulationScheme,
+}
+
+impl SweepControl {
+    pub fn encode(&self) -> Vec<u8> {
+        let scheme = self.scheme.to_string();
+
+        let mut packet = Vec::with_capacity(CONTROL_HEADER_SIZE + scheme.len() + 4);
+        packet.extend_from_slice(&SWEEP_MAGIC);
+        packet.extend_from_slice(&self.step.to_le_bytes());
+        packet.extend_from_slice(&self.hold_ms.to_le_bytes());
+        packet.push(scheme.len() as u8);
+        packet.extend_from_slice(scheme.as_bytes());
+
+        let crc = compute_crc(&packet);
+        packet.extend_from_slice(&crc.to_le_bytes());
+
+        packet
+    }
+
+    /// Returns `None` for anything but an intact control packet
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if read_bytes::<4>(data, 0)? != SWEEP_MAGIC || data.len() < CONTROL_HEADER_SIZE + 4 {
+            return None;
+        }
+
+        let (payload, crc) = data.split_at(data.len() - 4);
+        if u32::from_le_bytes(read_bytes(crc, 0)?) != compute_crc(payload) {
+            return None;
+        }
+
+        let step = u16::from_le_bytes(read_bytes(payload, 4)?);
+        let hold_ms = u32::from_le_bytes(read_bytes(payload, 6)?);
+        let scheme_len = payload[10] as usize;
+        let scheme = payload.get(CONTROL_HEADER_SIZE..CONTROL_HEADER_SIZE + scheme_len)?;
+        let scheme = std::str::from_utf8(scheme).ok()?.parse().ok()?;
+
+        Some(Self {
+            step,
+            hold_ms,
+            scheme,
+        })
+    }
+
+    pub fn hold(&self) -> Duration {
+        Duration::from_millis(self.hold_ms as u64)
+    }
+}
+
+/// Server side of a step: acknowledges `control` and switches modulation
+pub async fn follow(
+    radio_client: &mut RadioClient,
+    module: usize,
+    packet: &[u8],
+    control: &SweepControl,
+) -> Result<(), ControllerError> {
+    let echo_frame = Frame::<2048>::new_from_slice(packet)?;
+    radio_client.transmit(module, &echo_frame).await?;
+
+    radio_client
+        .set_modulation(module, control.scheme.to_modulation())
+        .await?;
+    println!(
+        "[SWEEP] Step {} on {} for {} ms",
+        control.step, control.scheme, control.hold_ms
+    );
+
+    Ok(())
+}
+
+/// Outcome of one step, `None` if the server never acknowledged it
+#[derive(Debug)]
+pub struct SweepResult {
+    pub scheme: ModulationScheme,
+    pub stats: Option<TestStats>,
+}
+
+pub async fn run_sweep(
+    address: &str,
+    cfg: &config::Config,
+    json: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cfg.sweep.modulations.is_empty() {
+        return Err("no modulations in the [sweep] section".into());
+    }
+
+    let module = cfg.iperf.module;
+    let packet_size = cfg
+        .iperf
+        .payload_size
+        .clamp(MIN_PACKET_SIZE, MAX_PACKET_SIZE);
+    let step_duration = Duration::from_secs(cfg.sweep.step_duration);
+    let settle = Duration::from_millis(cfg.sweep.settle_ms);
+
+    // The server holds a modulation until our test is over for sure, the
+    // last request of a test may still wait for its response and switching
+    // modulation takes a round trip to commd
+    let hold = settle + step_duration + Duration::from_millis(2 * RESPONSE_TIMEOUT_MS);
+
+    println!("=== Kaonic Modulation Sweep ===");
+    let mut radio_client = connect(address, cfg).await?;
+    configure_radio(&mut radio_client, cfg).await?;
+
+    let base_modulation = radio_client
+        .get_modulation(module)
+        .await
+        .map_err(|e| format!("Modulation error: {:?}", e))?;
+
+    println!("Packet size: {} bytes", packet_size);
+    println!(
+        "Steps: {} x {} seconds, settling {} ms\n",
+        cfg.sweep.modulations.len(),
+        cfg.sweep.step_duration,
+        cfg.sweep.settle_ms
+    );
+
+    let mut module_rx = radio_client.module_receive();
+    let mut results = Vec::with_capacity(cfg.sweep.modulations.len());
+
+    for (step, scheme) in cfg.sweep.modulations.iter().enumerate() {
+        println!(
+            "\n=== Step {}/{}: {} ===",
+            step + 1,
+            cfg.sweep.modulations.len(),
+            scheme
+        );
+
+        let control = SweepControl {
+            step: step as u16,
+            hold_ms: hold.as_millis() as u32,
+            scheme: *scheme,
+        };
+
+        let acked = send_control(&mut radio_client, &mut module_rx, module, &control).await;
+        let hold_end = tokio::time::Instant::now() + hold;
+
+        let stats = if acked {
+            radio_client
+                .set_modulation(module, scheme.to_modulation())
+                .await
+                .map_err(|e| format!("Modulation error: {:?}", e))?;
+            tokio::time::sleep(settle).await;
+
+            let stats = run_test(
+                &mut radio_client,
+                &mut module_rx,
+                module,
+                packet_size,
+                step_duration,
+            )
+            .await;
+
+            radio_client
+                .set_modulation(module, base_modulation)
+                .await
+                .map_err(|e| format!("Modulation error: {:?}", e))?;
+
+            Some(stats)
+        } else {
+            warn!("Step {}: server didn't acknowledge {}", step + 1, scheme);
+            None
+        };
+
+        results.push(SweepResult {
+            scheme: *scheme,
+            stats,
+        });
+
+        // The server may have switched even if its acknowledgement got lost
+        tokio::time::sleep_until(hold_end).await;
+    }
+
+    radio_client.cancel();
+
+    println!("\n=== Sweep Results ===");
+    print!("{}", format_table(&results));
+
+    if let Some(path) = json {
+        std::fs::write(path, serde_json::to_string_pretty(&to_json(&results))?)?;
+        println!("\nResults written to {}", path);
+    }
+
+    Ok(())
+}
+
+/// Sends `control` until the server echoes it
+async fn send_control(
+    radio_client: &mut RadioClient,
+    module_rx: &mut broadcast::Receiver<Box<ReceiveModule>>,
+    module: usize,
+    control: &SweepControl,
+) -> bool {
+    let Ok(frame) = Frame::<2048>::new_from_slice(&control.encode()) else {
+        return false;
+    };
+
+    for _ in 0..CONTROL_ATTEMPTS {
+        if let Err(e) = radio_client.transmit(module, &frame).await {
+            warn!("Transmit error: {:?}", e);
+            continue;
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(RESPONSE_TIMEOUT_MS);
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match timeout(remaining, module_rx.recv()).await {
+                Ok(Ok(rx_module)) => {
+                    if rx_module.module == module
+                        && SweepControl::parse(rx_module.frame.as_slice()).as_ref() == Some(control)
+                    {
+                        return true;
+                    }
+                }
+                Ok(Err(_)) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    false
+}
+
+fn format_optional(value: Option<f64>, precision: usize) -> String {
+    value.map_or("-".to_string(), |v| format!("{:.*}", precision, v))
+}
+
+fn format_table(results: &[SweepResult]) -> String {
+    let mut table = format!(
+        "{:<28} {:>6} {:>6} {:>7} {:>10} {:>9} {:>8}\n",
+        "Modulation", "Sent", "Recv", "PER %", "kb/s", "RSSI dBm", "RTT ms"
+    );
+
+    for result in results {
+        let line = match &result.stats {
+            Some(stats) => format!(
+                "{:<28} {:>6} {:>6} {:>7} {:>10.2} {:>9} {:>8}\n",
+                result.scheme.to_string(),
+                stats.packets_sent,
+                stats.received,
+                format_optional(stats.loss_percent(), 1),
+                stats.speed_kbps(),
+                format_optional(stats.avg_rssi(), 1),
+                format_optional(stats.avg_rtt_ms(), 1),
+            ),
+            None => format!(
+                "{:<28} not acknowledged by the server\n",
+                result.scheme.to_string()
+            ),
+        };
+        table.push_str(&line);
+    }
+
+    table
+}
+
+fn to_json(results: &[SweepResult]) -> serde_json::Value {
+    let steps = results
+        .iter()
+        .map(|result| match &result.stats {
+            Some(stats) => {
+                let mut step = stats.to_json();
+                step["modulation"] = result.scheme.to_string().into();
+                step["acknowledged"] = true.into();
+                step
+            }
+            None => serde_json::json!({
+                "modulation": result.scheme.to_string(),
+                "acknowledged": false,
+            }),
+        })
+        .collect();
+
+    serde_json::Value::Array(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control() -> SweepControl {
+        SweepControl {
+            step: 3,
+            hold_ms: 5700,
+            scheme: "qpsk:2000:mode3:tx12".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_control_round_trip() {
+        let packet = control().encode();
+        assert_eq!(SweepControl::parse(&packet), Some(control()));
+        assert_eq!(control().hold(), Duration::from_millis(5700));
+    }
+
+    #[test]
+    fn test_control_rejects_other_packets() {
+        let packet = control().encode();
+
+        let mut corrupt = packet.clone();
+        corrupt[12] ^= 0x01;
+        assert_eq!(SweepControl::parse(&corrupt), None);
+        assert_eq!(SweepControl::parse(&packet[..packet.len() - 1]), None);
+        assert_eq!(SweepControl::parse(&packet[..8]), None);
+
+        // Echoed RTT packets aren't control packets
+        let mut frame = Frame::<2048>::new();
+        crate::fill_packet(&mut frame, 1, 64);
+        assert_eq!(SweepControl::parse(frame.as_slice()), None);
+    }
+
+    #[test]
+    fn test_results_output() {
+        let mut stats = TestStats::new(100);
+        stats.elapsed = Duration::from_secs(2);
+        stats.packets_sent = 10;
+        stats.received = 8;
+        stats.rtt_sum = 400;
+        stats.rssi_sum = -560;
+        stats.bytes_transferred = 1600;
+
+        let results = [
+            SweepResult {
+                scheme: "ofdm:mcs3:opt1:tx10".parse().unwrap(),
+                stats: Some(stats),
+            },
+            SweepResult {
+                scheme: "qpsk:2000:mode3:tx10".parse().unwrap(),
+                stats: None,
+            },
+        ];
+
+        let table = format_table(&results);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("ofdm:mcs3:opt1:tx10"));
+        assert!(lines[1].contains(" 20.0 "));
+        assert!(lines[1].contains(" 6.40 "));
+        assert!(lines[1].contains(" -70.0 "));
+        assert!(lines[2].contains("not acknowledged"));
+
+        let json = to_json(&results);
+        assert_eq!(json[0]["packets_received"], 8);
+        assert_eq!(json[0]["per_percent"], 20.0);
+        assert_eq!(json[0]["avg_rtt_ms"], 50.0);
+        assert_eq!(json[1]["acknowledged"], false);
+    }
+}
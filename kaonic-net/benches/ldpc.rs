@@ -0,0 +1,145 @@
+//! Throughput of the LDPC packet coder on the receive hot path
+//!
+//! Run with `cargo bench -p kaonic-net --bench ldpc`. Every case codes a 2048
+//! byte payload (16 payload codewords), throughput is reported per payload
+//! byte. The `ldpc_iteration_limit` group compares the default bit-flip
+//! iteration limit with a tight one on clean, noisy and undecodable frames.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use kaonic_frame::frame::Frame;
+use kaonic_net::{
+    coder::{
+        LdpcPacketCoder, PacketCoder, HEADER_LDPC_CODE, LDPC_MAX_ITERATIONS, PAYLOAD_LDPC_CODE,
+    },
+    packet::Packet,
+};
+
+const PAYLOAD_SIZE: usize = 2048;
+/// Header codeword followed by the payload codewords
+const FRAME_SIZE: usize = HEADER_LDPC_CODE.n() / 8
+    + PAYLOAD_SIZE / (PAYLOAD_LDPC_CODE.k() / 8) * (PAYLOAD_LDPC_CODE.n() / 8);
+
+fn packet() -> Packet<FRAME_SIZE> {
+    let mut packet = Packet::new();
+    let payload: Vec<u8> = (0..PAYLOAD_SIZE).map(|i| (i * 7 + 3) as u8).collect();
+
+    packet
+        .frame_mut()
+        .push_data(&payload)
+        .expect("payload fits the packet");
+    packet.build();
+
+    packet
+}
+
+fn encoded() -> Frame<FRAME_SIZE> {
+    let mut frame = Frame::new();
+
+    LdpcPacketCoder::<FRAME_SIZE>::new()
+        .encode(&packet(), &mut frame)
+        .expect("encoded frame");
+
+    frame
+}
+
+/// Flips one bit every `stride` bytes of each payload codeword
+fn corrupt_payload(frame: &mut Frame<FRAME_SIZE>, stride: usize) {
+    let header_len = HEADER_LDPC_CODE.n() / 8;
+
+    for byte in frame.as_slice_mut()[header_len..]
+        .iter_mut()
+        .step_by(stride)
+    {
+        *byte ^= 0x01;
+    }
+}
+
+/// Every codeword needs a few corrections, all of them correctable
+fn noisy() -> Frame<FRAME_SIZE> {
+    let mut frame = encoded();
+    corrupt_payload(&mut frame, 64);
+    frame
+}
+
+/// Every codeword but the last one needs correcting and the last one burns
+/// through the iteration limit without converging
+fn undecodable() -> Frame<FRAME_SIZE> {
+    let mut frame = noisy();
+    let last_codeword = frame.len() - PAYLOAD_LDPC_CODE.n() / 8;
+    for byte in frame.as_slice_mut()[last_codeword..].iter_mut() {
+        *byte ^= 0x55;
+    }
+    frame
+}
+
+fn bench_ldpc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ldpc");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+
+    let mut coder = LdpcPacketCoder::<FRAME_SIZE>::new();
+    let packet = packet();
+    let mut frame = Frame::<FRAME_SIZE>::new();
+
+    group.bench_function("encode", |b| {
+        b.iter(|| coder.encode(black_box(&packet), &mut frame).unwrap())
+    });
+
+    let clean = encoded();
+    let mut output = Packet::<FRAME_SIZE>::new();
+
+    group.bench_function("decode_clean", |b| {
+        b.iter(|| coder.decode(black_box(&clean), &mut output).unwrap())
+    });
+
+    let noisy = noisy();
+    coder
+        .decode(&noisy, &mut output)
+        .expect("correctable frame");
+
+    group.bench_function("decode_noisy", |b| {
+        b.iter(|| coder.decode(black_box(&noisy), &mut output).unwrap())
+    });
+
+    let corrupted = undecodable();
+    assert!(coder.decode(&corrupted, &mut output).is_err());
+
+    group.bench_function("decode_corrupted", |b| {
+        b.iter(|| {
+            coder
+                .decode(black_box(&corrupted), &mut output)
+                .unwrap_err()
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_iteration_limit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ldpc_iteration_limit");
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+
+    let frames = [
+        ("clean", encoded()),
+        ("noisy", noisy()),
+        ("undecodable", undecodable()),
+    ];
+    let mut output = Packet::<FRAME_SIZE>::new();
+
+    for limit in [LDPC_MAX_ITERATIONS, 5] {
+        let mut coder = LdpcPacketCoder::<FRAME_SIZE>::new().with_max_iterations(limit);
+
+        for (name, frame) in frames.iter() {
+            group.bench_with_input(BenchmarkId::new(*name, limit), frame, |b, frame| {
+                b.iter(|| {
+                    let _ = coder.decode(black_box(frame), &mut output);
+                    coder.iterations()
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_ldpc, bench_iteration_limit);
+criterion_main!(benches);
@@ -4,14 +4,78 @@ use labrador_ldpc::LDPCCode;
 use crate::{
     error::NetworkError,
     packet::{Packet, HEADER_SIZE},
+    whitening::Whitening,
 };
 
 pub const HEADER_LDPC_CODE: LDPCCode = LDPCCode::TC256;
+/// Payload code of [`PayloadCode::Rate1_2`], the largest one sizes the buffers
 pub const PAYLOAD_LDPC_CODE: LDPCCode = LDPCCode::TM2048;
 
 pub const PAYLOAD_LDPC_OUTPUT_BUFFER_SIZE: usize = PAYLOAD_LDPC_CODE.output_len();
 pub const PAYLOAD_LDPC_WORKING_BUFFER_SIZE: usize = PAYLOAD_LDPC_CODE.decode_bf_working_len();
 
+/// Default bit-flip iteration limit per codeword
+pub const LDPC_MAX_ITERATIONS: usize = 20;
+
+/// Payload LDPC code, each codeword carries 128 bytes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayloadCode {
+    /// TM2048, the most robust
+    #[default]
+    Rate1_2 = 0,
+    /// TM1536
+    Rate2_3 = 1,
+    /// TM1280
+    Rate4_5 = 2,
+}
+
+impl PayloadCode {
+    pub const ALL: [Self; 3] = [Self::Rate1_2, Self::Rate2_3, Self::Rate4_5];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Rate1_2),
+            1 => Some(Self::Rate2_3),
+            2 => Some(Self::Rate4_5),
+            _ => None,
+        }
+    }
+
+    pub const fn ldpc(self) -> LDPCCode {
+        match self {
+            Self::Rate1_2 => LDPCCode::TM2048,
+            Self::Rate2_3 => LDPCCode::TM1536,
+            Self::Rate4_5 => LDPCCode::TM1280,
+        }
+    }
+}
+
+/// Air format of coded packets, both ends of a link have to agree on it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinkCoding {
+    /// [`Whitening`] seed of the coded frame, 0 disables whitening
+    pub whitening_seed: u16,
+    pub payload_code: PayloadCode,
+}
+
+/// Coded frame length of a packet carrying `payload_len` bytes with `code`
+pub const fn encoded_len(payload_len: usize, code: PayloadCode) -> usize {
+    let code = code.ldpc();
+    HEADER_LDPC_CODE.n() / 8 + payload_len.div_ceil(code.k() / 8) * (code.n() / 8)
+}
+
+/// Largest payload a coded frame of `frame_size` bytes carries with `code`
+pub const fn max_payload_for_frame_size(frame_size: usize, code: PayloadCode) -> usize {
+    let header_len = HEADER_LDPC_CODE.n() / 8;
+    if frame_size < header_len {
+        return 0;
+    }
+
+    let code = code.ldpc();
+    (frame_size - header_len) / (code.n() / 8) * (code.k() / 8)
+}
+
 pub trait PacketCoder<const S: usize> {
     const MAX_PAYLOAD_SIZE: usize;
 
@@ -24,23 +88,116 @@ pub trait PacketCoder<const S: usize> {
 pub struct LdpcPacketCoder<const S: usize> {
     working_buffer: [u8; PAYLOAD_LDPC_WORKING_BUFFER_SIZE],
     output_buffer: [u8; PAYLOAD_LDPC_OUTPUT_BUFFER_SIZE],
+    codeword_buffer: [u8; PAYLOAD_LDPC_CODE.n() / 8],
+    coding: LinkCoding,
+    max_iterations: usize,
+    iteration_budget: Option<usize>,
+    iterations: usize,
+    corrected_bits: usize,
 }
 
 impl<const S: usize> LdpcPacketCoder<S> {
-    const MAX_ENCODED_PAYLOAD_SIZE: usize = (S - (HEADER_LDPC_CODE.n() / 8));
     pub fn new() -> Self {
         Self {
             working_buffer: [0u8; PAYLOAD_LDPC_WORKING_BUFFER_SIZE],
             output_buffer: [0u8; PAYLOAD_LDPC_OUTPUT_BUFFER_SIZE],
+            codeword_buffer: [0u8; PAYLOAD_LDPC_CODE.n() / 8],
+            coding: LinkCoding::default(),
+            max_iterations: LDPC_MAX_ITERATIONS,
+            iteration_budget: None,
+            iterations: 0,
+            corrected_bits: 0,
+        }
+    }
+
+    /// Limits the bit-flip iterations spent on each codeword
+    ///
+    /// Decoding of a codeword stops as soon as its parity check passes, so the
+    /// limit only bounds the work spent on frames that don't converge.
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Limits the bit-flip iterations spent on a whole frame, unbounded unless set
+    ///
+    /// Once the codewords decoded so far used up `budget`, each further
+    /// codeword gets a single parity check and the decode fails with
+    /// [`NetworkError::CorruptedData`] at the first one that doesn't pass. A
+    /// badly corrupted large frame then costs at most about `budget`
+    /// iterations instead of [`LdpcPacketCoder::with_max_iterations`] per
+    /// codeword.
+    pub fn with_iteration_budget(mut self, budget: usize) -> Self {
+        self.iteration_budget = Some(budget);
+        self
+    }
+
+    /// Selects the payload code and whitening, [`LinkCoding::default`] unless set
+    pub fn with_coding(mut self, coding: LinkCoding) -> Self {
+        self.coding = coding;
+        self
+    }
+
+    pub fn set_coding(&mut self, coding: LinkCoding) {
+        self.coding = coding;
+    }
+
+    pub fn coding(&self) -> LinkCoding {
+        self.coding
+    }
+
+    /// Total bit-flip iterations used by the last `decode` over all codewords
+    ///
+    /// A clean frame decodes with zero iterations.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Bits flipped by the last successful `decode` over all codewords
+    pub fn corrected_bits(&self) -> usize {
+        self.corrected_bits
+    }
+
+    /// Iteration limit of the next codeword, what's left of the frame budget
+    fn codeword_iterations(&self) -> usize {
+        match self.iteration_budget {
+            // Finding a codeword clean takes one iteration, which flips nothing
+            Some(budget) => self
+                .max_iterations
+                .min(budget.saturating_sub(self.iterations) + 1),
+            None => self.max_iterations,
         }
     }
 }
 
+/// Number of bits that differ between a received codeword and its decoded form
+fn bit_errors(received: &[u8], decoded: &[u8]) -> usize {
+    received
+        .iter()
+        .zip(decoded)
+        .map(|(a, b)| (a ^ b).count_ones() as usize)
+        .sum()
+}
+
 impl<const S: usize> PacketCoder<S> for LdpcPacketCoder<S> {
-    const MAX_PAYLOAD_SIZE: usize = (Self::MAX_ENCODED_PAYLOAD_SIZE / (PAYLOAD_LDPC_CODE.n() / 8))
-        * (PAYLOAD_LDPC_CODE.k() / 8);
+    // Holds for every payload code, the most robust one carries the least
+    const MAX_PAYLOAD_SIZE: usize = max_payload_for_frame_size(S, PayloadCode::Rate1_2);
 
     fn encode(&mut self, input: &Packet<S>, output: &mut Frame<S>) -> Result<(), NetworkError> {
+        // Checked up front, the output is left alone rather than half encoded
+        let payload_len = input.frame().len();
+        let required = encoded_len(payload_len, self.coding.payload_code);
+        if required > S {
+            log::warn!(
+                "ldpc: {} byte payload codes to {} bytes, {} more than the {} byte frame",
+                payload_len,
+                required,
+                required - S,
+                S
+            );
+            return Err(NetworkError::OutOfMemory);
+        }
+
         // Reset output frame
         output.clear();
 
@@ -50,16 +207,13 @@ impl<const S: usize> PacketCoder<S> for LdpcPacketCoder<S> {
             let code = HEADER_LDPC_CODE;
 
             let codeword_len = code.n() / 8;
-            if codeword_len > S {
-                return Err(NetworkError::OutOfMemory);
-            }
 
             let _ = code.copy_encode(&header_data[..], output.alloc_buffer(codeword_len)?);
         }
 
         // Encode payload
         {
-            let code = PAYLOAD_LDPC_CODE;
+            let code = self.coding.payload_code.ldpc();
             let payload_data = input.frame().as_slice();
             let mut offset = 0;
 
@@ -91,12 +245,19 @@ impl<const S: usize> PacketCoder<S> for LdpcPacketCoder<S> {
             }
         }
 
+        Whitening::new(self.coding.whitening_seed).apply(output.as_slice_mut());
+
         Ok(())
     }
 
     fn decode(&mut self, input: &Frame<S>, output: &mut Packet<S>) -> Result<(), NetworkError> {
         output.reset();
 
+        self.iterations = 0;
+        self.corrected_bits = 0;
+
+        let mut whitening = Whitening::new(self.coding.whitening_seed);
+
         // Decode header
         {
             let code = HEADER_LDPC_CODE;
@@ -106,17 +267,26 @@ impl<const S: usize> PacketCoder<S> for LdpcPacketCoder<S> {
                 return Err(NetworkError::OutOfMemory);
             }
 
-            let (check, _) = code.decode_bf(
-                &input.as_slice()[..codeword_len],
+            let max_iterations = self.codeword_iterations();
+            let codeword = &mut self.codeword_buffer[..codeword_len];
+            codeword.copy_from_slice(&input.as_slice()[..codeword_len]);
+            whitening.apply(codeword);
+
+            let (check, iterations) = code.decode_bf(
+                codeword,
                 &mut self.output_buffer[..code.output_len()],
                 &mut self.working_buffer[..code.decode_bf_working_len()],
-                20,
+                max_iterations,
             );
 
+            self.iterations += iterations;
+
             if !check {
                 return Err(NetworkError::CorruptedData);
             }
 
+            self.corrected_bits += bit_errors(codeword, &self.output_buffer[..codeword_len]);
+
             output
                 .header_mut()
                 .unpack(&mut self.output_buffer[..HEADER_SIZE])?;
@@ -129,23 +299,32 @@ impl<const S: usize> PacketCoder<S> for LdpcPacketCoder<S> {
             // Skip header input
             let input = &input.as_slice()[HEADER_LDPC_CODE.n() / 8..];
 
-            let code = PAYLOAD_LDPC_CODE;
+            let code = self.coding.payload_code.ldpc();
 
             let codeword_len = code.n() / 8;
 
             let mut offset = 0usize;
             while offset < input.len() {
-                let (check, _) = code.decode_bf(
-                    &input[offset..offset + codeword_len],
+                let max_iterations = self.codeword_iterations();
+                let codeword = &mut self.codeword_buffer[..codeword_len];
+                codeword.copy_from_slice(&input[offset..offset + codeword_len]);
+                whitening.apply(codeword);
+
+                let (check, iterations) = code.decode_bf(
+                    codeword,
                     &mut self.output_buffer[..code.output_len()],
                     &mut self.working_buffer[..code.decode_bf_working_len()],
-                    20,
+                    max_iterations,
                 );
 
+                self.iterations += iterations;
+
                 if !check {
                     return Err(NetworkError::CorruptedData);
                 }
 
+                self.corrected_bits += bit_errors(codeword, &self.output_buffer[..codeword_len]);
+
                 output
                     .frame_mut()
                     .push_data(&self.output_buffer[..code.k() / 8])?;
@@ -154,8 +333,12 @@ impl<const S: usize> PacketCoder<S> for LdpcPacketCoder<S> {
             }
         }
 
-        // Resize to original payload length
+        // Resize to original payload length, a header claiming more than
+        // was decoded is corrupted even if its codeword checked out
         let len = output.header().len() as usize;
+        if len > output.frame().len() {
+            return Err(NetworkError::CorruptedData);
+        }
         output.frame_mut().resize(len);
 
         Ok(())
@@ -261,4 +444,197 @@ mod tests {
 
         assert_eq!(test_data.as_bytes(), packet.frame().as_slice());
     }
+
+    #[test]
+    fn test_decode_clean_frame_terminates_early() {
+        const SIZE: usize = 2048;
+
+        let test_data = "@@ TEST PACKET DATA @@";
+        let mut packet: Packet<SIZE> = Packet::new();
+        let mut frame: Frame<SIZE> = Frame::new();
+
+        let mut coder = LdpcPacketCoder::<SIZE>::new();
+
+        packet
+            .frame_mut()
+            .push_data(test_data.as_bytes())
+            .expect("packet with data");
+
+        packet.build();
+
+        coder.encode(&packet, &mut frame).expect("encoded frame");
+
+        coder.decode(&frame, &mut packet).expect("decoded frame");
+        assert_eq!(coder.iterations(), 0);
+        assert_eq!(coder.corrected_bits(), 0);
+
+        // Corrupt data
+        {
+            frame.as_slice_mut()[33] ^= 0x01;
+            frame.as_slice_mut()[90] ^= 0x10;
+        }
+
+        coder.decode(&frame, &mut packet).expect("decoded frame");
+        assert!(coder.iterations() > 0);
+        assert!(coder.iterations() < 2 * LDPC_MAX_ITERATIONS);
+        assert_eq!(coder.corrected_bits(), 2);
+
+        assert!(packet.validate());
+    }
+
+    #[test]
+    fn test_iteration_budget_bounds_corrupted_frame() {
+        const SIZE: usize = 2048;
+        const BUDGET: usize = 8;
+
+        let mut packet: Packet<SIZE> = Packet::new();
+        let mut frame: Frame<SIZE> = Frame::new();
+        packet
+            .frame_mut()
+            .push_data(&[0xA5u8; 800])
+            .expect("packet with data");
+        packet.build();
+
+        LdpcPacketCoder::<SIZE>::new()
+            .encode(&packet, &mut frame)
+            .expect("encoded frame");
+
+        let clean = frame;
+
+        // A few bit errors in every payload codeword, each one correctable
+        let header_len = HEADER_LDPC_CODE.n() / 8;
+        let codeword_len = PAYLOAD_LDPC_CODE.n() / 8;
+        for codeword in frame.as_slice_mut()[header_len..].chunks_mut(codeword_len) {
+            codeword[3] ^= 0x81;
+            codeword[100] ^= 0x18;
+        }
+
+        let mut unbounded = LdpcPacketCoder::<SIZE>::new();
+        unbounded
+            .decode(&frame, &mut packet)
+            .expect("decoded frame");
+        assert!(unbounded.iterations() > BUDGET);
+
+        let mut budgeted = LdpcPacketCoder::<SIZE>::new().with_iteration_budget(BUDGET);
+        assert!(matches!(
+            budgeted.decode(&frame, &mut packet),
+            Err(NetworkError::CorruptedData)
+        ));
+        assert!(budgeted.iterations() <= BUDGET + 1);
+
+        // A clean frame doesn't touch the budget
+        budgeted.decode(&clean, &mut packet).expect("decoded frame");
+        assert_eq!(budgeted.iterations(), 0);
+    }
+
+    #[test]
+    fn test_link_coding_round_trip() {
+        const SIZE: usize = 2048;
+
+        let test_data = [0x5Au8; 300];
+        let mut packet: Packet<SIZE> = Packet::new();
+        let mut frame: Frame<SIZE> = Frame::new();
+
+        packet
+            .frame_mut()
+            .push_data(&test_data)
+            .expect("packet with data");
+        packet.build();
+
+        for payload_code in PayloadCode::ALL {
+            let coding = LinkCoding {
+                whitening_seed: 0x1A5,
+                payload_code,
+            };
+            let mut coder = LdpcPacketCoder::<SIZE>::new().with_coding(coding);
+
+            coder.encode(&packet, &mut frame).expect("encoded frame");
+
+            let block_len = payload_code.ldpc().n() / 8;
+            assert_eq!(frame.len(), HEADER_LDPC_CODE.n() / 8 + 3 * block_len);
+
+            let mut decoded = Packet::new();
+            coder.decode(&frame, &mut decoded).expect("decoded frame");
+            assert!(decoded.validate());
+            assert_eq!(decoded.frame().as_slice(), &test_data[..]);
+
+            // A receiver with another whitening seed can't decode it
+            coder.set_coding(LinkCoding {
+                whitening_seed: 0x0F0,
+                ..coding
+            });
+            assert!(coder.decode(&frame, &mut decoded).is_err());
+        }
+    }
+
+    #[test]
+    fn test_encode_rejects_payload_exceeding_frame() {
+        const SIZE: usize = 2048;
+
+        for payload_code in PayloadCode::ALL {
+            let max_payload = max_payload_for_frame_size(SIZE, payload_code);
+            assert!(encoded_len(max_payload, payload_code) <= SIZE);
+            assert!(encoded_len(max_payload + 1, payload_code) > SIZE);
+
+            let mut coder = LdpcPacketCoder::<SIZE>::new().with_coding(LinkCoding {
+                payload_code,
+                ..Default::default()
+            });
+
+            let mut packet: Packet<SIZE> = Packet::new();
+            packet
+                .frame_mut()
+                .push_data(&vec![0xA5; max_payload])
+                .expect("packet with data");
+            packet.build();
+
+            let mut frame: Frame<SIZE> = Frame::new();
+            coder.encode(&packet, &mut frame).expect("encoded frame");
+            let encoded = frame;
+
+            packet
+                .frame_mut()
+                .push_data(&[0xA5])
+                .expect("one more byte");
+            packet.build();
+
+            assert!(matches!(
+                coder.encode(&packet, &mut frame),
+                Err(NetworkError::OutOfMemory)
+            ));
+            assert_eq!(frame.as_slice(), encoded.as_slice());
+        }
+
+        assert_eq!(
+            LdpcPacketCoder::<SIZE>::MAX_PAYLOAD_SIZE,
+            max_payload_for_frame_size(SIZE, PayloadCode::Rate1_2)
+        );
+        assert_eq!(max_payload_for_frame_size(16, PayloadCode::Rate4_5), 0);
+    }
+
+    #[test]
+    fn test_decode_rejects_oversized_length() {
+        const SIZE: usize = 2048;
+
+        let mut packet: Packet<SIZE> = Packet::new();
+        let mut frame: Frame<SIZE> = Frame::new();
+
+        let mut coder = LdpcPacketCoder::<SIZE>::new();
+
+        packet
+            .frame_mut()
+            .push_data(b"@@ TEST PACKET DATA @@")
+            .expect("packet with data");
+        packet.build();
+
+        // A valid header codeword claiming more payload than the frame carries
+        packet.header_mut().set_len(1000);
+        coder.encode(&packet, &mut frame).expect("encoded frame");
+
+        let mut decoded = Packet::new();
+        assert!(matches!(
+            coder.decode(&frame, &mut decoded),
+            Err(NetworkError::CorruptedData)
+        ));
+    }
 }
@@ -9,6 +9,8 @@ pub enum NetworkError {
     IncorrectSequence,
     NotSupported,
     Busy,
+    /// Hop-limited packet has no hops left
+    HopLimitReached,
 }
 
 impl From<FrameError> for NetworkError {
@@ -1,6 +1,9 @@
 use rand::{CryptoRng, RngCore};
 
-use crate::{error::NetworkError, packet::PacketId};
+use crate::{
+    error::NetworkError,
+    packet::{PacketId, PacketIdNamespace},
+};
 
 pub struct Generator {}
 
@@ -18,6 +21,14 @@ impl Generator {
         Ok(PacketId::from_ne_bytes(bytes))
     }
 
+    /// Generates a packet id carrying the node prefix of `namespace`
+    pub fn generate_packet_id_in<R: CryptoRng + RngCore + Copy>(
+        rng: R,
+        namespace: &PacketIdNamespace,
+    ) -> Result<PacketId, NetworkError> {
+        Ok(namespace.apply(Self::generate_packet_id(rng)?))
+    }
+
     pub fn generate_payload<R: CryptoRng + RngCore + Copy>(
         mut rng: R,
         output: &mut [u8],
@@ -6,6 +6,7 @@ pub mod muxer;
 pub mod network;
 pub mod packet;
 pub mod request;
+pub mod whitening;
 
 pub type NetworkTime = u128;
 
@@ -27,10 +28,11 @@ mod tests {
     use crate::{
         coder::{LdpcPacketCoder, PacketCoder},
         demuxer::Demuxer,
+        error::NetworkError,
         generator::Generator,
         muxer::Muxer,
         network::Network,
-        packet::Packet,
+        packet::{Packet, PacketFlag},
     };
 
     const FRAME_SIZE: usize = 2048;
@@ -82,15 +84,15 @@ mod tests {
                 .expect("consumed packet");
         }
 
-        let received_data = muxer
-            .process(1, &mut received_frame)
-            .expect("received full frame")
-            .as_slice();
+        let received_packet = muxer
+            .process(&mut received_frame)
+            .expect("received full frame");
+        let received_data = received_packet.as_slice();
 
         assert_eq!(received_data.len(), original_data.len());
         assert_eq!(received_data, original_data);
 
-        assert!(muxer.process(1, &mut received_frame).is_err());
+        assert!(muxer.process(&mut received_frame).is_err());
     }
 
     #[test]
@@ -104,17 +106,87 @@ mod tests {
         };
 
         type Coder = LdpcPacketCoder<FRAME_SIZE>;
-        let mut coder = Coder::new();
-
-        let mut network =
-            Network::<FRAME_SIZE, MAX_SEGMENTS_COUNT, 6, { Coder::MAX_PAYLOAD_SIZE }, Coder>::new(
-                coder,
-            );
+        let mut network = Network::<FRAME_SIZE, MAX_SEGMENTS_COUNT, 6, Coder>::new(Coder::new());
 
         let mut frames = [Frame::new(); MAX_SEGMENTS_COUNT];
 
-        network
-            .transmit(&original_data[..], rng, &mut frames, &mut trx)
+        let frames = network
+            .transmit(&original_data[..], rng, &mut frames)
             .expect("demuxed frames");
+
+        for frame in frames {
+            network.receive(1, frame).expect("decoded frame");
+        }
+
+        let mut received_frame = FrameSegment::<FRAME_SIZE, MAX_SEGMENTS_COUNT>::new();
+        let received_packet = network
+            .process(1, &mut received_frame)
+            .expect("received full frame");
+
+        assert_eq!(received_packet.as_slice(), original_data);
+    }
+
+    #[test]
+    fn test_network_hop_limit() {
+        let rng = OsRng;
+
+        type Coder = LdpcPacketCoder<FRAME_SIZE>;
+        type HopNetwork = Network<FRAME_SIZE, MAX_SEGMENTS_COUNT, 6, Coder>;
+        let mut sender = HopNetwork::new(Coder::new()).with_ttl(2);
+        let mut node = HopNetwork::new(Coder::new());
+
+        let mut frames = [Frame::new(); MAX_SEGMENTS_COUNT];
+        let frame = sender
+            .transmit(b"mesh", rng, &mut frames)
+            .expect("demuxed frames")[0];
+
+        // First hop delivers and passes it on with one hop left
+        let mut forwarded = Frame::new();
+        node.receive(1, &frame).expect("decoded frame");
+        node.forward(&frame, &mut forwarded)
+            .expect("forwarded frame");
+
+        // Last hop delivers but doesn't forward
+        let mut received_frame = FrameSegment::<FRAME_SIZE, MAX_SEGMENTS_COUNT>::new();
+        let mut last_hop = HopNetwork::new(Coder::new());
+        last_hop.receive(1, &forwarded).expect("decoded frame");
+        let received_packet = last_hop
+            .process(1, &mut received_frame)
+            .expect("received full frame");
+        assert_eq!(received_packet.as_slice(), b"mesh");
+
+        let mut output = Frame::new();
+        assert!(matches!(
+            last_hop.forward(&forwarded, &mut output),
+            Err(NetworkError::HopLimitReached)
+        ));
+
+        // A hop-limited packet without hops left is dropped on receipt
+        let mut coder = Coder::new();
+        let mut packet = Packet::new();
+        packet
+            .header_mut()
+            .add_flag(PacketFlag::Segmented)
+            .add_flag(PacketFlag::HopLimited)
+            .set_seq_count(1)
+            .set_ttl(0);
+        packet.frame_mut().push_data(b"mesh").expect("payload");
+        packet.build();
+        coder.encode(&packet, &mut output).expect("encoded frame");
+
+        assert!(matches!(
+            node.receive(1, &output),
+            Err(NetworkError::HopLimitReached)
+        ));
+
+        // Packets sent without a TTL are never forwarded
+        let mut unlimited = HopNetwork::new(Coder::new());
+        let frame = unlimited
+            .transmit(b"mesh", rng, &mut frames)
+            .expect("demuxed frames")[0];
+        assert!(matches!(
+            node.forward(&frame, &mut output),
+            Err(NetworkError::NotSupported)
+        ));
     }
 }
@@ -3,7 +3,7 @@ use kaonic_frame::frame::FrameSegment;
 use crate::{
     error::NetworkError,
     network_time_elapsed,
-    packet::{AssembledPacket, Packet, PacketFlag, PacketId},
+    packet::{AssembledPacket, Packet, PacketFlag, PacketId, PacketIdNamespace, PacketType},
     NetworkTime,
 };
 
@@ -35,6 +35,11 @@ impl<const S: usize, const R: usize> PacketMuxer<S, R> {
         self.count == 0
     }
 
+    /// Some segments were collected but the packet can't be assembled yet
+    pub fn is_partial(&self) -> bool {
+        !self.is_empty() && !self.can_assemble()
+    }
+
     pub fn push(&mut self, current_time: NetworkTime, new_packet: &Packet<S>) -> bool {
         // Packet collection is already full
         if self.count >= R {
@@ -143,10 +148,17 @@ impl<const S: usize, const R: usize> PacketMuxer<S, R> {
 }
 
 /// The muxer can handle up to 'Q' packets divided into 'R' segments of 'S' size
+///
+/// When the number of partially received packets reaches the pending budget,
+/// the least recently updated one of the node holding the most is evicted to
+/// make room for a new packet id.
 #[derive(Debug)]
 pub struct Muxer<const S: usize, const R: usize, const Q: usize> {
     queue: [PacketMuxer<S, R>; Q],
     timeout: core::time::Duration,
+    max_pending: usize,
+    evictions: usize,
+    id_namespace: PacketIdNamespace,
 }
 
 impl<const S: usize, const R: usize, const Q: usize> Muxer<S, R, Q> {
@@ -154,6 +166,72 @@ impl<const S: usize, const R: usize, const Q: usize> Muxer<S, R, Q> {
         Self {
             queue: [PacketMuxer::new(); Q],
             timeout: core::time::Duration::from_millis(500),
+            max_pending: Q,
+            evictions: 0,
+            id_namespace: PacketIdNamespace::NONE,
+        }
+    }
+
+    /// Tells the nodes apart by the prefix of their packet ids in `namespace`
+    ///
+    /// A node flooding the muxer then only evicts its own partial packets.
+    pub fn with_id_namespace(mut self, namespace: PacketIdNamespace) -> Self {
+        self.id_namespace = namespace;
+        self
+    }
+
+    /// Limits the number of partially received packets (1..=Q)
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.clamp(1, Q);
+        self
+    }
+
+    /// Number of partial packets dropped to stay within the pending budget
+    pub fn evictions(&self) -> usize {
+        self.evictions
+    }
+
+    /// Number of packets still waiting for segments, assembled packets
+    /// waiting for `process` don't count
+    fn pending(&self) -> usize {
+        self.queue.iter().filter(|px| px.is_partial()).count()
+    }
+
+    /// Partial packets of the node `prefix` stands for
+    fn pending_of(&self, prefix: PacketId) -> usize {
+        self.queue
+            .iter()
+            .filter(|px| px.is_partial() && self.id_namespace.prefix_of(px.packet_id()) == prefix)
+            .count()
+    }
+
+    fn evict_oldest(&mut self) {
+        let pending_of =
+            |px: &PacketMuxer<S, R>| self.pending_of(self.id_namespace.prefix_of(px.packet_id()));
+
+        let Some(most) = self
+            .queue
+            .iter()
+            .filter(|px| px.is_partial())
+            .map(pending_of)
+            .max()
+        else {
+            return;
+        };
+
+        let oldest = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, px)| px.is_partial() && pending_of(px) == most)
+            .min_by_key(|(_, px)| px.last_update_time)
+            .map(|(index, _)| index);
+
+        if let Some(px) = oldest.map(|index| &mut self.queue[index]) {
+            log::debug!("muxer: evict partial packet {:0>8X}", px.packet_id());
+
+            px.release();
+            self.evictions += 1;
         }
     }
 
@@ -162,10 +240,14 @@ impl<const S: usize, const R: usize, const Q: usize> Muxer<S, R, Q> {
         current_time: NetworkTime,
         packet: &Packet<S>,
     ) -> Result<(), NetworkError> {
-        if !packet.header().has_flag(PacketFlag::Segmented) {
+        // Beacons and heartbeats are link traffic, never reassembled as data
+        if packet.header().packet_type() != PacketType::Payload
+            || !packet.header().has_flag(PacketFlag::Segmented)
+        {
             return Err(NetworkError::NotSupported);
         }
 
+        // Keyed by the whole id, node prefix included, see `PacketIdNamespace`
         let packet_id = packet.header().id();
 
         for px in self.queue.iter_mut() {
@@ -176,11 +258,18 @@ impl<const S: usize, const R: usize, const Q: usize> Muxer<S, R, Q> {
             }
         }
 
-        for px in self.queue.iter_mut() {
-            if px.timeout_reached(current_time, self.timeout) {
-                px.release();
-            }
+        self.release_expired(current_time);
 
+        // Packet can't be collected, don't evict anything for it
+        if packet.header().seq_count() > R {
+            return Err(NetworkError::TryAgain);
+        }
+
+        if self.pending() >= self.max_pending {
+            self.evict_oldest();
+        }
+
+        for px in self.queue.iter_mut() {
             if px.is_empty() {
                 if px.push(current_time, packet) {
                     return Ok(());
@@ -217,3 +306,157 @@ impl<const S: usize, const R: usize, const Q: usize> Muxer<S, R, Q> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::PacketIdNamespace;
+
+    const SIZE: usize = 64;
+
+    fn segment(id: PacketId, seq: usize, data: &[u8]) -> Packet<SIZE> {
+        let mut packet = Packet::new();
+
+        packet
+            .header_mut()
+            .add_flag(PacketFlag::Segmented)
+            .set_id(id)
+            .set_seq(seq)
+            .set_seq_count(2);
+        packet.frame_mut().push_data(data).expect("segment data");
+        packet.build();
+
+        packet
+    }
+
+    #[test]
+    fn test_evict_oldest_partial_packet() {
+        let mut muxer = Muxer::<SIZE, 2, 4>::new().with_max_pending(2);
+        let mut frame = FrameSegment::<SIZE, 2>::new();
+
+        for (time, id) in [(1, 0xA1), (2, 0xA2), (3, 0xA3)] {
+            muxer
+                .multiplex(time, &segment(id, 0, b"head"))
+                .expect("segment accepted");
+        }
+
+        assert_eq!(muxer.evictions(), 1);
+        assert_eq!(muxer.pending(), 2);
+
+        // Oldest packet lost its first segment and can't be assembled anymore
+        muxer
+            .multiplex(4, &segment(0xA2, 1, b"tail"))
+            .expect("segment accepted");
+        assert_eq!(muxer.evictions(), 1);
+
+        let packet = muxer.process(&mut frame).expect("assembled packet");
+        assert_eq!(packet.id(), 0xA2);
+        assert_eq!(packet.as_slice(), b"headtail");
+
+        muxer
+            .multiplex(5, &segment(0xA1, 1, b"tail"))
+            .expect("segment accepted");
+        assert!(muxer.process(&mut frame).is_err());
+    }
+
+    #[test]
+    fn test_assembled_packet_is_not_evicted() {
+        let mut muxer = Muxer::<SIZE, 2, 4>::new().with_max_pending(1);
+        let mut frame = FrameSegment::<SIZE, 2>::new();
+
+        for segment in [segment(0xB1, 0, b"head"), segment(0xB1, 1, b"tail")] {
+            muxer.multiplex(1, &segment).expect("segment accepted");
+        }
+
+        // Budget is spent on partial packets only
+        muxer
+            .multiplex(2, &segment(0xB2, 0, b"head"))
+            .expect("segment accepted");
+        muxer
+            .multiplex(3, &segment(0xB3, 0, b"head"))
+            .expect("segment accepted");
+        assert_eq!(muxer.evictions(), 1);
+
+        let packet = muxer.process(&mut frame).expect("assembled packet");
+        assert_eq!(packet.id(), 0xB1);
+        assert_eq!(packet.as_slice(), b"headtail");
+    }
+
+    #[test]
+    fn test_evict_from_busiest_namespace() {
+        let node_a = PacketIdNamespace::new(0x0A, 8);
+        let node_b = PacketIdNamespace::new(0x0B, 8);
+        let mut muxer = Muxer::<SIZE, 2, 4>::new()
+            .with_max_pending(3)
+            .with_id_namespace(node_a);
+        let mut frame = FrameSegment::<SIZE, 2>::new();
+
+        for (time, id) in [
+            (1, node_b.apply(0xB1)),
+            (2, node_a.apply(0xA1)),
+            (3, node_a.apply(0xA2)),
+            (4, node_a.apply(0xA3)),
+        ] {
+            muxer
+                .multiplex(time, &segment(id, 0, b"head"))
+                .expect("segment accepted");
+        }
+
+        // The oldest packet of node A goes, node B keeps its older one
+        assert_eq!(muxer.evictions(), 1);
+        muxer
+            .multiplex(5, &segment(node_b.apply(0xB1), 1, b"tail"))
+            .expect("segment accepted");
+        let packet = muxer.process(&mut frame).expect("assembled packet");
+        assert_eq!(packet.id(), node_b.apply(0xB1));
+
+        muxer
+            .multiplex(6, &segment(node_a.apply(0xA1), 1, b"tail"))
+            .expect("segment accepted");
+        assert!(muxer.process(&mut frame).is_err());
+    }
+
+    #[test]
+    fn test_link_packets_are_not_reassembled() {
+        let mut muxer = Muxer::<SIZE, 2, 4>::new();
+
+        for packet_type in [PacketType::Beacon, PacketType::Heartbeat] {
+            let mut packet = segment(0xC1, 0, b"head");
+            packet.header_mut().set_packet_type(packet_type);
+            packet.build();
+
+            assert!(matches!(
+                muxer.multiplex(1, &packet),
+                Err(NetworkError::NotSupported)
+            ));
+        }
+        assert_eq!(muxer.pending(), 0);
+    }
+
+    #[test]
+    fn test_namespaced_ids_reassemble_apart() {
+        let mut muxer = Muxer::<SIZE, 2, 4>::new();
+        let mut frame = FrameSegment::<SIZE, 2>::new();
+
+        // Both nodes drew the same random bits
+        let id_a = PacketIdNamespace::new(0x0A, 8).apply(0x0012_3456);
+        let id_b = PacketIdNamespace::new(0x0B, 8).apply(0x0012_3456);
+
+        for segment in [
+            segment(id_a, 0, b"a-head"),
+            segment(id_b, 0, b"b-head"),
+            segment(id_b, 1, b"b-tail"),
+            segment(id_a, 1, b"a-tail"),
+        ] {
+            muxer.multiplex(1, &segment).expect("segment accepted");
+        }
+
+        let packet = muxer.process(&mut frame).expect("assembled packet");
+        assert_eq!(packet.id(), id_a);
+        assert_eq!(packet.as_slice(), b"a-heada-tail");
+
+        let packet = muxer.process(&mut frame).expect("assembled packet");
+        assert_eq!(packet.id(), id_b);
+        assert_eq!(packet.as_slice(), b"b-headb-tail");
+    }
+}
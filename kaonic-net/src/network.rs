@@ -7,7 +7,7 @@ use crate::{
     error::NetworkError,
     generator::Generator,
     muxer::Muxer,
-    packet::{AssembledPacket, Packet},
+    packet::{AssembledPacket, Packet, PacketFlag, PacketIdNamespace},
     NetworkTime,
 };
 
@@ -24,6 +24,8 @@ pub struct Network<const S: usize, const R: usize, const Q: usize, C: PacketCode
     muxer: Muxer<S, R, Q>,
     packets: [Packet<S>; R],
     coder: C,
+    ttl: u8,
+    id_namespace: PacketIdNamespace,
 }
 
 impl<const S: usize, const R: usize, const Q: usize, C: PacketCoder<S>> Network<S, R, Q, C> {
@@ -33,9 +35,42 @@ impl<const S: usize, const R: usize, const Q: usize, C: PacketCoder<S>> Network<
             muxer: Muxer::new(),
             packets: [Packet::new(); R],
             coder,
+            ttl: 0,
+            id_namespace: PacketIdNamespace::NONE,
         }
     }
 
+    /// Sends packets hop-limited to `ttl` hops, 0 leaves them unlimited
+    pub fn with_ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Prefixes the ids of sent packets with the node prefix of `namespace`
+    ///
+    /// Receivers reassemble by packet id, so nodes sharing a medium should
+    /// each use a distinct prefix. Received packets are told apart by node
+    /// with the same prefix width.
+    pub fn with_id_namespace(mut self, namespace: PacketIdNamespace) -> Self {
+        self.id_namespace = namespace;
+        self.muxer = self.muxer.with_id_namespace(namespace);
+        self
+    }
+
+    /// Limits the number of partially received packets kept for reassembly
+    ///
+    /// Once the budget is reached the oldest partial packet is evicted, so a
+    /// flood of spoofed packet ids can't lock out legitimate traffic.
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.muxer = self.muxer.with_max_pending(max_pending);
+        self
+    }
+
+    /// Number of partial packets evicted to stay within the pending budget
+    pub fn evictions(&self) -> usize {
+        self.muxer.evictions()
+    }
+
     pub fn receive(
         &mut self,
         current_time: NetworkTime,
@@ -43,6 +78,12 @@ impl<const S: usize, const R: usize, const Q: usize, C: PacketCoder<S>> Network<
     ) -> Result<(), NetworkError> {
         self.coder.decode(&frame, &mut self.packets[0])?;
 
+        let header = self.packets[0].header();
+        if header.has_flag(PacketFlag::HopLimited) && header.ttl() == 0 {
+            log::debug!("network: drop packet {:0>8X}, ttl expired", header.id());
+            return Err(NetworkError::HopLimitReached);
+        }
+
         let _ = self.muxer.multiplex(current_time, &self.packets[0]);
 
         Ok(())
@@ -66,20 +107,55 @@ impl<const S: usize, const R: usize, const Q: usize, C: PacketCoder<S>> Network<
         rng: RNG,
         output_frames: &'a mut [Frame<S>],
     ) -> Result<&'a [Frame<S>], NetworkError> {
-        let packet_id = Generator::generate_packet_id(rng)?;
+        let packet_id = Generator::generate_packet_id_in(rng, &self.id_namespace)?;
 
-        let packets = self
+        let count = self
             .demuxer
-            .demultiplex(packet_id, data, &mut self.packets[..])?;
+            .demultiplex(packet_id, data, &mut self.packets[..])?
+            .len();
 
-        if output_frames.len() < packets.len() {
+        if output_frames.len() < count {
             return Err(NetworkError::PayloadTooBig);
         }
 
-        for i in 0..packets.len() {
-            self.coder.encode(&packets[i], &mut output_frames[i])?;
+        for (packet, output_frame) in self.packets[..count]
+            .iter_mut()
+            .zip(output_frames.iter_mut())
+        {
+            if self.ttl > 0 {
+                packet
+                    .header_mut()
+                    .add_flag(PacketFlag::HopLimited)
+                    .set_ttl(self.ttl);
+            }
+
+            self.coder.encode(packet, output_frame)?;
         }
 
-        Ok(&output_frames[..packets.len()])
+        Ok(&output_frames[..count])
+    }
+
+    /// Re-encodes a received frame for the next hop with its TTL decremented
+    ///
+    /// Only hop-limited packets are forwarded, a packet on its last hop is
+    /// dropped so frames can't circulate in a mesh.
+    pub fn forward(&mut self, frame: &Frame<S>, output: &mut Frame<S>) -> Result<(), NetworkError> {
+        let packet = &mut self.packets[0];
+        self.coder.decode(frame, packet)?;
+
+        let header = packet.header_mut();
+        if !header.has_flag(PacketFlag::HopLimited) {
+            return Err(NetworkError::NotSupported);
+        }
+
+        if header.ttl() <= 1 {
+            log::debug!("network: drop packet {:0>8X}, last hop", header.id());
+            return Err(NetworkError::HopLimitReached);
+        }
+
+        let ttl = header.ttl() - 1;
+        header.set_ttl(ttl);
+
+        self.coder.encode(packet, output)
     }
 }
@@ -6,14 +6,80 @@ use crate::error::NetworkError;
 
 pub const HEADER_SIZE: usize = 16;
 
+/// Highest priority a header carries, larger values are clamped
+pub const MAX_PRIORITY: u8 = 7;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum PacketType {
     Payload = 0xBA,
+    /// Periodic node identity announcement
+    Beacon = 0xBE,
+    /// Link probe that keeps the receiver's QoS assessment current
+    Heartbeat = 0xBB,
 }
 
 pub type PacketId = u32;
 
+/// Widest node prefix of a packet id, the rest stays random
+pub const MAX_ID_PREFIX_BITS: u8 = 16;
+
+/// Node prefix in the top bits of the packet ids a device generates
+///
+/// Packets are reassembled by id, so nodes with distinct prefixes can't mix
+/// up each other's segments however their random bits turn out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PacketIdNamespace {
+    prefix: PacketId,
+    bits: u8,
+}
+
+impl PacketIdNamespace {
+    /// Ids are random in all of their bits
+    pub const NONE: Self = Self { prefix: 0, bits: 0 };
+
+    /// Puts `prefix` in the top `bits` bits (up to [`MAX_ID_PREFIX_BITS`]),
+    /// higher prefix bits are dropped
+    pub fn new(prefix: PacketId, bits: u8) -> Self {
+        let bits = bits.min(MAX_ID_PREFIX_BITS);
+        let prefix = if bits == 0 {
+            0
+        } else {
+            prefix & ((1 << bits) - 1)
+        };
+
+        Self { prefix, bits }
+    }
+
+    pub fn prefix(&self) -> PacketId {
+        self.prefix
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Replaces the top bits of the random `id` with the prefix
+    pub fn apply(&self, id: PacketId) -> PacketId {
+        if self.bits == 0 {
+            return id;
+        }
+
+        let shift = PacketId::BITS - self.bits as u32;
+
+        (self.prefix << shift) | (id & ((1 << shift) - 1))
+    }
+
+    /// Prefix carried in the top bits of `id`
+    pub fn prefix_of(&self, id: PacketId) -> PacketId {
+        if self.bits == 0 {
+            return 0;
+        }
+
+        id >> (PacketId::BITS - self.bits as u32)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum PacketFlag {
@@ -23,6 +89,8 @@ pub enum PacketFlag {
     Segmented = 0b0000_0010,
     ///
     Acknowledge = 0b0000_0100,
+    /// Header carries a TTL, the packet is dropped once it reaches zero
+    HopLimited = 0b0000_1000,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -45,6 +113,15 @@ pub struct Header {
     /// Packet payload length
     len: u16,
 
+    /// Priority of the packet, 0 if unused
+    priority: u8,
+
+    /// Remaining hops, 0 if unused
+    ttl: u8,
+
+    /// Destination port, 0 if unused
+    port: u8,
+
     // CRC
     crc: u32,
 }
@@ -58,6 +135,9 @@ impl Header {
             seq: 0,
             seq_count: 0,
             len: 0,
+            priority: 0,
+            ttl: 0,
+            port: 0,
             crc: 0,
         }
     }
@@ -67,6 +147,15 @@ impl Header {
         self
     }
 
+    pub fn set_packet_type(&mut self, packet_type: PacketType) -> &mut Self {
+        self.packet_type = packet_type;
+        self
+    }
+
+    pub fn packet_type(&self) -> PacketType {
+        self.packet_type
+    }
+
     pub fn set_id(&mut self, id: PacketId) -> &mut Self {
         self.id = id;
         self
@@ -76,6 +165,11 @@ impl Header {
         self.id
     }
 
+    /// Node prefix of the packet id in `namespace`'s width
+    pub fn id_prefix(&self, namespace: &PacketIdNamespace) -> PacketId {
+        namespace.prefix_of(self.id)
+    }
+
     pub fn set_seq(&mut self, seq: usize) -> &mut Self {
         self.seq = seq;
         self
@@ -117,6 +211,33 @@ impl Header {
         self.len
     }
 
+    pub fn set_priority(&mut self, priority: u8) -> &mut Self {
+        self.priority = priority.min(MAX_PRIORITY);
+        self
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    pub fn set_ttl(&mut self, ttl: u8) -> &mut Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn ttl(&self) -> u8 {
+        self.ttl
+    }
+
+    pub fn set_port(&mut self, port: u8) -> &mut Self {
+        self.port = port;
+        self
+    }
+
+    pub fn port(&self) -> u8 {
+        self.port
+    }
+
     pub fn crc(&self) -> u32 {
         self.crc
     }
@@ -138,7 +259,10 @@ impl Header {
         buffer[offset..offset + 4].copy_from_slice(&self.id.to_le_bytes());
         offset += 4;
 
-        // Reserved
+        // Formerly reserved, zero for senders without these fields
+        buffer[offset] = self.priority;
+        buffer[offset + 1] = self.ttl;
+        buffer[offset + 2] = self.port;
         offset += 3;
 
         buffer[offset..offset + 2].copy_from_slice(&self.len.to_le_bytes());
@@ -158,6 +282,8 @@ impl Header {
 
         self.packet_type = match data[offset] {
             0xBA => PacketType::Payload,
+            0xBE => PacketType::Beacon,
+            0xBB => PacketType::Heartbeat,
             _ => return Err(NetworkError::NotSupported),
         };
         offset += 1;
@@ -177,7 +303,9 @@ impl Header {
         ]);
         offset += 4;
 
-        // Reserved
+        self.priority = data[offset].min(MAX_PRIORITY);
+        self.ttl = data[offset + 1];
+        self.port = data[offset + 2];
         offset += 3;
 
         self.len = u16::from_le_bytes([data[offset + 0], data[offset + 1]]);
@@ -245,7 +373,7 @@ impl<const S: usize> Packet<S> {
             return false;
         }
 
-        if self.frame.len() != self.header.len.into() {
+        if self.frame.len() != usize::from(self.header.len) {
             return false;
         }
 
@@ -261,11 +389,16 @@ impl<const S: usize> Packet<S> {
     }
 
     fn calculate_crc(data: &[u8]) -> u32 {
-        let crc = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
-        crc.checksum(data)
+        crc32(data)
     }
 }
 
+/// CRC-32 (ISO-HDLC) used to validate packet payloads
+pub fn crc32(data: &[u8]) -> u32 {
+    let crc = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+    crc.checksum(data)
+}
+
 pub struct AssembledPacket<'a, const S: usize, const R: usize> {
     id: PacketId,
     frame: &'a FrameSegment<S, R>,
@@ -288,3 +421,67 @@ impl<'a, const S: usize, const R: usize> AssembledPacket<'a, S, R> {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_fields_round_trip() {
+        let mut header = Header::new();
+        header
+            .set_id(0x1234_5678)
+            .set_len(42)
+            .set_priority(5)
+            .set_ttl(3)
+            .set_port(0x21);
+
+        let mut unpacked = Header::new();
+        assert_eq!(
+            unpacked.unpack(&header.pack()).expect("header"),
+            HEADER_SIZE
+        );
+        assert_eq!(unpacked.id(), 0x1234_5678);
+        assert_eq!(unpacked.len(), 42);
+        assert_eq!(unpacked.priority(), 5);
+        assert_eq!(unpacked.ttl(), 3);
+        assert_eq!(unpacked.port(), 0x21);
+
+        assert_eq!(header.set_priority(200).priority(), MAX_PRIORITY);
+
+        // Frames from older senders leave the bytes zeroed
+        let mut legacy = Header::new().pack();
+        legacy[7..10].fill(0);
+        let mut unpacked = Header::new();
+        unpacked.set_ttl(9);
+        assert_eq!(unpacked.unpack(&legacy).expect("header"), HEADER_SIZE);
+        assert_eq!(unpacked.priority(), 0);
+        assert_eq!(unpacked.ttl(), 0);
+        assert_eq!(unpacked.port(), 0);
+    }
+
+    #[test]
+    fn test_id_namespaces_never_collide() {
+        let node_a = PacketIdNamespace::new(0x1A, 8);
+        let node_b = PacketIdNamespace::new(0x1B, 8);
+
+        for random in [0, 0x00AB_CDEF, 0x1BAB_CDEF, PacketId::MAX] {
+            let id_a = node_a.apply(random);
+            let id_b = node_b.apply(random);
+            assert_ne!(id_a, id_b);
+            assert_eq!(node_a.prefix_of(id_a), 0x1A);
+            assert_eq!(node_b.prefix_of(id_b), 0x1B);
+            // The random lower bits are kept
+            assert_eq!(id_a & 0x00FF_FFFF, random & 0x00FF_FFFF);
+        }
+
+        let mut header = Header::new();
+        header.set_id(node_b.apply(0x1234_5678));
+        assert_eq!(header.id(), 0x1B34_5678);
+        assert_eq!(header.id_prefix(&node_b), 0x1B);
+
+        assert_eq!(PacketIdNamespace::NONE.apply(0x1234_5678), 0x1234_5678);
+        assert_eq!(PacketIdNamespace::new(0x1FF, 8).prefix(), 0xFF);
+        assert_eq!(PacketIdNamespace::new(1, 32).bits(), MAX_ID_PREFIX_BITS);
+    }
+}
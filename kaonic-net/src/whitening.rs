@@ -0,0 +1,65 @@
+/// PN9 data whitening (x^9 + x^5 + 1)
+///
+/// XORs the data with a pseudo-random sequence so long runs of equal bits
+/// don't reach the air. Applying the same seed twice restores the data. The
+/// sequence continues across [`Whitening::apply`] calls, so a frame can be
+/// whitened piece by piece. Seed 0 gives an all-zero sequence and leaves the
+/// data as it is.
+#[derive(Copy, Clone, Debug)]
+pub struct Whitening {
+    state: u16,
+}
+
+impl Whitening {
+    /// Only the low 9 bits of `seed` are used
+    pub fn new(seed: u16) -> Self {
+        Self {
+            state: seed & 0x1FF,
+        }
+    }
+
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            let mut mask = 0u8;
+            for bit in 0..8 {
+                mask |= ((self.state & 1) as u8) << bit;
+
+                let feedback = (self.state ^ (self.state >> 5)) & 1;
+                self.state = (self.state >> 1) | (feedback << 8);
+            }
+
+            *byte ^= mask;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whitening_round_trip() {
+        let original = [0u8; 64];
+        let mut data = original;
+
+        Whitening::new(0x1FF).apply(&mut data);
+        assert_ne!(data, original);
+
+        Whitening::new(0x1FF).apply(&mut data);
+        assert_eq!(data, original);
+
+        // Piece by piece continues the same sequence
+        let mut whitening = Whitening::new(0x0A5);
+        whitening.apply(&mut data[..10]);
+        whitening.apply(&mut data[10..]);
+        Whitening::new(0x0A5).apply(&mut data);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn test_whitening_seed_zero_is_identity() {
+        let mut data = *b"kaonic";
+        Whitening::new(0).apply(&mut data);
+        assert_eq!(&data, b"kaonic");
+    }
+}
@@ -0,0 +1,44 @@
+use radio_rf215::bus::BusClock;
+
+/// Monotonic time source for QoS timing
+pub trait Clock {
+    /// Current time in milliseconds
+    fn current_time(&mut self) -> u64;
+}
+
+/// The RF215 bus clock of a platform can drive the QoS timing directly
+impl<T: BusClock> Clock for T {
+    fn current_time(&mut self) -> u64 {
+        BusClock::current_time(self)
+    }
+}
+
+/// Clock counting from its creation using `std::time::Instant`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn current_time(&mut self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+}
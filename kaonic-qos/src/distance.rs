@@ -0,0 +1,67 @@
+//! Rough range estimation from received signal strength
+
+/// Estimates the distance to a transmitter with the log-distance path-loss model
+///
+/// `reference_rssi` is the RSSI measured 1 m from a transmitter at 0 dBm, so
+/// the expected RSSI at distance `d` is
+/// `tx_power_dbm + reference_rssi - 10 * path_loss_exponent * log10(d)`.
+/// Typical exponents are 2 in free space, 2.7-3.5 in urban areas and 4-6
+/// indoors with obstructions.
+///
+/// Treat the result as an order of magnitude only. Fading, antenna gains and
+/// orientation, body shadowing and RSSI quantization easily shift the
+/// received level by 10 dB, which is a factor of 3 in distance at an exponent
+/// of 2. The model also assumes the peer's TX power is known.
+///
+/// Returns `None` unless `path_loss_exponent` is positive and finite, an
+/// exponent of 0 would divide by zero.
+pub fn estimate_distance_m(
+    rssi: i8,
+    tx_power_dbm: i8,
+    path_loss_exponent: f32,
+    reference_rssi: i8,
+) -> Option<f32> {
+    if !path_loss_exponent.is_finite() || path_loss_exponent <= 0.0 {
+        return None;
+    }
+
+    let path_loss = tx_power_dbm as f32 + reference_rssi as f32 - rssi as f32;
+
+    Some(10f32.powf(path_loss / (10.0 * path_loss_exponent)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("distance estimate");
+        assert!(
+            (actual - expected).abs() <= expected * 1e-4,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_estimate_distance() {
+        // 14 + (-40) - (-66) = 40 dB over 1 m, 40 / 20 = 2 decades
+        assert_close(estimate_distance_m(-66, 14, 2.0, -40), 100.0);
+
+        // 0 + (-40) - (-70) = 30 dB, 30 / 30 = 1 decade
+        assert_close(estimate_distance_m(-70, 0, 3.0, -40), 10.0);
+
+        // Stronger than the 1 m reference
+        assert_close(estimate_distance_m(-30, 0, 2.0, -40), 0.316_227_77);
+
+        assert_close(estimate_distance_m(-40, 0, 2.7, -40), 1.0);
+    }
+
+    #[test]
+    fn test_estimate_distance_rejects_invalid_exponent() {
+        assert_eq!(estimate_distance_m(-66, 14, 0.0, -40), None);
+        assert_eq!(estimate_distance_m(-66, 14, -2.0, -40), None);
+        assert_eq!(estimate_distance_m(-66, 14, f32::NAN, -40), None);
+    }
+}
@@ -0,0 +1,260 @@
+//! Interference classification from a short EDV history
+//!
+//! The channel assessment only tracks the EDV level. How the interference
+//! behaves over time matters as well when picking a backoff: waiting doesn't
+//! help against a continuous interferer, while a bursty or periodic one
+//! leaves gaps which a short backoff can catch.
+
+use crate::ChannelQuality;
+
+/// Number of EDV samples the classification is based on
+pub const HISTORY_LEN: usize = 32;
+
+/// Samples needed before the history is classified at all
+const MIN_SAMPLES: usize = 8;
+
+/// Backoff against a continuous interferer, independent of its level
+pub const CONTINUOUS_BACKOFF_MS: u32 = 20000;
+
+/// Shortest backoff against intermittent interference
+pub const MIN_BACKOFF_MS: u32 = 100;
+
+/// Time behaviour of the interference on a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterferenceClass {
+    Clear,      // No sample above the busy threshold
+    Continuous, // Busy with little variation
+    Bursty,     // Busy at random times
+    Periodic,   // Busy at a regular interval
+}
+
+/// Classifies the interference from the EDV samples pushed into it
+///
+/// A history which is busy on average and varies less than the variance
+/// threshold is continuous. Otherwise the autocorrelation of the history is
+/// searched for a peak after its first zero crossing, a peak at or above the
+/// periodicity threshold makes it periodic and bursty without one.
+#[derive(Debug, Clone)]
+pub struct InterferenceClassifier {
+    history: [i8; HISTORY_LEN],
+    len: usize,
+    head: usize,
+    busy_threshold: i8,         // EDV in dBm at and above which a sample is busy
+    variance_threshold: u32,    // Variance in dB² below which busy is continuous
+    periodicity_threshold: u32, // Autocorrelation peak in percent for periodic
+    class: InterferenceClass,
+    duty_cycle: u32, // Busy samples in percent
+}
+
+impl InterferenceClassifier {
+    pub fn new() -> Self {
+        Self {
+            history: [0; HISTORY_LEN],
+            len: 0,
+            head: 0,
+            busy_threshold: -70,
+            variance_threshold: 9,
+            periodicity_threshold: 60,
+            class: InterferenceClass::Clear,
+            duty_cycle: 0,
+        }
+    }
+
+    pub fn with_busy_threshold(mut self, edv: i8) -> Self {
+        log::debug!("QoS: Setting interference busy threshold to {} dBm", edv);
+        self.busy_threshold = edv;
+        self
+    }
+
+    pub fn with_variance_threshold(mut self, variance: u32) -> Self {
+        log::debug!(
+            "QoS: Setting interference variance threshold to {} dB²",
+            variance
+        );
+        self.variance_threshold = variance;
+        self
+    }
+
+    pub fn with_periodicity_threshold(mut self, percent: u32) -> Self {
+        log::debug!(
+            "QoS: Setting interference periodicity threshold to {}%",
+            percent
+        );
+        self.periodicity_threshold = percent;
+        self
+    }
+
+    /// Add an EDV sample, the oldest one is dropped once the history is full
+    pub fn push(&mut self, edv: i8) {
+        self.history[self.head] = edv;
+        self.head = (self.head + 1) % HISTORY_LEN;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+
+        let old_class = self.class;
+        self.classify();
+
+        if old_class != self.class {
+            log::info!(
+                "QoS: Interference changed {:?} -> {:?} (duty cycle: {}%)",
+                old_class,
+                self.class,
+                self.duty_cycle
+            );
+        }
+    }
+
+    /// Classification of the current history
+    pub fn class(&self) -> InterferenceClass {
+        self.class
+    }
+
+    /// Share of busy samples in the history in percent
+    pub fn duty_cycle(&self) -> u32 {
+        self.duty_cycle
+    }
+
+    /// Recommended backoff for a channel of `quality`
+    ///
+    /// Continuous interference gets a long fixed backoff. Intermittent
+    /// interference scales the backoff of the quality by the duty cycle, so
+    /// short bursts are waited out quickly.
+    pub fn backoff_ms(&self, quality: ChannelQuality) -> u32 {
+        match self.class {
+            InterferenceClass::Clear => quality.backoff_ms(),
+            InterferenceClass::Continuous => CONTINUOUS_BACKOFF_MS,
+            InterferenceClass::Bursty | InterferenceClass::Periodic => {
+                (quality.backoff_ms() * self.duty_cycle / 100).max(MIN_BACKOFF_MS)
+            }
+        }
+    }
+
+    /// Forget the history
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.head = 0;
+        self.class = InterferenceClass::Clear;
+        self.duty_cycle = 0;
+    }
+
+    /// Sample `index` of the history, oldest first
+    fn sample(&self, index: usize) -> i32 {
+        let start = (self.head + HISTORY_LEN - self.len) % HISTORY_LEN;
+        self.history[(start + index) % HISTORY_LEN] as i32
+    }
+
+    fn classify(&mut self) {
+        let n = self.len;
+
+        let busy = (0..n)
+            .filter(|&i| self.sample(i) >= self.busy_threshold as i32)
+            .count();
+        self.duty_cycle = (busy * 100 / n) as u32;
+
+        if n < MIN_SAMPLES || busy == 0 {
+            self.class = InterferenceClass::Clear;
+            return;
+        }
+
+        let sum: i32 = (0..n).map(|i| self.sample(i)).sum();
+        let mean = sum / n as i32;
+
+        // Deviations scaled by n keep the integer mean exact
+        let deviation = |i: usize| (self.sample(i) * n as i32 - sum) as i64;
+        let energy: i64 = (0..n).map(|i| deviation(i) * deviation(i)).sum();
+        let variance = energy / (n * n * n) as i64;
+
+        if mean >= self.busy_threshold as i32 && variance < self.variance_threshold as i64 {
+            self.class = InterferenceClass::Continuous;
+            return;
+        }
+
+        // Autocorrelation in percent, normalised by the overlap of each lag
+        let correlation = |lag: usize| {
+            let covariance: i64 = (0..n - lag)
+                .map(|i| deviation(i) * deviation(i + lag))
+                .sum();
+            covariance * 100 * n as i64 / (energy * (n - lag) as i64).max(1)
+        };
+
+        // Lags up to half the history still overlap enough to be meaningful
+        let peak = (1..=n / 2)
+            .map(correlation)
+            .skip_while(|&r| r > 0)
+            .max()
+            .unwrap_or(0);
+
+        self.class = if peak >= self.periodicity_threshold as i64 {
+            InterferenceClass::Periodic
+        } else {
+            InterferenceClass::Bursty
+        };
+    }
+}
+
+impl Default for InterferenceClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(pattern: impl Iterator<Item = i8>) -> InterferenceClassifier {
+        let mut classifier = InterferenceClassifier::new();
+        pattern.for_each(|edv| classifier.push(edv));
+        classifier
+    }
+
+    /// Deterministic ±1 dB jitter
+    fn jitter(i: usize) -> i8 {
+        (i * 7 % 3) as i8 - 1
+    }
+
+    #[test]
+    fn test_classifies_synthetic_edv_patterns() {
+        // Noise floor only
+        let clear = classify((0..HISTORY_LEN).map(|i| -95 + jitter(i)));
+        assert_eq!(clear.class(), InterferenceClass::Clear);
+
+        // Steady interferer
+        let continuous = classify((0..HISTORY_LEN).map(|i| -40 + jitter(i)));
+        assert_eq!(continuous.class(), InterferenceClass::Continuous);
+        assert_eq!(continuous.duty_cycle(), 100);
+
+        // Busy for 2 of every 8 samples
+        let periodic = classify((0..HISTORY_LEN).map(|i| if i % 8 < 2 { -45 } else { -95 }));
+        assert_eq!(periodic.class(), InterferenceClass::Periodic);
+        assert_eq!(periodic.duty_cycle(), 25);
+
+        // Irregular bursts of different lengths
+        let bursts = [1, 2, 9, 10, 11, 19, 27, 28];
+        let bursty =
+            classify((0..HISTORY_LEN).map(|i| if bursts.contains(&i) { -45 } else { -95 }));
+        assert_eq!(bursty.class(), InterferenceClass::Bursty);
+
+        // Too few samples to tell
+        let short = classify((0..MIN_SAMPLES - 1).map(|_| -40));
+        assert_eq!(short.class(), InterferenceClass::Clear);
+    }
+
+    #[test]
+    fn test_backoff_follows_class() {
+        let quality = ChannelQuality::Fair;
+
+        let clear = classify((0..HISTORY_LEN).map(|_| -95));
+        assert_eq!(clear.backoff_ms(quality), quality.backoff_ms());
+
+        let continuous = classify((0..HISTORY_LEN).map(|_| -40));
+        assert_eq!(continuous.backoff_ms(quality), CONTINUOUS_BACKOFF_MS);
+
+        let periodic = classify((0..HISTORY_LEN).map(|i| if i % 8 < 2 { -45 } else { -95 }));
+        assert_eq!(periodic.backoff_ms(quality), quality.backoff_ms() / 4);
+
+        // The history moves on once the interferer stops
+        let mut classifier = continuous;
+        (0..HISTORY_LEN).for_each(|_| classifier.push(-95));
+        assert_eq!(classifier.class(), InterferenceClass::Clear);
+    }
+}
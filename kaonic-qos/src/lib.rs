@@ -1,12 +1,28 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod distance;
+pub mod interference;
 pub mod profile;
+pub mod scheme;
 
-use kaonic_radio::modulation::{
-    Modulation, OfdmMcs, OfdmModulation, OfdmOption, QpskChipFrequency, QpskModulation,
+use core::time::Duration;
+
+use radio_common::modulation::{
+    Modulation, OfdmBandwidthOption, OfdmMcs, OfdmModulation, QpskChipFrequency, QpskModulation,
     QpskRateMode,
 };
 
+pub use clock::Clock;
+#[cfg(feature = "std")]
+pub use clock::StdClock;
+pub use interference::{InterferenceClass, InterferenceClassifier};
+pub use scheme::ParseSchemeError;
+
 /// Modulation scheme with specific parameters
+///
+/// Parses from and displays as a string like `ofdm:mcs3:opt1`, see [`scheme`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ModulationScheme {
     Ofdm(OfdmModulation),
@@ -14,7 +30,7 @@ pub enum ModulationScheme {
 }
 
 impl ModulationScheme {
-    /// Convert to kaonic_radio::Modulation
+    /// Convert to radio_common::Modulation
     pub fn to_modulation(&self) -> Modulation {
         match self {
             ModulationScheme::Ofdm(ofdm) => Modulation::Ofdm(*ofdm),
@@ -74,8 +90,8 @@ pub enum ModulationType {
     Qpsk,
 }
 
-/// Channel quality assessment based on EDV measurements
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Channel quality assessment, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ChannelQuality {
     Excellent, // Very low interference, EDV < -90 dBm
     Good,      // Low interference, EDV < -80 dBm
@@ -95,6 +111,26 @@ impl ChannelQuality {
         }
     }
 
+    /// Next more robust quality level
+    pub fn degrade(&self) -> Self {
+        match self {
+            ChannelQuality::Excellent => ChannelQuality::Good,
+            ChannelQuality::Good => ChannelQuality::Fair,
+            ChannelQuality::Fair => ChannelQuality::Poor,
+            ChannelQuality::Poor | ChannelQuality::Bad => ChannelQuality::Bad,
+        }
+    }
+
+    /// Next faster quality level
+    pub fn improve(&self) -> Self {
+        match self {
+            ChannelQuality::Excellent | ChannelQuality::Good => ChannelQuality::Excellent,
+            ChannelQuality::Fair => ChannelQuality::Good,
+            ChannelQuality::Poor => ChannelQuality::Fair,
+            ChannelQuality::Bad => ChannelQuality::Poor,
+        }
+    }
+
     /// Get recommended backoff time in milliseconds
     pub fn backoff_ms(&self) -> u32 {
         match self {
@@ -121,29 +157,34 @@ impl ChannelQuality {
     pub fn recommended_ofdm(&self, base_power: u8) -> OfdmModulation {
         match self {
             ChannelQuality::Excellent => OfdmModulation {
-                mcs: OfdmMcs::Mcs6,       // Highest data rate (BPSK 1/2)
-                opt: OfdmOption::Option1, // Smallest interleaving, fastest
+                mcs: OfdmMcs::QamC3_4,             // Highest data rate (16-QAM 3/4)
+                opt: OfdmBandwidthOption::Option1, // Widest bandwidth, fastest
                 tx_power: base_power,
+                ..Default::default()
             },
             ChannelQuality::Good => OfdmModulation {
-                mcs: OfdmMcs::Mcs4, // High data rate (QPSK 1/2)
-                opt: OfdmOption::Option2,
+                mcs: OfdmMcs::QpskC3_4, // High data rate (QPSK 3/4)
+                opt: OfdmBandwidthOption::Option2,
                 tx_power: base_power,
+                ..Default::default()
             },
             ChannelQuality::Fair => OfdmModulation {
-                mcs: OfdmMcs::Mcs2,       // Medium data rate (QPSK 1/2)
-                opt: OfdmOption::Option3, // More interleaving for robustness
+                mcs: OfdmMcs::QpskC1_2_2x,         // Medium data rate (QPSK 1/2, 2x)
+                opt: OfdmBandwidthOption::Option3, // Narrower bandwidth for robustness
                 tx_power: base_power + 2,
+                ..Default::default()
             },
             ChannelQuality::Poor => OfdmModulation {
-                mcs: OfdmMcs::Mcs1,       // Low data rate, more robust
-                opt: OfdmOption::Option4, // Maximum interleaving
+                mcs: OfdmMcs::BpskC1_2_2x,         // Low data rate, more robust
+                opt: OfdmBandwidthOption::Option4, // Narrowest bandwidth
                 tx_power: base_power + 4,
+                ..Default::default()
             },
             ChannelQuality::Bad => OfdmModulation {
-                mcs: OfdmMcs::Mcs0,       // Lowest data rate, most robust
-                opt: OfdmOption::Option4, // Maximum interleaving
+                mcs: OfdmMcs::BpskC1_2_4x,         // Lowest data rate, most robust
+                opt: OfdmBandwidthOption::Option4, // Narrowest bandwidth
                 tx_power: base_power + 6,
+                ..Default::default()
             },
         }
     }
@@ -152,33 +193,45 @@ impl ChannelQuality {
     pub fn recommended_qpsk(&self, base_power: u8) -> QpskModulation {
         match self {
             ChannelQuality::Excellent => QpskModulation {
-                chip_freq: QpskChipFrequency::Freq2000, // Highest chip rate
-                mode: QpskRateMode::Mode3,              // Highest data rate
+                fchip: QpskChipFrequency::Fchip2000, // Highest chip rate
+                mode: QpskRateMode::RateMode3,       // Highest data rate
                 tx_power: base_power,
             },
             ChannelQuality::Good => QpskModulation {
-                chip_freq: QpskChipFrequency::Freq1000,
-                mode: QpskRateMode::Mode2,
+                fchip: QpskChipFrequency::Fchip1000,
+                mode: QpskRateMode::RateMode2,
                 tx_power: base_power,
             },
             ChannelQuality::Fair => QpskModulation {
-                chip_freq: QpskChipFrequency::Freq1000,
-                mode: QpskRateMode::Mode1,
+                fchip: QpskChipFrequency::Fchip1000,
+                mode: QpskRateMode::RateMode1,
                 tx_power: base_power + 2,
             },
             ChannelQuality::Poor => QpskModulation {
-                chip_freq: QpskChipFrequency::Freq200,
-                mode: QpskRateMode::Mode1,
+                fchip: QpskChipFrequency::Fchip200,
+                mode: QpskRateMode::RateMode1,
                 tx_power: base_power + 4,
             },
             ChannelQuality::Bad => QpskModulation {
-                chip_freq: QpskChipFrequency::Freq100, // Lowest chip rate, most robust
-                mode: QpskRateMode::Mode0,             // Lowest data rate
+                fchip: QpskChipFrequency::Fchip100, // Lowest chip rate, most robust
+                mode: QpskRateMode::RateMode0,      // Lowest data rate
                 tx_power: base_power + 6,
             },
         }
     }
 
+    /// Modulation family for this quality
+    ///
+    /// O-QPSK at `qpsk_crossover` and worse for its sensitivity at low SNR,
+    /// OFDM above it for throughput.
+    pub fn recommended_modulation_type(&self, qpsk_crossover: ChannelQuality) -> ModulationType {
+        if *self >= qpsk_crossover {
+            ModulationType::Qpsk
+        } else {
+            ModulationType::Ofdm
+        }
+    }
+
     /// Get recommended modulation based on preferred modulation type
     pub fn recommended_modulation(
         &self,
@@ -201,8 +254,8 @@ pub struct ChannelAssessment {
     pub interference_level: i8, // Estimated interference level
     pub quality: ChannelQuality,
     pub sample_count: u32,
-    pub last_rx_time: Option<std::time::Instant>, // Time of last RX frame
-    pub no_rx_timeout: std::time::Duration,       // Timeout to recover quality
+    pub last_rx_time: Option<u64>, // Clock time of last RX frame in milliseconds
+    pub no_rx_timeout: Duration,   // Timeout to recover quality
 }
 
 impl ChannelAssessment {
@@ -215,7 +268,7 @@ impl ChannelAssessment {
             quality: ChannelQuality::Excellent,
             sample_count: 0,
             last_rx_time: None,
-            no_rx_timeout: std::time::Duration::from_secs(5), // Default 10 seconds
+            no_rx_timeout: Duration::from_secs(5),
         }
     }
 
@@ -235,7 +288,7 @@ impl ChannelAssessment {
             );
         } else {
             // EMA with alpha = 0.2
-            self.idle_edv = ((self.idle_edv as i32 * 4 + edv as i32) / 5) as i8;
+            self.idle_edv = edv_step(self.idle_edv, edv, 1, 5);
             self.noise_floor = self.idle_edv.min(self.noise_floor);
         }
 
@@ -252,18 +305,19 @@ impl ChannelAssessment {
         }
     }
 
-    pub fn update_rx(&mut self, edv: i8) {
+    /// Update assessment with EDV of a received frame at clock time `now` (ms)
+    pub fn update_rx(&mut self, edv: i8, now: u64) {
         let old_quality = self.quality;
 
         // Update last RX time
-        self.last_rx_time = Some(std::time::Instant::now());
+        self.last_rx_time = Some(now);
 
         // Use exponential moving average
         if self.sample_count == 0 {
             self.rx_edv = edv;
             log::debug!("QoS: Initial RX EDV = {} dBm", edv);
         } else {
-            self.rx_edv = ((self.rx_edv as i32 * 4 + edv as i32) / 5) as i8;
+            self.rx_edv = edv_step(self.rx_edv, edv, 1, 5);
         }
 
         // Interference is the difference between RX and noise floor
@@ -305,16 +359,16 @@ impl ChannelAssessment {
     }
 
     /// Check if we should recover channel quality due to no RX activity
-    /// Returns true if quality was recovered
-    pub fn check_no_rx_recovery(&mut self) -> bool {
+    /// at clock time `now` (ms). Returns true if quality was recovered
+    pub fn check_no_rx_recovery(&mut self, now: u64) -> bool {
         if let Some(last_rx) = self.last_rx_time {
-            let elapsed = last_rx.elapsed();
+            let elapsed = Duration::from_millis(now.saturating_sub(last_rx));
             if elapsed > self.no_rx_timeout {
                 let old_quality = self.quality;
 
                 // If we haven't received anything, the interference might have cleared
                 // Reset RX EDV to be closer to idle EDV
-                self.rx_edv = ((self.rx_edv as i32 + self.idle_edv as i32 * 3) / 4) as i8;
+                self.rx_edv = edv_step(self.rx_edv, self.idle_edv, 3, 4);
                 self.interference_level = self.rx_edv.saturating_sub(self.noise_floor);
 
                 self.update_quality();
@@ -332,7 +386,7 @@ impl ChannelAssessment {
     }
 
     /// Set the timeout duration for no-RX quality recovery
-    pub fn set_no_rx_timeout(&mut self, timeout: std::time::Duration) {
+    pub fn set_no_rx_timeout(&mut self, timeout: Duration) {
         self.no_rx_timeout = timeout;
         log::debug!(
             "QoS: No-RX recovery timeout set to {} seconds",
@@ -341,40 +395,205 @@ impl ChannelAssessment {
     }
 }
 
+/// Moves `average` towards `target` by `num / den` of their difference
+///
+/// The step is rounded to the nearest dB rather than truncated towards zero,
+/// which biased negative EDVs upwards, and is at least 1 dB so the average
+/// can settle on the target. The result lies between both inputs.
+fn edv_step(average: i8, target: i8, num: i32, den: i32) -> i8 {
+    let diff = target as i32 - average as i32;
+    let step = ((diff.abs() * num * 2 + den) / (den * 2)).max(1) * diff.signum();
+
+    (average as i32 + step).clamp(i8::MIN as i32, i8::MAX as i32) as i8
+}
+
 impl Default for ChannelAssessment {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Packet error rate over fixed windows of received frames
+#[derive(Debug, Clone)]
+pub struct PacketErrorRate {
+    window: u32,
+    frames: u32,
+    errors: u32,
+}
+
+impl PacketErrorRate {
+    pub fn new(window: u32) -> Self {
+        Self {
+            window: window.max(1),
+            frames: 0,
+            errors: 0,
+        }
+    }
+
+    /// Record a frame, returns the PER in percent when it completes a window
+    pub fn record(&mut self, decoded: bool) -> Option<u32> {
+        self.frames += 1;
+        if !decoded {
+            self.errors += 1;
+        }
+
+        if self.frames < self.window {
+            return None;
+        }
+
+        let per = self.errors * 100 / self.frames;
+        self.frames = 0;
+        self.errors = 0;
+
+        Some(per)
+    }
+}
+
+/// Partial QoS settings update, `None` fields keep their current value
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QoSSettings {
+    pub cca_threshold: Option<i8>,
+    pub adaptive_tx_power: Option<bool>,
+    pub adaptive_backoff: Option<bool>,
+    pub adaptive_modulation: Option<bool>,
+    pub adaptive_modulation_type: Option<bool>,
+    pub qpsk_crossover: Option<ChannelQuality>,
+    pub default_modulation: Option<ModulationScheme>,
+    pub per_threshold: Option<u32>,
+    pub modulation_debounce: Option<Duration>,
+    pub min_rx_rssi: Option<i8>,
+    pub modulation_bounds: Option<ModulationBounds>,
+}
+
+/// Limits on the modulations recommended for the channel quality
+///
+/// Lets an application keep the adaptive modulation within what it can live
+/// with, e.g. never below an MCS for latency or never above a power. The
+/// maximum wins over a minimum above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModulationBounds {
+    /// Most robust OFDM MCS to recommend
+    pub min_mcs: Option<OfdmMcs>,
+    /// Fastest OFDM MCS to recommend
+    pub max_mcs: Option<OfdmMcs>,
+    /// Highest transmit power to recommend, OFDM and QPSK
+    pub max_tx_power: Option<u8>,
+}
+
+impl ModulationBounds {
+    pub fn clamp_ofdm(&self, mut ofdm: OfdmModulation) -> OfdmModulation {
+        if let Some(min) = self.min_mcs.filter(|min| (ofdm.mcs as u8) < *min as u8) {
+            ofdm.mcs = min;
+        }
+
+        if let Some(max) = self.max_mcs.filter(|max| (ofdm.mcs as u8) > *max as u8) {
+            ofdm.mcs = max;
+        }
+
+        ofdm.tx_power = self.clamp_tx_power(ofdm.tx_power);
+        ofdm
+    }
+
+    pub fn clamp_qpsk(&self, mut qpsk: QpskModulation) -> QpskModulation {
+        qpsk.tx_power = self.clamp_tx_power(qpsk.tx_power);
+        qpsk
+    }
+
+    pub fn clamp(&self, scheme: ModulationScheme) -> ModulationScheme {
+        match scheme {
+            ModulationScheme::Ofdm(ofdm) => ModulationScheme::Ofdm(self.clamp_ofdm(ofdm)),
+            ModulationScheme::Qpsk(qpsk) => ModulationScheme::Qpsk(self.clamp_qpsk(qpsk)),
+        }
+    }
+
+    fn clamp_tx_power(&self, power: u8) -> u8 {
+        self.max_tx_power.map_or(power, |max| power.min(max))
+    }
+}
+
 /// QoS Manager with EDV-based channel assessment
-pub struct QoSManager {
+///
+/// Decode failures reported through [`QoSManager::update_decode`] form a
+/// second, PER-based assessment. Modulation follows the more conservative of
+/// the two, so a link with a clean noise floor but heavy multipath still
+/// falls back to a robust scheme.
+///
+/// Idle EDV readings also feed an [`InterferenceClassifier`], which tailors
+/// the backoff to how the interference behaves over time.
+///
+/// Timing comes from a [`Clock`], so the manager also runs without std.
+pub struct QoSManager<C: Clock> {
+    clock: C,
     assessment: ChannelAssessment,
+    interference: InterferenceClassifier,
+    per: PacketErrorRate,
+    per_threshold: u32, // PER in percent above which modulation steps down
+    per_quality: ChannelQuality,
     cca_threshold: i8, // Clear Channel Assessment threshold in dBm
     adaptive_tx_power: bool,
     adaptive_backoff: bool,
     adaptive_modulation: bool,
+    adaptive_modulation_type: bool,
+    qpsk_crossover: ChannelQuality, // Quality at which the family switches to QPSK
     modulation_type: ModulationType,
     default_modulation: ModulationScheme,
     base_tx_power: u8,
+    modulation_debounce: Duration, // Minimum time between two modulation changes
+    min_rx_rssi: i8,               // Weakest RX EDV that feeds the RX assessment
+    bounds: ModulationBounds,
+    applied_modulation: Option<ModulationScheme>,
+    last_modulation_change: Option<u64>,
 }
 
-impl QoSManager {
+/// Frames per PER measurement unless configured otherwise
+pub const DEFAULT_PER_WINDOW: u32 = 20;
+
+/// Minimum time between modulation changes unless configured otherwise
+pub const DEFAULT_MODULATION_DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[cfg(feature = "std")]
+impl QoSManager<StdClock> {
     pub fn new() -> Self {
+        Self::with_clock(StdClock::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for QoSManager<StdClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> QoSManager<C> {
+    pub fn with_clock(clock: C) -> Self {
         log::debug!("QoS: Creating new QoS Manager with default settings");
         Self {
+            clock,
             assessment: ChannelAssessment::new(),
+            interference: InterferenceClassifier::new(),
+            per: PacketErrorRate::new(DEFAULT_PER_WINDOW),
+            per_threshold: 10,
+            per_quality: ChannelQuality::Excellent,
             cca_threshold: -75, // Default CCA threshold
             adaptive_tx_power: true,
             adaptive_backoff: true,
             adaptive_modulation: true,
+            adaptive_modulation_type: false,
+            qpsk_crossover: ChannelQuality::Poor,
             modulation_type: ModulationType::Ofdm,
             default_modulation: ModulationScheme::Ofdm(OfdmModulation {
-                mcs: OfdmMcs::Mcs3,
-                opt: OfdmOption::Option2,
+                mcs: OfdmMcs::QpskC1_2,
+                opt: OfdmBandwidthOption::Option2,
                 tx_power: 10,
+                ..Default::default()
             }),
             base_tx_power: 10,
+            modulation_debounce: DEFAULT_MODULATION_DEBOUNCE,
+            min_rx_rssi: i8::MIN,
+            bounds: ModulationBounds::default(),
+            applied_modulation: None,
+            last_modulation_change: None,
         }
     }
 
@@ -411,6 +630,26 @@ impl QoSManager {
         self
     }
 
+    /// Let the channel quality pick the modulation family as well
+    ///
+    /// Overrides the configured modulation type while adaptive modulation is
+    /// on, see [`QoSManager::with_qpsk_crossover`].
+    pub fn enable_adaptive_modulation_type(mut self, enabled: bool) -> Self {
+        log::debug!(
+            "QoS: Adaptive modulation type: {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+        self.adaptive_modulation_type = enabled;
+        self
+    }
+
+    /// Quality at and below which the adaptive modulation type picks QPSK
+    pub fn with_qpsk_crossover(mut self, quality: ChannelQuality) -> Self {
+        log::debug!("QoS: Setting QPSK crossover to {:?}", quality);
+        self.qpsk_crossover = quality;
+        self
+    }
+
     pub fn with_modulation_type(mut self, modulation_type: ModulationType) -> Self {
         log::debug!("QoS: Setting modulation type to {:?}", modulation_type);
         self.modulation_type = modulation_type;
@@ -433,22 +672,168 @@ impl QoSManager {
         self
     }
 
-    pub fn with_no_rx_timeout(mut self, timeout: std::time::Duration) -> Self {
+    /// Step down the modulation when the PER of a window exceeds `percent`
+    pub fn with_per_threshold(mut self, percent: u32) -> Self {
+        log::debug!("QoS: Setting PER threshold to {}%", percent);
+        self.per_threshold = percent;
+        self
+    }
+
+    /// Number of received frames per PER measurement
+    pub fn with_per_window(mut self, frames: u32) -> Self {
+        self.per = PacketErrorRate::new(frames);
+        self
+    }
+
+    /// Minimum time between two modulation changes from [`QoSManager::modulation_change`]
+    pub fn with_modulation_debounce(mut self, interval: Duration) -> Self {
+        log::debug!("QoS: Setting modulation debounce to {:?}", interval);
+        self.modulation_debounce = interval;
+        self
+    }
+
+    /// Keep frames received below `rssi` dBm out of the RX EDV average
+    ///
+    /// Barely decoded or spurious frames otherwise drag the interference
+    /// estimate around on noisy links. They still count as received for the
+    /// no-RX recovery.
+    pub fn with_min_rx_rssi(mut self, rssi: i8) -> Self {
+        log::debug!("QoS: Setting minimum RX RSSI to {} dBm", rssi);
+        self.min_rx_rssi = rssi;
+        self
+    }
+
+    /// Keep the recommended modulations within `bounds`
+    ///
+    /// The default modulation used without adaptive modulation isn't clamped.
+    pub fn with_modulation_bounds(mut self, bounds: ModulationBounds) -> Self {
+        log::debug!("QoS: Setting modulation bounds to {:?}", bounds);
+        self.bounds = bounds;
+        self
+    }
+
+    /// Classify the interference with `classifier`, e.g. with other thresholds
+    pub fn with_interference_classifier(mut self, classifier: InterferenceClassifier) -> Self {
+        self.interference = classifier;
+        self
+    }
+
+    pub fn with_no_rx_timeout(mut self, timeout: Duration) -> Self {
         self.assessment.set_no_rx_timeout(timeout);
         self
     }
 
+    /// Apply a partial settings update while keeping the channel assessment
+    pub fn update_settings(&mut self, settings: QoSSettings) {
+        if let Some(threshold) = settings.cca_threshold {
+            log::debug!("QoS: Updating CCA threshold to {} dBm", threshold);
+            self.cca_threshold = threshold;
+        }
+
+        if let Some(enabled) = settings.adaptive_tx_power {
+            self.adaptive_tx_power = enabled;
+        }
+
+        if let Some(enabled) = settings.adaptive_backoff {
+            self.adaptive_backoff = enabled;
+        }
+
+        if let Some(enabled) = settings.adaptive_modulation {
+            self.adaptive_modulation = enabled;
+        }
+
+        if let Some(enabled) = settings.adaptive_modulation_type {
+            self.adaptive_modulation_type = enabled;
+        }
+
+        if let Some(quality) = settings.qpsk_crossover {
+            log::debug!("QoS: Updating QPSK crossover to {:?}", quality);
+            self.qpsk_crossover = quality;
+        }
+
+        if let Some(percent) = settings.per_threshold {
+            log::debug!("QoS: Updating PER threshold to {}%", percent);
+            self.per_threshold = percent;
+        }
+
+        if let Some(interval) = settings.modulation_debounce {
+            log::debug!("QoS: Updating modulation debounce to {:?}", interval);
+            self.modulation_debounce = interval;
+        }
+
+        if let Some(rssi) = settings.min_rx_rssi {
+            log::debug!("QoS: Updating minimum RX RSSI to {} dBm", rssi);
+            self.min_rx_rssi = rssi;
+        }
+
+        if let Some(bounds) = settings.modulation_bounds {
+            log::debug!("QoS: Updating modulation bounds to {:?}", bounds);
+            self.bounds = bounds;
+        }
+
+        if let Some(modulation) = settings.default_modulation {
+            log::debug!("QoS: Updating default modulation to {:?}", modulation);
+            self.default_modulation = modulation;
+            self.modulation_type = modulation.modulation_type();
+            self.base_tx_power = match modulation {
+                ModulationScheme::Ofdm(ofdm) => ofdm.tx_power,
+                ModulationScheme::Qpsk(qpsk) => qpsk.tx_power,
+            };
+        }
+    }
+
     /// Update with EDV reading during idle state
     pub fn update_idle_edv(&mut self, edv: i8) {
         self.assessment.update_idle(edv);
+        self.interference.push(edv);
 
         // Check if we should recover quality due to no RX activity
-        self.assessment.check_no_rx_recovery();
+        let now = self.clock.current_time();
+        self.assessment.check_no_rx_recovery(now);
     }
 
     /// Update with EDV reading during RX state
+    ///
+    /// Readings below the minimum RX RSSI only mark the frame as received,
+    /// see [`QoSManager::with_min_rx_rssi`].
     pub fn update_rx_edv(&mut self, edv: i8) {
-        self.assessment.update_rx(edv);
+        let now = self.clock.current_time();
+
+        if edv < self.min_rx_rssi {
+            self.assessment.last_rx_time = Some(now);
+            return;
+        }
+
+        self.assessment.update_rx(edv, now);
+    }
+
+    /// Update with the outcome of decoding a received frame (e.g. LDPC)
+    ///
+    /// Every full window above the PER threshold degrades the PER assessment
+    /// by one level, a window at half the threshold or less improves it again.
+    /// The assessment is independent of the EDV one, so it doesn't keep a
+    /// degradation caused by interference which has cleared since.
+    pub fn update_decode(&mut self, decoded: bool) {
+        let Some(per) = self.per.record(decoded) else {
+            return;
+        };
+
+        let old_quality = self.per_quality;
+
+        if per > self.per_threshold {
+            self.per_quality = self.per_quality.degrade();
+        } else if per <= self.per_threshold / 2 {
+            self.per_quality = self.per_quality.improve();
+        }
+
+        if old_quality != self.per_quality {
+            log::info!(
+                "QoS: PER quality changed {:?} -> {:?} (PER: {}%)",
+                old_quality,
+                self.per_quality,
+                per
+            );
+        }
     }
 
     /// Get current channel assessment
@@ -456,15 +841,27 @@ impl QoSManager {
         &self.assessment
     }
 
+    /// Time behaviour of the interference seen while idle
+    pub fn interference(&self) -> InterferenceClass {
+        self.interference.class()
+    }
+
+    /// More conservative of the EDV and the PER based quality
+    pub fn quality(&self) -> ChannelQuality {
+        self.assessment.quality.max(self.per_quality)
+    }
+
     /// Check if channel is clear for transmission
     pub fn can_transmit(&self) -> bool {
         self.assessment.is_clear(self.cca_threshold)
     }
 
     /// Get recommended backoff time before retry
+    ///
+    /// Tailored to the interference class, see [`InterferenceClassifier::backoff_ms`].
     pub fn get_backoff_ms(&self) -> u32 {
         if self.adaptive_backoff {
-            self.assessment.quality.backoff_ms()
+            self.interference.backoff_ms(self.assessment.quality)
         } else {
             0
         }
@@ -479,16 +876,27 @@ impl QoSManager {
         }
     }
 
+    /// Modulation family the recommendations are made for
+    pub fn recommended_modulation_type(&self) -> ModulationType {
+        if self.adaptive_modulation && self.adaptive_modulation_type {
+            self.quality()
+                .recommended_modulation_type(self.qpsk_crossover)
+        } else {
+            self.modulation_type
+        }
+    }
+
     /// Get recommended modulation based on current channel quality
     pub fn get_recommended_modulation(&self) -> ModulationScheme {
         if self.adaptive_modulation {
-            let modulation = self
-                .assessment
-                .quality
-                .recommended_modulation(self.modulation_type, self.base_tx_power);
+            let quality = self.quality();
+            let modulation = self.bounds.clamp(
+                quality
+                    .recommended_modulation(self.recommended_modulation_type(), self.base_tx_power),
+            );
             log::trace!(
                 "QoS: Recommended modulation for {:?} quality: {:?}",
-                self.assessment.quality,
+                quality,
                 modulation
             );
             modulation
@@ -497,24 +905,439 @@ impl QoSManager {
         }
     }
 
-    /// Get modulation as kaonic_radio::Modulation
+    /// Modulation to reprogram the radio with, if it should change now
+    ///
+    /// Returns the current recommendation when it differs from the last one
+    /// returned and at least the debounce interval has passed since that
+    /// change. Recommendations in between are coalesced, only the latest one
+    /// is applied once the interval is over. The first call always returns
+    /// the recommendation.
+    pub fn modulation_change(&mut self) -> Option<ModulationScheme> {
+        let recommended = self.get_recommended_modulation();
+        if self.applied_modulation == Some(recommended) {
+            return None;
+        }
+
+        let now = self.clock.current_time();
+        if let Some(last) = self.last_modulation_change {
+            if Duration::from_millis(now.saturating_sub(last)) < self.modulation_debounce {
+                return None;
+            }
+        }
+
+        log::debug!(
+            "QoS: Changing modulation {:?} -> {:?}",
+            self.applied_modulation,
+            recommended
+        );
+        self.applied_modulation = Some(recommended);
+        self.last_modulation_change = Some(now);
+
+        Some(recommended)
+    }
+
+    /// Get modulation as radio_common::Modulation
     pub fn get_modulation(&self) -> Modulation {
         self.get_recommended_modulation().to_modulation()
     }
 
     /// Get recommended OFDM modulation
     pub fn get_recommended_ofdm(&self) -> OfdmModulation {
-        self.assessment.quality.recommended_ofdm(self.base_tx_power)
+        self.bounds
+            .clamp_ofdm(self.quality().recommended_ofdm(self.base_tx_power))
     }
 
     /// Get recommended QPSK modulation
     pub fn get_recommended_qpsk(&self) -> QpskModulation {
-        self.assessment.quality.recommended_qpsk(self.base_tx_power)
+        self.bounds
+            .clamp_qpsk(self.quality().recommended_qpsk(self.base_tx_power))
     }
 
     /// Reset statistics
     pub fn reset(&mut self) {
         log::debug!("QoS: Resetting channel assessment statistics");
         self.assessment = ChannelAssessment::new();
+        self.interference.reset();
+        self.per = PacketErrorRate::new(self.per.window);
+        self.per_quality = ChannelQuality::Excellent;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+
+    /// Manually advanced clock, usable without std
+    struct TickClock<'a>(&'a Cell<u64>);
+
+    impl Clock for TickClock<'_> {
+        fn current_time(&mut self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn test_no_rx_recovery_after_timeout() {
+        let mut assessment = ChannelAssessment::new();
+
+        assessment.update_idle(-90);
+        for _ in 0..30 {
+            assessment.update_rx(-20, 1_000);
+        }
+        assert_eq!(assessment.quality, ChannelQuality::Poor);
+
+        // Still within the 5 s no-RX timeout
+        assert!(!assessment.check_no_rx_recovery(6_000));
+
+        assert!(assessment.check_no_rx_recovery(6_001));
+        assert_eq!(assessment.quality, ChannelQuality::Excellent);
+    }
+
+    #[test]
+    fn test_edv_average_stays_in_bounds() {
+        let edges = [i8::MIN, i8::MIN + 1, -1, 0, 1, i8::MAX - 1, i8::MAX];
+
+        for &start in &edges {
+            for &sample in &edges {
+                let mut assessment = ChannelAssessment::new();
+                assessment.update_idle(start);
+                assessment.update_rx(start, 0);
+
+                for _ in 0..300 {
+                    let (idle, rx) = (assessment.idle_edv, assessment.rx_edv);
+                    assessment.update_idle(sample);
+                    assessment.update_rx(sample, 0);
+
+                    assert!(assessment.idle_edv >= idle.min(sample));
+                    assert!(assessment.idle_edv <= idle.max(sample));
+                    assert!(assessment.rx_edv >= rx.min(sample));
+                    assert!(assessment.rx_edv <= rx.max(sample));
+
+                    let interference = assessment.rx_edv as i32 - assessment.noise_floor as i32;
+                    assert_eq!(
+                        assessment.interference_level as i32,
+                        interference.clamp(i8::MIN as i32, i8::MAX as i32)
+                    );
+                }
+
+                // Settles exactly on a constant input
+                assert_eq!(assessment.idle_edv, sample);
+                assert_eq!(assessment.rx_edv, sample);
+
+                let sir = assessment.get_sir_db(i8::MAX);
+                assert_eq!(sir, i8::MAX.saturating_sub(sample));
+                assert_eq!(
+                    assessment.get_sir_db(i8::MIN),
+                    i8::MIN.saturating_sub(sample)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_edv_average_rounding_is_symmetric() {
+        // Truncation towards zero used to leave the average 1 dB high forever
+        assert_eq!(edv_step(-99, -100, 1, 5), -100);
+        assert_eq!(edv_step(-101, -100, 1, 5), -100);
+
+        assert_eq!(edv_step(-100, -90, 1, 5), -98);
+        assert_eq!(edv_step(-90, -100, 1, 5), -92);
+        assert_eq!(edv_step(i8::MIN, i8::MAX, 1, 5), i8::MIN + 51);
+        assert_eq!(edv_step(i8::MAX, i8::MIN, 1, 5), i8::MAX - 51);
+        assert_eq!(edv_step(i8::MIN, i8::MAX, 3, 4), i8::MAX - 64);
+        assert_eq!(edv_step(-50, -50, 1, 5), -50);
+    }
+
+    #[test]
+    fn test_manager_uses_clock() {
+        let now = Cell::new(0);
+        let mut qos =
+            QoSManager::with_clock(TickClock(&now)).with_no_rx_timeout(Duration::from_secs(1));
+
+        qos.update_idle_edv(-90);
+        for _ in 0..30 {
+            qos.update_rx_edv(-20);
+        }
+        assert_eq!(qos.get_assessment().quality, ChannelQuality::Poor);
+
+        now.set(1_000);
+        qos.update_idle_edv(-90);
+        assert_eq!(qos.get_assessment().quality, ChannelQuality::Poor);
+
+        now.set(1_001);
+        qos.update_idle_edv(-90);
+        assert_eq!(qos.get_assessment().quality, ChannelQuality::Excellent);
+    }
+
+    #[test]
+    fn test_decode_failures_force_fallback() {
+        let now = Cell::new(0);
+        let mut qos = QoSManager::with_clock(TickClock(&now))
+            .with_per_window(10)
+            .with_per_threshold(20);
+
+        qos.update_idle_edv(-90);
+        assert_eq!(qos.get_assessment().quality, ChannelQuality::Excellent);
+        assert_eq!(qos.get_recommended_ofdm().mcs, OfdmMcs::QamC3_4);
+
+        // Clean noise floor, but half of the frames fail to decode
+        for i in 0..20 {
+            qos.update_decode(i % 2 == 0);
+        }
+
+        assert_eq!(qos.get_assessment().quality, ChannelQuality::Excellent);
+        assert_eq!(qos.quality(), ChannelQuality::Fair);
+        assert_eq!(qos.get_recommended_ofdm().mcs, OfdmMcs::QpskC1_2_2x);
+
+        // EDV worse than PER wins
+        for _ in 0..30 {
+            qos.update_rx_edv(-20);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Poor);
+
+        // An error free window steps back up
+        qos.reset();
+        qos.update_idle_edv(-90);
+        for i in 0..10 {
+            qos.update_decode(i >= 3);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Good);
+        for _ in 0..10 {
+            qos.update_decode(true);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Excellent);
+    }
+
+    #[test]
+    fn test_decode_failures_do_not_latch_edv_quality() {
+        let now = Cell::new(0);
+        let mut qos = QoSManager::with_clock(TickClock(&now))
+            .with_per_window(10)
+            .with_no_rx_timeout(Duration::from_secs(1));
+
+        qos.update_idle_edv(-90);
+        for _ in 0..30 {
+            qos.update_rx_edv(-20);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Poor);
+
+        // Failures during the interference degrade the PER assessment one level
+        for _ in 0..10 {
+            qos.update_decode(false);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Poor);
+
+        // Once the interference clears, the PER assessment alone remains
+        for t in 2..8 {
+            now.set(t * 1_000);
+            qos.update_idle_edv(-90);
+        }
+        assert_eq!(qos.get_assessment().quality, ChannelQuality::Excellent);
+        assert_eq!(qos.quality(), ChannelQuality::Good);
+    }
+
+    #[test]
+    fn test_recommendations_within_bounds() {
+        let now = Cell::new(0);
+        let mut qos = QoSManager::with_clock(TickClock(&now))
+            .with_per_window(10)
+            .with_base_tx_power(10)
+            .with_modulation_bounds(ModulationBounds {
+                min_mcs: Some(OfdmMcs::QpskC1_2_2x),
+                max_mcs: Some(OfdmMcs::QpskC3_4),
+                max_tx_power: Some(12),
+            });
+
+        qos.update_idle_edv(-90);
+
+        // Every failed window degrades the quality by one level
+        let expected = [
+            (ChannelQuality::Excellent, OfdmMcs::QpskC3_4, 10),
+            (ChannelQuality::Good, OfdmMcs::QpskC3_4, 10),
+            (ChannelQuality::Fair, OfdmMcs::QpskC1_2_2x, 12),
+            (ChannelQuality::Poor, OfdmMcs::QpskC1_2_2x, 12),
+            (ChannelQuality::Bad, OfdmMcs::QpskC1_2_2x, 12),
+        ];
+        for (quality, mcs, tx_power) in expected {
+            assert_eq!(qos.quality(), quality);
+
+            let ofdm = qos.get_recommended_ofdm();
+            assert_eq!((ofdm.mcs, ofdm.tx_power), (mcs, tx_power), "{:?}", quality);
+            assert_eq!(qos.get_recommended_qpsk().tx_power, tx_power, "{:?}", quality);
+            assert_eq!(
+                qos.get_recommended_modulation(),
+                ModulationScheme::Ofdm(ofdm),
+                "{:?}",
+                quality
+            );
+
+            for _ in 0..10 {
+                qos.update_decode(false);
+            }
+        }
+
+        // Without bounds the recommendation is left alone
+        qos.update_settings(QoSSettings {
+            modulation_bounds: Some(ModulationBounds::default()),
+            ..Default::default()
+        });
+        let ofdm = qos.get_recommended_ofdm();
+        assert_eq!((ofdm.mcs, ofdm.tx_power), (OfdmMcs::BpskC1_2_4x, 16));
+    }
+
+    #[test]
+    fn test_modulation_type_follows_quality() {
+        let now = Cell::new(0);
+        let mut qos = QoSManager::with_clock(TickClock(&now))
+            .with_per_window(10)
+            .enable_adaptive_modulation_type(true)
+            .with_qpsk_crossover(ChannelQuality::Poor);
+
+        qos.update_idle_edv(-90);
+
+        // Every failed window degrades the quality by one level
+        let expected = [
+            (ChannelQuality::Excellent, ModulationType::Ofdm),
+            (ChannelQuality::Good, ModulationType::Ofdm),
+            (ChannelQuality::Fair, ModulationType::Ofdm),
+            (ChannelQuality::Poor, ModulationType::Qpsk),
+            (ChannelQuality::Bad, ModulationType::Qpsk),
+        ];
+        for (quality, modulation_type) in expected {
+            assert_eq!(qos.quality(), quality);
+            assert_eq!(
+                qos.get_recommended_modulation().modulation_type(),
+                modulation_type,
+                "{:?}",
+                quality
+            );
+
+            for _ in 0..10 {
+                qos.update_decode(false);
+            }
+        }
+
+        // A lower crossover keeps OFDM on a poor link
+        qos.update_settings(QoSSettings {
+            qpsk_crossover: Some(ChannelQuality::Bad),
+            ..Default::default()
+        });
+        qos.reset();
+        qos.update_idle_edv(-90);
+        for _ in 0..30 {
+            qos.update_decode(false);
+        }
+        assert_eq!(qos.quality(), ChannelQuality::Poor);
+        assert_eq!(qos.recommended_modulation_type(), ModulationType::Ofdm);
+
+        // Without the toggle the configured type is kept
+        qos.update_settings(QoSSettings {
+            adaptive_modulation_type: Some(false),
+            qpsk_crossover: Some(ChannelQuality::Good),
+            ..Default::default()
+        });
+        assert_eq!(qos.recommended_modulation_type(), ModulationType::Ofdm);
+    }
+
+    #[test]
+    fn test_modulation_changes_are_debounced() {
+        let now = Cell::new(0);
+        let mut qos = QoSManager::with_clock(TickClock(&now))
+            .with_per_window(1)
+            .with_modulation_debounce(Duration::from_secs(1));
+
+        qos.update_idle_edv(-90);
+
+        // Every decode result flips the recommendation between two levels
+        let mut changes = 0;
+        let mut last: Option<(u64, ModulationScheme)> = None;
+        for t in (0..3_500).step_by(10) {
+            now.set(t);
+            qos.update_decode(t % 20 == 0);
+            if let Some(modulation) = qos.modulation_change() {
+                match last {
+                    Some((last_t, _)) => assert!(t - last_t >= 1_000, "change at {}", t),
+                    None => assert_eq!(t, 0),
+                }
+                last = Some((t, modulation));
+                changes += 1;
+            }
+        }
+        assert!(changes >= 3);
+
+        // Once settled, the latest recommendation is applied after the interval
+        qos.update_decode(false);
+        let settled = qos.get_recommended_modulation();
+        let (last_t, applied) = last.unwrap();
+        assert_ne!(applied, settled);
+
+        now.set(last_t + 999);
+        assert_eq!(qos.modulation_change(), None);
+        now.set(last_t + 1_000);
+        assert_eq!(qos.modulation_change(), Some(settled));
+        now.set(last_t + 5_000);
+        assert_eq!(qos.modulation_change(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_update_settings_keeps_assessment() {
+        let mut qos = QoSManager::new();
+
+        qos.update_idle_edv(-90);
+        qos.update_idle_edv(-88);
+        qos.update_rx_edv(-60);
+
+        let before = qos.get_assessment().clone();
+
+        qos.update_settings(QoSSettings {
+            cca_threshold: Some(-80),
+            adaptive_modulation: Some(false),
+            ..Default::default()
+        });
+
+        let after = qos.get_assessment();
+
+        assert_eq!(after.sample_count, before.sample_count);
+        assert_eq!(after.noise_floor, before.noise_floor);
+        assert_eq!(after.rx_edv, before.rx_edv);
+        assert_eq!(after.quality, before.quality);
+
+        assert_eq!(qos.cca_threshold, -80);
+        assert!(!qos.adaptive_modulation);
+        assert!(qos.adaptive_tx_power);
+        assert!(qos.adaptive_backoff);
+    }
+
+    #[test]
+    fn test_weak_frames_skip_rx_edv() {
+        let now = Cell::new(0);
+        let mut qos = QoSManager::with_clock(TickClock(&now)).with_min_rx_rssi(-95);
+
+        qos.update_idle_edv(-100);
+        for _ in 0..50 {
+            qos.update_rx_edv(-70);
+        }
+        assert_eq!(qos.get_assessment().rx_edv, -70);
+
+        // Weak frames leave the estimate alone but still count as received
+        for (t, edv) in [(100, -110), (200, -100), (300, -96)] {
+            now.set(t);
+            qos.update_rx_edv(edv);
+            assert_eq!(qos.get_assessment().rx_edv, -70);
+            assert_eq!(qos.get_assessment().last_rx_time, Some(t));
+        }
+
+        qos.update_rx_edv(-95);
+        assert_eq!(qos.get_assessment().rx_edv, -75);
+
+        qos.update_settings(QoSSettings {
+            min_rx_rssi: Some(i8::MIN),
+            ..Default::default()
+        });
+        qos.update_rx_edv(-110);
+        assert_eq!(qos.get_assessment().rx_edv, -82);
     }
 }
@@ -46,15 +46,15 @@ impl Metrics {
 
     /// Update packet error rate (0-100)
     pub fn update_per(&mut self, errors: u32, total: u32) {
-        if total > 0 {
-            self.per = ((errors * 100) / total) as i32;
+        if let Some(per) = (errors * 100).checked_div(total) {
+            self.per = per as i32;
         }
     }
 
     /// Get link quality score (0-100, higher is better)
     pub fn quality_score(&self) -> u32 {
-        let rssi_score = ((self.rssi + 100).max(0).min(50) * 2) as u32;
-        let sir_score = ((self.sir + 10).max(0).min(50) * 2) as u32;
+        let rssi_score = ((self.rssi + 100).clamp(0, 50) * 2) as u32;
+        let sir_score = ((self.sir + 10).clamp(0, 50) * 2) as u32;
         let per_score = (100 - self.per).max(0) as u32;
 
         // Weighted average: RSSI 30%, SIR 40%, PER 30%
@@ -0,0 +1,315 @@
+//! Human readable modulation schemes for CLI arguments and config files
+//!
+//! A scheme is a colon separated list starting with the modulation type:
+//!
+//! - `ofdm:mcs<0-6>:opt<0-3>`, e.g. `ofdm:mcs3:opt1` for bandwidth option 2
+//! - `qpsk:<100|200|1000|2000>:mode<0-4>`, e.g. `qpsk:2000:mode3` (chip rate in kchip/s)
+//!
+//! Both accept a trailing `tx<power>`, OFDM also `pdt<threshold>` and `lfo`
+//! for reception with low frequency offset. Omitted
+//! fields keep the modulation defaults. Numbers are the register values, so the
+//! OFDM bandwidth options count from 0 like in the gRPC API and config files.
+
+use core::fmt;
+use core::str::FromStr;
+
+use radio_common::modulation::{
+    OfdmBandwidthOption, OfdmMcs, OfdmModulation, QpskChipFrequency, QpskModulation, QpskRateMode,
+};
+
+use crate::ModulationScheme;
+
+/// Error returned when a modulation scheme string can't be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseSchemeError {
+    /// The type is neither `ofdm` nor `qpsk`
+    UnknownType,
+    /// A required field is missing
+    MissingField(&'static str),
+    /// A field is malformed or out of range
+    InvalidField(&'static str),
+}
+
+impl fmt::Display for ParseSchemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseSchemeError::UnknownType => {
+                write!(f, "unknown modulation type, expected ofdm or qpsk")
+            }
+            ParseSchemeError::MissingField(field) => write!(f, "missing {}", field),
+            ParseSchemeError::InvalidField(field) => write!(f, "invalid {}", field),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseSchemeError {}
+
+fn parse_field<T: FromStr>(
+    value: &str,
+    prefix: &str,
+    field: &'static str,
+) -> Result<T, ParseSchemeError> {
+    value
+        .strip_prefix(prefix)
+        .and_then(|v| v.parse().ok())
+        .ok_or(ParseSchemeError::InvalidField(field))
+}
+
+/// OFDM MCS with the index `mcs<index>` stands for, 0 to 6
+pub fn ofdm_mcs(index: u8) -> Option<OfdmMcs> {
+    match index {
+        0 => Some(OfdmMcs::BpskC1_2_4x),
+        1 => Some(OfdmMcs::BpskC1_2_2x),
+        2 => Some(OfdmMcs::QpskC1_2_2x),
+        3 => Some(OfdmMcs::QpskC1_2),
+        4 => Some(OfdmMcs::QpskC3_4),
+        5 => Some(OfdmMcs::QamC1_2),
+        6 => Some(OfdmMcs::QamC3_4),
+        _ => None,
+    }
+}
+
+fn parse_ofdm<'a>(
+    mut fields: impl Iterator<Item = &'a str>,
+) -> Result<OfdmModulation, ParseSchemeError> {
+    let mut ofdm = OfdmModulation::default();
+
+    let mcs = fields.next().ok_or(ParseSchemeError::MissingField("mcs"))?;
+    ofdm.mcs = ofdm_mcs(parse_field::<u8>(mcs, "mcs", "mcs")?)
+        .ok_or(ParseSchemeError::InvalidField("mcs"))?;
+
+    let opt = fields.next().ok_or(ParseSchemeError::MissingField("opt"))?;
+    ofdm.opt = match parse_field::<u8>(opt, "opt", "opt")? {
+        0 => OfdmBandwidthOption::Option1,
+        1 => OfdmBandwidthOption::Option2,
+        2 => OfdmBandwidthOption::Option3,
+        3 => OfdmBandwidthOption::Option4,
+        _ => return Err(ParseSchemeError::InvalidField("opt")),
+    };
+
+    for field in fields {
+        if field.starts_with("tx") {
+            ofdm.tx_power = parse_field(field, "tx", "tx power")?;
+        } else if field.starts_with("pdt") {
+            ofdm.pdt = parse_field(field, "pdt", "pdt")?;
+        } else if field == "lfo" {
+            ofdm.lfo = true;
+        } else {
+            return Err(ParseSchemeError::InvalidField("option"));
+        }
+    }
+
+    Ok(ofdm)
+}
+
+fn parse_qpsk<'a>(
+    mut fields: impl Iterator<Item = &'a str>,
+) -> Result<QpskModulation, ParseSchemeError> {
+    let mut qpsk = QpskModulation::default();
+
+    let fchip = fields
+        .next()
+        .ok_or(ParseSchemeError::MissingField("chip frequency"))?;
+    qpsk.fchip = match parse_field::<u32>(fchip, "", "chip frequency")? {
+        100 => QpskChipFrequency::Fchip100,
+        200 => QpskChipFrequency::Fchip200,
+        1000 => QpskChipFrequency::Fchip1000,
+        2000 => QpskChipFrequency::Fchip2000,
+        _ => return Err(ParseSchemeError::InvalidField("chip frequency")),
+    };
+
+    let mode = fields
+        .next()
+        .ok_or(ParseSchemeError::MissingField("mode"))?;
+    qpsk.mode = match parse_field::<u8>(mode, "mode", "mode")? {
+        0 => QpskRateMode::RateMode0,
+        1 => QpskRateMode::RateMode1,
+        2 => QpskRateMode::RateMode2,
+        3 => QpskRateMode::RateMode3,
+        4 => QpskRateMode::RateMode4,
+        _ => return Err(ParseSchemeError::InvalidField("mode")),
+    };
+
+    for field in fields {
+        if field.starts_with("tx") {
+            qpsk.tx_power = parse_field(field, "tx", "tx power")?;
+        } else {
+            return Err(ParseSchemeError::InvalidField("option"));
+        }
+    }
+
+    Ok(qpsk)
+}
+
+impl FromStr for ModulationScheme {
+    type Err = ParseSchemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.trim().split(':').map(str::trim);
+
+        match fields.next().unwrap_or_default() {
+            kind if kind.eq_ignore_ascii_case("ofdm") => {
+                parse_ofdm(fields).map(ModulationScheme::Ofdm)
+            }
+            kind if kind.eq_ignore_ascii_case("qpsk") => {
+                parse_qpsk(fields).map(ModulationScheme::Qpsk)
+            }
+            _ => Err(ParseSchemeError::UnknownType),
+        }
+    }
+}
+
+impl fmt::Display for ModulationScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModulationScheme::Ofdm(ofdm) => {
+                write!(
+                    f,
+                    "ofdm:mcs{}:opt{}:tx{}",
+                    ofdm.mcs as u8,
+                    ofdm.opt as u8,
+                    ofdm.tx_power
+                )?;
+
+                if ofdm.pdt != OfdmModulation::default().pdt {
+                    write!(f, ":pdt{}", ofdm.pdt)?;
+                }
+
+                if ofdm.lfo {
+                    write!(f, ":lfo")?;
+                }
+
+                Ok(())
+            }
+            ModulationScheme::Qpsk(qpsk) => {
+                let fchip = match qpsk.fchip {
+                    QpskChipFrequency::Fchip100 => 100,
+                    QpskChipFrequency::Fchip200 => 200,
+                    QpskChipFrequency::Fchip1000 => 1000,
+                    QpskChipFrequency::Fchip2000 => 2000,
+                };
+
+                write!(
+                    f,
+                    "qpsk:{}:mode{}:tx{}",
+                    fchip, qpsk.mode as u8, qpsk.tx_power
+                )
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_schemes() {
+        let ofdm: ModulationScheme = "ofdm:mcs3:opt1".parse().unwrap();
+        assert_eq!(
+            ofdm,
+            ModulationScheme::Ofdm(OfdmModulation {
+                mcs: OfdmMcs::QpskC1_2,
+                opt: OfdmBandwidthOption::Option2,
+                ..Default::default()
+            })
+        );
+
+        let qpsk: ModulationScheme = "QPSK:2000:mode3:tx14".parse().unwrap();
+        assert_eq!(
+            qpsk,
+            ModulationScheme::Qpsk(QpskModulation {
+                fchip: QpskChipFrequency::Fchip2000,
+                mode: QpskRateMode::RateMode3,
+                tx_power: 14,
+            })
+        );
+
+        let ofdm: ModulationScheme = "ofdm:mcs0:opt3:pdt5:tx3".parse().unwrap();
+        let ModulationScheme::Ofdm(ofdm) = ofdm else {
+            panic!("expected ofdm");
+        };
+        assert_eq!(ofdm.pdt, 5);
+        assert_eq!(ofdm.tx_power, 3);
+        assert!(!ofdm.lfo);
+
+        let ofdm: ModulationScheme = "ofdm:mcs3:opt1:lfo".parse().unwrap();
+        let ModulationScheme::Ofdm(ofdm) = ofdm else {
+            panic!("expected ofdm");
+        };
+        assert!(ofdm.lfo);
+    }
+
+    #[test]
+    fn test_parse_invalid_schemes() {
+        let parse = |s: &str| s.parse::<ModulationScheme>().unwrap_err();
+
+        assert_eq!(parse(""), ParseSchemeError::UnknownType);
+        assert_eq!(parse("fsk:50"), ParseSchemeError::UnknownType);
+        assert_eq!(parse("ofdm"), ParseSchemeError::MissingField("mcs"));
+        assert_eq!(parse("ofdm:mcs3"), ParseSchemeError::MissingField("opt"));
+        assert_eq!(
+            parse("ofdm:mcs7:opt1"),
+            ParseSchemeError::InvalidField("mcs")
+        );
+        assert_eq!(
+            parse("ofdm:mcs3:opt4"),
+            ParseSchemeError::InvalidField("opt")
+        );
+        assert_eq!(parse("ofdm:3:opt1"), ParseSchemeError::InvalidField("mcs"));
+        assert_eq!(
+            parse("ofdm:mcs3:opt1:tx300"),
+            ParseSchemeError::InvalidField("tx power")
+        );
+        assert_eq!(
+            parse("ofdm:mcs3:opt1:fast"),
+            ParseSchemeError::InvalidField("option")
+        );
+        assert_eq!(
+            parse("qpsk:500:mode1"),
+            ParseSchemeError::InvalidField("chip frequency")
+        );
+        assert_eq!(
+            parse("qpsk:1000:mode5"),
+            ParseSchemeError::InvalidField("mode")
+        );
+        assert_eq!(
+            parse("qpsk:1000:mode1:pdt3"),
+            ParseSchemeError::InvalidField("option")
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let schemes = [
+            ModulationScheme::Ofdm(OfdmModulation::default()),
+            ModulationScheme::Ofdm(OfdmModulation {
+                mcs: OfdmMcs::BpskC1_2_4x,
+                opt: OfdmBandwidthOption::Option4,
+                pdt: 0x07,
+                tx_power: 0,
+                lfo: true,
+            }),
+            ModulationScheme::Qpsk(QpskModulation::default()),
+            ModulationScheme::Qpsk(QpskModulation {
+                fchip: QpskChipFrequency::Fchip2000,
+                mode: QpskRateMode::RateMode4,
+                tx_power: 31,
+            }),
+        ];
+
+        for scheme in schemes {
+            let text = scheme.to_string();
+            assert_eq!(text.parse::<ModulationScheme>(), Ok(scheme), "{}", text);
+        }
+
+        assert_eq!(
+            "ofdm:mcs3:opt1"
+                .parse::<ModulationScheme>()
+                .unwrap()
+                .to_string(),
+            "ofdm:mcs3:opt1:tx10"
+        );
+    }
+}
@@ -11,6 +11,20 @@ pub enum KaonicError {
     NotSupported,
     DataCorruption,
     TryAgain,
+    ChannelBusy,
+    BufferOverrun,
+    /// [`crate::radio::Radio::bringup`] failed at the step
+    Bringup(BringupStep),
+}
+
+/// Step of the startup sequence of a transceiver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BringupStep {
+    Reset,     // Pulling the reset line
+    Wakeup,    // Waiting for the transceiver to come out of reset
+    TrxOff,    // Checking it settled in TRXOFF
+    Configure, // Programming the board setup and the current settings
+    Receive,   // Entering RX
 }
 
 impl From<FrameError> for KaonicError {
@@ -0,0 +1,122 @@
+use radio_common::{frequency::BandwidthFilter, RadioConfig};
+
+/// Levels of the front end control lines of a Kaonic 1S module, `true` is high
+///
+/// | Line   | GPIO           | Function                                     |
+/// |--------|----------------|----------------------------------------------|
+/// | FLT_V1 | `*-flt-sel-v1` | sub-GHz filter select, together with FLT_V2  |
+/// | FLT_V2 | `*-flt-sel-v2` | sub-GHz filter select, together with FLT_V1  |
+/// | FLT_24 | `*-flt-sel-24` | 2.4 GHz filter select, low in automatic mode |
+/// | ANT_24 | `*-ant-sel-24` | 2.4 GHz antenna switch, not on every board   |
+///
+/// FLT_V1 high with FLT_V2 low selects the wideband sub-GHz filter, which also
+/// serves 902-928 MHz. FLT_V1 low with FLT_V2 high selects the 862-876 MHz SAW
+/// filter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FemPins {
+    pub flt_v1: bool,
+    pub flt_v2: bool,
+    pub flt_24: bool,
+    pub ant_24: bool,
+}
+
+impl FemPins {
+    /// Wideband sub-GHz filter, the 2.4 GHz lines low
+    pub const WIDEBAND: Self = Self {
+        flt_v1: true,
+        flt_v2: false,
+        flt_24: false,
+        ant_24: false,
+    };
+
+    /// 862-876 MHz SAW filter, the 2.4 GHz lines low
+    pub const NARROW_868: Self = Self {
+        flt_v1: false,
+        flt_v2: true,
+        flt_24: false,
+        ant_24: false,
+    };
+
+    /// Lines for `config` picked from its frequency and bandwidth filter
+    ///
+    /// A narrow filter is only fitted for 862-876 MHz, other frequencies get
+    /// the wideband one.
+    pub fn automatic(config: &RadioConfig) -> Self {
+        let freq = config.freq.as_mhz();
+
+        match config.bandwidth_filter {
+            BandwidthFilter::Narrow if (862..=876).contains(&freq) => Self::NARROW_868,
+            _ => Self::WIDEBAND,
+        }
+    }
+}
+
+/// How the front end of a module is set up
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FemPath {
+    /// Follows the frequency and bandwidth filter of each configuration
+    #[default]
+    Auto,
+    /// Holds the lines whatever the configuration, e.g. to test an antenna
+    Manual(FemPins),
+}
+
+impl FemPath {
+    /// Lines the front end is set to for `config`
+    pub fn pins(&self, config: &RadioConfig) -> FemPins {
+        match self {
+            FemPath::Auto => FemPins::automatic(config),
+            FemPath::Manual(pins) => *pins,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use radio_common::{Hertz, RadioConfigBuilder};
+
+    use super::*;
+
+    fn config(mhz: u64, bandwidth_filter: BandwidthFilter) -> RadioConfig {
+        let mut config = RadioConfigBuilder::new().build();
+        config.freq = Hertz::from_mhz(mhz);
+        config.bandwidth_filter = bandwidth_filter;
+        config
+    }
+
+    #[test]
+    fn test_automatic_pins_follow_frequency() {
+        let auto = FemPath::Auto;
+
+        assert_eq!(
+            auto.pins(&config(869, BandwidthFilter::Narrow)),
+            FemPins::NARROW_868
+        );
+        assert_eq!(
+            auto.pins(&config(869, BandwidthFilter::Wide)),
+            FemPins::WIDEBAND
+        );
+        assert_eq!(
+            auto.pins(&config(915, BandwidthFilter::Narrow)),
+            FemPins::WIDEBAND
+        );
+        assert_eq!(
+            auto.pins(&config(433, BandwidthFilter::Narrow)),
+            FemPins::WIDEBAND
+        );
+    }
+
+    #[test]
+    fn test_manual_path_overrides_frequency() {
+        let pins = FemPins {
+            flt_v1: false,
+            flt_v2: true,
+            flt_24: true,
+            ant_24: true,
+        };
+        let manual = FemPath::Manual(pins);
+
+        assert_eq!(manual.pins(&config(915, BandwidthFilter::Wide)), pins);
+        assert_eq!(manual.pins(&config(2450, BandwidthFilter::Narrow)), pins);
+    }
+}
@@ -0,0 +1,142 @@
+use radio_common::{
+    frequency::BandwidthFilter, Hertz, RadioChannel, RadioConfig, RadioConfigBuilder,
+};
+
+/// Legal channel raster of a regional band
+///
+/// Channel `n` is centered at `freq + n * channel_spacing`, which matches the
+/// way the RF215 derives its carrier from `RadioConfig`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyPlan {
+    pub name: &'static str,
+    /// Lower edge of the band
+    pub band_start: Hertz,
+    /// Upper edge of the band
+    pub band_end: Hertz,
+    /// Center frequency of channel 0
+    pub freq: Hertz,
+    pub channel_spacing: Hertz,
+    pub channel_count: u16,
+}
+
+/// EU 863-870 MHz SRD band
+pub const EU_868: FrequencyPlan = FrequencyPlan {
+    name: "EU 868",
+    band_start: Hertz::from_mhz(863),
+    band_end: Hertz::from_mhz(870),
+    freq: Hertz::from_khz(863_100),
+    channel_spacing: Hertz::from_khz(200),
+    channel_count: 35,
+};
+
+/// US 902-928 MHz ISM band
+pub const US_915: FrequencyPlan = FrequencyPlan {
+    name: "US 915",
+    band_start: Hertz::from_mhz(902),
+    band_end: Hertz::from_mhz(928),
+    freq: Hertz::from_khz(902_100),
+    channel_spacing: Hertz::from_khz(200),
+    channel_count: 130,
+};
+
+/// 2400-2483.5 MHz ISM band with the IEEE 802.15.4 channel raster
+pub const ISM_2400: FrequencyPlan = FrequencyPlan {
+    name: "2.4 GHz",
+    band_start: Hertz::from_mhz(2400),
+    band_end: Hertz::from_khz(2_483_500),
+    freq: Hertz::from_mhz(2405),
+    channel_spacing: Hertz::from_mhz(5),
+    channel_count: 16,
+};
+
+/// Presets offered to clients
+pub const FREQUENCY_PLANS: [FrequencyPlan; 3] = [EU_868, US_915, ISM_2400];
+
+impl FrequencyPlan {
+    /// Returns the preset whose channel raster `config` is tuned on
+    pub fn matching(config: &RadioConfig) -> Option<&'static FrequencyPlan> {
+        FREQUENCY_PLANS
+            .iter()
+            .find(|plan| plan.freq == config.freq && plan.channel_spacing == config.channel_spacing)
+    }
+
+    /// Returns the center frequency of `channel` or `None` if it's outside the plan
+    pub fn channel_to_freq(&self, channel: RadioChannel) -> Option<Hertz> {
+        if channel >= self.channel_count {
+            return None;
+        }
+
+        Some(Hertz::new(
+            self.freq.as_hz() + channel as u64 * self.channel_spacing.as_hz(),
+        ))
+    }
+
+    /// Builds a radio configuration tuned to `channel` of this plan
+    pub fn radio_config(
+        &self,
+        channel: RadioChannel,
+        bandwidth_filter: BandwidthFilter,
+    ) -> Option<RadioConfig> {
+        self.channel_to_freq(channel)?;
+
+        Some(
+            RadioConfigBuilder::new()
+                .freq(self.freq)
+                .channel_spacing(self.channel_spacing)
+                .channel(channel)
+                .bandwidth_filter(bandwidth_filter)
+                .build(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channels_within_band() {
+        for plan in FREQUENCY_PLANS.iter() {
+            let half_spacing = plan.channel_spacing.as_hz() / 2;
+
+            for channel in 0..plan.channel_count {
+                let center = plan.channel_to_freq(channel).unwrap().as_hz();
+
+                assert!(
+                    center - half_spacing >= plan.band_start.as_hz(),
+                    "{} channel {} below band",
+                    plan.name,
+                    channel
+                );
+                assert!(
+                    center + half_spacing <= plan.band_end.as_hz(),
+                    "{} channel {} above band",
+                    plan.name,
+                    channel
+                );
+            }
+
+            assert_eq!(plan.channel_to_freq(plan.channel_count), None);
+        }
+    }
+
+    #[test]
+    fn test_channel_to_freq() {
+        assert_eq!(EU_868.channel_to_freq(0), Some(Hertz::from_khz(863_100)));
+        assert_eq!(EU_868.channel_to_freq(34), Some(Hertz::from_khz(869_900)));
+        assert_eq!(ISM_2400.channel_to_freq(15), Some(Hertz::from_mhz(2480)));
+
+        let config = US_915
+            .radio_config(10, BandwidthFilter::Wide)
+            .expect("legal channel");
+        assert_eq!(config.freq, US_915.freq);
+        assert_eq!(config.channel, 10);
+        assert!(US_915.radio_config(130, BandwidthFilter::Wide).is_none());
+
+        assert_eq!(FrequencyPlan::matching(&config), Some(&US_915));
+        assert_eq!(
+            FrequencyPlan::matching(&RadioConfigBuilder::new().build()),
+            None
+        );
+    }
+}
@@ -1,3 +1,9 @@
 pub mod error;
+pub mod fem;
+pub mod frequency_plan;
 pub mod platform;
+pub mod power;
 pub mod radio;
+pub mod regulatory;
+pub mod spi;
+pub mod thermal;
@@ -1,9 +1,12 @@
-use std::sync::{atomic::AtomicUsize, Arc};
+use std::{
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
 
-use linux_embedded_hal::spidev::SpidevOptions;
+use linux_embedded_hal::spidev::{SpiModeFlags, SpidevOptions};
 use radio_common::{modulation::OfdmModulation, Hertz, Modulation, RadioConfigBuilder};
 use radio_rf215::{
-    bus::{Bus, BusError, SpiBus},
+    bus::{Bus, BusError, BusRetryPolicy, SpiBus},
     error::RadioError,
     radio::{AgcGainMap, AuxiliarySettings, FrontendPinConfig, PaVol},
     regs::{BasebandInterrupt, BasebandInterruptMask, RadioInterrupt, RadioInterruptMask},
@@ -11,15 +14,24 @@ use radio_rf215::{
     PadOutputDrive, Rf215,
 };
 
-use crate::platform::{
-    kaonic1s::{Kaonic1SRadio, Kaonic1SRadioEvent, Kaonic1SRadioFem},
-    linux::{
-        LinuxClock, LinuxGpioConfig, LinuxGpioInterrupt, LinuxGpioLineConfig, LinuxGpioReset,
-        LinuxOutputPin, LinuxSpi, LinuxSpiConfig, SharedBus,
+use crate::{
+    platform::{
+        kaonic1s::{Kaonic1SRadio, Kaonic1SRadioEvent, Kaonic1SRadioFem},
+        linux::{
+            LinuxClock, LinuxGpioConfig, LinuxGpioInterrupt, LinuxGpioLineConfig, LinuxGpioReset,
+            LinuxOutputPin, LinuxSpi, LinuxSpiConfig, SharedBus,
+        },
+        linux_rf215::AtomicInterrupt,
     },
-    linux_rf215::AtomicInterrupt,
+    spi::{SpiMode, SpiSettings},
 };
 
+/// AGC gain map of the Kaonic 1S front end, its external LNA adds 12 dB
+pub(super) const FEM_AGC_GAIN_MAP: AgcGainMap = AgcGainMap::Extranal12dB;
+
+/// Transient SPI glitches are retried before a register access fails
+const SPI_RETRY_POLICY: BusRetryPolicy = BusRetryPolicy::new(3, Duration::from_micros(500));
+
 struct RadioBusConfig {
     name: &'static str,
     rst_gpio: LinuxGpioConfig,
@@ -133,7 +145,8 @@ const RADIO_CONFIG_REV_B: [RadioBusConfig; 2] = [
 
 const RADIO_CONFIG_REV_C: [RadioBusConfig; 2] = RADIO_CONFIG_REV_B;
 
-pub fn create_radios() -> Result<[Option<Kaonic1SRadio>; 2], BusError> {
+/// Sets up both radios, a radio that doesn't come up leaves its bus error
+pub fn create_radios(spi: &SpiSettings) -> [Result<Kaonic1SRadio, BusError>; 2] {
     // Read machine configuration from /etc/kaonic/kaonic_machine
     let machine_config = match std::fs::read_to_string("/etc/kaonic/kaonic_machine") {
         Ok(content) => content.trim().to_string(),
@@ -162,22 +175,14 @@ pub fn create_radios() -> Result<[Option<Kaonic1SRadio>; 2], BusError> {
         }
     };
 
-    let mut radios: [Option<Kaonic1SRadio>; 2] = [None, None];
-
-    // Create radios based on selected configuration
-    for (index, config) in radio_configs.iter().enumerate() {
-        match create_radio(index, config) {
-            Ok(radio) => {
-                radios[index] = Some(radio);
-            }
-            Err(_e) => {
-                log::error!("failed to create radio {}", config.name);
-                // Continue with other radios even if one fails
-            }
-        }
-    }
+    // Create radios based on selected configuration, one failing doesn't
+    // keep the other from coming up
+    core::array::from_fn(|index| {
+        let config = &radio_configs[index];
 
-    Ok(radios)
+        create_radio(index, config, spi)
+            .inspect_err(|e| log::error!("failed to create radio {}: {}", config.name, e))
+    })
 }
 
 fn configure_radio_09<I: Bus + Clone>(
@@ -190,7 +195,7 @@ fn configure_radio_09<I: Bus + Clone>(
             aven: false,
             avect: false,
             pavol: PaVol::Voltage2400mV,
-            map: AgcGainMap::Extranal12dB,
+            map: FEM_AGC_GAIN_MAP,
         })
         .map_err(|_| BusError::ControlFailure)?;
 
@@ -211,7 +216,7 @@ fn configure_radio_24<I: Bus + Clone>(
             aven: false,
             avect: false,
             pavol: PaVol::Voltage2400mV,
-            map: AgcGainMap::Extranal12dB,
+            map: FEM_AGC_GAIN_MAP,
         })
         .map_err(|_| BusError::ControlFailure)?;
 
@@ -220,7 +225,11 @@ fn configure_radio_24<I: Bus + Clone>(
     Ok(())
 }
 
-fn configure_radio<I: Bus + Clone>(rf: &mut Rf215<I>, index: usize) -> Result<(), RadioError> {
+/// Board setup of a freshly reset RF215, leaves it receiving on the defaults
+pub(super) fn configure_radio<I: Bus + Clone>(
+    rf: &mut Rf215<I>,
+    index: usize,
+) -> Result<(), RadioError> {
     rf.set_config(&radio_rf215::RfConfig {
         output_drive: PadOutputDrive::Drive8mA,
         irq_active_low: false,
@@ -258,12 +267,29 @@ fn configure_radio<I: Bus + Clone>(rf: &mut Rf215<I>, index: usize) -> Result<()
     Ok(())
 }
 
-fn create_radio(index: usize, config: &RadioBusConfig) -> Result<Kaonic1SRadio, BusError> {
+fn spi_mode_flags(mode: SpiMode) -> SpiModeFlags {
+    match mode {
+        SpiMode::Mode0 => SpiModeFlags::SPI_MODE_0,
+        SpiMode::Mode1 => SpiModeFlags::SPI_MODE_1,
+        SpiMode::Mode2 => SpiModeFlags::SPI_MODE_2,
+        SpiMode::Mode3 => SpiModeFlags::SPI_MODE_3,
+    }
+}
+
+fn create_radio(
+    index: usize,
+    config: &RadioBusConfig,
+    settings: &SpiSettings,
+) -> Result<Kaonic1SRadio, BusError> {
     let mut spi = LinuxSpi::open(&config.spi.path).map_err(|_| BusError::ControlFailure)?;
 
+    let max_speed = settings.speed(config.spi.max_speed);
+    log::info!("{} spi: {} Hz, {:?}", config.name, max_speed, settings.mode);
+
     spi.configure(
         &SpidevOptions::new()
-            .max_speed_hz(config.spi.max_speed)
+            .max_speed_hz(max_speed)
+            .mode(spi_mode_flags(settings.mode))
             .build(),
     )
     .map_err(|_| BusError::ControlFailure)?;
@@ -285,7 +311,7 @@ fn create_radio(index: usize, config: &RadioBusConfig) -> Result<Kaonic1SRadio,
     let clock = LinuxClock::new();
 
     // Create the bus with all interfaces
-    let bus = SpiBus::new(spi, interrupt_atomic, clock, reset_gpio);
+    let bus = SpiBus::new(spi, interrupt_atomic, clock, reset_gpio).with_retry(SPI_RETRY_POLICY);
 
     let bus = std::sync::Arc::new(std::sync::Mutex::new(bus));
 
@@ -328,5 +354,5 @@ fn create_radio(index: usize, config: &RadioBusConfig) -> Result<Kaonic1SRadio,
         ant_24,
     );
 
-    Ok(Kaonic1SRadio::new(radio, radio_event, fem))
+    Ok(Kaonic1SRadio::new(index, radio, radio_event, fem))
 }
@@ -4,40 +4,69 @@ use std::{
 };
 
 use kaonic_frame::frame::Frame;
-use radio_common::{
-    frequency::BandwidthFilter, modulation::OfdmModulation, Hertz, Modulation, RadioConfig,
-    RadioConfigBuilder,
-};
+use radio_common::{modulation::OfdmModulation, Modulation, RadioConfig, RadioConfigBuilder};
 use radio_rf215::{
-    baseband::BasebandFrame,
-    bus::{BusInterrupt, SpiBus},
+    baseband::{BasebandFrame, FCS_LEN},
+    bus::{BusError, BusInterrupt, SpiBus},
     Rf215,
 };
 
 use crate::{
-    error::KaonicError,
+    error::{BringupStep, KaonicError},
+    fem::FemPath,
     platform::{
-        kaonic1s::machine::create_radios,
+        kaonic1s::machine::{configure_radio, create_radios, FEM_AGC_GAIN_MAP},
         linux::{
             LinuxClock, LinuxGpioInterrupt, LinuxGpioReset, LinuxOutputPin, LinuxSpi, SharedBus,
         },
         linux_rf215::AtomicInterrupt,
     },
-    radio::{Radio, ReceiveResult, ScanResult},
+    power::TxPowerLimit,
+    radio::{
+        frame_timeout, AddressFilter, AgcGainMap, AutoAck, CcaMode, EnergyCca, IrqCounters,
+        PartNumber, PhaseMeasurement, Radio, ReceiveResult, ResetKind, ScanResult, TransmitReport,
+        TransmitResult, TxTurnaround, DEFAULT_RX_RESTORE_TIMEOUT, MAX_TX_RETRIES,
+    },
+    regulatory::LbtParams,
+    spi::SpiSettings,
+    thermal::ThermalZone,
 };
 
 mod machine;
 
 pub const FRAME_SIZE: usize = 2048usize;
 
+/// Time between two RSSI readings of a channel scan
+const SCAN_SAMPLE_INTERVAL: core::time::Duration = core::time::Duration::from_micros(500);
+
+/// STM32MP1 SoC thermal zone, the closest sensor to the RF215 transceivers
+const THERMAL_ZONE_TYPE: &str = "cpu-thermal";
+
+/// Retries after the first transmit attempt unless configured otherwise
+pub const DEFAULT_TX_RETRIES: u8 = 3;
+
+/// Time the RF215 gets to raise its WAKEUP IRQ after a reset
+const WAKEUP_TIMEOUT: core::time::Duration = core::time::Duration::from_millis(10);
+
 pub type Kaonic1SBus = SpiBus<LinuxSpi, AtomicInterrupt, LinuxClock, LinuxGpioReset>;
 
+/// Filter and antenna switches in front of an RF215, see [`FemPins`] for
+/// the lines
 #[derive(Debug)]
 pub struct Kaonic1SRadioFem {
     flt_v1: LinuxOutputPin,
     flt_v2: LinuxOutputPin,
     flt_24: LinuxOutputPin,
     ant_24: Option<LinuxOutputPin>,
+    path: FemPath,
+}
+
+fn set_pin(pin: &mut LinuxOutputPin, high: bool) -> Result<(), KaonicError> {
+    if high {
+        pin.set_high()
+    } else {
+        pin.set_low()
+    }
 }
 
 impl Kaonic1SRadioFem {
@@ -52,60 +81,44 @@ impl Kaonic1SRadioFem {
             flt_v2,
             flt_24,
             ant_24,
+            path: FemPath::Auto,
         }
     }
 
-    fn set_bandwidth_filter(
-        &mut self,
-        filter: BandwidthFilter,
-        freq: Hertz,
-    ) -> Result<(), KaonicError> {
-        let freq = freq.as_mhz();
-
-        match filter {
-            BandwidthFilter::Narrow => {
-                log::debug!("set narrowband filter");
-
-                if (902 <= freq) && (freq <= 928) {
-                    self.flt_v1.set_high()?;
-                    self.flt_v2.set_low()?;
-                }
-
-                if (862 <= freq) && (freq <= 876) {
-                    self.flt_v1.set_low()?;
-                    self.flt_v2.set_high()?;
-                }
-
-                // Use Wideband filter
-                if freq < 862 {
-                    log::trace!(
-                        "narrow band is not supported for {}MHz, wideband will be used",
-                        freq
-                    );
-
-                    self.flt_v1.set_high()?;
-                    self.flt_v2.set_low()?;
-                }
-            }
-            BandwidthFilter::Wide => {
-                log::debug!("set wideband filter");
-                self.flt_v1.set_high()?;
-                self.flt_v2.set_low()?;
+    /// Overrides the automatic setup from the next [`Kaonic1SRadioFem::adjust`]
+    ///
+    /// Fails with `NotSupported` for a manual path switching ANT_24 on a
+    /// board without it.
+    pub fn set_path(&mut self, path: FemPath) -> Result<(), KaonicError> {
+        if let FemPath::Manual(pins) = path {
+            if pins.ant_24 && self.ant_24.is_none() {
+                return Err(KaonicError::NotSupported);
             }
         }
 
+        self.path = path;
+
         Ok(())
     }
 
+    pub fn path(&self) -> FemPath {
+        self.path
+    }
+
     pub fn adjust(&mut self, config: &RadioConfig) -> Result<(), KaonicError> {
+        let pins = self.path.pins(config);
+
+        log::debug!("set fem {:?} ({:?})", pins, self.path);
+
         if let Some(ant_24) = &mut self.ant_24 {
-            ant_24.set_low()?;
+            set_pin(ant_24, pins.ant_24)?;
         }
 
-        self.set_bandwidth_filter(config.bandwidth_filter, config.freq)?;
+        set_pin(&mut self.flt_v1, pins.flt_v1)?;
+        set_pin(&mut self.flt_v2, pins.flt_v2)?;
 
-        // NOTE: Should be set to 0
-        let _ = self.flt_24.set_low();
+        // NOTE: Should be set to 0 unless overridden
+        let _ = set_pin(&mut self.flt_24, pins.flt_24);
 
         Ok(())
     }
@@ -138,6 +151,7 @@ impl Kaonic1SRadioEvent {
 }
 
 pub struct Kaonic1SRadio {
+    index: usize,
     fem: Kaonic1SRadioFem,
     radio: Kaonic1SRf215,
     event: Arc<Mutex<Kaonic1SRadioEvent>>,
@@ -145,23 +159,56 @@ pub struct Kaonic1SRadio {
 
     config: RadioConfig,
     modulation: Modulation,
+    power_limit: TxPowerLimit,
+    thermal: Option<ThermalZone>,
+
+    battery_low: bool,
+    battery_tx_inhibit: bool,
+    battery_threshold_mv: Option<u16>,
+
+    tx_retries: u8,
+    rx_restore_timeout: core::time::Duration,
+    last_transmit: TransmitReport,
+    min_off_time: core::time::Duration,
+    last_tx_end: Option<Instant>,
+
+    address_filter: Option<AddressFilter>,
+    auto_ack: Option<AutoAck>,
+    hardware_fcs: bool,
+    agc_gain_map: AgcGainMap,
 
     noise_dbm: i8,
 }
 
 impl Kaonic1SRadio {
     pub fn new(
+        index: usize,
         radio: Rf215<SharedBus<Kaonic1SBus>>,
         event: Kaonic1SRadioEvent,
         fem: Kaonic1SRadioFem,
     ) -> Self {
         Self {
+            index,
             radio,
             event: Arc::new(Mutex::new(event)),
             fem,
             bb_frame: BasebandFrame::new(),
             config: RadioConfigBuilder::new().build(),
             modulation: Modulation::Ofdm(OfdmModulation::default()),
+            power_limit: TxPowerLimit::default(),
+            thermal: ThermalZone::find(THERMAL_ZONE_TYPE),
+            battery_low: false,
+            battery_tx_inhibit: false,
+            battery_threshold_mv: None,
+            tx_retries: DEFAULT_TX_RETRIES,
+            rx_restore_timeout: DEFAULT_RX_RESTORE_TIMEOUT,
+            last_transmit: TransmitReport::default(),
+            min_off_time: core::time::Duration::ZERO,
+            last_tx_end: None,
+            address_filter: None,
+            auto_ack: None,
+            hardware_fcs: false,
+            agc_gain_map: FEM_AGC_GAIN_MAP,
             noise_dbm: -127,
         }
     }
@@ -173,6 +220,33 @@ impl Kaonic1SRadio {
     pub fn event(&self) -> Arc<Mutex<Kaonic1SRadioEvent>> {
         self.event.clone()
     }
+
+    /// Programs the settings applied through [`Radio`] again after a reset
+    fn restore_settings(&mut self) -> Result<(), KaonicError> {
+        let config = self.config;
+        self.set_config(&config)?;
+
+        let modulation = self.modulation;
+        self.set_modulation(&modulation)?;
+
+        if let Some(threshold_mv) = self.battery_threshold_mv {
+            self.set_battery_monitor(threshold_mv, self.battery_tx_inhibit)?;
+        }
+
+        self.set_address_filter(self.address_filter)?;
+        self.set_auto_ack(self.auto_ack)?;
+        if self.hardware_fcs {
+            self.set_hardware_fcs(true)?;
+        }
+
+        self.set_agc_gain_map(self.agc_gain_map)?;
+
+        Ok(())
+    }
+
+    pub fn power_limit(&self) -> TxPowerLimit {
+        self.power_limit
+    }
 }
 
 impl Radio for Kaonic1SRadio {
@@ -180,11 +254,13 @@ impl Radio for Kaonic1SRadio {
     type RxFrame = Kaonic1SFrame;
 
     fn set_modulation(&mut self, modulation: &Modulation) -> Result<(), KaonicError> {
+        let modulation = self.power_limit.clamp(self.config.freq, modulation);
+
         log::debug!("set modulation ({}) = {}", self.radio.name(), modulation);
 
-        self.radio.configure(modulation)?;
+        self.radio.configure(&modulation)?;
 
-        self.modulation = *modulation;
+        self.modulation = modulation;
 
         Ok(())
     }
@@ -202,6 +278,12 @@ impl Radio for Kaonic1SRadio {
 
         self.config = *config;
 
+        // Band may have changed, so re-apply the current modulation within its ceiling
+        if self.modulation.tx_power() > self.power_limit.ceiling(config.freq) {
+            let modulation = self.modulation;
+            self.set_modulation(&modulation)?;
+        }
+
         Ok(())
     }
 
@@ -209,27 +291,105 @@ impl Radio for Kaonic1SRadio {
         self.config
     }
 
+    fn set_config_with_modulation(
+        &mut self,
+        config: &RadioConfig,
+        modulation: &Modulation,
+    ) -> Result<(), KaonicError> {
+        self.fem.adjust(config)?;
+
+        let modulation = self.power_limit.clamp(config.freq, modulation);
+
+        log::debug!(
+            "set radio config ({}) = {} with {}",
+            self.radio.name(),
+            config,
+            modulation
+        );
+
+        self.radio.apply(config, &modulation)?;
+
+        self.config = *config;
+        self.modulation = modulation;
+
+        Ok(())
+    }
+
     fn update_event(&mut self) -> Result<(), KaonicError> {
         self.radio
             .update_irqs()
             .map_err(|_| KaonicError::HardwareError)?;
 
+        if self.radio.take_battery_low_irq() && !self.battery_low {
+            log::warn!("battery low ({})", self.radio.name());
+            self.battery_low = true;
+        }
+
+        // The monitor only interrupts on the falling edge, poll for recovery
+        if self.battery_low && !self.radio.is_battery_low()? {
+            log::info!("battery recovered ({})", self.radio.name());
+            self.battery_low = false;
+        }
+
         Ok(())
     }
 
     fn transmit(&mut self, frame: &Self::TxFrame) -> Result<(), KaonicError> {
+        if self.battery_low && self.battery_tx_inhibit {
+            self.last_transmit = TransmitReport {
+                attempts: 0,
+                result: TransmitResult::MaxRetries,
+            };
+
+            return Err(KaonicError::InvalidState);
+        }
+
         let mut result = Ok(());
-        for i in 0..4 {
+        let mut busy = 0u8;
+        let mut attempts = 0u8;
+
+        // The baseband overwrites the last bytes with the FCS
+        let fcs_len = if self.hardware_fcs { FCS_LEN } else { 0 };
+        let mut bb_frame = BasebandFrame::new();
+        let data = bb_frame.as_buffer_mut(frame.len() + fcs_len);
+        if data.len() < frame.len() + fcs_len {
+            self.last_transmit = TransmitReport {
+                attempts: 0,
+                result: TransmitResult::MaxRetries,
+            };
+
+            return Err(KaonicError::PayloadTooBig);
+        }
+        data[..frame.len()].copy_from_slice(frame.as_slice());
+
+        let tx_timeout = frame_timeout(&self.modulation, bb_frame.len());
+        self.radio.set_tx_timeout(tx_timeout);
+
+        // Regulatory minimum off-time since the previous transmission
+        if let Some(wait) = self
+            .last_tx_end
+            .and_then(|end| self.min_off_time.checked_sub(end.elapsed()))
+        {
+            std::thread::sleep(wait);
+        }
+
+        for i in 0..=self.tx_retries {
             let start = Instant::now();
 
-            result = self
-                .radio
-                .bb_transmit(&BasebandFrame::new_from_slice(frame.as_slice()))
-                .map_err(|_| KaonicError::HardwareError);
+            attempts = attempts.saturating_add(1);
+
+            result = self.radio.bb_transmit(&bb_frame).map_err(KaonicError::from);
 
             if result.is_err() {
+                if result == Err(KaonicError::ChannelBusy) {
+                    busy = busy.saturating_add(1);
+                }
+
                 log::error!("tx [{}] {} error", self.radio.name(), i);
-                std::thread::sleep(core::time::Duration::from_millis(4));
+
+                if i < self.tx_retries {
+                    std::thread::sleep(core::time::Duration::from_millis(4));
+                }
             } else {
                 log::debug!(
                     "tx [{}] -) |o| {:>4} bytes {:>4}us",
@@ -242,11 +402,52 @@ impl Radio for Kaonic1SRadio {
             }
         }
 
-        let _ = self.radio.start_receive();
+        self.last_transmit = TransmitReport::new(attempts, busy, &result);
+
+        if result.is_ok() {
+            self.last_tx_end = Some(Instant::now());
+        }
+
+        // The baseband is already back in RX after an automatic turnaround
+        if result.is_err() || !self.radio.tx_auto_rx() {
+            let start = Instant::now();
+
+            // A frame that outlasted the transmit wait is let end rather than cut
+            match self
+                .radio
+                .restore_receive(self.rx_restore_timeout.max(tx_timeout))
+            {
+                Ok(()) => log::trace!(
+                    "rx [{}] re-entered in {}us",
+                    self.radio.name(),
+                    start.elapsed().as_micros()
+                ),
+                Err(err) => log::error!(
+                    "rx [{}] not re-entered after tx: {:?}",
+                    self.radio.name(),
+                    err
+                ),
+            }
+        }
 
         result
     }
 
+    fn transmit_with_modulation(
+        &mut self,
+        frame: &Self::TxFrame,
+        modulation: &Modulation,
+    ) -> Result<(), KaonicError> {
+        // Compare what would actually be applied, the stored modulation is clamped
+        let modulation = self.power_limit.clamp(self.config.freq, modulation);
+
+        if modulation != self.modulation {
+            self.set_modulation(&modulation)?;
+        }
+
+        self.transmit(frame)
+    }
+
     fn receive<'a>(
         &mut self,
         frame: &'a mut Self::RxFrame,
@@ -269,12 +470,13 @@ impl Radio for Kaonic1SRadio {
                     start.elapsed().as_micros(),
                 );
 
-                frame.copy_from_slice(self.bb_frame.as_slice());
+                // Frames with a bad FCS don't make it here
+                let fcs_len = if self.hardware_fcs { FCS_LEN } else { 0 };
+                let len = self.bb_frame.len().saturating_sub(fcs_len);
 
-                Ok(ReceiveResult {
-                    rssi: edv,
-                    len: self.bb_frame.len(),
-                })
+                frame.copy_from_slice(&self.bb_frame.as_slice()[..len])?;
+
+                Ok(ReceiveResult { rssi: edv, len })
             }
             Err(err) => match err {
                 radio_rf215::error::RadioError::Timeout => {
@@ -286,6 +488,11 @@ impl Radio for Kaonic1SRadio {
 
                     return Err(KaonicError::Timeout);
                 }
+                radio_rf215::error::RadioError::BufferOverrun => {
+                    log::warn!("rx buffer overrun {}", self.radio.name());
+
+                    return Err(KaonicError::BufferOverrun);
+                }
                 _ => {
                     log::error!("receive error {}", self.radio.name());
 
@@ -295,23 +502,337 @@ impl Radio for Kaonic1SRadio {
         }
     }
 
-    fn scan(&mut self, _timeout: core::time::Duration) -> Result<ScanResult, KaonicError> {
-        let rssi = self.radio.read_rssi()?;
+    fn scan(&mut self, timeout: core::time::Duration) -> Result<ScanResult, KaonicError> {
+        // Keep the strongest reading over the dwell, a short burst on the
+        // channel would be missed by a single sample
+        let deadline = Instant::now() + timeout;
+        let mut peak = self.radio.read_rssi()?;
+
+        while Instant::now() < deadline {
+            std::thread::sleep(SCAN_SAMPLE_INTERVAL);
+
+            // The RSSI is briefly invalid while the receiver resettles
+            if let Ok(rssi) = self.radio.read_rssi() {
+                peak = peak.max(rssi);
+            }
+        }
+
+        Ok(ScanResult { rssi: peak, snr: 0 })
+    }
+
+    fn read_temperature(&mut self) -> Result<f32, KaonicError> {
+        match &self.thermal {
+            Some(thermal) => thermal.read_celsius(),
+            None => Err(KaonicError::NotSupported),
+        }
+    }
+
+    fn measure_phase(&mut self) -> Result<PhaseMeasurement, KaonicError> {
+        Ok(self.radio.measure_phase()?)
+    }
+
+    fn set_battery_monitor(
+        &mut self,
+        threshold_mv: u16,
+        tx_inhibit: bool,
+    ) -> Result<(), KaonicError> {
+        self.radio
+            .configure_battery_monitor(threshold_mv)
+            .map_err(|_| KaonicError::IncorrectSettings)?;
+
+        self.battery_tx_inhibit = tx_inhibit;
+        self.battery_threshold_mv = Some(threshold_mv);
+        self.battery_low = self.radio.is_battery_low()?;
+
+        Ok(())
+    }
+
+    fn battery_low(&self) -> bool {
+        self.battery_low
+    }
+
+    fn set_tx_turnaround(&mut self, turnaround: TxTurnaround) -> Result<(), KaonicError> {
+        log::debug!(
+            "set tx turnaround ({}) = {:?}",
+            self.radio.name(),
+            turnaround
+        );
+
+        self.radio.set_tx_auto_rx(turnaround == TxTurnaround::Auto);
+
+        Ok(())
+    }
+
+    fn tx_turnaround(&self) -> TxTurnaround {
+        if self.radio.tx_auto_rx() {
+            TxTurnaround::Auto
+        } else {
+            TxTurnaround::Manual
+        }
+    }
+
+    fn set_cca_mode(&mut self, mode: CcaMode) -> Result<(), KaonicError> {
+        log::debug!("set cca mode ({}) = {:?}", self.radio.name(), mode);
+
+        self.radio.set_cca_mode(mode);
+
+        Ok(())
+    }
+
+    fn cca_mode(&self) -> CcaMode {
+        self.radio.cca_mode()
+    }
+
+    fn set_lbt(&mut self, lbt: LbtParams) -> Result<(), KaonicError> {
+        log::debug!("set lbt ({}) = {:?}", self.radio.name(), lbt);
+
+        self.radio.set_energy_cca(lbt.energy);
+        self.min_off_time = lbt.min_off_time;
+
+        Ok(())
+    }
+
+    fn lbt(&self) -> LbtParams {
+        LbtParams {
+            energy: self.radio.energy_cca(),
+            min_off_time: self.min_off_time,
+        }
+    }
+
+    fn flush_receive(&mut self) -> Result<(), KaonicError> {
+        self.radio.flush_receive()?;
+
+        Ok(())
+    }
+
+    fn set_power_limit(&mut self, limit: TxPowerLimit) -> Result<(), KaonicError> {
+        log::debug!(
+            "set tx power limit ({}) = {}/{}",
+            self.radio.name(),
+            limit.band_09,
+            limit.band_24
+        );
+
+        self.power_limit = limit;
+
+        // The current modulation may have been applied under a higher ceiling
+        if self.modulation.tx_power() > self.power_limit.ceiling(self.config.freq) {
+            let modulation = self.modulation;
+            self.set_modulation(&modulation)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_tx_retries(&mut self, retries: u8) -> Result<(), KaonicError> {
+        log::debug!("set tx retries ({}) = {}", self.radio.name(), retries);
 
-        Ok(ScanResult { rssi, snr: 0 })
+        if retries > MAX_TX_RETRIES {
+            return Err(KaonicError::IncorrectSettings);
+        }
+
+        self.tx_retries = retries;
+
+        Ok(())
+    }
+
+    fn set_rx_restore_timeout(&mut self, timeout: core::time::Duration) -> Result<(), KaonicError> {
+        log::debug!(
+            "set rx restore timeout ({}) = {:?}",
+            self.radio.name(),
+            timeout
+        );
+
+        if timeout.is_zero() {
+            return Err(KaonicError::IncorrectSettings);
+        }
+
+        self.rx_restore_timeout = timeout;
+
+        Ok(())
+    }
+
+    fn set_address_filter(&mut self, filter: Option<AddressFilter>) -> Result<(), KaonicError> {
+        log::debug!("set address filter ({}) = {:?}", self.radio.name(), filter);
+
+        if filter.is_none() && self.auto_ack.is_some() {
+            self.set_auto_ack(None)?;
+        }
+
+        self.radio.set_address_filter(filter.as_ref())?;
+        self.address_filter = filter;
+
+        Ok(())
+    }
+
+    fn set_auto_ack(&mut self, ack: Option<AutoAck>) -> Result<(), KaonicError> {
+        log::debug!("set auto-ack ({}) = {:?}", self.radio.name(), ack);
+
+        if ack.is_some() && self.address_filter.is_none() {
+            return Err(KaonicError::IncorrectSettings);
+        }
+
+        self.radio
+            .set_auto_ack(ack.as_ref())
+            .map_err(|_| KaonicError::IncorrectSettings)?;
+        self.auto_ack = ack;
+
+        Ok(())
+    }
+
+    fn reset(&mut self, kind: ResetKind) -> Result<(), KaonicError> {
+        log::warn!("reset ({}) = {:?}", self.radio.name(), kind);
+
+        self.radio.reset()?;
+        configure_radio(&mut self.radio, self.index)?;
+
+        match kind {
+            ResetKind::Soft => self.restore_settings()?,
+            ResetKind::Hard => {
+                self.config = RadioConfigBuilder::new().build();
+                self.modulation = Modulation::Ofdm(OfdmModulation::default());
+                self.power_limit = TxPowerLimit::default();
+                self.battery_low = false;
+                self.battery_tx_inhibit = false;
+                self.battery_threshold_mv = None;
+                self.tx_retries = DEFAULT_TX_RETRIES;
+                self.rx_restore_timeout = DEFAULT_RX_RESTORE_TIMEOUT;
+                self.radio.set_tx_auto_rx(false);
+                self.radio.set_cca_mode(CcaMode::default());
+                self.radio.set_energy_cca(EnergyCca::default());
+                self.min_off_time = core::time::Duration::ZERO;
+                self.address_filter = None;
+                self.auto_ack = None;
+                self.hardware_fcs = false;
+                self.agc_gain_map = FEM_AGC_GAIN_MAP;
+                self.fem.set_path(FemPath::Auto)?;
+                self.fem.adjust(&self.config)?;
+            }
+        }
+
+        self.radio.start_receive()?;
+
+        Ok(())
+    }
+
+    fn bringup(&mut self) -> Result<(), KaonicError> {
+        log::info!("bringup ({})", self.radio.name());
+
+        self.radio
+            .hardware_reset()
+            .map_err(bringup_failed(BringupStep::Reset))?;
+
+        if !self.radio.wait_wakeup(WAKEUP_TIMEOUT) {
+            return Err(bringup_failed(BringupStep::Wakeup)("no WAKEUP IRQ"));
+        }
+
+        let trx_off = self
+            .radio
+            .is_trx_off()
+            .map_err(bringup_failed(BringupStep::TrxOff))?;
+        if !trx_off {
+            return Err(bringup_failed(BringupStep::TrxOff)("not in TRXOFF"));
+        }
+
+        configure_radio(&mut self.radio, self.index)
+            .map_err(bringup_failed(BringupStep::Configure))?;
+        self.restore_settings()
+            .map_err(bringup_failed(BringupStep::Configure))?;
+
+        self.radio
+            .start_receive()
+            .map_err(bringup_failed(BringupStep::Receive))?;
+
+        Ok(())
+    }
+
+    fn last_transmit(&self) -> TransmitReport {
+        self.last_transmit
+    }
+
+    fn irq_counters(&mut self) -> Result<IrqCounters, KaonicError> {
+        Ok(self.radio.irq_counters())
+    }
+
+    fn set_fem_path(&mut self, path: FemPath) -> Result<(), KaonicError> {
+        log::debug!("set fem path ({}) = {:?}", self.radio.name(), path);
+
+        self.fem.set_path(path)?;
+
+        let config = self.config;
+        self.fem.adjust(&config)
+    }
+
+    fn fem_path(&self) -> FemPath {
+        self.fem.path()
+    }
+
+    fn read_tx_power(&mut self) -> Result<u8, KaonicError> {
+        Ok(self.radio.read_tx_power()?)
+    }
+
+    fn set_hardware_fcs(&mut self, enabled: bool) -> Result<(), KaonicError> {
+        // The IQ variant has no baseband core to compute it
+        if self.radio.part_number() == PartNumber::At86Rf215Iq {
+            return Err(KaonicError::NotSupported);
+        }
+
+        self.radio.set_fcs(enabled)?;
+        self.hardware_fcs = enabled;
+
+        Ok(())
+    }
+
+    fn hardware_fcs(&self) -> bool {
+        self.hardware_fcs
+    }
+
+    fn set_agc_gain_map(&mut self, map: AgcGainMap) -> Result<(), KaonicError> {
+        self.radio.set_agc_gain_map(map)?;
+        self.agc_gain_map = map;
+
+        Ok(())
+    }
+
+    fn part_number(&self) -> Option<PartNumber> {
+        Some(self.radio.part_number())
+    }
+}
+
+/// Maps an error of a bring-up step to [`KaonicError::Bringup`], logging it
+fn bringup_failed<E: core::fmt::Debug>(step: BringupStep) -> impl Fn(E) -> KaonicError {
+    move |e| {
+        log::error!("bringup failed at {:?}: {:?}", step, e);
+        KaonicError::Bringup(step)
     }
 }
 
 pub const KAONIC1S_RADIO_COUNT: usize = 2;
 pub struct Kaonic1SMachine {
     radios: [Option<Kaonic1SRadio>; KAONIC1S_RADIO_COUNT],
+    /// Why the radios missing from `radios` didn't come up
+    errors: [Option<BusError>; KAONIC1S_RADIO_COUNT],
 }
 
 impl Kaonic1SMachine {
     pub fn new() -> Result<Self, KaonicError> {
-        let radios = create_radios().map_err(|_| KaonicError::HardwareError)?;
+        Self::with_spi(&SpiSettings::default())
+    }
+
+    /// Sets the radio buses up with `spi` instead of the board defaults
+    pub fn with_spi(spi: &SpiSettings) -> Result<Self, KaonicError> {
+        let radios = create_radios(spi);
+        let errors = core::array::from_fn(|index| radios[index].as_ref().err().copied());
+
+        Ok(Self {
+            radios: radios.map(Result::ok),
+            errors,
+        })
+    }
 
-        Ok(Self { radios })
+    /// Bus error that kept radio `index` from coming up, `None` if it did
+    pub fn radio_error(&self, index: usize) -> Option<BusError> {
+        self.errors.get(index).copied().flatten()
     }
 
     pub fn take_radio(&mut self, index: usize) -> Option<Kaonic1SRadio> {
@@ -8,26 +8,7 @@ use linux_embedded_hal::SpidevDevice;
 
 use crate::error::KaonicError;
 
-#[derive(Debug)]
-pub struct SharedBus<T> {
-    pub(super) bus: std::sync::Arc<std::sync::Mutex<T>>,
-}
-
-impl<T> SharedBus<T> {
-    /// Create a new `SharedDevice`.
-    #[inline]
-    pub fn new(bus: std::sync::Arc<std::sync::Mutex<T>>) -> Self {
-        Self { bus }
-    }
-}
-
-impl<T> Clone for SharedBus<T> {
-    fn clone(&self) -> Self {
-        Self {
-            bus: self.bus.clone(),
-        }
-    }
-}
+pub use super::shared_bus::SharedBus;
 
 #[derive(Debug)]
 pub struct LinuxGpioConfig {
@@ -1,5 +1,4 @@
 use libgpiod::line::Value;
-use radio_rf215::bus::Bus;
 use radio_rf215::bus::BusClock;
 use radio_rf215::bus::BusError;
 use radio_rf215::bus::BusInterrupt;
@@ -13,56 +12,9 @@ use std::time::Instant;
 
 use super::linux::LinuxClock;
 use super::linux::LinuxGpioReset;
-use super::linux::SharedBus;
 use crate::error::KaonicError;
 use crate::platform::linux::LinuxGpioInterrupt;
 
-impl<T: Bus> Bus for SharedBus<T> {
-    #[inline]
-    fn write_regs(
-        &mut self,
-        addr: radio_rf215::regs::RegisterAddress,
-        values: &[radio_rf215::regs::RegisterValue],
-    ) -> Result<(), BusError> {
-        let mut bus = self.bus.lock().unwrap();
-        bus.write_regs(addr, values)
-    }
-
-    #[inline]
-    fn read_regs(
-        &mut self,
-        addr: radio_rf215::regs::RegisterAddress,
-        values: &mut [radio_rf215::regs::RegisterValue],
-    ) -> Result<(), BusError> {
-        let mut bus = self.bus.lock().unwrap();
-        bus.read_regs(addr, values)
-    }
-
-    #[inline]
-    fn wait_interrupt(&mut self, timeout: Option<std::time::Duration>) -> bool {
-        let mut bus = self.bus.lock().unwrap();
-        bus.wait_interrupt(timeout)
-    }
-
-    #[inline]
-    fn delay(&mut self, timeout: std::time::Duration) {
-        let mut bus = self.bus.lock().unwrap();
-        bus.delay(timeout)
-    }
-
-    #[inline]
-    fn current_time(&mut self) -> u64 {
-        let mut bus = self.bus.lock().unwrap();
-        bus.current_time()
-    }
-
-    #[inline]
-    fn hardware_reset(&mut self) -> Result<(), BusError> {
-        let mut bus = self.bus.lock().unwrap();
-        bus.hardware_reset()
-    }
-}
-
 impl BusInterrupt for LinuxGpioInterrupt {
     fn wait_on_interrupt(&mut self, timeout: Option<core::time::Duration>) -> bool {
         if let Ok(status) = self.request.wait_edge_events(timeout) {
@@ -81,13 +33,13 @@ impl BusReset for LinuxGpioReset {
     fn hardware_reset(&mut self) -> Result<(), BusError> {
         self.request
             .set_value(self.line, Value::Active)
-            .map_err(|_| BusError::ControlFailure)?;
+            .map_err(|_| BusError::ResetFailure)?;
 
         std::thread::sleep(std::time::Duration::from_millis(25));
 
         self.request
             .set_value(self.line, Value::InActive)
-            .map_err(|_| BusError::ControlFailure)?;
+            .map_err(|_| BusError::ResetFailure)?;
 
         std::thread::sleep(std::time::Duration::from_millis(25));
 
@@ -150,6 +102,8 @@ impl From<RadioError> for KaonicError {
             RadioError::IncorrectState => Self::HardwareError,
             RadioError::CommunicationFailure => Self::HardwareError,
             RadioError::Timeout => Self::Timeout,
+            RadioError::ChannelBusy => Self::ChannelBusy,
+            RadioError::BufferOverrun => Self::BufferOverrun,
         }
     }
 }
@@ -8,6 +8,8 @@ pub mod linux;
 #[cfg(feature = "machine-kaonic1s")]
 pub mod linux_rf215;
 
+pub mod shared_bus;
+
 #[cfg(feature = "machine-host")]
 #[path = "platform_dummy.rs"]
 mod platform_impl;
@@ -1,38 +1,210 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use kaonic_frame::frame::Frame;
-use radio_common::{modulation::OfdmModulation, Modulation, RadioConfig, RadioConfigBuilder};
+use radio_common::{
+    modulation::OfdmModulation, Modulation, RadioChannel, RadioConfig, RadioConfigBuilder,
+};
+use radio_rf215::regs::RadioInterruptMask;
 
 use crate::{
-    error::KaonicError,
-    radio::{Radio, ReceiveResult, ScanResult},
+    error::{BringupStep, KaonicError},
+    fem::{FemPath, FemPins},
+    power::TxPowerLimit,
+    radio::{
+        CcaMode, IrqCounters, Radio, ReceiveResult, ResetKind, ScanResult, TransmitReport,
+        TransmitResult, TxTurnaround, DEFAULT_RX_RESTORE_TIMEOUT, MAX_TX_RETRIES,
+    },
+    regulatory::LbtParams,
+    spi::SpiSettings,
 };
 
 pub type DummyFrame = Frame<2048>;
 
-pub struct DummyRadioEvent;
+/// Maximum number of transmitted frames waiting to be looped back
+const LOOPBACK_CAPACITY: usize = 32;
+
+/// Settle delay the RF215 driver waits before re-entering RX manually
+const MANUAL_TURNAROUND: Duration = Duration::from_micros(200);
+
+/// Retries after the first attempt until set otherwise
+const DEFAULT_TX_RETRIES: u8 = 3;
+
+/// How often a waiting event thread checks the loopback for frames
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Energy reported by a scan of a channel without simulated traffic, in dBm
+const NOISE_FLOOR: i8 = -100;
+
+type Loopback = Arc<Mutex<VecDeque<DummyFrame>>>;
+
+pub struct DummyRadioEvent {
+    loopback: Loopback,
+}
 
 impl DummyRadioEvent {
     pub fn wait_for_event(&mut self, timeout: Option<core::time::Duration>) -> bool {
-        // No hardware events on the host platform; sleep briefly so the event
-        // thread stays responsive to shutdown without busy-looping.
-        let sleep = timeout.unwrap_or(core::time::Duration::from_millis(100));
-        std::thread::sleep(sleep);
-        false
+        // No hardware events on the host platform; looped back frames are
+        // reported as a receive event as soon as they show up.
+        let deadline = Instant::now() + timeout.unwrap_or(core::time::Duration::from_millis(10));
+
+        loop {
+            std::thread::sleep(EVENT_POLL_INTERVAL);
+
+            if !self.loopback.lock().unwrap().is_empty() {
+                return true;
+            }
+
+            if Instant::now() >= deadline {
+                return false;
+            }
+        }
     }
 }
 
+/// Host radio which loops transmitted frames back to its own receiver
 pub struct DummyRadio {
     event: Arc<Mutex<DummyRadioEvent>>,
+    loopback: Loopback,
+    config: RadioConfig,
+    modulation: Modulation,
+    power_limit: TxPowerLimit,
+    tx_turnaround: TxTurnaround,
+    cca_mode: CcaMode,
+    lbt: LbtParams,
+    last_tx_end: Option<Instant>,
+    rx_ready: Instant,
+    tx_retries: u8,
+    rx_restore_timeout: Duration,
+    rx_restores: u32,
+    busy_attempts: u32,
+    tx_error: Option<KaonicError>,
+    tx_timeout: bool,
+    /// Left in TX by a transmission which never ended
+    tx_stuck: bool,
+    last_transmit: TransmitReport,
+    modulation_changes: u32,
+    rx_overrun: bool,
+    rx_flushes: u32,
+    channel_energy: HashMap<RadioChannel, i8>,
+    bringup_failure: Option<BringupStep>,
+    /// IRQ status the next event update reads
+    irq_status: RadioInterruptMask,
+    irq_counters: IrqCounters,
+    fem_path: FemPath,
 }
 
 impl DummyRadio {
     pub fn new() -> Self {
+        let loopback: Loopback = Arc::new(Mutex::new(VecDeque::new()));
+
         Self {
-            event: Arc::new(Mutex::new(DummyRadioEvent)),
+            event: Arc::new(Mutex::new(DummyRadioEvent {
+                loopback: loopback.clone(),
+            })),
+            loopback,
+            config: RadioConfigBuilder::new().build(),
+            modulation: Modulation::Ofdm(OfdmModulation::default()),
+            power_limit: TxPowerLimit::default(),
+            tx_turnaround: TxTurnaround::Manual,
+            cca_mode: CcaMode::default(),
+            lbt: LbtParams::default(),
+            last_tx_end: None,
+            rx_ready: Instant::now(),
+            tx_retries: DEFAULT_TX_RETRIES,
+            rx_restore_timeout: DEFAULT_RX_RESTORE_TIMEOUT,
+            rx_restores: 0,
+            busy_attempts: 0,
+            tx_error: None,
+            tx_timeout: false,
+            tx_stuck: false,
+            last_transmit: TransmitReport::default(),
+            modulation_changes: 0,
+            rx_overrun: false,
+            rx_flushes: 0,
+            channel_energy: HashMap::new(),
+            bringup_failure: None,
+            irq_status: RadioInterruptMask::new(),
+            irq_counters: IrqCounters::default(),
+            fem_path: FemPath::Auto,
         }
     }
 
+    /// Number of times the modulation was reprogrammed
+    pub fn modulation_changes(&self) -> u32 {
+        self.modulation_changes
+    }
+
+    /// Makes the next `attempts` transmit attempts find the channel busy
+    pub fn simulate_busy_channel(&mut self, attempts: u32) {
+        self.busy_attempts = attempts;
+    }
+
+    /// Makes the next transmit fail with `error` before any attempt, like a
+    /// battery inhibit
+    pub fn simulate_tx_error(&mut self, error: KaonicError) {
+        self.tx_error = Some(error);
+    }
+
+    /// Makes every attempt of the next transmit time out and leave the radio
+    /// in TX, where nothing is received until RX is restored
+    pub fn simulate_tx_timeout(&mut self) {
+        self.tx_timeout = true;
+    }
+
+    /// Makes the next received frame report a receive buffer overrun
+    pub fn simulate_rx_overrun(&mut self) {
+        self.rx_overrun = true;
+    }
+
+    /// Makes the next bring-up fail at `step`
+    pub fn simulate_bringup_failure(&mut self, step: BringupStep) {
+        self.bringup_failure = Some(step);
+    }
+
+    /// Makes the next event update read `irqs` as the IRQ status
+    pub fn simulate_irqs(&mut self, irqs: RadioInterruptMask) {
+        self.irq_status.combine(&irqs);
+    }
+
+    /// Makes scans of `channel` measure `rssi` dBm instead of the noise floor
+    pub fn simulate_channel_energy(&mut self, channel: RadioChannel, rssi: i8) {
+        self.channel_energy.insert(channel, rssi);
+    }
+
+    /// Number of times the receive buffer was flushed
+    pub fn rx_flushes(&self) -> u32 {
+        self.rx_flushes
+    }
+
+    pub fn rx_restore_timeout(&self) -> Duration {
+        self.rx_restore_timeout
+    }
+
+    /// Front end lines a module would drive for the current configuration
+    pub fn fem_pins(&self) -> FemPins {
+        self.fem_path.pins(&self.config)
+    }
+
+    /// Number of times RX was restored after a transmission
+    pub fn rx_restores(&self) -> u32 {
+        self.rx_restores
+    }
+
+    fn restore_receive(&mut self) {
+        self.tx_stuck = false;
+        self.rx_restores += 1;
+
+        // Model the RX gap left after a transmission
+        self.rx_ready = match self.tx_turnaround {
+            TxTurnaround::Auto => Instant::now(),
+            TxTurnaround::Manual => Instant::now() + MANUAL_TURNAROUND,
+        };
+    }
+
     pub fn event(&self) -> Arc<Mutex<DummyRadioEvent>> {
         self.event.clone()
     }
@@ -61,39 +233,239 @@ impl Radio for DummyRadio {
     type RxFrame = DummyFrame;
 
     fn update_event(&mut self) -> Result<(), KaonicError> {
+        // The status is cleared on read
+        self.irq_counters.record(&self.irq_status);
+        self.irq_status.reset();
+
         Ok(())
     }
 
-    fn set_config(&mut self, _config: &RadioConfig) -> Result<(), KaonicError> {
+    fn set_config(&mut self, config: &RadioConfig) -> Result<(), KaonicError> {
+        self.config = *config;
         Ok(())
     }
 
     fn get_config(&self) -> RadioConfig {
-        RadioConfigBuilder::new().build()
+        self.config
     }
 
-    fn set_modulation(&mut self, _modulation: &Modulation) -> Result<(), KaonicError> {
+    fn set_modulation(&mut self, modulation: &Modulation) -> Result<(), KaonicError> {
+        self.modulation = self.power_limit.clamp(self.config.freq, modulation);
+        self.modulation_changes += 1;
         Ok(())
     }
 
     fn get_modulation(&self) -> Modulation {
-        Modulation::Ofdm(OfdmModulation::default())
+        self.modulation
     }
 
-    fn transmit(&mut self, _frame: &Self::TxFrame) -> Result<(), KaonicError> {
-        Err(KaonicError::HardwareError)
+    fn transmit(&mut self, frame: &Self::TxFrame) -> Result<(), KaonicError> {
+        if let Some(error) = self.tx_error.take() {
+            self.last_transmit = TransmitReport {
+                attempts: 0,
+                result: TransmitResult::MaxRetries,
+            };
+
+            return Err(error);
+        }
+
+        let mut result = Err(KaonicError::ChannelBusy);
+        let mut attempts = 0u8;
+        let mut busy = 0u8;
+
+        let timeout = core::mem::take(&mut self.tx_timeout);
+
+        if let Some(wait) = self
+            .last_tx_end
+            .and_then(|end| self.lbt.min_off_time.checked_sub(end.elapsed()))
+        {
+            std::thread::sleep(wait);
+        }
+
+        for _ in 0..=self.tx_retries {
+            attempts = attempts.saturating_add(1);
+
+            if timeout {
+                self.tx_stuck = true;
+                result = Err(KaonicError::Timeout);
+                continue;
+            }
+
+            if self.busy_attempts > 0 {
+                self.busy_attempts -= 1;
+                busy = busy.saturating_add(1);
+                result = Err(KaonicError::ChannelBusy);
+                continue;
+            }
+
+            let mut loopback = self.loopback.lock().unwrap();
+            if loopback.len() >= LOOPBACK_CAPACITY {
+                result = Err(KaonicError::TryAgain);
+                continue;
+            }
+
+            loopback.push_back(*frame);
+            result = Ok(());
+            break;
+        }
+
+        self.last_transmit = TransmitReport::new(attempts, busy, &result);
+
+        if result.is_ok() {
+            self.last_tx_end = Some(Instant::now());
+        }
+
+        self.restore_receive();
+
+        result
     }
 
     fn receive<'a>(
         &mut self,
-        _frame: &'a mut Self::RxFrame,
+        frame: &'a mut Self::RxFrame,
         _timeout: core::time::Duration,
     ) -> Result<ReceiveResult, KaonicError> {
-        Err(KaonicError::HardwareError)
+        if self.tx_stuck {
+            return Err(KaonicError::Timeout);
+        }
+
+        let gap = self.rx_ready.saturating_duration_since(Instant::now());
+        if !gap.is_zero() {
+            std::thread::sleep(gap);
+        }
+
+        match self.loopback.lock().unwrap().pop_front() {
+            // The frame was overwritten while it was read out
+            Some(_) if self.rx_overrun => {
+                self.rx_overrun = false;
+                Err(KaonicError::BufferOverrun)
+            }
+            Some(rx) => {
+                frame.copy_from_slice(rx.as_slice())?;
+
+                Ok(ReceiveResult {
+                    rssi: -40,
+                    len: rx.len(),
+                })
+            }
+            None => Err(KaonicError::Timeout),
+        }
     }
 
     fn scan(&mut self, _timeout: core::time::Duration) -> Result<ScanResult, KaonicError> {
-        Err(KaonicError::HardwareError)
+        let rssi = self
+            .channel_energy
+            .get(&self.config.channel)
+            .copied()
+            .unwrap_or(NOISE_FLOOR);
+
+        Ok(ScanResult { rssi, snr: 0 })
+    }
+
+    fn set_tx_turnaround(&mut self, turnaround: TxTurnaround) -> Result<(), KaonicError> {
+        self.tx_turnaround = turnaround;
+        Ok(())
+    }
+
+    fn tx_turnaround(&self) -> TxTurnaround {
+        self.tx_turnaround
+    }
+
+    fn set_cca_mode(&mut self, mode: CcaMode) -> Result<(), KaonicError> {
+        self.cca_mode = mode;
+        Ok(())
+    }
+
+    fn cca_mode(&self) -> CcaMode {
+        self.cca_mode
+    }
+
+    fn set_lbt(&mut self, lbt: LbtParams) -> Result<(), KaonicError> {
+        self.lbt = lbt;
+        Ok(())
+    }
+
+    fn lbt(&self) -> LbtParams {
+        self.lbt
+    }
+
+    fn flush_receive(&mut self) -> Result<(), KaonicError> {
+        self.rx_flushes += 1;
+        Ok(())
+    }
+
+    fn set_power_limit(&mut self, limit: TxPowerLimit) -> Result<(), KaonicError> {
+        self.power_limit = limit;
+        Ok(())
+    }
+
+    fn set_tx_retries(&mut self, retries: u8) -> Result<(), KaonicError> {
+        if retries > MAX_TX_RETRIES {
+            return Err(KaonicError::IncorrectSettings);
+        }
+
+        self.tx_retries = retries;
+        Ok(())
+    }
+
+    fn set_rx_restore_timeout(&mut self, timeout: Duration) -> Result<(), KaonicError> {
+        if timeout.is_zero() {
+            return Err(KaonicError::IncorrectSettings);
+        }
+
+        self.rx_restore_timeout = timeout;
+        Ok(())
+    }
+
+    fn reset(&mut self, kind: ResetKind) -> Result<(), KaonicError> {
+        // The receive buffer doesn't survive a transceiver reset
+        self.loopback.lock().unwrap().clear();
+
+        if kind == ResetKind::Hard {
+            self.config = RadioConfigBuilder::new().build();
+            self.modulation = Modulation::Ofdm(OfdmModulation::default());
+            self.power_limit = TxPowerLimit::default();
+            self.tx_turnaround = TxTurnaround::Manual;
+            self.cca_mode = CcaMode::default();
+            self.lbt = LbtParams::default();
+            self.tx_retries = DEFAULT_TX_RETRIES;
+            self.rx_restore_timeout = DEFAULT_RX_RESTORE_TIMEOUT;
+            self.fem_path = FemPath::Auto;
+        }
+
+        self.tx_stuck = false;
+        self.rx_ready = Instant::now();
+
+        Ok(())
+    }
+
+    fn bringup(&mut self) -> Result<(), KaonicError> {
+        if let Some(step) = self.bringup_failure.take() {
+            return Err(KaonicError::Bringup(step));
+        }
+
+        self.reset(ResetKind::Soft)
+    }
+
+    fn last_transmit(&self) -> TransmitReport {
+        self.last_transmit
+    }
+
+    fn irq_counters(&mut self) -> Result<IrqCounters, KaonicError> {
+        Ok(self.irq_counters)
+    }
+
+    fn set_fem_path(&mut self, path: FemPath) -> Result<(), KaonicError> {
+        self.fem_path = path;
+        Ok(())
+    }
+
+    fn fem_path(&self) -> FemPath {
+        self.fem_path
+    }
+
+    fn read_tx_power(&mut self) -> Result<u8, KaonicError> {
+        Ok(self.modulation.tx_power())
     }
 }
 
@@ -101,6 +473,248 @@ pub fn create_machine() -> Result<DummyMachine, KaonicError> {
     DummyMachine::new()
 }
 
+/// The host platform has no SPI bus, `spi` is ignored
+pub fn create_machine_with_spi(_spi: &SpiSettings) -> Result<DummyMachine, KaonicError> {
+    DummyMachine::new()
+}
+
 pub type PlatformRadio = DummyRadio;
 pub type PlatformRadioEvent = DummyRadioEvent;
 pub type PlatformRadioFrame = DummyFrame;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transmit_reports_retries() {
+        let mut radio = DummyRadio::new();
+        let frame = DummyFrame::new();
+
+        radio.set_tx_retries(2).unwrap();
+
+        radio.simulate_busy_channel(2);
+        radio.transmit(&frame).unwrap();
+        assert_eq!(
+            radio.last_transmit(),
+            TransmitReport {
+                attempts: 3,
+                result: TransmitResult::Sent,
+            }
+        );
+
+        radio.simulate_busy_channel(3);
+        assert_eq!(radio.transmit(&frame), Err(KaonicError::ChannelBusy));
+        assert_eq!(
+            radio.last_transmit(),
+            TransmitReport {
+                attempts: 3,
+                result: TransmitResult::ChannelBusy,
+            }
+        );
+
+        radio.simulate_busy_channel(1);
+        for _ in 1..LOOPBACK_CAPACITY {
+            radio.transmit(&frame).unwrap();
+        }
+        assert_eq!(radio.transmit(&frame), Err(KaonicError::TryAgain));
+        assert_eq!(
+            radio.last_transmit(),
+            TransmitReport {
+                attempts: 3,
+                result: TransmitResult::MaxRetries,
+            }
+        );
+
+        assert_eq!(
+            radio.set_tx_retries(MAX_TX_RETRIES + 1),
+            Err(KaonicError::IncorrectSettings)
+        );
+    }
+
+    #[test]
+    fn test_transmit_waits_min_off_time() {
+        let mut radio = DummyRadio::new();
+        let frame = DummyFrame::new();
+
+        radio
+            .set_lbt(LbtParams {
+                min_off_time: Duration::from_millis(30),
+                ..Default::default()
+            })
+            .unwrap();
+
+        radio.transmit(&frame).unwrap();
+        let start = Instant::now();
+        radio.transmit(&frame).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(30));
+
+        // A hard reset drops the rules
+        radio.reset(ResetKind::Hard).unwrap();
+        assert_eq!(radio.lbt(), LbtParams::default());
+    }
+
+    #[test]
+    fn test_failed_transmit_restores_receive() {
+        let mut radio = DummyRadio::new();
+        let mut frame = DummyFrame::new();
+        frame.copy_from_slice(b"after timeout").unwrap();
+
+        radio.set_tx_retries(1).unwrap();
+        radio.simulate_tx_timeout();
+        assert_eq!(radio.transmit(&frame), Err(KaonicError::Timeout));
+        assert_eq!(
+            radio.last_transmit(),
+            TransmitReport {
+                attempts: 2,
+                result: TransmitResult::MaxRetries,
+            }
+        );
+        assert_eq!(radio.rx_restores(), 1);
+
+        radio.transmit(&frame).unwrap();
+        assert_eq!(radio.rx_restores(), 2);
+
+        let mut rx = DummyFrame::new();
+        let result = radio.receive(&mut rx, Duration::from_millis(10)).unwrap();
+        assert_eq!(&rx.as_slice()[..result.len], b"after timeout");
+
+        assert_eq!(
+            radio.set_rx_restore_timeout(Duration::ZERO),
+            Err(KaonicError::IncorrectSettings)
+        );
+    }
+
+    #[test]
+    fn test_irq_counters_accumulate_per_read() {
+        use radio_rf215::regs::RadioInterrupt;
+
+        let mut radio = DummyRadio::new();
+
+        radio.simulate_irqs(
+            RadioInterruptMask::new()
+                .add_irq(RadioInterrupt::TransceiverError)
+                .add_irq(RadioInterrupt::TransceiverReady)
+                .build(),
+        );
+        radio.update_event().unwrap();
+        radio.update_event().unwrap();
+
+        radio.simulate_irqs(
+            RadioInterruptMask::new()
+                .add_irq(RadioInterrupt::TransceiverError)
+                .build(),
+        );
+        radio.update_event().unwrap();
+
+        assert_eq!(
+            radio.irq_counters(),
+            Ok(IrqCounters {
+                transceiver_ready: 1,
+                transceiver_error: 2,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_receive_reports_overrun_until_flushed() {
+        let mut radio = DummyRadio::new();
+        let mut rx = DummyFrame::new();
+        let timeout = core::time::Duration::from_millis(1);
+
+        radio
+            .transmit(&DummyFrame::new_from_slice(b"first").unwrap())
+            .unwrap();
+        radio
+            .transmit(&DummyFrame::new_from_slice(b"second").unwrap())
+            .unwrap();
+
+        radio.simulate_rx_overrun();
+        assert_eq!(
+            radio.receive(&mut rx, timeout).map(|r| r.len),
+            Err(KaonicError::BufferOverrun)
+        );
+
+        radio.flush_receive().unwrap();
+        assert_eq!(radio.rx_flushes(), 1);
+
+        assert_eq!(radio.receive(&mut rx, timeout).map(|r| r.len), Ok(6));
+        assert_eq!(rx.as_slice(), b"second");
+    }
+
+    #[test]
+    fn test_per_frame_modulation_reprograms_on_change_only() {
+        let mut radio = DummyRadio::new();
+        let frame = DummyFrame::new();
+
+        let robust = Modulation::Ofdm(OfdmModulation {
+            mcs: radio_common::modulation::OfdmMcs::BpskC1_2_4x,
+            ..Default::default()
+        });
+        let fast = Modulation::Ofdm(OfdmModulation::default());
+
+        // Default modulation is already the fast one
+        for modulation in [fast, robust, robust, fast, fast, fast, robust] {
+            radio.transmit_with_modulation(&frame, &modulation).unwrap();
+            radio
+                .receive(&mut DummyFrame::new(), Duration::ZERO)
+                .unwrap();
+            assert_eq!(radio.get_modulation(), modulation);
+        }
+
+        assert_eq!(radio.modulation_changes(), 3);
+    }
+
+    #[test]
+    fn test_select_best_channel_picks_quietest() {
+        let mut radio = DummyRadio::new();
+        let original = radio.get_config();
+
+        for channel in 0..8 {
+            radio.simulate_channel_energy(channel, -60 - channel as i8);
+        }
+        radio.simulate_channel_energy(5, -95);
+        radio.simulate_channel_energy(6, -95);
+
+        let energies = radio.scan_channels(0..8, Duration::ZERO).unwrap();
+        assert_eq!(energies.len(), 8);
+        assert_eq!(energies[3].rssi, -63);
+        assert_eq!(radio.get_config(), original, "scan restores the channel");
+
+        let best = radio.select_best_channel(0..8, Duration::ZERO).unwrap();
+        assert_eq!(best.channel, 5);
+        assert_eq!(best.rssi, -95);
+        assert_eq!(
+            radio.get_config(),
+            RadioConfig {
+                channel: 5,
+                ..original
+            }
+        );
+
+        assert_eq!(
+            radio.select_best_channel(3..3, Duration::ZERO),
+            Err(KaonicError::IncorrectSettings)
+        );
+    }
+
+    #[test]
+    fn test_bringup_reports_failed_step() {
+        let mut radio = DummyRadio::new();
+
+        for step in [
+            BringupStep::Reset,
+            BringupStep::Wakeup,
+            BringupStep::TrxOff,
+            BringupStep::Configure,
+            BringupStep::Receive,
+        ] {
+            radio.simulate_bringup_failure(step);
+            assert_eq!(radio.bringup(), Err(KaonicError::Bringup(step)));
+        }
+
+        // A failure is reported once, the next attempt comes up
+        assert_eq!(radio.bringup(), Ok(()));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     error::KaonicError,
     platform::kaonic1s::{Kaonic1SFrame, Kaonic1SMachine, Kaonic1SRadio, Kaonic1SRadioEvent},
+    spi::SpiSettings,
 };
 
 pub mod kaonic1s;
@@ -9,6 +10,10 @@ pub fn create_machine() -> Result<Kaonic1SMachine, KaonicError> {
     Kaonic1SMachine::new()
 }
 
+pub fn create_machine_with_spi(spi: &SpiSettings) -> Result<Kaonic1SMachine, KaonicError> {
+    Kaonic1SMachine::with_spi(spi)
+}
+
 pub type PlatformRadio = Kaonic1SRadio;
 pub type PlatformRadioEvent = Kaonic1SRadioEvent;
 pub type PlatformRadioFrame = Kaonic1SFrame;
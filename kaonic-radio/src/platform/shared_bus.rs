@@ -0,0 +1,189 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use radio_rf215::bus::{Bus, BusError};
+use radio_rf215::regs::{RegisterAddress, RegisterValue};
+
+/// RF215 bus shared by the sub-GHz and the 2.4GHz transceiver of one chip
+///
+/// Every register transaction, including the read and write of
+/// `modify_reg_u8`, runs with the bus locked, so both bands may be driven
+/// from different threads. Sequences spanning several transactions are not
+/// atomic, which is fine as long as each band only touches its own
+/// registers. Registers common to the chip (e.g. `RF_CFG` or the battery
+/// monitor) and the hardware reset, which resets both bands, are left to
+/// the `Rf215` owning them.
+///
+/// `wait_interrupt` keeps the bus locked while waiting, the driver only
+/// waits in slices of at most 500us so the other band isn't held up longer.
+#[derive(Debug)]
+pub struct SharedBus<T> {
+    bus: Arc<Mutex<T>>,
+}
+
+impl<T> SharedBus<T> {
+    /// Create a new `SharedBus`.
+    #[inline]
+    pub fn new(bus: Arc<Mutex<T>>) -> Self {
+        Self { bus }
+    }
+
+    /// Locks the bus, a panic of another user doesn't leave it unusable
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.bus.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Clone for SharedBus<T> {
+    fn clone(&self) -> Self {
+        Self {
+            bus: self.bus.clone(),
+        }
+    }
+}
+
+impl<T: Bus> Bus for SharedBus<T> {
+    #[inline]
+    fn write_regs(
+        &mut self,
+        addr: RegisterAddress,
+        values: &[RegisterValue],
+    ) -> Result<(), BusError> {
+        self.lock().write_regs(addr, values)
+    }
+
+    #[inline]
+    fn read_regs(
+        &mut self,
+        addr: RegisterAddress,
+        values: &mut [RegisterValue],
+    ) -> Result<(), BusError> {
+        self.lock().read_regs(addr, values)
+    }
+
+    /// Holds the lock across the read and the write, the other band can't
+    /// change the register in between
+    #[inline]
+    fn modify_reg_u8(
+        &mut self,
+        addr: RegisterAddress,
+        mask: u8,
+        new_value: u8,
+    ) -> Result<u8, BusError> {
+        self.lock().modify_reg_u8(addr, mask, new_value)
+    }
+
+    #[inline]
+    fn wait_interrupt(&mut self, timeout: Option<std::time::Duration>) -> Result<(), BusError> {
+        self.lock().wait_interrupt(timeout)
+    }
+
+    /// Sleeps without the bus, the other band keeps access to it meanwhile
+    #[inline]
+    fn delay(&mut self, timeout: std::time::Duration) {
+        std::thread::sleep(timeout)
+    }
+
+    #[inline]
+    fn current_time(&mut self) -> u64 {
+        self.lock().current_time()
+    }
+
+    #[inline]
+    fn hardware_reset(&mut self) -> Result<(), BusError> {
+        self.lock().hardware_reset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REG_COUNT: usize = 0x40;
+    const SHARED_REG: RegisterAddress = 0x10;
+
+    /// Register file that yields within reads to provoke interleaving
+    struct RegisterFile {
+        regs: [RegisterValue; REG_COUNT],
+    }
+
+    impl Bus for RegisterFile {
+        fn write_regs(
+            &mut self,
+            addr: RegisterAddress,
+            values: &[RegisterValue],
+        ) -> Result<(), BusError> {
+            let addr = addr as usize;
+            self.regs[addr..addr + values.len()].copy_from_slice(values);
+            Ok(())
+        }
+
+        fn read_regs(
+            &mut self,
+            addr: RegisterAddress,
+            values: &mut [RegisterValue],
+        ) -> Result<(), BusError> {
+            let addr = addr as usize;
+            values.copy_from_slice(&self.regs[addr..addr + values.len()]);
+            std::thread::yield_now();
+            Ok(())
+        }
+
+        fn wait_interrupt(
+            &mut self,
+            _timeout: Option<std::time::Duration>,
+        ) -> Result<(), BusError> {
+            Err(BusError::Timeout)
+        }
+
+        fn delay(&mut self, _timeout: std::time::Duration) {}
+
+        fn current_time(&mut self) -> u64 {
+            0
+        }
+
+        fn hardware_reset(&mut self) -> Result<(), BusError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_concurrent_register_access() {
+        const ITERATIONS: usize = 2000;
+
+        let bus = SharedBus::new(Arc::new(Mutex::new(RegisterFile {
+            regs: [0; REG_COUNT],
+        })));
+
+        // Every thread flips its own bit of a common register and owns a
+        // register of its own, none may lose an update of another
+        let worker = |mut bus: SharedBus<RegisterFile>, bit: u8, own_reg: RegisterAddress| {
+            move || {
+                for i in 0..ITERATIONS {
+                    bus.modify_reg_u8(SHARED_REG, bit, bit).unwrap();
+                    assert_ne!(bus.read_reg_u8(SHARED_REG).unwrap() & bit, 0);
+
+                    bus.modify_reg_u8(SHARED_REG, bit, 0).unwrap();
+                    assert_eq!(bus.read_reg_u8(SHARED_REG).unwrap() & bit, 0);
+
+                    bus.write_reg_u16(own_reg, i as u16).unwrap();
+                    assert_eq!(bus.read_reg_u16(own_reg).unwrap(), i as u16);
+                }
+            }
+        };
+
+        std::thread::scope(|s| {
+            for n in 0..4 {
+                s.spawn(worker(bus.clone(), 1 << n, 0x20 + 2 * n));
+            }
+        });
+
+        let mut bus = bus;
+        assert_eq!(bus.read_reg_u8(SHARED_REG).unwrap(), 0);
+        for n in 0..4 {
+            assert_eq!(
+                bus.read_reg_u16(0x20 + 2 * n).unwrap(),
+                (ITERATIONS - 1) as u16
+            );
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use radio_common::{Hertz, Modulation};
+
+/// Maximum value of the RF215 PAC.TXPWR field
+pub const TX_POWER_MAX: u8 = 31;
+
+/// Frequencies below this belong to the sub-GHz (RF09) band
+const BAND_24_START_MHZ: u64 = 2_000;
+
+/// Per-band transmit power ceiling
+///
+/// Every modulation applied to a radio is clamped to the ceiling of the band
+/// it operates in, so adaptive power adjustments can't exceed regulatory limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxPowerLimit {
+    /// Ceiling for the sub-GHz band
+    pub band_09: u8,
+    /// Ceiling for the 2.4GHz band
+    pub band_24: u8,
+}
+
+impl TxPowerLimit {
+    pub const fn new(band_09: u8, band_24: u8) -> Self {
+        Self { band_09, band_24 }
+    }
+
+    /// Returns the ceiling for the band containing `freq`
+    pub fn ceiling(&self, freq: Hertz) -> u8 {
+        if freq.as_mhz() < BAND_24_START_MHZ {
+            self.band_09
+        } else {
+            self.band_24
+        }
+    }
+
+    /// Returns `modulation` with its transmit power clamped to the ceiling for `freq`
+    pub fn clamp(&self, freq: Hertz, modulation: &Modulation) -> Modulation {
+        let ceiling = self.ceiling(freq);
+        let mut modulation = *modulation;
+
+        if modulation.tx_power() > ceiling {
+            log::warn!(
+                "tx power {} exceeds ceiling {} at {}MHz, clamping",
+                modulation.tx_power(),
+                ceiling,
+                freq.as_mhz()
+            );
+
+            modulation.set_tx_power(ceiling);
+        }
+
+        modulation
+    }
+}
+
+impl Default for TxPowerLimit {
+    fn default() -> Self {
+        Self::new(TX_POWER_MAX, TX_POWER_MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use radio_common::modulation::OfdmModulation;
+
+    use super::*;
+
+    #[test]
+    fn test_boosted_power_clamped_to_ceiling() {
+        let limit = TxPowerLimit::new(14, 20);
+
+        // Base power 12 boosted by +6 for "Bad" channel quality
+        let boosted = Modulation::Ofdm(OfdmModulation {
+            tx_power: 12 + 6,
+            ..Default::default()
+        });
+
+        let sub_ghz = limit.clamp(Hertz::new(869_535_000), &boosted);
+        assert_eq!(sub_ghz.tx_power(), 14);
+
+        let ism_24 = limit.clamp(Hertz::new(2_450_000_000), &boosted);
+        assert_eq!(ism_24.tx_power(), 18);
+    }
+}
@@ -1,6 +1,13 @@
-use radio_common::{Modulation, RadioConfig};
+use core::{ops::Range, time::Duration};
 
-use crate::error::KaonicError;
+use radio_common::{Modulation, RadioChannel, RadioConfig};
+pub use radio_rf215::baseband::{AddressFilter, AutoAck, PhaseMeasurement, MAX_ACK_TIME_US};
+pub use radio_rf215::transceiver::{CcaMode, EnergyCca};
+use radio_rf215::transceiver::TX_FRAME_END_DURATION;
+pub use radio_rf215::radio::{AgcGainMap, IrqCounters};
+pub use radio_rf215::PartNumber;
+
+use crate::{error::KaonicError, fem::FemPath, power::TxPowerLimit, regulatory::LbtParams};
 
 /// Result of a successful frame reception.
 pub struct ReceiveResult {
@@ -18,6 +25,103 @@ pub struct ScanResult {
     pub snr: i8,
 }
 
+/// Energy measured on one channel of a channel scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelEnergy {
+    pub channel: RadioChannel,
+    /// Measured noise floor in dBm.
+    pub rssi: i8,
+}
+
+/// How the radio returns to receive after a transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TxTurnaround {
+    /// The driver re-enters RX once the frame is sent.
+    ///
+    /// Allows a clear channel assessment before each transmission.
+    #[default]
+    Manual,
+    /// The baseband switches to RX by itself at the end of the frame.
+    ///
+    /// Minimizes the RX gap so an immediate reply (e.g. an ACK) is caught,
+    /// at the cost of transmitting without a clear channel assessment.
+    Auto,
+}
+
+/// How much of the radio state [`Radio::reset`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Restores the configuration, modulation and transmit settings after
+    /// the transceiver reset.
+    Soft,
+    /// Leaves the radio on the defaults it starts up with, the front end
+    /// back on [`FemPath::Auto`].
+    Hard,
+}
+
+/// How the last transmission ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransmitResult {
+    /// The frame went out.
+    #[default]
+    Sent,
+    /// Every attempt found the channel busy.
+    ChannelBusy,
+    /// The retry limit was reached without sending the frame.
+    MaxRetries,
+}
+
+impl TransmitResult {
+    /// Returns `true` if the frame left the radio.
+    pub fn is_sent(&self) -> bool {
+        matches!(self, Self::Sent)
+    }
+}
+
+/// Attempts made by a transmission and how it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransmitReport {
+    /// Number of tries including the first one.
+    pub attempts: u8,
+    pub result: TransmitResult,
+}
+
+impl TransmitReport {
+    /// Report of a transmission which made `attempts` tries, `busy` of them
+    /// finding the channel busy. It only ended with a busy channel if every
+    /// attempt did.
+    pub fn new(attempts: u8, busy: u8, result: &Result<(), KaonicError>) -> Self {
+        let result = match result {
+            Ok(_) => TransmitResult::Sent,
+            Err(_) if busy == attempts => TransmitResult::ChannelBusy,
+            Err(_) => TransmitResult::MaxRetries,
+        };
+
+        Self { attempts, result }
+    }
+}
+
+/// Highest retry count accepted by [`Radio::set_tx_retries`].
+pub const MAX_TX_RETRIES: u8 = 15;
+
+/// Time the radio gets to return to RX after a transmission until set otherwise.
+pub const DEFAULT_RX_RESTORE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Added to the time on air of a frame, covers state changes and interrupt latency.
+const FRAME_TIMEOUT_MARGIN: Duration = Duration::from_millis(5);
+
+/// Time to wait for a `len` byte frame sent or received with `modulation`.
+///
+/// One and a half times the time on air plus a margin, so frames of robust
+/// modulations aren't cut short and fast ones aren't waited on needlessly.
+/// Modulations without a modelled air time get the driver default.
+pub fn frame_timeout(modulation: &Modulation, len: usize) -> Duration {
+    modulation
+        .air_time(len)
+        .map(|air_time| air_time + air_time / 2 + FRAME_TIMEOUT_MARGIN)
+        .unwrap_or(TX_FRAME_END_DURATION)
+}
+
 /// Trait representing a physical radio module.
 ///
 /// Implementors are responsible for managing hardware state including
@@ -43,9 +147,44 @@ pub trait Radio {
     /// Returns the current modulation scheme.
     fn get_modulation(&self) -> Modulation;
 
+    /// Applies `config` and `modulation` as one change.
+    ///
+    /// Radios that can reprogram both without receiving in between override
+    /// this, so the radio never runs the new frequency with the old
+    /// modulation. The default applies them one after the other.
+    fn set_config_with_modulation(
+        &mut self,
+        config: &RadioConfig,
+        modulation: &Modulation,
+    ) -> Result<(), KaonicError> {
+        self.set_config(config)?;
+        self.set_modulation(modulation)
+    }
+
     /// Transmits a frame over the air.
     fn transmit(&mut self, frame: &Self::TxFrame) -> Result<(), KaonicError>;
 
+    /// Transmits a frame with `modulation`, which stays active afterwards.
+    ///
+    /// The radio is only reprogrammed when `modulation` differs from the
+    /// current one, so a run of frames with the same modulation costs nothing
+    /// extra. A switch is expensive: the RF215 goes to TRXOFF, both
+    /// transceivers get their frontend and baseband registers rewritten over
+    /// SPI and the PLL relocks before RX resumes, which typically adds around
+    /// a millisecond. Keep frames of one modulation together where the
+    /// protocol allows.
+    fn transmit_with_modulation(
+        &mut self,
+        frame: &Self::TxFrame,
+        modulation: &Modulation,
+    ) -> Result<(), KaonicError> {
+        if self.get_modulation() != *modulation {
+            self.set_modulation(modulation)?;
+        }
+
+        self.transmit(frame)
+    }
+
     /// Blocks until a frame is received or `timeout` elapses.
     ///
     /// Returns [`KaonicError::Timeout`] if no frame arrives within the timeout.
@@ -56,5 +195,310 @@ pub trait Radio {
     ) -> Result<ReceiveResult, KaonicError>;
 
     /// Performs a passive energy scan on the current channel for up to `timeout`.
+    ///
+    /// Reports the strongest energy seen during the scan.
     fn scan(&mut self, timeout: core::time::Duration) -> Result<ScanResult, KaonicError>;
+
+    /// Tunes to each of `channels` in turn and scans it for `dwell`.
+    ///
+    /// The rest of the configuration is kept. The radio is left on the
+    /// configuration it had before the scan, also when a scan fails.
+    fn scan_channels(
+        &mut self,
+        channels: Range<RadioChannel>,
+        dwell: core::time::Duration,
+    ) -> Result<Vec<ChannelEnergy>, KaonicError> {
+        let original = self.get_config();
+
+        let mut energies = Vec::with_capacity(channels.len());
+        let mut result = Ok(());
+        for channel in channels {
+            let scanned = self
+                .set_config(&RadioConfig {
+                    channel,
+                    ..original
+                })
+                .and_then(|_| self.scan(dwell));
+
+            match scanned {
+                Ok(scan) => energies.push(ChannelEnergy {
+                    channel,
+                    rssi: scan.rssi,
+                }),
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        self.set_config(&original)?;
+        result.map(|_| energies)
+    }
+
+    /// Scans `channels` and tunes the radio to the one with the lowest energy.
+    ///
+    /// Ties go to the lower channel. Returns [`KaonicError::IncorrectSettings`]
+    /// if `channels` is empty.
+    fn select_best_channel(
+        &mut self,
+        channels: Range<RadioChannel>,
+        dwell: core::time::Duration,
+    ) -> Result<ChannelEnergy, KaonicError> {
+        let best = self
+            .scan_channels(channels, dwell)?
+            .into_iter()
+            .min_by_key(|energy| energy.rssi)
+            .ok_or(KaonicError::IncorrectSettings)?;
+
+        self.set_config(&RadioConfig {
+            channel: best.channel,
+            ..self.get_config()
+        })?;
+
+        Ok(best)
+    }
+
+    /// Reads the temperature near the transceiver in degrees Celsius.
+    ///
+    /// Returns [`KaonicError::NotSupported`] if the platform has no sensor.
+    fn read_temperature(&mut self) -> Result<f32, KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Reads the carrier phase measured on the last received preamble.
+    ///
+    /// Used for phase-based ranging between two nodes. Returns
+    /// [`KaonicError::NotSupported`] if the transceiver has no phase
+    /// measurement unit.
+    fn measure_phase(&mut self) -> Result<PhaseMeasurement, KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Arms the supply voltage monitor with a brownout threshold in millivolts.
+    ///
+    /// With `tx_inhibit` set, transmissions are refused while the supply is low
+    /// to protect the power amplifier.
+    fn set_battery_monitor(
+        &mut self,
+        _threshold_mv: u16,
+        _tx_inhibit: bool,
+    ) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns `true` while the supply voltage is below the battery monitor threshold.
+    fn battery_low(&self) -> bool {
+        false
+    }
+
+    /// Selects how the radio returns to receive after [`Radio::transmit`].
+    fn set_tx_turnaround(&mut self, _turnaround: TxTurnaround) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns the TX to RX turnaround in use.
+    fn tx_turnaround(&self) -> TxTurnaround {
+        TxTurnaround::Manual
+    }
+
+    /// Selects the clear channel assessment [`Radio::transmit`] runs before
+    /// each attempt.
+    ///
+    /// Has no effect with [`TxTurnaround::Auto`], which transmits without one.
+    fn set_cca_mode(&mut self, _mode: CcaMode) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns the clear channel assessment in use.
+    fn cca_mode(&self) -> CcaMode {
+        CcaMode::Energy
+    }
+
+    /// Sets the listen before talk rules [`Radio::transmit`] follows: the
+    /// energy detection of the clear channel assessment and the minimum time
+    /// between two transmissions.
+    fn set_lbt(&mut self, _lbt: LbtParams) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns the listen before talk rules in use.
+    fn lbt(&self) -> LbtParams {
+        LbtParams::default()
+    }
+
+    /// Drops whatever is left in the receive buffer and re-enters RX.
+    ///
+    /// Call it after [`Radio::receive`] returned [`KaonicError::BufferOverrun`].
+    fn flush_receive(&mut self) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Caps the transmit power of every modulation applied to the radio.
+    ///
+    /// Modulations above the ceiling of the current band are clamped, check
+    /// [`Radio::get_modulation`] for the power actually in use.
+    fn set_power_limit(&mut self, _limit: TxPowerLimit) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Limits how many times [`Radio::transmit`] retries a frame after the first attempt.
+    ///
+    /// Fails with `IncorrectSettings` above [`MAX_TX_RETRIES`].
+    fn set_tx_retries(&mut self, _retries: u8) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Bounds how long [`Radio::transmit`] waits for the radio to get back to
+    /// RX once it's done, whether the frame went out or not.
+    ///
+    /// The wait is never shorter than the time on air of the frame, a
+    /// transmission still running after it is aborted. Fails with
+    /// `IncorrectSettings` for a zero timeout.
+    fn set_rx_restore_timeout(&mut self, _timeout: Duration) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Drops received frames which aren't 802.15.4 MAC frames addressed to
+    /// `filter`, `None` accepts every frame again.
+    fn set_address_filter(&mut self, _filter: Option<AddressFilter>) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Acknowledges frames passing the address filter in hardware, `None`
+    /// turns it off.
+    ///
+    /// Fails with `IncorrectSettings` without an address filter set through
+    /// [`Radio::set_address_filter`].
+    fn set_auto_ack(&mut self, _ack: Option<AutoAck>) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Resets the transceiver and re-enters RX.
+    ///
+    /// Recovers a radio stuck in a bad state. Frames waiting in the receive
+    /// buffer are lost.
+    fn reset(&mut self, _kind: ResetKind) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Brings the transceiver up from reset: reset, wakeup, TRXOFF, the
+    /// current configuration and RX, verifying each step.
+    ///
+    /// Fails with [`KaonicError::Bringup`] naming the step that went wrong,
+    /// so a dead transceiver is told apart from a misconfigured one.
+    fn bringup(&mut self) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Lets the transceiver append a frame check sequence to transmitted
+    /// frames and drop received frames with a bad one.
+    ///
+    /// The FCS is added and removed below [`Radio::transmit`] and
+    /// [`Radio::receive`], frames are handed over without it. Returns
+    /// [`KaonicError::NotSupported`] if the transceiver can't, e.g. a part
+    /// without baseband core.
+    fn set_hardware_fcs(&mut self, _enabled: bool) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns `true` while the transceiver appends and checks an FCS.
+    fn hardware_fcs(&self) -> bool {
+        false
+    }
+
+    /// Selects how the AGC accounts for an external LNA in front of the
+    /// transceiver.
+    ///
+    /// A map not matching the front end skews every RSSI and energy reading.
+    fn set_agc_gain_map(&mut self, _map: AgcGainMap) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Sets up the RF front end as `path` says, [`FemPath::Auto`] hands it
+    /// back to the frequency of each configuration.
+    ///
+    /// Returns [`KaonicError::NotSupported`] if the platform has no
+    /// switchable front end.
+    fn set_fem_path(&mut self, _path: FemPath) -> Result<(), KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns how the RF front end is set up.
+    fn fem_path(&self) -> FemPath {
+        FemPath::Auto
+    }
+
+    /// Counts of the transceiver IRQs seen since startup, read each time the
+    /// IRQ status is.
+    ///
+    /// Returns [`KaonicError::NotSupported`] if the platform can't tell.
+    fn irq_counters(&mut self) -> Result<IrqCounters, KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Reads back the transmit power the transceiver is actually set to.
+    ///
+    /// Confirms a power applied through [`Radio::set_modulation`] reached the
+    /// hardware. Returns [`KaonicError::NotSupported`] if the platform can't
+    /// read it back.
+    fn read_tx_power(&mut self) -> Result<u8, KaonicError> {
+        Err(KaonicError::NotSupported)
+    }
+
+    /// Returns the transceiver part, `None` if the platform doesn't know it.
+    fn part_number(&self) -> Option<PartNumber> {
+        None
+    }
+
+    /// Returns the attempts and outcome of the last [`Radio::transmit`].
+    ///
+    /// Also valid when the transmission failed.
+    fn last_transmit(&self) -> TransmitReport {
+        TransmitReport {
+            attempts: 1,
+            result: TransmitResult::Sent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use radio_common::modulation::{
+        OfdmBandwidthOption, OfdmMcs, OfdmModulation, QpskChipFrequency, QpskModulation,
+        QpskRateMode,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_frame_timeout_scales_with_data_rate() {
+        const LEN: usize = 2048;
+
+        let slow = Modulation::Qpsk(QpskModulation {
+            fchip: QpskChipFrequency::Fchip100,
+            mode: QpskRateMode::RateMode0,
+            ..Default::default()
+        });
+        let fast = Modulation::Ofdm(OfdmModulation {
+            mcs: OfdmMcs::QamC3_4,
+            opt: OfdmBandwidthOption::Option1,
+            ..Default::default()
+        });
+
+        // 2048 bytes at 6.25 kbit/s take over 2.6 s on air
+        let slow_timeout = frame_timeout(&slow, LEN);
+        assert!(slow_timeout > slow.air_time(LEN).unwrap());
+        assert!(slow_timeout > TX_FRAME_END_DURATION);
+
+        // and about 7 ms at 2.4 Mbit/s
+        let fast_timeout = frame_timeout(&fast, LEN);
+        assert!(fast_timeout > fast.air_time(LEN).unwrap());
+        assert!(fast_timeout < Duration::from_millis(20));
+
+        assert!(frame_timeout(&slow, 16) < slow_timeout);
+        assert!(frame_timeout(&fast, 16) >= FRAME_TIMEOUT_MARGIN);
+
+        assert_eq!(frame_timeout(&Modulation::Fsk, LEN), TX_FRAME_END_DURATION);
+    }
 }
@@ -0,0 +1,67 @@
+use core::time::Duration;
+
+use crate::radio::EnergyCca;
+
+/// ETSI EN 300 220 LBT threshold in dBm
+pub const ETSI_LBT_THRESHOLD: i8 = -85;
+
+/// ETSI EN 300 220 minimum listen time before each transmission
+pub const ETSI_LBT_LISTEN_TIME: Duration = Duration::from_millis(5);
+
+/// ETSI EN 300 220 minimum TX off-time between transmissions
+pub const ETSI_MIN_OFF_TIME: Duration = Duration::from_millis(100);
+
+/// Listen before talk rules of a radio's transmissions
+///
+/// The energy measurement only takes place with a clear channel assessment
+/// that senses energy, see [`crate::radio::CcaMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LbtParams {
+    /// Energy detection before each transmit attempt
+    pub energy: EnergyCca,
+    /// Minimum time from the end of a transmission to the start of the next
+    pub min_off_time: Duration,
+}
+
+/// Regulations the transmissions of a radio comply with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegulatoryProfile {
+    /// FCC part 15.247, no listen before talk requirement
+    #[default]
+    Fcc,
+    /// ETSI EN 300 220 listen before talk for the EU 868 MHz SRD band
+    Etsi,
+}
+
+impl RegulatoryProfile {
+    /// Returns the listen before talk rules of the profile
+    pub fn lbt(self) -> LbtParams {
+        match self {
+            RegulatoryProfile::Fcc => LbtParams::default(),
+            RegulatoryProfile::Etsi => LbtParams {
+                energy: EnergyCca {
+                    threshold: ETSI_LBT_THRESHOLD,
+                    duration: Some(ETSI_LBT_LISTEN_TIME),
+                },
+                min_off_time: ETSI_MIN_OFF_TIME,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etsi_profile_lbt() {
+        let lbt = RegulatoryProfile::Etsi.lbt();
+        assert_eq!(lbt.energy.threshold, -85);
+        assert_eq!(lbt.energy.duration, Some(Duration::from_millis(5)));
+        assert_eq!(lbt.min_off_time, Duration::from_millis(100));
+
+        // No rules beyond the default energy detection
+        assert_eq!(RegulatoryProfile::Fcc.lbt(), LbtParams::default());
+        assert_eq!(RegulatoryProfile::default(), RegulatoryProfile::Fcc);
+    }
+}
@@ -0,0 +1,43 @@
+/// Highest SPI clock the RF215 accepts
+pub const RF215_MAX_SPI_SPEED: u32 = 25_000_000;
+
+/// SPI clock polarity and phase (CPOL, CPHA)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpiMode {
+    /// CPOL 0, CPHA 0, the mode of the RF215 datasheet
+    #[default]
+    Mode0,
+    Mode1,
+    Mode2,
+    Mode3,
+}
+
+impl SpiMode {
+    pub fn from_u8(mode: u8) -> Option<Self> {
+        match mode {
+            0 => Some(Self::Mode0),
+            1 => Some(Self::Mode1),
+            2 => Some(Self::Mode2),
+            3 => Some(Self::Mode3),
+            _ => None,
+        }
+    }
+}
+
+/// SPI bus setup of the radio modules
+///
+/// Longer cables or other board revisions may need a slower clock than the
+/// one the machine defaults to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpiSettings {
+    /// Clock in Hz, the board revision's default if unset
+    pub max_speed: Option<u32>,
+    pub mode: SpiMode,
+}
+
+impl SpiSettings {
+    /// Clock to use on a bus whose board default is `default_speed`
+    pub fn speed(&self, default_speed: u32) -> u32 {
+        self.max_speed.unwrap_or(default_speed)
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::KaonicError;
+
+const THERMAL_CLASS_PATH: &str = "/sys/class/thermal";
+
+/// Linux thermal zone (`/sys/class/thermal/thermal_zoneN`)
+///
+/// The RF215 doesn't expose a die temperature, so the SoC thermal zone next
+/// to the transceivers is used as the board temperature source.
+#[derive(Debug, Clone)]
+pub struct ThermalZone {
+    temp_path: PathBuf,
+}
+
+impl ThermalZone {
+    /// Creates a sensor reading millidegrees Celsius from `temp_path`
+    pub fn new<P: AsRef<Path>>(temp_path: P) -> Self {
+        Self {
+            temp_path: temp_path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Finds the first thermal zone with the given `type`
+    pub fn find(zone_type: &str) -> Option<Self> {
+        let entries = std::fs::read_dir(THERMAL_CLASS_PATH).ok()?;
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = entry.file_name();
+
+            if !name.to_string_lossy().starts_with("thermal_zone") {
+                continue;
+            }
+
+            if let Ok(t) = std::fs::read_to_string(path.join("type")) {
+                if t.trim() == zone_type {
+                    return Some(Self::new(path.join("temp")));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Reads current temperature in degrees Celsius
+    pub fn read_celsius(&self) -> Result<f32, KaonicError> {
+        let value =
+            std::fs::read_to_string(&self.temp_path).map_err(|_| KaonicError::HardwareError)?;
+
+        let millidegrees = value
+            .trim()
+            .parse::<i32>()
+            .map_err(|_| KaonicError::DataCorruption)?;
+
+        Ok(millidegrees as f32 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_celsius() {
+        let path = std::env::temp_dir().join(format!("kaonic-thermal-{}", std::process::id()));
+        std::fs::write(&path, "45250\n").expect("temp file");
+
+        let zone = ThermalZone::new(&path);
+        assert_eq!(zone.read_celsius(), Ok(45.25));
+
+        std::fs::write(&path, "n/a\n").expect("temp file");
+        assert_eq!(zone.read_celsius(), Err(KaonicError::DataCorruption));
+
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(zone.read_celsius(), Err(KaonicError::HardwareError));
+    }
+}
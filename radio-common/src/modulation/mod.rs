@@ -1,14 +1,19 @@
 mod ofdm;
 mod qpsk;
 
-use core::fmt;
+use core::{fmt, time::Duration};
 
 use serde::{Deserialize, Serialize};
 
+use crate::Hertz;
+
 pub use ofdm::*;
 pub use qpsk::*;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Step of the AT86RF215 channel spacing in both bands
+pub const CHANNEL_SPACING_STEP: Hertz = Hertz::from_khz(25);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum Modulation {
     Off,
     Ofdm(OfdmModulation),
@@ -25,6 +30,62 @@ impl Modulation {
             Modulation::Fsk => 0,
         }
     }
+
+    /// PHY data rate in bits per second, `None` when it isn't modelled
+    pub fn data_rate_bps(&self) -> Option<u32> {
+        match self {
+            Modulation::Ofdm(ofdm) => Some(ofdm.data_rate_bps()),
+            Modulation::Qpsk(qpsk) => Some(qpsk.data_rate_bps()),
+            Modulation::Off | Modulation::Fsk => None,
+        }
+    }
+
+    /// Approximate time on air of a `len` byte frame, `None` when it isn't modelled
+    pub fn air_time(&self, len: usize) -> Option<Duration> {
+        match self {
+            Modulation::Ofdm(ofdm) => Some(ofdm.air_time(len)),
+            Modulation::Qpsk(qpsk) => Some(qpsk.air_time(len)),
+            Modulation::Off | Modulation::Fsk => None,
+        }
+    }
+
+    /// Typical receiver sensitivity in dBm, `None` when it isn't modelled
+    pub fn sensitivity_dbm(&self) -> Option<f32> {
+        match self {
+            Modulation::Ofdm(ofdm) => Some(ofdm.sensitivity_dbm()),
+            Modulation::Qpsk(qpsk) => Some(qpsk.sensitivity_dbm()),
+            Modulation::Off | Modulation::Fsk => None,
+        }
+    }
+
+    /// Bandwidth the signal occupies, `None` when it isn't modelled
+    pub fn occupied_bandwidth(&self) -> Option<Hertz> {
+        match self {
+            Modulation::Ofdm(ofdm) => Some(ofdm.occupied_bandwidth()),
+            Modulation::Qpsk(qpsk) => Some(qpsk.occupied_bandwidth()),
+            Modulation::Off | Modulation::Fsk => None,
+        }
+    }
+
+    /// Narrowest channel spacing adjacent channels don't overlap at, the
+    /// occupied bandwidth rounded up to [`CHANNEL_SPACING_STEP`]
+    ///
+    /// The occupied bandwidth doesn't depend on the band, neither does the
+    /// spacing.
+    pub fn recommended_channel_spacing(&self) -> Option<Hertz> {
+        let step = CHANNEL_SPACING_STEP.as_hz();
+        let bandwidth = self.occupied_bandwidth()?.as_hz();
+
+        Some(Hertz::new(bandwidth.div_ceil(step) * step))
+    }
+
+    pub fn set_tx_power(&mut self, tx_power: u8) {
+        match self {
+            Modulation::Ofdm(ofdm) => ofdm.tx_power = tx_power,
+            Modulation::Qpsk(qpsk) => qpsk.tx_power = tx_power,
+            Modulation::Off | Modulation::Fsk => {}
+        }
+    }
 }
 
 impl fmt::Display for Modulation {
@@ -53,3 +114,45 @@ impl fmt::Display for Modulation {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommended_spacing_covers_occupied_bandwidth() {
+        let ofdm = |opt| {
+            Modulation::Ofdm(OfdmModulation {
+                opt,
+                ..Default::default()
+            })
+        };
+        let qpsk = |fchip| {
+            Modulation::Qpsk(QpskModulation {
+                fchip,
+                ..Default::default()
+            })
+        };
+
+        let cases = [
+            (ofdm(OfdmBandwidthOption::Option1), 1_094_000, 1_100_000),
+            (ofdm(OfdmBandwidthOption::Option2), 552_000, 575_000),
+            (ofdm(OfdmBandwidthOption::Option3), 281_000, 300_000),
+            (ofdm(OfdmBandwidthOption::Option4), 156_000, 175_000),
+            (qpsk(QpskChipFrequency::Fchip100), 100_000, 100_000),
+            (qpsk(QpskChipFrequency::Fchip2000), 2_000_000, 2_000_000),
+        ];
+
+        for (modulation, bandwidth, spacing) in cases {
+            let occupied = modulation.occupied_bandwidth().expect("bandwidth");
+            let recommended = modulation.recommended_channel_spacing().expect("spacing");
+
+            assert_eq!(occupied.as_hz(), bandwidth);
+            assert_eq!(recommended.as_hz(), spacing);
+            assert!(recommended.as_hz() - occupied.as_hz() < CHANNEL_SPACING_STEP.as_hz());
+        }
+
+        assert_eq!(Modulation::Off.recommended_channel_spacing(), None);
+        assert_eq!(Modulation::Fsk.recommended_channel_spacing(), None);
+    }
+}
@@ -1,5 +1,9 @@
+use core::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::Hertz;
+
 ///  Modulation and Coding Scheme
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
@@ -22,12 +26,16 @@ pub enum OfdmBandwidthOption {
     Option4 = 0x03,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct OfdmModulation {
     pub mcs: OfdmMcs,
     pub opt: OfdmBandwidthOption,
     pub pdt: u8, // Preamble Detection Threshold
     pub tx_power: u8,
+    /// Reception with low frequency offset, for peers with tight frequency
+    /// accuracy. Trades tolerance to frequency offset for sensitivity.
+    #[serde(default)]
+    pub lfo: bool,
 }
 
 impl Default for OfdmModulation {
@@ -37,6 +45,74 @@ impl Default for OfdmModulation {
             opt: OfdmBandwidthOption::Option1,
             pdt: 0x03,
             tx_power: 10,
+            lfo: false,
+        }
+    }
+}
+
+impl OfdmModulation {
+    /// Whether the AT86RF215 supports this MCS with the bandwidth option
+    ///
+    /// Option 1 supports MCS 0-3, option 2 MCS 0-5, option 3 MCS 1-6 and
+    /// option 4 MCS 2-6.
+    pub fn is_supported(&self) -> bool {
+        let mcs = self.mcs as u8;
+
+        match self.opt {
+            OfdmBandwidthOption::Option1 => mcs <= 3,
+            OfdmBandwidthOption::Option2 => mcs <= 5,
+            OfdmBandwidthOption::Option3 => mcs >= 1,
+            OfdmBandwidthOption::Option4 => mcs >= 2,
+        }
+    }
+
+    /// PHY data rate in bits per second
+    ///
+    /// Each bandwidth option halves the rate of the previous one.
+    pub fn data_rate_bps(&self) -> u32 {
+        const OPTION1_RATE_KBPS: [u32; 7] = [100, 200, 400, 800, 1200, 1600, 2400];
+
+        (OPTION1_RATE_KBPS[self.mcs as usize] * 1000) >> (self.opt as u32)
+    }
+
+    /// Approximate time on air of a `len` byte PSDU
+    ///
+    /// The synchronization and PHY headers take 9 symbols on options 1 and 2
+    /// and 12 on options 3 and 4, the PSDU and 6 tail bits fill whole 120 us
+    /// symbols at the data rate.
+    pub fn air_time(&self, len: usize) -> Duration {
+        const SYMBOL_US: u64 = 120;
+        const TAIL_BITS: u64 = 6;
+
+        let header_symbols = match self.opt {
+            OfdmBandwidthOption::Option1 | OfdmBandwidthOption::Option2 => 9,
+            OfdmBandwidthOption::Option3 | OfdmBandwidthOption::Option4 => 12,
+        };
+
+        let bits = len as u64 * 8 + TAIL_BITS;
+        let payload_us = (bits * 1_000_000).div_ceil(self.data_rate_bps() as u64);
+
+        Duration::from_micros((header_symbols + payload_us.div_ceil(SYMBOL_US)) * SYMBOL_US)
+    }
+
+    /// Nominal bandwidth of the bandwidth option (IEEE 802.15.4g MR-OFDM)
+    pub fn occupied_bandwidth(&self) -> Hertz {
+        match self.opt {
+            OfdmBandwidthOption::Option1 => Hertz::from_khz(1094),
+            OfdmBandwidthOption::Option2 => Hertz::from_khz(552),
+            OfdmBandwidthOption::Option3 => Hertz::from_khz(281),
+            OfdmBandwidthOption::Option4 => Hertz::from_khz(156),
         }
     }
+
+    /// Typical receiver sensitivity in dBm
+    ///
+    /// Approximate AT86RF215 figures for option 1, each narrower bandwidth
+    /// option gains about 3 dB.
+    pub fn sensitivity_dbm(&self) -> f32 {
+        const OPTION1_SENSITIVITY_DBM: [f32; 7] =
+            [-103.0, -101.0, -98.0, -95.0, -92.0, -89.0, -86.0];
+
+        OPTION1_SENSITIVITY_DBM[self.mcs as usize] - 3.0 * (self.opt as u8) as f32
+    }
 }
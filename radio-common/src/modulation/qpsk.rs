@@ -1,5 +1,9 @@
+use core::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::Hertz;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum QpskChipFrequency {
@@ -19,7 +23,7 @@ pub enum QpskRateMode {
     RateMode4 = 0x04,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct QpskModulation {
     pub fchip: QpskChipFrequency,
     pub mode: QpskRateMode,
@@ -35,3 +39,66 @@ impl Default for QpskModulation {
         }
     }
 }
+
+impl QpskModulation {
+    /// PHY data rate in bits per second
+    ///
+    /// Rate mode 4 is only defined for 1000 and 2000 kchip/s, other chip
+    /// rates fall back to rate mode 3.
+    pub fn data_rate_bps(&self) -> u32 {
+        let rates_bps: [u32; 5] = match self.fchip {
+            QpskChipFrequency::Fchip100 => [6_250, 12_500, 25_000, 50_000, 50_000],
+            QpskChipFrequency::Fchip200 => [12_500, 25_000, 50_000, 100_000, 100_000],
+            QpskChipFrequency::Fchip1000 => [31_250, 125_000, 250_000, 500_000, 1_000_000],
+            QpskChipFrequency::Fchip2000 => [31_250, 125_000, 250_000, 500_000, 2_000_000],
+        };
+
+        rates_bps[self.mode as usize]
+    }
+
+    /// Approximate time on air of a `len` byte PSDU
+    ///
+    /// The synchronization and PHY headers (6 octets) go out at the rate of
+    /// rate mode 0, the PSDU at the data rate.
+    pub fn air_time(&self, len: usize) -> Duration {
+        const HEADER_BITS: u64 = 6 * 8;
+
+        let header_rate = Self {
+            mode: QpskRateMode::RateMode0,
+            ..*self
+        }
+        .data_rate_bps() as u64;
+
+        let header_us = (HEADER_BITS * 1_000_000).div_ceil(header_rate);
+        let payload_us = (len as u64 * 8 * 1_000_000).div_ceil(self.data_rate_bps() as u64);
+
+        Duration::from_micros(header_us + payload_us)
+    }
+
+    /// Main lobe bandwidth, about the chip rate
+    pub fn occupied_bandwidth(&self) -> Hertz {
+        match self.fchip {
+            QpskChipFrequency::Fchip100 => Hertz::from_khz(100),
+            QpskChipFrequency::Fchip200 => Hertz::from_khz(200),
+            QpskChipFrequency::Fchip1000 => Hertz::from_khz(1000),
+            QpskChipFrequency::Fchip2000 => Hertz::from_khz(2000),
+        }
+    }
+
+    /// Typical receiver sensitivity in dBm
+    ///
+    /// Scaled from the AT86RF215 figure of -123 dBm at 6.25 kbit/s.
+    pub fn sensitivity_dbm(&self) -> f32 {
+        // Every rate is 6.25 kbit/s times 2^n * 5^m, which keeps the dB
+        // scaling free of `log10` (not available without std)
+        let mut ratio = self.data_rate_bps() / 6_250;
+        let mut gain_db = 0.0;
+
+        while ratio % 5 == 0 {
+            ratio /= 5;
+            gain_db += 6.9897;
+        }
+
+        -123.0 + gain_db + 3.0103 * ratio.trailing_zeros() as f32
+    }
+}
@@ -11,10 +11,17 @@ use crate::{
     frame::Frame,
     radio::Band,
     regs::{self, BasebandInterrupt, BasebandInterruptMask, RegisterAddress, RG_BBCX_FRAME_SIZE},
+    shadow::{RegisterShadow, RegisterWrite},
 };
 
 pub type BasebandFrame = Frame<RG_BBCX_FRAME_SIZE>;
 
+/// Length of the 32 bit FCS the baseband appends with [`Baseband::set_fcs`]
+///
+/// It's part of the frame length on both ends: the last bytes of a frame
+/// loaded for TX are overwritten with it and a received frame ends with it.
+pub const FCS_LEN: usize = 4;
+
 pub struct BasebandControl {
     pub continuous_tx: bool,
     pub fcs_filter: bool,
@@ -44,6 +51,64 @@ impl Default for BasebandAutoMode {
     }
 }
 
+const AFC0_AFEN0: u8 = 0b0000_0001;
+
+const AMCS_AACKFT: u8 = 0b1000_0000;
+const AMCS_AACK: u8 = 0b0000_1000;
+const AMCS_AUTO_ACK_MASK: u8 = 0b1111_1000;
+
+const AMAACKPD_PD0: u8 = 0b0000_0001;
+const AMAACKTH_MASK: u8 = 0b0000_0111;
+
+/// Longest ACK transmit time AMAACKT holds, 11 bits
+pub const MAX_ACK_TIME_US: u16 = 0x07FF;
+
+/// IEEE 802.15.4 addresses of frame filter unit 0
+///
+/// With the filter enabled the baseband drops frames which aren't 802.15.4
+/// MAC frames addressed to this node (its short or extended address, or
+/// broadcast, on its PAN).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressFilter {
+    pub pan_id: u16,
+    pub short_address: u16,
+    pub extended_address: u64,
+}
+
+/// Hardware acknowledgement of frames passing the [`AddressFilter`]
+///
+/// Frames with the ACK request bit set are acknowledged by the baseband
+/// itself, the host only sees the received frame. Like 802.15.4 requires,
+/// frames with a bad FCS aren't acknowledged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AutoAck {
+    /// Frame pending bit of the ACKs (AMAACKPD.PD0)
+    pub frame_pending: bool,
+    /// Time from the end of the frame to the start of the ACK in µs
+    /// (AMAACKT), the 802.15.4 turnaround time of the PHY if unset
+    pub ack_time_us: Option<u16>,
+}
+
+/// Phase measurement unit (PMU) result of the last received preamble
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PhaseMeasurement {
+    /// Phase, signed with 256 steps per full turn (PMUVAL)
+    pub phase: i8,
+    /// Quality factor of the measurement (PMUQF)
+    pub quality: u8,
+    /// In-phase component (PMUI)
+    pub i: i8,
+    /// Quadrature component (PMUQ)
+    pub q: i8,
+}
+
+impl PhaseMeasurement {
+    /// Phase in radians, in the range -π..π
+    pub fn phase_radians(&self) -> f32 {
+        self.phase as f32 * core::f32::consts::PI / 128.0
+    }
+}
+
 #[derive(Debug)]
 pub struct Baseband<B, I>
 where
@@ -53,6 +118,10 @@ where
     _band: PhantomData<B>,
     bus: I,
     irqs: BasebandInterruptMask,
+    /// Auto-ACK bits of AMCS, kept when the other automatic modes change
+    auto_ack: u8,
+    /// PHY registers as last written by the configuration calls
+    shadow: RegisterShadow,
 }
 
 impl<B, I> Baseband<B, I>
@@ -65,6 +134,8 @@ where
             _band: PhantomData::default(),
             bus,
             irqs: BasebandInterruptMask::new(),
+            auto_ack: 0,
+            shadow: RegisterShadow::new(),
         }
     }
 
@@ -74,20 +145,29 @@ where
         Ok(())
     }
 
+    /// Reads the received frame, its length is the one the baseband reports
+    ///
+    /// `frame` is left empty if it can't be read completely, so the bytes of
+    /// a previous frame never pass for the new one.
     pub fn load_rx<'a>(
         &mut self,
         frame: &'a mut BasebandFrame,
     ) -> Result<&'a mut BasebandFrame, RadioError> {
+        frame.clear();
+
         let len = self.bus.read_reg_u16(Self::abs_reg(regs::RG_BBCX_RXFLL))?;
 
         if len as usize > regs::RG_BBCX_FRAME_SIZE {
             return Err(RadioError::IncorrectState);
         }
 
-        self.bus.read_regs(
+        if let Err(err) = self.bus.read_regs(
             B::BASEBAND_FRAME_BUFFER_ADDRESS + regs::RG_BBCX_FBRXS,
             frame.as_buffer_mut(len as usize),
-        )?;
+        ) {
+            frame.clear();
+            return Err(err.into());
+        }
 
         Ok(frame)
     }
@@ -123,12 +203,89 @@ where
             amcs = amcs | 0b0000_0001;
         }
 
+        amcs |= self.auto_ack;
+
         self.bus
             .write_reg_u8(Self::abs_reg(regs::RG_BBCX_AMCS), amcs)?;
 
         Ok(())
     }
 
+    /// Programs frame filter unit 0, `None` disables it
+    pub fn set_address_filter(&mut self, filter: Option<&AddressFilter>) -> Result<(), RadioError> {
+        let Some(filter) = filter else {
+            self.bus
+                .modify_reg_u8(Self::abs_reg(regs::RG_BBCX_AFC0), AFC0_AFEN0, 0)?;
+            return Ok(());
+        };
+
+        // MACEA0..7 hold the extended address least significant byte first
+        self.bus.write_regs(
+            Self::abs_reg(regs::RG_BBCX_MACEA0),
+            &filter.extended_address.to_le_bytes(),
+        )?;
+
+        let pan_id = filter.pan_id.to_le_bytes();
+        let short_address = filter.short_address.to_le_bytes();
+        self.bus.write_regs(
+            Self::abs_reg(regs::RG_BBCX_MACPID0F0),
+            &[pan_id[0], pan_id[1], short_address[0], short_address[1]],
+        )?;
+
+        self.bus
+            .modify_reg_u8(Self::abs_reg(regs::RG_BBCX_AFC0), AFC0_AFEN0, AFC0_AFEN0)?;
+
+        Ok(())
+    }
+
+    /// Enables the automatic acknowledgement, `None` disables it
+    ///
+    /// Only frames passing filter unit 0 are acknowledged, see
+    /// [`Baseband::set_address_filter`].
+    pub fn set_auto_ack(&mut self, ack: Option<&AutoAck>) -> Result<(), RadioError> {
+        let Some(ack) = ack else {
+            self.auto_ack = 0;
+            self.bus
+                .modify_reg_u8(Self::abs_reg(regs::RG_BBCX_AMCS), AMCS_AUTO_ACK_MASK, 0)?;
+            return Ok(());
+        };
+
+        let mut auto_ack = AMCS_AACK;
+
+        if let Some(ack_time) = ack.ack_time_us {
+            if ack_time > MAX_ACK_TIME_US {
+                return Err(RadioError::IncorrectConfig);
+            }
+
+            let [low, high] = ack_time.to_le_bytes();
+            self.bus.write_regs(
+                Self::abs_reg(regs::RG_BBCX_AMAACKTL),
+                &[low, high & AMAACKTH_MASK],
+            )?;
+
+            auto_ack |= AMCS_AACKFT;
+        }
+
+        let pending = if ack.frame_pending { AMAACKPD_PD0 } else { 0 };
+        self.bus
+            .modify_reg_u8(Self::abs_reg(regs::RG_BBCX_AMAACKPD), AMAACKPD_PD0, pending)?;
+
+        self.bus.modify_reg_u8(
+            Self::abs_reg(regs::RG_BBCX_AMCS),
+            AMCS_AUTO_ACK_MASK,
+            auto_ack,
+        )?;
+        self.auto_ack = auto_ack;
+
+        Ok(())
+    }
+
+    /// Forgets the auto-ACK setup and the PHY shadow after the registers were reset
+    pub(crate) fn clear_register_state(&mut self) {
+        self.auto_ack = 0;
+        self.shadow.invalidate();
+    }
+
     pub fn set_auto_edt(&mut self, threshold: i8) -> Result<(), RadioError> {
         let amedt: u8 = threshold as u8;
 
@@ -156,6 +313,22 @@ where
     }
 
     pub fn configure(&mut self, modulation: &Modulation) -> Result<(), RadioError> {
+        for write in Self::phy_writes(modulation)?.iter().flatten() {
+            self.write_shadowed(write)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the PHY registers were last written with `modulation`
+    pub(crate) fn is_configured(&self, modulation: &Modulation) -> Result<bool, RadioError> {
+        Ok(Self::phy_writes(modulation)?
+            .iter()
+            .flatten()
+            .all(|write| self.shadow.is_current(write)))
+    }
+
+    fn phy_writes(modulation: &Modulation) -> Result<[Option<RegisterWrite>; 4], RadioError> {
         let phy_type: u8 = match modulation {
             Modulation::Off => 0x00,
             Modulation::Fsk => 0x01,
@@ -163,35 +336,41 @@ where
             Modulation::Qpsk(_) => 0x03,
         };
 
-        // Update baseband phy type
-        let mut value = self.bus.read_reg_u8(Self::abs_reg(regs::RG_BBCX_PC))?;
-
-        value = (value & 0b1111_1100) | phy_type;
-
-        self.bus
-            .write_reg_u8(Self::abs_reg(regs::RG_BBCX_PC), value)?;
+        // Baseband phy type
+        let pc = Some(RegisterWrite::masked(
+            regs::RG_BBCX_PC,
+            0b0000_0011,
+            phy_type,
+        ));
 
         match modulation {
-            Modulation::Off => Ok(()),
-            Modulation::Ofdm(ofdm) => self.configure_ofdm(ofdm),
-            Modulation::Qpsk(qpsk) => self.configure_qpsk(qpsk),
+            Modulation::Off => Ok([pc, None, None, None]),
+            Modulation::Ofdm(ofdm) => {
+                let [ofdmc, phrtx, sw] = Self::ofdm_writes(ofdm);
+                Ok([pc, Some(ofdmc), Some(phrtx), Some(sw)])
+            }
+            Modulation::Qpsk(qpsk) => {
+                let [c0, phrtx] = Self::qpsk_writes(qpsk);
+                Ok([pc, Some(c0), Some(phrtx), None])
+            }
             _ => Err(RadioError::IncorrectConfig),
         }
     }
 
+    /// Appends an FCS to transmitted frames and drops received frames with a
+    /// bad one, see [`FCS_LEN`]
     pub fn set_fcs(&mut self, enabled: bool) -> Result<(), RadioError> {
         const FCSFE_BIT: u8 = 0b0100_0000;
         const TXAFCS_BIT: u8 = 0b0001_0000;
+        const FCST_BIT: u8 = 0b0000_1000; // Cleared for a 32 bit FCS
 
         let value = if enabled { TXAFCS_BIT | FCSFE_BIT } else { 0 };
 
-        self.bus.modify_reg_u8(
-            Self::abs_reg(regs::RG_BBCX_PC),
-            TXAFCS_BIT | FCSFE_BIT,
+        self.write_shadowed(&RegisterWrite::masked(
+            regs::RG_BBCX_PC,
+            TXAFCS_BIT | FCSFE_BIT | FCST_BIT,
             value,
-        )?;
-
-        Ok(())
+        ))
     }
 
     pub fn enable(&mut self) -> Result<(), RadioError> {
@@ -207,10 +386,7 @@ where
 
         let value = if enabled { BBEN_BIT } else { 0 };
 
-        self.bus
-            .modify_reg_u8(Self::abs_reg(regs::RG_BBCX_PC), BBEN_BIT, value)?;
-
-        Ok(())
+        self.write_shadowed(&RegisterWrite::masked(regs::RG_BBCX_PC, BBEN_BIT, value))
     }
 
     pub fn read_counter(&mut self) -> Result<u32, RadioError> {
@@ -222,34 +398,58 @@ where
         Ok(u32::from_le_bytes(bytes))
     }
 
-    fn configure_ofdm(&mut self, modulation: &OfdmModulation) -> Result<(), RadioError> {
-        let phy_config: u8 = modulation.opt as u8;
+    /// Enables the phase measurement unit and reads its latest result.
+    ///
+    /// The PMU latches the carrier phase while a preamble is received, so the
+    /// first call after enabling it returns stale values until a frame arrives.
+    pub fn measure_phase(&mut self) -> Result<PhaseMeasurement, RadioError> {
+        const PMUEN_BIT: u8 = 0b0000_0001;
+
         self.bus
-            .write_reg_u8(Self::abs_reg(regs::RG_BBCX_OFDMC), phy_config)?;
+            .modify_reg_u8(Self::abs_reg(regs::RG_BBCX_PMUC), PMUEN_BIT, PMUEN_BIT)?;
 
+        // PMUVAL, PMUQF, PMUI and PMUQ are consecutive
+        let mut values = [0u8; 4];
         self.bus
-            .write_reg_u8(Self::abs_reg(regs::RG_BBCX_OFDMPHRTX), modulation.mcs as u8)?;
+            .read_regs(Self::abs_reg(regs::RG_BBCX_PMUVAL), &mut values[..])?;
+
+        Ok(PhaseMeasurement {
+            phase: values[0] as i8,
+            quality: values[1],
+            i: values[2] as i8,
+            q: values[3] as i8,
+        })
+    }
+
+    fn ofdm_writes(modulation: &OfdmModulation) -> [RegisterWrite; 3] {
+        const LFO_BIT: u8 = 0b0000_1000;
 
+        let lfo = if modulation.lfo { LFO_BIT } else { 0 };
+        let phy_config: u8 = modulation.opt as u8 | lfo;
         let ofdm_switches: u8 = (modulation.pdt << 5) | 0b10000;
-        self.bus
-            .write_reg_u8(Self::abs_reg(regs::RG_BBCX_OFDMSW), ofdm_switches)?;
 
-        Ok(())
+        [
+            RegisterWrite::new(regs::RG_BBCX_OFDMC, phy_config),
+            RegisterWrite::new(regs::RG_BBCX_OFDMPHRTX, modulation.mcs as u8),
+            RegisterWrite::new(regs::RG_BBCX_OFDMSW, ofdm_switches),
+        ]
     }
 
-    fn configure_qpsk(&mut self, modulation: &QpskModulation) -> Result<(), RadioError> {
-        self.bus.modify_reg_u8(
-            Self::abs_reg(regs::RG_BBCX_OQPSKC0),
-            0b0000_0011,
-            modulation.fchip as u8,
-        )?;
-
-        self.bus.modify_reg_u8(
-            Self::abs_reg(regs::RG_BBCX_OQPSKPHRTX),
-            0b0000_1110,
-            (modulation.mode as u8) << 1,
-        )?;
+    fn qpsk_writes(modulation: &QpskModulation) -> [RegisterWrite; 2] {
+        [
+            RegisterWrite::masked(regs::RG_BBCX_OQPSKC0, 0b0000_0011, modulation.fchip as u8),
+            RegisterWrite::masked(
+                regs::RG_BBCX_OQPSKPHRTX,
+                0b0000_1110,
+                (modulation.mode as u8) << 1,
+            ),
+        ]
+    }
 
+    /// Programs `write` unless the PHY register holds its bits already
+    fn write_shadowed(&mut self, write: &RegisterWrite) -> Result<(), RadioError> {
+        self.shadow
+            .write(&mut self.bus, B::BASEBAND_ADDRESS, write)?;
         Ok(())
     }
 
@@ -264,6 +464,13 @@ where
         Ok(BasebandInterruptMask::new_from_mask(irq_status))
     }
 
+    /// Consumes a latched interrupt, returns `true` if it was pending
+    pub fn take_irq(&mut self, irq: BasebandInterrupt) -> bool {
+        self.irqs
+            .retrieve(&BasebandInterruptMask::new().add_irq(irq).build())
+            .is_some()
+    }
+
     pub fn clear_irqs(&mut self) -> Result<&mut Self, RadioError> {
         let _ = self.read_irqs()?;
         self.irqs.reset();
@@ -292,7 +499,8 @@ where
                 }
             }
 
-            self.bus
+            let _ = self
+                .bus
                 .wait_interrupt(Some(core::time::Duration::from_micros(100)));
         }
 
@@ -1,15 +1,70 @@
-use core::time::Duration;
+use core::{fmt, time::Duration};
 
 use embedded_hal::spi::{self, SpiDevice};
 
 use crate::regs::{RegisterAddress, RegisterValue, RG_OP_READ, RG_OP_WRITE};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BusError {
+    /// SPI transfer failed, retries included
     CommunicationFailure,
+    /// SPI device or GPIO line couldn't be set up or driven
     ControlFailure,
+    /// Register address outside the register map
     InvalidAddress,
+    /// No interrupt within the wait time
     Timeout,
+    /// Reset line couldn't be driven or the transceiver didn't reset
+    ResetFailure,
+    /// Register read back a value the driver doesn't accept
+    RegisterOutOfRange {
+        addr: RegisterAddress,
+        value: RegisterValue,
+    },
+}
+
+impl fmt::Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusError::CommunicationFailure => write!(f, "SPI transfer failed"),
+            BusError::ControlFailure => write!(f, "SPI or GPIO control failed"),
+            BusError::InvalidAddress => write!(f, "invalid register address"),
+            BusError::Timeout => write!(f, "timed out waiting for an interrupt"),
+            BusError::ResetFailure => write!(f, "reset failed"),
+            BusError::RegisterOutOfRange { addr, value } => {
+                write!(f, "register 0x{:04X} out of range: 0x{:02X}", addr, value)
+            }
+        }
+    }
+}
+
+impl core::error::Error for BusError {}
+
+/// Retry policy for SPI register transactions
+///
+/// Transient SPI glitches are retried `retries` times with `delay` in between
+/// before `BusError::CommunicationFailure` is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusRetryPolicy {
+    pub retries: u8,
+    pub delay: Duration,
+}
+
+impl BusRetryPolicy {
+    pub const fn new(retries: u8, delay: Duration) -> Self {
+        Self { retries, delay }
+    }
+
+    /// Fail on the first unsuccessful transaction
+    pub const fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+}
+
+impl Default for BusRetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
 }
 
 pub trait BusInterrupt {
@@ -80,8 +135,9 @@ pub trait Bus {
         values: &mut [RegisterValue],
     ) -> Result<(), BusError>;
 
-    /// Helper method for waiting on event interrupt with timeout
-    fn wait_interrupt(&mut self, timeout: Option<Duration>) -> bool;
+    /// Helper method for waiting on event interrupt with timeout, returns
+    /// [`BusError::Timeout`] when none arrived in time
+    fn wait_interrupt(&mut self, timeout: Option<Duration>) -> Result<(), BusError>;
 
     /// Helper method to delay for a specific duration
     fn delay(&mut self, timeout: Duration);
@@ -113,6 +169,7 @@ where
     interrupt: I,
     clock: C,
     reset: R,
+    retry: BusRetryPolicy,
 }
 
 impl<S, I, C, R> SpiBus<S, I, C, R>
@@ -128,6 +185,36 @@ where
             interrupt,
             clock,
             reset,
+            retry: BusRetryPolicy::none(),
+        }
+    }
+
+    /// Sets retry policy for register reads and writes
+    pub fn with_retry(mut self, retry: BusRetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn transaction<F>(&mut self, addr: RegisterAddress, mut op: F) -> Result<(), BusError>
+    where
+        F: FnMut(&mut S) -> Result<(), S::Error>,
+    {
+        let mut attempt = 0u8;
+        loop {
+            match op(&mut self.spi) {
+                Ok(()) => return Ok(()),
+                Err(_) if attempt < self.retry.retries => {
+                    attempt += 1;
+                    log::debug!(
+                        "spi transaction at 0x{:04X} failed, retry {}/{}",
+                        addr,
+                        attempt,
+                        self.retry.retries
+                    );
+                    self.clock.delay(self.retry.delay);
+                }
+                Err(_) => return Err(BusError::CommunicationFailure),
+            }
         }
     }
 }
@@ -144,11 +231,11 @@ where
         addr: RegisterAddress,
         values: &[RegisterValue],
     ) -> Result<(), BusError> {
-        let addr = (addr | RG_OP_WRITE).to_be_bytes();
+        let cmd = (addr | RG_OP_WRITE).to_be_bytes();
 
-        self.spi
-            .transaction(&mut [spi::Operation::Write(&addr), spi::Operation::Write(&values)])
-            .map_err(|_| BusError::Timeout)
+        self.transaction(addr, |spi| {
+            spi.transaction(&mut [spi::Operation::Write(&cmd), spi::Operation::Write(values)])
+        })
     }
 
     fn read_regs(
@@ -156,15 +243,19 @@ where
         addr: RegisterAddress,
         values: &mut [RegisterValue],
     ) -> Result<(), BusError> {
-        let addr = (addr | RG_OP_READ).to_be_bytes();
+        let cmd = (addr | RG_OP_READ).to_be_bytes();
 
-        self.spi
-            .transaction(&mut [spi::Operation::Write(&addr), spi::Operation::Read(values)])
-            .map_err(|_| BusError::Timeout)
+        self.transaction(addr, |spi| {
+            spi.transaction(&mut [spi::Operation::Write(&cmd), spi::Operation::Read(values)])
+        })
     }
 
-    fn wait_interrupt(&mut self, timeout: Option<Duration>) -> bool {
-        self.interrupt.wait_on_interrupt(timeout)
+    fn wait_interrupt(&mut self, timeout: Option<Duration>) -> Result<(), BusError> {
+        if self.interrupt.wait_on_interrupt(timeout) {
+            Ok(())
+        } else {
+            Err(BusError::Timeout)
+        }
     }
 
     fn delay(&mut self, timeout: Duration) {
@@ -179,3 +270,127 @@ where
         self.reset.hardware_reset()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RadioError;
+
+    struct FlakySpi {
+        failures: usize,
+        transactions: usize,
+    }
+
+    impl spi::ErrorType for FlakySpi {
+        type Error = spi::ErrorKind;
+    }
+
+    impl SpiDevice for FlakySpi {
+        fn transaction(
+            &mut self,
+            operations: &mut [spi::Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            self.transactions += 1;
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(spi::ErrorKind::Other);
+            }
+
+            for op in operations {
+                if let spi::Operation::Read(values) = op {
+                    values.fill(0x34);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    struct NoInterrupt;
+
+    impl BusInterrupt for NoInterrupt {
+        fn wait_on_interrupt(&mut self, _timeout: Option<Duration>) -> bool {
+            false
+        }
+    }
+
+    struct NoClock;
+
+    impl BusClock for NoClock {
+        fn delay(&mut self, _duration: Duration) {}
+
+        fn current_time(&mut self) -> u64 {
+            0
+        }
+    }
+
+    struct NoReset;
+
+    impl BusReset for NoReset {
+        fn hardware_reset(&mut self) -> Result<(), BusError> {
+            Ok(())
+        }
+    }
+
+    fn flaky_bus(failures: usize) -> SpiBus<FlakySpi, NoInterrupt, NoClock, NoReset> {
+        let spi = FlakySpi {
+            failures,
+            transactions: 0,
+        };
+        SpiBus::new(spi, NoInterrupt, NoClock, NoReset)
+    }
+
+    #[test]
+    fn test_retry_recovers_transient_failure() {
+        let mut bus = flaky_bus(1).with_retry(BusRetryPolicy::new(2, Duration::from_millis(1)));
+
+        assert_eq!(bus.read_reg_u8(0x0D), Ok(0x34));
+        assert_eq!(bus.write_reg_u8(0x0D, 0x01), Ok(()));
+        assert_eq!(bus.spi.transactions, 3);
+    }
+
+    #[test]
+    fn test_no_retry_fails_immediately() {
+        let mut bus = flaky_bus(1);
+
+        assert_eq!(bus.read_reg_u8(0x0D), Err(BusError::CommunicationFailure));
+        assert_eq!(bus.spi.transactions, 1);
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let mut bus = flaky_bus(5).with_retry(BusRetryPolicy::new(2, Duration::from_millis(1)));
+
+        assert_eq!(bus.read_reg_u8(0x0D), Err(BusError::CommunicationFailure));
+        assert_eq!(bus.spi.transactions, 3);
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let messages = [
+            (BusError::CommunicationFailure, "SPI transfer failed"),
+            (BusError::ControlFailure, "SPI or GPIO control failed"),
+            (BusError::InvalidAddress, "invalid register address"),
+            (BusError::Timeout, "timed out waiting for an interrupt"),
+            (BusError::ResetFailure, "reset failed"),
+            (
+                BusError::RegisterOutOfRange {
+                    addr: 0x0D,
+                    value: 0x42,
+                },
+                "register 0x000D out of range: 0x42",
+            ),
+        ];
+
+        for (error, message) in messages {
+            assert_eq!(error.to_string(), message);
+        }
+
+        // Only waiting on the transceiver ends in a radio timeout
+        assert_eq!(RadioError::from(BusError::Timeout), RadioError::Timeout);
+        assert_eq!(
+            RadioError::from(BusError::ResetFailure),
+            RadioError::CommunicationFailure
+        );
+    }
+}
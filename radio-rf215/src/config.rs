@@ -34,6 +34,9 @@ impl<I: Bus + Clone> TransreceiverConfigurator for Transreceiver<Band09, I> {
                 agc_control.average_time = crate::radio::AgcAverageTime::Samples8;
                 agc_control.agc_input = false;
 
+                // The sub-GHz band keeps the receiver frontend recommended for
+                // low frequency offset reception whether OFDMC.LFO is set or
+                // not, deployed nodes rely on its selectivity
                 match ofdm.opt {
                     OfdmBandwidthOption::Option1 => {
                         tx_config.sr = FrequencySampleRate::SampleRate1333kHz;
@@ -173,8 +176,7 @@ impl<I: Bus + Clone> TransreceiverConfigurator for Transreceiver<Band24, I> {
                 agc_control.average_time = crate::radio::AgcAverageTime::Samples8;
                 agc_control.agc_input = false;
 
-                // TODO: Configure OFDM.LFO (Reception with Low Frequency Offset)
-                let ofdm_lfo = false;
+                let ofdm_lfo = ofdm.lfo;
 
                 match ofdm.opt {
                     OfdmBandwidthOption::Option1 => {
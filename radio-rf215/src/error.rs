@@ -6,11 +6,17 @@ pub enum RadioError {
     IncorrectState,
     CommunicationFailure,
     Timeout,
+    ChannelBusy,
+    /// The next frame overwrote the RX frame buffer while it was being read
+    BufferOverrun,
 }
 
 impl From<BusError> for RadioError {
-    fn from(_value: BusError) -> Self {
-        Self::CommunicationFailure
+    fn from(value: BusError) -> Self {
+        match value {
+            BusError::Timeout => Self::Timeout,
+            _ => Self::CommunicationFailure,
+        }
     }
 }
 
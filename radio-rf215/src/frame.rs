@@ -35,6 +35,10 @@ impl<const S: usize> Frame<S> {
         self.len
     }
 
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
     pub fn as_buffer_mut(&mut self, len: usize) -> &mut [u8] {
         self.len = if len <= S { len } else { S };
         &mut self.data[..self.len]
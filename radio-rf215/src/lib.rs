@@ -1,14 +1,17 @@
+#![cfg_attr(not(test), no_std)]
+
 use core::fmt;
 
 use bus::{Bus, BusError};
 use error::RadioError;
 use radio_common::{Modulation, RadioConfig, RadioConfigBuilder};
-use transceiver::{Band09, Band24, Transreceiver};
+use transceiver::{Band09, Band24, CcaMode, EnergyCca, Transreceiver, TX_FRAME_END_DURATION};
 
 use crate::{
-    baseband::BasebandFrame,
+    baseband::{AddressFilter, AutoAck, BasebandFrame, PhaseMeasurement},
     config::TransreceiverConfigurator,
-    regs::{BasebandInterruptMask, RadioInterruptMask},
+    radio::{AgcGainMap, IrqCounters, RadioState},
+    regs::{BasebandInterruptMask, RadioInterrupt, RadioInterruptMask},
 };
 
 pub mod baseband;
@@ -20,6 +23,7 @@ pub mod regs;
 pub mod transceiver;
 
 mod config;
+mod shadow;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
@@ -74,6 +78,178 @@ impl Default for RfConfig {
     }
 }
 
+/// Output current of the LVDS I/Q data pads (RF_IQIFC0.DRV)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum IqDriveStrength {
+    Drive1mA = 0x00,
+    Drive2mA = 0x01,
+    Drive3mA = 0x02,
+    Drive4mA = 0x03,
+}
+
+/// Common mode voltage of the LVDS I/Q data pads (RF_IQIFC0.CMV, CMV1V2)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IqCommonMode {
+    Cmv150mV,
+    Cmv200mV,
+    Cmv250mV,
+    Cmv300mV,
+    /// IEEE 1596 compliant 1.2 V common mode
+    Cmv1200mV,
+}
+
+/// Delay of the I/Q data clock against the data lines (RF_IQIFC1.SKEWDRV)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum IqClockSkew {
+    Skew1906ps = 0x00,
+    Skew2906ps = 0x01,
+    Skew3906ps = 0x02,
+    Skew4906ps = 0x03,
+}
+
+/// Reference clock driven on the CLKO pin, e.g. for an external baseband or
+/// an MCU/FPGA clocked by the RF215 (RF_CLKO.OS)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum IqClockOutput {
+    Off = 0x00,
+    Clock26MHz = 0x01,
+    Clock32MHz = 0x02,
+    Clock16MHz = 0x03,
+    Clock8MHz = 0x04,
+    Clock4MHz = 0x05,
+}
+
+/// LVDS I/Q interface settings for an external baseband processor
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct IqConfig {
+    pub drive_strength: IqDriveStrength,
+    pub common_mode: IqCommonMode,
+    pub clock_skew: IqClockSkew,
+    pub clock_output: IqClockOutput,
+    /// Embed transceiver control (e.g. AGC gain, TX start) in the I/Q data stream
+    pub embedded_control: bool,
+}
+
+impl Default for IqConfig {
+    /// Register reset values
+    fn default() -> Self {
+        Self {
+            drive_strength: IqDriveStrength::Drive3mA,
+            common_mode: IqCommonMode::Cmv200mV,
+            clock_skew: IqClockSkew::Skew3906ps,
+            clock_output: IqClockOutput::Clock26MHz,
+            embedded_control: true,
+        }
+    }
+}
+
+const IQIFC0_EEC: u8 = 0b0000_0001;
+const IQIFC0_CMV1V2: u8 = 0b0000_0010;
+const IQIFC0_CMV_SHIFT: u8 = 2;
+const IQIFC0_DRV_SHIFT: u8 = 4;
+const IQIFC0_CONFIG_MASK: u8 = 0b0011_1111;
+const IQIFC1_SKEWDRV_MASK: u8 = 0b0000_0011;
+const CLKO_OS_MASK: u8 = 0b0000_0111;
+const CLKO_DRV_SHIFT: u8 = 3;
+const CLKO_CONFIG_MASK: u8 = 0b0001_1111;
+const XOC_TRIM_MASK: u8 = 0b0000_1111;
+
+/// Highest crystal load trim step of [`Rf215::configure_xtal`]
+pub const XTAL_TRIM_MAX: u8 = XOC_TRIM_MASK;
+
+/// Clock output on the CLKO pin (RF_CLKO)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ClockConfig {
+    pub output: IqClockOutput,
+    pub drive: PadOutputDrive,
+}
+
+impl Default for ClockConfig {
+    /// Register reset values, 26 MHz at 4 mA
+    fn default() -> Self {
+        Self {
+            output: IqClockOutput::Clock26MHz,
+            drive: PadOutputDrive::Drive4mA,
+        }
+    }
+}
+
+impl ClockConfig {
+    fn clko_value(&self) -> u8 {
+        ((self.drive as u8) << CLKO_DRV_SHIFT) | self.output as u8
+    }
+
+    fn from_clko(value: u8) -> Self {
+        let output = match value & CLKO_OS_MASK {
+            0x01 => IqClockOutput::Clock26MHz,
+            0x02 => IqClockOutput::Clock32MHz,
+            0x03 => IqClockOutput::Clock16MHz,
+            0x04 => IqClockOutput::Clock8MHz,
+            0x05 => IqClockOutput::Clock4MHz,
+            // 0x06 and 0x07 are reserved
+            _ => IqClockOutput::Off,
+        };
+
+        let drive = match (value >> CLKO_DRV_SHIFT) & 0b11 {
+            0x00 => PadOutputDrive::Drive2mA,
+            0x01 => PadOutputDrive::Drive4mA,
+            0x02 => PadOutputDrive::Drive6mA,
+            _ => PadOutputDrive::Drive8mA,
+        };
+
+        Self { output, drive }
+    }
+}
+
+impl IqConfig {
+    fn iqifc0_value(&self) -> u8 {
+        let common_mode = match self.common_mode {
+            IqCommonMode::Cmv150mV => 0x00 << IQIFC0_CMV_SHIFT,
+            IqCommonMode::Cmv200mV => 0x01 << IQIFC0_CMV_SHIFT,
+            IqCommonMode::Cmv250mV => 0x02 << IQIFC0_CMV_SHIFT,
+            IqCommonMode::Cmv300mV => 0x03 << IQIFC0_CMV_SHIFT,
+            IqCommonMode::Cmv1200mV => IQIFC0_CMV1V2,
+        };
+
+        let mut value = ((self.drive_strength as u8) << IQIFC0_DRV_SHIFT) | common_mode;
+
+        if self.embedded_control {
+            value |= IQIFC0_EEC;
+        }
+
+        value
+    }
+}
+
+/// Lowest battery monitor threshold (low range, 50 mV steps)
+pub const BATTERY_MONITOR_MIN_MV: u16 = 1700;
+/// Lowest threshold of the high range (75 mV steps)
+const BATTERY_MONITOR_HIGH_RANGE_MV: u16 = 2550;
+/// Highest battery monitor threshold
+pub const BATTERY_MONITOR_MAX_MV: u16 = 3675;
+
+const BMDVC_BMVTH_MASK: u8 = 0b0000_1111;
+const BMDVC_BMHR: u8 = 0b0001_0000;
+const BMDVC_BMS: u8 = 0b0010_0000;
+
+/// Encodes a battery monitor threshold into RF_BMDVC (BMHR and BMVTH)
+///
+/// The threshold is rounded down to the nearest step of its range.
+fn battery_monitor_value(threshold_mv: u16) -> Result<u8, RadioError> {
+    if !(BATTERY_MONITOR_MIN_MV..=BATTERY_MONITOR_MAX_MV).contains(&threshold_mv) {
+        return Err(RadioError::IncorrectConfig);
+    }
+
+    if threshold_mv < BATTERY_MONITOR_HIGH_RANGE_MV {
+        Ok((((threshold_mv - BATTERY_MONITOR_MIN_MV) / 50) as u8).min(BMDVC_BMVTH_MASK))
+    } else {
+        Ok(BMDVC_BMHR | ((threshold_mv - BATTERY_MONITOR_HIGH_RANGE_MV) / 75) as u8)
+    }
+}
+
 #[derive(Debug)]
 pub struct Rf215<I: Bus + Clone> {
     name: &'static str,
@@ -83,6 +259,10 @@ pub struct Rf215<I: Bus + Clone> {
     trx_09: Transreceiver<Band09, I>,
     trx_24: Transreceiver<Band24, I>,
     freq_config: RadioConfig,
+    tx_auto_rx: bool,
+    cca_mode: CcaMode,
+    energy_cca: EnergyCca,
+    tx_timeout: core::time::Duration,
 }
 
 impl<I: Bus + Clone> Rf215<I> {
@@ -92,7 +272,12 @@ impl<I: Bus + Clone> Rf215<I> {
             0x34 => PartNumber::At86Rf215,
             0x35 => PartNumber::At86Rf215Iq,
             0x36 => PartNumber::At86Rf215M,
-            _ => return Err(BusError::CommunicationFailure),
+            value => {
+                return Err(BusError::RegisterOutOfRange {
+                    addr: regs::RG_RF_PN,
+                    value,
+                })
+            }
         };
 
         let version = bus.read_reg_u8(regs::RG_RF_VN)?;
@@ -100,8 +285,8 @@ impl<I: Bus + Clone> Rf215<I> {
         let mut trx_09 = Transreceiver::<Band09, I>::new(bus.clone());
         let mut trx_24 = Transreceiver::<Band24, I>::new(bus.clone());
 
-        trx_09.reset().map_err(|_| BusError::ControlFailure)?;
-        trx_24.reset().map_err(|_| BusError::ControlFailure)?;
+        trx_09.reset().map_err(|_| BusError::ResetFailure)?;
+        trx_24.reset().map_err(|_| BusError::ResetFailure)?;
 
         let freq_config = RadioConfigBuilder::new().build();
         if let Err(_) = trx_09.set_frequency(&freq_config) {
@@ -116,6 +301,10 @@ impl<I: Bus + Clone> Rf215<I> {
             trx_09,
             trx_24,
             freq_config,
+            tx_auto_rx: false,
+            cca_mode: CcaMode::default(),
+            energy_cca: EnergyCca::default(),
+            tx_timeout: TX_FRAME_END_DURATION,
         })
     }
 
@@ -132,6 +321,75 @@ impl<I: Bus + Clone> Rf215<I> {
         Ok(())
     }
 
+    /// Configures the electrical and timing parameters of the LVDS I/Q interface
+    /// and the reference clock output
+    ///
+    /// The settings only take effect in a chip mode with the I/Q interface
+    /// enabled, so call this before switching with [`Rf215::set_mode`]. The
+    /// chip mode and the external loopback are left untouched.
+    pub fn configure_iq_interface(&mut self, config: &IqConfig) -> Result<(), RadioError> {
+        self.bus.modify_reg_u8(
+            regs::RG_RF_IQIFC0,
+            IQIFC0_CONFIG_MASK,
+            config.iqifc0_value(),
+        )?;
+
+        self.bus.modify_reg_u8(
+            regs::RG_RF_IQIFC1,
+            IQIFC1_SKEWDRV_MASK,
+            config.clock_skew as u8,
+        )?;
+
+        self.bus
+            .modify_reg_u8(regs::RG_RF_CLKO, CLKO_OS_MASK, config.clock_output as u8)?;
+
+        Ok(())
+    }
+
+    /// Configures the clock driven on the CLKO pin
+    ///
+    /// Boards clocking an MCU or FPGA from the RF215 typically use 26 MHz
+    /// (the crystal frequency) or 32 MHz; [`IqClockOutput::Off`] saves power
+    /// and spurs when nothing uses the clock. Raise the drive above the 4 mA
+    /// default only for long or heavily loaded clock traces.
+    pub fn configure_clock_output(&mut self, config: ClockConfig) -> Result<(), RadioError> {
+        self.bus
+            .modify_reg_u8(regs::RG_RF_CLKO, CLKO_CONFIG_MASK, config.clko_value())?;
+
+        Ok(())
+    }
+
+    pub fn clock_output(&mut self) -> Result<ClockConfig, RadioError> {
+        Ok(ClockConfig::from_clko(
+            self.bus.read_reg_u8(regs::RG_RF_CLKO)?,
+        ))
+    }
+
+    /// Trims the load capacitance of the 26 MHz crystal (RF_XOC.TRIM)
+    ///
+    /// Each step adds about 0.3 pF to both crystal pins, up to 4.5 pF at
+    /// [`XTAL_TRIM_MAX`], and pulls the reference, and with it every channel
+    /// frequency, lower. 0 (the reset value) suits boards with the specified
+    /// external load capacitors; a board whose carrier measures high is
+    /// trimmed up until it is on frequency, once per board design or unit.
+    pub fn configure_xtal(&mut self, trim: u8) -> Result<(), RadioError> {
+        if trim > XTAL_TRIM_MAX {
+            return Err(RadioError::IncorrectConfig);
+        }
+
+        self.bus
+            .modify_reg_u8(regs::RG_RF_XOC, XOC_TRIM_MASK, trim)?;
+
+        Ok(())
+    }
+
+    pub fn xtal_trim(&mut self) -> Result<u8, RadioError> {
+        Ok(self.bus.read_reg_u8(regs::RG_RF_XOC)? & XOC_TRIM_MASK)
+    }
+
+    /// Selects which of the baseband cores and the I/Q interface are enabled
+    ///
+    /// See [`Rf215::configure_iq_interface`] for the I/Q interface settings.
     pub fn set_mode(&mut self, chip_mode: ChipMode) -> Result<(), RadioError> {
         let chip_mode = (chip_mode as u8) << 4;
 
@@ -187,15 +445,51 @@ impl<I: Bus + Clone> Rf215<I> {
     }
 
     pub fn configure(&mut self, modulation: &Modulation) -> Result<&mut Self, RadioError> {
-        self.trx_09.configure(
-            modulation,
-            &self.trx_09.create_modulation_config(modulation),
-        )?;
+        let config_09 = self.trx_09.create_modulation_config(modulation);
+        let config_24 = self.trx_24.create_modulation_config(modulation);
 
-        self.trx_24.configure(
+        // Register level view of what the modulation resolves to, for field debugging
+        log::trace!(
+            "configure ({}) trx09 {} => {:?}",
+            self.name,
             modulation,
-            &self.trx_24.create_modulation_config(modulation),
-        )?;
+            config_09
+        );
+        log::trace!(
+            "configure ({}) trx24 {} => {:?}",
+            self.name,
+            modulation,
+            config_24
+        );
+
+        self.trx_09.configure(modulation, &config_09)?;
+        self.trx_24.configure(modulation, &config_24)?;
+
+        Ok(self)
+    }
+
+    /// Tunes to `config` and programs `modulation` without receiving in
+    /// between
+    ///
+    /// The transceiver of the band of `config` goes through TRXOFF once for
+    /// both, the other one only gets the modulation.
+    pub fn apply(
+        &mut self,
+        config: &RadioConfig,
+        modulation: &Modulation,
+    ) -> Result<&mut Self, RadioError> {
+        let config_09 = self.trx_09.create_modulation_config(modulation);
+        let config_24 = self.trx_24.create_modulation_config(modulation);
+
+        if self.trx_09.check_band(config.freq) {
+            self.trx_09.apply(config, modulation, &config_09)?;
+            self.trx_24.configure(modulation, &config_24)?;
+        } else {
+            self.trx_09.configure(modulation, &config_09)?;
+            self.trx_24.apply(config, modulation, &config_24)?;
+        }
+
+        self.freq_config = *config;
 
         Ok(self)
     }
@@ -206,6 +500,43 @@ impl<I: Bus + Clone> Rf215<I> {
         Ok(self)
     }
 
+    /// Sets the EVDD voltage below which the BatteryLow interrupt is raised
+    /// and unmasks the interrupt, which stays masked until then
+    pub fn configure_battery_monitor(&mut self, threshold_mv: u16) -> Result<(), RadioError> {
+        let value = battery_monitor_value(threshold_mv)?;
+
+        self.bus
+            .modify_reg_u8(regs::RG_RF_BMDVC, BMDVC_BMHR | BMDVC_BMVTH_MASK, value)?;
+
+        self.trx_09.radio().enable_irq(RadioInterrupt::BatteryLow)?;
+        self.trx_24.radio().enable_irq(RadioInterrupt::BatteryLow)?;
+
+        Ok(())
+    }
+
+    /// Reads the battery monitor status, `true` while EVDD is below the threshold
+    pub fn is_battery_low(&mut self) -> Result<bool, RadioError> {
+        let value = self.bus.read_reg_u8(regs::RG_RF_BMDVC)?;
+
+        Ok(value & BMDVC_BMS == 0)
+    }
+
+    /// IRQs read from both transceivers since the chip was set up
+    pub fn irq_counters(&mut self) -> IrqCounters {
+        let irqs_09 = self.trx_09.radio().irq_counters();
+        let irqs_24 = self.trx_24.radio().irq_counters();
+
+        irqs_09.combine(&irqs_24)
+    }
+
+    /// Consumes a pending BatteryLow interrupt of either transceiver
+    pub fn take_battery_low_irq(&mut self) -> bool {
+        let irq_09 = self.trx_09.radio().take_irq(RadioInterrupt::BatteryLow);
+        let irq_24 = self.trx_24.radio().take_irq(RadioInterrupt::BatteryLow);
+
+        irq_09 || irq_24
+    }
+
     pub fn start_receive(&mut self) -> Result<&mut Self, RadioError> {
         self.trx_09.start_receive()?;
         self.trx_24.start_receive()?;
@@ -213,11 +544,57 @@ impl<I: Bus + Clone> Rf215<I> {
         Ok(self)
     }
 
+    /// Selects the automatic TX to RX turnaround (TX2RX) for `bb_transmit`
+    ///
+    /// TX2RX can't be combined with CCATX, so frames are sent without a clear
+    /// channel assessment while it is enabled.
+    pub fn set_tx_auto_rx(&mut self, enabled: bool) {
+        self.tx_auto_rx = enabled;
+    }
+
+    pub fn tx_auto_rx(&self) -> bool {
+        self.tx_auto_rx
+    }
+
+    /// Selects the clear channel assessment of `bb_transmit` without TX2RX
+    pub fn set_cca_mode(&mut self, mode: CcaMode) {
+        self.cca_mode = mode;
+    }
+
+    pub fn cca_mode(&self) -> CcaMode {
+        self.cca_mode
+    }
+
+    /// Sets the threshold and duration of the energy detection CCA
+    pub fn set_energy_cca(&mut self, energy: EnergyCca) {
+        self.energy_cca = energy;
+    }
+
+    pub fn energy_cca(&self) -> EnergyCca {
+        self.energy_cca
+    }
+
+    /// Limits the wait for the end of a transmitted frame
+    ///
+    /// Should cover the time on air of the frames sent with the current
+    /// modulation, [`TX_FRAME_END_DURATION`] unless set.
+    pub fn set_tx_timeout(&mut self, timeout: core::time::Duration) {
+        self.tx_timeout = timeout;
+    }
+
     pub fn bb_transmit(&mut self, frame: &BasebandFrame) -> Result<(), RadioError> {
         if self.trx_09.check_band(self.freq_config.freq) {
-            self.trx_09.bb_transmit_cca(frame)
+            if self.tx_auto_rx {
+                self.trx_09.bb_transmit_auto_rx(frame, self.tx_timeout)
+            } else {
+                self.trx_09
+                    .bb_transmit_cca(frame, self.cca_mode, &self.energy_cca, self.tx_timeout)
+            }
+        } else if self.tx_auto_rx {
+            self.trx_24.bb_transmit_auto_rx(frame, self.tx_timeout)
         } else {
-            self.trx_24.bb_transmit_cca(frame)
+            self.trx_24
+                .bb_transmit_cca(frame, self.cca_mode, &self.energy_cca, self.tx_timeout)
         }
     }
 
@@ -233,6 +610,25 @@ impl<I: Bus + Clone> Rf215<I> {
         }
     }
 
+    /// Returns the active band to RX after `bb_transmit`, see
+    /// [`Transreceiver::restore_receive`]
+    pub fn restore_receive(&mut self, timeout: core::time::Duration) -> Result<(), RadioError> {
+        if self.trx_09.check_band(self.freq_config.freq) {
+            self.trx_09.restore_receive(timeout)
+        } else {
+            self.trx_24.restore_receive(timeout)
+        }
+    }
+
+    /// Drops a possibly overrun frame buffer and restarts RX on the active band.
+    pub fn flush_receive(&mut self) -> Result<(), RadioError> {
+        if self.trx_09.check_band(self.freq_config.freq) {
+            self.trx_09.flush_receive()
+        } else {
+            self.trx_24.flush_receive()
+        }
+    }
+
     pub fn read_rssi(&mut self) -> Result<i8, RadioError> {
         if self.trx_09.check_band(self.freq_config.freq) {
             self.trx_09.radio().read_rssi()
@@ -249,6 +645,54 @@ impl<I: Bus + Clone> Rf215<I> {
         }
     }
 
+    /// Lets the basebands of both bands append and check an FCS
+    pub fn set_fcs(&mut self, enabled: bool) -> Result<(), RadioError> {
+        self.trx_09.baseband().set_fcs(enabled)?;
+        self.trx_24.baseband().set_fcs(enabled)
+    }
+
+    /// Selects the AGC gain map of both bands
+    pub fn set_agc_gain_map(&mut self, map: AgcGainMap) -> Result<(), RadioError> {
+        self.trx_09.radio().set_agc_gain_map(map)?;
+        self.trx_24.radio().set_agc_gain_map(map)
+    }
+
+    pub fn read_tx_power(&mut self) -> Result<u8, RadioError> {
+        if self.trx_09.check_band(self.freq_config.freq) {
+            self.trx_09.radio().read_tx_power()
+        } else {
+            self.trx_24.radio().read_tx_power()
+        }
+    }
+
+    pub fn measure_phase(&mut self) -> Result<PhaseMeasurement, RadioError> {
+        if self.trx_09.check_band(self.freq_config.freq) {
+            self.trx_09.baseband().measure_phase()
+        } else {
+            self.trx_24.baseband().measure_phase()
+        }
+    }
+
+    /// Programs frame filter unit 0 of both basebands, `None` disables it
+    pub fn set_address_filter(&mut self, filter: Option<&AddressFilter>) -> Result<(), RadioError> {
+        self.trx_09.baseband().set_address_filter(filter)?;
+        self.trx_24.baseband().set_address_filter(filter)?;
+
+        Ok(())
+    }
+
+    /// Enables the automatic acknowledgement on both basebands
+    ///
+    /// Needs the address filter, frames which don't pass it aren't
+    /// acknowledged. Survives [`Rf215::set_tx_auto_rx`] and the transmit
+    /// modes, not [`Rf215::reset`].
+    pub fn set_auto_ack(&mut self, ack: Option<&AutoAck>) -> Result<(), RadioError> {
+        self.trx_09.baseband().set_auto_ack(ack)?;
+        self.trx_24.baseband().set_auto_ack(ack)?;
+
+        Ok(())
+    }
+
     pub fn trx_09(&mut self) -> &mut Transreceiver<Band09, I> {
         &mut self.trx_09
     }
@@ -257,6 +701,15 @@ impl<I: Bus + Clone> Rf215<I> {
         &mut self.trx_24
     }
 
+    /// Borrows both transceivers at once, e.g. to drive the bands from
+    /// separate threads
+    ///
+    /// The bus has to serialize register transactions for that. A transceiver
+    /// reset is a reset of the whole chip, use [`Rf215::reset`] instead.
+    pub fn split(&mut self) -> (&mut Transreceiver<Band09, I>, &mut Transreceiver<Band24, I>) {
+        (&mut self.trx_09, &mut self.trx_24)
+    }
+
     pub fn reset(&mut self) -> Result<(), RadioError> {
         self.trx_09.reset()?;
         self.trx_24.reset()?;
@@ -264,6 +717,34 @@ impl<I: Bus + Clone> Rf215<I> {
         Ok(())
     }
 
+    /// Pulls the reset line and leaves the chip as the reset did
+    ///
+    /// Unlike [`Rf215::reset`] no state is commanded and no IRQ cleared, so
+    /// [`Rf215::wait_wakeup`] and [`Rf215::is_trx_off`] can check it came up.
+    pub fn hardware_reset(&mut self) -> Result<(), RadioError> {
+        self.bus.hardware_reset()?;
+
+        self.trx_09.clear_register_state();
+        self.trx_24.clear_register_state();
+
+        Ok(())
+    }
+
+    /// Waits for the WAKEUP IRQ the chip raises once a reset is complete
+    pub fn wait_wakeup(&mut self, timeout: core::time::Duration) -> bool {
+        let wakeup = RadioInterruptMask::new()
+            .add_irq(RadioInterrupt::Wakeup)
+            .build();
+
+        self.trx_09.radio().wait_irq(wakeup, timeout).is_some()
+    }
+
+    /// Whether both transceivers are in TRXOFF, as they are after a reset
+    pub fn is_trx_off(&mut self) -> Result<bool, RadioError> {
+        Ok(self.trx_09.radio().read_state()? == RadioState::TrxOff
+            && self.trx_24.radio().read_state()? == RadioState::TrxOff)
+    }
+
     pub fn part_number(&self) -> PartNumber {
         self.part_number
     }
@@ -276,3 +757,333 @@ impl<I: Bus + Clone> Rf215<I> {
         self.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::regs::{BasebandInterrupt, RegisterAddress, RegisterValue};
+
+    #[derive(Clone, Debug)]
+    struct MockBus(Rc<RefCell<Vec<RegisterValue>>>);
+
+    impl Bus for MockBus {
+        fn write_regs(
+            &mut self,
+            addr: RegisterAddress,
+            values: &[RegisterValue],
+        ) -> Result<(), BusError> {
+            let addr = addr as usize;
+            self.0.borrow_mut()[addr..addr + values.len()].copy_from_slice(values);
+            Ok(())
+        }
+
+        fn read_regs(
+            &mut self,
+            addr: RegisterAddress,
+            values: &mut [RegisterValue],
+        ) -> Result<(), BusError> {
+            let addr = addr as usize;
+            values.copy_from_slice(&self.0.borrow()[addr..addr + values.len()]);
+            Ok(())
+        }
+
+        fn wait_interrupt(
+            &mut self,
+            _timeout: Option<core::time::Duration>,
+        ) -> Result<(), BusError> {
+            Err(BusError::Timeout)
+        }
+
+        fn delay(&mut self, _timeout: core::time::Duration) {}
+
+        fn current_time(&mut self) -> u64 {
+            0
+        }
+
+        fn hardware_reset(&mut self) -> Result<(), BusError> {
+            Ok(())
+        }
+    }
+
+    fn rf215(bus: &MockBus) -> Rf215<MockBus> {
+        Rf215 {
+            name: "mock",
+            part_number: PartNumber::At86Rf215,
+            version: 3,
+            bus: bus.clone(),
+            trx_09: Transreceiver::new(bus.clone()),
+            trx_24: Transreceiver::new(bus.clone()),
+            freq_config: RadioConfigBuilder::new().build(),
+            tx_auto_rx: false,
+            cca_mode: CcaMode::default(),
+            energy_cca: EnergyCca::default(),
+            tx_timeout: TX_FRAME_END_DURATION,
+        }
+    }
+
+    #[test]
+    fn test_battery_monitor_threshold_encoding() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let bmdvc = || bus.0.borrow()[regs::RG_RF_BMDVC as usize];
+
+        // Status bit is read-only and must be preserved
+        bus.0.borrow_mut()[regs::RG_RF_BMDVC as usize] = BMDVC_BMS;
+
+        rf.configure_battery_monitor(1700)
+            .expect("low range minimum");
+        assert_eq!(bmdvc(), BMDVC_BMS);
+
+        rf.configure_battery_monitor(2100).expect("low range");
+        assert_eq!(bmdvc(), BMDVC_BMS | 8);
+
+        rf.configure_battery_monitor(2550)
+            .expect("high range minimum");
+        assert_eq!(bmdvc(), BMDVC_BMS | BMDVC_BMHR);
+
+        rf.configure_battery_monitor(3300).expect("high range");
+        assert_eq!(bmdvc(), BMDVC_BMS | BMDVC_BMHR | 10);
+
+        rf.configure_battery_monitor(3675)
+            .expect("high range maximum");
+        assert_eq!(bmdvc(), BMDVC_BMS | BMDVC_BMHR | 15);
+
+        assert_eq!(
+            rf.configure_battery_monitor(1600),
+            Err(RadioError::IncorrectConfig)
+        );
+        assert_eq!(
+            rf.configure_battery_monitor(3700),
+            Err(RadioError::IncorrectConfig)
+        );
+
+        assert!(!rf.is_battery_low().expect("status"));
+
+        // Monitoring unmasks BatteryLow on both transceivers
+        let irqm_09 = bus.0.borrow()[(regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_IRQM) as usize];
+        let irqm_24 = bus.0.borrow()[(regs::RG_RF24_BASE_ADDRESS + regs::RG_RFXX_IRQM) as usize];
+        assert_eq!(irqm_09, RadioInterrupt::BatteryLow as u8);
+        assert_eq!(irqm_24, RadioInterrupt::BatteryLow as u8);
+    }
+
+    #[test]
+    fn test_configure_iq_interface() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let reg = |addr: RegisterAddress| bus.0.borrow()[addr as usize];
+
+        // CLKO output drive is kept
+        bus.0.borrow_mut()[regs::RG_RF_CLKO as usize] = 0b0001_1001;
+
+        rf.set_iq_loopback(true).expect("loopback");
+
+        // Interface first, then the chip mode which enables it
+        rf.configure_iq_interface(&IqConfig {
+            drive_strength: IqDriveStrength::Drive4mA,
+            common_mode: IqCommonMode::Cmv250mV,
+            clock_skew: IqClockSkew::Skew4906ps,
+            clock_output: IqClockOutput::Clock32MHz,
+            embedded_control: false,
+        })
+        .expect("iq config");
+        rf.set_mode(ChipMode::Radio).expect("chip mode");
+
+        // EXTLB | DRV=3 | CMV=2
+        assert_eq!(reg(regs::RG_RF_IQIFC0), 0b1011_1000);
+        // CHPM=1 | SKEWDRV=3
+        assert_eq!(reg(regs::RG_RF_IQIFC1), 0b0001_0011);
+        // DRVCLKO=3 | OS=2
+        assert_eq!(reg(regs::RG_RF_CLKO), 0b0001_1010);
+
+        rf.configure_iq_interface(&IqConfig {
+            common_mode: IqCommonMode::Cmv1200mV,
+            ..Default::default()
+        })
+        .expect("iq config");
+
+        // EXTLB | DRV=2 | CMV1V2 | EEC
+        assert_eq!(reg(regs::RG_RF_IQIFC0), 0b1010_0011);
+        // CHPM=1 | SKEWDRV=2
+        assert_eq!(reg(regs::RG_RF_IQIFC1), 0b0001_0010);
+        // DRVCLKO=3 | OS=1
+        assert_eq!(reg(regs::RG_RF_CLKO), 0b0001_1001);
+    }
+
+    #[test]
+    fn test_configure_clock() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let reg = |addr: RegisterAddress| bus.0.borrow()[addr as usize];
+
+        // Reset values, the crystal fast start-up (FS) is kept
+        bus.0.borrow_mut()[regs::RG_RF_CLKO as usize] = 0b0000_1001;
+        bus.0.borrow_mut()[regs::RG_RF_XOC as usize] = 0b0001_0000;
+        assert_eq!(rf.clock_output(), Ok(ClockConfig::default()));
+
+        let config = ClockConfig {
+            output: IqClockOutput::Clock32MHz,
+            drive: PadOutputDrive::Drive8mA,
+        };
+        rf.configure_clock_output(config).expect("clock output");
+        // DRV=3 | OS=2
+        assert_eq!(reg(regs::RG_RF_CLKO), 0b0001_1010);
+        assert_eq!(rf.clock_output(), Ok(config));
+
+        rf.configure_clock_output(ClockConfig {
+            output: IqClockOutput::Off,
+            drive: PadOutputDrive::Drive2mA,
+        })
+        .expect("clock off");
+        assert_eq!(reg(regs::RG_RF_CLKO), 0b0000_0000);
+
+        rf.configure_xtal(9).expect("crystal trim");
+        // FS | TRIM=9
+        assert_eq!(reg(regs::RG_RF_XOC), 0b0001_1001);
+        assert_eq!(rf.xtal_trim(), Ok(9));
+
+        assert_eq!(
+            rf.configure_xtal(XTAL_TRIM_MAX + 1),
+            Err(RadioError::IncorrectConfig)
+        );
+        assert_eq!(reg(regs::RG_RF_XOC), 0b0001_1001);
+    }
+
+    #[test]
+    fn test_irq_counters_count_status_reads() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+
+        bus.0.borrow_mut()[regs::RG_RF09_IRQS as usize] =
+            RadioInterrupt::TransceiverError as u8 | RadioInterrupt::BatteryLow as u8;
+        bus.0.borrow_mut()[regs::RG_RF24_IRQS as usize] = RadioInterrupt::IqIfSyncFail as u8;
+        rf.update_irqs().expect("irqs read");
+
+        // The mock keeps the status latched, so the sub-GHz IRQs count again
+        bus.0.borrow_mut()[regs::RG_RF24_IRQS as usize] = 0;
+        rf.update_irqs().expect("irqs read");
+
+        assert_eq!(
+            rf.irq_counters(),
+            IrqCounters {
+                transceiver_error: 2,
+                battery_low: 2,
+                iq_if_sync_fail: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_receive_detects_buffer_overrun() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let irqs = regs::RG_BBC0_IRQS as usize;
+        let rxfll = (regs::RG_BBC0_BASE_ADDRESS + regs::RG_BBCX_RXFLL) as usize;
+        let timeout = core::time::Duration::from_millis(1);
+
+        bus.0.borrow_mut()[rxfll] = 4;
+        bus.0.borrow_mut()[regs::RG_BBC0_FRAME_BUFFER_ADDRESS as usize] = 0xA5;
+
+        let mut frame = BasebandFrame::new();
+
+        bus.0.borrow_mut()[irqs] = BasebandInterrupt::ReceiverFrameEnd as u8;
+        rf.trx_09()
+            .bb_receive(&mut frame, timeout)
+            .expect("received frame");
+        assert_eq!(frame.as_slice(), &[0xA5, 0, 0, 0]);
+
+        // The mock keeps the status latched, so the frame start shows up again
+        // after the frame was read, as if the next frame was being received
+        bus.0.borrow_mut()[irqs] =
+            BasebandInterrupt::ReceiverFrameStart as u8 | BasebandInterrupt::ReceiverFrameEnd as u8;
+        assert_eq!(
+            rf.trx_09().bb_receive(&mut frame, timeout),
+            Err(RadioError::BufferOverrun)
+        );
+
+        bus.0.borrow_mut()[irqs] = BasebandInterrupt::FrameBufferLevelIndication as u8
+            | BasebandInterrupt::ReceiverFrameEnd as u8;
+        assert_eq!(
+            rf.trx_09().bb_receive(&mut frame, timeout),
+            Err(RadioError::BufferOverrun)
+        );
+    }
+
+    #[test]
+    fn test_measure_phase_reads_pmu() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let bbc0 = |reg: RegisterAddress| (regs::RG_BBC0_BASE_ADDRESS + reg) as usize;
+
+        // AVG and SYNC settings must survive enabling the PMU
+        bus.0.borrow_mut()[bbc0(regs::RG_BBCX_PMUC)] = 0b0000_1010;
+        bus.0.borrow_mut()[bbc0(regs::RG_BBCX_PMUVAL)] = 0xC0;
+        bus.0.borrow_mut()[bbc0(regs::RG_BBCX_PMUQF)] = 0x15;
+        bus.0.borrow_mut()[bbc0(regs::RG_BBCX_PMUI)] = 0x7F;
+        bus.0.borrow_mut()[bbc0(regs::RG_BBCX_PMUQ)] = 0x81;
+
+        let measurement = rf.measure_phase().expect("phase measurement");
+
+        assert_eq!(bus.0.borrow()[bbc0(regs::RG_BBCX_PMUC)], 0b0000_1011);
+        assert_eq!(
+            measurement,
+            PhaseMeasurement {
+                phase: -64,
+                quality: 0x15,
+                i: 127,
+                q: -127,
+            }
+        );
+        assert_eq!(measurement.phase_radians(), -core::f32::consts::FRAC_PI_2);
+
+        // The 2.4 GHz baseband has its own PMU
+        let bbc1 = (regs::RG_BBC1_BASE_ADDRESS + regs::RG_BBCX_PMUC) as usize;
+        assert_eq!(bus.0.borrow()[bbc1], 0);
+    }
+
+    #[test]
+    fn test_hardware_reset_wakeup_and_trx_off() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let state_09 = (regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_STATE) as usize;
+        let state_24 = (regs::RG_RF24_BASE_ADDRESS + regs::RG_RFXX_STATE) as usize;
+
+        rf.hardware_reset().expect("reset");
+
+        bus.0.borrow_mut()[regs::RG_RF09_IRQS as usize] = RadioInterrupt::Wakeup as u8;
+        bus.0.borrow_mut()[state_09] = 0x02;
+        bus.0.borrow_mut()[state_24] = 0x02;
+
+        assert!(rf.wait_wakeup(core::time::Duration::from_millis(1)));
+        assert!(rf.is_trx_off().expect("state"));
+
+        // Still in transition on the 2.4 GHz side
+        bus.0.borrow_mut()[state_24] = 0x06;
+        assert!(!rf.is_trx_off().expect("state"));
+    }
+
+    #[test]
+    fn test_agc_gain_map_register_writes() {
+        let bus = MockBus(Rc::new(RefCell::new(vec![0; 0x4000])));
+        let mut rf = rf215(&bus);
+        let auxs_09 = (regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_AUXS) as usize;
+        let auxs_24 = (regs::RG_RF24_BASE_ADDRESS + regs::RG_RFXX_AUXS) as usize;
+
+        // External LNA bypass and PA voltage must survive the map change
+        bus.0.borrow_mut()[auxs_09] = 0b1000_0010;
+        bus.0.borrow_mut()[auxs_24] = 0b1000_0010;
+
+        for (map, agcmap) in [
+            (AgcGainMap::Extranal9dB, 0b0010_0000),
+            (AgcGainMap::Extranal12dB, 0b0100_0000),
+            (AgcGainMap::Internal, 0b0000_0000),
+        ] {
+            rf.set_agc_gain_map(map).expect("gain map");
+
+            assert_eq!(bus.0.borrow()[auxs_09], 0b1000_0010 | agcmap);
+            assert_eq!(bus.0.borrow()[auxs_24], 0b1000_0010 | agcmap);
+        }
+    }
+}
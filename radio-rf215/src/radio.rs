@@ -6,6 +6,7 @@ use crate::{
     bus::Bus,
     error::RadioError,
     regs::{self, RadioInterruptMask, RegisterAddress},
+    shadow::{RegisterShadow, RegisterWrite},
 };
 
 pub trait Band {
@@ -69,6 +70,8 @@ pub enum AgcAverageTime {
     Samples64 = 0x03,
 }
 
+/// AGC Gain Map
+/// Gain of an external LNA the AGC accounts for in RSSI and energy detection
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum AgcGainMap {
@@ -92,6 +95,7 @@ pub enum AgcTargetLevel {
     TargetN42dB = 0x07,
 }
 
+#[derive(Debug)]
 pub struct AgcReceiverGain {
     pub target_level: AgcTargetLevel,
     pub gcw: u8,
@@ -107,6 +111,7 @@ impl Default for AgcReceiverGain {
 }
 
 // 6.2.5.3 RFn_AGCC – Receiver AGC Control 0
+#[derive(Debug)]
 pub struct AgcReceiverControl {
     pub agc_input: bool,              // This bit controls the input signal of the AGC
     pub average_time: AgcAverageTime, // The time of averaging RX data samples for the AGC values is defined by number of samples
@@ -197,6 +202,7 @@ pub enum ReceiverBandwidth {
 }
 
 /// Transmitter Frontend Configuration
+#[derive(Debug)]
 pub struct RadioTransmitterConfig {
     pub sr: FrequencySampleRate,
     pub rcut: RelativeCutOff,
@@ -220,6 +226,7 @@ impl Default for RadioTransmitterConfig {
 }
 
 /// Receiver Frontend Configuration
+#[derive(Debug)]
 pub struct RadioReceiverConfig {
     pub sr: FrequencySampleRate,
     pub rcut: RelativeCutOff,
@@ -240,6 +247,7 @@ impl Default for RadioReceiverConfig {
     }
 }
 
+#[derive(Debug)]
 pub struct RadioTransreceiverConfig {
     pub tx_config: RadioTransmitterConfig,
     pub rx_config: RadioReceiverConfig,
@@ -312,6 +320,63 @@ pub enum RadioCommand {
     Reset = 0x7,
 }
 
+/// Number of times each radio IRQ was read as set
+///
+/// The IRQ status is cleared on read, so every count is one occurrence.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IrqCounters {
+    pub wakeup: u32,
+    pub transceiver_ready: u32,
+    pub energy_detection: u32,
+    pub battery_low: u32,
+    pub transceiver_error: u32,
+    pub iq_if_sync_fail: u32,
+}
+
+impl IrqCounters {
+    /// Counts each IRQ set in `irqs`
+    pub fn record(&mut self, irqs: &RadioInterruptMask) {
+        let counters = [
+            (&mut self.wakeup, regs::RadioInterrupt::Wakeup),
+            (
+                &mut self.transceiver_ready,
+                regs::RadioInterrupt::TransceiverReady,
+            ),
+            (
+                &mut self.energy_detection,
+                regs::RadioInterrupt::EnergyDetectionCompletion,
+            ),
+            (&mut self.battery_low, regs::RadioInterrupt::BatteryLow),
+            (
+                &mut self.transceiver_error,
+                regs::RadioInterrupt::TransceiverError,
+            ),
+            (
+                &mut self.iq_if_sync_fail,
+                regs::RadioInterrupt::IqIfSyncFail,
+            ),
+        ];
+
+        for (count, irq) in counters {
+            if irqs.has_irq(irq) {
+                *count = count.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Sum of both counters, e.g. of the two transceivers of a chip
+    pub fn combine(&self, other: &IrqCounters) -> IrqCounters {
+        IrqCounters {
+            wakeup: self.wakeup.wrapping_add(other.wakeup),
+            transceiver_ready: self.transceiver_ready.wrapping_add(other.transceiver_ready),
+            energy_detection: self.energy_detection.wrapping_add(other.energy_detection),
+            battery_low: self.battery_low.wrapping_add(other.battery_low),
+            transceiver_error: self.transceiver_error.wrapping_add(other.transceiver_error),
+            iq_if_sync_fail: self.iq_if_sync_fail.wrapping_add(other.iq_if_sync_fail),
+        }
+    }
+}
+
 /// Represents radio module part of the transceiver
 /// B is a sub-GHz or 2.4GHz band
 #[derive(Debug)]
@@ -323,6 +388,9 @@ where
     _band: PhantomData<B>,
     bus: I,
     irqs: RadioInterruptMask,
+    irq_counters: IrqCounters,
+    /// Frontend registers as last written by the configuration calls
+    shadow: RegisterShadow,
 }
 
 impl<B, I> Radio<B, I>
@@ -335,6 +403,8 @@ where
             _band: PhantomData::default(),
             bus,
             irqs: RadioInterruptMask::new(),
+            irq_counters: IrqCounters::default(),
+            shadow: RegisterShadow::new(),
         }
     }
 
@@ -366,6 +436,13 @@ where
         Ok(())
     }
 
+    /// Unmasks a single interrupt, keeping the rest of the mask
+    pub fn enable_irq(&mut self, irq: regs::RadioInterrupt) -> Result<(), RadioError> {
+        self.bus
+            .modify_reg_u8(Self::abs_reg(regs::RG_RFXX_IRQM), irq as u8, irq as u8)?;
+        Ok(())
+    }
+
     pub fn wait_on_state<F: Fn(RadioState) -> bool>(
         &mut self,
         timeout: core::time::Duration,
@@ -416,8 +493,11 @@ where
         Ok(state)
     }
 
-    pub fn wait_interrupt(&mut self, timeout: Option<core::time::Duration>) -> bool {
-        self.bus.wait_interrupt(timeout)
+    pub fn wait_interrupt(
+        &mut self,
+        timeout: Option<core::time::Duration>,
+    ) -> Result<(), RadioError> {
+        Ok(self.bus.wait_interrupt(timeout)?)
     }
 
     pub fn receive(&mut self) -> Result<(), RadioError> {
@@ -509,6 +589,37 @@ where
         Ok(())
     }
 
+    /// Transitions into TRXPREP and waits until the state is reached, which
+    /// happens once the frequency settling is completed
+    ///
+    /// The state register is polled instead of waiting for TRXRDY, which is
+    /// never reported while the radio interrupts are masked.
+    pub fn wait_frequency_settled(
+        &mut self,
+        timeout: core::time::Duration,
+    ) -> Result<(), RadioError> {
+        self.set_state(RadioState::TrxPrep)?;
+
+        let deadline = self.bus.deadline(timeout);
+        while self.read_state()? != RadioState::TrxPrep {
+            if self.bus.deadline_reached(deadline) {
+                return Err(RadioError::Timeout);
+            }
+
+            self.bus.delay(core::time::Duration::from_micros(50));
+        }
+
+        // Drop the TRXRDY of this transition so it doesn't satisfy a later wait
+        self.update_irqs()?;
+        self.irqs.clear_irqs(
+            &RadioInterruptMask::new()
+                .add_irq(regs::RadioInterrupt::TransceiverReady)
+                .build(),
+        );
+
+        Ok(())
+    }
+
     pub fn read_rssi(&mut self) -> Result<i8, RadioError> {
         let value = self.bus.read_reg_u8(Self::abs_reg(regs::RG_RFXX_RSSI))?;
         let rssi = value as i8;
@@ -527,6 +638,27 @@ where
         Ok(edv)
     }
 
+    /// Reads back the power field of the PAC register, the output power the
+    /// transmitter is actually set to
+    pub fn read_tx_power(&mut self) -> Result<u8, RadioError> {
+        let pac = self.bus.read_reg_u8(Self::abs_reg(regs::RG_RFXX_PAC))?;
+
+        Ok(pac & 0b0001_1111)
+    }
+
+    /// Selects the AGC gain map, leaving the other AUXS settings as they are
+    pub fn set_agc_gain_map(&mut self, map: AgcGainMap) -> Result<(), RadioError> {
+        const AGCMAP_MASK: u8 = 0b0110_0000;
+
+        self.bus.modify_reg_u8(
+            Self::abs_reg(regs::RG_RFXX_AUXS),
+            AGCMAP_MASK,
+            (map as u8) << 5,
+        )?;
+
+        Ok(())
+    }
+
     pub fn set_ed_mode(&mut self, mode: EnergyDetectionMode) -> Result<(), RadioError> {
         self.bus
             .write_reg_u8(Self::abs_reg(regs::RG_RFXX_EDC), mode as u8)?;
@@ -535,18 +667,22 @@ where
     }
 
     pub fn set_ed_duration(&mut self, duration: core::time::Duration) -> Result<(), RadioError> {
+        let write = Self::ed_duration_write(duration)?;
+        self.write_shadowed(&write)
+    }
+
+    fn ed_duration_write(duration: core::time::Duration) -> Result<RegisterWrite, RadioError> {
         let dtb_mul: [u32; 4] = [2, 8, 32, 128];
 
+        // Rounded up, a shorter measurement than asked for could miss a
+        // transmission a listen before talk rule requires to be heard
         let expected_duration = duration.as_micros() as u32;
         for i in 0..dtb_mul.len() {
-            let df = expected_duration / dtb_mul[i];
-            if df < 63 {
+            let df = expected_duration.div_ceil(dtb_mul[i]);
+            if df <= 63 {
                 let edd = ((df as u8) << 2) | (i as u8);
 
-                self.bus
-                    .write_reg_u8(Self::abs_reg(regs::RG_RFXX_EDD), edd)?;
-
-                return Ok(());
+                return Ok(RegisterWrite::new(regs::RG_RFXX_EDD, edd));
             }
         }
 
@@ -571,7 +707,8 @@ where
                 }
             }
 
-            self.bus
+            let _ = self
+                .bus
                 .wait_interrupt(Some(core::time::Duration::from_micros(500)));
         }
 
@@ -596,7 +733,8 @@ where
                 }
             }
 
-            self.bus
+            let _ = self
+                .bus
                 .wait_interrupt(Some(core::time::Duration::from_micros(500)));
         }
 
@@ -611,7 +749,21 @@ where
 
     fn read_irqs(&mut self) -> Result<RadioInterruptMask, RadioError> {
         let irq_status = self.bus.read_reg_u8(B::RADIO_IRQ_ADDRESS)?;
-        Ok(RadioInterruptMask::new_from_mask(irq_status))
+        let irqs = RadioInterruptMask::new_from_mask(irq_status);
+        self.irq_counters.record(&irqs);
+        Ok(irqs)
+    }
+
+    /// IRQs read since the radio was created
+    pub fn irq_counters(&self) -> IrqCounters {
+        self.irq_counters
+    }
+
+    /// Consumes a latched interrupt, returns `true` if it was pending
+    pub fn take_irq(&mut self, irq: regs::RadioInterrupt) -> bool {
+        self.irqs
+            .retrieve(&RadioInterruptMask::new().add_irq(irq).build())
+            .is_some()
     }
 
     pub fn clear_irqs(&mut self) -> Result<&mut Self, RadioError> {
@@ -624,90 +776,107 @@ where
         &mut self,
         config: &RadioTransmitterConfig,
     ) -> Result<(), RadioError> {
-        // Transmitter TX Digital Frontend
-        {
-            let mut txdfe = self.bus.read_reg_u8(Self::abs_reg(regs::RG_RFXX_TXDFE))?;
-
-            // Clear SR and RCUT bits
-            txdfe = txdfe & 0b0001_0000;
-            txdfe = txdfe | (config.sr as u8) | ((config.rcut as u8) << 5);
-
-            self.bus
-                .write_reg_u8(Self::abs_reg(regs::RG_RFXX_TXDFE), txdfe)?;
+        for write in Self::transmitter_writes(config) {
+            self.write_shadowed(&write)?;
         }
 
-        // Transmitter Filter Cutoff Control and PA Ramp Time
-        {
-            let mut txcutc = self.bus.read_reg_u8(Self::abs_reg(regs::RG_RFXX_TXCUTC))?;
-
-            // Clear SR and RCUT bits
-            txcutc = txcutc & 0b0011_0000;
-            txcutc = txcutc | (config.lpfcut as u8) | ((config.paramp as u8) << 6);
+        Ok(())
+    }
 
-            self.bus
-                .write_reg_u8(Self::abs_reg(regs::RG_RFXX_TXCUTC), txcutc)?;
+    pub fn configure_receiver(&mut self, config: &RadioReceiverConfig) -> Result<(), RadioError> {
+        for write in Self::receiver_writes(config) {
+            self.write_shadowed(&write)?;
         }
 
-        // Transmitter Power Amplifier Control
-        {
-            let mut pac = 0u8;
-
-            pac = pac | core::cmp::min(31, config.power);
-            pac = pac | ((config.pacur as u8) << 5);
+        Ok(())
+    }
 
-            self.bus
-                .write_reg_u8(Self::abs_reg(regs::RG_RFXX_PAC), pac)?;
+    pub fn configure_transreceiver(
+        &mut self,
+        config: &RadioTransreceiverConfig,
+    ) -> Result<(), RadioError> {
+        for write in Self::transreceiver_writes(config)? {
+            self.write_shadowed(&write)?;
         }
 
         Ok(())
     }
 
-    pub fn configure_receiver(&mut self, config: &RadioReceiverConfig) -> Result<(), RadioError> {
-        // Receiver Digital Frontend
-        {
-            let mut rxdfe = self.bus.read_reg_u8(Self::abs_reg(regs::RG_RFXX_RXDFE))?;
+    /// Returns `true` if the frontend registers were last written with `config`
+    pub(crate) fn is_configured(
+        &self,
+        config: &RadioTransreceiverConfig,
+    ) -> Result<bool, RadioError> {
+        Ok(Self::transreceiver_writes(config)?
+            .iter()
+            .all(|write| self.shadow.is_current(write)))
+    }
 
-            // Clear SR and RCUT bits
-            rxdfe = rxdfe & 0b0001_0000;
-            rxdfe = rxdfe | (config.sr as u8) | ((config.rcut as u8) << 5);
+    fn transreceiver_writes(
+        config: &RadioTransreceiverConfig,
+    ) -> Result<[RegisterWrite; 8], RadioError> {
+        let [txdfe, txcutc, pac] = Self::transmitter_writes(&config.tx_config);
+        let [rxdfe, rxbwc] = Self::receiver_writes(&config.rx_config);
+
+        Ok([
+            txdfe,
+            txcutc,
+            pac,
+            rxdfe,
+            rxbwc,
+            Self::agc_control_write(&config.agc_control),
+            Self::agc_gain_write(&config.agc_gain),
+            Self::ed_duration_write(config.edd)?,
+        ])
+    }
+
+    fn transmitter_writes(config: &RadioTransmitterConfig) -> [RegisterWrite; 3] {
+        // Transmitter TX Digital Frontend, DM bit is kept
+        let txdfe = RegisterWrite::masked(
+            regs::RG_RFXX_TXDFE,
+            0b1110_1111,
+            (config.sr as u8) | ((config.rcut as u8) << 5),
+        );
 
-            self.bus
-                .write_reg_u8(Self::abs_reg(regs::RG_RFXX_RXDFE), rxdfe)?;
-        }
+        // Transmitter Filter Cutoff Control and PA Ramp Time
+        let txcutc = RegisterWrite::masked(
+            regs::RG_RFXX_TXCUTC,
+            0b1100_1111,
+            (config.lpfcut as u8) | ((config.paramp as u8) << 6),
+        );
 
-        // Receiver Filter Bandwidth Control
-        {
-            let mut rxbwc = self.bus.read_reg_u8(Self::abs_reg(regs::RG_RFXX_RXBWC))?;
+        // Transmitter Power Amplifier Control
+        let mut pac = 0u8;
 
-            rxbwc = rxbwc & 0b1100_0000;
-            rxbwc = rxbwc | (config.bw as u8);
+        pac = pac | core::cmp::min(31, config.power);
+        pac = pac | ((config.pacur as u8) << 5);
 
-            if config.if_inversion {
-                rxbwc = rxbwc | 0b0010_0000;
-            }
+        [txdfe, txcutc, RegisterWrite::new(regs::RG_RFXX_PAC, pac)]
+    }
 
-            if config.if_shift {
-                rxbwc = rxbwc | 0b0001_0000;
-            }
+    fn receiver_writes(config: &RadioReceiverConfig) -> [RegisterWrite; 2] {
+        // Receiver Digital Frontend
+        let rxdfe = RegisterWrite::masked(
+            regs::RG_RFXX_RXDFE,
+            0b1110_1111,
+            (config.sr as u8) | ((config.rcut as u8) << 5),
+        );
 
-            self.bus
-                .write_reg_u8(Self::abs_reg(regs::RG_RFXX_RXBWC), rxbwc)?;
-        }
+        // Receiver Filter Bandwidth Control
+        let mut rxbwc = config.bw as u8;
 
-        Ok(())
-    }
+        if config.if_inversion {
+            rxbwc = rxbwc | 0b0010_0000;
+        }
 
-    pub fn configure_transreceiver(
-        &mut self,
-        config: &RadioTransreceiverConfig,
-    ) -> Result<(), RadioError> {
-        self.configure_transmitter(&config.tx_config)?;
-        self.configure_receiver(&config.rx_config)?;
-        self.set_agc_control(&config.agc_control)?;
-        self.set_agc_gain(&config.agc_gain)?;
-        self.set_ed_duration(config.edd)?;
+        if config.if_shift {
+            rxbwc = rxbwc | 0b0001_0000;
+        }
 
-        Ok(())
+        [
+            rxdfe,
+            RegisterWrite::masked(regs::RG_RFXX_RXBWC, 0b0011_1111, rxbwc),
+        ]
     }
 
     pub fn set_control_pad(&mut self, config: FrontendPinConfig) -> Result<&mut Self, RadioError> {
@@ -720,6 +889,16 @@ where
     }
 
     pub fn set_agc_control(&mut self, agc_control: &AgcReceiverControl) -> Result<(), RadioError> {
+        self.write_shadowed(&Self::agc_control_write(agc_control))
+    }
+
+    pub fn set_agc_gain(&mut self, agc_gain: &AgcReceiverGain) -> Result<&mut Self, RadioError> {
+        self.write_shadowed(&Self::agc_gain_write(agc_gain))?;
+
+        Ok(self)
+    }
+
+    fn agc_control_write(agc_control: &AgcReceiverControl) -> RegisterWrite {
         let mut agcc = 0u8;
 
         if agc_control.enabled {
@@ -740,22 +919,16 @@ where
 
         agcc = agcc | ((agc_control.average_time as u8) << 4);
 
-        self.bus
-            .write_reg_u8(Self::abs_reg(regs::RG_RFXX_AGCC), agcc)?;
-
-        Ok(())
+        RegisterWrite::new(regs::RG_RFXX_AGCC, agcc)
     }
 
-    pub fn set_agc_gain(&mut self, agc_gain: &AgcReceiverGain) -> Result<&mut Self, RadioError> {
+    fn agc_gain_write(agc_gain: &AgcReceiverGain) -> RegisterWrite {
         let mut agcs = 0u8;
 
         agcs = agcs | ((agc_gain.target_level as u8) << 5);
         agcs = agcs | core::cmp::min(23, agc_gain.gcw);
 
-        self.bus
-            .write_reg_u8(Self::abs_reg(regs::RG_RFXX_AGCS), agcs)?;
-
-        Ok(self)
+        RegisterWrite::new(regs::RG_RFXX_AGCS, agcs)
     }
 
     pub fn set_aux_settings(
@@ -787,16 +960,29 @@ where
 
     pub fn reset(&mut self) -> Result<(), RadioError> {
         self.bus.hardware_reset().map_err(RadioError::from)?;
+        self.clear_register_state();
 
         self.set_state(RadioState::TrxOff)?;
 
         Ok(())
     }
 
+    /// Forgets the latched IRQs and the frontend shadow after the chip was reset
+    pub(crate) fn clear_register_state(&mut self) {
+        self.irqs.reset();
+        self.shadow.invalidate();
+    }
+
     pub fn check_band(freq: Hertz) -> bool {
         (freq <= B::MAX_FREQUENCY) && (freq >= B::MIN_FREQUENCY)
     }
 
+    /// Programs `write` unless the frontend register holds its bits already
+    fn write_shadowed(&mut self, write: &RegisterWrite) -> Result<(), RadioError> {
+        self.shadow.write(&mut self.bus, B::RADIO_ADDRESS, write)?;
+        Ok(())
+    }
+
     /// Returns absolute register address for a specified `Band`
     const fn abs_reg(addr: RegisterAddress) -> RegisterAddress {
         B::RADIO_ADDRESS + addr
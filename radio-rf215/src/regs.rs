@@ -91,6 +91,7 @@ pub(crate) const RG_BBCX_OQPSKC2: RegisterAddress = 0x012;
 pub(crate) const RG_BBCX_OQPSKC3: RegisterAddress = 0x013;
 pub(crate) const RG_BBCX_OQPSKPHRTX: RegisterAddress = 0x014;
 pub(crate) const RG_BBCX_OQPSKPHRRX: RegisterAddress = 0x015;
+// Frame filter (AFC: address filter control, not frequency control)
 pub(crate) const RG_BBCX_AFC0: RegisterAddress = 0x020;
 pub(crate) const RG_BBCX_AFC1: RegisterAddress = 0x021;
 pub(crate) const RG_BBCX_AFFTM: RegisterAddress = 0x022;
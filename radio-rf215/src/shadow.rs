@@ -0,0 +1,93 @@
+use crate::{
+    bus::{Bus, BusError},
+    regs::{RegisterAddress, RegisterValue},
+};
+
+/// Configuration registers of a radio or baseband block all sit below this offset
+const SHADOW_SIZE: usize = 0x20;
+
+/// Register bits to program, the remaining bits are left as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RegisterWrite {
+    pub reg: RegisterAddress,
+    pub mask: RegisterValue,
+    pub value: RegisterValue,
+}
+
+impl RegisterWrite {
+    pub const fn new(reg: RegisterAddress, value: RegisterValue) -> Self {
+        Self::masked(reg, 0xFF, value)
+    }
+
+    pub const fn masked(reg: RegisterAddress, mask: RegisterValue, value: RegisterValue) -> Self {
+        Self {
+            reg,
+            mask,
+            value: value & mask,
+        }
+    }
+}
+
+/// Last value written to each configuration register of one block
+///
+/// Registers are addressed relative to the block. A register without a
+/// value is unknown and is read back before its bits are modified.
+#[derive(Debug)]
+pub(crate) struct RegisterShadow {
+    values: [Option<RegisterValue>; SHADOW_SIZE],
+}
+
+impl RegisterShadow {
+    pub const fn new() -> Self {
+        Self {
+            values: [None; SHADOW_SIZE],
+        }
+    }
+
+    pub fn get(&self, reg: RegisterAddress) -> Option<RegisterValue> {
+        self.values.get(reg as usize).copied().flatten()
+    }
+
+    pub fn set(&mut self, reg: RegisterAddress, value: RegisterValue) {
+        if let Some(slot) = self.values.get_mut(reg as usize) {
+            *slot = Some(value);
+        }
+    }
+
+    /// Returns `true` if the register is known to hold the bits of `write`
+    pub fn is_current(&self, write: &RegisterWrite) -> bool {
+        self.get(write.reg)
+            .is_some_and(|value| value & write.mask == write.value)
+    }
+
+    /// Programs the bits of `write` into the block at `base`
+    ///
+    /// Nothing is written if the shadow shows the bits are set already.
+    pub fn write<I: Bus>(
+        &mut self,
+        bus: &mut I,
+        base: RegisterAddress,
+        write: &RegisterWrite,
+    ) -> Result<(), BusError> {
+        if self.is_current(write) {
+            return Ok(());
+        }
+
+        let current = match self.get(write.reg) {
+            _ if write.mask == 0xFF => 0,
+            Some(value) => value,
+            None => bus.read_reg_u8(base + write.reg)?,
+        };
+
+        let value = (current & !write.mask) | write.value;
+        bus.write_reg_u8(base + write.reg, value)?;
+        self.set(write.reg, value);
+
+        Ok(())
+    }
+
+    /// Forgets every register, e.g. after the chip was reset
+    pub fn invalidate(&mut self) {
+        self.values = [None; SHADOW_SIZE];
+    }
+}
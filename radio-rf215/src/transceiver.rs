@@ -23,7 +23,8 @@ impl Band for Band09 {
     const MIN_FREQUENCY: Hertz = Hertz::new(389_500_000);
     const MAX_FREQUENCY: Hertz = Hertz::new(1_020_000_000);
     const FREQUENCY_OFFSET: Hertz = Hertz(0);
-    const MAX_CHANNEL: RadioChannel = 255;
+    // CNL and the CNH bit of CNM make up 9 bits in both bands
+    const MAX_CHANNEL: RadioChannel = 511;
 }
 
 impl Band for Band24 {
@@ -38,6 +39,55 @@ impl Band for Band24 {
     const MAX_CHANNEL: RadioChannel = 511;
 }
 
+/// Clear channel assessment before a transmission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CcaMode {
+    /// Busy if the energy on the channel is above the ED threshold (CCATX)
+    ///
+    /// Any interferer counts, whether it's a frame or not.
+    #[default]
+    Energy,
+    /// Busy if the baseband detects the start of a frame while listening
+    ///
+    /// Ignores energy that isn't a frame of the configured PHY.
+    CarrierSense,
+    /// Busy if either of the above says so
+    Combined,
+}
+
+impl CcaMode {
+    pub fn senses_energy(self) -> bool {
+        matches!(self, CcaMode::Energy | CcaMode::Combined)
+    }
+
+    pub fn senses_carrier(self) -> bool {
+        matches!(self, CcaMode::CarrierSense | CcaMode::Combined)
+    }
+}
+
+/// Busy threshold of the energy detection CCA unless configured, in dBm
+pub const DEFAULT_ED_THRESHOLD: i8 = -50;
+
+/// Energy measurement of the [`CcaMode::Energy`] assessment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnergyCca {
+    /// The channel is busy above this energy in dBm (AMEDT)
+    pub threshold: i8,
+    /// Measurement duration, the ED duration of the modulation if unset
+    ///
+    /// Rounded up to a duration the RF215 can measure, at most 8064 us.
+    pub duration: Option<core::time::Duration>,
+}
+
+impl Default for EnergyCca {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_ED_THRESHOLD,
+            duration: None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Transreceiver<B: Band, I: Bus + Clone> {
     radio: Radio<B, I>,
@@ -45,6 +95,11 @@ pub struct Transreceiver<B: Band, I: Bus + Clone> {
 }
 
 const CHANGE_STATE_DURATION: core::time::Duration = core::time::Duration::from_millis(500);
+const FREQUENCY_SETTLE_DURATION: core::time::Duration = core::time::Duration::from_millis(10);
+/// Default wait for the end of a transmitted frame
+pub const TX_FRAME_END_DURATION: core::time::Duration = core::time::Duration::from_millis(500);
+/// Time spent listening for a frame start before a carrier sensed transmission
+const CARRIER_SENSE_DURATION: core::time::Duration = core::time::Duration::from_millis(2);
 
 impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
     pub(crate) fn new(bus: I) -> Self {
@@ -62,6 +117,9 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
 
         self.radio.set_frequency(config)?;
 
+        self.radio
+            .wait_frequency_settled(FREQUENCY_SETTLE_DURATION)?;
+
         self.radio.receive()?;
 
         Ok(())
@@ -96,25 +154,69 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
         Ok(())
     }
 
-    pub fn bb_transmit(&mut self, frame: &BasebandFrame) -> Result<(), RadioError> {
+    /// Sends `frame` and waits up to `timeout` for its end, after which the
+    /// radio is back in TRXPREP
+    pub fn bb_transmit(
+        &mut self,
+        frame: &BasebandFrame,
+        timeout: core::time::Duration,
+    ) -> Result<(), RadioError> {
         self.radio
             .change_state(CHANGE_STATE_DURATION, RadioState::TrxPrep)?;
 
+        // Drop a TXFE left over from a previous transmission
+        self.baseband.update_irqs()?;
+        self.baseband
+            .take_irq(BasebandInterrupt::TransmitterFrameEnd);
+
         self.baseband.load_tx(frame)?;
 
         self.radio.send_command(crate::radio::RadioCommand::Tx)?;
 
-        if let Some(irqs) = self.radio.wait_any_irq(
-            RadioInterruptMask::new()
-                .add_irq(regs::RadioInterrupt::TransceiverReady)
-                .add_irq(regs::RadioInterrupt::TransceiverError)
-                .build(),
-            core::time::Duration::from_millis(500),
-        ) {
-            if irqs.has_irq(regs::RadioInterrupt::TransceiverReady) {}
+        if self
+            .baseband
+            .wait_irq(BasebandInterrupt::TransmitterFrameEnd, timeout)
+        {
+            Ok(())
+        } else {
+            Err(RadioError::Timeout)
         }
+    }
 
-        Ok(())
+    /// Sends `frame` and waits up to `timeout` for its end
+    pub fn bb_transmit_auto_rx(
+        &mut self,
+        frame: &BasebandFrame,
+        timeout: core::time::Duration,
+    ) -> Result<(), RadioError> {
+        // NOTE: 6.15.3 Transmit and Switch to Receive (TX2RX)
+        // The baseband moves the transceiver to RX on TXFE, so there is no gap
+        // spent polling for TRXPREP and issuing the RX command.
+        self.radio
+            .change_state(CHANGE_STATE_DURATION, RadioState::TrxPrep)?;
+
+        self.baseband.set_auto_mode(BasebandAutoMode {
+            auto_rx: true,
+            ..Default::default()
+        })?;
+
+        // Drop a TXFE left over from a previous transmission
+        self.baseband.update_irqs()?;
+        self.baseband
+            .take_irq(BasebandInterrupt::TransmitterFrameEnd);
+
+        self.baseband.load_tx(frame)?;
+
+        self.radio.send_command(crate::radio::RadioCommand::Tx)?;
+
+        if self
+            .baseband
+            .wait_irq(BasebandInterrupt::TransmitterFrameEnd, timeout)
+        {
+            Ok(())
+        } else {
+            Err(RadioError::Timeout)
+        }
     }
 
     pub fn measure_ed(&mut self) -> Result<i8, RadioError> {
@@ -133,7 +235,55 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
         }
     }
 
-    pub fn bb_transmit_cca(&mut self, frame: &BasebandFrame) -> Result<(), RadioError> {
+    /// Sends `frame` once the channel is assessed clear according to `mode`,
+    /// measuring the energy as set by `energy`, and waits up to `timeout` for
+    /// its end
+    ///
+    /// Fails with [`RadioError::ChannelBusy`] without transmitting otherwise.
+    pub fn bb_transmit_cca(
+        &mut self,
+        frame: &BasebandFrame,
+        mode: CcaMode,
+        energy: &EnergyCca,
+        timeout: core::time::Duration,
+    ) -> Result<(), RadioError> {
+        if mode.senses_carrier() && self.sense_carrier(CARRIER_SENSE_DURATION)? {
+            return Err(RadioError::ChannelBusy);
+        }
+
+        if mode.senses_energy() {
+            self.bb_transmit_energy_cca(frame, energy, timeout)
+        } else {
+            self.baseband.set_auto_mode(BasebandAutoMode::default())?;
+            self.bb_transmit(frame, timeout)
+        }
+    }
+
+    /// Listens for `duration`, returns `true` if a frame started meanwhile
+    ///
+    /// A frame start (RXFS) means the baseband synchronized on a preamble of
+    /// the configured PHY. The frame itself is received as usual.
+    pub fn sense_carrier(&mut self, duration: core::time::Duration) -> Result<bool, RadioError> {
+        self.baseband.enable()?;
+        self.start_receive()?;
+
+        // Only frames starting from now on count, a stale frame start
+        // belongs to a frame which has been received already
+        self.baseband.update_irqs()?;
+        self.baseband
+            .take_irq(BasebandInterrupt::ReceiverFrameStart);
+
+        Ok(self
+            .baseband
+            .wait_irq(BasebandInterrupt::ReceiverFrameStart, duration))
+    }
+
+    fn bb_transmit_energy_cca(
+        &mut self,
+        frame: &BasebandFrame,
+        energy: &EnergyCca,
+        timeout: core::time::Duration,
+    ) -> Result<(), RadioError> {
         // NOTE: 6.15.5 Clear Channel Assessment with Automatic Transmit (CCATX)
 
         // NOTE: It is recommended disabling the baseband (set PC.BBEN to 0) to avoid that the
@@ -149,10 +299,13 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
             ..Default::default()
         })?;
 
-        // TODO: provide EDT value in params
-        self.baseband.set_auto_edt(-50)?;
+        self.baseband.set_auto_edt(energy.threshold)?;
+        if let Some(duration) = energy.duration {
+            self.radio.set_ed_duration(duration)?;
+        }
 
         self.radio.clear_irqs()?;
+        self.baseband.clear_irqs()?;
 
         self.radio
             .set_ed_mode(crate::radio::EnergyDetectionMode::Single)?;
@@ -160,6 +313,7 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
         self.baseband.load_tx(frame)?;
 
         let mut transmitted = false;
+        let mut busy = false;
 
         if let Some(irqs) = self.radio.wait_any_irq(
             RadioInterruptMask::new()
@@ -173,6 +327,7 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
                 // channel has assessed as busy, the baseband needs to be enabled again by setting
                 // PC.BBEN to 1.
                 self.baseband.enable()?;
+                busy = true;
             }
 
             if irqs.has_irq(regs::RadioInterrupt::TransceiverReady) {
@@ -180,33 +335,101 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
             }
         }
 
+        // TRXRDY only tells the transmission started
         if transmitted {
-            Ok(())
+            if self
+                .baseband
+                .wait_irq(BasebandInterrupt::TransmitterFrameEnd, timeout)
+            {
+                Ok(())
+            } else {
+                Err(RadioError::Timeout)
+            }
+        } else if busy {
+            Err(RadioError::ChannelBusy)
         } else {
             Err(RadioError::Timeout)
         }
     }
 
+    /// Waits for the end of a received frame and reads it from the frame buffer
+    ///
+    /// The baseband has a single RX frame buffer. A frame start or frame buffer
+    /// level interrupt raised while the frame was read means the next frame is
+    /// already filling the buffer, so [`RadioError::BufferOverrun`] is returned
+    /// instead of a possibly corrupted frame.
     pub fn bb_receive(
         &mut self,
         frame: &mut BasebandFrame,
         timeout: core::time::Duration,
     ) -> Result<(), RadioError> {
-        if self
+        if !self
             .baseband
             .wait_irq(BasebandInterrupt::ReceiverFrameEnd, timeout)
         {
-            self.baseband.load_rx(frame)?;
-            Ok(())
-        } else {
-            Err(RadioError::Timeout)
+            return Err(RadioError::Timeout);
         }
+
+        // Interrupts of the frame that just ended
+        self.baseband
+            .take_irq(BasebandInterrupt::ReceiverFrameStart);
+        self.baseband
+            .take_irq(BasebandInterrupt::FrameBufferLevelIndication);
+
+        self.baseband.load_rx(frame)?;
+
+        self.baseband.update_irqs()?;
+        let next_frame = self
+            .baseband
+            .take_irq(BasebandInterrupt::ReceiverFrameStart)
+            | self
+                .baseband
+                .take_irq(BasebandInterrupt::FrameBufferLevelIndication);
+
+        if next_frame {
+            return Err(RadioError::BufferOverrun);
+        }
+
+        Ok(())
+    }
+
+    /// Drops the frame buffer content and pending interrupts, then restarts RX
+    ///
+    /// Recovers the receiver after [`RadioError::BufferOverrun`].
+    pub fn flush_receive(&mut self) -> Result<(), RadioError> {
+        self.radio.set_state(RadioState::TrxOff)?;
+        self.radio
+            .wait_on_state(core::time::Duration::from_millis(100), |s| {
+                s == RadioState::TrxOff
+            })?;
+
+        self.radio.clear_irqs()?;
+        self.baseband.clear_irqs()?;
+
+        self.radio.receive()
     }
 
     pub fn start_receive(&mut self) -> Result<(), RadioError> {
         self.radio.receive()
     }
 
+    /// Returns to RX after a transmission, whatever state it left the radio in
+    ///
+    /// A transmission that failed or timed out can leave the radio in TX. It
+    /// gets `timeout` to finish, then it's forced through TRXOFF, which aborts
+    /// the frame, before RX is entered.
+    pub fn restore_receive(&mut self, timeout: core::time::Duration) -> Result<(), RadioError> {
+        let state = self.radio.wait_on_state(timeout, |s| {
+            s == RadioState::TrxOff || s == RadioState::TrxPrep || s == RadioState::Rx
+        });
+
+        if state.is_err() {
+            self.radio.change_state(timeout, RadioState::TrxOff)?;
+        }
+
+        self.radio.receive()
+    }
+
     pub fn update_irqs(&mut self) -> Result<(), RadioError> {
         self.radio.update_irqs()?;
         self.baseband.update_irqs()?;
@@ -214,11 +437,20 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
         Ok(())
     }
 
+    /// Programs the frontend for `trx_config` and the baseband for `modulation`
+    ///
+    /// Only registers whose value differs from the last one written are
+    /// touched. If none differs the radio is left as it is, otherwise it's
+    /// taken through TRXOFF and ends up receiving.
     pub fn configure(
         &mut self,
         modulation: &Modulation,
         trx_config: &RadioTransreceiverConfig,
     ) -> Result<(), RadioError> {
+        if self.radio.is_configured(trx_config)? && self.baseband.is_configured(modulation)? {
+            return Ok(());
+        }
+
         self.radio
             .change_state(CHANGE_STATE_DURATION, RadioState::TrxOff)?;
 
@@ -237,14 +469,55 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
         Ok(())
     }
 
+    /// Tunes to `config` and programs `modulation` in a single pass through
+    /// TRXOFF
+    ///
+    /// [`Self::set_frequency`] followed by [`Self::configure`] receives in
+    /// between, with the new frequency and the old modulation. Here the
+    /// radio only receives again once both are written.
+    pub fn apply(
+        &mut self,
+        config: &RadioConfig,
+        modulation: &Modulation,
+        trx_config: &RadioTransreceiverConfig,
+    ) -> Result<(), RadioError> {
+        self.radio
+            .change_state(CHANGE_STATE_DURATION, RadioState::TrxOff)?;
+
+        self.baseband.disable()?;
+
+        self.radio.configure_transreceiver(trx_config)?;
+
+        self.baseband.configure(modulation)?;
+
+        // Writing the channel last also takes over the frontend settings
+        self.radio.set_frequency(config)?;
+
+        self.baseband.enable()?;
+
+        self.radio
+            .wait_frequency_settled(FREQUENCY_SETTLE_DURATION)?;
+
+        self.radio.receive()?;
+
+        Ok(())
+    }
+
     pub fn reset(&mut self) -> Result<(), RadioError> {
         self.radio.reset()?;
+        self.baseband.clear_register_state();
 
         self.disable_irqs()?;
 
         Ok(())
     }
 
+    /// Forgets the cached register state after the chip was reset
+    pub(crate) fn clear_register_state(&mut self) {
+        self.radio.clear_register_state();
+        self.baseband.clear_register_state();
+    }
+
     pub fn radio(&mut self) -> &mut Radio<B, I> {
         &mut self.radio
     }
@@ -253,3 +526,802 @@ impl<B: Band, I: Bus + Clone> Transreceiver<B, I> {
         &mut self.baseband
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use radio_common::{
+        modulation::{OfdmBandwidthOption, OfdmMcs, OfdmModulation},
+        RadioConfigBuilder,
+    };
+
+    use super::*;
+    use crate::baseband::{AddressFilter, AutoAck, MAX_ACK_TIME_US};
+    use crate::bus::BusError;
+    use crate::config::TransreceiverConfigurator;
+    use crate::radio::{RadioCommand, ReceiverBandwidth, RelativeCutOff};
+    use crate::regs::{RadioInterrupt, RegisterValue};
+
+    const RF09_CMD: RegisterAddress = regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_CMD;
+    const RF09_STATE: RegisterAddress = regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_STATE;
+    const RF09_IRQM: RegisterAddress = regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_IRQM;
+    const RF09_EDC: RegisterAddress = regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_EDC;
+    const BBC0_AMCS: RegisterAddress = regs::RG_BBC0_BASE_ADDRESS + regs::RG_BBCX_AMCS;
+
+    const AMCS_CCATX: u8 = 0b0000_0010;
+    const AMCS_TX2RX: u8 = 0b0000_0001;
+
+    /// Register file which follows state commands and raises TRXRDY on TRXPREP
+    ///
+    /// Without PLL lock the radio stays in transition. Like the hardware,
+    /// TRXRDY is only reported while it's enabled in the interrupt mask.
+    /// TX ends with TXFE after `air_time` ticks of the clock, in RX with
+    /// TX2RX set and in TRXPREP otherwise. A single energy measurement with
+    /// CCATX set ends in TRXERR on `energy_busy` and in TRXRDY for the
+    /// transmission otherwise. With `carrier` the baseband keeps reporting
+    /// frame starts.
+    struct MockState {
+        regs: Vec<RegisterValue>,
+        commands: Vec<u8>,
+        writes: Vec<RegisterAddress>,
+        pll_lock: bool,
+        time: u64,
+        energy_busy: bool,
+        carrier: bool,
+        air_time: u64,
+        tx_end: Option<u64>,
+    }
+
+    impl MockState {
+        fn start_tx(&mut self) {
+            self.regs[RF09_STATE as usize] = RadioState::Tx as u8;
+            self.tx_end = Some(self.time + self.air_time);
+            self.update_tx();
+        }
+
+        fn update_tx(&mut self) {
+            if self.tx_end.is_none_or(|end| self.time < end) {
+                return;
+            }
+
+            let next = if self.regs[BBC0_AMCS as usize] & AMCS_TX2RX != 0 {
+                RadioState::Rx
+            } else {
+                RadioState::TrxPrep
+            };
+
+            self.tx_end = None;
+            self.regs[RF09_STATE as usize] = next as u8;
+            self.regs[regs::RG_BBC0_IRQS as usize] |= BasebandInterrupt::TransmitterFrameEnd as u8;
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockBus(Rc<RefCell<MockState>>);
+
+    impl MockBus {
+        fn new(pll_lock: bool) -> Self {
+            Self(Rc::new(RefCell::new(MockState {
+                regs: vec![0; 0x4000],
+                commands: Vec::new(),
+                writes: Vec::new(),
+                pll_lock,
+                time: 0,
+                energy_busy: false,
+                carrier: false,
+                air_time: 0,
+                tx_end: None,
+            })))
+        }
+    }
+
+    impl Bus for MockBus {
+        fn write_regs(
+            &mut self,
+            addr: RegisterAddress,
+            values: &[RegisterValue],
+        ) -> Result<(), BusError> {
+            let mut state = self.0.borrow_mut();
+            state.writes.push(addr);
+            let addr = addr as usize;
+            state.regs[addr..addr + values.len()].copy_from_slice(values);
+
+            if addr == RF09_EDC as usize
+                && values[0] == crate::radio::EnergyDetectionMode::Single as u8
+                && state.regs[BBC0_AMCS as usize] & AMCS_CCATX != 0
+            {
+                if state.energy_busy {
+                    state.regs[regs::RG_RF09_IRQS as usize] |=
+                        RadioInterrupt::TransceiverError as u8;
+                } else {
+                    state.regs[regs::RG_RF09_IRQS as usize] |=
+                        RadioInterrupt::TransceiverReady as u8;
+                    state.start_tx();
+                }
+            }
+
+            if addr == RF09_CMD as usize {
+                let cmd = values[0];
+                state.commands.push(cmd);
+                state.regs[RF09_STATE as usize] = cmd;
+
+                if cmd == RadioCommand::TrxPrep as u8 {
+                    if !state.pll_lock {
+                        state.regs[RF09_STATE as usize] = RadioState::Transition as u8;
+                    } else if state.regs[RF09_IRQM as usize]
+                        & RadioInterrupt::TransceiverReady as u8
+                        != 0
+                    {
+                        state.regs[regs::RG_RF09_IRQS as usize] |=
+                            RadioInterrupt::TransceiverReady as u8;
+                    }
+                }
+
+                if cmd == RadioCommand::Tx as u8 {
+                    state.start_tx();
+                }
+            }
+
+            Ok(())
+        }
+
+        fn read_regs(
+            &mut self,
+            addr: RegisterAddress,
+            values: &mut [RegisterValue],
+        ) -> Result<(), BusError> {
+            let mut state = self.0.borrow_mut();
+            state.update_tx();
+            let addr = addr as usize;
+            values.copy_from_slice(&state.regs[addr..addr + values.len()]);
+
+            // IRQ status is cleared on read
+            if addr == regs::RG_RF09_IRQS as usize || addr == regs::RG_BBC0_IRQS as usize {
+                state.regs[addr] = 0;
+            }
+
+            if addr == regs::RG_BBC0_IRQS as usize && state.carrier {
+                values[0] |= BasebandInterrupt::ReceiverFrameStart as u8;
+            }
+
+            Ok(())
+        }
+
+        fn wait_interrupt(
+            &mut self,
+            _timeout: Option<core::time::Duration>,
+        ) -> Result<(), BusError> {
+            Err(BusError::Timeout)
+        }
+
+        fn delay(&mut self, _timeout: core::time::Duration) {}
+
+        fn current_time(&mut self) -> u64 {
+            let mut state = self.0.borrow_mut();
+            state.time += 1;
+            state.time
+        }
+
+        fn hardware_reset(&mut self) -> Result<(), BusError> {
+            Ok(())
+        }
+    }
+
+    fn config() -> RadioConfig {
+        RadioConfigBuilder::new()
+            .freq(Hertz::new(869_535_000))
+            .channel(5)
+            .build()
+    }
+
+    #[test]
+    fn test_set_frequency_waits_for_settling() {
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        trx.set_frequency(&config()).expect("frequency set");
+
+        assert_eq!(
+            bus.0.borrow().commands,
+            [
+                RadioCommand::TrxOff as u8,
+                RadioCommand::TrxPrep as u8,
+                RadioCommand::Rx as u8
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_frequency_high_channel() {
+        const RF09: RegisterAddress = regs::RG_RF09_BASE_ADDRESS;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        let mut config = config();
+        config.channel = 0x12C;
+        trx.set_frequency(&config).expect("frequency set");
+
+        {
+            let state = bus.0.borrow();
+            assert_eq!(state.regs[(RF09 + regs::RG_RFXX_CNL) as usize], 0x2C);
+            assert_eq!(state.regs[(RF09 + regs::RG_RFXX_CNM) as usize], 0x01);
+        }
+
+        config.channel = Band09::MAX_CHANNEL + 1;
+        assert_eq!(trx.set_frequency(&config), Err(RadioError::IncorrectConfig));
+    }
+
+    #[test]
+    fn test_set_frequency_with_irqs_disabled() {
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        trx.disable_irqs().expect("irqs disabled");
+        trx.set_frequency(&config()).expect("frequency set");
+
+        assert_eq!(
+            bus.0.borrow().commands.last(),
+            Some(&(RadioCommand::Rx as u8))
+        );
+    }
+
+    #[test]
+    fn test_set_frequency_settle_timeout() {
+        let bus = MockBus::new(false);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        assert_eq!(trx.set_frequency(&config()), Err(RadioError::Timeout));
+    }
+
+    #[test]
+    fn test_interrupt_wait_timeout() {
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        // No interrupt arrives on the mock bus, which is a timeout and not a
+        // communication failure
+        assert_eq!(
+            trx.radio()
+                .wait_interrupt(Some(core::time::Duration::from_micros(100))),
+            Err(RadioError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_auto_rx_transmit_skips_cca_and_stays_in_rx() {
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        trx.bb_transmit_auto_rx(
+            &BasebandFrame::new_from_slice(b"frame"),
+            TX_FRAME_END_DURATION,
+        )
+        .expect("frame transmitted");
+
+        let state = bus.0.borrow();
+
+        let amcs = state.regs[BBC0_AMCS as usize];
+        assert_eq!(amcs & AMCS_CCATX, 0);
+        assert_eq!(amcs & AMCS_TX2RX, AMCS_TX2RX);
+
+        // No RX command for an energy measurement before TX, nor after it
+        assert_eq!(
+            state.commands,
+            [RadioCommand::TrxPrep as u8, RadioCommand::Tx as u8]
+        );
+        assert_eq!(state.regs[RF09_STATE as usize], RadioState::Rx as u8);
+    }
+
+    #[test]
+    fn test_cca_modes() {
+        // (mode, energy above threshold, frame start seen, busy)
+        let cases = [
+            (CcaMode::Energy, false, false, false),
+            (CcaMode::Energy, false, true, false),
+            (CcaMode::Energy, true, false, true),
+            (CcaMode::CarrierSense, false, false, false),
+            (CcaMode::CarrierSense, true, false, false),
+            (CcaMode::CarrierSense, false, true, true),
+            (CcaMode::Combined, false, false, false),
+            (CcaMode::Combined, true, false, true),
+            (CcaMode::Combined, false, true, true),
+            (CcaMode::Combined, true, true, true),
+        ];
+
+        for (mode, energy_busy, carrier, busy) in cases {
+            let bus = MockBus::new(true);
+            {
+                let mut state = bus.0.borrow_mut();
+                state.energy_busy = energy_busy;
+                state.carrier = carrier;
+            }
+            let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+            let result = trx.bb_transmit_cca(
+                &BasebandFrame::new_from_slice(b"frame"),
+                mode,
+                &EnergyCca::default(),
+                TX_FRAME_END_DURATION,
+            );
+
+            let case = (mode, energy_busy, carrier);
+            if busy {
+                assert_eq!(result, Err(RadioError::ChannelBusy), "{case:?}");
+            } else {
+                assert_eq!(result, Ok(()), "{case:?}");
+            }
+
+            // Without energy detection the frame goes out on a TX command
+            let state = bus.0.borrow();
+            let tx_command = state.commands.contains(&(RadioCommand::Tx as u8));
+            assert_eq!(
+                tx_command,
+                mode == CcaMode::CarrierSense && !busy,
+                "{case:?}"
+            );
+            if !mode.senses_energy() {
+                assert_eq!(state.regs[BBC0_AMCS as usize] & AMCS_CCATX, 0, "{case:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_energy_cca_threshold_and_duration() {
+        const RF09_EDD: RegisterAddress = regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_EDD;
+        const BBC0_AMEDT: RegisterAddress = regs::RG_BBC0_BASE_ADDRESS + regs::RG_BBCX_AMEDT;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+        let frame = BasebandFrame::new_from_slice(b"frame");
+
+        let energy = EnergyCca {
+            threshold: -85,
+            duration: Some(core::time::Duration::from_millis(5)),
+        };
+        trx.bb_transmit_cca(&frame, CcaMode::Energy, &energy, TX_FRAME_END_DURATION)
+            .expect("frame transmitted");
+
+        {
+            let state = bus.0.borrow();
+            assert_eq!(state.regs[BBC0_AMEDT as usize] as i8, -85);
+
+            // 40 x 128 us, 39 would measure less than 5 ms
+            let edd = state.regs[RF09_EDD as usize];
+            assert_eq!(edd & 0b11, 3);
+            assert_eq!(edd >> 2, 40);
+        }
+
+        // Without a duration the ED duration of the modulation stays
+        bus.0.borrow_mut().regs[RF09_EDD as usize] = 0x7A;
+        trx.bb_transmit_cca(
+            &frame,
+            CcaMode::Energy,
+            &EnergyCca::default(),
+            TX_FRAME_END_DURATION,
+        )
+        .expect("frame transmitted");
+
+        let state = bus.0.borrow();
+        assert_eq!(state.regs[BBC0_AMEDT as usize] as i8, DEFAULT_ED_THRESHOLD);
+        assert_eq!(state.regs[RF09_EDD as usize], 0x7A);
+    }
+
+    #[test]
+    fn test_auto_ack_timing_and_filter() {
+        const BBC0: RegisterAddress = regs::RG_BBC0_BASE_ADDRESS;
+        const AMCS_AACK: u8 = 0b0000_1000;
+        const AMCS_AACKFT: u8 = 0b1000_0000;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        trx.baseband()
+            .set_address_filter(Some(&AddressFilter {
+                pan_id: 0xCAFE,
+                short_address: 0x1234,
+                extended_address: 0x0011_2233_4455_6677,
+            }))
+            .expect("filter set");
+        trx.baseband()
+            .set_auto_ack(Some(&AutoAck {
+                frame_pending: true,
+                ack_time_us: Some(0x2A5),
+            }))
+            .expect("auto-ack set");
+
+        // Transmitting rewrites AMCS, the auto-ACK has to survive it
+        trx.bb_transmit_auto_rx(
+            &BasebandFrame::new_from_slice(b"frame"),
+            TX_FRAME_END_DURATION,
+        )
+        .expect("frame transmitted");
+
+        {
+            let state = bus.0.borrow();
+            let reg = |addr: RegisterAddress| state.regs[(BBC0 + addr) as usize];
+
+            assert_eq!(reg(regs::RG_BBCX_AFC0) & 0b0000_0001, 1);
+            assert_eq!(
+                [
+                    reg(regs::RG_BBCX_MACPID0F0),
+                    reg(regs::RG_BBCX_MACPID1F0),
+                    reg(regs::RG_BBCX_MACSHA0F0),
+                    reg(regs::RG_BBCX_MACSHA1F0),
+                ],
+                [0xFE, 0xCA, 0x34, 0x12]
+            );
+            assert_eq!(reg(regs::RG_BBCX_MACEA0), 0x77);
+            assert_eq!(reg(regs::RG_BBCX_MACEA7), 0x00);
+
+            assert_eq!(reg(regs::RG_BBCX_AMAACKTL), 0xA5);
+            assert_eq!(reg(regs::RG_BBCX_AMAACKTH), 0x02);
+            assert_eq!(reg(regs::RG_BBCX_AMAACKPD) & 0b0000_0001, 1);
+
+            let amcs = reg(regs::RG_BBCX_AMCS);
+            assert_eq!(amcs & (AMCS_AACK | AMCS_AACKFT), AMCS_AACK | AMCS_AACKFT);
+            assert_eq!(amcs & AMCS_TX2RX, AMCS_TX2RX);
+        }
+
+        assert_eq!(
+            trx.baseband().set_auto_ack(Some(&AutoAck {
+                frame_pending: false,
+                ack_time_us: Some(MAX_ACK_TIME_US + 1),
+            })),
+            Err(RadioError::IncorrectConfig)
+        );
+
+        trx.baseband().set_auto_ack(None).expect("auto-ack off");
+        trx.bb_transmit_auto_rx(
+            &BasebandFrame::new_from_slice(b"frame"),
+            TX_FRAME_END_DURATION,
+        )
+        .expect("frame transmitted");
+        assert_eq!(
+            bus.0.borrow().regs[(BBC0 + regs::RG_BBCX_AMCS) as usize] & AMCS_AACK,
+            0
+        );
+    }
+
+    #[test]
+    fn test_read_tx_power_decodes_pac() {
+        const RF09_PAC: RegisterAddress = regs::RG_RF09_BASE_ADDRESS + regs::RG_RFXX_PAC;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        let modulation = Modulation::Ofdm(OfdmModulation {
+            tx_power: 14,
+            ..Default::default()
+        });
+        let trx_config = trx.create_modulation_config(&modulation);
+        trx.configure(&modulation, &trx_config).expect("configured");
+
+        assert_eq!(trx.radio().read_tx_power().expect("tx power"), 14);
+
+        // PA current bits aren't part of the power
+        bus.0.borrow_mut().regs[RF09_PAC as usize] = 0b0110_0111;
+        assert_eq!(trx.radio().read_tx_power().expect("tx power"), 7);
+    }
+
+    #[test]
+    fn test_reconfigure_writes_changed_registers_only() {
+        const RF09: RegisterAddress = regs::RG_RF09_BASE_ADDRESS;
+        const BBC0: RegisterAddress = regs::RG_BBC0_BASE_ADDRESS;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        let mut ofdm = OfdmModulation::default();
+        let modulation = Modulation::Ofdm(ofdm);
+        let trx_config = trx.create_modulation_config(&modulation);
+
+        trx.configure(&modulation, &trx_config).expect("configured");
+        assert!(bus
+            .0
+            .borrow()
+            .writes
+            .contains(&(RF09 + regs::RG_RFXX_TXDFE)));
+
+        bus.0.borrow_mut().writes.clear();
+        trx.configure(&modulation, &trx_config)
+            .expect("reconfigured");
+        assert_eq!(bus.0.borrow().writes, []);
+
+        // A new MCS only touches its own register, apart from the state sequence
+        ofdm.mcs = OfdmMcs::QpskC1_2;
+        let modulation = Modulation::Ofdm(ofdm);
+        let trx_config = trx.create_modulation_config(&modulation);
+
+        trx.configure(&modulation, &trx_config)
+            .expect("mcs changed");
+        {
+            let state = bus.0.borrow();
+            assert!(state.writes.contains(&(BBC0 + regs::RG_BBCX_OFDMPHRTX)));
+            assert!(!state.writes.contains(&(BBC0 + regs::RG_BBCX_OFDMC)));
+            assert!(!state.writes.contains(&(RF09 + regs::RG_RFXX_PAC)));
+            assert_eq!(
+                state.regs[(BBC0 + regs::RG_BBCX_OFDMPHRTX) as usize],
+                OfdmMcs::QpskC1_2 as u8
+            );
+        }
+
+        // The registers are back to their defaults after a reset
+        trx.reset().expect("reset");
+        bus.0.borrow_mut().writes.clear();
+        trx.configure(&modulation, &trx_config)
+            .expect("configured after reset");
+        {
+            let state = bus.0.borrow();
+            assert!(state.writes.contains(&(RF09 + regs::RG_RFXX_PAC)));
+            assert!(state.writes.contains(&(BBC0 + regs::RG_BBCX_OFDMC)));
+        }
+    }
+
+    #[test]
+    fn test_ofdm_lfo_bit_follows_modulation() {
+        const LFO_BIT: u8 = 0b0000_1000;
+
+        /// OFDMC of band `B` after configuring its baseband with `ofdm`
+        fn ofdmc<B: Band>(ofdm: OfdmModulation) -> u8 {
+            let bus = MockBus::new(true);
+            let mut trx = Transreceiver::<B, _>::new(bus.clone());
+            trx.baseband()
+                .configure(&Modulation::Ofdm(ofdm))
+                .expect("configured");
+
+            let reg = bus.0.borrow().regs[(B::BASEBAND_ADDRESS + regs::RG_BBCX_OFDMC) as usize];
+            reg
+        }
+
+        for opt in [
+            OfdmBandwidthOption::Option1,
+            OfdmBandwidthOption::Option2,
+            OfdmBandwidthOption::Option3,
+            OfdmBandwidthOption::Option4,
+        ] {
+            for lfo in [false, true] {
+                let ofdm = OfdmModulation {
+                    opt,
+                    lfo,
+                    ..Default::default()
+                };
+                let expected = opt as u8 | if lfo { LFO_BIT } else { 0 };
+
+                assert_eq!(ofdmc::<Band09>(ofdm), expected, "{:?}", ofdm);
+                assert_eq!(ofdmc::<Band24>(ofdm), expected, "{:?}", ofdm);
+            }
+        }
+
+        // The 2.4 GHz receiver frontend follows, e.g. narrower for option 1
+        let ofdm = OfdmModulation::default();
+        let lfo = OfdmModulation { lfo: true, ..ofdm };
+        let bw = |config: RadioTransreceiverConfig| config.rx_config.bw;
+
+        let trx = Transreceiver::<Band24, _>::new(MockBus::new(true));
+        assert_eq!(
+            bw(trx.create_modulation_config(&Modulation::Ofdm(ofdm))),
+            ReceiverBandwidth::Bw1600kHzIf2000kHz
+        );
+        assert_eq!(
+            bw(trx.create_modulation_config(&Modulation::Ofdm(lfo))),
+            ReceiverBandwidth::Bw1250kHzIf2000kHz
+        );
+
+        // The sub-GHz frontend stays the one deployed nodes always used
+        let trx = Transreceiver::<Band09, _>::new(MockBus::new(true));
+        let expected = [
+            (
+                RelativeCutOff::Fcut1_000,
+                ReceiverBandwidth::Bw1250kHzIf2000kHz,
+                true,
+            ),
+            (
+                RelativeCutOff::Fcut0_500,
+                ReceiverBandwidth::Bw800kHzIf1000kHz,
+                true,
+            ),
+            (
+                RelativeCutOff::Fcut0_500,
+                ReceiverBandwidth::Bw400kHzIf500kHz,
+                false,
+            ),
+            (
+                RelativeCutOff::Fcut0_375,
+                ReceiverBandwidth::Bw250kHzIf250kHz,
+                true,
+            ),
+        ];
+        for (opt, expected) in [
+            OfdmBandwidthOption::Option1,
+            OfdmBandwidthOption::Option2,
+            OfdmBandwidthOption::Option3,
+            OfdmBandwidthOption::Option4,
+        ]
+        .into_iter()
+        .zip(expected)
+        {
+            for lfo in [false, true] {
+                let ofdm = OfdmModulation {
+                    opt,
+                    lfo,
+                    ..Default::default()
+                };
+                let rx = trx
+                    .create_modulation_config(&Modulation::Ofdm(ofdm))
+                    .rx_config;
+                assert_eq!((rx.rcut, rx.bw, rx.if_shift), expected, "{:?}", ofdm);
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_frame_after_long_frame() {
+        const BBC0_RXFLL: RegisterAddress = regs::RG_BBC0_BASE_ADDRESS + regs::RG_BBCX_RXFLL;
+        const BBC0_FBRXS: RegisterAddress =
+            regs::RG_BBC0_FRAME_BUFFER_ADDRESS + regs::RG_BBCX_FBRXS;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+        let mut frame = BasebandFrame::new();
+
+        // The frame buffer isn't cleared between frames, like the hardware
+        let receive = |trx: &mut Transreceiver<Band09, MockBus>,
+                       frame: &mut BasebandFrame,
+                       data: &[u8],
+                       len: u16| {
+            {
+                let mut state = bus.0.borrow_mut();
+                let start = BBC0_FBRXS as usize;
+                state.regs[start..start + data.len()].copy_from_slice(data);
+                state.regs[BBC0_RXFLL as usize..BBC0_RXFLL as usize + 2]
+                    .copy_from_slice(&len.to_le_bytes());
+                state.regs[regs::RG_BBC0_IRQS as usize] |=
+                    BasebandInterrupt::ReceiverFrameEnd as u8;
+            }
+
+            trx.bb_receive(frame, core::time::Duration::from_millis(10))
+        };
+
+        let long = [0xAA; 200];
+        receive(&mut trx, &mut frame, &long, 200).expect("long frame received");
+        assert_eq!(frame.as_slice(), long);
+
+        receive(&mut trx, &mut frame, b"short", 5).expect("short frame received");
+        assert_eq!(frame.as_slice(), b"short");
+
+        // A length the buffer can't hold leaves no frame behind
+        assert_eq!(
+            receive(&mut trx, &mut frame, &[], 0x900),
+            Err(RadioError::IncorrectState)
+        );
+        assert_eq!(frame.len(), 0);
+    }
+
+    #[test]
+    fn test_restore_receive_after_stuck_transmit() {
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+        let timeout = core::time::Duration::from_millis(5);
+
+        // A frame that never ended keeps the radio in TX
+        bus.0.borrow_mut().regs[RF09_STATE as usize] = RadioState::Tx as u8;
+        trx.restore_receive(timeout).expect("restored");
+        assert_eq!(
+            bus.0.borrow().commands,
+            [
+                RadioCommand::TrxOff as u8,
+                RadioCommand::TrxPrep as u8,
+                RadioCommand::Rx as u8
+            ]
+        );
+        assert_eq!(
+            bus.0.borrow().regs[RF09_STATE as usize],
+            RadioState::Rx as u8
+        );
+
+        // After a frame that went out RX is entered straight from TRXPREP
+        bus.0.borrow_mut().commands.clear();
+        bus.0.borrow_mut().regs[RF09_STATE as usize] = RadioState::TrxPrep as u8;
+        trx.restore_receive(timeout).expect("restored");
+        assert_eq!(bus.0.borrow().commands, [RadioCommand::Rx as u8]);
+    }
+
+    #[test]
+    fn test_long_frame_is_not_cut_by_restore() {
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+        let frame = BasebandFrame::new_from_slice(&[0x55; 2047]);
+        let restore_timeout = core::time::Duration::from_millis(10);
+
+        // Transmit returns on TXFE, well past the restore timeout
+        bus.0.borrow_mut().air_time = 50;
+        trx.bb_transmit(&frame, TX_FRAME_END_DURATION)
+            .expect("frame transmitted");
+        trx.restore_receive(restore_timeout).expect("restored");
+        assert_eq!(
+            bus.0.borrow().commands,
+            [
+                RadioCommand::TrxPrep as u8,
+                RadioCommand::Tx as u8,
+                RadioCommand::Rx as u8
+            ]
+        );
+
+        // A frame outlasting the transmit timeout still gets the restore
+        // timeout to end before TRXOFF would abort it
+        bus.0.borrow_mut().commands.clear();
+        bus.0.borrow_mut().air_time = 15;
+        assert_eq!(
+            trx.bb_transmit(&frame, core::time::Duration::from_millis(5)),
+            Err(RadioError::Timeout)
+        );
+        trx.restore_receive(restore_timeout).expect("restored");
+
+        let state = bus.0.borrow();
+        assert!(!state.commands.contains(&(RadioCommand::TrxOff as u8)));
+        assert_eq!(state.regs[RF09_STATE as usize], RadioState::Rx as u8);
+    }
+
+    #[test]
+    fn test_apply_keeps_radio_out_of_rx_between_writes() {
+        const RF09: RegisterAddress = regs::RG_RF09_BASE_ADDRESS;
+        const BBC0: RegisterAddress = regs::RG_BBC0_BASE_ADDRESS;
+
+        let bus = MockBus::new(true);
+        let mut trx = Transreceiver::<Band09, _>::new(bus.clone());
+
+        let mut ofdm = OfdmModulation::default();
+        let modulation = Modulation::Ofdm(ofdm);
+        let trx_config = trx.create_modulation_config(&modulation);
+        trx.set_frequency(&config()).expect("frequency set");
+        trx.configure(&modulation, &trx_config).expect("configured");
+
+        {
+            let mut state = bus.0.borrow_mut();
+            state.writes.clear();
+            state.commands.clear();
+        }
+
+        let mut config = config();
+        config.channel = 7;
+        ofdm.mcs = OfdmMcs::QpskC1_2;
+        let modulation = Modulation::Ofdm(ofdm);
+        let trx_config = trx.create_modulation_config(&modulation);
+        trx.apply(&config, &modulation, &trx_config)
+            .expect("applied");
+
+        let state = bus.0.borrow();
+        assert_eq!(
+            state.commands,
+            [
+                RadioCommand::TrxOff as u8,
+                RadioCommand::TrxPrep as u8,
+                RadioCommand::Rx as u8
+            ]
+        );
+
+        let position = |addr: RegisterAddress| {
+            state
+                .writes
+                .iter()
+                .position(|&write| write == addr)
+                .expect("register written")
+        };
+        let commands: Vec<_> = state
+            .writes
+            .iter()
+            .enumerate()
+            .filter(|(_, &write)| write == RF09_CMD)
+            .map(|(index, _)| index)
+            .collect();
+
+        // TRXOFF before the first write, RX only after the last one
+        let frequency = position(RF09 + regs::RG_RFXX_CNL);
+        let modulation = position(BBC0 + regs::RG_BBCX_OFDMPHRTX);
+        assert!(commands[0] < frequency.min(modulation));
+        assert!(commands[2] > frequency.max(modulation));
+        assert_eq!(state.regs[RF09_STATE as usize], RadioState::Rx as u8);
+    }
+}